@@ -0,0 +1,36 @@
+use std::{env, fs, path::Path, process::Command};
+
+fn main() {
+    // Embed the commit we were built from, for `--version --verbose`
+    let commit = Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=YOTREDASH_COMMIT={}", commit);
+
+    // And the cargo features it was built with. Cargo only gives us their names uppercased, with
+    // dashes turned into underscores.
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase))
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=YOTREDASH_FEATURES={}", features.join(", "));
+
+    // HEAD only changes when switching branches, so the branch it points to is watched too. Files
+    // that don't exist aren't listed, since cargo would then rerun this on every build.
+    let git = Path::new("../.git");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    let head = fs::read_to_string(git.join("HEAD")).unwrap_or_default();
+    if let Some(reference) = head.trim().strip_prefix("ref: ") {
+        for path in &[git.join(reference), git.join("packed-refs")] {
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+}
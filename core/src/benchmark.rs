@@ -0,0 +1,295 @@
+//! Renders a config as fast as possible for a number of frames or seconds, then summarizes the
+//! frame and node timings, so shader revisions can be compared in scripts
+
+use anyhow::{bail, format_err, Context, Error};
+use indexmap::IndexMap;
+use log::info;
+use serde_derive::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::mpsc,
+    time::Instant,
+};
+
+use crate::{
+    config::{Config, PresentMode},
+    renderer,
+    stats::Percentiles,
+};
+
+/// How long to run a benchmark for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    /// A number of frames
+    Frames(u32),
+    /// A number of seconds
+    Seconds(f32),
+}
+
+impl Length {
+    /// Parse a length, which is a number of frames or, followed by `s`, a number of seconds
+    pub fn parse(length: &str) -> Result<Self, Error> {
+        let length = length.trim();
+        let parsed = if length.ends_with('s') {
+            length[..length.len() - 1]
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&seconds: &f32| seconds > 0.0)
+                .map(Length::Seconds)
+        } else {
+            length
+                .parse()
+                .ok()
+                .filter(|&frames| frames > 0)
+                .map(Length::Frames)
+        };
+        parsed.ok_or_else(|| {
+            format_err!(
+                "Invalid benchmark length `{}`, expected a number of frames like `500` or of \
+                 seconds like `10s`",
+                length
+            )
+        })
+    }
+}
+
+/// Summary of a set of times (milliseconds)
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    /// Fastest time
+    pub min: f32,
+    /// Mean time
+    pub avg: f32,
+    /// Median
+    pub p50: f32,
+    /// 90th percentile
+    pub p90: f32,
+    /// 99th percentile
+    pub p99: f32,
+    /// Slowest time
+    pub max: f32,
+}
+
+impl Summary {
+    /// Summarize some times
+    pub fn new(times: &[f32]) -> Self {
+        if times.is_empty() {
+            return Default::default();
+        }
+
+        let percentiles = Percentiles::new(times);
+        Self {
+            min: times.iter().cloned().fold(std::f32::INFINITY, f32::min),
+            avg: times.iter().sum::<f32>() / times.len() as f32,
+            p50: percentiles.p50,
+            p90: percentiles.p90,
+            p99: percentiles.p99,
+            max: percentiles.max,
+        }
+    }
+}
+
+/// Timings of a node over the benchmark
+#[derive(Debug, Serialize)]
+struct NodeReport {
+    /// Number of frames the node was rendered in, since switches can skip it
+    frames: usize,
+    /// Time spent on the GPU
+    gpu_time: Summary,
+    /// Time spent on the CPU
+    cpu_time: Summary,
+}
+
+/// The results of a benchmark
+#[derive(Debug, Serialize)]
+struct Report {
+    /// The config that was benchmarked
+    config: String,
+    /// When the benchmark finished
+    timestamp: String,
+    /// Number of frames measured
+    frames: usize,
+    /// How long the measured frames took (seconds)
+    duration: f32,
+    /// Mean frames per second
+    fps: f32,
+    /// Time between frames
+    frame_time: Summary,
+    /// CPU time per frame
+    cpu_time: Summary,
+    /// Timings for each node, in render order
+    nodes: IndexMap<String, NodeReport>,
+}
+
+impl Report {
+    /// Write the report as CSV, with a row for each measurement
+    fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "name,metric,frames,min,avg,p50,p90,p99,max")?;
+        let mut row = |name: &str, metric: &str, frames: usize, summary: &Summary| {
+            // Node names can have commas in them
+            writeln!(
+                writer,
+                "\"{}\",{},{},{},{},{},{},{},{}",
+                name.replace('"', "\"\""),
+                metric,
+                frames,
+                summary.min,
+                summary.avg,
+                summary.p50,
+                summary.p90,
+                summary.p99,
+                summary.max
+            )
+        };
+        row("(frame)", "frame_time", self.frames, &self.frame_time)?;
+        row("(frame)", "cpu_time", self.frames, &self.cpu_time)?;
+        for (name, node) in &self.nodes {
+            row(name, "gpu_time", node.frames, &node.gpu_time)?;
+            row(name, "cpu_time", node.frames, &node.cpu_time)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the config with vsync off for `length`, logging a summary of the timings and writing it
+/// to `report` as JSON, or as CSV if the file has a `.csv` extension
+pub fn benchmark(config_path: &Path, length: Length, report: Option<&Path>) -> Result<(), Error> {
+    let mut config = Config::parse(config_path)?;
+    config.vsync = false;
+    config.present = Some(PresentMode::Immediate);
+    config.fps_limit = None;
+
+    let mut events_loop = renderer::events_loop();
+    let backend = renderer::new_backend(&config, events_loop.as_ref(), "yotredash benchmark")?;
+    let (_sender, receiver) = mpsc::channel();
+    let mut renderer = backend.new_renderer(&config, receiver)?;
+    renderer.set_profiling(true);
+    renderer.warm_up(config.warmup_frames)?;
+
+    info!("Benchmarking {}", config_path.display());
+    let mut frame_times = Vec::new();
+    let mut cpu_times = Vec::new();
+    let mut nodes: IndexMap<String, (Vec<f32>, Vec<f32>)> = IndexMap::new();
+    let start = Instant::now();
+    let mut frame = 0;
+    loop {
+        let elapsed = start.elapsed();
+        let elapsed = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1e9;
+        let done = match length {
+            Length::Frames(frames) => frame >= frames,
+            Length::Seconds(seconds) => elapsed >= seconds,
+        };
+        if done {
+            break;
+        }
+
+        // The window has to keep handling events or the system thinks we've hung
+        if let Some(ref mut events_loop) = events_loop {
+            events_loop.poll_events(|_| ());
+        }
+        renderer.update()?;
+        renderer.render()?;
+        renderer.swap_buffers()?;
+
+        // The stats are for the frame before, so the first frame's aren't worth anything
+        if frame > 0 {
+            let stats = renderer.stats();
+            frame_times.push(stats.frame_time);
+            cpu_times.push(stats.cpu_time);
+            for node in &stats.nodes {
+                let times = nodes.entry(node.name.clone()).or_default();
+                times.0.push(node.gpu_time);
+                times.1.push(node.cpu_time);
+            }
+        }
+        frame += 1;
+    }
+
+    if frame_times.is_empty() {
+        bail!("The benchmark was too short to measure anything");
+    }
+    let duration = frame_times.iter().sum::<f32>() / 1_000.0;
+    let report_data = Report {
+        config: config_path.display().to_string(),
+        timestamp: time::now().rfc3339().to_string(),
+        frames: frame_times.len(),
+        duration,
+        fps: frame_times.len() as f32 / duration,
+        frame_time: Summary::new(&frame_times),
+        cpu_time: Summary::new(&cpu_times),
+        nodes: nodes
+            .into_iter()
+            .map(|(name, (gpu_times, cpu_times))| {
+                let node = NodeReport {
+                    frames: gpu_times.len(),
+                    gpu_time: Summary::new(&gpu_times),
+                    cpu_time: Summary::new(&cpu_times),
+                };
+                (name, node)
+            })
+            .collect(),
+    };
+
+    info!(
+        "{} frames in {:.2}s, {:.1} fps, frame time avg {:.2} ms p99 {:.2} ms max {:.2} ms",
+        report_data.frames,
+        report_data.duration,
+        report_data.fps,
+        report_data.frame_time.avg,
+        report_data.frame_time.p99,
+        report_data.frame_time.max
+    );
+    for (name, node) in &report_data.nodes {
+        info!(
+            "{:<16} gpu avg {:6.2} ms p99 {:6.2} ms  cpu avg {:6.2} ms p99 {:6.2} ms",
+            name, node.gpu_time.avg, node.gpu_time.p99, node.cpu_time.avg, node.cpu_time.p99
+        );
+    }
+
+    if let Some(path) = report {
+        let file = File::create(path)
+            .context(format!("Could not create report file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let csv = path.extension().map_or(false, |extension| {
+            extension.to_string_lossy().eq_ignore_ascii_case("csv")
+        });
+        if csv {
+            report_data.write_csv(&mut writer)?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, &report_data)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        info!("Saved benchmark report to {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Length, Summary};
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(Length::parse("500").unwrap(), Length::Frames(500));
+        assert_eq!(Length::parse("10s").unwrap(), Length::Seconds(10.0));
+        assert_eq!(Length::parse(" 2.5 s").unwrap(), Length::Seconds(2.5));
+        assert!(Length::parse("0").is_err());
+        assert!(Length::parse("-1s").is_err());
+        assert!(Length::parse("fast").is_err());
+    }
+
+    #[test]
+    fn summarizes_times() {
+        let summary = Summary::new(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.avg, 2.5);
+        assert_eq!(summary.p50, 2.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(Summary::new(&[]), Summary::default());
+    }
+}
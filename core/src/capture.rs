@@ -0,0 +1,301 @@
+//! Writes captured frames to disk, along with metadata describing what produced them, so a render
+//! can always be traced back to the config and shaders it came from
+
+use anyhow::{Context, Error};
+use crc32fast::Hasher;
+use image::{self, jpeg::JPEGEncoder, png::PNGEncoder};
+use sha1::Sha1;
+use std::{
+    fs::{self, File},
+    io::prelude::*,
+    path::{Path, PathBuf},
+};
+
+use crate::config::{capture::CaptureFormat, Config};
+
+/// Information about what produced a capture
+#[derive(Clone, Debug)]
+pub struct CaptureMetadata {
+    /// Path to the config file
+    pub config_path: PathBuf,
+    /// Hash of the contents of the config and every file it references
+    pub content_hash: String,
+    /// Time since the demo started (seconds), filled in by the renderer
+    pub demo_time: f32,
+}
+
+impl CaptureMetadata {
+    /// Collects the metadata for a config
+    pub fn new(config_path: &Path, config: &Config) -> Result<Self, Error> {
+        Ok(Self {
+            config_path: config_path.to_path_buf(),
+            content_hash: content_hash(config_path, config)?,
+            demo_time: 0.0,
+        })
+    }
+
+    /// Returns the metadata as keyword and text pairs
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "Software",
+                format!("yotredash {}", env!("CARGO_PKG_VERSION")),
+            ),
+            ("Config", self.config_path.to_string_lossy().to_string()),
+            ("Content Hash", self.content_hash.clone()),
+            ("Demo Time", format!("{:.3}", self.demo_time)),
+        ]
+    }
+}
+
+/// Hashes a file the same way git hashes blobs
+fn blob_hash(path: &Path) -> Result<String, Error> {
+    let contents = fs::read(path)
+        .with_context(|| format!("Could not read {} for hashing", path.to_string_lossy()))?;
+
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", contents.len()).as_bytes());
+    hasher.update(&contents);
+    Ok(hasher.digest().to_string())
+}
+
+/// Hashes the config and every file it references, similarly to how git hashes a tree: each file
+/// is hashed as a blob, then the list of blob hashes and paths is hashed
+pub fn content_hash(config_path: &Path, config: &Config) -> Result<String, Error> {
+    let mut hasher = Sha1::new();
+    for path in Some(config_path.to_path_buf())
+        .into_iter()
+        .chain(config.files())
+    {
+        let name = path
+            .strip_prefix(&config._cwd)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        hasher.update(format!("{} {}\n", blob_hash(&path)?, name).as_bytes());
+    }
+    Ok(hasher.digest().to_string())
+}
+
+/// Writes a PNG chunk
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    let mut hasher = Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&hasher.finalize().to_be_bytes())?;
+    Ok(())
+}
+
+/// Flattens rows of RGBA pixels as read from a texture
+pub fn flatten(rows: Vec<Vec<(f32, f32, f32, f32)>>) -> Vec<f32> {
+    rows.into_iter()
+        .flat_map(|row| row.into_iter())
+        .flat_map(|(r, g, b, a)| vec![r, g, b, a])
+        .collect()
+}
+
+/// Shrinks RGBA pixel data by a whole factor, averaging each block of pixels into one
+pub fn downsample(pixels: &[f32], width: u32, height: u32, factor: u32) -> Vec<f32> {
+    let (width, height, factor) = (width as usize, height as usize, factor as usize);
+    let (out_width, out_height) = (width / factor, height / factor);
+    let samples = (factor * factor) as f32;
+
+    let mut output = vec![0.0; out_width * out_height * 4];
+    for y in 0..out_height * factor {
+        for x in 0..out_width * factor {
+            let source = (y * width + x) * 4;
+            let target = ((y / factor) * out_width + x / factor) * 4;
+            for channel in 0..4 {
+                output[target + channel] += pixels[source + channel] / samples;
+            }
+        }
+    }
+    output
+}
+
+/// Converts pixel data to 8 bits per channel, keeping only the given number of channels
+pub fn to_u8(pixels: &[f32], channels: usize) -> Vec<u8> {
+    pixels
+        .chunks(4)
+        .flat_map(|pixel| pixel[..channels].iter())
+        .map(|value| (value.max(0.0).min(1.0) * 255.0).round() as u8)
+        .collect()
+}
+
+/// Converts an sRGB encoded value to linear light
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Saves RGBA pixel data, from top to bottom, in the given format. The metadata is embedded if
+/// the format supports it.
+pub fn save(
+    path: &Path,
+    format: CaptureFormat,
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    metadata: &CaptureMetadata,
+) -> Result<(), Error> {
+    match format {
+        CaptureFormat::Png => save_png(path, &to_u8(pixels, 4), width, height, metadata),
+        CaptureFormat::Jpg => {
+            let mut file = File::create(path).context("Could not create capture file")?;
+            JPEGEncoder::new_with_quality(&mut file, 95).encode(
+                &to_u8(pixels, 3),
+                width,
+                height,
+                image::RGB(8),
+            )?;
+            Ok(())
+        }
+        CaptureFormat::Exr => save_exr(path, pixels, width, height, metadata),
+    }
+}
+
+/// Saves RGBA pixel data as a PNG with the metadata in iTXt chunks
+pub fn save_png(
+    path: &Path,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    metadata: &CaptureMetadata,
+) -> Result<(), Error> {
+    let mut png = Vec::new();
+    PNGEncoder::new(&mut png).encode(data, width, height, image::RGBA(8))?;
+
+    // The signature is followed by the IHDR chunk, and text chunks can go anywhere after that
+    let ihdr_end = 8 + 4 + 4 + 13 + 4;
+
+    let mut file = File::create(path).context("Could not create capture file")?;
+    file.write_all(&png[..ihdr_end])?;
+    for (keyword, text) in metadata.entries() {
+        // Keyword, then no compression, no language tag, and no translated keyword
+        let mut chunk = keyword.as_bytes().to_vec();
+        chunk.extend_from_slice(&[0, 0, 0, 0, 0]);
+        chunk.extend_from_slice(text.as_bytes());
+        write_chunk(&mut file, b"iTXt", &chunk)?;
+    }
+    file.write_all(&png[ihdr_end..])?;
+
+    Ok(())
+}
+
+/// Appends an OpenEXR header attribute
+fn exr_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Saves RGBA pixel data as an uncompressed OpenEXR image with 32-bit float channels, converting
+/// to linear light and storing the metadata as string attributes
+pub fn save_exr(
+    path: &Path,
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    metadata: &CaptureMetadata,
+) -> Result<(), Error> {
+    // Magic number, then version 2 with no flags (a single part made of scanlines)
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+
+    // Channels must be sorted by name. Each is 32-bit float (2), not linear, and not subsampled.
+    let mut channels = Vec::new();
+    for name in &["A", "B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&2i32.to_le_bytes());
+        channels.extend_from_slice(&[0; 4]);
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect();
+
+    exr_attribute(&mut header, "channels", "chlist", &channels);
+    exr_attribute(&mut header, "compression", "compression", &[0]);
+    exr_attribute(&mut header, "dataWindow", "box2i", &window);
+    exr_attribute(&mut header, "displayWindow", "box2i", &window);
+    exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_bits().to_le_bytes(),
+    );
+    exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_bits().to_le_bytes(),
+    );
+    for (name, text) in metadata.entries() {
+        exr_attribute(&mut header, name, "string", text.as_bytes());
+    }
+    header.push(0);
+
+    // Without compression each block is a single scanline: its y coordinate, its size, then each
+    // channel's values for the whole line
+    let line_size = width as usize * 4 * 4;
+    let blocks_start = header.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        let offset = (blocks_start + y * (8 + line_size)) as u64;
+        header.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    let mut file = File::create(path).context("Could not create capture file")?;
+    file.write_all(&header)?;
+    let mut line = Vec::with_capacity(8 + line_size);
+    for y in 0..height as usize {
+        line.clear();
+        line.extend_from_slice(&(y as i32).to_le_bytes());
+        line.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for &channel in &[3, 2, 1, 0] {
+            for x in 0..width as usize {
+                let mut value = pixels[(y * width as usize + x) * 4 + channel];
+                if channel != 3 {
+                    value = srgb_to_linear(value);
+                }
+                line.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+        }
+        file.write_all(&line)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::downsample;
+
+    #[test]
+    fn downsample_averages_blocks() {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let pixels = [
+            0.0, 0.0, 0.0, 1.0,  1.0, 1.0, 1.0, 1.0,  0.5, 0.5, 0.5, 1.0,  0.5, 0.5, 0.5, 1.0,
+            1.0, 1.0, 1.0, 1.0,  0.0, 0.0, 0.0, 1.0,  0.5, 0.5, 0.5, 1.0,  0.5, 0.5, 0.5, 1.0,
+        ];
+        assert_eq!(
+            downsample(&pixels, 4, 2, 2),
+            vec![0.5, 0.5, 0.5, 1.0, 0.5, 0.5, 0.5, 1.0]
+        );
+    }
+}
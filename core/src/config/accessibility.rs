@@ -0,0 +1,54 @@
+//! Configuration related to accessibility
+
+use serde_derive::Deserialize;
+
+/// Accessibility configuration. These can also be set on the command line, so an installation can
+/// honor accessibility requests without needing separate configs.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AccessibilityConfig {
+    /// Draw errors, the HUD and notices in high-contrast colors with larger text
+    #[serde(default = "default_high_contrast")]
+    pub high_contrast: bool,
+
+    /// Ask for reduced motion. Info nodes report this through their `reduced_motion` output (1.0
+    /// when set, 0.0 otherwise), so shaders can tone themselves down
+    #[serde(default = "default_reduced_motion")]
+    pub reduced_motion: bool,
+
+    /// The fastest time may pass, relative to real time, when reduced motion is asked for. Configs
+    /// opt in to slowing down by setting this
+    #[serde(default)]
+    pub reduced_motion_speed: Option<f32>,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            high_contrast: default_high_contrast(),
+            reduced_motion: default_reduced_motion(),
+            reduced_motion_speed: Default::default(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `high_contrast` field
+fn default_high_contrast() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `reduced_motion` field
+fn default_reduced_motion() -> bool {
+    false
+}
+
+impl AccessibilityConfig {
+    /// Returns the fastest the clock may run, if it's capped
+    pub fn max_speed(&self) -> Option<f32> {
+        if self.reduced_motion {
+            self.reduced_motion_speed
+        } else {
+            None
+        }
+    }
+}
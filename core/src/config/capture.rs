@@ -0,0 +1,162 @@
+//! Configuration related to capturing images of the output
+
+use anyhow::{ensure, Context, Error};
+use serde_derive::Deserialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::PathBuf,
+    process,
+};
+use time;
+
+/// Image formats captures can be saved in
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureFormat {
+    /// PNG, which also stores the capture metadata
+    Png,
+    /// JPEG
+    Jpg,
+    /// OpenEXR, with linear 32-bit float channels, which also stores the capture metadata
+    Exr,
+}
+
+impl CaptureFormat {
+    /// Returns the file extension for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Jpg => "jpg",
+            CaptureFormat::Exr => "exr",
+        }
+    }
+
+    /// Returns the format a file extension stands for, if any
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "png" => Some(CaptureFormat::Png),
+            "jpg" | "jpeg" => Some(CaptureFormat::Jpg),
+            "exr" => Some(CaptureFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// Capture configuration
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Directory to save captures in, relative to the current working directory
+    #[serde(default = "default_directory")]
+    pub directory: PathBuf,
+
+    /// Name of this instance, which is included in capture names so that several instances can
+    /// capture into the same directory. Defaults to the process ID
+    #[serde(default)]
+    pub instance: Option<String>,
+
+    /// Whether or not to save captures in a subdirectory named after the instance
+    #[serde(default = "default_per_instance")]
+    pub per_instance: bool,
+
+    /// Image format to save captures in
+    #[serde(default = "default_format")]
+    pub format: CaptureFormat,
+
+    /// Render captures at this many times the window's resolution, then downsample them, for
+    /// smoother stills
+    #[serde(default = "default_supersample")]
+    pub supersample: u32,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            directory: default_directory(),
+            instance: Default::default(),
+            per_instance: default_per_instance(),
+            format: default_format(),
+            supersample: default_supersample(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `directory` field
+fn default_directory() -> PathBuf {
+    PathBuf::from(".")
+}
+
+/// A function that returns the default value of the `per_instance` field
+fn default_per_instance() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `format` field
+fn default_format() -> CaptureFormat {
+    CaptureFormat::Png
+}
+
+/// A function that returns the default value of the `supersample` field
+fn default_supersample() -> u32 {
+    1
+}
+
+impl CaptureConfig {
+    /// Checks the settings that can't be caught while parsing
+    pub fn validate(&self) -> Result<(), Error> {
+        ensure!(
+            self.supersample >= 1,
+            "Capture supersample factor must be at least 1"
+        );
+        Ok(())
+    }
+
+    /// Returns the name of this instance
+    pub fn instance(&self) -> String {
+        self.instance
+            .clone()
+            .unwrap_or_else(|| process::id().to_string())
+    }
+
+    /// Reserves a path for a new capture of the given frame, creating the capture directory if
+    /// necessary. The file is created empty so that no other instance can claim the same name.
+    pub fn reserve_path(&self, frame: u64) -> Result<PathBuf, Error> {
+        self.reserve_path_with_extension(frame, self.format.extension())
+    }
+
+    /// Like `reserve_path`, but for a file with a different extension than the capture format
+    pub fn reserve_path_with_extension(
+        &self,
+        frame: u64,
+        extension: &str,
+    ) -> Result<PathBuf, Error> {
+        let instance = self.instance();
+
+        let mut directory = self.directory.clone();
+        if self.per_instance {
+            directory.push(&instance);
+        }
+        fs::create_dir_all(&directory).context("Could not create capture directory")?;
+
+        let stem = format!(
+            "{}_{}_{:06}",
+            time::now().strftime("%Y-%m-%d_%H-%M-%S")?,
+            instance,
+            frame
+        );
+
+        let mut path = directory.join(format!("{}.{}", stem, extension));
+        let mut suffix = 1;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(path),
+                Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                    path = directory.join(format!("{}_{}.{}", stem, suffix, extension));
+                    suffix += 1;
+                }
+                Err(e) => return Err(e).context("Could not create capture file"),
+            }
+        }
+    }
+}
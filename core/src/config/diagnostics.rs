@@ -0,0 +1,32 @@
+//! Configuration related to saving diagnostics when errors occur
+
+use serde_derive::Deserialize;
+use std::path::PathBuf;
+
+/// Diagnostics configuration
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    /// Directory to save diagnostics bundles in, relative to the current working directory. No
+    /// bundles are saved if this isn't set
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+
+    /// Minimum time between bundles (seconds), so a persistent error doesn't fill the disk
+    #[serde(default = "default_interval")]
+    pub interval: f32,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            directory: Default::default(),
+            interval: default_interval(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `interval` field
+fn default_interval() -> f32 {
+    60.0
+}
@@ -0,0 +1,365 @@
+//! Configuration related to groups, which reuse the graph of another config file like a function
+//!
+//! ```yaml
+//! groups:
+//!     glow:
+//!         config: effects/glow.yml
+//!         inputs:
+//!             texture: { node: scene, output: texture }
+//!             radius: { param: glow_radius }
+//!         params:
+//!             tint: [1.0, 0.5, 0.0, 1.0]
+//! ```
+//!
+//! The nodes of the group's config are merged into this one under the name of the group (e.g.
+//! `glow/blur` for its `blur` node), so the same config can be used by several groups. Inside it,
+//! connections to the `input` node read the group's `inputs`, and any of its `params` can be
+//! overridden by an input with the same name, or given a value for this group with `params`.
+//! Other nodes connect to the group like any other node: its `texture` output is whatever the
+//! group's output node shows, and more outputs can be declared with `outputs`.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use indexmap::IndexMap;
+use serde_derive::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    nodes::{NodeConfig, NodeConnection},
+    params::{ParamValue, PARAM_PREFIX},
+    Config,
+};
+
+/// Name of the node the nodes of a group read its inputs from
+pub const GROUP_INPUT_NODE: &str = "input";
+
+thread_local! {
+    /// The group config files being loaded, to catch groups that include themselves
+    static LOADING: RefCell<Vec<PathBuf>> = RefCell::new(Vec::new());
+}
+
+/// A group of nodes loaded from another config file
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    /// The config file holding the group's nodes, relative to this config
+    pub config: PathBuf,
+
+    /// Connections the group's nodes read through the `input` node or its params, by name
+    #[serde(default)]
+    pub inputs: IndexMap<String, NodeConnection>,
+
+    /// Outputs of the group, as connections to the group's nodes. `texture` defaults to the
+    /// texture shown by the group's output node, if it has one.
+    #[serde(default)]
+    pub outputs: IndexMap<String, NodeConnection>,
+
+    /// Values for params of the group's config, used instead of the ones it gives
+    #[serde(default)]
+    pub params: IndexMap<String, ParamValue>,
+}
+
+/// Merges the graph of a group's config into a config, returning the connections its outputs
+/// stand for
+fn merge_group(
+    config: &mut Config,
+    name: &str,
+    group: &GroupConfig,
+    mut graph: Config,
+) -> Result<HashMap<String, NodeConnection>, Error> {
+    ensure!(
+        graph.timeline.is_none(),
+        "Groups can't have timelines of their own"
+    );
+
+    // Plugin nodes need their libraries, which are found relative to the group's config. They go
+    // through the same sandbox check as the config's own plugins.
+    let directory = group.config.parent().unwrap_or_else(|| Path::new(""));
+    for plugin in &graph.plugins {
+        let plugin = directory.join(plugin);
+        if !config.plugins.contains(&plugin) {
+            config.plugins.push(plugin);
+        }
+    }
+
+    for (param, value) in &group.params {
+        match graph.params.get(param) {
+            Some(default) => ensure!(
+                mem::discriminant(default) == mem::discriminant(value),
+                "The param `{}` must be a {}",
+                param,
+                match *default {
+                    ParamValue::Scalar(_) => "number",
+                    ParamValue::Color(_) => "color",
+                }
+            ),
+            None => bail!("The group's config has no param named `{}`", param),
+        }
+        if let Some(node) = graph.nodes.get_mut(&format!("{}{}", PARAM_PREFIX, param)) {
+            node.config = NodeConfig::Constant(value.constant());
+        }
+    }
+
+    let rename = |node: &str| format!("{}/{}", name, node);
+
+    // Params that an input overrides are read from the input instead of their node
+    let overridden = |node: &str| {
+        if node.starts_with(PARAM_PREFIX) {
+            group.inputs.get(&node[PARAM_PREFIX.len()..])
+        } else {
+            None
+        }
+    };
+
+    let mut texture = None;
+    for (node_name, mut node) in graph.nodes {
+        if overridden(&node_name).is_some() {
+            continue;
+        }
+
+        for connection in node.config.connections_mut() {
+            let input = if connection.node == GROUP_INPUT_NODE {
+                Some(group.inputs.get(&connection.output).ok_or_else(|| {
+                    format_err!("The group has no input named `{}`", connection.output)
+                })?)
+            } else {
+                overridden(&connection.node)
+            };
+            match input {
+                // The name and type belong to the connection, not where it reads from
+                Some(input) => {
+                    connection.node = input.node.clone();
+                    connection.output = input.output.clone();
+                    connection.param = input.param.clone();
+                    connection.track = input.track.clone();
+                }
+                None => connection.node = rename(&connection.node),
+            }
+        }
+
+        if let NodeConfig::Output(output_config) = node.config {
+            texture = Some(output_config.texture);
+            continue;
+        }
+        // Files are relative to the group's config
+        node.config.resolve_paths(&graph._cwd);
+
+        let node_name = rename(&node_name);
+        ensure!(
+            !config.nodes.contains_key(&node_name),
+            "There's already a node named `{}`",
+            node_name
+        );
+        config.nodes.insert(node_name, node);
+    }
+
+    let mut outputs: HashMap<String, NodeConnection> = group
+        .outputs
+        .iter()
+        .map(|(output, connection)| {
+            let mut connection = connection.clone();
+            connection.node = rename(&connection.node);
+            (output.clone(), connection)
+        })
+        .collect();
+    if let Some(texture) = texture {
+        outputs.entry("texture".to_string()).or_insert(texture);
+    }
+    Ok(outputs)
+}
+
+/// Merges the graphs of the groups into a config, and connects the nodes that read from the
+/// groups to the nodes inside them. This comes before params are expanded, so inputs can read
+/// params of this config.
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    let groups = config.groups.clone();
+
+    let mut group_outputs = HashMap::new();
+    for (name, group) in &groups {
+        ensure!(
+            !config.nodes.contains_key(name),
+            "The group `{}` has the same name as a node",
+            name
+        );
+
+        let path = config.path_to(&group.config);
+        let graph = load_guarded(&path, |path| Config::load(path).map(|(graph, _)| graph))
+            .context(format!("Error in group `{}`", name))?;
+        let outputs = merge_group(config, name, group, graph)
+            .context(format!("Error in group `{}`", name))?;
+        group_outputs.insert(name.as_str(), outputs);
+    }
+
+    // Groups can read from each other, so connections are only redirected once every group is in
+    let mut connections: Vec<&mut NodeConnection> = config
+        .nodes
+        .values_mut()
+        .flat_map(|node| node.config.connections_mut())
+        .collect();
+    if let Some(ref mut timeline) = config.timeline {
+        connections.extend(
+            timeline
+                .scenes
+                .iter_mut()
+                .filter_map(|scene| scene.texture.as_mut()),
+        );
+    }
+    for connection in connections {
+        if let Some(outputs) = group_outputs.get(connection.node.as_str()) {
+            match outputs.get(&connection.output) {
+                Some(output) => {
+                    connection.node = output.node.clone();
+                    connection.output = output.output.clone();
+                }
+                None => bail!(
+                    "The group `{}` has no output named `{}`",
+                    connection.node,
+                    connection.output
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the config file of a group or a timeline scene with `load`, refusing to load one that's
+/// already being loaded further up
+pub(super) fn load_guarded<T>(
+    path: &Path,
+    load: impl FnOnce(&Path) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let canonical = path
+        .canonicalize()
+        .context(format!("Unable to open `{}`", path.display()))?;
+    let cycle = LOADING.with(|loading| loading.borrow().contains(&canonical));
+    ensure!(!cycle, "`{}` includes itself", path.display());
+
+    LOADING.with(|loading| loading.borrow_mut().push(canonical));
+    let result = load(path);
+    LOADING.with(|loading| loading.borrow_mut().pop());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_group, GroupConfig};
+    use crate::config::{
+        nodes::{ConstantConfig, NodeConfig},
+        params, Config,
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn merges_groups() {
+        let graph = "
+params:
+    radius: 4.0
+    amount: 1.0
+nodes:
+    blur:
+        type: blur
+        texture:
+            node: input
+            output: texture
+        radius:
+            param: radius
+    output:
+        type: output
+        texture:
+            node: blur
+            output: texture
+";
+        let group = "
+config: glow.yml
+inputs:
+    texture: { node: scene, output: texture }
+    radius: { node: knob, output: value }
+";
+        let mut graph: Config = serde_yaml::from_str(graph).unwrap();
+        params::expand(&mut graph).unwrap();
+        let group: GroupConfig = serde_yaml::from_str(group).unwrap();
+
+        let mut config = Config::default();
+        let outputs = merge_group(&mut config, "glow", &group, graph).unwrap();
+
+        let names: Vec<&str> = config.nodes.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["glow/blur", "glow/params/amount"]);
+
+        let connections = config.nodes["glow/blur"].config.connections();
+        assert_eq!(connections[0].1.node, "scene");
+        assert_eq!(connections[1].1.node, "knob");
+        assert_eq!(connections[1].1.output, "value");
+        assert_eq!(outputs["texture"].node, "glow/blur");
+    }
+
+    #[test]
+    fn gives_each_group_its_own_params() {
+        let graph = "
+params:
+    tint: [1.0, 1.0, 1.0, 1.0]
+nodes:
+    output:
+        type: output
+        texture:
+            node: input
+            output: texture
+";
+        let mut graph: Config = serde_yaml::from_str(graph).unwrap();
+        params::expand(&mut graph).unwrap();
+
+        let mut config = Config::default();
+        for (name, tint) in &[("red", "[1, 0, 0, 1]"), ("blue", "[0, 0, 1, 1]")] {
+            let group = format!(
+                "{{config: glow.yml, inputs: {{texture: {{node: scene, output: texture}}}}, \
+                 params: {{tint: {}}}}}",
+                tint
+            );
+            let group: GroupConfig = serde_yaml::from_str(&group).unwrap();
+            merge_group(&mut config, name, &group, graph.clone()).unwrap();
+        }
+
+        match config.nodes["blue/params/tint"].config {
+            NodeConfig::Constant(ConstantConfig::Color(color)) => {
+                assert_eq!(color, [0.0, 0.0, 1.0, 1.0])
+            }
+            ref other => panic!("Unexpected node: {:?}", other),
+        }
+        match config.nodes["red/params/tint"].config {
+            NodeConfig::Constant(ConstantConfig::Color(color)) => {
+                assert_eq!(color, [1.0, 0.0, 0.0, 1.0])
+            }
+            ref other => panic!("Unexpected node: {:?}", other),
+        }
+
+        let group: GroupConfig =
+            serde_yaml::from_str("{config: glow.yml, params: {tint: 2}}").unwrap();
+        assert!(merge_group(&mut config, "green", &group, graph).is_err());
+    }
+
+    #[test]
+    fn keeps_plugins_of_groups() {
+        let graph = "
+plugins: [libnodes.so]
+nodes:
+    output:
+        type: output
+        texture:
+            node: input
+            output: texture
+";
+        let graph: Config = serde_yaml::from_str(graph).unwrap();
+        let group: GroupConfig = serde_yaml::from_str(
+            "{config: effects/glow.yml, inputs: {texture: {node: scene, output: texture}}}",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        merge_group(&mut config, "glow", &group, graph).unwrap();
+        assert_eq!(config.plugins, vec![PathBuf::from("effects/libnodes.so")]);
+    }
+}
@@ -0,0 +1,288 @@
+//! Configuration of the keys that control yotredash while it's running
+//!
+//! Keys are named like winit's virtual key codes (`F5`, `Escape`, `Space`, `A`, `1`), optionally
+//! preceded by modifiers (`Ctrl+`, `Shift+`, `Alt+`, `Logo+`). Bindings in the config are added to
+//! the defaults, replacing any default for the same key, and binding a key to `none` removes its
+//! default. The movement keys (W, A, S, D, Q, E) always go to camera nodes.
+
+use anyhow::{bail, format_err, Error};
+use indexmap::IndexMap;
+use serde_derive::Deserialize;
+use winit::{ModifiersState, VirtualKeyCode};
+
+/// Something a key can do
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// Close the window
+    Close,
+    /// Capture an image
+    Capture,
+    /// Start or stop recording an animation
+    ToggleRecording,
+    /// Reload, carrying over the time and feedback
+    Reload,
+    /// Reload from scratch
+    Restart,
+    /// Pause or resume rendering
+    Pause,
+    /// Pause and move forward by one frame
+    Step,
+    /// Show or hide the performance HUD
+    ToggleHud,
+    /// Show or hide the tweak panel, which takes over the arrow keys while it's shown
+    ToggleTweaks,
+    /// Show or hide the graph editor, which takes over the arrow keys while it's shown
+    ToggleEditor,
+    /// Switch to the next supported present mode
+    CyclePresentMode,
+    /// Turn vertical sync on or off
+    ToggleVsync,
+    /// Clear the values of feedback nodes
+    ClearFeedback,
+    /// Move forward or backward by this many seconds
+    TimeScrub(f32),
+    /// Multiply the playback speed by this factor
+    Speed(f32),
+    /// Jump to the start of this scene of the timeline, counting from 0
+    SwitchScene(usize),
+    /// Do nothing, to remove a default binding
+    None,
+}
+
+/// The bindings used unless the config replaces them
+const DEFAULTS: &[(&str, Action)] = &[
+    ("Escape", Action::Close),
+    ("F1", Action::ToggleHud),
+    ("F2", Action::Capture),
+    ("F3", Action::ToggleRecording),
+    ("F4", Action::ToggleTweaks),
+    ("F5", Action::Reload),
+    ("Shift+F5", Action::Restart),
+    ("F6", Action::Pause),
+    ("F7", Action::Step),
+    ("F8", Action::CyclePresentMode),
+    ("Shift+F8", Action::ToggleVsync),
+    ("F9", Action::ClearFeedback),
+    ("F10", Action::ToggleEditor),
+    ("Left", Action::TimeScrub(-1.0)),
+    ("Right", Action::TimeScrub(1.0)),
+    ("Up", Action::Speed(2.0)),
+    ("Down", Action::Speed(0.5)),
+];
+
+/// Returns the key code with a name
+fn key_code(name: &str) -> Option<VirtualKeyCode> {
+    use winit::VirtualKeyCode::*;
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] =
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const NUMPAD: [VirtualKeyCode; 10] = [
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    ];
+    const FUNCTION: [VirtualKeyCode; 15] = [
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+    ];
+
+    let name = name.to_lowercase();
+    let index = |prefix: &str, count: usize, first: usize| {
+        if !name.starts_with(prefix) {
+            return None;
+        }
+        name[prefix.len()..]
+            .parse::<usize>()
+            .ok()
+            .filter(|&number| number >= first && number < first + count)
+            .map(|number| number - first)
+    };
+
+    if name.len() == 1 {
+        let c = name.as_bytes()[0];
+        return match c {
+            b'a'..=b'z' => Some(LETTERS[usize::from(c - b'a')]),
+            b'0'..=b'9' => Some(DIGITS[usize::from(c - b'0')]),
+            _ => None,
+        };
+    }
+    if let Some(index) = index("numpad", NUMPAD.len(), 0) {
+        return Some(NUMPAD[index]);
+    }
+    if let Some(index) = index("f", FUNCTION.len(), 1) {
+        return Some(FUNCTION[index]);
+    }
+
+    Some(match name.as_str() {
+        "escape" | "esc" => Escape,
+        "space" => Space,
+        "return" | "enter" => Return,
+        "tab" => Tab,
+        "backspace" | "back" => Back,
+        "insert" => Insert,
+        "delete" => Delete,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "left" => Left,
+        "right" => Right,
+        "up" => Up,
+        "down" => Down,
+        "minus" => Minus,
+        "equals" => Equals,
+        "comma" => Comma,
+        "period" => Period,
+        "slash" => Slash,
+        "backslash" => Backslash,
+        "semicolon" => Semicolon,
+        "apostrophe" => Apostrophe,
+        "grave" => Grave,
+        "lbracket" => LBracket,
+        "rbracket" => RBracket,
+        "pause" => Pause,
+        _ => return None,
+    })
+}
+
+/// A key along with the modifiers that have to be held with it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyChord {
+    /// The key
+    pub key: VirtualKeyCode,
+    /// Whether control is held
+    pub ctrl: bool,
+    /// Whether shift is held
+    pub shift: bool,
+    /// Whether alt is held
+    pub alt: bool,
+    /// Whether the logo key is held
+    pub logo: bool,
+}
+
+impl KeyChord {
+    /// Parses a key name with modifiers, like `Ctrl+Shift+F5`
+    pub fn parse(chord: &str) -> Result<Self, Error> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let name = parts.pop().unwrap();
+        let key = key_code(name).ok_or_else(|| format_err!("Unknown key `{}`", name))?;
+
+        let mut result = Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+            logo: false,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => result.ctrl = true,
+                "shift" => result.shift = true,
+                "alt" => result.alt = true,
+                "logo" | "super" | "cmd" => result.logo = true,
+                _ => bail!("Unknown modifier `{}` in `{}`", modifier, chord),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns whether a key press with these modifiers is this chord
+    pub fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl
+            && self.shift == modifiers.shift
+            && self.alt == modifiers.alt
+            && self.logo == modifiers.logo
+    }
+}
+
+/// The lookup from key presses to actions
+#[derive(Debug, Clone)]
+pub struct Keybindings {
+    /// The keys that are bound, with what they do
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Keybindings {
+    /// Creates the lookup from the bindings in a config, on top of the defaults
+    pub fn new(config: &IndexMap<String, Action>) -> Result<Self, Error> {
+        let mut bindings: Vec<(KeyChord, Action)> = DEFAULTS
+            .iter()
+            .map(|&(chord, action)| (KeyChord::parse(chord).unwrap(), action))
+            .collect();
+
+        for (chord, &action) in config {
+            let chord = KeyChord::parse(chord)
+                .map_err(|e| format_err!("Invalid key binding `{}`: {}", chord, e))?;
+            bindings.retain(|&(bound, _)| bound != chord);
+            if action != Action::None {
+                bindings.push((chord, action));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// Returns what a key press does, if anything
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key, modifiers))
+            .map(|&(_, action)| action)
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self::new(&IndexMap::new()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, KeyChord, Keybindings};
+    use indexmap::IndexMap;
+    use winit::{ModifiersState, VirtualKeyCode};
+
+    #[test]
+    fn parses_chords() {
+        let chord = KeyChord::parse("Ctrl+Shift+F5").unwrap();
+        assert_eq!(chord.key, VirtualKeyCode::F5);
+        assert!(chord.ctrl && chord.shift && !chord.alt && !chord.logo);
+
+        assert_eq!(KeyChord::parse("1").unwrap().key, VirtualKeyCode::Key1);
+        assert_eq!(KeyChord::parse("space").unwrap().key, VirtualKeyCode::Space);
+        assert!(KeyChord::parse("F99").is_err());
+        assert!(KeyChord::parse("Hyper+A").is_err());
+    }
+
+    #[test]
+    fn overrides_defaults() {
+        let mut config = IndexMap::new();
+        config.insert("Escape".to_string(), Action::None);
+        config.insert("F5".to_string(), Action::Capture);
+        config.insert("Shift+1".to_string(), Action::SwitchScene(1));
+        let keybindings = Keybindings::new(&config).unwrap();
+
+        let none = ModifiersState::default();
+        let shift = ModifiersState {
+            shift: true,
+            ..ModifiersState::default()
+        };
+        assert_eq!(keybindings.action(VirtualKeyCode::Escape, none), None);
+        assert_eq!(
+            keybindings.action(VirtualKeyCode::F5, none),
+            Some(Action::Capture)
+        );
+        assert_eq!(
+            keybindings.action(VirtualKeyCode::F5, shift),
+            Some(Action::Restart)
+        );
+        assert_eq!(
+            keybindings.action(VirtualKeyCode::Key1, shift),
+            Some(Action::SwitchScene(1))
+        );
+        assert_eq!(keybindings.action(VirtualKeyCode::Key1, none), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Keyframe animation, so parameters can be choreographed over time instead of computed in
+//! shaders
+//!
+//! ```yaml
+//! keyframes:
+//!     zoom:
+//!         keys:
+//!             - { time: 0.0, value: 1.0, ease: ease_in_out }
+//!             - { time: 4.0, value: 2.5, ease: hold }
+//!             - { time: 6.0, value: 1.0 }
+//!         repeat: true
+//!     tint:
+//!         keys:
+//!             - { time: 0.0, value: [1.0, 0.0, 0.0, 1.0] }
+//!             - { time: 2.0, value: [0.0, 0.0, 1.0, 1.0] }
+//! ```
+//!
+//! Any connection can read a track with `track: zoom` instead of `node` and `output`. Each track
+//! becomes a `keyframes` node named `keyframes/<name>`, whose `value` output is the track's value
+//! at the current demo time, so it follows pausing and seeking. A key's `ease` is how the value
+//! moves from it to the next key. Before the first key and after the last the value stays at
+//! theirs, unless the track repeats.
+
+use anyhow::{ensure, Context, Error};
+use serde_derive::Deserialize;
+use std::mem;
+
+use super::{
+    nodes::{Node, NodeConfig},
+    params::ParamValue,
+    Config,
+};
+
+/// Prefix of the names of the nodes tracks are read from
+pub const TRACK_PREFIX: &str = "keyframes/";
+
+/// How the value moves from one key to the next
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// Stay at this key's value until the next key
+    Hold,
+    /// Move at a constant speed
+    Linear,
+    /// Start slowly and speed up
+    EaseIn,
+    /// Start quickly and slow down
+    EaseOut,
+    /// Start and end slowly
+    EaseInOut,
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::Linear
+    }
+}
+
+impl Easing {
+    /// Returns how far the value has moved, from 0 to 1, when `t` of the time between the keys has
+    /// passed
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            Easing::Hold => 0.0,
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// A value at a point in time
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    /// When the track reaches the value (seconds)
+    pub time: f32,
+
+    /// A number, or a color (RGBA)
+    pub value: ParamValue,
+
+    /// How the value moves from this key to the next
+    #[serde(default)]
+    pub ease: Easing,
+}
+
+/// Keyframes node type - outputs a value animated between keys. Usually added from the
+/// `keyframes` section, but it can be written as a node too.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrackConfig {
+    /// The keys, in the order of their times
+    pub keys: Vec<Keyframe>,
+
+    /// Start over from the first key after the last one
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+impl TrackConfig {
+    /// Checks that the track has keys, in order, and that they're all the same kind of value
+    pub fn validate(&self) -> Result<(), Error> {
+        ensure!(!self.keys.is_empty(), "The track has no keys");
+        for pair in self.keys.windows(2) {
+            ensure!(
+                pair[1].time >= pair[0].time,
+                "Keys must be in the order of their times, but {} comes after {}",
+                pair[1].time,
+                pair[0].time
+            );
+            ensure!(
+                mem::discriminant(&pair[0].value) == mem::discriminant(&pair[1].value),
+                "Keys must all be numbers or all be colors"
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the track at a time (seconds). The track must be valid.
+    pub fn value_at(&self, time: f32) -> ParamValue {
+        let first = &self.keys[0];
+        let last = &self.keys[self.keys.len() - 1];
+        let length = last.time - first.time;
+        let time = if self.repeat && length > 0.0 {
+            first.time + (time - first.time).rem_euclid(length)
+        } else {
+            time
+        };
+
+        match self.keys.iter().position(|key| key.time > time) {
+            None => last.value,
+            Some(0) => first.value,
+            Some(next) => {
+                let from = &self.keys[next - 1];
+                let to = &self.keys[next];
+                let t = from.ease.apply((time - from.time) / (to.time - from.time));
+                mix(from.value, to.value, t)
+            }
+        }
+    }
+}
+
+/// Moves from one value towards another of the same kind by `t`
+fn mix(from: ParamValue, to: ParamValue, t: f32) -> ParamValue {
+    match (from, to) {
+        (ParamValue::Scalar(from), ParamValue::Scalar(to)) => {
+            ParamValue::Scalar(from + (to - from) * t)
+        }
+        (ParamValue::Color(from), ParamValue::Color(to)) => {
+            let mut color = from;
+            for (channel, to) in color.iter_mut().zip(&to) {
+                *channel += (to - *channel) * t;
+            }
+            ParamValue::Color(color)
+        }
+        _ => from,
+    }
+}
+
+/// Adds a keyframes node for each track, and points the connections that read tracks at them
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    for (node_name, node) in config.nodes.iter_mut() {
+        for connection in node.config.connections_mut() {
+            if let Some(track) = connection.track.take() {
+                ensure!(
+                    connection.node.is_empty()
+                        && connection.output.is_empty()
+                        && connection.param.is_none(),
+                    "Node `{}`: a connection can't have both `track` and `node`, `output` or \
+                     `param`",
+                    node_name
+                );
+                ensure!(
+                    config.keyframes.contains_key(&track),
+                    "Node `{}`: there's no keyframe track named `{}`",
+                    node_name,
+                    track
+                );
+                connection.node = format!("{}{}", TRACK_PREFIX, track);
+                connection.output = "value".to_string();
+            }
+        }
+    }
+
+    for (name, track) in &config.keyframes {
+        track
+            .validate()
+            .context(format!("Error in keyframe track `{}`", name))?;
+
+        let node_name = format!("{}{}", TRACK_PREFIX, name);
+        ensure!(
+            !config.nodes.contains_key(&node_name),
+            "There's already a node named `{}`",
+            node_name
+        );
+        config.nodes.insert(
+            node_name,
+            Node {
+                priority: None,
+                config: NodeConfig::Keyframes(track.clone()),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, Easing, Keyframe, TrackConfig};
+    use crate::config::{params::ParamValue, Config};
+
+    fn track(keys: &[(f32, f32, Easing)], repeat: bool) -> TrackConfig {
+        TrackConfig {
+            keys: keys
+                .iter()
+                .map(|&(time, value, ease)| Keyframe {
+                    time,
+                    value: ParamValue::Scalar(value),
+                    ease,
+                })
+                .collect(),
+            repeat,
+        }
+    }
+
+    fn scalar(value: ParamValue) -> f32 {
+        match value {
+            ParamValue::Scalar(value) => value,
+            ParamValue::Color(_) => panic!("Expected a number"),
+        }
+    }
+
+    #[test]
+    fn animates_between_keys() {
+        let keys = [
+            (1.0, 0.0, Easing::Linear),
+            (3.0, 2.0, Easing::Hold),
+            (4.0, 5.0, Easing::Linear),
+        ];
+        let once = track(&keys, false);
+        assert_eq!(scalar(once.value_at(0.0)), 0.0);
+        assert_eq!(scalar(once.value_at(2.0)), 1.0);
+        assert_eq!(scalar(once.value_at(3.5)), 2.0);
+        assert_eq!(scalar(once.value_at(10.0)), 5.0);
+
+        let looped = track(&keys, true);
+        assert_eq!(scalar(looped.value_at(4.0)), 0.0);
+        assert_eq!(scalar(looped.value_at(0.0)), 2.0);
+
+        assert!(track(
+            &[(1.0, 0.0, Easing::Linear), (0.0, 1.0, Easing::Linear)],
+            false
+        )
+        .validate()
+        .is_err());
+        assert!(track(&[], false).validate().is_err());
+    }
+
+    #[test]
+    fn eases_from_zero_to_one() {
+        for &ease in &[Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(ease.apply(0.0), 0.0);
+            assert_eq!(ease.apply(1.0), 1.0);
+        }
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn connects_tracks() {
+        let source = "
+keyframes:
+    radius:
+        keys:
+            - { time: 0.0, value: 1.0 }
+            - { time: 1.0, value: 4.0 }
+nodes:
+    blur:
+        type: blur
+        texture:
+            node: image
+            output: texture
+        radius:
+            track: radius
+";
+        let mut config: Config = serde_yaml::from_str(source).unwrap();
+        expand(&mut config).unwrap();
+
+        let connections = config.nodes["blur"].config.connections();
+        let connection = connections[1].1;
+        assert_eq!(connection.node, "keyframes/radius");
+        assert_eq!(connection.output, "value");
+        assert!(config.nodes.contains_key("keyframes/radius"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Mappings, which route a value like the audio node's band energies to a parameter of another
+//! node, scaled and smoothed on the way
+//!
+//! ```yaml
+//! mappings:
+//!     - from: audio.bass
+//!       to: shader.uniform.zoom
+//!       range: [1.0, 1.5]
+//!       smoothing: 0.1
+//!     - from: audio.beat
+//!       to: blur.radius
+//!       input: [0.0, 1.0]
+//!       range: [0.0, 8.0]
+//! ```
+//!
+//! `from` is a node and one of its float outputs. `to` is a node and one of its parameters, or
+//! `<node>.uniform.<name>` for a shader uniform, which is added if the shader doesn't have it yet.
+//! The value is moved from the `input` range to the `range` given, then smoothed. Each mapping
+//! becomes a `mapping` node named `mappings/<index>`, which the parameter is connected to.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use serde_derive::Deserialize;
+
+use super::{
+    nodes::{InputType, Node, NodeConfig, NodeConnection, NodeParameter},
+    Config,
+};
+
+/// Prefix of the names of the nodes mappings are read from
+pub const MAPPING_PREFIX: &str = "mappings/";
+
+/// A value routed from a node output to a parameter
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MappingConfig {
+    /// The output to read, as `<node>.<output>`
+    pub from: String,
+
+    /// The parameter to set, as `<node>.<parameter>` or `<node>.uniform.<name>`
+    pub to: String,
+
+    /// The values of the output that map to the ends of `range`
+    #[serde(default = "default_range")]
+    pub input: [f32; 2],
+
+    /// The values the parameter moves between
+    #[serde(default = "default_range")]
+    pub range: [f32; 2],
+
+    /// Keep the value inside `range`, even when the output goes outside `input`
+    #[serde(default = "default_clamp")]
+    pub clamp: bool,
+
+    /// How long the value takes to follow the output, as a time constant (seconds). 0 follows it
+    /// right away.
+    #[serde(default)]
+    pub smoothing: f32,
+}
+
+/// Mapping node type - added for each entry of the `mappings` section
+#[derive(Debug, Deserialize, Clone)]
+pub struct MappingNodeConfig {
+    /// The output the mapping reads
+    pub input: NodeConnection,
+
+    /// How the value is scaled and smoothed
+    pub mapping: MappingConfig,
+}
+
+impl MappingConfig {
+    /// Moves a value from the `input` range to `range`
+    pub fn scale(&self, value: f32) -> f32 {
+        let [low, high] = self.input;
+        let t = if high == low {
+            0.0
+        } else {
+            (value - low) / (high - low)
+        };
+        let t = if self.clamp { t.max(0.0).min(1.0) } else { t };
+        self.range[0] + (self.range[1] - self.range[0]) * t
+    }
+}
+
+/// Splits `<node>.<rest>` at the first dot
+fn split_target(target: &str) -> Result<(&str, &str), Error> {
+    let mut parts = target.splitn(2, '.');
+    match (parts.next(), parts.next()) {
+        (Some(node), Some(rest)) if !node.is_empty() && !rest.is_empty() => Ok((node, rest)),
+        _ => bail!("Expected `<node>.<name>`, got `{}`", target),
+    }
+}
+
+/// Points the parameter a mapping sets at the mapping's node
+fn connect_target(config: &mut Config, to: &str, node_name: &str) -> Result<(), Error> {
+    let (target, parameter) = split_target(to)?;
+    let node = config
+        .nodes
+        .get_mut(target)
+        .ok_or_else(|| format_err!("There's no node named `{}`", target))?;
+    let connection = |name: &str| NodeConnection {
+        node: node_name.to_string(),
+        output: "value".to_string(),
+        name: name.to_string(),
+        type_: InputType::Float,
+        param: None,
+        track: None,
+    };
+
+    if parameter.starts_with("uniform.") {
+        let uniform = &parameter["uniform.".len()..];
+        let shader_config = match node.config {
+            NodeConfig::Shader(ref mut shader_config) => shader_config,
+            _ => bail!("Node `{}` isn't a shader, so it has no uniforms", target),
+        };
+        match shader_config
+            .uniforms
+            .iter_mut()
+            .find(|existing| existing.name == uniform)
+        {
+            // The uniform keeps its declared type
+            Some(existing) => {
+                existing.node = node_name.to_string();
+                existing.output = "value".to_string();
+            }
+            None => shader_config.uniforms.push(connection(uniform)),
+        }
+        return Ok(());
+    }
+
+    match node
+        .config
+        .float_parameters_mut()
+        .into_iter()
+        .find(|&(name, _)| name == parameter)
+    {
+        Some((_, value)) => *value = NodeParameter::NodeConnection(connection(parameter)),
+        None => bail!(
+            "Node `{}` has no parameter named `{}` that can be mapped",
+            target,
+            parameter
+        ),
+    }
+    Ok(())
+}
+
+/// Adds the node for a mapping, and connects the parameter it sets to it
+fn add_mapping(config: &mut Config, node_name: &str, mapping: &MappingConfig) -> Result<(), Error> {
+    let (node, output) = split_target(&mapping.from)?;
+    ensure!(
+        config.nodes.contains_key(node),
+        "There's no node named `{}`",
+        node
+    );
+    ensure!(mapping.smoothing >= 0.0, "Smoothing can't be negative");
+    ensure!(
+        !config.nodes.contains_key(node_name),
+        "There's already a node named `{}`",
+        node_name
+    );
+    connect_target(config, &mapping.to, node_name)?;
+
+    let input = NodeConnection {
+        node: node.to_string(),
+        output: output.to_string(),
+        name: String::new(),
+        type_: InputType::Float,
+        param: None,
+        track: None,
+    };
+    config.nodes.insert(
+        node_name.to_string(),
+        Node {
+            priority: None,
+            config: NodeConfig::Mapping(MappingNodeConfig {
+                input,
+                mapping: mapping.clone(),
+            }),
+        },
+    );
+    Ok(())
+}
+
+/// Adds a mapping node for each mapping, and connects the parameters they set to them
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    for (index, mapping) in config.mappings.clone().iter().enumerate() {
+        let node_name = format!("{}{}", MAPPING_PREFIX, index);
+        add_mapping(config, &node_name, mapping).context(format!(
+            "Error in mapping from `{}` to `{}`",
+            mapping.from, mapping.to
+        ))?;
+    }
+    Ok(())
+}
+
+/// A function that returns the default value of the `input` and `range` fields
+fn default_range() -> [f32; 2] {
+    [0.0, 1.0]
+}
+
+/// A function that returns the default value of the `clamp` field
+fn default_clamp() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use crate::config::{
+        nodes::{NodeConfig, NodeParameter},
+        Config,
+    };
+
+    #[test]
+    fn scales_values() {
+        let source = "
+mappings:
+    - { from: audio.bass, to: blur.radius, input: [0.5, 1.0], range: [2.0, 4.0] }
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let mapping = &config.mappings[0];
+        assert_eq!(mapping.scale(0.75), 3.0);
+        assert_eq!(mapping.scale(0.0), 2.0);
+        assert_eq!(mapping.scale(2.0), 4.0);
+    }
+
+    #[test]
+    fn connects_parameters() {
+        let source = "
+mappings:
+    - { from: audio.bass, to: shader.uniform.zoom, range: [1.0, 1.5] }
+    - { from: audio.beat, to: blur.radius }
+nodes:
+    audio:
+        type: audio
+    shader:
+        type: shader
+        fragment_source: 'void main() {}'
+    blur:
+        type: blur
+        texture:
+            node: shader
+            output: texture
+";
+        let mut config: Config = serde_yaml::from_str(source).unwrap();
+        expand(&mut config).unwrap();
+
+        match config.nodes["shader"].config {
+            NodeConfig::Shader(ref shader_config) => {
+                let uniform = &shader_config.uniforms[0];
+                assert_eq!(uniform.name, "zoom");
+                assert_eq!(uniform.node, "mappings/0");
+            }
+            _ => unreachable!(),
+        }
+        match config.nodes["blur"].config {
+            NodeConfig::Blur(ref blur_config) => match blur_config.radius {
+                NodeParameter::NodeConnection(ref connection) => {
+                    assert_eq!(connection.node, "mappings/1")
+                }
+                NodeParameter::Static(_) => panic!("The radius wasn't connected"),
+            },
+            _ => unreachable!(),
+        }
+        match config.nodes["mappings/1"].config {
+            NodeConfig::Mapping(ref mapping_config) => {
+                assert_eq!(mapping_config.input.node, "audio");
+                assert_eq!(mapping_config.input.output, "beat");
+            }
+            _ => unreachable!(),
+        }
+
+        let mut missing: Config = serde_yaml::from_str(source).unwrap();
+        missing.mappings[1].to = "blur.missing".to_string();
+        assert!(expand(&mut missing).is_err());
+    }
+}
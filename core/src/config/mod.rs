@@ -0,0 +1,1111 @@
+//! The `config` module provides definitions for all configuration structs as well as methods
+//! necessary for configuration via yaml, json, or toml and command line.
+
+pub mod accessibility;
+pub mod capture;
+pub mod diagnostics;
+pub mod group;
+pub mod keybindings;
+pub mod keyframes;
+pub mod mappings;
+pub mod nodes;
+pub mod params;
+pub mod recording;
+pub mod sandbox;
+pub mod stats;
+pub mod timeline;
+pub mod tweaks;
+pub mod validation;
+pub mod wallpaper;
+pub mod webcam;
+
+use anyhow::{bail, Context, Error};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use indexmap::IndexMap;
+use log::debug;
+use nfd::{self, Response};
+use serde_derive::Deserialize;
+use std::{
+    env, fmt,
+    fs::File,
+    io::{prelude::*, BufReader},
+    path::{Path, PathBuf},
+};
+
+use self::{
+    accessibility::AccessibilityConfig,
+    capture::CaptureConfig,
+    diagnostics::DiagnosticsConfig,
+    group::GroupConfig,
+    keybindings::{Action, Keybindings},
+    keyframes::TrackConfig,
+    mappings::MappingConfig,
+    nodes::{Node, NodeConfig},
+    params::ParamValue,
+    recording::RecordingConfig,
+    stats::StatsConfig,
+    timeline::TimelineConfig,
+    tweaks::Tweak,
+    wallpaper::WallpaperConfig,
+    webcam::WebcamConfig,
+};
+use crate::platform::config::PlatformSpecificConfig;
+
+/// The file formats a configuration can be written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    /// YAML (`.yml` or `.yaml`)
+    Yaml,
+    /// JSON (`.json`)
+    Json,
+    /// TOML (`.toml`)
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Extensions of files that can be opened as configurations, for use in file dialogs
+    pub const EXTENSIONS: &'static str = "yml,yaml,json,toml";
+
+    /// Detects the format of a configuration file from its extension, defaulting to YAML
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .as_ref()
+            .map(String::as_str)
+        {
+            Some("json") => ConfigFormat::Json,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    /// Parses a configuration in this format
+    pub fn parse(self, source: &str) -> Result<Config, Error> {
+        Ok(match self {
+            ConfigFormat::Yaml => ::serde_yaml::from_str(source)?,
+            ConfigFormat::Json => ::serde_json::from_str(source)?,
+            ConfigFormat::Toml => ::toml::from_str(source)?,
+        })
+    }
+}
+
+/// How frames are presented to the screen
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    /// Wait for vertical blank, queueing frames (vsync)
+    Fifo,
+    /// Wait for vertical blank, replacing the queued frame with the newest one
+    Mailbox,
+    /// Present immediately, which may tear
+    Immediate,
+    /// Wait for vertical blank unless the frame is late, in which case present immediately
+    Adaptive,
+}
+
+impl PresentMode {
+    /// Every present mode, in the order they can be cycled through
+    pub const ALL: &'static [PresentMode] = &[
+        PresentMode::Fifo,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+        PresentMode::Adaptive,
+    ];
+
+    /// Returns the mode we'll actually get when asking for this one. OpenGL only lets us turn
+    /// vsync on or off, so mailbox and adaptive fall back to FIFO, which also doesn't tear.
+    pub fn supported(self) -> Self {
+        match self {
+            PresentMode::Fifo | PresentMode::Mailbox | PresentMode::Adaptive => PresentMode::Fifo,
+            PresentMode::Immediate => PresentMode::Immediate,
+        }
+    }
+
+    /// Returns whether this mode waits for vertical blank
+    pub fn vsync(self) -> bool {
+        self != PresentMode::Immediate
+    }
+
+    /// Returns the next supported mode after this one
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap();
+        Self::ALL
+            .iter()
+            .cycle()
+            .skip(index + 1)
+            .map(|mode| mode.supported())
+            .find(|&mode| mode != self.supported())
+            .unwrap_or(self)
+    }
+}
+
+impl fmt::Display for PresentMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            PresentMode::Fifo => "fifo",
+            PresentMode::Mailbox => "mailbox",
+            PresentMode::Immediate => "immediate",
+            PresentMode::Adaptive => "adaptive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The main configuration contains all the information necessary to build a renderer
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The current working directory
+    /// Not meant to actually be specified in the config file, but can be
+    #[serde(default)]
+    pub _cwd: PathBuf,
+
+    /// Whether `--trust` was given, lifting the limits on what the config can do
+    #[serde(skip)]
+    pub trusted: bool,
+
+    /// The values shown in the tweak panel, which are collected when the config is loaded
+    #[serde(skip)]
+    pub tweaks: Vec<Tweak>,
+
+    /// The node configurations, keyed by name, in the order they appear in the config file
+    #[serde(default)]
+    pub nodes: IndexMap<String, Node>,
+
+    /// Named values that connections can read with `param: <name>`, which can be overridden
+    /// from the command line and the environment
+    #[serde(default)]
+    pub params: IndexMap<String, ParamValue>,
+
+    /// Animation tracks that connections can read with `track: <name>`
+    #[serde(default)]
+    pub keyframes: IndexMap<String, TrackConfig>,
+
+    /// Node outputs routed to parameters of other nodes, like the audio node's band energies to
+    /// a shader uniform
+    #[serde(default)]
+    pub mappings: Vec<MappingConfig>,
+
+    /// Graphs reused from other config files, keyed by the names their nodes are merged under
+    #[serde(default)]
+    pub groups: IndexMap<String, GroupConfig>,
+
+    /// Initial width of the window
+    #[serde(default = "default_width")]
+    pub width: u32,
+
+    /// Initial height of the window
+    #[serde(default = "default_height")]
+    pub height: u32,
+
+    /// Initial position of the window
+    #[serde(default)]
+    pub position: Option<[i32; 2]>,
+
+    /// Whether or not the window has decorations (title bar and borders)
+    #[serde(default = "default_decorations")]
+    pub decorations: bool,
+
+    /// Whether or not to maximize the window
+    #[serde(default = "default_maximize")]
+    pub maximize: bool,
+
+    /// Whether or not to make the window fullscreen
+    #[serde(default = "default_fullscreen")]
+    pub fullscreen: bool,
+
+    /// The monitor to make the window fullscreen on, by name or index. Defaults to the primary
+    /// monitor
+    #[serde(default)]
+    pub monitor: Option<String>,
+
+    /// Whether or not to use a borderless window covering every monitor
+    #[serde(default = "default_span")]
+    pub span: bool,
+
+    /// Whether or not the window is see-through where the output's alpha is below 1, for overlays.
+    /// This needs a compositor, and the output should have premultiplied alpha
+    #[serde(default = "default_transparent")]
+    pub transparent: bool,
+
+    /// Whether or not to keep the window above other windows
+    #[serde(default = "default_always_on_top")]
+    pub always_on_top: bool,
+
+    /// Whether or not clicks and other input go through the window to whatever is below it
+    #[serde(default = "default_click_through")]
+    pub click_through: bool,
+
+    /// Window title template. `{config}` is replaced with the name of the config file, `{fps}`
+    /// with the current frames per second, and `{time}` with the seconds since startup
+    #[serde(default = "default_title")]
+    pub title: String,
+
+    /// Path to an image to use as the window icon
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
+
+    /// Whether or not the program should use vertical sync
+    #[serde(default = "default_vsync")]
+    pub vsync: bool,
+
+    /// How frames are presented (fifo, mailbox, immediate, or adaptive). Overrides `vsync` if
+    /// given
+    #[serde(default)]
+    pub present: Option<PresentMode>,
+
+    /// Most frames to render per second, paced by sleeping between frames. Works with or without
+    /// vsync, and keeps headless or uncapped runs from using the whole GPU.
+    #[serde(default)]
+    pub fps_limit: Option<f32>,
+
+    /// Number of samples per pixel for multisample antialiasing on the window's framebuffer (a
+    /// power of two, or 0 for none). This only smooths what's drawn straight to the window, like
+    /// the HUD; shader nodes render to textures and work out each pixel's color in the fragment
+    /// shader, so raymarched edges need `supersample` instead.
+    #[serde(default = "default_multisampling")]
+    pub multisampling: u16,
+
+    /// Renders the node graph at this multiple of the window's resolution, and has the output node
+    /// average each block of pixels down to one. The factor applies to the window's size, so after
+    /// a resize every node renders again at the new size times the factor, and the pointer and
+    /// `resolution` outputs of info nodes are scaled to match. Memory use grows with the square of
+    /// the factor.
+    #[serde(default = "default_supersample")]
+    pub supersample: u32,
+
+    /// Texture memory the graph may use before the textures of image nodes that aren't shown are
+    /// dropped, least recently shown first (megabytes). They're read from file again when they're
+    /// shown. Sizes are estimates, so leave some headroom below the GPU's memory.
+    #[serde(default)]
+    pub texture_budget: Option<u64>,
+
+    /// Most time a node may take on the GPU in one frame before it's disabled, with an error shown
+    /// over the frame, so a runaway shader can't take the whole system down with it
+    /// (milliseconds). Disabled nodes keep their last output until the graph is reloaded.
+    /// Turning it on keeps GPU profiling running. Untrusted configs can't set it over a second.
+    #[serde(default)]
+    pub gpu_watchdog: Option<f32>,
+
+    /// Number of frames to render with the window hidden before showing it, so shaders are
+    /// compiled and textures are uploaded before the first frame is presented
+    #[serde(default = "default_warmup_frames")]
+    pub warmup_frames: u32,
+
+    /// Specifies which renderer to use (current options: opengl)
+    #[serde(default = "default_renderer")]
+    pub renderer: String,
+
+    /// Use a headless renderer
+    #[serde(default = "default_headless")]
+    pub headless: bool,
+
+    /// Reload automatically when file changes are detected
+    #[serde(default = "default_autoreload")]
+    pub autoreload: bool,
+
+    /// Show the performance HUD on startup
+    #[serde(default = "default_hud")]
+    pub hud: bool,
+
+    /// Check for a newer release on startup, and show a notice if there is one
+    #[serde(default = "default_check_updates")]
+    pub check_updates: bool,
+
+    /// Keep compiled shader programs in the user's cache directory, so they don't have to be
+    /// compiled again the next time they're loaded. Some drivers have trouble with cached
+    /// programs, which this can be turned off for.
+    #[serde(default = "default_shader_cache")]
+    pub shader_cache: bool,
+
+    /// Scenes to show one after another, which the `timeline` node shows
+    #[serde(default)]
+    pub timeline: Option<TimelineConfig>,
+
+    /// Keys mapped to what they do, like `Ctrl+R: reload` or `1: {switch-scene: 0}`, on top of
+    /// the default bindings
+    #[serde(default)]
+    pub keybindings: IndexMap<String, Action>,
+
+    /// Options for high-contrast text and reduced motion
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+
+    /// Options for capturing images of the output
+    #[serde(default)]
+    pub capture: CaptureConfig,
+
+    /// Options for recording animations of the output
+    #[serde(default)]
+    pub recording: RecordingConfig,
+
+    /// Options for saving diagnostics when errors occur
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+
+    /// Options for logging frame statistics
+    #[serde(default)]
+    pub stats: StatsConfig,
+
+    /// Options for drawing wallpapers on every monitor
+    #[serde(default)]
+    pub wallpaper: WallpaperConfig,
+
+    /// Listens for commands on a unix domain socket at this path, if set. See the `control`
+    /// module for the commands.
+    #[serde(default)]
+    pub control_socket: Option<PathBuf>,
+
+    /// Writes frames to a virtual webcam, if set
+    #[serde(default)]
+    pub webcam: Option<WebcamConfig>,
+
+    /// Dynamic libraries providing node types for `plugin` nodes. Plugins run native code, so
+    /// they're only loaded for trusted configs.
+    #[serde(default)]
+    pub plugins: Vec<PathBuf>,
+
+    /// Extra platform-specific configurations
+    #[serde(default)]
+    pub platform_config: PlatformSpecificConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            _cwd: Default::default(),
+            trusted: false,
+            tweaks: Default::default(),
+            nodes: Default::default(),
+            params: Default::default(),
+            keyframes: Default::default(),
+            mappings: Default::default(),
+            groups: Default::default(),
+            width: default_width(),
+            height: default_height(),
+            position: Default::default(),
+            decorations: default_decorations(),
+            maximize: default_maximize(),
+            fullscreen: default_fullscreen(),
+            monitor: Default::default(),
+            span: default_span(),
+            transparent: default_transparent(),
+            always_on_top: default_always_on_top(),
+            click_through: default_click_through(),
+            title: default_title(),
+            icon: Default::default(),
+            vsync: default_vsync(),
+            present: Default::default(),
+            fps_limit: Default::default(),
+            multisampling: default_multisampling(),
+            supersample: default_supersample(),
+            texture_budget: Default::default(),
+            gpu_watchdog: Default::default(),
+            warmup_frames: default_warmup_frames(),
+            renderer: default_renderer(),
+            headless: default_headless(),
+            autoreload: default_autoreload(),
+            hud: default_hud(),
+            check_updates: default_check_updates(),
+            shader_cache: default_shader_cache(),
+            timeline: Default::default(),
+            keybindings: Default::default(),
+            accessibility: Default::default(),
+            capture: Default::default(),
+            recording: Default::default(),
+            diagnostics: Default::default(),
+            stats: Default::default(),
+            wallpaper: Default::default(),
+            control_socket: Default::default(),
+            webcam: Default::default(),
+            plugins: Default::default(),
+            platform_config: Default::default(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `width` field
+fn default_width() -> u32 {
+    640
+}
+
+/// A function that returns the default value of the `width` field
+fn default_height() -> u32 {
+    400
+}
+
+/// A function that returns the default value of the `decorations` field
+fn default_decorations() -> bool {
+    true
+}
+
+/// A function that returns the default value of the `maximize` field
+fn default_maximize() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `fullscreen` field
+fn default_fullscreen() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `span` field
+fn default_span() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `transparent` field
+fn default_transparent() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `always_on_top` field
+fn default_always_on_top() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `click_through` field
+fn default_click_through() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `title` field
+fn default_title() -> String {
+    "yotredash".to_string()
+}
+
+/// A function that returns the default value of the `vsync` field
+fn default_vsync() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `multisampling` field
+fn default_multisampling() -> u16 {
+    0
+}
+
+/// A function that returns the default value of the `supersample` field
+fn default_supersample() -> u32 {
+    1
+}
+
+/// A function that returns the default value of the `warmup_frames` field
+fn default_warmup_frames() -> u32 {
+    0
+}
+
+/// A function that returns the default value of the `renderer` field
+fn default_renderer() -> String {
+    return "opengl".to_string();
+}
+
+/// A function that returns the default value of the `headless` field
+fn default_headless() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `autoreload` field
+fn default_autoreload() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `hud` field
+fn default_hud() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `check_updates` field
+fn default_check_updates() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `shader_cache` field
+fn default_shader_cache() -> bool {
+    true
+}
+
+impl Config {
+    /// Builds the application description needed to parse command-line arguments
+    pub fn build_cli() -> App<'static, 'static> {
+        App::new("yotredash")
+            .version(env!("CARGO_PKG_VERSION"))
+            // We handle `--version` ourselves so it can be combined with `--verbose`
+            .setting(AppSettings::DisableVersion)
+            .author("Ash Levy <ashlea@protonmail.com>")
+            .args(&[
+                Arg::with_name("width")
+                    .short("w")
+                    .long("width")
+                    .help("Set window width")
+                    .takes_value(true),
+                Arg::with_name("height")
+                    .short("h")
+                    .long("height")
+                    .help("Set window height")
+                    .takes_value(true),
+                Arg::with_name("maximize")
+                    .long("maximize")
+                    .help("Maximize window dimensions"),
+                Arg::with_name("fullscreen")
+                    .long("fullscreen")
+                    .help("Make window fullscreen"),
+                Arg::with_name("monitor")
+                    .long("monitor")
+                    .help("Go fullscreen on this monitor (name or index)")
+                    .takes_value(true),
+                Arg::with_name("transparent")
+                    .long("transparent")
+                    .help("Make the window see-through where the output is transparent"),
+                Arg::with_name("always_on_top")
+                    .long("always-on-top")
+                    .help("Keep the window above other windows"),
+                Arg::with_name("click_through")
+                    .long("click-through")
+                    .help("Let clicks go through the window to whatever is below it"),
+                Arg::with_name("title")
+                    .long("title")
+                    .help("Set window title ({config}, {fps} and {time} are substituted)")
+                    .takes_value(true),
+                Arg::with_name("vsync")
+                    .long("vsync")
+                    .help("Enable vertical sync"),
+                Arg::with_name("present")
+                    .long("present")
+                    .help("Set how frames are presented (cycle with F8, toggle vsync with Shift+F8)")
+                    .possible_values(&["fifo", "mailbox", "immediate", "adaptive"])
+                    .takes_value(true),
+                Arg::with_name("fps_limit")
+                    .long("fps-limit")
+                    .help("Render at most this many frames per second")
+                    .takes_value(true),
+                Arg::with_name("warmup_frames")
+                    .long("warmup-frames")
+                    .help("Render this many frames before showing the window, to avoid stutter at the start")
+                    .takes_value(true),
+                Arg::with_name("renderer")
+                    .long("renderer")
+                    .help("Specify renderer to use")
+                    .takes_value(true),
+                Arg::with_name("headless")
+                    .long("headless")
+                    .help("Render without a window, using OSMesa if there's no display server"),
+                Arg::with_name("autoreload")
+                    .long("autoreload")
+                    .help("Automatically reload when changes to the shaders are detected"),
+                Arg::with_name("hud")
+                    .long("hud")
+                    .help("Show the performance HUD (toggle with F1)"),
+                Arg::with_name("high_contrast")
+                    .long("high-contrast")
+                    .help("Draw errors, the HUD and notices in high-contrast colors with larger text"),
+                Arg::with_name("reduced_motion")
+                    .long("reduced-motion")
+                    .help("Ask for reduced motion, for configs that support it"),
+                Arg::with_name("wallpaper")
+                    .long("wallpaper")
+                    .help("Draw as a wallpaper on every monitor"),
+                Arg::with_name("span")
+                    .long("span")
+                    .help("Use one borderless window spanning every monitor, also in wallpaper mode"),
+                Arg::with_name("diagnostics")
+                    .long("diagnostics")
+                    .help("Save diagnostics to this directory when errors occur")
+                    .takes_value(true),
+                Arg::with_name("log_stats")
+                    .long("log-stats")
+                    .help("Log frame statistics every second"),
+                Arg::with_name("stats_file")
+                    .long("stats-file")
+                    .help("Append frame statistics to this file as JSON lines")
+                    .takes_value(true),
+                Arg::with_name("param")
+                    .long("param")
+                    .help("Override a parameter from the config's `params` section, e.g. `speed=2.0`")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1),
+                Arg::with_name("control_socket")
+                    .long("control-socket")
+                    .help("Listen for commands like `pause` or `capture <path>` on a unix socket, at this path or where `yotredash ctl` looks by default")
+                    .takes_value(true)
+                    .min_values(0),
+                Arg::with_name("thumbnailer")
+                    .long("thumbnailer")
+                    .help("Render a thumbnail of a config to a PNG file and exit")
+                    .value_names(&["input", "output"])
+                    .number_of_values(2),
+                Arg::with_name("size")
+                    .long("size")
+                    .help("With --thumbnailer, the largest width or height of the thumbnail")
+                    .default_value("256"),
+                Arg::with_name("screenshot")
+                    .long("screenshot")
+                    .help("Render a single frame headless to an image file (PNG, JPEG or EXR) and exit")
+                    .value_name("output")
+                    .takes_value(true),
+                Arg::with_name("at_time")
+                    .long("at-time")
+                    .help("With --screenshot, the demo time to render the frame at (seconds)")
+                    .default_value("0"),
+                Arg::with_name("benchmark")
+                    .long("benchmark")
+                    .help("Render for this many frames, or seconds with an `s` suffix, without vsync and report frame timings")
+                    .value_name("length")
+                    .takes_value(true),
+                Arg::with_name("report")
+                    .long("report")
+                    .help("With --benchmark, write the timings to this file as JSON, or CSV with a .csv extension")
+                    .value_name("file")
+                    .takes_value(true),
+                Arg::with_name("trust")
+                    .long("trust")
+                    .help("Trust the config, lifting the limits on size, file writing and GPU time"),
+                Arg::with_name("version")
+                    .short("V")
+                    .long("version")
+                    .help("Print version information"),
+                Arg::with_name("verbose")
+                    .long("verbose")
+                    .help("With --version, also print build and OpenGL information"),
+                Arg::with_name("config")
+                    .short("c")
+                    .long("config")
+                    .help("Load a config file")
+                    .takes_value(true),
+                Arg::with_name("no_dialog")
+                    .long("no-dialog")
+                    .help("Exit with an error instead of showing a file dialog if no config is found"),
+            ])
+            .subcommand(
+                SubCommand::with_name("ctl")
+                    .about("Send a command to a running instance started with `--control-socket`")
+                    .setting(AppSettings::SubcommandRequiredElseHelp)
+                    .arg(
+                        Arg::with_name("socket")
+                            .long("socket")
+                            .help("Path of the control socket, if it isn't the default")
+                            .takes_value(true),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("status").about("Show the state of each window"),
+                    )
+                    .subcommand(SubCommand::with_name("pause").about("Pause rendering"))
+                    .subcommand(SubCommand::with_name("resume").about("Resume rendering"))
+                    .subcommand(SubCommand::with_name("reload").about("Reload the config"))
+                    .subcommand(
+                        SubCommand::with_name("capture")
+                            .about("Capture an image to a file")
+                            .arg(
+                                Arg::with_name("path")
+                                    .help("File to save the image to")
+                                    .required(true),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("set")
+                            .about("Set the value of a constant node")
+                            .arg(
+                                Arg::with_name("name")
+                                    .help("Name of the constant node")
+                                    .required(true),
+                            )
+                            .arg(
+                                Arg::with_name("values")
+                                    .help("The numbers making up the value")
+                                    .required(true)
+                                    .multiple(true)
+                                    .allow_hyphen_values(true),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("scene")
+                            .about("Jump to a scene of the timeline")
+                            .arg(
+                                Arg::with_name("scene")
+                                    .help("Index of the scene, the node showing it, or its config's name")
+                                    .required(true),
+                            ),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("audio-devices")
+                    .about("List the input devices the audio node can use")
+                    .arg(
+                        Arg::with_name("backend")
+                            .long("backend")
+                            .help("The audio backend to list the devices of")
+                            .possible_values(&["portaudio", "cpal"])
+                            .default_value("portaudio"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("graph")
+                    .about("Print the node graph of a config without opening a window")
+                    .arg(
+                        Arg::with_name("config")
+                            .long("config")
+                            .help("Config to print the graph of, if it isn't the default")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("format")
+                            .long("format")
+                            .help("Print Graphviz dot or JSON")
+                            .possible_values(&["dot", "json"])
+                            .default_value("dot"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("test")
+                    .about("Render a config headless at fixed times and compare the frames against reference images")
+                    .arg(
+                        Arg::with_name("config")
+                            .help("Config to test")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("times")
+                            .long("times")
+                            .help("Demo times to render frames at (seconds)")
+                            .multiple(true)
+                            .use_delimiter(true)
+                            .default_value("0"),
+                    )
+                    .arg(
+                        Arg::with_name("references")
+                            .long("references")
+                            .help("Directory of reference images, if it isn't `references` next to the config")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("threshold")
+                            .long("threshold")
+                            .help("Perceptual difference, from 0 to 1, above which a pixel counts as different")
+                            .default_value("0.1"),
+                    )
+                    .arg(
+                        Arg::with_name("tolerance")
+                            .long("tolerance")
+                            .help("Fraction of pixels that may be different before a frame fails")
+                            .default_value("0.001"),
+                    )
+                    .arg(
+                        Arg::with_name("update")
+                            .long("update")
+                            .help("Write the rendered frames as the new references"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("new")
+                    .about("Create a new project with a starter config and shaders")
+                    .arg(
+                        Arg::with_name("dir")
+                            .help("Directory to create the project in")
+                            .required(true),
+                    ),
+            )
+            .after_help(
+                "\
+                 This program uses `env_logger` as its logging backend.\n\
+                 Common usage: `RUST_LOG=yotredash=info yotredash`\n\
+                 See http://rust-lang-nursery.github.io/log/env_logger/ for more information.\
+                 ",
+            )
+    }
+
+    /// Parses the configuration from command-line arguments
+    fn merge_args(&mut self, args: &ArgMatches<'_>) -> Result<(), Error> {
+        self.platform_config.merge_args(args);
+        self.trusted = args.is_present("trust");
+
+        if let Some(value) = args.value_of("width") {
+            self.width = value.parse::<u32>()?;
+        }
+
+        if let Some(value) = args.value_of("height") {
+            self.height = value.parse::<u32>()?;
+        }
+
+        if args.is_present("maximize") {
+            self.maximize = true;
+        }
+
+        if args.is_present("fullscreen") {
+            self.fullscreen = true;
+        }
+
+        if let Some(value) = args.value_of("monitor") {
+            self.monitor = Some(value.to_string());
+        }
+
+        if args.is_present("transparent") {
+            self.transparent = true;
+        }
+
+        if args.is_present("always_on_top") {
+            self.always_on_top = true;
+        }
+
+        if args.is_present("click_through") {
+            self.click_through = true;
+        }
+
+        if let Some(value) = args.value_of("title") {
+            self.title = value.to_string();
+        }
+
+        if args.is_present("vsync") {
+            self.vsync = true;
+        }
+
+        if let Some(value) = args.value_of("present") {
+            self.present = Some(::serde_yaml::from_str(value)?);
+        }
+
+        if let Some(value) = args.value_of("fps_limit") {
+            self.fps_limit = Some(value.parse::<f32>()?);
+        }
+
+        if let Some(value) = args.value_of("warmup_frames") {
+            self.warmup_frames = value.parse::<u32>()?;
+        }
+
+        if let Some(value) = args.value_of("renderer") {
+            self.renderer = value.to_string();
+        }
+
+        if args.is_present("headless") {
+            self.headless = true;
+        }
+
+        if args.is_present("autoreload") {
+            self.autoreload = true;
+        }
+
+        if args.is_present("hud") {
+            self.hud = true;
+        }
+
+        if args.is_present("high_contrast") {
+            self.accessibility.high_contrast = true;
+        }
+
+        if args.is_present("reduced_motion") {
+            self.accessibility.reduced_motion = true;
+        }
+
+        if args.is_present("wallpaper") {
+            self.wallpaper.enabled = true;
+        }
+
+        if args.is_present("span") {
+            self.span = true;
+            self.wallpaper.span = true;
+        }
+
+        if let Some(value) = args.value_of_os("diagnostics") {
+            self.diagnostics.directory = Some(PathBuf::from(value));
+        }
+
+        if args.is_present("log_stats") {
+            self.stats.log = true;
+        }
+
+        if let Some(value) = args.value_of_os("stats_file") {
+            self.stats.file = Some(PathBuf::from(value));
+        }
+
+        for assignment in args.values_of("param").into_iter().flatten() {
+            params::set(self, assignment)?;
+        }
+
+        if args.is_present("control_socket") {
+            self.control_socket = Some(
+                args.value_of_os("control_socket")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(crate::control::default_path),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Parses and checks the configuration from a file, without the command line, sandbox or
+    /// file dialog that `parse` uses
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let (config, config_str) = Self::load(path)?;
+        config.check(&config_str)?;
+
+        Ok(config)
+    }
+
+    /// Parses and checks a configuration without reading any command line arguments or building
+    /// any nodes, reporting every problem with the node graph at once. Files the config refers to
+    /// are found relative to the working directory. Malformed configs are always returned as
+    /// errors, never panics, so this can be driven by a fuzzer.
+    pub fn validate_str(source: &str, format: ConfigFormat) -> Result<Self, Error> {
+        let config = Self::from_source(source, format, Path::new(""))?;
+        config.check(source)?;
+
+        Ok(config)
+    }
+
+    /// Checks the node graph, keybindings and capture settings of an expanded config
+    fn check(&self, source: &str) -> Result<(), Error> {
+        validation::validate(self, source)?;
+        Keybindings::new(&self.keybindings)?;
+        self.capture.validate()?;
+
+        Ok(())
+    }
+
+    /// Parses a config file and expands its groups, params and timeline, but doesn't validate
+    /// it, since the graphs of groups read from their inputs and needn't have an output node.
+    /// The source of the file is returned along with it.
+    fn load(path: &Path) -> Result<(Self, String), Error> {
+        debug!("Using config file: {}", path.display());
+        let file = File::open(path).context("Unable to open config file")?;
+        let mut reader = BufReader::new(file);
+        let mut config_str = String::new();
+        reader
+            .read_to_string(&mut config_str)
+            .context("Could not read config file")?;
+        let cwd = path.parent().unwrap_or_else(|| Path::new(""));
+        let config = Self::from_source(&config_str, ConfigFormat::from_path(path), cwd)?;
+
+        Ok((config, config_str))
+    }
+
+    /// Parses a config and expands its groups, keyframes, params, tweaks, mappings and timeline,
+    /// whose files are found relative to `cwd`
+    fn from_source(source: &str, format: ConfigFormat, cwd: &Path) -> Result<Self, Error> {
+        let mut config = format
+            .parse(source)
+            .context("Could not parse config file")?;
+        config._cwd = cwd.to_path_buf();
+
+        group::expand(&mut config)?;
+        keyframes::expand(&mut config)?;
+        params::expand(&mut config)?;
+        tweaks::expand(&mut config)?;
+        mappings::expand(&mut config)?;
+        timeline::expand(&mut config)?;
+
+        Ok(config)
+    }
+
+    /// Returns the configuration, appropriately noded from both command-line arguments and the
+    /// config file
+    pub fn parse(path: &Path) -> Result<Self, Error> {
+        let app = PlatformSpecificConfig::build_cli();
+        let args = app.get_matches();
+
+        let mut config = Self::from_file(path)?;
+        // Options given on the command line come from the user, so they aren't restricted
+        if !args.is_present("trust") {
+            sandbox::restrict(&mut config)?;
+        }
+        config.merge_args(&args)?;
+
+        Ok(config)
+    }
+
+    /// Gets a backup config without sourcing a file
+    pub fn backup() -> Result<Self, Error> {
+        let app = PlatformSpecificConfig::build_cli();
+        let args = app.get_matches();
+
+        let mut config = Self::default();
+        config.merge_args(&args)?;
+
+        Ok(config)
+    }
+
+    /// Returns the default config file path (`~/.config/yotredash/config.yml` on Linux)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("yotredash").join("config.yml"))
+    }
+
+    /// Returns the chosen config file path. In order, this checks the `--config` argument, the
+    /// `YOTREDASH_CONFIG` environment variable, and the default config path, then falls back to
+    /// asking the user with a file dialog unless `--no-dialog` was given.
+    pub fn get_path() -> Result<PathBuf, Error> {
+        let app = PlatformSpecificConfig::build_cli();
+        let args = app.get_matches();
+
+        let path = match args
+            .value_of_os("config")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("YOTREDASH_CONFIG").map(PathBuf::from))
+            .or_else(|| Self::default_path().filter(|path| path.exists()))
+        {
+            Some(path) => path,
+            None if args.is_present("no_dialog") => bail!(
+                "No config file specified. Use `--config`, set `YOTREDASH_CONFIG`, or create {}",
+                Self::default_path()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "a default config".to_string())
+            ),
+            None => {
+                let result = nfd::open_file_dialog(
+                    Some(ConfigFormat::EXTENSIONS),
+                    env::current_dir().unwrap_or_default().to_str(),
+                )?;
+                match result {
+                    Response::Okay(path) => Path::new(&path).to_path_buf(),
+                    Response::OkayMultiple(paths) => Path::new(&paths[0]).to_path_buf(),
+                    Response::Cancel => bail!("No file selected"),
+                }
+            }
+        };
+
+        Ok(path)
+    }
+
+    /// Returns the present mode asked for, which falls back to `vsync` if `present` isn't given
+    pub fn present_mode(&self) -> PresentMode {
+        match self.present {
+            Some(mode) => mode,
+            None if self.vsync => PresentMode::Fifo,
+            None => PresentMode::Immediate,
+        }
+    }
+
+    /// Provides a way to get the complete path to a file referenced in a configuration
+    pub fn path_to(&self, path: &Path) -> PathBuf {
+        self._cwd.join(path)
+    }
+
+    /// Returns the complete paths to all the files the nodes load, in the order they appear
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for node in self.nodes.values() {
+            match node.config {
+                NodeConfig::Image(ref image_config) => files.push(self.path_to(&image_config.path)),
+                NodeConfig::Lut(ref lut_config) => files.push(self.path_to(&lut_config.path)),
+                NodeConfig::Script(ref script_config) => {
+                    files.push(self.path_to(&script_config.path))
+                }
+                NodeConfig::Shader(ref shader_config) => {
+                    for path in shader_config.sources() {
+                        files.push(self.path_to(path));
+                    }
+                }
+                _ => (),
+            }
+        }
+        for group in self.groups.values() {
+            files.push(self.path_to(&group.config));
+        }
+        if let Some(ref timeline) = self.timeline {
+            for scene in &timeline.scenes {
+                if let Some(ref path) = scene.config {
+                    files.push(self.path_to(path));
+                }
+            }
+        }
+        files
+    }
+}
@@ -0,0 +1,1878 @@
+//! Configuration related to Nodes
+
+use indexmap::IndexMap;
+use serde_derive::Deserialize;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    default::Default,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    keyframes::TrackConfig, mappings::MappingNodeConfig, params::ParamValue,
+    timeline::TimelineConfig,
+};
+
+/// Input types for deserialization
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    /// Unspecified
+    #[serde(skip_deserializing)]
+    Any,
+    /// Color input
+    Color,
+    /// Float input
+    Float,
+    /// Float vec2 input
+    Float2,
+    /// Float vec4 input
+    Float4,
+    /// 4x4 matrix input
+    Mat4,
+    /// Float array input
+    FloatArray,
+    /// Text input
+    Text,
+    /// Texture2d input
+    Texture2d,
+    /// Texture1d input
+    Texture1d,
+}
+
+impl Default for InputType {
+    fn default() -> Self {
+        InputType::Any
+    }
+}
+
+impl fmt::Display for InputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            InputType::Any => "any",
+            InputType::Color => "color",
+            InputType::Float => "float",
+            InputType::Float2 => "float2",
+            InputType::Float4 => "float4",
+            InputType::Mat4 => "mat4",
+            InputType::FloatArray => "float_array",
+            InputType::Text => "text",
+            InputType::Texture2d => "texture2d",
+            InputType::Texture1d => "texture1d",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A connection to a `Node` and one of its outputs
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct NodeConnection {
+    /// The name of the `Node` to connect to
+    #[serde(default)]
+    pub node: String,
+    /// The output of the node
+    #[serde(default)]
+    pub output: String,
+    /// The name of the connection
+    #[serde(default)]
+    pub name: String,
+    /// The type of output
+    #[serde(rename = "type", default)]
+    pub type_: InputType,
+    /// A parameter from the `params` section to read instead of a node's output, which is
+    /// replaced with a connection to the parameter's node when the config is loaded
+    #[serde(default)]
+    pub param: Option<String>,
+    /// A track from the `keyframes` section to read instead of a node's output, which is replaced
+    /// with a connection to the track's node when the config is loaded
+    #[serde(default)]
+    pub track: Option<String>,
+}
+
+/// Represents a parameter to a node which can either be a static value
+/// or a pointer to the output of a different node.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum NodeParameter<T> {
+    /// A reference to another node's output
+    NodeConnection(NodeConnection),
+
+    /// A static input
+    Static(T),
+}
+
+impl<T: Default> Default for NodeParameter<T> {
+    fn default() -> NodeParameter<T> {
+        NodeParameter::Static(Default::default())
+    }
+}
+
+impl<T> NodeParameter<T>
+where
+    T: Default,
+{
+    /// Returns the inner value if `Static`, or `Default::default()` if a `NodeConnection`.
+    pub fn or_default(self) -> T {
+        match self {
+            NodeParameter::Static(v) => v,
+            NodeParameter::NodeConnection { .. } => Default::default(),
+        }
+    }
+}
+
+/// Output node type
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutputConfig {
+    /// Node to read from
+    pub texture: NodeConnection,
+}
+
+/// How the frames of an animated image are kept
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFrames {
+    /// Every frame is uploaded to the GPU when the image is loaded, which is fastest but uses the
+    /// most video memory
+    Upload,
+    /// Frames are decoded into memory when the image is loaded and uploaded as they're shown
+    Memory,
+    /// Frames are decoded from the file as they're shown, which uses the least memory but the
+    /// most CPU time
+    Stream,
+}
+
+impl Default for ImageFrames {
+    fn default() -> Self {
+        ImageFrames::Upload
+    }
+}
+
+/// Image node type
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImageConfig {
+    /// Relative path to the image
+    pub path: PathBuf,
+
+    /// How to keep the frames of an animated image
+    #[serde(default)]
+    pub frames: ImageFrames,
+
+    /// Number of frames kept on the GPU when they aren't all uploaded. Frames that are still on
+    /// the GPU when they come around again aren't uploaded again.
+    #[serde(default = "image_default_resident_frames")]
+    pub resident_frames: usize,
+}
+
+/// Shader node type
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ShaderConfig {
+    /// Relative path to the vertex shader. If it's left out, a built-in one draws the quad covering
+    /// the frame: it takes the `in vec2 position` attribute, which goes from -1 to 1 across the
+    /// frame, and passes it on as `gl_Position`. It has the same `#version` as the fragment shader,
+    /// and needs at least GLSL 1.30 or GLSL ES 3.00.
+    #[serde(default)]
+    pub vertex: Option<PathBuf>,
+
+    /// Relative path to the fragment shader
+    #[serde(default)]
+    pub fragment: Option<PathBuf>,
+
+    /// The fragment shader's source, written in the config instead of `fragment`, for small
+    /// shaders
+    #[serde(default)]
+    pub fragment_source: Option<String>,
+
+    /// Relative path to a fragment shader to use instead on OpenGL ES
+    #[serde(default)]
+    pub fragment_gles: Option<PathBuf>,
+
+    /// Relative path to a fragment shader to use instead if any of the `extensions` aren't
+    /// supported
+    #[serde(default)]
+    pub fragment_fallback: Option<PathBuf>,
+
+    /// Relative path to a geometry shader
+    #[serde(default)]
+    pub geometry: Option<PathBuf>,
+
+    /// Relative path to a tessellation control shader, which needs `tess_eval` too
+    #[serde(default)]
+    pub tess_control: Option<PathBuf>,
+
+    /// Relative path to a tessellation evaluation shader. With tessellation the quad is drawn as
+    /// patches of three vertices instead of triangles.
+    #[serde(default)]
+    pub tess_eval: Option<PathBuf>,
+
+    /// GLSL extensions the shaders need, e.g. `GL_OES_standard_derivatives`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+
+    /// Input nodes for the shader program
+    #[serde(default)]
+    pub uniforms: Vec<NodeConnection>,
+
+    /// Connect the uniforms the program uses that aren't in `uniforms` by their names: to an
+    /// output with the same name, like `time`, `resolution`, `pointer` or `spectrum`, or else to
+    /// the output of a node with the same name
+    #[serde(default)]
+    pub auto_bind: bool,
+
+    /// Names of the fragment shader's color outputs, each rendered to its own texture and exposed
+    /// as a node output with the same name. If empty, the node has a single `texture` output.
+    #[serde(default)]
+    pub outputs: Vec<String>,
+
+    /// Preprocessor definitions added to every shader stage, after the `#version` directive
+    #[serde(default)]
+    pub defines: IndexMap<String, DefineValue>,
+}
+
+impl ShaderConfig {
+    /// Returns the paths of every shader source, including the variants
+    pub fn sources(&self) -> Vec<&PathBuf> {
+        let mut sources: Vec<&PathBuf> = self.vertex.iter().collect();
+        sources.extend(self.fragment.iter());
+        sources.extend(self.fragment_gles.iter());
+        sources.extend(self.fragment_fallback.iter());
+        sources.extend(self.geometry.iter());
+        sources.extend(self.tess_control.iter());
+        sources.extend(self.tess_eval.iter());
+        sources
+    }
+
+    /// Returns the names of the node's texture outputs
+    pub fn output_names(&self) -> Vec<String> {
+        if self.outputs.is_empty() {
+            vec!["texture".to_string()]
+        } else {
+            self.outputs.clone()
+        }
+    }
+}
+
+/// The value of a preprocessor definition
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DefineValue {
+    /// A boolean, defined as 1 or 0
+    Bool(bool),
+    /// An integer
+    Int(i64),
+    /// A float
+    Float(f64),
+    /// Anything else, defined as written
+    Text(String),
+}
+
+impl fmt::Display for DefineValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DefineValue::Bool(value) => write!(f, "{}", value as i32),
+            DefineValue::Int(value) => write!(f, "{}", value),
+            // Always include a decimal point so GLSL sees a float
+            DefineValue::Float(value) => write!(f, "{:?}", value),
+            DefineValue::Text(ref value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Blend node type - blends the output of multiple nodes
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BlendConfig {
+    /// Math operation
+    pub operation: BlendOp,
+
+    /// Input node names and alpha transparencies
+    pub textures: Vec<NodeConnection>,
+}
+
+/// Text node type - renders text
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TextConfig {
+    /// Text to render
+    pub text: NodeParameter<String>,
+
+    /// Position to render at
+    #[serde(default)]
+    pub position: NodeParameter<[f32; 2]>,
+
+    /// Color to render in
+    #[serde(default = "text_default_color")]
+    pub color: NodeParameter<[f32; 4]>,
+
+    /// Font name
+    #[serde(default)]
+    pub font_name: String,
+
+    /// Font size
+    #[serde(default = "text_default_font_size")]
+    pub font_size: f32,
+
+    /// How to align lines within the text box
+    #[serde(default)]
+    pub align: TextAlign,
+
+    /// Wrap lines longer than this (pixels)
+    #[serde(default)]
+    pub max_width: Option<f32>,
+
+    /// Distance between lines, relative to the line height of the font
+    #[serde(default = "text_default_line_spacing")]
+    pub line_spacing: f32,
+
+    /// Scroll the text through a viewport instead of drawing it in place
+    #[serde(default)]
+    pub scroll: Option<TextScroll>,
+}
+
+/// How a text node scrolls its text
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TextScroll {
+    /// Which way the text moves
+    #[serde(default)]
+    pub direction: ScrollDirection,
+
+    /// How fast the text moves (pixels per second)
+    #[serde(default = "scroll_default_speed")]
+    pub speed: f32,
+
+    /// What happens when the text reaches the end of the viewport
+    #[serde(default)]
+    pub mode: ScrollMode,
+
+    /// The area the text scrolls through and is clipped to, as `[x, y, width, height]` with the
+    /// same origin as `position` (pixels). Defaults to the whole texture. Across the direction it
+    /// scrolls in, the text stays where `position` puts it.
+    #[serde(default)]
+    pub viewport: Option<[f32; 4]>,
+}
+
+/// Which way scrolling text moves
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollDirection {
+    /// Towards the left edge, like a news ticker
+    Left,
+    /// Towards the right edge
+    Right,
+    /// Towards the top edge, like credits
+    Up,
+    /// Towards the bottom edge
+    Down,
+}
+
+impl Default for ScrollDirection {
+    fn default() -> Self {
+        ScrollDirection::Left
+    }
+}
+
+/// What scrolling text does when it reaches the end of the viewport
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollMode {
+    /// Leave through one edge and come back in through the other
+    Loop,
+    /// Turn around whenever an end of the text meets an edge
+    Bounce,
+}
+
+impl Default for ScrollMode {
+    fn default() -> Self {
+        ScrollMode::Loop
+    }
+}
+
+/// Text alignment
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    /// Align to the left edge
+    Left,
+    /// Center each line
+    Center,
+    /// Align to the right edge
+    Right,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign::Left
+    }
+}
+
+/// FPS counter node type - renders text. Besides the text, it outputs `fps`, the average
+/// `frame_time` and the `p50` and `p99` frame times over the window (milliseconds), and `low_1`,
+/// the frame rate of the slowest 1% of frames in the window.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FpsConfig {
+    /// Position to render at
+    #[serde(default)]
+    pub position: NodeParameter<[f32; 2]>,
+
+    /// Color to render in
+    #[serde(default = "text_default_color")]
+    pub color: NodeParameter<[f32; 4]>,
+
+    /// Font name
+    #[serde(default)]
+    pub font_name: String,
+
+    /// Font size
+    #[serde(default = "text_default_font_size")]
+    pub font_size: f32,
+
+    /// Update interval (seconds)
+    #[serde(default = "fps_default_interval")]
+    pub interval: f32,
+
+    /// How far back the frame time stats look (seconds)
+    #[serde(default = "fps_default_window")]
+    pub window: f32,
+}
+
+/// The library the audio node gets its input from
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioBackend {
+    /// PortAudio, which needs its C library (and the `portaudio` feature, on by default)
+    Portaudio,
+    /// cpal, which is written in Rust and builds without extra libraries on most systems
+    Cpal,
+}
+
+impl Default for AudioBackend {
+    fn default() -> Self {
+        AudioBackend::Portaudio
+    }
+}
+
+/// Audio node type - analyzes audio from an input device
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Number of channels to open and analyze, e.g. 6 for 5.1 loopback
+    #[serde(default = "audio_default_channels")]
+    pub channels: u32,
+
+    /// The library to get the input from
+    #[serde(default)]
+    pub backend: AudioBackend,
+
+    /// Name of the input device, as listed by `yotredash audio-devices`, instead of the default
+    /// one
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+/// Constant node type - outputs a value, so several nodes can share it. The key gives the type
+/// of the value, e.g. `color: [1.0, 0.5, 0.0, 1.0]`, or `mat4: [[...], [...], [...], [...]]` for a
+/// matrix given as its four columns
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstantConfig {
+    /// A single float
+    Float(f32),
+    /// Two floats
+    Float2([f32; 2]),
+    /// Four floats
+    Float4([f32; 4]),
+    /// A color (RGBA)
+    Color([f32; 4]),
+    /// A 4x4 matrix, as a list of columns
+    Mat4([[f32; 4]; 4]),
+    /// Any number of floats, bound to a `float` array uniform in shaders
+    FloatArray(Vec<f32>),
+}
+
+/// How the camera node is controlled
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    /// Dragging circles the camera around the target and scrolling zooms, while WASD, Q and E
+    /// move the target
+    Orbit,
+    /// Dragging looks around and WASD, Q and E move the camera
+    Fly,
+}
+
+/// Camera node type - outputs view and projection matrices controlled with the mouse and keyboard,
+/// so shaders can share a camera
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CameraConfig {
+    /// How the camera is controlled
+    #[serde(default = "camera_default_mode")]
+    pub mode: CameraMode,
+
+    /// Where the camera starts
+    #[serde(default = "camera_default_position")]
+    pub position: [f32; 3],
+
+    /// The point the camera starts out looking at
+    #[serde(default)]
+    pub target: [f32; 3],
+
+    /// Vertical field of view (degrees)
+    #[serde(default = "camera_default_fov")]
+    pub fov: f32,
+
+    /// Distance to the near clipping plane
+    #[serde(default = "camera_default_near")]
+    pub near: f32,
+
+    /// Distance to the far clipping plane
+    #[serde(default = "camera_default_far")]
+    pub far: f32,
+
+    /// How fast the keys move the camera (units per second)
+    #[serde(default = "camera_default_speed")]
+    pub speed: f32,
+
+    /// How far dragging turns the camera (radians per pixel)
+    #[serde(default = "camera_default_sensitivity")]
+    pub sensitivity: f32,
+}
+
+/// How the values of a random node change over time
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RandomMode {
+    /// A new, unrelated value every step
+    White,
+    /// Perlin noise, moving smoothly from step to step
+    Smooth,
+}
+
+/// Random node type - outputs random values that depend only on the seed and the time, so
+/// stochastic effects come out the same on every run and in offline renders
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RandomConfig {
+    /// Seed for the values - nodes with different seeds give different values
+    #[serde(default)]
+    pub seed: u64,
+
+    /// How the values change over time
+    #[serde(default = "random_default_mode")]
+    pub mode: RandomMode,
+
+    /// Steps per second of demo time. With white noise, 0 gives a new value every frame in which
+    /// time moves. Smooth noise needs a rate above 0
+    #[serde(default)]
+    pub rate: f32,
+}
+
+/// How a file node reads its file
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    /// Plain text, only output as a whole
+    Text,
+    /// Comma separated values, with the column names in the first row
+    Csv,
+    /// JSON, either an object of numbers and lists of numbers or a list of objects
+    Json,
+}
+
+/// File node type - outputs the contents of a text, CSV or JSON file, and picks up changes to it
+/// without reloading, so other programs can feed data to the graph by writing the file
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Relative path to the file
+    pub path: PathBuf,
+
+    /// How to read the file. If not given, it's guessed from the extension, and anything but
+    /// `.csv` or `.json` is read as text
+    #[serde(default)]
+    pub format: Option<FileFormat>,
+
+    /// Numeric columns of a CSV or JSON file to output as float arrays, named after the columns
+    /// (or the keys in JSON files)
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+/// HTTP node type - polls a URL in the background and outputs the latest response, so chat bots,
+/// dashboards and home automation can push live data into the graph
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// URL to request
+    pub url: String,
+
+    /// Time to wait between requests (seconds)
+    #[serde(default = "http_default_interval")]
+    pub interval: f32,
+
+    /// Numeric fields of a JSON response to output as floats, named after the fields. Nested
+    /// fields and list items are reached with dots, like `data.readings.0`
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+/// Link node type - joins an Ableton Link session on the local network and outputs its tempo and
+/// phase. Only available when built with the `link` feature.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LinkConfig {
+    /// Tempo to start the session at if there are no other peers yet (beats per minute)
+    #[serde(default = "link_default_tempo")]
+    pub tempo: f32,
+
+    /// Beats in a bar, which `bar_phase` goes from 0 to 1 over
+    #[serde(default = "link_default_quantum")]
+    pub quantum: f32,
+}
+
+/// Clock node type - outputs the current date and time as text, to show with a text node
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClockConfig {
+    /// `strftime` pattern to format the time with, like `%H:%M` or `%A, %B %e`
+    #[serde(default = "clock_default_format")]
+    pub format: String,
+
+    /// Use UTC instead of the local time zone
+    #[serde(default)]
+    pub utc: bool,
+}
+
+/// Envelope node type - follows the mean brightness of a texture over time
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EnvelopeConfig {
+    /// Texture to follow the brightness of
+    pub texture: NodeConnection,
+
+    /// How quickly the envelope rises (seconds)
+    #[serde(default = "envelope_default_attack")]
+    pub attack: f32,
+
+    /// How quickly the envelope falls (seconds)
+    #[serde(default = "envelope_default_release")]
+    pub release: f32,
+}
+
+/// Readback node type - passes a texture through, reading it back to CPU memory for anything
+/// subscribed to the node. If the output doesn't depend on it, give it a priority so it's still
+/// rendered.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ReadbackConfig {
+    /// Texture to read back
+    pub texture: NodeConnection,
+}
+
+/// Transform node type - translates, rotates, scales, flips, and crops a texture
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TransformConfig {
+    /// Texture to transform
+    pub texture: NodeConnection,
+
+    /// Offset, as a fraction of the output size
+    #[serde(default)]
+    pub translate: NodeParameter<[f32; 2]>,
+
+    /// Rotation around the center, counterclockwise (radians)
+    #[serde(default)]
+    pub rotate: NodeParameter<f32>,
+
+    /// Scale around the center
+    #[serde(default = "transform_default_scale")]
+    pub scale: NodeParameter<[f32; 2]>,
+
+    /// Mirror horizontally
+    #[serde(default)]
+    pub flip_x: bool,
+
+    /// Mirror vertically
+    #[serde(default)]
+    pub flip_y: bool,
+
+    /// The region of the texture to use, as `[x, y, width, height]` in texture coordinates (0 to
+    /// 1, from the bottom left). The region is stretched to fill the output before transforming.
+    #[serde(default = "transform_default_crop")]
+    pub crop: NodeParameter<[f32; 4]>,
+}
+
+/// Blur node type - blurs a texture with a separable gaussian
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BlurConfig {
+    /// Texture to blur
+    pub texture: NodeConnection,
+
+    /// Blur radius (pixels)
+    #[serde(default = "blur_default_radius")]
+    pub radius: NodeParameter<f32>,
+}
+
+/// Bloom node type - makes the bright parts of a texture glow
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BloomConfig {
+    /// Texture to add the glow to
+    pub texture: NodeConnection,
+
+    /// Brightness above which pixels start to glow
+    #[serde(default = "bloom_default_threshold")]
+    pub threshold: NodeParameter<f32>,
+
+    /// Radius of the glow (pixels)
+    #[serde(default = "bloom_default_radius")]
+    pub radius: f32,
+
+    /// Strength of the glow
+    #[serde(default = "bloom_default_intensity")]
+    pub intensity: NodeParameter<f32>,
+}
+
+/// Color grade node type - adjusts exposure, contrast, and saturation, then optionally maps the
+/// colors through a lookup table
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ColorGradeConfig {
+    /// Texture to grade
+    pub texture: NodeConnection,
+
+    /// Exposure adjustment (stops)
+    #[serde(default)]
+    pub exposure: NodeParameter<f32>,
+
+    /// Contrast multiplier, around middle gray
+    #[serde(default = "color_grade_default_contrast")]
+    pub contrast: NodeParameter<f32>,
+
+    /// Saturation multiplier, where 0 is grayscale
+    #[serde(default = "color_grade_default_saturation")]
+    pub saturation: NodeParameter<f32>,
+
+    /// Lookup table, as a horizontal strip of `n` squares of `n` by `n` pixels (e.g. 256x16): one
+    /// square per blue value, with red increasing to the right and green increasing downwards
+    #[serde(default)]
+    pub lut: Option<NodeConnection>,
+}
+
+/// LUT node type - maps the colors of a texture through a 3D lookup table loaded from an Adobe
+/// `.cube` file, like the ones exported by Resolve and Photoshop
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LutConfig {
+    /// Texture to map
+    pub texture: NodeConnection,
+
+    /// Relative path to the `.cube` file
+    pub path: PathBuf,
+
+    /// How much of the mapped color to use, from 0 (none) to 1 (all of it)
+    #[serde(default = "lut_default_amount")]
+    pub amount: NodeParameter<f32>,
+}
+
+/// Switch node type - passes through one of several textures, chosen by an index. Only the
+/// selected branch of the graph is rendered, so a change of a connected index shows from the next
+/// frame on.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SwitchConfig {
+    /// Textures to choose between
+    pub textures: Vec<NodeConnection>,
+
+    /// Index of the texture to pass through, rounded down and clamped to the number of textures
+    #[serde(default)]
+    pub index: NodeParameter<f32>,
+}
+
+/// Transition node type - blends from one texture to another as the progress goes from 0 to 1
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionConfig {
+    /// Texture shown at the start of the transition
+    pub from: NodeConnection,
+
+    /// Texture shown at the end of the transition
+    pub to: NodeConnection,
+
+    /// How far the transition has gone, from 0 to 1
+    #[serde(default)]
+    pub progress: NodeParameter<f32>,
+
+    /// Which transition to use
+    #[serde(default = "transition_default_transition")]
+    pub transition: TransitionKind,
+
+    /// For wipes, the direction the edge moves in, counterclockwise from the right (radians)
+    #[serde(default)]
+    pub angle: f32,
+
+    /// For wipes and luma dissolves, the width of the edge as a fraction of the transition
+    #[serde(default = "transition_default_softness")]
+    pub softness: f32,
+
+    /// For luma dissolves, the texture whose brightness decides when each pixel changes: dark
+    /// pixels change first
+    #[serde(default)]
+    pub mask: Option<NodeConnection>,
+}
+
+/// Transitions between two textures
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Fade evenly from one texture to the other
+    Crossfade,
+    /// Move an edge across the texture
+    Wipe,
+    /// Change pixels in order of the brightness of a mask texture
+    LumaDissolve,
+}
+
+/// Config for FeedbackNode
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeedbackConfig {
+    /// Input connections
+    pub inputs: Vec<NodeConnection>,
+
+    /// Color of textures until the first frame is fed back, and whenever they're cleared
+    #[serde(default)]
+    pub clear_color: [f32; 4],
+
+    /// Clear textures when the window is resized, rather than stretching the last frame
+    #[serde(default = "feedback_default_clear_on_resize")]
+    pub clear_on_resize: bool,
+
+    /// Factor textures are multiplied by each time they're fed back, so trails fade out
+    #[serde(default = "feedback_default_decay")]
+    pub decay: f32,
+}
+
+/// Config for PluginNode
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Name of the node type, as provided by one of the libraries in the `plugins` section
+    pub plugin: String,
+
+    /// Input connections, which are passed to the plugin by name
+    #[serde(default)]
+    pub inputs: Vec<NodeConnection>,
+
+    /// Names and types of the outputs the plugin produces. Plugins can't use textures.
+    #[serde(default)]
+    pub outputs: IndexMap<String, InputType>,
+
+    /// Settings passed to the plugin as JSON when the node is created
+    #[serde(default)]
+    pub settings: serde_json::Value,
+}
+
+/// Config for ScriptNode
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// Relative path to a Rhai script, which is run every frame. Inputs are constants named after
+    /// their connections, and outputs are variables named after them, which the script assigns to
+    /// (without `let`) and which keep their values between frames.
+    pub path: PathBuf,
+
+    /// Input connections
+    #[serde(default)]
+    pub inputs: Vec<NodeConnection>,
+
+    /// Names and types of the outputs the script sets, which can be floats, float2s, colors or
+    /// text
+    #[serde(default)]
+    pub outputs: IndexMap<String, InputType>,
+}
+
+/// Blend node operations
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendOp {
+    /// Take the minimum RGBA value
+    Min,
+    /// Take the maximum RGBA value
+    Max,
+    /// Add the RGBA values
+    Add,
+    /// Subtract the RGBA values
+    Sub,
+}
+
+/// The node configuration contains all the information necessary to build a node
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type")]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeConfig {
+    /// Configuration for the info node
+    Info,
+    /// Configuration for the output node
+    Output(OutputConfig),
+    /// Configuration for the image node
+    Image(ImageConfig),
+    /// Configuration for the shader node
+    Shader(ShaderConfig),
+    /// Configuration for the blend node
+    Blend(BlendConfig),
+    /// Configuration for the text node
+    Text(TextConfig),
+    /// Configuration for the FPS node
+    Fps(FpsConfig),
+    /// Configuration for the audio node
+    Audio(AudioConfig),
+    /// Configuration for the constant node
+    Constant(ConstantConfig),
+    /// Configuration for the camera node
+    Camera(CameraConfig),
+    /// Configuration for the random node
+    Random(RandomConfig),
+    /// Configuration for the keyframes node
+    Keyframes(TrackConfig),
+    /// Configuration for the mapping node, which is added from the `mappings` section rather
+    /// than written as a node
+    #[serde(skip_deserializing)]
+    Mapping(MappingNodeConfig),
+    /// Configuration for the file node
+    File(FileConfig),
+    /// Configuration for the HTTP node
+    Http(HttpConfig),
+    /// Configuration for the Link node
+    Link(LinkConfig),
+    /// Configuration for the clock node
+    Clock(ClockConfig),
+    /// Configuration for the envelope node
+    Envelope(EnvelopeConfig),
+    /// Configuration for the readback node
+    Readback(ReadbackConfig),
+    /// Configuration for the transform node
+    Transform(TransformConfig),
+    /// Configuration for the blur node
+    Blur(BlurConfig),
+    /// Configuration for the bloom node
+    Bloom(BloomConfig),
+    /// Configuration for the color grade node
+    ColorGrade(ColorGradeConfig),
+    /// Configuration for the LUT node
+    Lut(LutConfig),
+    /// Configuration for the switch node
+    Switch(SwitchConfig),
+    /// Configuration for the transition node
+    Transition(TransitionConfig),
+    /// Configuration for the timeline node, which is added from the `timeline` section rather
+    /// than written as a node
+    #[serde(skip_deserializing)]
+    Timeline(TimelineConfig),
+    /// Configuration for the feedback node
+    Feedback(FeedbackConfig),
+    /// Configuration for a node provided by a plugin
+    Plugin(PluginConfig),
+    /// Configuration for the script node
+    Script(ScriptConfig),
+}
+
+/// A node in the graph, with the settings common to every type of node
+#[derive(Debug, Deserialize, Clone)]
+pub struct Node {
+    /// When the dependencies allow more than one node to be rendered next, the node with the
+    /// lowest priority goes first (ties are broken by the order of the config file). Nodes with a
+    /// priority are rendered even if the output doesn't depend on them, so a high priority can be
+    /// used to make nodes with side effects run last.
+    #[serde(default)]
+    pub priority: Option<i32>,
+
+    /// The configuration for the type of node
+    #[serde(flatten)]
+    pub config: NodeConfig,
+}
+
+/// Returns the order in which to render the nodes: everything the output depends on, plus every
+/// node with an explicit priority and the nodes it depends on. The graph must not contain cycles.
+/// The order only depends on the config, so it's the same on every run.
+pub fn render_order(nodes: &IndexMap<String, Node>, output: &str) -> Vec<String> {
+    let dependencies = |name: &str| -> HashSet<&str> {
+        nodes
+            .get(name)
+            .map(|node| node.config.dependencies())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|dependency| nodes.contains_key(*dependency))
+            .collect()
+    };
+
+    // Find every node that needs to be rendered
+    let mut stack: Vec<&str> = nodes
+        .iter()
+        .filter(|(_, node)| node.priority.is_some())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    stack.push(output);
+    let mut needed = HashSet::new();
+    while let Some(name) = stack.pop() {
+        if nodes.contains_key(name) && needed.insert(name) {
+            stack.extend(dependencies(name));
+        }
+    }
+
+    // Count how many dependencies each node is waiting on, and who is waiting on each node
+    let mut waiting: HashMap<&str, usize> = HashMap::new();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for &name in &needed {
+        let node_dependencies = dependencies(name);
+        waiting.insert(name, node_dependencies.len());
+        for dependency in node_dependencies {
+            dependents.entry(dependency).or_default().push(name);
+        }
+    }
+
+    let key = |name: &str| {
+        let (index, _, node) = nodes.get_full(name).unwrap();
+        Reverse((node.priority.unwrap_or(0), index))
+    };
+    let mut ready: BinaryHeap<_> = waiting
+        .iter()
+        .filter(|&(_, &count)| count == 0)
+        .map(|(&name, _)| key(name))
+        .collect();
+
+    let mut order = Vec::new();
+    while let Some(Reverse((_, index))) = ready.pop() {
+        let (name, _) = nodes.get_index(index).unwrap();
+        for &dependent in dependents.get(name.as_str()).into_iter().flatten() {
+            let count = waiting.get_mut(dependent).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(key(dependent));
+            }
+        }
+        order.push(name.to_string());
+    }
+
+    order
+}
+
+/// Number of monitors the info node has `monitor_<n>` outputs for
+pub const INFO_MONITORS: usize = 4;
+
+impl NodeConfig {
+    /// Returns the name of the type of node, as written in the config
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            NodeConfig::Info => "info",
+            NodeConfig::Output(_) => "output",
+            NodeConfig::Image(_) => "image",
+            NodeConfig::Shader(_) => "shader",
+            NodeConfig::Blend(_) => "blend",
+            NodeConfig::Text(_) => "text",
+            NodeConfig::Fps(_) => "fps",
+            NodeConfig::Audio(_) => "audio",
+            NodeConfig::Constant(_) => "constant",
+            NodeConfig::Camera(_) => "camera",
+            NodeConfig::Random(_) => "random",
+            NodeConfig::Keyframes(_) => "keyframes",
+            NodeConfig::Mapping(_) => "mapping",
+            NodeConfig::File(_) => "file",
+            NodeConfig::Http(_) => "http",
+            NodeConfig::Link(_) => "link",
+            NodeConfig::Clock(_) => "clock",
+            NodeConfig::Envelope(_) => "envelope",
+            NodeConfig::Readback(_) => "readback",
+            NodeConfig::Transform(_) => "transform",
+            NodeConfig::Blur(_) => "blur",
+            NodeConfig::Bloom(_) => "bloom",
+            NodeConfig::ColorGrade(_) => "color_grade",
+            NodeConfig::Lut(_) => "lut",
+            NodeConfig::Switch(_) => "switch",
+            NodeConfig::Transition(_) => "transition",
+            NodeConfig::Timeline(_) => "timeline",
+            NodeConfig::Feedback(_) => "feedback",
+            NodeConfig::Plugin(_) => "plugin",
+            NodeConfig::Script(_) => "script",
+        }
+    }
+
+    /// Returns the names and types of the outputs this node produces
+    pub fn outputs(&self) -> Vec<(String, InputType)> {
+        let texture = || vec![("texture".to_string(), InputType::Texture2d)];
+
+        match *self {
+            NodeConfig::Info => {
+                let mut outputs = vec![
+                    ("time".to_string(), InputType::Float),
+                    ("resolution".to_string(), InputType::Float2),
+                    ("pointer".to_string(), InputType::Float4),
+                    ("buttons".to_string(), InputType::Float4),
+                    ("scroll".to_string(), InputType::Float),
+                    ("drag".to_string(), InputType::Float2),
+                    ("reduced_motion".to_string(), InputType::Float),
+                    ("present_mode".to_string(), InputType::Text),
+                    ("hidpi_factor".to_string(), InputType::Float),
+                    ("monitor_count".to_string(), InputType::Float),
+                ];
+                outputs.extend(
+                    (0..INFO_MONITORS).map(|i| (format!("monitor_{}", i), InputType::Float4)),
+                );
+                outputs
+            }
+            NodeConfig::Output(_) => Vec::new(),
+            NodeConfig::Shader(ref shader_config) => shader_config
+                .output_names()
+                .into_iter()
+                .map(|name| (name, InputType::Texture2d))
+                .collect(),
+            NodeConfig::Image(_)
+            | NodeConfig::Blend(_)
+            | NodeConfig::Readback(_)
+            | NodeConfig::Transform(_)
+            | NodeConfig::Blur(_)
+            | NodeConfig::Bloom(_)
+            | NodeConfig::ColorGrade(_)
+            | NodeConfig::Lut(_)
+            | NodeConfig::Transition(_)
+            | NodeConfig::Timeline(_) => texture(),
+            NodeConfig::Text(_) => vec![
+                ("texture".to_string(), InputType::Texture2d),
+                ("width".to_string(), InputType::Float),
+                ("height".to_string(), InputType::Float),
+            ],
+            NodeConfig::Fps(_) => vec![
+                ("texture".to_string(), InputType::Texture2d),
+                ("width".to_string(), InputType::Float),
+                ("height".to_string(), InputType::Float),
+                ("fps".to_string(), InputType::Float),
+                ("frame_time".to_string(), InputType::Float),
+                ("low_1".to_string(), InputType::Float),
+                ("p50".to_string(), InputType::Float),
+                ("p99".to_string(), InputType::Float),
+            ],
+            NodeConfig::Audio(ref audio_config) => {
+                let mut outputs = vec![
+                    ("waveform".to_string(), InputType::Texture1d),
+                    ("spectrum".to_string(), InputType::Texture1d),
+                    ("spectra".to_string(), InputType::Texture2d),
+                ];
+                for channel in 0..audio_config.channels {
+                    outputs.push((format!("rms_{}", channel), InputType::Float));
+                }
+                for band in &["bass", "mid", "treble", "beat"] {
+                    outputs.push((band.to_string(), InputType::Float));
+                }
+                outputs
+            }
+            NodeConfig::Constant(ref constant_config) => {
+                let type_ = match *constant_config {
+                    ConstantConfig::Float(_) => InputType::Float,
+                    ConstantConfig::Float2(_) => InputType::Float2,
+                    ConstantConfig::Float4(_) => InputType::Float4,
+                    ConstantConfig::Color(_) => InputType::Color,
+                    ConstantConfig::Mat4(_) => InputType::Mat4,
+                    ConstantConfig::FloatArray(_) => InputType::FloatArray,
+                };
+                vec![("value".to_string(), type_)]
+            }
+            NodeConfig::Mapping(_) => vec![("value".to_string(), InputType::Float)],
+            NodeConfig::Camera(_) => vec![
+                ("view".to_string(), InputType::Mat4),
+                ("projection".to_string(), InputType::Mat4),
+                ("inverse_view".to_string(), InputType::Mat4),
+                ("position".to_string(), InputType::Float4),
+            ],
+            NodeConfig::Random(_) => vec![
+                ("value".to_string(), InputType::Float),
+                ("value2".to_string(), InputType::Float2),
+            ],
+            NodeConfig::Keyframes(ref track_config) => {
+                let type_ = match track_config.keys.first().map(|key| key.value) {
+                    Some(ParamValue::Color(_)) => InputType::Color,
+                    _ => InputType::Float,
+                };
+                vec![("value".to_string(), type_)]
+            }
+            NodeConfig::File(ref file_config) => {
+                let mut outputs = vec![("text".to_string(), InputType::Text)];
+                for column in &file_config.columns {
+                    outputs.push((column.clone(), InputType::FloatArray));
+                }
+                outputs
+            }
+            NodeConfig::Http(ref http_config) => {
+                let mut outputs = vec![("text".to_string(), InputType::Text)];
+                for field in &http_config.fields {
+                    outputs.push((field.clone(), InputType::Float));
+                }
+                outputs
+            }
+            NodeConfig::Link(_) => vec![
+                ("tempo".to_string(), InputType::Float),
+                ("beat".to_string(), InputType::Float),
+                ("beat_phase".to_string(), InputType::Float),
+                ("bar_phase".to_string(), InputType::Float),
+                ("peers".to_string(), InputType::Float),
+            ],
+            NodeConfig::Clock(_) => vec![("text".to_string(), InputType::Text)],
+            NodeConfig::Switch(_) => vec![
+                ("texture".to_string(), InputType::Texture2d),
+                ("index".to_string(), InputType::Float),
+            ],
+            NodeConfig::Envelope(_) => vec![
+                ("level".to_string(), InputType::Float),
+                ("envelope".to_string(), InputType::Float),
+            ],
+            NodeConfig::Feedback(ref feedback_config) => feedback_config
+                .inputs
+                .iter()
+                .map(|input| (input.name.clone(), input.type_.clone()))
+                .collect(),
+            NodeConfig::Plugin(ref plugin_config) => plugin_config
+                .outputs
+                .iter()
+                .map(|(name, type_)| (name.clone(), type_.clone()))
+                .collect(),
+            NodeConfig::Script(ref script_config) => script_config
+                .outputs
+                .iter()
+                .map(|(name, type_)| (name.clone(), type_.clone()))
+                .collect(),
+        }
+    }
+
+    /// Returns the connections this node reads from, along with the name of the input each one
+    /// is connected to and the type that input expects
+    pub fn connections(&self) -> Vec<(&'static str, &NodeConnection, InputType)> {
+        fn parameter<'a, T>(
+            connections: &mut Vec<(&'static str, &'a NodeConnection, InputType)>,
+            name: &'static str,
+            parameter: &'a NodeParameter<T>,
+            type_: InputType,
+        ) {
+            if let NodeParameter::NodeConnection(ref connection) = *parameter {
+                connections.push((name, connection, type_));
+            }
+        }
+
+        let mut connections = Vec::new();
+        match *self {
+            NodeConfig::Info
+            | NodeConfig::Image(_)
+            | NodeConfig::Audio(_)
+            | NodeConfig::Constant(_)
+            | NodeConfig::Camera(_)
+            | NodeConfig::Random(_)
+            | NodeConfig::Keyframes(_)
+            | NodeConfig::File(_)
+            | NodeConfig::Http(_)
+            | NodeConfig::Link(_)
+            | NodeConfig::Clock(_) => (),
+            NodeConfig::Output(ref output_config) => {
+                connections.push(("texture", &output_config.texture, InputType::Texture2d));
+            }
+            NodeConfig::Envelope(ref envelope_config) => {
+                connections.push(("texture", &envelope_config.texture, InputType::Texture2d));
+            }
+            NodeConfig::Mapping(ref mapping_config) => {
+                connections.push(("input", &mapping_config.input, InputType::Float));
+            }
+            NodeConfig::Readback(ref readback_config) => {
+                connections.push(("texture", &readback_config.texture, InputType::Texture2d));
+            }
+            NodeConfig::Transform(ref transform_config) => {
+                connections.push(("texture", &transform_config.texture, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "translate",
+                    &transform_config.translate,
+                    InputType::Float2,
+                );
+                parameter(
+                    &mut connections,
+                    "rotate",
+                    &transform_config.rotate,
+                    InputType::Float,
+                );
+                parameter(
+                    &mut connections,
+                    "scale",
+                    &transform_config.scale,
+                    InputType::Float2,
+                );
+                parameter(
+                    &mut connections,
+                    "crop",
+                    &transform_config.crop,
+                    InputType::Float4,
+                );
+            }
+            NodeConfig::Blur(ref blur_config) => {
+                connections.push(("texture", &blur_config.texture, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "radius",
+                    &blur_config.radius,
+                    InputType::Float,
+                );
+            }
+            NodeConfig::Bloom(ref bloom_config) => {
+                connections.push(("texture", &bloom_config.texture, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "threshold",
+                    &bloom_config.threshold,
+                    InputType::Float,
+                );
+                parameter(
+                    &mut connections,
+                    "intensity",
+                    &bloom_config.intensity,
+                    InputType::Float,
+                );
+            }
+            NodeConfig::ColorGrade(ref color_grade_config) => {
+                connections.push(("texture", &color_grade_config.texture, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "exposure",
+                    &color_grade_config.exposure,
+                    InputType::Float,
+                );
+                parameter(
+                    &mut connections,
+                    "contrast",
+                    &color_grade_config.contrast,
+                    InputType::Float,
+                );
+                parameter(
+                    &mut connections,
+                    "saturation",
+                    &color_grade_config.saturation,
+                    InputType::Float,
+                );
+                if let Some(ref lut) = color_grade_config.lut {
+                    connections.push(("lut", lut, InputType::Texture2d));
+                }
+            }
+            NodeConfig::Lut(ref lut_config) => {
+                connections.push(("texture", &lut_config.texture, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "amount",
+                    &lut_config.amount,
+                    InputType::Float,
+                );
+            }
+            NodeConfig::Shader(ref shader_config) => {
+                for connection in &shader_config.uniforms {
+                    connections.push(("uniforms", connection, connection.type_.clone()));
+                }
+            }
+            NodeConfig::Blend(ref blend_config) => {
+                for connection in &blend_config.textures {
+                    connections.push(("textures", connection, InputType::Texture2d));
+                }
+            }
+            NodeConfig::Text(ref text_config) => {
+                parameter(&mut connections, "text", &text_config.text, InputType::Text);
+                parameter(
+                    &mut connections,
+                    "position",
+                    &text_config.position,
+                    InputType::Float2,
+                );
+                parameter(
+                    &mut connections,
+                    "color",
+                    &text_config.color,
+                    InputType::Color,
+                );
+            }
+            NodeConfig::Fps(ref fps_config) => {
+                parameter(
+                    &mut connections,
+                    "position",
+                    &fps_config.position,
+                    InputType::Float2,
+                );
+                parameter(
+                    &mut connections,
+                    "color",
+                    &fps_config.color,
+                    InputType::Color,
+                );
+            }
+            NodeConfig::Switch(ref switch_config) => {
+                for connection in &switch_config.textures {
+                    connections.push(("textures", connection, InputType::Texture2d));
+                }
+                parameter(
+                    &mut connections,
+                    "index",
+                    &switch_config.index,
+                    InputType::Float,
+                );
+            }
+            NodeConfig::Transition(ref transition_config) => {
+                connections.push(("from", &transition_config.from, InputType::Texture2d));
+                connections.push(("to", &transition_config.to, InputType::Texture2d));
+                parameter(
+                    &mut connections,
+                    "progress",
+                    &transition_config.progress,
+                    InputType::Float,
+                );
+                if let Some(ref mask) = transition_config.mask {
+                    connections.push(("mask", mask, InputType::Texture2d));
+                }
+            }
+            NodeConfig::Timeline(ref timeline_config) => {
+                for connection in timeline_config
+                    .scenes
+                    .iter()
+                    .filter_map(|scene| scene.texture.as_ref())
+                {
+                    connections.push(("scenes", connection, InputType::Texture2d));
+                }
+            }
+            NodeConfig::Feedback(ref feedback_config) => {
+                for connection in &feedback_config.inputs {
+                    connections.push(("inputs", connection, connection.type_.clone()));
+                }
+            }
+            NodeConfig::Plugin(ref plugin_config) => {
+                for connection in &plugin_config.inputs {
+                    connections.push(("inputs", connection, connection.type_.clone()));
+                }
+            }
+            NodeConfig::Script(ref script_config) => {
+                for connection in &script_config.inputs {
+                    connections.push(("inputs", connection, connection.type_.clone()));
+                }
+            }
+        }
+        connections
+    }
+
+    /// Returns the connections this node reads from, for changing which nodes they point to
+    pub fn connections_mut(&mut self) -> Vec<&mut NodeConnection> {
+        fn parameter<T>(parameter: &mut NodeParameter<T>) -> Option<&mut NodeConnection> {
+            match *parameter {
+                NodeParameter::NodeConnection(ref mut connection) => Some(connection),
+                NodeParameter::Static(_) => None,
+            }
+        }
+
+        match *self {
+            NodeConfig::Info
+            | NodeConfig::Image(_)
+            | NodeConfig::Audio(_)
+            | NodeConfig::Constant(_)
+            | NodeConfig::Camera(_)
+            | NodeConfig::Random(_)
+            | NodeConfig::Keyframes(_)
+            | NodeConfig::File(_)
+            | NodeConfig::Http(_)
+            | NodeConfig::Link(_)
+            | NodeConfig::Clock(_) => Vec::new(),
+            NodeConfig::Output(ref mut output_config) => vec![&mut output_config.texture],
+            NodeConfig::Envelope(ref mut envelope_config) => vec![&mut envelope_config.texture],
+            NodeConfig::Mapping(ref mut mapping_config) => vec![&mut mapping_config.input],
+            NodeConfig::Readback(ref mut readback_config) => vec![&mut readback_config.texture],
+            NodeConfig::Transform(ref mut transform_config) => {
+                let mut connections = vec![&mut transform_config.texture];
+                connections.extend(parameter(&mut transform_config.translate));
+                connections.extend(parameter(&mut transform_config.rotate));
+                connections.extend(parameter(&mut transform_config.scale));
+                connections.extend(parameter(&mut transform_config.crop));
+                connections
+            }
+            NodeConfig::Blur(ref mut blur_config) => {
+                let mut connections = vec![&mut blur_config.texture];
+                connections.extend(parameter(&mut blur_config.radius));
+                connections
+            }
+            NodeConfig::Bloom(ref mut bloom_config) => {
+                let mut connections = vec![&mut bloom_config.texture];
+                connections.extend(parameter(&mut bloom_config.threshold));
+                connections.extend(parameter(&mut bloom_config.intensity));
+                connections
+            }
+            NodeConfig::ColorGrade(ref mut color_grade_config) => {
+                let mut connections = vec![&mut color_grade_config.texture];
+                connections.extend(parameter(&mut color_grade_config.exposure));
+                connections.extend(parameter(&mut color_grade_config.contrast));
+                connections.extend(parameter(&mut color_grade_config.saturation));
+                connections.extend(color_grade_config.lut.as_mut());
+                connections
+            }
+            NodeConfig::Lut(ref mut lut_config) => {
+                let mut connections = vec![&mut lut_config.texture];
+                connections.extend(parameter(&mut lut_config.amount));
+                connections
+            }
+            NodeConfig::Shader(ref mut shader_config) => {
+                shader_config.uniforms.iter_mut().collect()
+            }
+            NodeConfig::Blend(ref mut blend_config) => blend_config.textures.iter_mut().collect(),
+            NodeConfig::Text(ref mut text_config) => {
+                let mut connections = Vec::new();
+                connections.extend(parameter(&mut text_config.text));
+                connections.extend(parameter(&mut text_config.position));
+                connections.extend(parameter(&mut text_config.color));
+                connections
+            }
+            NodeConfig::Fps(ref mut fps_config) => {
+                let mut connections = Vec::new();
+                connections.extend(parameter(&mut fps_config.position));
+                connections.extend(parameter(&mut fps_config.color));
+                connections
+            }
+            NodeConfig::Switch(ref mut switch_config) => {
+                let mut connections: Vec<_> = switch_config.textures.iter_mut().collect();
+                connections.extend(parameter(&mut switch_config.index));
+                connections
+            }
+            NodeConfig::Transition(ref mut transition_config) => {
+                let mut connections = vec![&mut transition_config.from, &mut transition_config.to];
+                connections.extend(parameter(&mut transition_config.progress));
+                connections.extend(transition_config.mask.as_mut());
+                connections
+            }
+            NodeConfig::Timeline(ref mut timeline_config) => timeline_config
+                .scenes
+                .iter_mut()
+                .filter_map(|scene| scene.texture.as_mut())
+                .collect(),
+            NodeConfig::Feedback(ref mut feedback_config) => {
+                feedback_config.inputs.iter_mut().collect()
+            }
+            NodeConfig::Plugin(ref mut plugin_config) => plugin_config.inputs.iter_mut().collect(),
+            NodeConfig::Script(ref mut script_config) => script_config.inputs.iter_mut().collect(),
+        }
+    }
+
+    /// Returns the float parameters of this node along with the names of their fields, whether
+    /// they're static or connected, for editing
+    pub fn float_parameters_mut(&mut self) -> Vec<(&'static str, &mut NodeParameter<f32>)> {
+        match *self {
+            NodeConfig::Transform(ref mut transform_config) => {
+                vec![("rotate", &mut transform_config.rotate)]
+            }
+            NodeConfig::Blur(ref mut blur_config) => vec![("radius", &mut blur_config.radius)],
+            NodeConfig::Bloom(ref mut bloom_config) => vec![
+                ("threshold", &mut bloom_config.threshold),
+                ("intensity", &mut bloom_config.intensity),
+            ],
+            NodeConfig::ColorGrade(ref mut color_grade_config) => vec![
+                ("exposure", &mut color_grade_config.exposure),
+                ("contrast", &mut color_grade_config.contrast),
+                ("saturation", &mut color_grade_config.saturation),
+            ],
+            NodeConfig::Lut(ref mut lut_config) => vec![("amount", &mut lut_config.amount)],
+            NodeConfig::Switch(ref mut switch_config) => vec![("index", &mut switch_config.index)],
+            NodeConfig::Transition(ref mut transition_config) => {
+                vec![("progress", &mut transition_config.progress)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Makes the paths of the files this node loads relative to a directory, for nodes merged in
+    /// from another config file
+    pub fn resolve_paths(&mut self, cwd: &Path) {
+        match *self {
+            NodeConfig::Image(ref mut image_config) => {
+                image_config.path = cwd.join(&image_config.path);
+            }
+            NodeConfig::Lut(ref mut lut_config) => {
+                lut_config.path = cwd.join(&lut_config.path);
+            }
+            NodeConfig::File(ref mut file_config) => {
+                file_config.path = cwd.join(&file_config.path);
+            }
+            NodeConfig::Script(ref mut script_config) => {
+                script_config.path = cwd.join(&script_config.path);
+            }
+            NodeConfig::Shader(ref mut shader_config) => {
+                for path in shader_config
+                    .vertex
+                    .iter_mut()
+                    .chain(shader_config.fragment.iter_mut())
+                    .chain(shader_config.fragment_gles.iter_mut())
+                    .chain(shader_config.fragment_fallback.iter_mut())
+                    .chain(shader_config.geometry.iter_mut())
+                    .chain(shader_config.tess_control.iter_mut())
+                    .chain(shader_config.tess_eval.iter_mut())
+                {
+                    *path = cwd.join(&*path);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the names of the nodes which must be rendered before this one. Feedback nodes read
+    /// their inputs after the rest of the graph is rendered, so they have no dependencies.
+    pub fn dependencies(&self) -> Vec<&str> {
+        match *self {
+            NodeConfig::Feedback(_) => Vec::new(),
+            _ => self
+                .connections()
+                .into_iter()
+                .map(|(_, connection, _)| connection.node.as_str())
+                .collect(),
+        }
+    }
+}
+
+fn image_default_resident_frames() -> usize {
+    2
+}
+
+fn text_default_color() -> NodeParameter<[f32; 4]> {
+    NodeParameter::Static([1.0; 4])
+}
+
+fn text_default_font_size() -> f32 {
+    20.0
+}
+
+fn text_default_line_spacing() -> f32 {
+    1.0
+}
+
+fn scroll_default_speed() -> f32 {
+    100.0
+}
+
+fn audio_default_channels() -> u32 {
+    1
+}
+
+fn camera_default_mode() -> CameraMode {
+    CameraMode::Orbit
+}
+
+fn camera_default_position() -> [f32; 3] {
+    [0.0, 0.0, 5.0]
+}
+
+fn camera_default_fov() -> f32 {
+    60.0
+}
+
+fn camera_default_near() -> f32 {
+    0.1
+}
+
+fn camera_default_far() -> f32 {
+    100.0
+}
+
+fn camera_default_speed() -> f32 {
+    2.0
+}
+
+fn camera_default_sensitivity() -> f32 {
+    0.005
+}
+
+fn random_default_mode() -> RandomMode {
+    RandomMode::White
+}
+
+fn http_default_interval() -> f32 {
+    1.0
+}
+
+fn link_default_tempo() -> f32 {
+    120.0
+}
+
+fn link_default_quantum() -> f32 {
+    4.0
+}
+
+fn clock_default_format() -> String {
+    "%H:%M:%S".to_string()
+}
+
+fn envelope_default_attack() -> f32 {
+    0.05
+}
+
+fn envelope_default_release() -> f32 {
+    0.5
+}
+
+fn transform_default_scale() -> NodeParameter<[f32; 2]> {
+    NodeParameter::Static([1.0; 2])
+}
+
+fn transform_default_crop() -> NodeParameter<[f32; 4]> {
+    NodeParameter::Static([0.0, 0.0, 1.0, 1.0])
+}
+
+fn blur_default_radius() -> NodeParameter<f32> {
+    NodeParameter::Static(4.0)
+}
+
+fn bloom_default_threshold() -> NodeParameter<f32> {
+    NodeParameter::Static(0.8)
+}
+
+fn bloom_default_radius() -> f32 {
+    8.0
+}
+
+fn bloom_default_intensity() -> NodeParameter<f32> {
+    NodeParameter::Static(1.0)
+}
+
+fn color_grade_default_contrast() -> NodeParameter<f32> {
+    NodeParameter::Static(1.0)
+}
+
+fn color_grade_default_saturation() -> NodeParameter<f32> {
+    NodeParameter::Static(1.0)
+}
+
+fn feedback_default_clear_on_resize() -> bool {
+    true
+}
+
+fn feedback_default_decay() -> f32 {
+    1.0
+}
+
+fn lut_default_amount() -> NodeParameter<f32> {
+    NodeParameter::Static(1.0)
+}
+
+fn transition_default_transition() -> TransitionKind {
+    TransitionKind::Crossfade
+}
+
+fn transition_default_softness() -> f32 {
+    0.1
+}
+
+fn fps_default_interval() -> f32 {
+    1.0
+}
+
+fn fps_default_window() -> f32 {
+    5.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_order, ConstantConfig, NodeConfig};
+    use crate::config::Config;
+
+    #[test]
+    fn parses_constants() {
+        let source = "
+nodes:
+    accent:
+        type: constant
+        color: [1.0, 0.5, 0.0, 1.0]
+    speed:
+        type: constant
+        float: 0.25
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        match config.nodes["accent"].config {
+            NodeConfig::Constant(ConstantConfig::Color(color)) => {
+                assert_eq!(color, [1.0, 0.5, 0.0, 1.0])
+            }
+            ref other => panic!("unexpected config: {:?}", other),
+        }
+        match config.nodes["speed"].config {
+            NodeConfig::Constant(ConstantConfig::Float(value)) => assert_eq!(value, 0.25),
+            ref other => panic!("unexpected config: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn orders_by_priority() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: blend
+            output: texture
+    blend:
+        type: blend
+        operation: add
+        textures:
+            - node: b
+              output: texture
+            - node: a
+              output: texture
+    a:
+        type: text
+        text: a
+    b:
+        type: text
+        text: b
+        priority: -1
+    sink:
+        type: text
+        text: sink
+        priority: 10
+    dangling:
+        type: info
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(
+            render_order(&config.nodes, "output"),
+            vec!["b", "a", "blend", "output", "sink"]
+        );
+    }
+
+    #[test]
+    fn ties_follow_config_order() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: blend
+            output: texture
+    blend:
+        type: blend
+        operation: add
+        textures:
+            - node: a
+              output: texture
+            - node: c
+              output: texture
+            - node: b
+              output: texture
+    c:
+        type: text
+        text: c
+    a:
+        type: text
+        text: a
+    b:
+        type: text
+        text: b
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(
+            render_order(&config.nodes, "output"),
+            vec!["c", "a", "b", "blend", "output"]
+        );
+    }
+}
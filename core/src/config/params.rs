@@ -0,0 +1,230 @@
+//! Named parameters, so one config can serve several variants without editing shaders or copying
+//! the graph
+//!
+//! ```yaml
+//! params:
+//!     speed: 1.5
+//!     tint: [1.0, 0.5, 0.0, 1.0]
+//! ```
+//!
+//! Any connection can read a parameter with `param: speed` instead of `node` and `output`. Each
+//! parameter becomes a constant node named `params/<name>`. Values can be overridden with
+//! `YOTREDASH_PARAM_<NAME>` environment variables (with the name in upper case), and those with
+//! `--param name=value`. Colors are written as four numbers separated by commas.
+
+use anyhow::{bail, ensure, format_err, Error};
+use log::warn;
+use serde_derive::Deserialize;
+use std::env;
+
+use super::{
+    nodes::{ConstantConfig, Node, NodeConfig},
+    Config,
+};
+
+/// Prefix of the names of the nodes parameters are read from
+pub const PARAM_PREFIX: &str = "params/";
+
+/// The value of a parameter
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum ParamValue {
+    /// A single number
+    Scalar(f32),
+    /// A color (RGBA)
+    Color([f32; 4]),
+}
+
+impl ParamValue {
+    /// Parses a new value for a parameter from text, which must be the same kind of value
+    fn parse(self, text: &str) -> Result<Self, Error> {
+        let numbers = text
+            .split(',')
+            .map(|number| {
+                number
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| format_err!("`{}` isn't a number", number.trim()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(match self {
+            ParamValue::Scalar(_) => {
+                ensure!(numbers.len() == 1, "Expected a number, got `{}`", text);
+                ParamValue::Scalar(numbers[0])
+            }
+            ParamValue::Color(_) => {
+                ensure!(
+                    numbers.len() == 4,
+                    "Expected a color as four numbers, got `{}`",
+                    text
+                );
+                ParamValue::Color([numbers[0], numbers[1], numbers[2], numbers[3]])
+            }
+        })
+    }
+
+    /// Returns the constant node configuration for this value
+    pub fn constant(self) -> ConstantConfig {
+        match self {
+            ParamValue::Scalar(value) => ConstantConfig::Float(value),
+            ParamValue::Color(value) => ConstantConfig::Color(value),
+        }
+    }
+}
+
+/// Returns the name of the environment variable that overrides a parameter
+fn env_var(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("YOTREDASH_PARAM_{}", name)
+}
+
+/// Applies overrides from the environment, adds a constant node for each parameter, and points
+/// the connections that read parameters at them
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    for (name, value) in config.params.iter_mut() {
+        if let Ok(text) = env::var(env_var(name)) {
+            *value = value
+                .parse(&text)
+                .map_err(|e| format_err!("Invalid value for parameter `{}`: {}", name, e))?;
+        }
+    }
+
+    for (node_name, node) in config.nodes.iter_mut() {
+        for connection in node.config.connections_mut() {
+            match connection.param.take() {
+                Some(param) => {
+                    ensure!(
+                        connection.node.is_empty() && connection.output.is_empty(),
+                        "Node `{}`: a connection can't have both `param` and `node` or `output`",
+                        node_name
+                    );
+                    ensure!(
+                        config.params.contains_key(&param),
+                        "Node `{}`: there's no parameter named `{}`",
+                        node_name,
+                        param
+                    );
+                    connection.node = format!("{}{}", PARAM_PREFIX, param);
+                    connection.output = "value".to_string();
+                }
+                None => ensure!(
+                    !connection.node.is_empty() && !connection.output.is_empty(),
+                    "Node `{}`: connections need a `node` and an `output`, a `param` or a `track`",
+                    node_name
+                ),
+            }
+        }
+    }
+
+    for (name, value) in &config.params {
+        let node_name = format!("{}{}", PARAM_PREFIX, name);
+        ensure!(
+            !config.nodes.contains_key(&node_name),
+            "There's already a node named `{}`",
+            node_name
+        );
+        config.nodes.insert(
+            node_name,
+            Node {
+                priority: None,
+                config: NodeConfig::Constant(value.constant()),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Overrides a parameter of an expanded config from a `name=value` assignment. Unknown
+/// parameters are ignored with a warning, since the same command line may be used with several
+/// configs.
+pub fn set(config: &mut Config, assignment: &str) -> Result<(), Error> {
+    let mut parts = assignment.splitn(2, '=');
+    let name = parts.next().unwrap().trim();
+    let text = match parts.next() {
+        Some(text) => text,
+        None => bail!("Expected `name=value` for `--param`, got `{}`", assignment),
+    };
+
+    let value = match config.params.get_mut(name) {
+        Some(value) => value,
+        None => {
+            warn!(
+                "Ignoring `--param {}`, since there's no parameter named `{}`",
+                assignment, name
+            );
+            return Ok(());
+        }
+    };
+    *value = value
+        .parse(text)
+        .map_err(|e| format_err!("Invalid value for parameter `{}`: {}", name, e))?;
+
+    if let Some(node) = config.nodes.get_mut(&format!("{}{}", PARAM_PREFIX, name)) {
+        node.config = NodeConfig::Constant(value.constant());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, set, ParamValue};
+    use crate::config::{
+        nodes::{ConstantConfig, NodeConfig},
+        Config,
+    };
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(
+            ParamValue::Scalar(1.0).parse("2.5").unwrap(),
+            ParamValue::Scalar(2.5)
+        );
+        assert_eq!(
+            ParamValue::Color([0.0; 4]).parse("1, 0.5, 0, 1").unwrap(),
+            ParamValue::Color([1.0, 0.5, 0.0, 1.0])
+        );
+        assert!(ParamValue::Scalar(1.0).parse("1,2").is_err());
+        assert!(ParamValue::Color([0.0; 4]).parse("red").is_err());
+    }
+
+    #[test]
+    fn connects_params() {
+        let source = "
+params:
+    radius: 4.0
+nodes:
+    blur:
+        type: blur
+        texture:
+            node: image
+            output: texture
+        radius:
+            param: radius
+";
+        let mut config: Config = serde_yaml::from_str(source).unwrap();
+        expand(&mut config).unwrap();
+        set(&mut config, "radius=8").unwrap();
+
+        let connections = config.nodes["blur"].config.connections();
+        let connection = connections[1].1;
+        assert_eq!(connection.node, "params/radius");
+        assert_eq!(connection.output, "value");
+        match config.nodes["params/radius"].config {
+            NodeConfig::Constant(ConstantConfig::Float(value)) => assert_eq!(value, 8.0),
+            ref other => panic!("Unexpected node: {:?}", other),
+        }
+
+        assert!(set(&mut config, "radius").is_err());
+        assert!(set(&mut config, "missing=1").is_ok());
+    }
+}
@@ -0,0 +1,45 @@
+//! Configuration related to recording animations of the output
+
+use serde_derive::Deserialize;
+
+/// Recording configuration. Recordings are saved as animated GIFs in the capture directory.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RecordingConfig {
+    /// Frames per second to record at
+    #[serde(default = "default_framerate")]
+    pub framerate: f32,
+
+    /// Recording stops automatically after this many seconds
+    #[serde(default = "default_max_duration")]
+    pub max_duration: f32,
+
+    /// Size of the recording relative to the window
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            framerate: default_framerate(),
+            max_duration: default_max_duration(),
+            scale: default_scale(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `framerate` field
+fn default_framerate() -> f32 {
+    15.0
+}
+
+/// A function that returns the default value of the `max_duration` field
+fn default_max_duration() -> f32 {
+    30.0
+}
+
+/// A function that returns the default value of the `scale` field
+fn default_scale() -> f32 {
+    0.5
+}
@@ -0,0 +1,195 @@
+//! Limits for configs that weren't trusted with `--trust`. People run configs they find on the
+//! internet, so a config alone shouldn't be able to exhaust video memory, write files wherever it
+//! likes, or hang the GPU.
+
+use anyhow::{bail, ensure, Error};
+use log::warn;
+
+use super::{capture::CaptureConfig, nodes::NodeConfig, Config};
+
+/// Most nodes an untrusted config may have
+pub const MAX_NODES: usize = 256;
+
+/// Largest width or height of a texture an untrusted config may create
+pub const MAX_TEXTURE_SIZE: u32 = 8192;
+
+/// Largest font size an untrusted config may use, since glyphs are rasterized into textures
+pub const MAX_FONT_SIZE: f32 = 512.0;
+
+/// Highest GPU watchdog limit an untrusted config may set (milliseconds)
+pub const NODE_TIME_BUDGET: f32 = 1000.0;
+
+/// Checks an untrusted config against the limits, and turns off the options that would let it
+/// choose where files are written. Nodes that make network requests aren't allowed at all, and
+/// neither are plugins, since they run native code.
+pub fn restrict(config: &mut Config) -> Result<(), Error> {
+    if let Some(plugin) = config.plugins.first() {
+        bail!(
+            "Plugin `{}` can't be loaded by an untrusted config (unless `--trust` is given)",
+            plugin.display()
+        );
+    }
+
+    ensure!(
+        config.nodes.len() <= MAX_NODES,
+        "Too many nodes: {} (the limit is {} unless `--trust` is given)",
+        config.nodes.len(),
+        MAX_NODES
+    );
+
+    let check_size = |what: &str, width: u32, height: u32| -> Result<(), Error> {
+        ensure!(
+            width <= MAX_TEXTURE_SIZE && height <= MAX_TEXTURE_SIZE,
+            "{} is too large: {}x{} (the limit is {} pixels unless `--trust` is given)",
+            what,
+            width,
+            height,
+            MAX_TEXTURE_SIZE
+        );
+        Ok(())
+    };
+    check_size("Window", config.width, config.height)?;
+    check_size(
+        "Supersampled window",
+        config.width.saturating_mul(config.supersample),
+        config.height.saturating_mul(config.supersample),
+    )?;
+    let factor = config.capture.supersample;
+    check_size(
+        "Supersampled capture",
+        config.width.saturating_mul(factor),
+        config.height.saturating_mul(factor),
+    )?;
+
+    for (name, node) in &config.nodes {
+        let font_size = match node.config {
+            NodeConfig::Image(ref image_config) => {
+                // Images that can't be read are reported by the node itself
+                if let Ok((width, height)) =
+                    image::image_dimensions(config.path_to(&image_config.path))
+                {
+                    check_size(&format!("Image on node `{}`", name), width, height)?;
+                }
+                continue;
+            }
+            NodeConfig::Http(ref http_config) => {
+                bail!(
+                    "Node `{}` requests `{}`, which untrusted configs can't do (unless `--trust` \
+                     is given)",
+                    name,
+                    http_config.url
+                );
+            }
+            NodeConfig::Link(_) => {
+                bail!(
+                    "Node `{}` joins an Ableton Link session on the network, which untrusted \
+                     configs can't do (unless `--trust` is given)",
+                    name
+                );
+            }
+            NodeConfig::Text(ref text_config) => text_config.font_size,
+            NodeConfig::Fps(ref fps_config) => fps_config.font_size,
+            _ => continue,
+        };
+        ensure!(
+            font_size <= MAX_FONT_SIZE,
+            "Font size on node `{}` is too large: {} (the limit is {} unless `--trust` is given)",
+            name,
+            font_size,
+            MAX_FONT_SIZE
+        );
+    }
+
+    let defaults = CaptureConfig::default();
+    if config.capture.directory != defaults.directory {
+        warn!(
+            "Ignoring capture directory `{}` from an untrusted config",
+            config.capture.directory.display()
+        );
+        config.capture.directory = defaults.directory;
+    }
+    let plain_name = |instance: &str| {
+        !instance.is_empty() && instance != ".." && !instance.contains(|c| c == '/' || c == '\\')
+    };
+    if let Some(instance) = config.capture.instance.take() {
+        if plain_name(&instance) {
+            config.capture.instance = Some(instance);
+        } else {
+            warn!(
+                "Ignoring capture instance name `{}` from an untrusted config",
+                instance
+            );
+        }
+    }
+    if let Some(directory) = config.diagnostics.directory.take() {
+        warn!(
+            "Ignoring diagnostics directory `{}` from an untrusted config",
+            directory.display()
+        );
+    }
+    if let Some(file) = config.stats.file.take() {
+        warn!(
+            "Ignoring stats file `{}` from an untrusted config",
+            file.display()
+        );
+    }
+    if let Some(socket) = config.control_socket.take() {
+        warn!(
+            "Ignoring control socket `{}` from an untrusted config",
+            socket.display()
+        );
+    }
+    if let Some(webcam) = config.webcam.take() {
+        warn!(
+            "Ignoring webcam device `{}` from an untrusted config",
+            webcam.device.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restrict, MAX_NODES};
+    use crate::config::{capture::CaptureConfig, Config};
+
+    #[test]
+    fn restricts_untrusted_configs() {
+        let source = "
+capture:
+    directory: /etc
+    instance: ../escape
+diagnostics:
+    directory: /tmp/anywhere
+stats:
+    file: /tmp/anywhere.jsonl
+webcam:
+    node: info
+    device: /tmp/anywhere
+nodes:
+    info:
+        type: info
+";
+        let mut config: Config = serde_yaml::from_str(source).unwrap();
+        restrict(&mut config).unwrap();
+        assert_eq!(config.capture.directory, CaptureConfig::default().directory);
+        assert_eq!(config.capture.instance, None);
+        assert_eq!(config.diagnostics.directory, None);
+        assert_eq!(config.stats.file, None);
+        assert_eq!(config.webcam, None);
+
+        config.width = 100_000;
+        assert!(restrict(&mut config).is_err());
+
+        let mut config: Config = serde_yaml::from_str("plugins: [libnodes.so]").unwrap();
+        assert!(restrict(&mut config).is_err());
+
+        let mut nodes = String::from("nodes:\n");
+        for i in 0..=MAX_NODES {
+            nodes.push_str(&format!("    info_{}:\n        type: info\n", i));
+        }
+        let mut config: Config = serde_yaml::from_str(&nodes).unwrap();
+        assert!(restrict(&mut config).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! Configuration related to logging frame statistics
+
+use serde_derive::Deserialize;
+use std::path::PathBuf;
+
+/// Frame statistics configuration. Stats are aggregated over each interval and either logged or
+/// written to a file as JSON lines.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Whether to log the stats
+    #[serde(default)]
+    pub log: bool,
+
+    /// File to append the stats to as JSON lines, relative to the current working directory
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+
+    /// How often the stats are aggregated (seconds)
+    #[serde(default = "default_interval")]
+    pub interval: f32,
+}
+
+impl StatsConfig {
+    /// Whether stats are collected at all
+    pub fn enabled(&self) -> bool {
+        self.log || self.file.is_some()
+    }
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            log: Default::default(),
+            file: Default::default(),
+            interval: default_interval(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `interval` field
+fn default_interval() -> f32 {
+    1.0
+}
@@ -0,0 +1,355 @@
+//! Configuration related to sequencing scenes into a timeline
+//!
+//! Each scene is either a node in this config, or a separate config file whose nodes are merged
+//! into this one under the index of the scene and the name of the file (e.g. `0-intro/background`
+//! for the `background` node of `intro.yml` in the first scene). A file used by several scenes is
+//! only merged once, under the first of them. The timeline then appears as a node named
+//! `timeline`, whose `texture` output shows the current scene.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use serde_derive::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    group,
+    nodes::{NodeConfig, NodeConnection, OutputConfig},
+    Config,
+};
+
+/// Name of the node the timeline is rendered by
+pub const TIMELINE_NODE: &str = "timeline";
+
+/// Timeline configuration
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TimelineConfig {
+    /// Scenes in the order they're shown
+    pub scenes: Vec<SceneConfig>,
+
+    /// How long to crossfade from each scene into the next (seconds)
+    #[serde(default)]
+    pub crossfade: f32,
+
+    /// Start over after the last scene, instead of staying on it
+    #[serde(default = "default_repeat")]
+    pub repeat: bool,
+}
+
+/// A scene in the timeline. Either `texture` or `config` must be given.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SceneConfig {
+    /// The node output showing the scene
+    #[serde(default)]
+    pub texture: Option<NodeConnection>,
+
+    /// A config file whose output shows the scene, relative to this config
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+
+    /// How long the scene is shown for (seconds)
+    pub duration: f32,
+}
+
+/// Where a timeline is at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelinePosition {
+    /// The scene being shown
+    pub scene: usize,
+    /// The scene being faded into, if any
+    pub next: Option<usize>,
+    /// How far the fade into the next scene has gone, from 0 to 1
+    pub mix: f32,
+}
+
+impl TimelineConfig {
+    /// Returns the time a scene starts at (seconds), if there's a scene with that index
+    pub fn scene_start(&self, index: usize) -> Option<f32> {
+        if index >= self.scenes.len() {
+            return None;
+        }
+        Some(
+            self.scenes[..index]
+                .iter()
+                .map(|scene| scene.duration.max(0.0))
+                .sum(),
+        )
+    }
+
+    /// Returns the index of the scene shown by a node, or loaded from a config file with this name
+    /// (without the extension)
+    pub fn scene_index(&self, name: &str) -> Option<usize> {
+        self.scenes.iter().position(|scene| {
+            scene
+                .texture
+                .as_ref()
+                .map_or(false, |texture| texture.node == name)
+                || scene
+                    .config
+                    .as_ref()
+                    .and_then(|config| config.file_stem())
+                    .map_or(false, |stem| stem == name)
+        })
+    }
+
+    /// Returns which scenes are shown at a time (seconds)
+    pub fn position(&self, time: f32) -> TimelinePosition {
+        let total: f32 = self
+            .scenes
+            .iter()
+            .map(|scene| scene.duration.max(0.0))
+            .sum();
+        if self.scenes.is_empty() || total <= 0.0 {
+            return TimelinePosition {
+                scene: 0,
+                next: None,
+                mix: 0.0,
+            };
+        }
+
+        let time = time.max(0.0);
+        let time = if self.repeat { time % total } else { time };
+
+        let mut start = 0.0;
+        for (index, scene) in self.scenes.iter().enumerate() {
+            let end = start + scene.duration.max(0.0);
+            let last = index + 1 == self.scenes.len();
+            if time < end || last {
+                let next = if !last {
+                    Some(index + 1)
+                } else if self.repeat {
+                    Some(0)
+                } else {
+                    None
+                };
+                let fade_start = end - self.crossfade.max(0.0).min(end - start);
+                let mix = if next.is_some() && time > fade_start {
+                    ((time - fade_start) / (end - fade_start)).min(1.0)
+                } else {
+                    0.0
+                };
+                return TimelinePosition {
+                    scene: index,
+                    next: if mix > 0.0 { next } else { None },
+                    mix,
+                };
+            }
+            start = end;
+        }
+
+        unreachable!()
+    }
+}
+
+/// Merges the graphs of scenes from other config files into a config, and adds the timeline node.
+/// If the config has no output node, one is added showing the timeline.
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    let mut timeline = match config.timeline.clone() {
+        Some(timeline) => timeline,
+        None => return Ok(()),
+    };
+
+    ensure!(!timeline.scenes.is_empty(), "The timeline has no scenes");
+    ensure!(
+        !config.nodes.contains_key(TIMELINE_NODE),
+        "The node name `{}` is used by the timeline",
+        TIMELINE_NODE
+    );
+
+    // The same file can be used for several scenes, but its nodes are only merged once
+    let mut merged: HashMap<PathBuf, NodeConnection> = HashMap::new();
+
+    for (index, scene) in timeline.scenes.iter_mut().enumerate() {
+        match (&scene.texture, &scene.config) {
+            (Some(_), None) => (),
+            (None, Some(path)) => {
+                let path = config.path_to(path);
+                if !merged.contains_key(&path) {
+                    let texture = load_scene(config, index, &path)
+                        .context(format!("Error in scene `{}`", path.display()))?;
+                    merged.insert(path.clone(), texture);
+                }
+                scene.texture = Some(merged[&path].clone());
+            }
+            _ => bail!("Each scene needs either `texture` or `config`"),
+        }
+    }
+
+    let has_output = config.nodes.values().any(|node| match node.config {
+        NodeConfig::Output(_) => true,
+        _ => false,
+    });
+    config.nodes.insert(
+        TIMELINE_NODE.to_string(),
+        super::nodes::Node {
+            priority: None,
+            config: NodeConfig::Timeline(timeline),
+        },
+    );
+    if !has_output {
+        ensure!(
+            !config.nodes.contains_key("output"),
+            "The timeline can't add an output node, since there's another node named `output`"
+        );
+        config.nodes.insert(
+            "output".to_string(),
+            super::nodes::Node {
+                priority: None,
+                config: NodeConfig::Output(OutputConfig {
+                    texture: NodeConnection {
+                        node: TIMELINE_NODE.to_string(),
+                        output: "texture".to_string(),
+                        name: String::new(),
+                        type_: Default::default(),
+                        param: None,
+                        track: None,
+                    },
+                }),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Loads the config file of the scene with an index, and adds its nodes to a config
+fn load_scene(config: &mut Config, index: usize, path: &Path) -> Result<NodeConnection, Error> {
+    let scene = group::load_guarded(path, Config::from_file)?;
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    merge_scene(config, &format!("{}-{}", index, stem), scene)
+}
+
+/// Adds the nodes of a scene's config to a config under a prefix, returning the connection its
+/// output node showed
+fn merge_scene(config: &mut Config, prefix: &str, scene: Config) -> Result<NodeConnection, Error> {
+    ensure!(
+        scene.timeline.is_none(),
+        "Scenes can't have timelines of their own"
+    );
+
+    let rename = |name: &str| format!("{}/{}", prefix, name);
+
+    let mut texture = None;
+    for (name, mut node) in scene.nodes {
+        for connection in node.config.connections_mut() {
+            connection.node = rename(&connection.node);
+        }
+
+        if let NodeConfig::Output(output_config) = node.config {
+            texture = Some(output_config.texture);
+            continue;
+        }
+        // Files are relative to the scene's config
+        node.config.resolve_paths(&scene._cwd);
+
+        let name = rename(&name);
+        ensure!(
+            !config.nodes.contains_key(&name),
+            "There's already a node named `{}`",
+            name
+        );
+        config.nodes.insert(name, node);
+    }
+
+    texture.ok_or_else(|| format_err!("The scene has no output node"))
+}
+
+/// A function that returns the default value of the `repeat` field
+fn default_repeat() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_scene, SceneConfig, TimelineConfig, TimelinePosition};
+    use crate::config::Config;
+    use std::{env, fs, process};
+
+    fn timeline(durations: &[f32], crossfade: f32, repeat: bool) -> TimelineConfig {
+        TimelineConfig {
+            scenes: durations
+                .iter()
+                .map(|&duration| SceneConfig {
+                    texture: None,
+                    config: None,
+                    duration,
+                })
+                .collect(),
+            crossfade,
+            repeat,
+        }
+    }
+
+    fn at(scene: usize, next: Option<usize>, mix: f32) -> TimelinePosition {
+        TimelinePosition { scene, next, mix }
+    }
+
+    #[test]
+    fn sequences_scenes() {
+        let cuts = timeline(&[2.0, 3.0], 0.0, false);
+        assert_eq!(cuts.position(0.0), at(0, None, 0.0));
+        assert_eq!(cuts.position(2.5), at(1, None, 0.0));
+        assert_eq!(cuts.position(100.0), at(1, None, 0.0));
+
+        let fades = timeline(&[2.0, 3.0], 1.0, true);
+        assert_eq!(fades.position(0.5), at(0, None, 0.0));
+        assert_eq!(fades.position(1.5), at(0, Some(1), 0.5));
+        assert_eq!(fades.position(4.5), at(1, Some(0), 0.5));
+        assert_eq!(fades.position(5.5), at(0, None, 0.0));
+    }
+
+    #[test]
+    fn finds_scene_starts() {
+        let scenes = timeline(&[2.0, 3.0, 1.0], 0.0, false);
+        assert_eq!(scenes.scene_start(0), Some(0.0));
+        assert_eq!(scenes.scene_start(2), Some(5.0));
+        assert_eq!(scenes.scene_start(3), None);
+    }
+
+    #[test]
+    fn keeps_scenes_with_the_same_file_name_apart() {
+        let scene = "
+nodes:
+    shader:
+        type: shader
+        fragment_source: 'void main() {}'
+    output:
+        type: output
+        texture:
+            node: shader
+            output: texture
+";
+        let scene: Config = serde_yaml::from_str(scene).unwrap();
+
+        let mut config = Config::default();
+        let first = merge_scene(&mut config, "0-intro", scene.clone()).unwrap();
+        let second = merge_scene(&mut config, "1-intro", scene).unwrap();
+        assert_eq!(first.node, "0-intro/shader");
+        assert_eq!(second.node, "1-intro/shader");
+        assert!(config.nodes.contains_key("0-intro/shader"));
+        assert!(config.nodes.contains_key("1-intro/shader"));
+    }
+
+    #[test]
+    fn refuses_scenes_that_include_each_other() {
+        let dir = env::temp_dir().join(format!("yotredash-timeline-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for &(name, other) in &[("a.yml", "b.yml"), ("b.yml", "a.yml")] {
+            let source = format!(
+                "timeline:\n    scenes:\n        - {{ config: {}, duration: 1.0 }}\n",
+                other
+            );
+            fs::write(dir.join(name), source).unwrap();
+        }
+
+        let result = Config::from_file(&dir.join("a.yml"));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Values that can be adjusted from the tweak panel while the graph is running: every parameter,
+//! and every shader uniform marked with a `// @tweak` comment.
+//!
+//! ```glsl
+//! uniform float speed; // @tweak 0.0 10.0 1.5
+//! uniform vec4 tint; // @tweak 1.0 0.5 0.0 1.0
+//! ```
+//!
+//! Floats take a minimum, a maximum and optionally a starting value (the minimum if it's left
+//! out). `vec4`s are colors, and optionally take a starting color. A marked uniform that isn't
+//! already connected to something becomes a constant node named `tweak/<node>/<uniform>`.
+
+use anyhow::{ensure, Error};
+use std::fs;
+
+use super::{
+    nodes::{ConstantConfig, InputType, Node, NodeConfig, NodeConnection},
+    params::{ParamValue, PARAM_PREFIX},
+    Config,
+};
+
+/// Prefix of the names of the nodes shader tweaks are read from
+pub const TWEAK_PREFIX: &str = "tweak/";
+
+/// A value that can be adjusted while running, which is the value of a constant node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tweak {
+    /// Name shown in the panel
+    pub label: String,
+    /// The constant node holding the value
+    pub node: String,
+    /// Smallest value of each component
+    pub min: f32,
+    /// Largest value of each component
+    pub max: f32,
+}
+
+/// A uniform marked with `// @tweak`, with its range and starting value
+#[derive(Debug, Clone)]
+struct Marked {
+    name: String,
+    min: f32,
+    max: f32,
+    value: ConstantConfig,
+}
+
+/// Parses a line declaring a uniform marked with `// @tweak`
+fn parse_line(line: &str) -> Option<Marked> {
+    let mut parts = line.splitn(2, "//");
+    let declaration = parts.next()?.trim().trim_end_matches(';');
+    let comment = parts.next()?.trim();
+    if !comment.starts_with("@tweak") {
+        return None;
+    }
+    let numbers = comment["@tweak".len()..]
+        .split_whitespace()
+        .map(|number| number.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let words: Vec<&str> = declaration.split_whitespace().collect();
+    let (type_, name) = match words[..] {
+        ["uniform", type_, name] => (type_, name),
+        _ => return None,
+    };
+
+    match (type_, &numbers[..]) {
+        ("float", &[min, max]) => Some((min, max, min)),
+        ("float", &[min, max, value]) => Some((min, max, value)),
+        _ => None,
+    }
+    .map(|(min, max, value)| Marked {
+        name: name.to_string(),
+        min,
+        max,
+        value: ConstantConfig::Float(value),
+    })
+    .or_else(|| {
+        match (type_, &numbers[..]) {
+            ("vec4", &[]) => Some([1.0; 4]),
+            ("vec4", &[r, g, b, a]) => Some([r, g, b, a]),
+            _ => None,
+        }
+        .map(|color| Marked {
+            name: name.to_string(),
+            min: 0.0,
+            max: 1.0,
+            value: ConstantConfig::Color(color),
+        })
+    })
+}
+
+/// Returns the range a parameter can be tweaked in. Colors go from 0 to 1, and numbers from 0 to
+/// twice their value, or at least to 1.
+fn param_range(value: ParamValue) -> (f32, f32) {
+    match value {
+        ParamValue::Scalar(value) => ((value * 2.0).min(0.0), (value * 2.0).max(1.0)),
+        ParamValue::Color(_) => (0.0, 1.0),
+    }
+}
+
+/// Collects the tweaks of an expanded config, adding constant nodes for the shader uniforms marked
+/// with `// @tweak`. Shaders that can't be read are skipped, since the shader node reports that.
+pub fn expand(config: &mut Config) -> Result<(), Error> {
+    let mut tweaks: Vec<Tweak> = config
+        .params
+        .iter()
+        .map(|(name, &value)| {
+            let (min, max) = param_range(value);
+            Tweak {
+                label: name.clone(),
+                node: format!("{}{}", PARAM_PREFIX, name),
+                min,
+                max,
+            }
+        })
+        .collect();
+
+    let mut constants = Vec::new();
+    for (node_name, node) in config.nodes.iter_mut() {
+        let shader_config = match node.config {
+            NodeConfig::Shader(ref mut shader_config) => shader_config,
+            _ => continue,
+        };
+
+        let mut sources: Vec<String> = shader_config
+            .sources()
+            .into_iter()
+            .filter_map(|path| fs::read_to_string(config._cwd.join(path)).ok())
+            .collect();
+        sources.extend(shader_config.fragment_source.iter().cloned());
+
+        let mut marked: Vec<Marked> = Vec::new();
+        for source in &sources {
+            for uniform in source.lines().filter_map(parse_line) {
+                if !marked.iter().any(|other| other.name == uniform.name) {
+                    marked.push(uniform);
+                }
+            }
+        }
+
+        for uniform in marked {
+            // Uniforms connected in the config keep their connection
+            if shader_config
+                .uniforms
+                .iter()
+                .any(|connection| connection.name == uniform.name)
+            {
+                continue;
+            }
+
+            let constant = format!("{}{}/{}", TWEAK_PREFIX, node_name, uniform.name);
+            shader_config.uniforms.push(NodeConnection {
+                node: constant.clone(),
+                output: "value".to_string(),
+                name: uniform.name.clone(),
+                type_: match uniform.value {
+                    ConstantConfig::Color(_) => InputType::Color,
+                    _ => InputType::Float,
+                },
+                param: None,
+                track: None,
+            });
+            tweaks.push(Tweak {
+                label: format!("{}.{}", node_name, uniform.name),
+                node: constant.clone(),
+                min: uniform.min,
+                max: uniform.max,
+            });
+            constants.push((constant, uniform.value));
+        }
+    }
+
+    for (name, value) in constants {
+        ensure!(
+            !config.nodes.contains_key(&name),
+            "There's already a node named `{}`",
+            name
+        );
+        config.nodes.insert(
+            name,
+            Node {
+                priority: None,
+                config: NodeConfig::Constant(value),
+            },
+        );
+    }
+    config.tweaks = tweaks;
+
+    Ok(())
+}
+
+/// Returns the current value of a tweak, as its components
+pub fn value(config: &Config, tweak: &Tweak) -> Vec<f32> {
+    match config.nodes.get(&tweak.node).map(|node| &node.config) {
+        Some(NodeConfig::Constant(ConstantConfig::Float(value))) => vec![*value],
+        Some(NodeConfig::Constant(ConstantConfig::Color(color))) => color.to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, Marked};
+    use crate::config::nodes::ConstantConfig;
+
+    #[test]
+    fn parses_marked_uniforms() {
+        match parse_line("uniform float speed; // @tweak 0.0 10.0 1.5") {
+            Some(Marked {
+                ref name,
+                min,
+                max,
+                value: ConstantConfig::Float(value),
+            }) => {
+                assert_eq!(name, "speed");
+                assert_eq!((min, max, value), (0.0, 10.0, 1.5));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_line("  uniform vec4 tint;  //@tweak 1 0.5 0 1") {
+            Some(Marked {
+                value: ConstantConfig::Color(color),
+                ..
+            }) => assert_eq!(color, [1.0, 0.5, 0.0, 1.0]),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_line("uniform float amount; // @tweak -1 1") {
+            Some(Marked {
+                value: ConstantConfig::Float(value),
+                ..
+            }) => assert_eq!(value, -1.0),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(parse_line("uniform float speed; // speed of the thing").is_none());
+        assert!(parse_line("uniform float speed;").is_none());
+        assert!(parse_line("uniform vec2 offset; // @tweak 0 1").is_none());
+        assert!(parse_line("uniform float speed; // @tweak fast").is_none());
+        assert!(parse_line("float speed = 1.0; // @tweak 0 1").is_none());
+    }
+}
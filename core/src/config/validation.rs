@@ -0,0 +1,598 @@
+//! Checks the node graph of a configuration before any nodes are built, so that mistakes are
+//! reported all at once instead of one at a time when rendering
+
+use anyhow::Error;
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+use super::{
+    nodes::{InputType, Node, NodeConfig},
+    Config,
+};
+
+/// Every problem found in a node graph, so embedders can show them separately
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid node graph:\n{}", .0.join("\n"))]
+pub struct InvalidGraph(pub Vec<String>);
+
+/// Finds the line on which a node is defined in the config source
+fn find_line(source: &str, name: &str) -> Option<usize> {
+    let lines: Vec<&str> = source.lines().collect();
+
+    // Tables can only be nodes in toml, wherever they are
+    let table = format!("[nodes.{}]", name);
+    if let Some(i) = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with(table.as_str()))
+    {
+        return Some(i + 1);
+    }
+
+    // Keys as they'd appear in yaml and json respectively
+    let keys = [format!("{}:", name), format!("\"{}\":", name)];
+
+    // Node names are keys in the `nodes` map, so skip everything before it to avoid matching
+    // top-level fields with the same name
+    let start = lines
+        .iter()
+        .position(|line| {
+            line.trim_start()
+                .trim_start_matches('"')
+                .starts_with("nodes")
+        })
+        .unwrap_or(0);
+
+    // Only keys as deeply indented as the map's first entry are node names, deeper ones are the
+    // nodes' own fields, and the map ends at the first line indented less
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let mut entries = lines
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .filter(|&(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .peekable();
+    let indent = indentation(entries.peek()?.1);
+
+    entries
+        .take_while(|&(_, line)| indentation(line) >= indent)
+        .find(|&(_, line)| {
+            indentation(line) == indent
+                && keys
+                    .iter()
+                    .any(|key| line.trim_start().starts_with(key.as_str()))
+        })
+        .map(|(i, _)| i + 1)
+}
+
+/// Searches the graph for a dependency cycle, returning the names of the nodes in the cycle in
+/// order, with the first node repeated at the end
+pub fn find_cycle(nodes: &IndexMap<String, Node>) -> Option<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    fn visit<'a>(
+        name: &'a str,
+        nodes: &'a IndexMap<String, Node>,
+        states: &mut HashMap<&'a str, State>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        match states.get(name).cloned().unwrap_or(State::Unvisited) {
+            State::Done => return None,
+            State::InProgress => {
+                let start = path.iter().position(|node| *node == name).unwrap();
+                let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+                cycle.push(name.to_string());
+                return Some(cycle);
+            }
+            State::Unvisited => (),
+        }
+
+        states.insert(name, State::InProgress);
+        path.push(name);
+
+        if let Some(node) = nodes.get(name) {
+            for dependency in node.config.dependencies() {
+                if let Some(cycle) = visit(dependency, nodes, states, path) {
+                    return Some(cycle);
+                }
+            }
+        }
+
+        path.pop();
+        states.insert(name, State::Done);
+        None
+    }
+
+    let mut states = HashMap::new();
+    for name in nodes.keys() {
+        if let Some(cycle) = visit(name, nodes, &mut states, &mut Vec::new()) {
+            return Some(cycle);
+        }
+    }
+
+    None
+}
+
+/// Checks that every connection refers to an existing node and output, and that the types of the
+/// connected output match what the input expects. `source` is the text the config was parsed from,
+/// and is used to point at the offending lines.
+pub fn validate(config: &Config, source: &str) -> Result<(), Error> {
+    let mut errors = Vec::new();
+
+    let outputs: HashMap<&str, Vec<(String, InputType)>> = config
+        .nodes
+        .iter()
+        .map(|(name, node)| (name.as_str(), node.config.outputs()))
+        .collect();
+
+    // Errors are reported in the order the nodes appear in the config
+    let names: Vec<&String> = config.nodes.keys().collect();
+
+    let location = |name: &str| match find_line(source, name) {
+        Some(line) => format!("Node `{}` (line {})", name, line),
+        None => format!("Node `{}`", name),
+    };
+
+    let output_nodes: Vec<&&String> = names
+        .iter()
+        .filter(|name| match config.nodes[name.as_str()].config {
+            NodeConfig::Output(_) => true,
+            _ => false,
+        })
+        .collect();
+    if output_nodes.is_empty() {
+        errors.push("No output node specified".to_string());
+    } else if output_nodes.len() > 1 {
+        errors.push(format!(
+            "There can only be one output node, found: `{}`",
+            output_nodes
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join("`, `")
+        ));
+    }
+
+    for name in names {
+        let node_config = &config.nodes[name].config;
+
+        for (input, connection, expected) in node_config.connections() {
+            let node_outputs = match outputs.get(connection.node.as_str()) {
+                Some(node_outputs) => node_outputs,
+                None => {
+                    errors.push(format!(
+                        "{}: input `{}` refers to nonexistent node `{}`",
+                        location(name),
+                        input,
+                        connection.node
+                    ));
+                    continue;
+                }
+            };
+
+            let actual = match node_outputs
+                .iter()
+                .find(|(output, _)| *output == connection.output)
+            {
+                Some((_, actual)) => actual,
+                None => {
+                    errors.push(format!(
+                        "{}: input `{}` refers to nonexistent output `{}` on node `{}` (available: {})",
+                        location(name),
+                        input,
+                        connection.output,
+                        connection.node,
+                        node_outputs
+                            .iter()
+                            .map(|(output, _)| format!("`{}`", output))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                    continue;
+                }
+            };
+
+            // Feedback outputs take the type declared on the feedback input, so `Any` here means
+            // the type was never declared, which is already an error on the feedback node
+            if *actual == InputType::Any {
+                continue;
+            }
+
+            if connection.type_ != InputType::Any && connection.type_ != *actual {
+                errors.push(format!(
+                    "{}: input `{}` is declared as {} but `{}.{}` is {}",
+                    location(name),
+                    input,
+                    connection.type_,
+                    connection.node,
+                    connection.output,
+                    actual
+                ));
+            } else if expected != InputType::Any && expected != *actual {
+                errors.push(format!(
+                    "{}: input `{}` expects {} but `{}.{}` is {}",
+                    location(name),
+                    input,
+                    expected,
+                    connection.node,
+                    connection.output,
+                    actual
+                ));
+            }
+
+            if let NodeConfig::Shader(_) = *node_config {
+                if connection.name.is_empty() {
+                    errors.push(format!(
+                        "{}: connections for shader nodes must have a name",
+                        location(name)
+                    ));
+                }
+                if *actual == InputType::Text {
+                    errors.push(format!(
+                        "{}: text output `{}.{}` can't be used as a uniform",
+                        location(name),
+                        connection.node,
+                        connection.output
+                    ));
+                }
+            }
+
+            if let NodeConfig::Plugin(_) | NodeConfig::Script(_) = *node_config {
+                if connection.name.is_empty() {
+                    errors.push(format!(
+                        "{}: connections for {} nodes must have a name",
+                        location(name),
+                        node_config.type_name()
+                    ));
+                }
+                if let InputType::Texture2d | InputType::Texture1d = *actual {
+                    errors.push(format!(
+                        "{}: texture output `{}.{}` can't be passed to a {} node",
+                        location(name),
+                        connection.node,
+                        connection.output,
+                        node_config.type_name()
+                    ));
+                }
+            }
+        }
+
+        if let NodeConfig::Blend(ref blend_config) = *node_config {
+            if blend_config.textures.is_empty() {
+                errors.push(format!(
+                    "{}: blend nodes need at least one texture",
+                    location(name)
+                ));
+            }
+        }
+
+        if let NodeConfig::Feedback(ref feedback_config) = *node_config {
+            for connection in &feedback_config.inputs {
+                if connection.type_ == InputType::Any {
+                    errors.push(format!(
+                        "{}: must specify `type` for input `{}`",
+                        location(name),
+                        connection.name
+                    ));
+                }
+            }
+        }
+
+        if let NodeConfig::Shader(ref shader_config) = *node_config {
+            match (&shader_config.fragment, &shader_config.fragment_source) {
+                (Some(_), Some(_)) => errors.push(format!(
+                    "{}: shader nodes can't have both `fragment` and `fragment_source`",
+                    location(name)
+                )),
+                (None, None) => errors.push(format!(
+                    "{}: shader nodes need `fragment` or `fragment_source`",
+                    location(name)
+                )),
+                _ => (),
+            }
+        }
+
+        if let NodeConfig::Plugin(ref plugin_config) = *node_config {
+            for (output, type_) in &plugin_config.outputs {
+                if let InputType::Texture2d | InputType::Texture1d = *type_ {
+                    errors.push(format!(
+                        "{}: plugins can't output textures, but `{}` is {}",
+                        location(name),
+                        output,
+                        type_
+                    ));
+                }
+            }
+        }
+
+        if let NodeConfig::Script(ref script_config) = *node_config {
+            for (output, type_) in &script_config.outputs {
+                match *type_ {
+                    InputType::Float | InputType::Float2 | InputType::Color | InputType::Text => (),
+                    _ => errors.push(format!(
+                        "{}: scripts can only output floats, float2s, colors and text, but `{}` \
+                         is {}",
+                        location(name),
+                        output,
+                        type_
+                    )),
+                }
+            }
+            for connection in &script_config.inputs {
+                if script_config.outputs.contains_key(&connection.name) {
+                    errors.push(format!(
+                        "{}: input `{}` has the same name as an output",
+                        location(name),
+                        connection.name
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(cycle) = find_cycle(&config.nodes) {
+        errors.push(format!(
+            "Dependency cycle detected: `{}`",
+            cycle.join("` -> `")
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(InvalidGraph(errors).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_cycle, find_line, validate, InvalidGraph};
+    use crate::config::{Config, ConfigFormat};
+
+    /// A config using most kinds of fields, for mutating
+    const VALID: &str = "
+width: 640
+height: 480
+keybindings:
+    Ctrl+R: reload
+nodes:
+    output:
+        type: output
+        texture:
+            node: blend
+            output: texture
+    blend:
+        type: blend
+        operation: add
+        textures:
+            - node: image
+              output: texture
+            - node: feedback
+              output: texture
+    feedback:
+        type: feedback
+        inputs:
+            - node: blend
+              output: texture
+              name: texture
+              type: texture2d
+    image:
+        type: image
+        path: image.png
+";
+
+    #[test]
+    fn reports_all_errors() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: info
+            output: resolution
+    text:
+        type: text
+        text:
+            node: missing
+            output: text
+    shader:
+        type: shader
+    info:
+        type: info
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let error = validate(&config, source).unwrap_err().to_string();
+
+        assert!(error.contains("Node `output` (line 3): input `texture` expects texture2d"));
+        assert!(error.contains("Node `text` (line 8): input `text` refers to nonexistent node"));
+        assert!(error.contains(
+            "Node `shader` (line 13): shader nodes need `fragment` or `fragment_source`"
+        ));
+    }
+
+    #[test]
+    fn validate_str_reports_every_problem() {
+        assert!(Config::validate_str(VALID, ConfigFormat::Yaml).is_ok());
+        let source = format!("capture:\n    supersample: 0{}", VALID);
+        assert!(Config::validate_str(&source, ConfigFormat::Yaml).is_err());
+
+        let source = "
+nodes:
+    a:
+        type: blend
+        operation: add
+        textures: []
+    b:
+        type: blend
+        operation: add
+        textures:
+            - node: c
+              output: texture
+    c:
+        type: blend
+        operation: add
+        textures:
+            - node: b
+              output: texture
+";
+        let error = Config::validate_str(source, ConfigFormat::Yaml).unwrap_err();
+        assert_eq!(error.downcast_ref::<InvalidGraph>().unwrap().0.len(), 3);
+        let error = error.to_string();
+        assert!(error.contains("No output node specified"));
+        assert!(error.contains("Node `a` (line 3): blend nodes need at least one texture"));
+        assert!(error.contains("Dependency cycle detected: `b` -> `c` -> `b`"));
+    }
+
+    #[test]
+    fn mutated_configs_never_panic() {
+        // A small xorshift generator, so failures can be reproduced from the iteration
+        let mut state: u32 = 0x9e37_79b9;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize % bound.max(1)
+        };
+
+        let pieces = [
+            "",
+            " ",
+            "\n",
+            ":",
+            "- ",
+            "[]",
+            "{}",
+            "\"",
+            "type: ",
+            "node: ",
+            "output",
+            "-1",
+            "nan",
+            "1e40",
+            "texture2d",
+            "output: texture",
+            "nodes:",
+            "\t",
+            "&a",
+            "*a",
+            "~",
+        ];
+        for _ in 0..2000 {
+            let mut source = VALID.to_string();
+            for _ in 0..random(4) + 1 {
+                let start = random(source.len());
+                let end = (start + random(16)).min(source.len());
+                if source.is_char_boundary(start) && source.is_char_boundary(end) {
+                    source.replace_range(start..end, pieces[random(pieces.len())]);
+                }
+            }
+            for &format in &[ConfigFormat::Yaml, ConfigFormat::Json, ConfigFormat::Toml] {
+                let _ = Config::validate_str(&source, format);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: a
+            output: texture
+    a:
+        type: blend
+        operation: add
+        textures:
+            - node: b
+              output: texture
+    b:
+        type: blend
+        operation: add
+        textures:
+            - node: a
+              output: texture
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(
+            find_cycle(&config.nodes),
+            Some(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+    }
+
+    #[test]
+    fn accepts_valid_graph() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: image
+            output: texture
+    image:
+        type: image
+        path: image.png
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert!(validate(&config, source).is_ok());
+    }
+
+    #[test]
+    fn finds_lines_in_all_formats() {
+        let json = r#"{
+    "nodes": {
+        "output": {
+            "type": "output",
+            "texture": { "node": "image", "output": "texture" }
+        },
+        "image": { "type": "image", "path": "image.png" }
+    }
+}"#;
+        let toml = r#"
+[nodes.output]
+type = "output"
+texture = { node = "image", output = "texture" }
+
+[nodes.image]
+type = "image"
+path = "image.png"
+"#;
+
+        for &(format, source, line) in
+            &[(ConfigFormat::Json, json, 7), (ConfigFormat::Toml, toml, 6)]
+        {
+            let config = format.parse(source).unwrap();
+            assert!(validate(&config, source).is_ok());
+            assert_eq!(find_line(source, "image"), Some(line));
+        }
+    }
+
+    #[test]
+    fn finds_node_lines_not_fields() {
+        let yaml = r#"
+nodes:
+  main:
+    type: output
+    texture:
+      node: output
+      output: texture
+
+  output:
+    type: image
+    path: image.png
+"#;
+
+        let config = ConfigFormat::Yaml.parse(yaml).unwrap();
+        assert!(validate(&config, yaml).is_ok());
+        assert_eq!(find_line(yaml, "main"), Some(3));
+        assert_eq!(find_line(yaml, "output"), Some(9));
+    }
+}
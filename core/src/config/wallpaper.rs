@@ -0,0 +1,42 @@
+//! Configuration related to wallpaper mode
+
+use serde_derive::Deserialize;
+use std::{collections::HashMap, path::PathBuf};
+
+/// Wallpaper mode configuration
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WallpaperConfig {
+    /// Whether or not to draw a wallpaper on every monitor
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Whether or not to use a single window spanning every monitor, instead of one per monitor
+    #[serde(default = "default_span")]
+    pub span: bool,
+
+    /// Config files to use for specific monitors, keyed by monitor name or index. Monitors which
+    /// aren't listed use the main config
+    #[serde(default)]
+    pub monitors: HashMap<String, PathBuf>,
+}
+
+impl Default for WallpaperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            span: default_span(),
+            monitors: Default::default(),
+        }
+    }
+}
+
+/// A function that returns the default value of the `enabled` field
+fn default_enabled() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `span` field
+fn default_span() -> bool {
+    false
+}
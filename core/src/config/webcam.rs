@@ -0,0 +1,66 @@
+//! Configuration related to writing the output to a virtual webcam
+
+use serde_derive::Deserialize;
+use std::path::PathBuf;
+
+/// Pixel formats frames can be written to the webcam in
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebcamFormat {
+    /// Packed 4:2:2 YUV, which almost everything reading webcams supports
+    Yuyv,
+    /// Planar 4:2:0 YUV, also known as I420
+    Yu12,
+    /// 24-bit RGB
+    Rgb24,
+}
+
+impl WebcamFormat {
+    /// Returns the V4L2 fourcc code for this format
+    pub fn fourcc(self) -> u32 {
+        let code = match self {
+            WebcamFormat::Yuyv => b"YUYV",
+            WebcamFormat::Yu12 => b"YU12",
+            WebcamFormat::Rgb24 => b"RGB3",
+        };
+        code.iter()
+            .rev()
+            .fold(0, |fourcc, &byte| fourcc << 8 | u32::from(byte))
+    }
+}
+
+/// Virtual webcam configuration. Frames read back by a readback node are written to a
+/// v4l2loopback device, so they can be used in video calls and OBS. Only supported on Linux.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WebcamConfig {
+    /// Name of the readback node to take frames from
+    pub node: String,
+
+    /// The v4l2loopback device to write to
+    #[serde(default = "default_device")]
+    pub device: PathBuf,
+
+    /// Pixel format to write frames in
+    #[serde(default = "default_format")]
+    pub format: WebcamFormat,
+
+    /// Most frames per second to write
+    #[serde(default = "default_fps")]
+    pub fps: f32,
+}
+
+/// A function that returns the default value of the `device` field
+fn default_device() -> PathBuf {
+    PathBuf::from("/dev/video0")
+}
+
+/// A function that returns the default value of the `format` field
+fn default_format() -> WebcamFormat {
+    WebcamFormat::Yuyv
+}
+
+/// A function that returns the default value of the `fps` field
+fn default_fps() -> f32 {
+    30.0
+}
@@ -0,0 +1,335 @@
+//! A control socket that lets scripts and window manager key bindings drive a running instance
+//!
+//! Clients connect to a unix domain socket and send one command per line. Each line is answered
+//! with `ok` (followed by a space and the result for commands that have one), or with `error: `
+//! and a description if the command couldn't be parsed. The commands are:
+//!
+//! - `status`, which answers with the state of each window
+//! - `pause` and `resume`
+//! - `reload`
+//! - `capture <path>`, which captures an image to a file
+//! - `set-uniform <name> <values...>`, which sets the value of the constant node with that name
+//! - `switch-scene <scene>`, which jumps to the start of a timeline scene, given its index, the
+//!   node showing it, or the name of its config file
+//!
+//! Named pipes aren't supported yet, so there's no control socket on Windows.
+//!
+//! `yotredash ctl` sends these commands from the command line, to the socket at `default_path`
+//! unless another is given.
+
+use anyhow::{bail, ensure, format_err, Error};
+use clap::ArgMatches;
+use std::{env, path::PathBuf};
+
+use crate::event::{Event, SceneId};
+
+/// A command received over the control socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Describe the state of each window
+    Status,
+    /// Pause rendering
+    Pause,
+    /// Resume rendering
+    Resume,
+    /// Reload the configuration
+    Reload,
+    /// Capture an image to this file
+    Capture(PathBuf),
+    /// Set the value of a constant node
+    SetUniform(String, Vec<f32>),
+    /// Jump to the start of a timeline scene
+    SwitchScene(String),
+}
+
+/// Returns where the control socket is when no path is given: `yotredash.sock` in the runtime
+/// directory, or in the temporary directory if there isn't one. `YOTREDASH_SOCKET` overrides it.
+pub fn default_path() -> PathBuf {
+    if let Some(path) = env::var_os("YOTREDASH_SOCKET") {
+        return PathBuf::from(path);
+    }
+    dirs::runtime_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("yotredash.sock")
+}
+
+impl Command {
+    /// Returns the event that carries out this command, if it's carried out by one
+    pub fn into_event(self) -> Option<Event> {
+        Some(match self {
+            Command::Status => return None,
+            Command::Pause => Event::Pause,
+            Command::Resume => Event::Resume,
+            Command::Reload => Event::Reload,
+            Command::Capture(path) => Event::CaptureTo(path),
+            Command::SetUniform(name, values) => Event::SetUniform(name, values),
+            Command::SwitchScene(scene) => Event::SwitchScene(match scene.parse() {
+                Ok(index) => SceneId::Index(index),
+                Err(_) => SceneId::Name(scene),
+            }),
+        })
+    }
+}
+
+/// Parses a line sent to the control socket
+pub fn parse(line: &str) -> Result<Command, Error> {
+    let line = line.trim();
+    let (command, rest) = match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim()),
+        None => (line, ""),
+    };
+
+    let no_arguments = |command: Command| -> Result<Command, Error> {
+        ensure!(rest.is_empty(), "`{}` doesn't take any arguments", line);
+        Ok(command)
+    };
+
+    match command {
+        "status" => no_arguments(Command::Status),
+        "pause" => no_arguments(Command::Pause),
+        "resume" => no_arguments(Command::Resume),
+        "reload" => no_arguments(Command::Reload),
+        "capture" => {
+            ensure!(!rest.is_empty(), "`capture` needs a path");
+            Ok(Command::Capture(PathBuf::from(rest)))
+        }
+        "set-uniform" => {
+            let mut words = rest.split_whitespace();
+            let name = words
+                .next()
+                .ok_or_else(|| format_err!("`set-uniform` needs a name and a value"))?;
+            let values = words
+                .map(|word| {
+                    word.parse::<f32>()
+                        .map_err(|_| format_err!("`{}` isn't a number", word))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            ensure!(!values.is_empty(), "`set-uniform` needs a value");
+            Ok(Command::SetUniform(name.to_string(), values))
+        }
+        "switch-scene" => {
+            ensure!(!rest.is_empty(), "`switch-scene` needs a scene");
+            Ok(Command::SwitchScene(rest.to_string()))
+        }
+        "" => bail!("Empty command"),
+        _ => bail!("Unknown command `{}`", command),
+    }
+}
+
+/// Runs `yotredash ctl`, sending a command to a running instance and printing the answer
+pub fn ctl(args: &ArgMatches<'_>) -> Result<(), Error> {
+    let socket = args
+        .value_of_os("socket")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_path);
+
+    let command = match args.subcommand() {
+        ("status", _) => "status".to_string(),
+        ("pause", _) => "pause".to_string(),
+        ("resume", _) => "resume".to_string(),
+        ("reload", _) => "reload".to_string(),
+        ("capture", Some(args)) => {
+            // The instance could be running somewhere else, so relative paths are resolved here
+            let path = env::current_dir()?.join(args.value_of_os("path").unwrap());
+            format!("capture {}", path.display())
+        }
+        ("set", Some(args)) => format!(
+            "set-uniform {} {}",
+            args.value_of("name").unwrap(),
+            args.values_of("values")
+                .unwrap()
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        ("scene", Some(args)) => format!("switch-scene {}", args.value_of("scene").unwrap()),
+        _ => bail!("No command given"),
+    };
+
+    send_command(&socket, &command)
+}
+
+#[cfg(unix)]
+fn send_command(socket: &std::path::Path, command: &str) -> Result<(), Error> {
+    let answer = send(socket, command)?;
+    if !answer.is_empty() {
+        println!("{}", answer);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_command(_socket: &std::path::Path, _command: &str) -> Result<(), Error> {
+    bail!("Control sockets aren't supported on this platform")
+}
+
+#[cfg(unix)]
+pub use self::unix::{send, ControlSocket, Request};
+
+#[cfg(unix)]
+mod unix {
+    use anyhow::{bail, Context, Error};
+    use log::{info, warn};
+    use std::{
+        fs,
+        io::{prelude::*, BufReader},
+        os::unix::net::{UnixListener, UnixStream},
+        path::{Path, PathBuf},
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
+        time::Duration,
+    };
+
+    use super::{parse, Command};
+
+    /// How long a connection waits for the main loop to answer a command
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A command waiting to be answered
+    pub struct Request {
+        /// The command
+        pub command: Command,
+        /// Sends the answer back to the connection
+        reply: Sender<String>,
+    }
+
+    impl Request {
+        /// Answer the command with some text, which may be empty
+        pub fn reply(self, text: String) {
+            // The client may have hung up already
+            let _ = self.reply.send(text);
+        }
+    }
+
+    /// Answers the commands on a connection until it's closed
+    fn serve(stream: UnixStream, sender: &Sender<Request>) -> Result<(), Error> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            match parse(&line?) {
+                Ok(command) => {
+                    let (reply, receiver) = mpsc::channel();
+                    if sender.send(Request { command, reply }).is_err() {
+                        // We're shutting down
+                        break;
+                    }
+                    match receiver.recv_timeout(REPLY_TIMEOUT) {
+                        Ok(ref text) if text.is_empty() => writeln!(writer, "ok")?,
+                        Ok(text) => writeln!(writer, "ok {}", text)?,
+                        Err(_) => writeln!(writer, "error: no answer from the main loop")?,
+                    }
+                }
+                Err(e) => writeln!(writer, "error: {}", e)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a command to the control socket at a path, returning the text it was answered with
+    pub fn send(path: &Path, command: &str) -> Result<String, Error> {
+        let stream = UnixStream::connect(path).context(format!(
+            "Could not connect to {}, is yotredash running with `--control-socket`?",
+            path.display()
+        ))?;
+        let mut writer = stream.try_clone()?;
+        writeln!(writer, "{}", command)?;
+
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer)?;
+        let answer = answer.trim_end();
+        if answer == "ok" {
+            Ok(String::new())
+        } else if answer.starts_with("ok ") {
+            Ok(answer["ok ".len()..].to_string())
+        } else if answer.starts_with("error: ") {
+            bail!("{}", &answer["error: ".len()..])
+        } else {
+            bail!("Unexpected answer: `{}`", answer)
+        }
+    }
+
+    /// A listening control socket. The socket file is removed when this is dropped.
+    pub struct ControlSocket {
+        /// Where the socket is
+        path: PathBuf,
+        /// Receives the commands from every connection
+        receiver: Receiver<Request>,
+    }
+
+    impl ControlSocket {
+        /// Listen on a socket at a path, replacing a socket left behind by an earlier run
+        pub fn bind(path: &Path) -> Result<Self, Error> {
+            if path.exists() && UnixStream::connect(path).is_err() {
+                fs::remove_file(path).context("Could not remove stale control socket")?;
+            }
+            let listener = UnixListener::bind(path).context(format!(
+                "Could not create control socket at {}",
+                path.display()
+            ))?;
+            info!("Listening for commands on {}", path.display());
+
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            warn!("Control socket connection failed: {}", e);
+                            continue;
+                        }
+                    };
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = serve(stream, &sender) {
+                            warn!("Control socket connection failed: {}", e);
+                        }
+                    });
+                }
+            });
+
+            Ok(Self {
+                path: path.to_path_buf(),
+                receiver,
+            })
+        }
+
+        /// Returns the next command that was received, if there is one. It has to be answered.
+        pub fn try_recv(&self) -> Option<Request> {
+            self.receiver.try_recv().ok()
+        }
+    }
+
+    impl Drop for ControlSocket {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Command};
+    use std::path::PathBuf;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("status").unwrap(), Command::Status);
+        assert_eq!(parse("pause\n").unwrap(), Command::Pause);
+        assert_eq!(
+            parse("capture /tmp/my shot.png").unwrap(),
+            Command::Capture(PathBuf::from("/tmp/my shot.png"))
+        );
+        assert_eq!(
+            parse("set-uniform tint 1 0.5 0 1").unwrap(),
+            Command::SetUniform("tint".to_string(), vec![1.0, 0.5, 0.0, 1.0])
+        );
+        assert_eq!(
+            parse("switch-scene intro").unwrap(),
+            Command::SwitchScene("intro".to_string())
+        );
+
+        assert!(parse("").is_err());
+        assert!(parse("pause now").is_err());
+        assert!(parse("set-uniform tint").is_err());
+        assert!(parse("set-uniform tint red").is_err());
+        assert!(parse("explode").is_err());
+    }
+}
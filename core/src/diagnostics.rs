@@ -0,0 +1,122 @@
+//! Saves a bundle of everything needed to diagnose an error: the error text, the config, and the
+//! shader sources as they were when the error happened. Errors during a live show are often gone by
+//! the next reload, so this lets them be debugged afterwards.
+
+use anyhow::{Context, Error};
+use log::{info, warn};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use time::{self, Tm};
+
+use crate::{
+    config::{nodes::NodeConfig, Config},
+    util::format_error,
+};
+
+/// Saves diagnostics bundles, at most once per configured interval
+#[derive(Default)]
+pub struct Diagnostics {
+    /// When the last bundle was saved
+    last_save: Option<Tm>,
+}
+
+impl Diagnostics {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Save a bundle for an error, unless diagnostics are disabled or a bundle was saved recently.
+    /// Failures are logged instead of returned, since we're already handling an error.
+    pub fn save(&mut self, error: &Error, config_path: &Path, config: &Config) {
+        let directory = match config.diagnostics.directory {
+            Some(ref directory) => directory,
+            None => return,
+        };
+
+        if let Some(last_save) = self.last_save {
+            let elapsed = (time::now() - last_save).num_milliseconds() as f32 / 1_000.0;
+            if elapsed < config.diagnostics.interval {
+                return;
+            }
+        }
+        self.last_save = Some(time::now());
+
+        match write_bundle(directory, error, config_path, config) {
+            Ok(bundle) => info!("Saved diagnostics to {}", bundle.to_string_lossy()),
+            Err(e) => warn!("Could not save diagnostics: {}", format_error(&e)),
+        }
+    }
+}
+
+/// Creates a new, uniquely named bundle directory
+fn create_bundle_dir(directory: &Path, config: &Config) -> Result<PathBuf, Error> {
+    fs::create_dir_all(directory).context("Could not create diagnostics directory")?;
+
+    let stem = format!(
+        "{}_{}",
+        time::now().strftime("%Y-%m-%d_%H-%M-%S")?,
+        config.capture.instance()
+    );
+    let mut bundle = directory.join(&stem);
+    let mut suffix = 1;
+    loop {
+        match fs::create_dir(&bundle) {
+            Ok(()) => return Ok(bundle),
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                bundle = directory.join(format!("{}_{}", stem, suffix));
+                suffix += 1;
+            }
+            Err(e) => return Err(e.context("Could not create diagnostics bundle").into()),
+        }
+    }
+}
+
+/// Copies a file into the bundle, keeping its path relative to the config
+fn copy_into(bundle: &Path, path: &Path, config: &Config) -> Result<(), Error> {
+    let relative = path
+        .strip_prefix(&config._cwd)
+        .ok()
+        .or_else(|| path.file_name().map(Path::new))
+        .unwrap_or(path);
+    let target = bundle.join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(path, &target)
+        .with_context(|| format!("Could not copy {}", path.to_string_lossy()))?;
+    Ok(())
+}
+
+/// Writes a bundle, returning its path
+fn write_bundle(
+    directory: &Path,
+    error: &Error,
+    config_path: &Path,
+    config: &Config,
+) -> Result<PathBuf, Error> {
+    let bundle = create_bundle_dir(directory, config)?;
+
+    fs::write(bundle.join("error.txt"), format_error(error))
+        .context("Could not write error text")?;
+
+    // The config is copied as is, since it may be the thing that failed to parse
+    let config_name = config_path.file_name().unwrap_or_default();
+    fs::copy(config_path, bundle.join(config_name)).context("Could not copy config file")?;
+
+    for node in config.nodes.values() {
+        if let NodeConfig::Shader(ref shader_config) = node.config {
+            for path in shader_config.sources() {
+                // A missing shader may well be the error we're saving, so keep going
+                if let Err(e) = copy_into(&bundle, &config.path_to(path), config) {
+                    warn!("{}", format_error(&e));
+                }
+            }
+        }
+    }
+
+    Ok(bundle)
+}
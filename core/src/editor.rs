@@ -0,0 +1,1153 @@
+//! The graph editor, which draws the nodes of the running graph and their connections over the
+//! frame, so connections can be pointed at other outputs and static parameters changed while the
+//! graph is running, and the changes written back to the config file.
+//!
+//! Nodes added when the config is loaded (from groups, parameters, the timeline and tweaks) have a
+//! `/` in their name and aren't shown, since they aren't written in the file, but their outputs
+//! can still be connected to. Writing back is only supported for YAML configs, and only replaces
+//! the values that were changed, so the rest of the file and its comments are kept.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use imgui::{Condition, Drag, Ui};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::Path,
+};
+use yaml_rust::{
+    parser::{Event, Parser},
+    scanner::TScalarStyle,
+};
+
+use crate::config::{
+    nodes::{InputType, NodeConfig, NodeParameter},
+    Config, ConfigFormat,
+};
+
+/// Width of a node in the canvas
+const NODE_WIDTH: f32 = 180.0;
+/// Height of the title of a node, and of each of its inputs, parameters and outputs
+const ROW_HEIGHT: f32 = 20.0;
+/// Space between the columns of nodes, and between the nodes in a column
+const SPACING: [f32; 2] = [60.0, 20.0];
+/// Space between the edges of a node and its text
+const PADDING: f32 = 6.0;
+/// Radius of the circles connections are drawn between
+const PIN_RADIUS: f32 = 4.0;
+
+const TITLE_COLOR: [f32; 4] = [0.26, 0.36, 0.5, 1.0];
+const BODY_COLOR: [f32; 4] = [0.12, 0.12, 0.14, 0.94];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PIN_COLOR: [f32; 4] = [0.9, 0.7, 0.3, 1.0];
+/// Background of the input or parameter the arrow keys change
+const SELECTED_COLOR: [f32; 4] = [0.26, 0.59, 0.98, 0.4];
+
+/// A change made in the editor, in terms of the config file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    /// A connection was pointed at another output
+    Connection {
+        /// The node the connection belongs to
+        node: String,
+        /// The field the connection is in
+        field: String,
+        /// For fields holding a list of connections, which one it is
+        index: usize,
+        /// The node connected to
+        source: String,
+        /// The output connected to
+        output: String,
+    },
+    /// A static parameter was changed
+    Static {
+        /// The node the parameter belongs to
+        node: String,
+        /// The field of the parameter
+        field: String,
+        /// The new value
+        value: f32,
+    },
+}
+
+/// Something done in the editor's window
+#[derive(Debug)]
+pub enum EditorAction {
+    /// The graph was changed, and this config should be loaded
+    Edited(Config),
+    /// The changes should be written to the config file
+    Save,
+}
+
+/// An input or parameter of a node, which can be selected with the arrow keys
+#[derive(Debug, Clone, PartialEq)]
+enum Row {
+    /// An input of a node, as its position in `NodeConfig::connections`
+    Connection { node: String, position: usize },
+    /// A static float parameter of a node
+    Static { node: String, field: &'static str },
+}
+
+/// Returns the nodes shown in the editor
+fn shown(config: &Config) -> impl Iterator<Item = (&String, &NodeConfig)> {
+    config
+        .nodes
+        .iter()
+        .filter(|(name, _)| !name.contains('/'))
+        .map(|(name, node)| (name, &node.config))
+}
+
+/// Returns the static float parameters of a node and their values
+fn statics(node_config: &NodeConfig) -> Vec<(&'static str, f32)> {
+    let mut node_config = node_config.clone();
+    node_config
+        .float_parameters_mut()
+        .into_iter()
+        .filter_map(|(field, parameter)| match *parameter {
+            NodeParameter::Static(value) => Some((field, value)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the inputs and parameters of the shown nodes, in the order the arrow keys go through
+/// them
+fn rows(config: &Config) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (name, node_config) in shown(config) {
+        for position in 0..node_config.connections().len() {
+            rows.push(Row::Connection {
+                node: name.clone(),
+                position,
+            });
+        }
+        for (field, _) in statics(node_config) {
+            rows.push(Row::Static {
+                node: name.clone(),
+                field,
+            });
+        }
+    }
+    rows
+}
+
+/// Returns the type of an output of a node
+fn output_type(config: &Config, node: &str, output: &str) -> Option<InputType> {
+    config
+        .nodes
+        .get(node)?
+        .config
+        .outputs()
+        .into_iter()
+        .find(|(name, _)| name == output)
+        .map(|(_, type_)| type_)
+}
+
+/// Whether `from` depends on `to`, directly or through other nodes
+fn depends_on(config: &Config, from: &str, to: &str) -> bool {
+    let mut stack = vec![from];
+    let mut visited = HashSet::new();
+    while let Some(name) = stack.pop() {
+        if name == to {
+            return true;
+        }
+        if visited.insert(name) {
+            if let Some(node) = config.nodes.get(name) {
+                stack.extend(node.config.dependencies());
+            }
+        }
+    }
+    false
+}
+
+/// Returns the outputs an input of a node could be connected to without changing its type or
+/// making a cycle, as node and output names
+fn candidates(config: &Config, node: &str, type_: &InputType) -> Vec<(String, String)> {
+    let feedback = match config.nodes.get(node).map(|node| &node.config) {
+        Some(NodeConfig::Feedback(_)) => true,
+        _ => false,
+    };
+
+    let mut candidates = Vec::new();
+    for (name, source) in &config.nodes {
+        if name == node || (!feedback && depends_on(config, name, node)) {
+            continue;
+        }
+        for (output, output_type) in source.config.outputs() {
+            if output_type == *type_ {
+                candidates.push((name.clone(), output));
+            }
+        }
+    }
+    candidates
+}
+
+/// A node of a YAML document, along with the index of the character it starts at
+#[derive(Debug)]
+enum Located {
+    Scalar(String, TScalarStyle, usize),
+    Sequence(Vec<Located>, usize),
+    Mapping(Vec<(Located, Located)>, usize),
+    Alias(usize),
+}
+
+impl Located {
+    /// Index of the first character
+    fn start(&self) -> usize {
+        match *self {
+            Located::Scalar(_, _, start)
+            | Located::Sequence(_, start)
+            | Located::Mapping(_, start)
+            | Located::Alias(start) => start,
+        }
+    }
+
+    /// Whether this is a scalar with this value
+    fn is(&self, value: &str) -> bool {
+        match *self {
+            Located::Scalar(ref scalar, ..) => scalar == value,
+            _ => false,
+        }
+    }
+
+    /// Returns the keys and values of a mapping
+    fn entries(&self) -> Option<&[(Located, Located)]> {
+        match *self {
+            Located::Mapping(ref entries, _) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a key of a mapping
+    fn get(&self, key: &str) -> Option<&Located> {
+        self.entries()?
+            .iter()
+            .find(|(name, _)| name.is(key))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Reads the node an event starts from the parser
+fn read_located<T>(parser: &mut Parser<T>, event: Event, start: usize) -> Result<Located, Error>
+where
+    T: Iterator<Item = char>,
+{
+    Ok(match event {
+        Event::Scalar(value, style, ..) => Located::Scalar(value, style, start),
+        Event::Alias(_) => Located::Alias(start),
+        Event::SequenceStart(_) => {
+            let mut items = Vec::new();
+            loop {
+                let (event, marker) = parser.next()?;
+                if let Event::SequenceEnd = event {
+                    break;
+                }
+                items.push(read_located(parser, event, marker.index())?);
+            }
+            Located::Sequence(items, start)
+        }
+        Event::MappingStart(_) => {
+            let mut entries = Vec::new();
+            loop {
+                let (event, marker) = parser.next()?;
+                if let Event::MappingEnd = event {
+                    break;
+                }
+                let key = read_located(parser, event, marker.index())?;
+                let (event, marker) = parser.next()?;
+                entries.push((key, read_located(parser, event, marker.index())?));
+            }
+            Located::Mapping(entries, start)
+        }
+        event => bail!("Unexpected {:?} in the config file", event),
+    })
+}
+
+/// Parses a YAML config, keeping track of where everything is in the source
+fn parse_located(source: &str) -> Result<Located, Error> {
+    let mut parser = Parser::new(source.chars());
+    loop {
+        let (event, marker) = parser.next()?;
+        match event {
+            Event::StreamStart | Event::DocumentStart => (),
+            Event::StreamEnd => bail!("The config file is empty"),
+            event => return read_located(&mut parser, event, marker.index()),
+        }
+    }
+}
+
+/// Returns the characters a scalar on a single line takes up, including quotes
+fn scalar_range(chars: &[char], located: &Located) -> Result<Range<usize>, Error> {
+    let (value, style, start) = match *located {
+        Located::Scalar(ref value, style, start) => (value, style, start),
+        _ => bail!("Only single values can be written back"),
+    };
+
+    let end = match style {
+        TScalarStyle::Plain => start + value.chars().count(),
+        TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => {
+            let quote = chars[start];
+            let mut end = start + 1;
+            loop {
+                match chars.get(end) {
+                    None => bail!("The config file ends in the middle of a string"),
+                    Some('\\') if quote == '"' => end += 2,
+                    Some('\'') if quote == '\'' && chars.get(end + 1) == Some(&'\'') => end += 2,
+                    Some(&c) if c == quote => break end + 1,
+                    Some(_) => end += 1,
+                }
+            }
+        }
+        _ => bail!("Block scalars can't be written back"),
+    };
+    // Plain scalars spanning several lines are folded into one, so they don't match the source
+    ensure!(
+        style != TScalarStyle::Plain
+            || chars
+                .get(start..end)
+                .map_or(false, |text| text.iter().cloned().eq(value.chars())),
+        "`{}` can only be written back if it's on one line",
+        value
+    );
+    Ok(start..end)
+}
+
+/// Writes a string as a YAML scalar, quoting it if it would be read as anything else
+fn yaml_string(text: &str) -> String {
+    let plain = text
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-./".contains(c))
+        && !["true", "false", "null"].contains(&text.to_lowercase().as_str());
+    if plain {
+        text.to_string()
+    } else {
+        // JSON strings are double-quoted YAML strings
+        serde_json::to_string(text).expect("Strings can always be serialized")
+    }
+}
+
+/// Returns the fields of a node, or of one of its connections, in a parsed config
+fn locate<'a>(
+    document: &'a Located,
+    node: &str,
+    connection: Option<(&str, usize)>,
+) -> Result<&'a Located, Error> {
+    let fields = document
+        .get("nodes")
+        .and_then(|nodes| nodes.get(node))
+        .ok_or_else(|| format_err!("Node `{}` isn't in the config file", node))?;
+    let located = match connection {
+        None => Some(fields),
+        Some((field, index)) => match fields.get(field) {
+            Some(Located::Sequence(ref items, _)) => items.get(index),
+            other => other,
+        },
+    };
+    match located {
+        Some(located @ Located::Mapping(..)) => Ok(located),
+        _ => bail!(
+            "Node `{}` has no {} that can be written back to",
+            node,
+            connection.map_or("fields".to_string(), |(field, _)| format!("`{}`", field))
+        ),
+    }
+}
+
+/// Returns the index of the character after the end of the line this one is on
+fn line_end(chars: &[char], index: usize) -> usize {
+    chars[index..]
+        .iter()
+        .position(|&c| c == '\n')
+        .map_or(chars.len(), |offset| index + offset)
+}
+
+/// Sets a field of a node, or of one of its connections, to a YAML value. A missing field is added
+/// after the last field with a single value.
+fn set_field(
+    text: &str,
+    node: &str,
+    connection: Option<(&str, usize)>,
+    key: &str,
+    value: &str,
+) -> Result<String, Error> {
+    let document = parse_located(text)?;
+    let mapping = locate(&document, node, connection)?;
+    let entries = mapping.entries().unwrap_or_default();
+    let mut chars: Vec<char> = text.chars().collect();
+
+    if let Some((_, old)) = entries.iter().find(|(name, _)| name.is(key)) {
+        let range = scalar_range(&chars, old)?;
+        chars.splice(range, value.chars());
+        return Ok(chars.into_iter().collect());
+    }
+
+    let flow = chars.get(mapping.start()) == Some(&'{');
+    let last = entries.iter().rev().find_map(|(_, value)| match *value {
+        Located::Scalar(..) => Some(scalar_range(&chars, value)),
+        _ => None,
+    });
+    let (at, inserted) = match (flow, last) {
+        (true, Some(last)) => (last?.end, format!(", {}: {}", key, value)),
+        (true, None) if entries.is_empty() => (mapping.start() + 1, format!("{}: {}", key, value)),
+        (false, Some(last)) => {
+            // On a line of its own, lined up with the other fields
+            let first = entries[0].0.start();
+            let column = chars[..first]
+                .iter()
+                .rev()
+                .take_while(|&&c| c != '\n')
+                .count();
+            let at = line_end(&chars, last?.end);
+            (at, format!("\n{}{}: {}", " ".repeat(column), key, value))
+        }
+        _ => bail!("Can't add `{}` to node `{}` in the config file", key, node),
+    };
+    chars.splice(at..at, inserted.chars());
+    Ok(chars.into_iter().collect())
+}
+
+/// Removes a field of a node's connection, along with the comment after it, if it's there
+fn remove_field(
+    text: &str,
+    node: &str,
+    connection: Option<(&str, usize)>,
+    key: &str,
+) -> Result<String, Error> {
+    let document = parse_located(text)?;
+    let mapping = locate(&document, node, connection)?;
+    let entries = mapping.entries().unwrap_or_default();
+    let index = match entries.iter().position(|(name, _)| name.is(key)) {
+        Some(index) => index,
+        None => return Ok(text.to_string()),
+    };
+    let mut chars: Vec<char> = text.chars().collect();
+
+    let start = entries[index].0.start();
+    let end = scalar_range(&chars, &entries[index].1)?.end;
+    let range = match (entries.get(index + 1), index.checked_sub(1)) {
+        // The next field moves into its place
+        (Some((next, _)), _) => start..next.start(),
+        (None, Some(previous)) => {
+            if chars.get(mapping.start()) == Some(&'{') {
+                scalar_range(&chars, &entries[previous].1)?.end..end
+            } else {
+                let line_start = chars[..start].iter().rposition(|&c| c == '\n').unwrap_or(0);
+                line_start..line_end(&chars, end)
+            }
+        }
+        (None, None) => bail!("Can't remove the only field of a connection"),
+    };
+    chars.splice(range, None);
+    Ok(chars.into_iter().collect())
+}
+
+/// Applies edits to the source of a YAML config, only changing the values they're about
+pub fn apply_edits(text: &str, edits: &[Edit]) -> Result<String, Error> {
+    let mut text = text.to_string();
+    for edit in edits {
+        text = match *edit {
+            Edit::Connection {
+                ref node,
+                ref field,
+                index,
+                ref source,
+                ref output,
+            } => {
+                let connection = Some((field.as_str(), index));
+                text = set_field(&text, node, connection, "node", &yaml_string(source))?;
+                text = set_field(&text, node, connection, "output", &yaml_string(output))?;
+                // Parameters and tracks replace the connection when the config is loaded
+                text = remove_field(&text, node, connection, "param")?;
+                remove_field(&text, node, connection, "track")?
+            }
+            Edit::Static {
+                ref node,
+                ref field,
+                value,
+            } => set_field(&text, node, None, field, &format!("{:?}", value))?,
+        };
+    }
+    Ok(text)
+}
+
+/// Returns the height of a node in the canvas
+fn node_height(node_config: &NodeConfig) -> f32 {
+    let rows = 1
+        + node_config.connections().len()
+        + statics(node_config).len()
+        + node_config.outputs().len();
+    rows as f32 * ROW_HEIGHT
+}
+
+/// Places the shown nodes in columns, each one to the right of the nodes it depends on
+fn layout(config: &Config) -> HashMap<String, [f32; 2]> {
+    fn depth<'a>(
+        config: &'a Config,
+        name: &'a str,
+        depths: &mut HashMap<&'a str, usize>,
+        visiting: &mut HashSet<&'a str>,
+    ) -> usize {
+        if let Some(&depth) = depths.get(name) {
+            return depth;
+        }
+        let node = match config.nodes.get(name) {
+            Some(node) if !name.contains('/') && visiting.insert(name) => node,
+            _ => return 0,
+        };
+        let depth = node
+            .config
+            .dependencies()
+            .into_iter()
+            .filter(|dependency| !dependency.contains('/'))
+            .map(|dependency| depth(config, dependency, depths, visiting) + 1)
+            .max()
+            .unwrap_or(0);
+        depths.insert(name, depth);
+        depth
+    }
+
+    let mut depths = HashMap::new();
+    let mut columns: Vec<f32> = Vec::new();
+    let mut positions = HashMap::new();
+    for (name, node_config) in shown(config) {
+        let column = depth(config, name, &mut depths, &mut HashSet::new());
+        if columns.len() <= column {
+            columns.resize(column + 1, 0.0);
+        }
+        positions.insert(
+            name.clone(),
+            [column as f32 * (NODE_WIDTH + SPACING[0]), columns[column]],
+        );
+        columns[column] += node_height(node_config) + SPACING[1];
+    }
+    positions
+}
+
+/// Keeps track of the editor's selection and the changes made in it
+#[derive(Debug, Default)]
+pub struct GraphEditor {
+    /// Index of the selected input, counting through the inputs of every node
+    selected: usize,
+    visible: bool,
+    /// Changes that haven't been written to the config file
+    edits: Vec<Edit>,
+    /// Where the nodes are in the canvas, relative to its top left corner
+    positions: HashMap<String, [f32; 2]>,
+    /// The input being connected to another output, as its node and its position in
+    /// `NodeConfig::connections`
+    rewiring: Option<(String, usize)>,
+}
+
+impl GraphEditor {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the editor is shown
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the editor
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Forgets the changes, for when the config is loaded from the file again
+    pub fn discard(&mut self) {
+        self.edits.clear();
+    }
+
+    /// Moves the selection by this many inputs, wrapping around
+    pub fn select(&mut self, config: &Config, offset: isize) {
+        let rows = rows(config).len() as isize;
+        if rows > 0 {
+            self.selected = (self.selected as isize + offset).rem_euclid(rows) as usize;
+        }
+    }
+
+    /// Returns the field an input of a node is in, its index in that field, what it's connected to,
+    /// and the type of outputs it can be connected to
+    fn input(
+        config: &Config,
+        node: &str,
+        position: usize,
+    ) -> Option<(&'static str, usize, (String, String), InputType)> {
+        let connections = config.nodes.get(node)?.config.connections();
+        let (field, connection, ref expected) = *connections.get(position)?;
+        let index = connections[..position]
+            .iter()
+            .filter(|(other, _, _)| *other == field)
+            .count();
+        let current = (connection.node.clone(), connection.output.clone());
+        // Inputs that take any type keep the type of what they're connected to
+        let type_ = match *expected {
+            InputType::Any => output_type(config, &current.0, &current.1)?,
+            ref expected => expected.clone(),
+        };
+        Some((field, index, current, type_))
+    }
+
+    /// Points an input of a node at another output, returning the changed config
+    fn rewire(
+        &mut self,
+        config: &Config,
+        node: &str,
+        position: usize,
+        source: String,
+        output: String,
+    ) -> Option<Config> {
+        let (field, index, current, _) = Self::input(config, node, position)?;
+        if current == (source.clone(), output.clone()) {
+            return None;
+        }
+
+        let mut config = config.clone();
+        let connection = config
+            .nodes
+            .get_mut(node)?
+            .config
+            .connections_mut()
+            .into_iter()
+            .nth(position)?;
+        connection.node = source.clone();
+        connection.output = output.clone();
+
+        self.edits.push(Edit::Connection {
+            node: node.to_string(),
+            field: field.to_string(),
+            index,
+            source,
+            output,
+        });
+        Some(config)
+    }
+
+    /// Changes a static parameter of a node, returning the changed config
+    fn set_static(
+        &mut self,
+        config: &Config,
+        node: &str,
+        field: &'static str,
+        value: f32,
+    ) -> Option<Config> {
+        let mut config = config.clone();
+        let mut changed = false;
+        for (name, parameter) in config.nodes.get_mut(node)?.config.float_parameters_mut() {
+            if name != field {
+                continue;
+            }
+            if let NodeParameter::Static(ref mut old) = *parameter {
+                *old = value;
+                changed = true;
+            }
+        }
+        if !changed {
+            return None;
+        }
+
+        self.edits.push(Edit::Static {
+            node: node.to_string(),
+            field: field.to_string(),
+            value,
+        });
+        Some(config)
+    }
+
+    /// Changes the selected input, pointing a connection at the next (or with a negative
+    /// `direction`, previous) compatible output, or changing a static parameter by a step. Returns
+    /// the changed config, if anything changed.
+    pub fn edit(&mut self, config: &Config, direction: i32, fine: bool) -> Option<Config> {
+        match rows(config).get(self.selected)?.clone() {
+            Row::Connection { node, position } => {
+                let (_, _, current, type_) = Self::input(config, &node, position)?;
+                let candidates = candidates(config, &node, &type_);
+                if candidates.is_empty() {
+                    return None;
+                }
+                let next = match candidates
+                    .iter()
+                    .position(|candidate| *candidate == current)
+                {
+                    Some(found) => {
+                        (found as i32 + direction).rem_euclid(candidates.len() as i32) as usize
+                    }
+                    None => 0,
+                };
+                let (source, output) = candidates[next].clone();
+                self.rewire(config, &node, position, source, output)
+            }
+            Row::Static { node, field } => {
+                let step = if fine { 0.01 } else { 0.1 } * direction as f32;
+                let (_, value) = statics(&config.nodes.get(&node)?.config)
+                    .into_iter()
+                    .find(|(name, _)| *name == field)?;
+                self.set_static(config, &node, field, value + step)
+            }
+        }
+    }
+
+    /// Writes the changes to the config file
+    pub fn save(&mut self, path: &Path) -> Result<(), Error> {
+        ensure!(!self.edits.is_empty(), "There are no changes to save");
+        if ConfigFormat::from_path(path) != ConfigFormat::Yaml {
+            bail!("Only YAML configs can be written back to");
+        }
+
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let source = apply_edits(&source, &self.edits)?;
+        fs::write(path, source).with_context(|| format!("Could not write {}", path.display()))?;
+
+        self.edits.clear();
+        Ok(())
+    }
+
+    /// Shows the editor's window, returning what was done in it. Closing the window hides the
+    /// editor.
+    pub fn build(&mut self, ui: &Ui, config: &Config) -> Option<EditorAction> {
+        let mut visible = self.visible;
+        let mut action = None;
+        let [width, height] = ui.io().display_size;
+        ui.window("Graph editor")
+            .opened(&mut visible)
+            .position([8.0, 8.0], Condition::FirstUseEver)
+            .size([width * 0.6, height * 0.6], Condition::FirstUseEver)
+            .build(|| {
+                ui.text_disabled(
+                    "Click an input to connect it to another output, drag a value to change it, \
+                     and drag a title to move its node. The arrow keys change the highlighted \
+                     input, and ctrl+s saves.",
+                );
+                if !self.edits.is_empty() {
+                    ui.text(format!("{} unsaved changes", self.edits.len()));
+                    ui.same_line();
+                    if ui.button("Save") {
+                        action = Some(EditorAction::Save);
+                    }
+                }
+
+                ui.child_window("canvas")
+                    .horizontal_scrollbar(true)
+                    .build(|| {
+                        if let Some(config) = self.canvas(ui, config) {
+                            action = Some(EditorAction::Edited(config));
+                        }
+                    });
+            });
+        self.visible = visible;
+        action
+    }
+
+    /// Draws the nodes and their connections, returning the changed config if anything was changed
+    fn canvas(&mut self, ui: &Ui, config: &Config) -> Option<Config> {
+        let origin = ui.cursor_screen_pos();
+        let layout = layout(config);
+        let selected = rows(config).get(self.selected).cloned();
+        let mut changed = None;
+
+        // Where each shown node is, and where the pins of their outputs are
+        let mut nodes = Vec::new();
+        let mut pins = HashMap::new();
+        let mut extent = [0.0f32, 0.0f32];
+        for (name, node_config) in shown(config) {
+            let offset = *self
+                .positions
+                .entry(name.clone())
+                .or_insert_with(|| layout[name]);
+            let position = [origin[0] + offset[0], origin[1] + offset[1]];
+            let inputs = node_config.connections().len() + statics(node_config).len();
+            for (row, (output, _)) in node_config.outputs().into_iter().enumerate() {
+                let y = position[1] + (1 + inputs + row) as f32 * ROW_HEIGHT + ROW_HEIGHT / 2.0;
+                pins.insert((name.as_str(), output), [position[0] + NODE_WIDTH, y]);
+            }
+            extent[0] = extent[0].max(offset[0] + NODE_WIDTH);
+            extent[1] = extent[1].max(offset[1] + node_height(node_config));
+            nodes.push((name, node_config, position));
+        }
+
+        let draw_list = ui.get_window_draw_list();
+
+        // Connections go behind the nodes
+        for &(_, node_config, position) in &nodes {
+            for (row, (_, connection, _)) in node_config.connections().into_iter().enumerate() {
+                let source = (connection.node.as_str(), connection.output.clone());
+                if let Some(&start) = pins.get(&source) {
+                    let end = [
+                        position[0],
+                        position[1] + (1 + row) as f32 * ROW_HEIGHT + ROW_HEIGHT / 2.0,
+                    ];
+                    let bend = ((end[0] - start[0]).abs() / 2.0).max(SPACING[0]);
+                    draw_list
+                        .add_bezier_curve(
+                            start,
+                            [start[0] + bend, start[1]],
+                            [end[0] - bend, end[1]],
+                            end,
+                            PIN_COLOR,
+                        )
+                        .thickness(2.0)
+                        .build();
+                }
+            }
+        }
+
+        for (name, node_config, position) in nodes {
+            let [x, y] = position;
+            let connections = node_config.connections();
+            let statics = statics(node_config);
+            let outputs = node_config.outputs();
+            let row_top = |row: usize| y + (1 + row) as f32 * ROW_HEIGHT;
+
+            draw_list
+                .add_rect(
+                    position,
+                    [x + NODE_WIDTH, y + node_height(node_config)],
+                    BODY_COLOR,
+                )
+                .filled(true)
+                .rounding(4.0)
+                .build();
+            draw_list
+                .add_rect(position, [x + NODE_WIDTH, y + ROW_HEIGHT], TITLE_COLOR)
+                .filled(true)
+                .rounding(4.0)
+                .build();
+            draw_list.add_text(
+                [x + PADDING, y + 3.0],
+                TEXT_COLOR,
+                format!("{} ({})", name, node_config.type_name()),
+            );
+
+            // Dragging the title moves the node
+            ui.set_cursor_screen_pos(position);
+            ui.invisible_button(format!("##move/{}", name), [NODE_WIDTH, ROW_HEIGHT]);
+            if ui.is_item_active() {
+                let delta = ui.io().mouse_delta;
+                if let Some(offset) = self.positions.get_mut(name) {
+                    offset[0] += delta[0];
+                    offset[1] += delta[1];
+                }
+            }
+
+            for (row, &(field, connection, _)) in connections.iter().enumerate() {
+                let row_y = row_top(row);
+                let is_selected = selected
+                    == Some(Row::Connection {
+                        node: name.clone(),
+                        position: row,
+                    });
+                if is_selected {
+                    draw_list
+                        .add_rect(
+                            [x, row_y],
+                            [x + NODE_WIDTH, row_y + ROW_HEIGHT],
+                            SELECTED_COLOR,
+                        )
+                        .filled(true)
+                        .build();
+                }
+                draw_list
+                    .add_circle([x, row_y + ROW_HEIGHT / 2.0], PIN_RADIUS, PIN_COLOR)
+                    .filled(true)
+                    .build();
+                let label = if connection.name.is_empty() {
+                    field.to_string()
+                } else {
+                    format!("{}.{}", field, connection.name)
+                };
+                // Connections to nodes that aren't shown say where they come from
+                let source = (connection.node.as_str(), connection.output.clone());
+                let label = if pins.contains_key(&source) {
+                    label
+                } else {
+                    format!("{} <- {}.{}", label, connection.node, connection.output)
+                };
+                draw_list.add_text([x + PADDING, row_y + 3.0], TEXT_COLOR, label);
+
+                ui.set_cursor_screen_pos([x, row_y]);
+                if ui.invisible_button(
+                    format!("##input/{}/{}", name, row),
+                    [NODE_WIDTH, ROW_HEIGHT],
+                ) {
+                    self.rewiring = Some((name.clone(), row));
+                    ui.open_popup("rewire");
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(format!("{}.{}", connection.node, connection.output));
+                }
+            }
+
+            for (row, &(field, value)) in statics.iter().enumerate() {
+                let row_y = row_top(connections.len() + row);
+                let is_selected = selected
+                    == Some(Row::Static {
+                        node: name.clone(),
+                        field,
+                    });
+                if is_selected {
+                    draw_list
+                        .add_rect(
+                            [x, row_y],
+                            [x + NODE_WIDTH, row_y + ROW_HEIGHT],
+                            SELECTED_COLOR,
+                        )
+                        .filled(true)
+                        .build();
+                }
+
+                ui.set_cursor_screen_pos([x + PADDING, row_y + 1.0]);
+                ui.set_next_item_width(NODE_WIDTH - PADDING * 2.0);
+                let mut value = value;
+                let dragged = Drag::new(format!("##static/{}/{}", name, field))
+                    .speed(0.01)
+                    .display_format(format!("{} %.3f", field))
+                    .build(ui, &mut value);
+                if dragged && changed.is_none() {
+                    changed = self.set_static(config, name, field, value);
+                }
+            }
+
+            for (row, (output, _)) in outputs.iter().enumerate() {
+                let row_y = row_top(connections.len() + statics.len() + row);
+                let text_width = ui.calc_text_size(output)[0];
+                draw_list.add_text(
+                    [x + NODE_WIDTH - PADDING - text_width, row_y + 3.0],
+                    TEXT_COLOR,
+                    output,
+                );
+                draw_list
+                    .add_circle(
+                        [x + NODE_WIDTH, row_y + ROW_HEIGHT / 2.0],
+                        PIN_RADIUS,
+                        PIN_COLOR,
+                    )
+                    .filled(true)
+                    .build();
+            }
+        }
+
+        // Picking what to connect an input to
+        let mut chosen = None;
+        ui.popup("rewire", || {
+            let (node, position) = match self.rewiring {
+                Some((ref node, position)) => (node, position),
+                None => return,
+            };
+            let candidates = match Self::input(config, node, position) {
+                Some((_, _, _, type_)) => candidates(config, node, &type_),
+                None => Vec::new(),
+            };
+            if candidates.is_empty() {
+                ui.text_disabled("No other outputs have the right type");
+            }
+            for (source, output) in candidates {
+                if ui.selectable(format!("{}.{}", source, output)) {
+                    chosen = Some((source, output));
+                }
+            }
+        });
+        if let (Some((source, output)), Some((node, position))) = (chosen, self.rewiring.clone()) {
+            if changed.is_none() {
+                changed = self.rewire(config, &node, position, source, output);
+            }
+        }
+
+        // Makes the canvas scroll to show every node
+        ui.set_cursor_screen_pos([origin[0] + extent[0], origin[1] + extent[1]]);
+        ui.dummy([PADDING, PADDING]);
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_edits, Edit, GraphEditor};
+    use crate::config::{nodes::NodeConfig, Config};
+
+    const SOURCE: &str = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: blend
+            output: texture
+    blend:
+        type: blend
+        operation: add
+        textures:
+            - node: a
+              output: texture
+            - node: b
+              output: texture
+    a:
+        type: image
+        path: a.png
+    b:
+        type: image
+        path: b.png
+    blur:
+        type: blur
+        texture:
+            node: a
+            output: texture
+        radius: 2.0
+";
+
+    #[test]
+    fn rewires_without_cycles() {
+        let config: Config = serde_yaml::from_str(SOURCE).unwrap();
+        let mut editor = GraphEditor::new();
+
+        // The output can show any texture, but the blend node can't take its own output
+        editor.select(&config, 1);
+        let config = editor.edit(&config, 1, false).unwrap();
+        match config.nodes["blend"].config {
+            NodeConfig::Blend(ref blend_config) => {
+                assert_eq!(blend_config.textures[0].node, "b");
+            }
+            _ => unreachable!(),
+        }
+        let config = editor.edit(&config, 1, false).unwrap();
+        match config.nodes["blend"].config {
+            NodeConfig::Blend(ref blend_config) => {
+                assert_eq!(blend_config.textures[0].node, "blur");
+            }
+            _ => unreachable!(),
+        }
+        let config = editor.edit(&config, 1, false).unwrap();
+        match config.nodes["blend"].config {
+            NodeConfig::Blend(ref blend_config) => {
+                assert_eq!(blend_config.textures[0].node, "a");
+            }
+            _ => unreachable!(),
+        }
+
+        editor.select(&config, -2);
+        assert!(editor.edit(&config, 1, true).is_some());
+        match editor.edits.last() {
+            Some(&Edit::Static {
+                ref node,
+                ref field,
+                value,
+            }) => {
+                assert_eq!((node.as_str(), field.as_str()), ("blur", "radius"));
+                assert!((value - 2.01).abs() < 1e-6);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(editor.edits.len(), 4);
+    }
+
+    #[test]
+    fn writes_edits_to_yaml() {
+        let source = "
+nodes:
+    # Shows the blend of the images
+    output:
+        type: output
+        texture: { node: blend, output: texture }
+    blend:
+        type: blend
+        textures:
+            - node: a # the background
+              output: texture
+            - param: overlay
+    blur:
+        type: blur
+        texture:
+            node: a
+            output: texture
+        radius: 2.0 # pixels
+    soft:
+        type: blur
+        texture:
+            node: blur
+            output: texture
+    turn:
+        type: transform
+        texture: { node: soft, output: texture }
+        rotate: { track: spin }
+";
+        let edits = [
+            Edit::Connection {
+                node: "output".to_string(),
+                field: "texture".to_string(),
+                index: 0,
+                source: "blur".to_string(),
+                output: "texture".to_string(),
+            },
+            Edit::Connection {
+                node: "blend".to_string(),
+                field: "textures".to_string(),
+                index: 1,
+                source: "blur".to_string(),
+                output: "texture".to_string(),
+            },
+            Edit::Static {
+                node: "blur".to_string(),
+                field: "radius".to_string(),
+                value: 4.0,
+            },
+            Edit::Static {
+                node: "soft".to_string(),
+                field: "radius".to_string(),
+                value: 0.5,
+            },
+            Edit::Connection {
+                node: "turn".to_string(),
+                field: "rotate".to_string(),
+                index: 0,
+                source: "info".to_string(),
+                output: "time".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            apply_edits(source, &edits).unwrap(),
+            "
+nodes:
+    # Shows the blend of the images
+    output:
+        type: output
+        texture: { node: blur, output: texture }
+    blend:
+        type: blend
+        textures:
+            - node: a # the background
+              output: texture
+            - node: blur
+              output: texture
+    blur:
+        type: blur
+        texture:
+            node: a
+            output: texture
+        radius: 4.0 # pixels
+    soft:
+        type: blur
+        radius: 0.5
+        texture:
+            node: blur
+            output: texture
+    turn:
+        type: transform
+        texture: { node: soft, output: texture }
+        rotate: { node: info, output: time }
+"
+        );
+
+        let missing = Edit::Static {
+            node: "nope".to_string(),
+            field: "radius".to_string(),
+            value: 1.0,
+        };
+        assert!(apply_edits(source, &[missing]).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Events are things that happen :D
+
+use std::path::PathBuf;
+use winit::{ElementState, ModifiersState, VirtualKeyCode};
+
+use crate::{
+    capture::CaptureMetadata,
+    config::{Config, PresentMode},
+};
+
+/// Mouse buttons we keep track of
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerButton {
+    /// The left (primary) button
+    Left,
+    /// The right (secondary) button
+    Right,
+    /// The middle button, which is usually the wheel
+    Middle,
+}
+
+/// Events related to the mouse pointer
+#[derive(Clone)]
+pub enum PointerEvent {
+    /// Pointer was moved to (x, y)
+    Move(f32, f32),
+    /// A mouse button was pressed
+    Press(PointerButton),
+    /// A mouse button was released
+    Release(PointerButton),
+    /// The wheel was scrolled by (x, y) lines, with positive y scrolling up
+    Scroll(f32, f32),
+}
+
+/// Keys that move the camera, wherever they are on the keyboard layout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MovementKey {
+    /// W
+    Forward,
+    /// S
+    Back,
+    /// A
+    Left,
+    /// D
+    Right,
+    /// E
+    Up,
+    /// Q
+    Down,
+}
+
+impl MovementKey {
+    /// Returns the movement key a key event is for, if it should move the camera. Presses with
+    /// modifiers held are left to the keybindings, but releases always count, so a key pressed
+    /// before a modifier doesn't get stuck.
+    pub fn from_key(
+        key: VirtualKeyCode,
+        state: ElementState,
+        modifiers: ModifiersState,
+    ) -> Option<Self> {
+        let modified = modifiers.shift || modifiers.ctrl || modifiers.alt || modifiers.logo;
+        if state == ElementState::Pressed && modified {
+            return None;
+        }
+        match key {
+            VirtualKeyCode::W => Some(MovementKey::Forward),
+            VirtualKeyCode::S => Some(MovementKey::Back),
+            VirtualKeyCode::A => Some(MovementKey::Left),
+            VirtualKeyCode::D => Some(MovementKey::Right),
+            VirtualKeyCode::E => Some(MovementKey::Up),
+            VirtualKeyCode::Q => Some(MovementKey::Down),
+            _ => None,
+        }
+    }
+}
+
+/// Events related to the keyboard
+#[derive(Clone)]
+pub enum KeyEvent {
+    /// A movement key was pressed
+    Press(MovementKey),
+    /// A movement key was released
+    Release(MovementKey),
+}
+
+/// Events related to the time seen by shaders
+#[derive(Clone)]
+pub enum TimeEvent {
+    /// Stop the clock
+    Pause,
+    /// Start the clock again
+    Resume,
+    /// Move the clock forward by one frame
+    Step,
+    /// Move the clock forward or backward by this many seconds
+    Seek(f32),
+    /// Set the clock to this time (seconds)
+    SeekTo(f32),
+    /// Multiply the speed of the clock by this factor
+    ScaleSpeed(f32),
+}
+
+/// Events related to the renderer
+#[derive(Clone)]
+pub enum RendererEvent {
+    /// Pointer event
+    Pointer(PointerEvent),
+    /// Keyboard event
+    Key(KeyEvent),
+    /// Window was resized
+    Resize(u32, u32),
+    /// Renderer should reload from a new configuration
+    Reload(Config),
+    /// Time control event
+    Time(TimeEvent),
+    /// The present mode changed
+    PresentMode(PresentMode),
+    /// The DPI factor of the window's monitor changed, so text should be scaled by this much
+    HidpiFactor(f32),
+    /// The parts of the window each monitor covers changed, as `[x, y, width, height]` from the
+    /// bottom left
+    Monitors(Vec<[f32; 4]>),
+    /// Renderer should capture an image to this file, embedding the metadata
+    Capture(PathBuf, CaptureMetadata),
+    /// Set the value of the constant node with this name
+    SetUniform(String, Vec<f32>),
+    /// Clear the values of feedback nodes
+    ClearFeedback,
+}
+
+/// A scene of the timeline, by index or by name
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneId {
+    /// The scene's index, counting from 0
+    Index(usize),
+    /// The node showing the scene, or the name of the scene's config file without its extension
+    Name(String),
+}
+
+/// All events
+#[derive(Clone)]
+pub enum Event {
+    /// Pointer event
+    Pointer(PointerEvent),
+    /// Keyboard event
+    Key(KeyEvent),
+    /// Window was resized
+    Resize(u32, u32),
+    /// Renderer should reload, carrying over the time and feedback
+    Reload,
+    /// Renderer should reload from scratch
+    Restart,
+    /// Renderer should capture an image
+    Capture,
+    /// Start or stop recording an animation
+    ToggleRecording,
+    /// Toggle the performance HUD
+    ToggleHud,
+    /// Toggle the tweak panel
+    ToggleTweaks,
+    /// Move the selection of the tweak panel by this many rows
+    SelectTweak(isize),
+    /// Change the value selected in the tweak panel by this fraction of its range
+    AdjustTweak(f32),
+    /// Toggle the graph editor
+    ToggleEditor,
+    /// Move the selection of the graph editor by this many rows
+    SelectEdit(isize),
+    /// Change the input selected in the graph editor in this direction, by a small step if set
+    Edit(i32, bool),
+    /// Write the changes made in the graph editor to the config file
+    SaveGraph,
+    /// Pause rendering
+    Pause,
+    /// Resume rendering
+    Resume,
+    /// Toggle whether rendering is paused
+    TogglePause,
+    /// Switch to the next supported present mode
+    CyclePresentMode,
+    /// Turn vertical sync on or off
+    ToggleVsync,
+    /// Pause and move forward by one frame
+    Step,
+    /// Move forward or backward by this many seconds
+    Seek(f32),
+    /// Multiply the playback speed by this factor
+    ScaleSpeed(f32),
+    /// Jump to the start of a scene of the timeline
+    SwitchScene(SceneId),
+    /// Renderer should capture an image to this file
+    CaptureTo(PathBuf),
+    /// Set the value of the constant node with this name
+    SetUniform(String, Vec<f32>),
+    /// Clear the values of feedback nodes
+    ClearFeedback,
+    /// Monitors were connected, disconnected, or rearranged
+    MonitorsChanged,
+    /// The window moved to a monitor with a different DPI factor
+    HidpiFactorChanged,
+    /// A newer release is available, described by the message
+    UpdateAvailable(String),
+    /// Close the window
+    Close,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MovementKey;
+    use winit::{ElementState, ModifiersState, VirtualKeyCode};
+
+    #[test]
+    fn leaves_chords_to_keybindings() {
+        let none = ModifiersState::default();
+        let ctrl = ModifiersState {
+            ctrl: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            MovementKey::from_key(VirtualKeyCode::W, ElementState::Pressed, none),
+            Some(MovementKey::Forward)
+        );
+        assert_eq!(
+            MovementKey::from_key(VirtualKeyCode::S, ElementState::Pressed, ctrl),
+            None
+        );
+        assert_eq!(
+            MovementKey::from_key(VirtualKeyCode::S, ElementState::Released, ctrl),
+            Some(MovementKey::Back)
+        );
+        assert_eq!(
+            MovementKey::from_key(VirtualKeyCode::F, ElementState::Pressed, none),
+            None
+        );
+    }
+}
@@ -0,0 +1,625 @@
+//! Provides methods and structs for loading fonts.
+
+use anyhow::Error;
+use euclid::{Point2D, Size2D};
+use font_kit::{
+    canvas::{Canvas, Format, RasterizationOptions},
+    family_name::FamilyName,
+    font::Font,
+    hinting::HintingOptions,
+    properties::Properties,
+    source::SystemSource,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Families to try first when the primary font is missing a glyph, before searching every font on
+/// the system
+const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans",
+    "DejaVu Sans",
+    "Noto Sans CJK JP",
+    "Noto Sans CJK SC",
+    "Source Han Sans",
+    "Droid Sans Fallback",
+    "Noto Emoji",
+    "Noto Color Emoji",
+    "Symbola",
+    "Arial Unicode MS",
+    "Segoe UI Symbol",
+    "Segoe UI Emoji",
+    "Apple Color Emoji",
+];
+
+/// Families to try first for emoji the primary font doesn't have, so they come out in color when
+/// there's a color emoji font
+const EMOJI_FAMILIES: &[&str] = &[
+    "Noto Color Emoji",
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Twemoji Mozilla",
+    "Noto Emoji",
+    "Symbola",
+];
+
+/// Tables holding color glyphs: bitmaps (Google, Apple) and layered outlines (Microsoft)
+const COLOR_TABLES: &[&[u8; 4]] = &[b"CBDT", b"sbix", b"COLR"];
+
+/// Returns whether a character combines with the one before it, so it should be drawn without
+/// advancing
+pub fn is_combining(c: char) -> bool {
+    match c as u32 {
+        // Combining diacritical marks, extended, supplement, for symbols, and half marks
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F => {
+            true
+        }
+        // Cyrillic combining marks
+        0x0483..=0x0489 => true,
+        // Japanese combining (semi-)voiced sound marks
+        0x3099..=0x309A => true,
+        _ => false,
+    }
+}
+
+/// Returns whether a character is usually shown as an emoji
+pub fn is_emoji(c: char) -> bool {
+    match c as u32 {
+        // Playing cards and enclosed characters, including regional indicators for flags, then
+        // pictographs, emoticons, transport, geometric shapes and more pictographs
+        0x1F000..=0x1FAFF => true,
+        // Miscellaneous symbols and dingbats
+        0x2600..=0x27BF => true,
+        // Watch, hourglass, media controls, stars and circles
+        0x231A..=0x231B | 0x23E9..=0x23FA | 0x2B50..=0x2B55 => true,
+        _ => false,
+    }
+}
+
+/// Returns whether the data of a font file (or the first font of a collection) has any of the
+/// tables that hold color glyphs
+fn has_color_tables(data: &[u8]) -> bool {
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+    };
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4).map(|bytes| {
+            u32::from(bytes[0]) << 24
+                | u32::from(bytes[1]) << 16
+                | u32::from(bytes[2]) << 8
+                | u32::from(bytes[3])
+        })
+    };
+
+    // Collections start with a header pointing at each font's table directory
+    let directory = if data.starts_with(b"ttcf") {
+        match read_u32(12) {
+            Some(offset) => offset as usize,
+            None => return false,
+        }
+    } else {
+        0
+    };
+    let tables = match read_u16(directory + 4) {
+        Some(tables) => usize::from(tables),
+        None => return false,
+    };
+
+    (0..tables).any(|table| {
+        let record = directory + 12 + table * 16;
+        data.get(record..record + 4).map_or(false, |tag| {
+            COLOR_TABLES.iter().any(|color| &color[..] == tag)
+        })
+    })
+}
+
+/// Scales a premultiplied RGBA image by averaging the pixels each new pixel covers
+fn scale_rgba(pixels: &[u8], width: u32, height: u32, new_width: u32, new_height: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = (new_width as usize, new_height as usize);
+    // The range of old pixels a new pixel covers, which always has at least one pixel in it
+    let span = |new: usize, old_size: usize, new_size: usize| {
+        let start = new * old_size / new_size;
+        let end = ((new + 1) * old_size / new_size)
+            .max(start + 1)
+            .min(old_size);
+        start..end
+    };
+
+    let mut scaled = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        let rows = span(y, height, new_height);
+        for x in 0..new_width {
+            let columns = span(x, width, new_width);
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for row in rows.clone() {
+                for column in columns.clone() {
+                    let pixel = (row * width + column) * 4;
+                    for (channel, sum) in sum.iter_mut().enumerate() {
+                        *sum += u32::from(pixels[pixel + channel]);
+                    }
+                    count += 1;
+                }
+            }
+            scaled.extend(sum.iter().map(|&sum| (sum / count) as u8));
+        }
+    }
+    scaled
+}
+
+/// Returns whether a character only affects how its neighbours are shaped, so it has no glyph of
+/// its own and should be skipped
+pub fn is_ignorable(c: char) -> bool {
+    match c as u32 {
+        // Zero width space, non-joiner and joiner, and variation selectors
+        0x200B..=0x200D | 0xFE00..=0xFE0F | 0xE0100..=0xE01EF => true,
+        _ => false,
+    }
+}
+
+/// Identifies a glyph in one of the fonts of a fallback chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    /// Index of the font in the fallback chain
+    pub font: usize,
+    /// ID of the glyph within the font
+    pub glyph: u32,
+}
+
+/// Contains information about a rendered glyph, including a buffer of pixel data to load into a
+/// texture
+#[derive(Clone, Debug)]
+pub struct RenderedGlyph {
+    /// Bitmap buffer (format: U8, or premultiplied U8U8U8U8 for color glyphs)
+    pub buffer: Vec<u8>,
+    /// Whether the glyph has colors of its own, rather than being a coverage mask to tint
+    pub color: bool,
+    /// Width of glyph in pixels
+    pub width: u32,
+    /// Height of glyph in pixels
+    pub height: u32,
+    /// Additional distance from left
+    pub bearing_x: i32,
+    /// Additional distance from top
+    pub bearing_y: i32,
+    /// Advance distance to start of next character
+    pub advance: u32,
+    /// Line height of the primary font
+    pub line_height: u32,
+}
+
+/// Generic loader for glyphs
+pub trait GlyphLoader {
+    /// Creates a new instance of the GlyphCache
+    fn new(path: &str, size: f32) -> Result<Self, Error>
+    where
+        Self: Sized;
+    /// Finds the glyph for a character, trying the `preferred` font first (so combining marks can
+    /// come from the same font as the character they combine with) and falling back to other fonts
+    /// if needed
+    fn lookup(&mut self, character: char, preferred: Option<usize>) -> GlyphKey;
+    /// Loads a glyph and renders it
+    fn load(&self, key: GlyphKey) -> Result<RenderedGlyph, Error>;
+    /// Returns the distance between lines
+    fn line_height(&self) -> u32;
+}
+
+/// A `GlyphLoader` implementation that uses the `FreeType` library to load and render glyphs
+pub struct FreeTypeRasterizer {
+    /// The primary font, followed by fallback fonts in the order they were loaded
+    fonts: Vec<Font>,
+    /// Whether each font has color glyphs
+    color_fonts: Vec<bool>,
+    /// Families to load fallback fonts from, in order, once the loaded fonts run out
+    fallback_families: VecDeque<String>,
+    /// Whether every family on the system has been added to `fallback_families`
+    searched_system: bool,
+    /// Families that have already been tried
+    tried_families: HashSet<String>,
+    /// Characters which weren't found in the primary font, and where they were found instead
+    fallbacks: HashMap<char, GlyphKey>,
+    size: f32,
+}
+
+impl FreeTypeRasterizer {
+    /// Returns the glyph for a character in a font, if the font has one
+    fn glyph_in(&self, font: usize, character: char) -> Option<GlyphKey> {
+        self.fonts[font]
+            .glyph_for_char(character)
+            .filter(|&glyph| glyph != 0)
+            .map(|glyph| GlyphKey { font, glyph })
+    }
+
+    /// Adds a font to the fallback chain
+    fn push_font(&mut self, font: Font) {
+        let color = font
+            .copy_font_data()
+            .map_or(false, |data| has_color_tables(&data));
+        self.fonts.push(font);
+        self.color_fonts.push(color);
+    }
+
+    /// Loads a family into the fallback chain unless it's been tried already, returning whether
+    /// it was loaded
+    fn load_family(&mut self, family: &str) -> bool {
+        if !self.tried_families.insert(family.to_string()) {
+            return false;
+        }
+
+        let font = SystemSource::new()
+            .select_family_by_name(family)
+            .ok()
+            .and_then(|handle| handle.fonts().first().and_then(|font| font.load().ok()));
+        match font {
+            Some(font) => {
+                self.push_font(font);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finds an emoji in the color fonts we already have, then in the emoji families
+    fn lookup_emoji(&mut self, character: char) -> Option<GlyphKey> {
+        let loaded = (0..self.fonts.len())
+            .filter(|&font| self.color_fonts[font])
+            .filter_map(|font| self.glyph_in(font, character))
+            .next();
+        if loaded.is_some() {
+            return loaded;
+        }
+
+        for family in EMOJI_FAMILIES {
+            if self.load_family(family) {
+                if let Some(key) = self.glyph_in(self.fonts.len() - 1, character) {
+                    return Some(key);
+                }
+            }
+        }
+        None
+    }
+
+    /// Loads the next fallback font, returning false when there are none left
+    fn load_fallback(&mut self) -> bool {
+        loop {
+            let family = match self.fallback_families.pop_front() {
+                Some(family) => family,
+                None if !self.searched_system => {
+                    self.searched_system = true;
+                    let mut families = SystemSource::new().all_families().unwrap_or_default();
+                    families.sort();
+                    self.fallback_families.extend(families);
+                    continue;
+                }
+                None => return false,
+            };
+
+            if self.load_family(&family) {
+                return true;
+            }
+        }
+    }
+
+    /// Renders a glyph from a color font into premultiplied RGBA, using FreeType directly since
+    /// font-kit only renders coverage masks with it. Color bitmaps only come in a few sizes, so the
+    /// closest one is scaled to the size we want.
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn load_color(&self, key: GlyphKey) -> Result<RenderedGlyph, Error> {
+        use anyhow::bail;
+        use freetype::freetype::{
+            FT_Done_Face, FT_Face, FT_Load_Glyph, FT_Select_Size, FT_Set_Pixel_Sizes,
+        };
+        use std::slice;
+
+        /// Renders the glyph as it's loaded, from the FreeType headers
+        const FT_LOAD_RENDER: i32 = 1 << 2;
+        /// Loads glyphs in color, from the FreeType headers
+        const FT_LOAD_COLOR: i32 = 1 << 20;
+        /// Pixel mode of color bitmaps, from the FreeType headers
+        const FT_PIXEL_MODE_BGRA: u8 = 7;
+
+        /// Our reference to a face, which `native_font` gives us to release when we're done
+        struct FaceRef(FT_Face);
+
+        impl Drop for FaceRef {
+            fn drop(&mut self) {
+                unsafe {
+                    FT_Done_Face(self.0);
+                }
+            }
+        }
+
+        let reference = FaceRef(self.fonts[key.font].native_font());
+        let face = reference.0;
+        unsafe {
+            // Bitmap fonts only come in the sizes they have, while layered fonts come in any size.
+            // Sizes of strikes are in 64ths of a pixel.
+            let strikes = (*face).num_fixed_sizes as usize;
+            let ppem = if strikes > 0 {
+                let sizes = slice::from_raw_parts((*face).available_sizes, strikes);
+                let (index, size) = sizes
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|&(_, size)| (size.y_ppem as i64 - (self.size * 64.0) as i64).abs())
+                    .unwrap();
+                if FT_Select_Size(face, index as _) != 0 {
+                    bail!("Couldn't select a size of the color font");
+                }
+                size.y_ppem as f32 / 64.0
+            } else {
+                let ppem = self.size.round();
+                if FT_Set_Pixel_Sizes(face, 0, ppem as _) != 0 {
+                    bail!("Couldn't set the size of the color font");
+                }
+                ppem
+            };
+
+            if FT_Load_Glyph(face, key.glyph as _, (FT_LOAD_COLOR | FT_LOAD_RENDER) as _) != 0 {
+                bail!("Couldn't render color glyph {}", key.glyph);
+            }
+
+            let slot = &*(*face).glyph;
+            let bitmap = &slot.bitmap;
+            let (width, height) = (bitmap.width as u32, bitmap.rows as u32);
+            let mut buffer = Vec::with_capacity((width * height * 4) as usize);
+            if width > 0 && height > 0 {
+                if bitmap.pixel_mode as u8 != FT_PIXEL_MODE_BGRA {
+                    bail!("Glyph {} isn't in color", key.glyph);
+                }
+                for row in 0..height as isize {
+                    let start = bitmap.buffer.offset(row * bitmap.pitch as isize);
+                    for pixel in slice::from_raw_parts(start, width as usize * 4).chunks(4) {
+                        buffer.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                    }
+                }
+            }
+
+            let scale = self.size / ppem;
+            let (new_width, new_height) = if buffer.is_empty() {
+                (0, 0)
+            } else {
+                (
+                    ((width as f32 * scale).round() as u32).max(1),
+                    ((height as f32 * scale).round() as u32).max(1),
+                )
+            };
+            Ok(RenderedGlyph {
+                buffer: if buffer.is_empty() {
+                    buffer
+                } else {
+                    scale_rgba(&buffer, width, height, new_width, new_height)
+                },
+                color: true,
+                width: new_width,
+                height: new_height,
+                bearing_x: (slot.bitmap_left as f32 * scale).round() as i32,
+                bearing_y: ((slot.bitmap_top as f32 - height as f32) * scale).round() as i32,
+                advance: (slot.advance.x as f32 / 64.0 * scale).round() as u32,
+                line_height: self.line_height(),
+            })
+        }
+    }
+
+    /// Renders a glyph from a color font into premultiplied RGBA, which the system's rasterizer
+    /// does in color
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    fn load_color(&self, key: GlyphKey) -> Result<RenderedGlyph, Error> {
+        let mut glyph = self.rasterize(key, Format::Rgba32)?;
+        glyph.color = true;
+        Ok(glyph)
+    }
+
+    /// Renders a glyph with font-kit into a canvas of the given format
+    fn rasterize(&self, key: GlyphKey, format: Format) -> Result<RenderedGlyph, Error> {
+        let font = &self.fonts[key.font];
+        let glyph_id = key.glyph;
+
+        let raster_bounds = font.raster_bounds(
+            glyph_id,
+            self.size,
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )?;
+
+        let mut canvas = Canvas::new(
+            &Size2D::new(
+                raster_bounds.size.width as u32,
+                raster_bounds.size.height as u32,
+            ),
+            format,
+        );
+
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            self.size,
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )?;
+
+        let metrics = font.metrics();
+        let scale = metrics.units_per_em as f32 / self.size;
+
+        Ok(RenderedGlyph {
+            buffer: canvas.pixels,
+            color: false,
+            width: canvas.size.width as u32,
+            height: canvas.size.height as u32,
+            bearing_x: raster_bounds.origin.x as i32,
+            bearing_y: raster_bounds.origin.y as i32,
+            advance: (font.advance(glyph_id)?.x / scale) as u32,
+            line_height: self.line_height(),
+        })
+    }
+}
+
+impl GlyphLoader for FreeTypeRasterizer {
+    fn new(font_name: &str, size: f32) -> Result<Self, Error> {
+        let font = SystemSource::new()
+            .select_best_match(
+                &[
+                    FamilyName::Title(font_name.to_string()),
+                    FamilyName::Monospace,
+                ],
+                &Properties::new(),
+            )
+            .unwrap()
+            .load()?;
+
+        let mut tried_families = HashSet::new();
+        tried_families.insert(font.family_name());
+
+        let mut rasterizer = Self {
+            fonts: Vec::new(),
+            color_fonts: Vec::new(),
+            fallback_families: FALLBACK_FAMILIES.iter().map(|s| s.to_string()).collect(),
+            searched_system: false,
+            tried_families,
+            fallbacks: HashMap::new(),
+            size,
+        };
+        rasterizer.push_font(font);
+        Ok(rasterizer)
+    }
+
+    fn lookup(&mut self, character: char, preferred: Option<usize>) -> GlyphKey {
+        if let Some(key) = preferred.and_then(|font| self.glyph_in(font, character)) {
+            return key;
+        }
+        if let Some(key) = self.glyph_in(0, character) {
+            return key;
+        }
+        if let Some(&key) = self.fallbacks.get(&character) {
+            return key;
+        }
+        if is_emoji(character) {
+            if let Some(key) = self.lookup_emoji(character) {
+                self.fallbacks.insert(character, key);
+                return key;
+            }
+        }
+
+        // Look through the fallback fonts we already have, then load more until one has the glyph.
+        // If none do, use the primary font's missing glyph so there's at least something to see.
+        let mut font = 1;
+        let key = loop {
+            if font == self.fonts.len() && !self.load_fallback() {
+                break GlyphKey { font: 0, glyph: 0 };
+            }
+            if let Some(key) = self.glyph_in(font, character) {
+                break key;
+            }
+            font += 1;
+        };
+        self.fallbacks.insert(character, key);
+        key
+    }
+
+    fn load(&self, key: GlyphKey) -> Result<RenderedGlyph, Error> {
+        if self.color_fonts[key.font] {
+            self.load_color(key)
+        } else {
+            self.rasterize(key, Format::A8)
+        }
+    }
+
+    fn line_height(&self) -> u32 {
+        // Every glyph uses the primary font's line height, so fallback glyphs don't make lines
+        // uneven
+        let metrics = self.fonts[0].metrics();
+        ((self.size / (metrics.ascent + metrics.descent)) * metrics.ascent) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::font::{
+        has_color_tables, is_combining, is_emoji, is_ignorable, scale_rgba, FreeTypeRasterizer,
+        GlyphLoader,
+    };
+
+    #[test]
+    fn detects_combining_characters() {
+        assert!(is_combining('\u{0301}'));
+        assert!(is_combining('\u{3099}'));
+        assert!(!is_combining('e'));
+        assert!(!is_combining('ж'));
+        assert!(!is_combining('あ'));
+
+        assert!(is_ignorable('\u{200D}'));
+        assert!(is_ignorable('\u{FE0F}'));
+        assert!(!is_ignorable('\u{0301}'));
+    }
+
+    #[test]
+    fn detects_emoji() {
+        assert!(is_emoji('😀'));
+        assert!(is_emoji('☕'));
+        assert!(is_emoji('\u{1F1EF}'));
+        assert!(!is_emoji('a'));
+        assert!(!is_emoji('あ'));
+    }
+
+    #[test]
+    fn finds_color_tables() {
+        // A table directory with `glyf` and then `CBDT`
+        let mut font = vec![0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+        font.extend_from_slice(b"glyf");
+        font.extend_from_slice(&[0; 12]);
+        font.extend_from_slice(b"CBDT");
+        font.extend_from_slice(&[0; 12]);
+        assert!(has_color_tables(&font));
+
+        // The same in a collection, whose first font starts after the header
+        let mut collection = b"ttcf".to_vec();
+        collection.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 16]);
+        collection.extend_from_slice(&font);
+        assert!(has_color_tables(&collection));
+
+        font[5] = 1;
+        assert!(!has_color_tables(&font));
+        assert!(!has_color_tables(b"ttcf"));
+    }
+
+    #[test]
+    fn scales_color_glyphs() {
+        let pixels = [
+            255, 0, 0, 255, 0, 0, 0, 0, //
+            255, 0, 0, 255, 0, 0, 0, 0,
+        ];
+        assert_eq!(scale_rgba(&pixels, 2, 2, 1, 1), vec![127, 0, 0, 127]);
+        assert_eq!(scale_rgba(&pixels, 2, 2, 2, 2), pixels.to_vec());
+        assert_eq!(scale_rgba(&pixels, 2, 2, 4, 1).len(), 16);
+    }
+
+    #[test]
+    fn renders_glyphs() {
+        let mut rasterizer = FreeTypeRasterizer::new("", 20.0).unwrap();
+
+        for c in ['F', 'U', 'C', 'K'].iter() {
+            let key = rasterizer.lookup(*c, None);
+            let glyph = rasterizer.load(key).unwrap();
+            let (w, h) = (glyph.width as usize, glyph.height as usize);
+            println!("{:?}", glyph);
+            for y in 0..h {
+                for x in 0..w {
+                    let _val = glyph.buffer[(y * w) + x];
+                    #[rustfmt::skip]
+                    print!(
+                        "{0}{0}",
+                        match glyph.buffer[(y * w) + x] {
+                              0..=51  => ' ',
+                             52..=102 => '░',
+                            103..=153 => '▒',
+                            154..=204 => '▓',
+                            205..=255 => '█',
+                        }
+                    );
+                }
+                println!();
+            }
+        }
+    }
+}
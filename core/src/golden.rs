@@ -0,0 +1,314 @@
+//! Renders a config headless at fixed times and compares the frames against reference images, so
+//! node implementations and shader packs can have visual regression tests that run in CI:
+//!
+//! ```shell
+//! yotredash test config.yml --times 0 1 2.5 --update   # write the references
+//! yotredash test config.yml --times 0 1 2.5            # check against them
+//! ```
+//!
+//! References are PNGs named after the config and the time, in a `references` directory next to
+//! the config unless another one is given. When a frame fails, the rendered frame and an image
+//! highlighting the differences are written next to its reference.
+
+use anyhow::{bail, format_err, Context, Error};
+use image::{self, RgbaImage};
+use log::{info, warn};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use crate::{
+    capture::{self, CaptureMetadata},
+    config::Config,
+    event::{RendererEvent, TimeEvent},
+    renderer,
+};
+
+/// How frames are compared against their references
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Demo times to render frames at (seconds)
+    pub times: Vec<f32>,
+    /// Perceptual difference, from 0 to 1, above which a pixel counts as different
+    pub threshold: f32,
+    /// Fraction of pixels that may be different before a frame fails
+    pub tolerance: f32,
+    /// Write the rendered frames as the new references instead of comparing them
+    pub update: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            times: vec![0.0],
+            threshold: 0.1,
+            tolerance: 0.001,
+            update: false,
+        }
+    }
+}
+
+/// What happened to a frame
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The frame matched its reference, with this many pixels different
+    Passed(usize),
+    /// Too many pixels were different
+    Failed(usize),
+    /// The frame was a different size than its reference
+    WrongSize(u32, u32),
+    /// There was no reference to compare with
+    Missing,
+    /// The reference was written from the frame
+    Updated,
+}
+
+/// The result of checking the frame at one time
+#[derive(Clone, Debug)]
+pub struct FrameResult {
+    /// Demo time of the frame (seconds)
+    pub time: f32,
+    /// The reference image for the frame
+    pub reference: PathBuf,
+    /// What happened
+    pub outcome: Outcome,
+}
+
+impl FrameResult {
+    /// Whether the frame is fine, including when its reference was just written
+    pub fn passed(&self) -> bool {
+        match self.outcome {
+            Outcome::Passed(_) | Outcome::Updated => true,
+            _ => false,
+        }
+    }
+}
+
+/// The perceptual difference between two RGBA pixels, from 0 to 1. Colors are compared in YIQ
+/// space with luma weighted highest, like pixelmatch, after blending them over white.
+pub fn pixel_difference(a: [u8; 4], b: [u8; 4]) -> f32 {
+    /// The largest possible value of the weighted YIQ difference
+    const MAX_DELTA: f32 = 35215.0;
+
+    let blend = |pixel: [u8; 4]| {
+        let alpha = f32::from(pixel[3]) / 255.0;
+        let channel = |value: u8| 255.0 + (f32::from(value) - 255.0) * alpha;
+        (channel(pixel[0]), channel(pixel[1]), channel(pixel[2]))
+    };
+    let (r1, g1, b1) = blend(a);
+    let (r2, g2, b2) = blend(b);
+    let (dr, dg, db) = (r1 - r2, g1 - g2, b1 - b2);
+
+    let y = dr * 0.298_895_31 + dg * 0.586_622_47 + db * 0.114_482_23;
+    let i = dr * 0.595_977_99 - dg * 0.274_176_3 - db * 0.321_801_69;
+    let q = dr * 0.211_470_17 - dg * 0.522_617_24 + db * 0.311_147_07;
+
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA).min(1.0)
+}
+
+/// Compares two RGBA images of the same size, returning how many pixels differ by more than
+/// `threshold`, and an image of the reference faded to grey with those pixels in red
+pub fn compare(actual: &[u8], reference: &[u8], threshold: f32) -> (usize, Vec<u8>) {
+    let mut different = 0;
+    let mut diff = Vec::with_capacity(reference.len());
+    for (a, b) in actual.chunks(4).zip(reference.chunks(4)) {
+        let delta = pixel_difference([a[0], a[1], a[2], a[3]], [b[0], b[1], b[2], b[3]]);
+        if delta > threshold {
+            different += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = 0.299 * f32::from(b[0]) + 0.587 * f32::from(b[1]) + 0.114 * f32::from(b[2]);
+            let grey = (255.0 - (255.0 - luma) * 0.1) as u8;
+            diff.extend_from_slice(&[grey, grey, grey, 255]);
+        }
+    }
+    (different, diff)
+}
+
+/// The reference image for a config at a time
+fn reference_path(references: &Path, config_path: &Path, time: f32) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .map_or("config".into(), |stem| stem.to_string_lossy());
+    references.join(format!("{}-{}s.png", stem, time))
+}
+
+/// A path next to a reference, with a suffix before the extension
+fn sibling(reference: &Path, suffix: &str) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    reference.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+/// Renders the config headless at each of the times and compares the frames against the
+/// references in `references`, or in a `references` directory next to the config
+pub fn run(
+    config_path: &Path,
+    references: Option<&Path>,
+    options: &Options,
+) -> Result<Vec<FrameResult>, Error> {
+    let references = match references {
+        Some(references) => references.to_path_buf(),
+        None => config_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("references"),
+    };
+    if options.update {
+        fs::create_dir_all(&references).context(format!(
+            "Could not create references directory {}",
+            references.display()
+        ))?;
+    }
+
+    let mut config = Config::parse(config_path)?;
+    config.headless = true;
+    let (width, height) = (config.width, config.height);
+
+    let events_loop = renderer::events_loop();
+    let backend = renderer::new_backend(&config, events_loop.as_ref(), "yotredash")?;
+    let (sender, receiver) = mpsc::channel();
+    let mut renderer = backend.new_renderer(&config, receiver)?;
+    sender.send(RendererEvent::Time(TimeEvent::Pause))?;
+
+    let mut results = Vec::new();
+    for &time in &options.times {
+        sender.send(RendererEvent::Time(TimeEvent::SeekTo(time)))?;
+        let pixels = renderer.render_still(width, height)?;
+        let actual = capture::to_u8(&pixels, 4);
+        let reference = reference_path(&references, config_path, time);
+
+        let mut metadata = CaptureMetadata::new(config_path, &config)?;
+        metadata.demo_time = time;
+
+        let outcome = if options.update {
+            capture::save_png(&reference, &actual, width, height, &metadata)?;
+            Outcome::Updated
+        } else if !reference.exists() {
+            Outcome::Missing
+        } else {
+            let expected: RgbaImage = image::open(&reference)
+                .context(format!("Could not open reference {}", reference.display()))?
+                .to_rgba();
+            if expected.dimensions() != (width, height) {
+                let (expected_width, expected_height) = expected.dimensions();
+                Outcome::WrongSize(expected_width, expected_height)
+            } else {
+                let (different, diff) = compare(&actual, &expected, options.threshold);
+                let allowed = (options.tolerance * (width * height) as f32) as usize;
+                if different > allowed {
+                    capture::save_png(
+                        &sibling(&reference, "actual"),
+                        &actual,
+                        width,
+                        height,
+                        &metadata,
+                    )?;
+                    capture::save_png(
+                        &sibling(&reference, "diff"),
+                        &diff,
+                        width,
+                        height,
+                        &metadata,
+                    )?;
+                    Outcome::Failed(different)
+                } else {
+                    Outcome::Passed(different)
+                }
+            }
+        };
+
+        results.push(FrameResult {
+            time,
+            reference,
+            outcome,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Runs the test and logs each frame, returning an error if any of them failed
+pub fn test(config_path: &Path, references: Option<&Path>, options: &Options) -> Result<(), Error> {
+    let results = run(config_path, references, options)?;
+
+    for result in &results {
+        let name = result.reference.display();
+        match result.outcome {
+            Outcome::Passed(different) => info!(
+                "ok      {}s: {} ({} pixels different)",
+                result.time, name, different
+            ),
+            Outcome::Failed(different) => warn!(
+                "FAILED  {}s: {} pixels differ from {}, see {}",
+                result.time,
+                different,
+                name,
+                sibling(&result.reference, "diff").display()
+            ),
+            Outcome::WrongSize(width, height) => warn!(
+                "FAILED  {}s: {} is {}x{}, which isn't the size of the config",
+                result.time, name, width, height
+            ),
+            Outcome::Missing => warn!(
+                "MISSING {}s: {} doesn't exist, run with --update to create it",
+                result.time, name
+            ),
+            Outcome::Updated => info!("updated {}s: {}", result.time, name),
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    if failed > 0 {
+        bail!(
+            "{} of {} frames of {} didn't match their references",
+            failed,
+            results.len(),
+            config_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Parses the times to render at from the command line
+pub fn parse_times<'a, I: Iterator<Item = &'a str>>(times: I) -> Result<Vec<f32>, Error> {
+    times
+        .map(|time| {
+            time.parse::<f32>()
+                .ok()
+                .filter(|time| time.is_finite())
+                .ok_or_else(|| format_err!("Invalid time `{}`", time))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, pixel_difference};
+
+    #[test]
+    fn pixel_difference_is_perceptual() {
+        assert_eq!(pixel_difference([10, 20, 30, 255], [10, 20, 30, 255]), 0.0);
+        assert!(pixel_difference([0, 0, 0, 255], [255, 255, 255, 255]) > 0.9);
+        // Fully transparent pixels look the same whatever their color
+        assert_eq!(pixel_difference([255, 0, 0, 0], [0, 0, 255, 0]), 0.0);
+        // A change in green is more visible than the same change in blue
+        assert!(
+            pixel_difference([0, 0, 0, 255], [0, 40, 0, 255])
+                > pixel_difference([0, 0, 0, 255], [0, 0, 40, 255])
+        );
+    }
+
+    #[test]
+    fn compare_counts_different_pixels() {
+        let reference = [0, 0, 0, 255, 100, 100, 100, 255, 255, 255, 255, 255];
+        let actual = [1, 1, 1, 255, 100, 100, 100, 255, 0, 0, 0, 255];
+        let (different, diff) = compare(&actual, &reference, 0.1);
+        assert_eq!(different, 1);
+        assert_eq!(&diff[8..], &[255, 0, 0, 255]);
+    }
+}
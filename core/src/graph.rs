@@ -0,0 +1,212 @@
+//! Prints the node graph of a config without opening a window, as Graphviz dot or JSON, for
+//! understanding and documenting larger graphs:
+//!
+//! ```shell
+//! yotredash graph --config config.yml --format dot | dot -Tsvg > graph.svg
+//! ```
+//!
+//! Nodes that aren't rendered (because nothing the output or a node with a priority depends on
+//! reads from them) are drawn dashed in dot, and left out of `order` in JSON.
+
+use anyhow::{bail, format_err, Error};
+use serde_derive::Serialize;
+use std::path::Path;
+
+use crate::config::{
+    nodes::{render_order, NodeConfig},
+    Config,
+};
+
+/// An output of a node
+#[derive(Debug, Serialize)]
+pub struct GraphOutput {
+    /// The name of the output
+    pub name: String,
+    /// The type of the output
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// A connection into an input of a node
+#[derive(Debug, Serialize)]
+pub struct GraphInput {
+    /// The input the connection goes into
+    pub input: String,
+    /// The node the connection comes from
+    pub node: String,
+    /// The output the connection comes from
+    pub output: String,
+    /// The type the input expects
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// A node and its connections
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    /// The name of the node
+    pub name: String,
+    /// The type of node
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// The node's priority, if it has one
+    pub priority: Option<i32>,
+    /// The outputs of the node
+    pub outputs: Vec<GraphOutput>,
+    /// The connections into the node's inputs
+    pub inputs: Vec<GraphInput>,
+}
+
+/// The node graph of a config
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    /// Every node, in the order of the config
+    pub nodes: Vec<GraphNode>,
+    /// The names of the nodes that are rendered, in the order they're rendered in
+    pub order: Vec<String>,
+}
+
+impl Graph {
+    /// Describes the graph of a validated config
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let output = config
+            .nodes
+            .iter()
+            .find(|(_, node)| match node.config {
+                NodeConfig::Output(_) => true,
+                _ => false,
+            })
+            .map(|(name, _)| name.as_str())
+            .ok_or_else(|| format_err!("No output node specified"))?;
+
+        let nodes = config
+            .nodes
+            .iter()
+            .map(|(name, node)| GraphNode {
+                name: name.clone(),
+                type_: node.config.type_name().to_string(),
+                priority: node.priority,
+                outputs: node
+                    .config
+                    .outputs()
+                    .into_iter()
+                    .map(|(name, type_)| GraphOutput {
+                        name,
+                        type_: type_.to_string(),
+                    })
+                    .collect(),
+                inputs: node
+                    .config
+                    .connections()
+                    .into_iter()
+                    .map(|(input, connection, type_)| GraphInput {
+                        // Shader uniforms and feedback inputs are named by the connection
+                        input: if connection.name.is_empty() {
+                            input.to_string()
+                        } else {
+                            connection.name.clone()
+                        },
+                        node: connection.node.clone(),
+                        output: connection.output.clone(),
+                        type_: type_.to_string(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Self {
+            nodes,
+            order: render_order(&config.nodes, output),
+        })
+    }
+
+    /// Formats the graph as Graphviz dot, with the nodes labelled with their type and the step
+    /// they're rendered at
+    pub fn to_dot(&self) -> String {
+        let quote = |name: &str| format!("\"{}\"", name.replace('"', "\\\""));
+
+        let mut dot = String::from("digraph yotredash {\n    rankdir=LR;\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let label = match self.order.iter().position(|name| *name == node.name) {
+                Some(step) => format!("{}\\n{} #{}", node.name, node.type_, step + 1),
+                None => format!("{}\\n{}", node.name, node.type_),
+            };
+            let style = if self.order.contains(&node.name) {
+                ""
+            } else {
+                ", style=dashed"
+            };
+            dot.push_str(&format!(
+                "    {} [label={}{}];\n",
+                quote(&node.name),
+                quote(&label),
+                style
+            ));
+        }
+        for node in &self.nodes {
+            for input in &node.inputs {
+                dot.push_str(&format!(
+                    "    {} -> {} [label={}];\n",
+                    quote(&input.node),
+                    quote(&node.name),
+                    quote(&format!("{} -> {}", input.output, input.input))
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Runs `yotredash graph`, printing the graph of the config at a path in a format (`dot` or
+/// `json`)
+pub fn print(path: &Path, format: &str) -> Result<(), Error> {
+    let graph = Graph::new(&Config::parse(path)?)?;
+    match format {
+        "dot" => print!("{}", graph.to_dot()),
+        "json" => println!("{}", serde_json::to_string_pretty(&graph)?),
+        _ => bail!("Unknown graph format `{}`", format),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Graph;
+    use crate::config::Config;
+
+    #[test]
+    fn describes_graph() {
+        let source = "
+nodes:
+    output:
+        type: output
+        texture:
+            node: shader
+            output: texture
+    shader:
+        type: shader
+        vertex: shader.vert
+        fragment: shader.frag
+        uniforms:
+            - node: info
+              output: resolution
+              name: resolution
+    info:
+        type: info
+    unused:
+        type: info
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let graph = Graph::new(&config).unwrap();
+
+        assert_eq!(graph.order, vec!["info", "shader", "output"]);
+        assert_eq!(graph.nodes[1].inputs[0].input, "resolution");
+        assert_eq!(graph.nodes[1].inputs[0].type_, "any");
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"info\" -> \"shader\" [label=\"resolution -> resolution\"];"));
+        assert!(dot.contains("\"unused\" [label=\"unused\\ninfo\", style=dashed];"));
+        assert!(dot.contains("\"output\" [label=\"output\\noutput #3\"];"));
+    }
+}
@@ -0,0 +1,149 @@
+//! The imgui context behind the tweak panel and the graph editor. It's given the pointer events of
+//! the window while either is shown, and builds a frame of widgets that the debug renderer draws
+//! over the graph.
+
+use imgui::{Context, DrawData, FontConfig, FontSource, MouseButton, SuspendedContext, Ui};
+use std::time::Instant;
+
+use crate::event::{PointerButton, PointerEvent};
+
+/// Height of the text in logical pixels
+const FONT_SIZE: f32 = 13.0;
+
+/// The glyphs imgui draws text with, as RGBA pixels
+pub struct FontTexture {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// The pixels, row by row from the top
+    pub data: Vec<u8>,
+}
+
+/// Keeps the state of the widgets between frames
+pub struct Gui {
+    /// The imgui context, which is only active while it's being used. There can only be one
+    /// active context at a time, and wallpaper mode has an instance for each monitor.
+    context: Option<SuspendedContext>,
+    /// The font atlas, rasterized for the DPI factor
+    fonts: FontTexture,
+    /// Physical pixels per logical pixel
+    hidpi_factor: f32,
+    /// When the last frame was built, for animations and double clicks
+    last_frame: Instant,
+}
+
+/// Rasterizes the font for a DPI factor, so text is sharp, and scales it back to logical pixels
+fn build_fonts(context: &mut Context, hidpi_factor: f32) -> FontTexture {
+    context.io_mut().font_global_scale = 1.0 / hidpi_factor;
+    let atlas = context.fonts();
+    atlas.clear();
+    atlas.add_font(&[FontSource::DefaultFontData {
+        config: Some(FontConfig {
+            size_pixels: FONT_SIZE * hidpi_factor,
+            ..FontConfig::default()
+        }),
+    }]);
+    let texture = atlas.build_rgba32_texture();
+    FontTexture {
+        width: texture.width,
+        height: texture.height,
+        data: texture.data.to_vec(),
+    }
+}
+
+/// Calls a function with the context activated, and suspends it again
+fn with_context<R, F>(context: &mut Option<SuspendedContext>, f: F) -> R
+where
+    F: FnOnce(&mut Context) -> R,
+{
+    let suspended = context.take().expect("The imgui context wasn't put back");
+    let mut active = match suspended.activate() {
+        Ok(active) => active,
+        Err(_) => panic!("Another imgui context is still active"),
+    };
+    let result = f(&mut active);
+    *context = Some(active.suspend());
+    result
+}
+
+impl Gui {
+    /// Create a new instance, with text scaled by the DPI factor
+    pub fn new(hidpi_factor: f32) -> Self {
+        let mut context = Some(SuspendedContext::create());
+        let fonts = with_context(&mut context, |context| {
+            // Window positions aren't worth a file next to every config
+            context.set_ini_filename(None);
+            build_fonts(context, hidpi_factor)
+        });
+
+        Self {
+            context,
+            fonts,
+            hidpi_factor,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Rasterize the font again for a new DPI factor. The debug renderer has to be made again to
+    /// upload it.
+    pub fn set_hidpi_factor(&mut self, hidpi_factor: f32) {
+        self.fonts = with_context(&mut self.context, |context| {
+            build_fonts(context, hidpi_factor)
+        });
+        self.hidpi_factor = hidpi_factor;
+    }
+
+    /// Pass on a pointer event, returning whether it was over a widget and shouldn't go to the
+    /// graph too
+    pub fn handle_pointer(&mut self, event: &PointerEvent) -> bool {
+        with_context(&mut self.context, |context| {
+            let io = context.io_mut();
+            match *event {
+                PointerEvent::Move(x, y) => io.add_mouse_pos_event([x, y]),
+                PointerEvent::Press(button) => {
+                    io.add_mouse_button_event(mouse_button(button), true)
+                }
+                PointerEvent::Release(button) => {
+                    io.add_mouse_button_event(mouse_button(button), false)
+                }
+                PointerEvent::Scroll(x, y) => io.add_mouse_wheel_event([x, y]),
+            }
+            io.want_capture_mouse
+        })
+    }
+
+    /// Build a frame of widgets for a framebuffer of this size, and draw it with the font atlas
+    pub fn frame<B, D, R>(&mut self, dimensions: (u32, u32), build: B, draw: D) -> R
+    where
+        B: FnOnce(&Ui),
+        D: FnOnce(&DrawData, &FontTexture) -> R,
+    {
+        let now = Instant::now();
+        let elapsed = now - self.last_frame;
+        self.last_frame = now;
+
+        let (hidpi_factor, fonts) = (self.hidpi_factor, &self.fonts);
+        with_context(&mut self.context, |context| {
+            let io = context.io_mut();
+            io.update_delta_time(elapsed);
+            io.display_size = [
+                dimensions.0 as f32 / hidpi_factor,
+                dimensions.1 as f32 / hidpi_factor,
+            ];
+            io.display_framebuffer_scale = [hidpi_factor, hidpi_factor];
+
+            build(context.new_frame());
+            draw(context.render(), fonts)
+        })
+    }
+}
+
+/// Returns imgui's name for a mouse button
+fn mouse_button(button: PointerButton) -> MouseButton {
+    match button {
+        PointerButton::Left => MouseButton::Left,
+        PointerButton::Right => MouseButton::Right,
+        PointerButton::Middle => MouseButton::Middle,
+    }
+}
@@ -0,0 +1,1050 @@
+//! An `Instance` is a window (or headless context) together with the configuration and renderer
+//! that draw to it. Usually there's only one, but wallpaper mode creates one for each monitor.
+
+use anyhow::{format_err, Error};
+use log::{error, info, warn};
+use notify::{self, Watcher};
+use std::{
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+use time::{self, Tm};
+use winit::{EventsLoop, ModifiersState, VirtualKeyCode, WindowId};
+
+use crate::{
+    capture::CaptureMetadata,
+    config::{
+        keybindings::{Action, Keybindings},
+        nodes::NodeConfig,
+        Config, PresentMode,
+    },
+    diagnostics::Diagnostics,
+    editor::{EditorAction, GraphEditor},
+    event::{Event, RendererEvent, SceneId, TimeEvent},
+    gui::Gui,
+    monitor,
+    recorder::Recorder,
+    renderer::{self, Backend, DebugRenderer, Renderer},
+    stats::StatsLogger,
+    tweaks::TweakPanel,
+    util::{format_error, format_title, FpsCounter},
+    webcam::Webcam,
+};
+
+/// If the context is lost again this soon after recovering, the graph isn't built again until it's
+/// reloaded, since it's probably what hangs the GPU
+const CONTEXT_LOSS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Changes applied to a configuration every time it's loaded
+pub type ConfigOverrides = Box<dyn Fn(&mut Config)>;
+
+/// Something made with the graphics context. It's only empty while `Instance::recover` replaces
+/// the context, so the old one is gone before the new one is made.
+struct WithContext<T>(Option<T>);
+
+impl<T> Deref for WithContext<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+            .as_ref()
+            .expect("The graphics context is being replaced")
+    }
+}
+
+impl<T> DerefMut for WithContext<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
+            .as_mut()
+            .expect("The graphics context is being replaced")
+    }
+}
+
+/// Creates a stats logger if the configuration enables one, logging any error instead of failing
+fn new_stats_logger(config: &Config) -> Option<StatsLogger> {
+    if !config.stats.enabled() {
+        return None;
+    }
+    match StatsLogger::new(&config.stats) {
+        Ok(stats) => Some(stats),
+        Err(e) => {
+            error!("{}", format_error(&e));
+            None
+        }
+    }
+}
+
+/// Starts writing to a webcam if the configuration has one, logging any error instead of failing.
+/// A webcam that's already running with the same settings is kept, so video calls aren't
+/// interrupted by reloads.
+fn update_webcam(webcam: Option<Webcam>, config: &Config) -> Option<Webcam> {
+    let webcam_config = config.webcam.as_ref()?;
+    if let Some(webcam) = webcam {
+        if webcam.config() == webcam_config {
+            return Some(webcam);
+        }
+    }
+    match Webcam::start(config, webcam_config) {
+        Ok(webcam) => Some(webcam),
+        Err(e) => {
+            error!("{}", format_error(&e));
+            None
+        }
+    }
+}
+
+fn setup_watches(
+    config_path: &Path,
+    config: &Config,
+    node_files: &[PathBuf],
+) -> Result<(notify::RecommendedWatcher, Receiver<notify::RawEvent>), Error> {
+    // Create a watcher to receive filesystem events
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::RecommendedWatcher::new_raw(sender)?;
+
+    // We still create the watcher, anyway, but if we're not watching anything then does it really
+    // matter?
+    if config.autoreload {
+        // Watch the config file for changes
+        watcher.watch(config_path, notify::RecursiveMode::NonRecursive)?;
+
+        // And the files it references. The nodes know best which files they use, but the ones
+        // that failed to build can't tell us, so the files named in the config are watched too.
+        let mut files = config.files();
+        files.extend(node_files.iter().cloned());
+        files.sort();
+        files.dedup();
+        for path in files {
+            watcher.watch(path, notify::RecursiveMode::NonRecursive)?;
+        }
+    }
+
+    Ok((watcher, receiver))
+}
+
+/// A window and everything needed to draw to it
+pub struct Instance {
+    /// Path to the configuration file
+    config_path: PathBuf,
+    /// The current configuration
+    config: Config,
+    /// Name of the configuration file, for the window title
+    config_name: String,
+    /// Changes applied to the configuration every time it's loaded
+    overrides: Option<ConfigOverrides>,
+    /// The window or headless surface, and the context the renderers draw to it with
+    backend: WithContext<Box<dyn Backend>>,
+    /// Size of the framebuffer the renderer was last told about, for backends that are resized
+    /// without a window event
+    dimensions: (u32, u32),
+    /// The renderer, if it was created successfully
+    renderer: Option<Box<dyn Renderer>>,
+    /// Renders errors and the HUD
+    debug_renderer: WithContext<Box<dyn DebugRenderer>>,
+    /// Sends events to the renderer
+    event_sender: Sender<RendererEvent>,
+    /// The error currently being displayed, if any
+    error: Option<Error>,
+    /// An error from the last reload, a node the watchdog disabled or a shader that didn't build
+    /// again, shown in a corner while the graph keeps rendering
+    reload_error: Option<Error>,
+    /// When the context was last lost, if it has been
+    context_lost_at: Option<Instant>,
+    /// Saves diagnostics when errors occur
+    diagnostics: Diagnostics,
+    /// Watches the config and the files it references
+    watcher: notify::RecommendedWatcher,
+    /// Receives filesystem events from the watcher
+    watch_receiver: Receiver<notify::RawEvent>,
+    /// What the keys do
+    keybindings: Keybindings,
+    /// Whether the performance HUD is shown
+    hud: bool,
+    /// The tweak panel, and the values adjusted in it
+    tweaks: TweakPanel,
+    /// Keeps the state of the widgets of the tweak panel and the graph editor
+    gui: Gui,
+    /// The graph editor, and the changes made in it
+    editor: GraphEditor,
+    /// A notice shown at the bottom of the window, like when an update is available
+    notice: Option<String>,
+    /// Whether rendering is paused
+    paused: bool,
+    /// Whether to render the next frame even though we're paused
+    redraw: bool,
+    /// The animation being recorded, if any
+    recorder: Option<Recorder>,
+    /// Aggregates and logs frame stats, if enabled
+    stats: Option<StatsLogger>,
+    /// The virtual webcam being written to, if any
+    webcam: Option<Webcam>,
+    /// Number of frames drawn so far
+    frame: u64,
+    /// The current window title
+    title: String,
+    /// Counts frames per second for the window title
+    fps_counter: FpsCounter,
+    /// When this instance was created
+    start: Tm,
+}
+
+impl Instance {
+    /// Create a new instance from a configuration, or from the error that occurred while loading it.
+    /// The events loop is only optional for headless instances.
+    pub fn new(
+        config_path: &Path,
+        config: Result<Config, Error>,
+        overrides: Option<ConfigOverrides>,
+        events_loop: Option<&EventsLoop>,
+    ) -> Result<Self, Error> {
+        let (mut config, mut error) = match config {
+            Ok(config) => (config, None),
+            Err(e) => {
+                error!("{}", format_error(&e));
+                (Config::backup()?, Some(e))
+            }
+        };
+        if let Some(ref overrides) = overrides {
+            overrides(&mut config);
+        }
+
+        // The window title can contain the config name, FPS, and time, so we keep it updated
+        let config_name = config_path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = format_title(&config.title, &config_name, 0.0, 0.0);
+
+        // Creates an appropriate renderer for the configuration
+        let (event_sender, event_receiver) = mpsc::channel();
+        let backend = renderer::new_backend(&config, events_loop, &title)?;
+        let renderer = match backend
+            .new_renderer(&config, event_receiver)
+            .and_then(|mut r| {
+                r.warm_up(config.warmup_frames)?;
+                Ok(r)
+            }) {
+            Ok(mut r) => {
+                r.set_profiling(config.hud || config.stats.enabled());
+                Some(r)
+            }
+            Err(e) => {
+                error!("{}", format_error(&e));
+                error = Some(e);
+                None
+            }
+        };
+        // Setup filesystem watches
+        let node_files = renderer.as_ref().map(|r| r.files()).unwrap_or_default();
+        let (watcher, watch_receiver) = setup_watches(config_path, &config, &node_files)?;
+
+        let debug_renderer = backend
+            .new_debug_renderer(config.accessibility.high_contrast, backend.hidpi_factor())?;
+
+        // The window starts hidden if there are warm-up frames to render
+        if let Some(window) = backend.window() {
+            window.show();
+        }
+
+        let stats = new_stats_logger(&config);
+        let webcam = update_webcam(None, &config);
+
+        let mut diagnostics = Diagnostics::new();
+        if let Some(ref error) = error {
+            diagnostics.save(error, config_path, &config);
+        }
+
+        let instance = Self {
+            config_path: config_path.to_path_buf(),
+            keybindings: Keybindings::new(&config.keybindings).unwrap_or_default(),
+            hud: config.hud,
+            tweaks: TweakPanel::new(&config),
+            gui: Gui::new(backend.hidpi_factor()),
+            editor: GraphEditor::new(),
+            config,
+            config_name,
+            overrides,
+            dimensions: backend.dimensions(),
+            backend: WithContext(Some(backend)),
+            renderer,
+            debug_renderer: WithContext(Some(debug_renderer)),
+            event_sender,
+            error,
+            reload_error: None,
+            context_lost_at: None,
+            diagnostics,
+            watcher,
+            watch_receiver,
+            notice: None,
+            paused: false,
+            redraw: false,
+            recorder: None,
+            stats,
+            webcam,
+            frame: 0,
+            title,
+            fps_counter: FpsCounter::new(1.0, 1.0),
+            start: time::now(),
+        };
+        instance.send_window_info()?;
+
+        Ok(instance)
+    }
+
+    /// Whether the configuration asks to check for updates
+    pub fn check_updates(&self) -> bool {
+        self.config.check_updates
+    }
+
+    /// Returns the event a key press is bound to, if any. The graph editor or the tweak panel
+    /// takes over the arrow keys while it's shown.
+    pub fn key_event(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Event> {
+        if self.editor.visible() {
+            match key {
+                VirtualKeyCode::Up => return Some(Event::SelectEdit(-1)),
+                VirtualKeyCode::Down => return Some(Event::SelectEdit(1)),
+                VirtualKeyCode::Left => return Some(Event::Edit(-1, modifiers.shift)),
+                VirtualKeyCode::Right => return Some(Event::Edit(1, modifiers.shift)),
+                VirtualKeyCode::S if modifiers.ctrl => return Some(Event::SaveGraph),
+                _ => (),
+            }
+        } else if self.tweaks.visible() {
+            let step = if modifiers.shift { 0.001 } else { 0.01 };
+            match key {
+                VirtualKeyCode::Up => return Some(Event::SelectTweak(-1)),
+                VirtualKeyCode::Down => return Some(Event::SelectTweak(1)),
+                VirtualKeyCode::Left => return Some(Event::AdjustTweak(-step)),
+                VirtualKeyCode::Right => return Some(Event::AdjustTweak(step)),
+                _ => (),
+            }
+        }
+
+        Some(match self.keybindings.action(key, modifiers)? {
+            Action::Close => Event::Close,
+            Action::Capture => Event::Capture,
+            Action::ToggleRecording => Event::ToggleRecording,
+            Action::Reload => Event::Reload,
+            Action::Restart => Event::Restart,
+            Action::Pause => Event::TogglePause,
+            Action::Step => Event::Step,
+            Action::ToggleHud => Event::ToggleHud,
+            Action::ToggleTweaks => Event::ToggleTweaks,
+            Action::ToggleEditor => Event::ToggleEditor,
+            Action::CyclePresentMode => Event::CyclePresentMode,
+            Action::ToggleVsync => Event::ToggleVsync,
+            Action::ClearFeedback => Event::ClearFeedback,
+            Action::TimeScrub(offset) => Event::Seek(offset),
+            Action::Speed(factor) => Event::ScaleSpeed(factor),
+            Action::SwitchScene(index) => Event::SwitchScene(SceneId::Index(index)),
+            Action::None => return None,
+        })
+    }
+
+    /// Describes the state of the instance in a line, for the control socket
+    pub fn status(&self) -> String {
+        let state = if self.error.is_some() {
+            "error"
+        } else if self.paused {
+            "paused"
+        } else {
+            "running"
+        };
+        format!(
+            "{}: {}, {:.1} fps",
+            self.config_path.display(),
+            state,
+            self.fps_counter.fps()
+        )
+    }
+
+    /// The path the configuration asks for a control socket at, if any
+    pub fn control_socket(&self) -> Option<PathBuf> {
+        self.config.control_socket.clone()
+    }
+
+    /// The most frames per second the configuration asks for, if there's a limit
+    pub fn fps_limit(&self) -> Option<f32> {
+        self.config.fps_limit.filter(|&fps| fps > 0.0)
+    }
+
+    /// Switch to the next supported present mode. Changing it means rebuilding the window, which
+    /// needs the events loop, so this is called instead of handling `Event::CyclePresentMode`.
+    pub fn cycle_present_mode(&mut self, events_loop: &EventsLoop) -> Result<(), Error> {
+        let next = self.backend.present_mode().next();
+        self.set_present_mode(events_loop, next)
+    }
+
+    /// Turn vsync off if it's on, or on if it's off. Like `cycle_present_mode`, this is called
+    /// instead of handling `Event::ToggleVsync`.
+    pub fn toggle_vsync(&mut self, events_loop: &EventsLoop) -> Result<(), Error> {
+        let next = if self.backend.present_mode().vsync() {
+            PresentMode::Immediate
+        } else {
+            PresentMode::Fifo
+        };
+        self.set_present_mode(events_loop, next)
+    }
+
+    /// Rebuild the window with a present mode, and tell the renderer which one we got
+    fn set_present_mode(
+        &mut self,
+        events_loop: &EventsLoop,
+        present_mode: PresentMode,
+    ) -> Result<(), Error> {
+        if self.backend.window().is_none() {
+            return Ok(());
+        }
+        let config = self.window_config();
+
+        let present_mode = self
+            .backend
+            .rebuild(&config, events_loop, &self.title, present_mode)?;
+
+        if self.renderer.is_some() {
+            self.event_sender
+                .send(RendererEvent::PresentMode(present_mode))?;
+        }
+
+        Ok(())
+    }
+
+    /// The configuration with the window's current size and position, so a window made again stays
+    /// where it is
+    fn window_config(&self) -> Config {
+        let mut config = self.config.clone();
+        if let Some(window) = self.backend.window() {
+            if let Some(size) = window.get_inner_size() {
+                let (width, height): (u32, u32) = size.into();
+                config.width = width;
+                config.height = height;
+            }
+            if let Some(position) = window.get_position() {
+                let (x, y): (i32, i32) = position.into();
+                config.position = Some([x, y]);
+            }
+        }
+        config
+    }
+
+    /// Whether the context was lost, like after the GPU was reset because a node hung it. Making
+    /// a new one needs the events loop, so `recover` is called when this is true after drawing.
+    pub fn context_lost(&self) -> bool {
+        self.backend.context_lost()
+    }
+
+    /// Tear down the window and renderers after the context was lost, and make them again with a
+    /// new context. The graph starts over, since its textures went with the old context. If the
+    /// context was lost shortly before too, an error is shown instead until the config is fixed.
+    pub fn recover(&mut self, events_loop: Option<&EventsLoop>) -> Result<(), Error> {
+        let again = self
+            .context_lost_at
+            .map_or(false, |time| time.elapsed() < CONTEXT_LOSS_INTERVAL);
+        self.context_lost_at = Some(Instant::now());
+        warn!("The graphics context was lost, making a new one");
+
+        // Everything made with the old context is dropped before the new one is made
+        let config = self.window_config();
+        self.renderer = None;
+        self.debug_renderer.0 = None;
+        self.backend.0 = None;
+        let backend = renderer::new_backend(&config, events_loop, &self.title)?;
+        let debug_renderer = backend.new_debug_renderer(
+            self.config.accessibility.high_contrast,
+            backend.hidpi_factor(),
+        )?;
+        self.backend = WithContext(Some(backend));
+        self.debug_renderer = WithContext(Some(debug_renderer));
+        self.error = None;
+        self.reload_error = None;
+
+        if again {
+            self.reload_failed(format_err!(
+                "The graphics context was lost twice within {} seconds, probably because a node \
+                 hangs the GPU (the graph is built again when the config changes)",
+                CONTEXT_LOSS_INTERVAL.as_secs()
+            ));
+            Ok(())
+        } else {
+            self.load(self.config.clone(), false)
+        }
+    }
+
+    /// Whether the graph has a camera node, which takes the movement keys
+    pub fn has_camera(&self) -> bool {
+        self.config.nodes.values().any(|node| match node.config {
+            NodeConfig::Camera(_) => true,
+            _ => false,
+        })
+    }
+
+    /// Whether the tweak panel or the graph editor is drawn over the frame. Errors from reloads
+    /// take their place.
+    fn gui_shown(&self) -> bool {
+        (self.tweaks.visible() || self.editor.visible()) && self.reload_error.is_none()
+    }
+
+    /// The ID of the window, if there is one
+    pub fn window_id(&self) -> Option<WindowId> {
+        self.backend.window_id()
+    }
+
+    /// Draw a frame, or the current error if there is one
+    pub fn draw(&mut self) -> Result<(), Error> {
+        self.frame += 1;
+
+        if let Some(ref mut renderer) = self.renderer {
+            renderer.update()?;
+        }
+
+        let mut editor_action = None;
+        match self.error {
+            None => {
+                if let Some(ref mut renderer) = self.renderer {
+                    if !self.paused || self.redraw {
+                        self.redraw = false;
+                        match renderer.render() {
+                            Err(e) => {
+                                error!("{}", format_error(&e));
+                                self.diagnostics.save(&e, &self.config_path, &self.config);
+                                self.error = Some(e);
+                            }
+                            _ => {
+                                if let Some(e) = renderer.take_error() {
+                                    self.diagnostics.save(&e, &self.config_path, &self.config);
+                                    self.reload_error = Some(e);
+                                }
+
+                                // Record before drawing the HUD so it isn't in the recording
+                                let recorded = match self.recorder {
+                                    Some(ref mut recorder) if recorder.wants_frame() => renderer
+                                        .read_frame(recorder.scale())
+                                        .and_then(|frame| recorder.add_frame(frame)),
+                                    _ => Ok(()),
+                                };
+                                if let Err(e) = recorded {
+                                    error!("{}", format_error(&e));
+                                    self.recorder = None;
+                                }
+
+                                self.debug_renderer.save_frame()?;
+
+                                if let Some(ref mut stats) = self.stats {
+                                    stats.add_frame(renderer.stats());
+                                }
+
+                                if self.hud {
+                                    self.debug_renderer.draw_hud(renderer.stats())?;
+                                }
+                                if let Some(ref notice) = self.notice {
+                                    self.debug_renderer.draw_notice(notice)?;
+                                }
+                                if let Some(ref error) = self.reload_error {
+                                    self.debug_renderer.draw_error_overlay(error)?;
+                                } else if self.tweaks.visible() || self.editor.visible() {
+                                    let (tweaks, editor, config, debug_renderer) = (
+                                        &mut self.tweaks,
+                                        &mut self.editor,
+                                        &self.config,
+                                        &mut self.debug_renderer,
+                                    );
+                                    let mut changed = Vec::new();
+                                    self.gui.frame(
+                                        self.backend.dimensions(),
+                                        |ui| {
+                                            if tweaks.visible() {
+                                                changed = tweaks.build(ui);
+                                            }
+                                            if editor.visible() {
+                                                editor_action = editor.build(ui, config);
+                                            }
+                                        },
+                                        |draw_data, fonts| {
+                                            debug_renderer.draw_gui(draw_data, fonts)
+                                        },
+                                    )?;
+                                    for (node, values) in changed {
+                                        self.event_sender
+                                            .send(RendererEvent::SetUniform(node, values))?;
+                                        self.redraw = self.paused;
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    match renderer.swap_buffers() {
+                        Err(e) => {
+                            error!("{}", format_error(&e));
+                            self.diagnostics.save(&e, &self.config_path, &self.config);
+                            self.error = Some(e);
+                        }
+                        _ => (),
+                    }
+                }
+
+                // Layer-shell surfaces and the root window have no window to send resize events,
+                // but their framebuffer resizes when the compositor or the screen says so
+                if self.backend.window().is_none() && self.backend.dimensions() != self.dimensions {
+                    self.send_resize()?;
+                }
+            }
+            Some(ref error) => self.debug_renderer.draw_error(error)?,
+        }
+
+        match editor_action {
+            Some(EditorAction::Edited(config)) => {
+                self.load(config, true)?;
+                self.redraw = self.paused;
+            }
+            Some(EditorAction::Save) => self.handle_event(Event::SaveGraph)?,
+            None => (),
+        }
+
+        if self.recorder.as_ref().map_or(false, Recorder::is_full) {
+            info!("Reached maximum recording duration");
+            self.recorder = None;
+        }
+
+        self.fps_counter.next_frame();
+        if let Some(window) = self.backend.window() {
+            let elapsed = (time::now() - self.start).num_milliseconds() as f32 / 1_000.0;
+            let title = format_title(
+                &self.config.title,
+                &self.config_name,
+                self.fps_counter.fps(),
+                elapsed,
+            );
+            if title != self.title {
+                window.set_title(&title);
+                self.title = title;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check for changes to watched files, returning whether we should reload
+    pub fn poll_watcher(&mut self) -> Result<bool, Error> {
+        let mut reload = false;
+
+        match self.watch_receiver.try_recv() {
+            Ok(notify::RawEvent {
+                path, op: Ok(op), ..
+            }) => {
+                // We listen for both WRITE and REMOVE events because some editors (like vim) will
+                // remove the file and write a new one in its place, and on Linux this will also
+                // remove the watch, so we won't ever receive a WRITE event in this case
+                if op.intersects(notify::op::WRITE | notify::op::REMOVE) {
+                    // Shaders are built again in place while the graph keeps running, unless the
+                    // graph is already broken and needs to be reloaded to be fixed
+                    let recompiled = match (&path, &mut self.renderer) {
+                        (Some(path), Some(renderer))
+                            if self.error.is_none() && self.reload_error.is_none() =>
+                        {
+                            renderer.recompile(path)
+                        }
+                        _ => false,
+                    };
+
+                    if let Some(ref path) = path {
+                        if recompiled {
+                            info!(
+                                "Detected file change for {}, rebuilding shaders...",
+                                path.to_str().unwrap()
+                            );
+                        } else {
+                            info!(
+                                "Detected file change for {}, reloading...",
+                                path.to_str().unwrap()
+                            );
+                        }
+                    } else {
+                        info!("Detected file change, reloading...");
+                    }
+
+                    reload = !recompiled;
+                }
+
+                // If the file was removed and replaced (how certain editors save files)
+                if op.contains(notify::op::REMOVE) {
+                    if let Some(path) = path {
+                        if path.exists() {
+                            self.watcher
+                                .watch(path, notify::RecursiveMode::NonRecursive)?;
+                        }
+                    }
+                }
+            }
+            Err(mpsc::TryRecvError::Disconnected) => error!("Filesystem watcher disconnected"),
+            _ => (),
+        }
+
+        Ok(reload)
+    }
+
+    /// Reload the configuration and rebuild the renderer, optionally carrying over the state of
+    /// the old one. If anything fails, the old renderer keeps running with the error shown over it.
+    fn reload(&mut self, preserve_state: bool) -> Result<(), Error> {
+        let mut config = match Config::parse(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                self.reload_failed(e);
+                return Ok(());
+            }
+        };
+        if let Some(ref overrides) = self.overrides {
+            overrides(&mut config);
+        }
+
+        // Changes made in the editor are either saved to the file by now or given up
+        self.editor.discard();
+        self.load(config, preserve_state)
+    }
+
+    /// Rebuild the renderer for a configuration, like `reload` does after parsing it
+    fn load(&mut self, config: Config, preserve_state: bool) -> Result<(), Error> {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let renderer = if config.renderer == self.backend.name() {
+            self.backend
+                .new_renderer(&config, event_receiver)
+                .and_then(|mut r| {
+                    r.warm_up(config.warmup_frames)?;
+                    Ok(r)
+                })
+        } else {
+            Err(format_err!(
+                "Changing the renderer to {} needs a restart",
+                config.renderer
+            ))
+        };
+        let mut renderer = match renderer {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                // Watch the files of the new config too, so fixing them triggers another reload
+                let node_files = self
+                    .renderer
+                    .as_ref()
+                    .map(|r| r.files())
+                    .unwrap_or_default();
+                let (watcher, watch_receiver) =
+                    setup_watches(&self.config_path, &config, &node_files)?;
+                self.watcher = watcher;
+                self.watch_receiver = watch_receiver;
+                self.reload_failed(e);
+                return Ok(());
+            }
+        };
+
+        if preserve_state {
+            if let Some(ref old) = self.renderer {
+                renderer.inherit_state(&**old);
+            }
+        }
+        renderer.set_profiling(self.hud || config.stats.enabled());
+
+        self.stats = new_stats_logger(&config);
+        self.webcam = update_webcam(self.webcam.take(), &config);
+        self.keybindings = Keybindings::new(&config.keybindings).unwrap_or_default();
+        let restored = self.tweaks.update(&config, preserve_state);
+        self.config = config;
+        self.event_sender = event_sender;
+        for (node, values) in restored {
+            self.event_sender
+                .send(RendererEvent::SetUniform(node, values))?;
+        }
+        self.renderer = Some(renderer);
+        self.error = None;
+        self.reload_error = None;
+
+        // The new renderer's clock starts running, so stop it again
+        if self.paused {
+            self.event_sender
+                .send(RendererEvent::Time(TimeEvent::Pause))?;
+        }
+        // The present mode may have been changed since startup
+        self.event_sender
+            .send(RendererEvent::PresentMode(self.backend.present_mode()))?;
+        self.send_window_info()?;
+
+        self.recreate_debug_renderer()?;
+
+        if let (Some(window), Some(ref icon)) = (self.backend.window(), &self.config.icon) {
+            match renderer::load_icon(&self.config.path_to(icon)) {
+                Ok(icon) => window.set_window_icon(Some(icon)),
+                Err(e) => error!("{}", format_error(&e)),
+            }
+        }
+
+        let node_files = self
+            .renderer
+            .as_ref()
+            .map(|r| r.files())
+            .unwrap_or_default();
+        let (watcher, watch_receiver) =
+            setup_watches(&self.config_path, &self.config, &node_files)?;
+        self.watcher = watcher;
+        self.watch_receiver = watch_receiver;
+
+        Ok(())
+    }
+
+    /// Show an error from a reload, over the old graph if it's still rendering
+    fn reload_failed(&mut self, error: Error) {
+        error!("{}", format_error(&error));
+        self.diagnostics
+            .save(&error, &self.config_path, &self.config);
+        if self.renderer.is_some() && self.error.is_none() {
+            self.reload_error = Some(error);
+            // Draw the overlay even if we're paused
+            self.redraw = true;
+        } else {
+            self.error = Some(error);
+        }
+    }
+
+    /// Put the window back in place after the monitors change, and apply the platform-specific
+    /// window attributes again since some window managers drop them when monitors are removed
+    fn fit_to_monitors(&self) {
+        if let Some(window) = self.backend.window() {
+            if self.config.fullscreen {
+                // The monitor we were fullscreen on might be gone, so use whichever one the window
+                // ended up on
+                info!("Moving fullscreen window to the current monitor");
+                window.set_fullscreen(Some(window.get_current_monitor()));
+            } else if let Some(position) = self.config.position {
+                window.set_position((position[0], position[1]).into());
+                window.set_inner_size((self.config.width, self.config.height).into());
+            }
+
+            crate::platform::window::init(&window, &self.config);
+        }
+    }
+
+    /// Tell the renderer the framebuffer's new size
+    fn send_resize(&mut self) -> Result<(), Error> {
+        self.dimensions = self.backend.dimensions();
+        if self.renderer.is_some() {
+            let (width, height) = self.dimensions;
+            self.event_sender
+                .send(RendererEvent::Resize(width, height))?;
+            self.send_window_info()?;
+        }
+        Ok(())
+    }
+
+    /// Tell the renderer about the window's DPI factor and which parts of it each monitor covers
+    fn send_window_info(&self) -> Result<(), Error> {
+        if let (Some(window), Some(_)) = (self.backend.window(), &self.renderer) {
+            self.event_sender
+                .send(RendererEvent::HidpiFactor(self.backend.hidpi_factor()))?;
+            let monitors = monitor::rects_in(&window);
+            self.event_sender.send(RendererEvent::Monitors(monitors))?;
+        }
+        Ok(())
+    }
+
+    /// Create a new debug renderer for the current config and DPI factor, keeping the last frame
+    fn recreate_debug_renderer(&mut self) -> Result<(), Error> {
+        let mut debug_renderer = self.backend.new_debug_renderer(
+            self.config.accessibility.high_contrast,
+            self.backend.hidpi_factor(),
+        )?;
+        debug_renderer.inherit_frame(&mut **self.debug_renderer);
+        *self.debug_renderer = debug_renderer;
+        Ok(())
+    }
+
+    /// Send a time control event to the renderer, drawing a new frame if we're paused so the
+    /// change is visible
+    fn send_time_event(&mut self, event: TimeEvent) -> Result<(), Error> {
+        if self.renderer.is_some() {
+            self.event_sender.send(RendererEvent::Time(event))?;
+            self.redraw = self.paused;
+        }
+        Ok(())
+    }
+
+    /// Pause or resume rendering, freezing or restarting the time seen by shaders
+    fn set_paused(&mut self, paused: bool) -> Result<(), Error> {
+        if paused != self.paused {
+            self.send_time_event(if paused {
+                TimeEvent::Pause
+            } else {
+                TimeEvent::Resume
+            })?;
+            self.paused = paused;
+        }
+        Ok(())
+    }
+
+    /// Handle an event. `Event::Close` is left to the caller, and `Event::CyclePresentMode` and
+    /// `Event::ToggleVsync` are handled by `cycle_present_mode` and `toggle_vsync`.
+    pub fn handle_event(&mut self, event: Event) -> Result<(), Error> {
+        match event {
+            Event::Pointer(pointer_event) => {
+                // The tweak panel and the graph editor keep the pointer while it's over them, and
+                // are drawn again to respond even while we're paused
+                let over_panel = if self.gui_shown() {
+                    self.redraw = self.paused;
+                    self.gui.handle_pointer(&pointer_event)
+                } else {
+                    false
+                };
+                if !over_panel && self.renderer.is_some() {
+                    self.event_sender
+                        .send(RendererEvent::Pointer(pointer_event))?;
+                }
+            }
+            Event::Key(key_event) => {
+                if self.renderer.is_some() {
+                    self.event_sender.send(RendererEvent::Key(key_event))?;
+                }
+            }
+            Event::Resize(..) => self.send_resize()?,
+            Event::Reload => self.reload(true)?,
+            Event::Restart => self.reload(false)?,
+            Event::Capture => {
+                let capture = self
+                    .config
+                    .capture
+                    .reserve_path(self.frame)
+                    .and_then(|path| {
+                        Ok((path, CaptureMetadata::new(&self.config_path, &self.config)?))
+                    });
+                match capture {
+                    Ok((path, metadata)) => {
+                        info!("Capturing to {}", path.to_string_lossy());
+                        self.event_sender
+                            .send(RendererEvent::Capture(path, metadata))?;
+                    }
+                    Err(e) => error!("{}", format_error(&e)),
+                }
+            }
+            Event::ToggleRecording => {
+                if self.recorder.is_some() {
+                    // Dropping the recorder finishes the recording
+                    self.recorder = None;
+                } else {
+                    let recorder = self
+                        .config
+                        .capture
+                        .reserve_path_with_extension(self.frame, "gif")
+                        .and_then(|path| Recorder::start(path, self.config.recording.clone()));
+                    match recorder {
+                        Ok(recorder) => self.recorder = Some(recorder),
+                        Err(e) => error!("{}", format_error(&e)),
+                    }
+                }
+            }
+            Event::ToggleHud => {
+                self.hud = !self.hud;
+                if let Some(ref mut renderer) = self.renderer {
+                    renderer.set_profiling(self.hud || self.stats.is_some());
+                }
+            }
+            Event::ToggleTweaks => {
+                self.tweaks.toggle();
+                self.redraw = self.paused;
+            }
+            Event::ToggleEditor => {
+                self.editor.toggle();
+                self.redraw = self.paused;
+            }
+            Event::SelectEdit(offset) => {
+                self.editor.select(&self.config, offset);
+                self.redraw = self.paused;
+            }
+            Event::Edit(direction, fine) => {
+                if let Some(config) = self.editor.edit(&self.config, direction, fine) {
+                    self.load(config, true)?;
+                }
+                self.redraw = self.paused;
+            }
+            Event::SaveGraph => {
+                match self.editor.save(&self.config_path) {
+                    Ok(()) => info!("Saved the graph to {}", self.config_path.display()),
+                    Err(e) => error!("{}", format_error(&e)),
+                }
+                self.redraw = self.paused;
+            }
+            Event::SelectTweak(offset) => {
+                self.tweaks.select(offset);
+                self.redraw = self.paused;
+            }
+            Event::AdjustTweak(fraction) => {
+                if let Some((node, values)) = self.tweaks.adjust(fraction) {
+                    if self.renderer.is_some() {
+                        self.event_sender
+                            .send(RendererEvent::SetUniform(node, values))?;
+                    }
+                }
+                self.redraw = self.paused;
+            }
+            Event::Pause => self.set_paused(true)?,
+            Event::Resume => self.set_paused(false)?,
+            Event::TogglePause => {
+                let paused = !self.paused;
+                self.set_paused(paused)?;
+            }
+            Event::Step => {
+                self.set_paused(true)?;
+                self.send_time_event(TimeEvent::Step)?;
+            }
+            Event::Seek(offset) => self.send_time_event(TimeEvent::Seek(offset))?,
+            Event::ScaleSpeed(factor) => self.send_time_event(TimeEvent::ScaleSpeed(factor))?,
+            Event::SwitchScene(scene) => {
+                let start = self.config.timeline.as_ref().and_then(|timeline| {
+                    let index = match scene {
+                        SceneId::Index(index) => Some(index),
+                        SceneId::Name(ref name) => timeline.scene_index(name),
+                    };
+                    index.and_then(|index| timeline.scene_start(index))
+                });
+                match start {
+                    Some(start) => self.send_time_event(TimeEvent::SeekTo(start))?,
+                    None => warn!("There's no scene {:?} to switch to", scene),
+                }
+            }
+            Event::CaptureTo(path) => match CaptureMetadata::new(&self.config_path, &self.config) {
+                Ok(metadata) => {
+                    if self.renderer.is_some() {
+                        info!("Capturing to {}", path.to_string_lossy());
+                        self.event_sender
+                            .send(RendererEvent::Capture(path, metadata))?;
+                    }
+                }
+                Err(e) => error!("{}", format_error(&e)),
+            },
+            Event::SetUniform(name, values) => {
+                if self.renderer.is_some() {
+                    self.event_sender
+                        .send(RendererEvent::SetUniform(name, values))?;
+                    self.redraw = self.paused;
+                }
+            }
+            Event::ClearFeedback => {
+                if self.renderer.is_some() {
+                    self.event_sender.send(RendererEvent::ClearFeedback)?;
+                    self.redraw = self.paused;
+                }
+            }
+            Event::MonitorsChanged => {
+                self.fit_to_monitors();
+                self.send_window_info()?;
+            }
+            Event::HidpiFactorChanged => {
+                // Text in the HUD, error messages and the widgets is scaled too
+                self.gui.set_hidpi_factor(self.backend.hidpi_factor());
+                self.recreate_debug_renderer()?;
+                // The framebuffer's size in pixels changes with the DPI factor
+                self.send_resize()?;
+            }
+            Event::UpdateAvailable(notice) => self.notice = Some(notice),
+            Event::Close | Event::CyclePresentMode | Event::ToggleVsync => (),
+        }
+
+        Ok(())
+    }
+}
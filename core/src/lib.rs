@@ -0,0 +1,66 @@
+//! The config, node graph and renderers behind yotredash, so other programs (games, installations,
+//! tools) can run a yotredash config and use the frames it renders.
+//!
+//! Embedding starts from a [`Config`], loaded from a file with [`Config::from_file`] or checked
+//! from source with [`Config::validate_str`]. Neither reads the command line. A [`Pipeline`]
+//! builds the config's node graph on a headless OpenGL context, and renders it at whatever times
+//! the caller steps it to, returning each frame as RGBA pixels:
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use yotredash_core::{Config, Pipeline};
+//!
+//! # fn main() -> Result<(), anyhow::Error> {
+//! let config = Config::from_file(Path::new("demo/config.yml"))?;
+//! let mut pipeline = Pipeline::new(&config)?;
+//! for frame in 0..60 {
+//!     let image = pipeline.render(frame as f32 / 60.0)?;
+//!     // Upload `image.pixels` to a texture, save it, send it to a projector...
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The items re-exported here, and the events in [`event`], are the stable API. The other
+//! modules are public so the yotredash binary can be built on this crate, and may change between
+//! minor versions.
+
+// Warn if things are missing documentation
+#![warn(missing_docs)]
+
+pub mod benchmark;
+pub mod capture;
+pub mod config;
+pub mod control;
+pub mod diagnostics;
+pub mod editor;
+pub mod event;
+pub mod font;
+pub mod golden;
+pub mod graph;
+pub mod gui;
+pub mod instance;
+pub mod limiter;
+pub mod monitor;
+pub mod opengl;
+pub mod pipeline;
+pub mod platform;
+pub mod plugin;
+pub mod readback;
+pub mod recorder;
+pub mod renderer;
+pub mod scaffold;
+pub mod screenshot;
+pub mod stats;
+pub mod thumbnail;
+pub mod tweaks;
+pub mod util;
+pub mod version;
+pub mod wallpaper;
+pub mod webcam;
+
+pub use crate::{
+    config::{Config, ConfigFormat},
+    pipeline::Pipeline,
+    renderer::{Frame, FrameStats, NodeTiming},
+};
@@ -0,0 +1,92 @@
+//! Caps the frame rate without vsync, so uncapped and headless runs don't keep the GPU busy
+//! rendering frames nobody sees
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long before a deadline we stop sleeping and start spinning, since sleeps can overshoot by
+/// about a scheduler tick
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+/// Returns the deadline for the next frame. Deadlines are spaced evenly from the previous one so
+/// that the average rate is exact, unless we've fallen more than a frame behind, in which case we
+/// start again from now instead of rushing to catch up.
+fn next_deadline(previous: Instant, now: Instant, interval: Duration) -> Instant {
+    let next = previous + interval;
+    if now > next + interval {
+        now + interval
+    } else {
+        next
+    }
+}
+
+/// Waits between frames to keep to a frame rate
+pub struct FrameLimiter {
+    /// When the next frame should start
+    deadline: Instant,
+}
+
+impl FrameLimiter {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Self {
+            deadline: Instant::now(),
+        }
+    }
+
+    /// Wait until the next frame should start, or return straight away if there's no limit
+    pub fn wait(&mut self, fps_limit: Option<f32>) {
+        let interval = match fps_limit {
+            Some(fps) if fps > 0.0 => Duration::from_nanos((1e9 / f64::from(fps)) as u64),
+            _ => {
+                self.deadline = Instant::now();
+                return;
+            }
+        };
+
+        self.deadline = next_deadline(self.deadline, Instant::now(), interval);
+
+        // Sleep for most of the wait, then spin for the rest, which is much more precise
+        let now = Instant::now();
+        if self.deadline > now + SPIN_MARGIN {
+            thread::sleep(self.deadline - now - SPIN_MARGIN);
+        }
+        while Instant::now() < self.deadline {
+            thread::yield_now();
+        }
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_deadline;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn paces_frames_evenly() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(10);
+
+        // A frame that finished early or a little late keeps to the schedule
+        assert_eq!(
+            next_deadline(start, start + Duration::from_millis(3), interval),
+            start + interval
+        );
+        assert_eq!(
+            next_deadline(start, start + Duration::from_millis(15), interval),
+            start + interval
+        );
+
+        // Falling far behind starts the schedule again
+        let now = start + Duration::from_millis(35);
+        assert_eq!(next_deadline(start, now, interval), now + interval);
+    }
+}
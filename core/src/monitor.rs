@@ -0,0 +1,234 @@
+//! Keeps track of the connected monitors so we can react when they change, and works out which
+//! parts of the screen they cover
+//!
+//! winit doesn't tell us when monitors are connected or disconnected, so instead we periodically
+//! compare the list of available monitors with the last one we saw.
+
+use log::warn;
+use time;
+use winit::{
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+    EventsLoop, MonitorId, Window,
+};
+
+use crate::config::Config;
+
+/// How often to check for changes to the monitors (nanoseconds)
+const POLL_INTERVAL: u64 = 1_000_000_000;
+
+/// The parts of a monitor we care about
+#[derive(Clone, Debug, PartialEq)]
+struct MonitorInfo {
+    /// Name of the monitor
+    name: Option<String>,
+    /// Position of the monitor, in physical pixels
+    position: (f64, f64),
+    /// Dimensions of the monitor, in physical pixels
+    dimensions: (f64, f64),
+    /// The DPI factor of the monitor
+    hidpi_factor: f64,
+}
+
+impl MonitorInfo {
+    /// Get the info for a monitor
+    fn of(monitor: &MonitorId) -> Self {
+        let position = monitor.get_position();
+        let dimensions = monitor.get_dimensions();
+        Self {
+            name: monitor.get_name(),
+            position: (position.x, position.y),
+            dimensions: (dimensions.width, dimensions.height),
+            hidpi_factor: monitor.get_hidpi_factor(),
+        }
+    }
+}
+
+/// Find a monitor by name or index, falling back to the primary monitor if there's no such monitor
+pub fn find(events_loop: &EventsLoop, name_or_index: Option<&str>) -> MonitorId {
+    let name_or_index = match name_or_index {
+        Some(name_or_index) => name_or_index,
+        None => return events_loop.get_primary_monitor(),
+    };
+
+    let monitor = events_loop
+        .get_available_monitors()
+        .enumerate()
+        .find(|(i, monitor)| {
+            monitor.get_name().as_ref().map(String::as_str) == Some(name_or_index)
+                || i.to_string() == name_or_index
+        })
+        .map(|(_, monitor)| monitor);
+    monitor.unwrap_or_else(|| {
+        warn!(
+            "There's no monitor called {}, using the primary monitor",
+            name_or_index
+        );
+        events_loop.get_primary_monitor()
+    })
+}
+
+/// The area of the screen covered by a monitor or a group of them
+#[derive(Clone, Copy, Debug)]
+pub struct Geometry {
+    /// Position of the top left corner, in physical pixels
+    pub position: PhysicalPosition,
+    /// Size, in physical pixels
+    pub size: PhysicalSize,
+    /// The DPI factor of the monitor the area is on
+    pub hidpi_factor: f64,
+}
+
+impl Geometry {
+    /// The area covered by a monitor
+    pub fn of(monitor: &MonitorId) -> Self {
+        Self {
+            position: monitor.get_position(),
+            size: monitor.get_dimensions(),
+            hidpi_factor: monitor.get_hidpi_factor(),
+        }
+    }
+
+    /// The smallest area covering all of the monitors
+    pub fn spanning(monitors: &[MonitorId], hidpi_factor: f64) -> Self {
+        let (mut left, mut top) = (std::f64::MAX, std::f64::MAX);
+        let (mut right, mut bottom) = (std::f64::MIN, std::f64::MIN);
+        for monitor in monitors {
+            let geometry = Self::of(monitor);
+            left = left.min(geometry.position.x);
+            top = top.min(geometry.position.y);
+            right = right.max(geometry.position.x + geometry.size.width);
+            bottom = bottom.max(geometry.position.y + geometry.size.height);
+        }
+
+        Self {
+            position: PhysicalPosition::new(left, top),
+            size: PhysicalSize::new(right - left, bottom - top),
+            hidpi_factor,
+        }
+    }
+
+    /// The smallest area covering every monitor that's connected
+    pub fn all(events_loop: &EventsLoop) -> Self {
+        let monitors: Vec<MonitorId> = events_loop.get_available_monitors().collect();
+        let hidpi_factor = events_loop.get_primary_monitor().get_hidpi_factor();
+        Self::spanning(&monitors, hidpi_factor)
+    }
+
+    /// Turn a config into a borderless window covering this area
+    pub fn apply(self, config: &mut Config) {
+        let position: LogicalPosition = self.position.to_logical(self.hidpi_factor);
+        let size: LogicalSize = self.size.to_logical(self.hidpi_factor);
+
+        config.position = Some([position.x.round() as i32, position.y.round() as i32]);
+        config.width = size.width.round() as u32;
+        config.height = size.height.round() as u32;
+        config.decorations = false;
+        config.maximize = false;
+        config.fullscreen = false;
+    }
+
+    /// The part of a window this area covers, as `[x, y, width, height]` in pixels from the bottom
+    /// left corner of the window like `gl_FragCoord`
+    pub fn rect_in(self, window: Geometry) -> [f32; 4] {
+        let left = self.position.x - window.position.x;
+        let top = self.position.y - window.position.y;
+        let bottom = window.size.height - (top + self.size.height);
+        [
+            left as f32,
+            bottom as f32,
+            self.size.width as f32,
+            self.size.height as f32,
+        ]
+    }
+}
+
+/// The parts of a window each monitor covers, in the order winit lists the monitors. See
+/// `Geometry::rect_in`.
+pub fn rects_in(window: &Window) -> Vec<[f32; 4]> {
+    let hidpi_factor = window.get_hidpi_factor();
+    let (position, size) = match (window.get_inner_position(), window.get_inner_size()) {
+        (Some(position), Some(size)) => (position, size),
+        _ => return Vec::new(),
+    };
+    let bounds = Geometry {
+        position: position.to_physical(hidpi_factor),
+        size: size.to_physical(hidpi_factor),
+        hidpi_factor,
+    };
+
+    window
+        .get_available_monitors()
+        .map(|monitor| Geometry::of(&monitor).rect_in(bounds))
+        .collect()
+}
+
+/// Notices when monitors are connected, disconnected, or rearranged
+pub struct MonitorWatcher {
+    /// The monitors as of the last check
+    monitors: Vec<MonitorInfo>,
+    /// When we last checked (nanoseconds)
+    last_check: u64,
+}
+
+impl MonitorWatcher {
+    /// Create a new instance
+    pub fn new(events_loop: &EventsLoop) -> Self {
+        Self {
+            monitors: Self::query(events_loop),
+            last_check: time::precise_time_ns(),
+        }
+    }
+
+    /// Get the info for all currently available monitors
+    fn query(events_loop: &EventsLoop) -> Vec<MonitorInfo> {
+        events_loop
+            .get_available_monitors()
+            .map(|monitor| MonitorInfo::of(&monitor))
+            .collect()
+    }
+
+    /// Returns whether the monitors have changed since the last time this returned true
+    pub fn poll(&mut self, events_loop: &EventsLoop) -> bool {
+        let now = time::precise_time_ns();
+        if now - self.last_check < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = now;
+
+        let monitors = Self::query(events_loop);
+        if monitors == self.monitors {
+            return false;
+        }
+
+        self.monitors = monitors;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Geometry;
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+    fn geometry(x: f64, y: f64, width: f64, height: f64) -> Geometry {
+        Geometry {
+            position: PhysicalPosition::new(x, y),
+            size: PhysicalSize::new(width, height),
+            hidpi_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn rect_in_window() {
+        // Two monitors side by side with different heights, spanned by one window
+        let window = geometry(0.0, 0.0, 3840.0, 1440.0);
+        assert_eq!(
+            geometry(0.0, 0.0, 2560.0, 1440.0).rect_in(window),
+            [0.0, 0.0, 2560.0, 1440.0]
+        );
+        assert_eq!(
+            geometry(2560.0, 0.0, 1280.0, 1024.0).rect_in(window),
+            [2560.0, 416.0, 1280.0, 1024.0]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Keeps a copy of the last successfully rendered frame, so errors can be drawn over it instead of
+//! a black screen
+
+use anyhow::Error;
+use glium::{
+    backend::Facade,
+    texture::{MipmapsOption, Texture2d},
+    uniforms::MagnifySamplerFilter,
+    BlitTarget, Program, Rect, Surface,
+};
+use std::rc::Rc;
+
+use super::{
+    quad::{self, Quad},
+    UniformsStorageVec,
+};
+
+/// How bright the frame is drawn behind an error
+const BRIGHTNESS: f32 = 0.3;
+
+/// Passes the texture coordinates of the quad's corners to the fragment shader
+const VERTEX: &str = "
+    #version 140
+
+    in vec2 position;
+    out vec2 uv;
+
+    void main() {
+        uv = position * 0.5 + 0.5;
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+";
+
+const FRAGMENT: &str = "
+    #version 140
+
+    in vec2 uv;
+    out vec4 color;
+
+    uniform sampler2D frame;
+    uniform float brightness;
+
+    void main() {
+        color = vec4(texture(frame, uv).rgb * brightness, 1.0);
+    }
+";
+
+/// A dimmed copy of the last good frame
+pub struct Backdrop {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// Shader program for drawing the frame
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// The saved frame, if one has been rendered
+    frame: Option<Texture2d>,
+}
+
+impl Backdrop {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        let program = quad::program_with_vertex(&**facade, VERTEX, FRAGMENT)?;
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            program,
+            quad: Quad::new(&**facade)?,
+            frame: None,
+        })
+    }
+
+    /// Copy the frame that was just rendered to the back buffer
+    pub fn save(&mut self) -> Result<(), Error> {
+        let (width, height) = self.facade.get_context().get_framebuffer_dimensions();
+        let resized = self
+            .frame
+            .as_ref()
+            .map_or(true, |frame| frame.dimensions() != (width, height));
+        if resized {
+            self.frame = Some(Texture2d::empty_with_mipmaps(
+                &*self.facade,
+                MipmapsOption::NoMipmap,
+                width,
+                height,
+            )?);
+        }
+
+        if let Some(ref frame) = self.frame {
+            let source_rect = Rect {
+                left: 0,
+                bottom: 0,
+                width,
+                height,
+            };
+            let target_rect = BlitTarget {
+                left: 0,
+                bottom: 0,
+                width: width as i32,
+                height: height as i32,
+            };
+            frame.as_surface().blit_from_frame(
+                &source_rect,
+                &target_rect,
+                MagnifySamplerFilter::Nearest,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Take the saved frame from another instance, so it survives a reload
+    pub fn inherit(&mut self, old: &mut Backdrop) {
+        self.frame = old.frame.take();
+    }
+
+    /// Draw the saved frame dimmed, or clear to black if there isn't one
+    pub fn draw<S: Surface>(&self, surface: &mut S) -> Result<(), Error> {
+        surface.clear_color(0.0, 0.0, 0.0, 1.0);
+        if let Some(ref frame) = self.frame {
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("frame", frame.sampled());
+            uniforms.push("brightness", BRIGHTNESS);
+            self.quad
+                .draw(surface, &self.program, &uniforms, &Default::default())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! The OpenGL `Backend`, which makes a window with glutin, or a headless context
+
+use anyhow::{format_err, Error};
+use glium::backend::{glutin::Display, Facade};
+use std::{cell::Ref, rc::Rc, sync::mpsc::Receiver};
+use winit::{EventsLoop, Window};
+
+use super::renderer::{self, OpenGLDebugRenderer, OpenGLRenderer};
+use crate::{
+    config::{Config, PresentMode},
+    event::RendererEvent,
+    renderer::{Backend, DebugRenderer, Renderer},
+};
+
+/// A window or headless context drawn to with OpenGL
+pub struct OpenGLBackend {
+    /// The facade renderers draw with
+    facade: Rc<dyn Facade>,
+    /// The window, if we're not headless
+    display: Option<Display>,
+    /// How frames are presented
+    present_mode: PresentMode,
+}
+
+impl OpenGLBackend {
+    /// Create a window, or a headless context if the configuration asks for one
+    pub fn new(
+        config: &Config,
+        events_loop: Option<&EventsLoop>,
+        title: &str,
+    ) -> Result<Self, Error> {
+        let (facade, display) = renderer::new_facade(config, events_loop, title)?;
+        Ok(Self {
+            facade,
+            display,
+            present_mode: config.present_mode().supported(),
+        })
+    }
+}
+
+impl Backend for OpenGLBackend {
+    fn name(&self) -> &'static str {
+        "opengl"
+    }
+
+    fn window(&self) -> Option<Ref<'_, Window>> {
+        self.display
+            .as_ref()
+            .map(|display| Ref::map(display.gl_window(), |gl_window| gl_window.window()))
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.facade.get_context().get_framebuffer_dimensions()
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    fn context_lost(&self) -> bool {
+        self.facade.get_context().is_context_lost()
+    }
+
+    fn rebuild(
+        &mut self,
+        config: &Config,
+        events_loop: &EventsLoop,
+        title: &str,
+        present_mode: PresentMode,
+    ) -> Result<PresentMode, Error> {
+        let display = match self.display {
+            Some(ref display) => display,
+            None => return Ok(self.present_mode),
+        };
+
+        let window_builder = renderer::window_builder(config, events_loop, title);
+        let (context_builder, present_mode) =
+            renderer::context_builder(present_mode, config.multisampling);
+        display
+            .rebuild(window_builder, context_builder, events_loop)
+            .map_err(|error| format_err!("{}", error))?;
+        if let Some(position) = config.position {
+            display
+                .gl_window()
+                .window()
+                .set_position((position[0], position[1]).into());
+        }
+        crate::platform::window::init(display.gl_window().window(), config);
+
+        self.present_mode = present_mode;
+        Ok(present_mode)
+    }
+
+    fn new_renderer(
+        &self,
+        config: &Config,
+        receiver: Receiver<RendererEvent>,
+    ) -> Result<Box<dyn Renderer>, Error> {
+        Ok(Box::new(OpenGLRenderer::new(
+            config,
+            &self.facade,
+            receiver,
+        )?))
+    }
+
+    fn new_debug_renderer(
+        &self,
+        high_contrast: bool,
+        hidpi_factor: f32,
+    ) -> Result<Box<dyn DebugRenderer>, Error> {
+        Ok(Box::new(OpenGLDebugRenderer::new(
+            &self.facade,
+            high_contrast,
+            hidpi_factor,
+        )?))
+    }
+}
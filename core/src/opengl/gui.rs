@@ -0,0 +1,203 @@
+//! Draws the widgets built by `crate::gui` over the frame
+
+use anyhow::{format_err, Error};
+use glium::{
+    backend::Facade,
+    implement_vertex,
+    index::{IndexBuffer, PrimitiveType},
+    program::ProgramCreationInput,
+    texture::{ClientFormat, MipmapsOption, RawImage2d},
+    Blend, DrawParameters, Program, Rect, Surface, Texture2d, VertexBuffer,
+};
+use imgui::{DrawCmd, DrawCmdParams, DrawData, DrawVert};
+use std::{borrow::Cow, rc::Rc, slice};
+
+use super::{profiler, UniformsStorageVec};
+use crate::gui::FontTexture;
+
+const VERTEX: &str = "
+    #version 140
+
+    in vec2 pos;
+    in vec2 uv;
+    in uvec4 col;
+    out vec2 texCoords;
+    out vec4 vertexColor;
+
+    uniform mat4 projection;
+
+    void main() {
+        texCoords = uv;
+        vertexColor = vec4(col) / 255.0;
+        gl_Position = projection * vec4(pos, 0.0, 1.0);
+    }
+";
+
+const FRAGMENT: &str = "
+    #version 140
+
+    in vec2 texCoords;
+    in vec4 vertexColor;
+    out vec4 color;
+
+    uniform sampler2D fonts;
+
+    void main() {
+        color = vertexColor * texture(fonts, texCoords);
+    }
+";
+
+/// A vertex laid out the same way as imgui's `DrawVert`
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct Vertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    col: [u8; 4],
+}
+implement_vertex!(Vertex, pos, uv, col);
+
+/// Returns imgui's vertices as ours, without copying them
+fn vertices(vertices: &[DrawVert]) -> &[Vertex] {
+    // Both are `repr(C)` with the same fields, so they have the same layout
+    unsafe { slice::from_raw_parts(vertices.as_ptr() as *const Vertex, vertices.len()) }
+}
+
+/// Draws imgui frames
+pub struct GuiRenderer {
+    /// Facade for interacting with OpenGL
+    facade: Rc<dyn Facade>,
+    /// The shader program it uses for drawing
+    program: Program,
+    /// The font atlas, uploaded when the first frame is drawn. It only changes with the DPI
+    /// factor, which gets a new debug renderer.
+    fonts: Option<Texture2d>,
+}
+
+impl GuiRenderer {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        let input = ProgramCreationInput::SourceCode {
+            vertex_shader: VERTEX,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: None,
+            fragment_shader: FRAGMENT,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        };
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            program: Program::new(&**facade, input)?,
+            fonts: None,
+        })
+    }
+
+    /// Draw a frame of widgets over the surface
+    pub fn draw<S: Surface>(
+        &mut self,
+        surface: &mut S,
+        draw_data: &DrawData,
+        fonts: &FontTexture,
+    ) -> Result<(), Error> {
+        let texture = match self.fonts {
+            Some(ref texture) => texture,
+            None => {
+                let image = RawImage2d {
+                    data: Cow::Borrowed(&fonts.data[..]),
+                    width: fonts.width,
+                    height: fonts.height,
+                    format: ClientFormat::U8U8U8U8,
+                };
+                let texture =
+                    Texture2d::with_mipmaps(&*self.facade, image, MipmapsOption::NoMipmap)?;
+                &*self.fonts.get_or_insert(texture)
+            }
+        };
+
+        let [left, top] = draw_data.display_pos;
+        let [width, height] = draw_data.display_size;
+        let [scale_x, scale_y] = draw_data.framebuffer_scale;
+        if width <= 0.0 || height <= 0.0 {
+            return Ok(());
+        }
+        let (_, surface_height) = surface.get_dimensions();
+
+        // Maps the display rectangle to the whole surface, with y going down
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let projection = [
+            [ 2.0 / width,                0.0,                         0.0,  0.0],
+            [ 0.0,                       -2.0 / height,                0.0,  0.0],
+            [ 0.0,                        0.0,                         1.0,  0.0],
+            [-2.0 * left / width - 1.0,   2.0 * top / height + 1.0,    0.0,  1.0],
+        ];
+
+        for list in draw_data.draw_lists() {
+            let vertex_buffer = VertexBuffer::new(&*self.facade, vertices(list.vtx_buffer()))?;
+            let index_buffer = IndexBuffer::new(
+                &*self.facade,
+                PrimitiveType::TrianglesList,
+                list.idx_buffer(),
+            )?;
+
+            for command in list.commands() {
+                let (count, clip_rect, vtx_offset, idx_offset) = match command {
+                    DrawCmd::Elements {
+                        count,
+                        cmd_params:
+                            DrawCmdParams {
+                                clip_rect,
+                                vtx_offset,
+                                idx_offset,
+                                ..
+                            },
+                    } => (count, clip_rect, vtx_offset, idx_offset),
+                    // We don't add callbacks, and the render state is set for every command
+                    DrawCmd::ResetRenderState | DrawCmd::RawCallback { .. } => continue,
+                };
+
+                // Clip rectangles are in display coordinates from the top left, and scissor
+                // rectangles in pixels from the bottom left
+                let x1 = ((clip_rect[0] - left) * scale_x).max(0.0);
+                let y1 = ((clip_rect[1] - top) * scale_y).max(0.0);
+                let x2 = (clip_rect[2] - left) * scale_x;
+                let y2 = (clip_rect[3] - top) * scale_y;
+                if x2 <= x1 || y2 <= y1 {
+                    continue;
+                }
+                let scissor = Rect {
+                    left: x1 as u32,
+                    bottom: (surface_height as f32 - y2).max(0.0) as u32,
+                    width: (x2 - x1) as u32,
+                    height: (y2 - y1) as u32,
+                };
+
+                let mut uniforms = UniformsStorageVec::new();
+                uniforms.push("projection", projection);
+                uniforms.push("fonts", texture.sampled());
+
+                let query = profiler::active_query();
+                let params = DrawParameters {
+                    blend: Blend::alpha_blending(),
+                    scissor: Some(scissor),
+                    ..profiler::draw_parameters(&query)
+                };
+
+                let out_of_range = || format_err!("imgui drew past the end of its buffers");
+                surface.draw(
+                    vertex_buffer.slice(vtx_offset..).ok_or_else(out_of_range)?,
+                    index_buffer
+                        .slice(idx_offset..idx_offset + count)
+                        .ok_or_else(out_of_range)?,
+                    &self.program,
+                    &uniforms,
+                    &params,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
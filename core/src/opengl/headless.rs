@@ -0,0 +1,239 @@
+//! Headless OpenGL contexts. With a display server, glutin makes a context without a visible
+//! window. Without one (like on CI or a server) we fall back to OSMesa, which renders in software,
+//! so thumbnails and screenshots work anywhere Mesa is installed. Either way the renderer draws
+//! into an offscreen texture at the configured size, since neither context has a real window.
+
+use anyhow::Error;
+use glium::{
+    backend::{glutin::headless::Headless, Facade},
+    glutin::{self, ContextBuilder},
+};
+use log::warn;
+use std::rc::Rc;
+use winit::EventsLoop;
+
+use crate::config::Config;
+
+/// Create a headless facade, with glutin if there's an events loop and OSMesa otherwise
+pub fn new_facade(
+    config: &Config,
+    events_loop: Option<&EventsLoop>,
+) -> Result<Rc<dyn Facade>, Error> {
+    if let Some(events_loop) = events_loop {
+        match glutin::Context::new(events_loop, ContextBuilder::new(), false) {
+            Ok(context) => return Ok(Rc::new(Headless::new(context)?)),
+            Err(e) => warn!("Could not create a headless context, trying OSMesa: {}", e),
+        }
+    }
+
+    osmesa_facade(config.width, config.height)
+}
+
+/// Create a facade using OSMesa
+#[cfg(unix)]
+fn osmesa_facade(width: u32, height: u32) -> Result<Rc<dyn Facade>, Error> {
+    let facade = osmesa::OsMesaFacade::new(width, height)?;
+    log::info!("Rendering headless with OSMesa");
+    Ok(Rc::new(facade))
+}
+
+/// OSMesa isn't available here, so there's no way to render without a display server
+#[cfg(not(unix))]
+fn osmesa_facade(_width: u32, _height: u32) -> Result<Rc<dyn Facade>, Error> {
+    anyhow::bail!("Could not create a headless OpenGL context without a display server");
+}
+
+#[cfg(unix)]
+mod osmesa {
+    //! A glium backend for OSMesa. The library is loaded at runtime, so it's only needed when
+    //! there's no display server.
+
+    use anyhow::{bail, format_err, Error};
+    use glium::{
+        backend::{Backend, Context, Facade},
+        debug::DebugCallbackBehavior,
+        SwapBuffersError,
+    };
+    use std::{
+        cell::RefCell,
+        ffi::CString,
+        os::raw::{c_char, c_int, c_uint, c_void},
+        ptr,
+        rc::Rc,
+    };
+
+    /// Opaque OSMesa context handle
+    type OsMesaContext = *mut c_void;
+
+    const GL_RGBA: c_uint = 0x1908;
+    const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+    const OSMESA_FORMAT: c_int = 0x22;
+    const OSMESA_DEPTH_BITS: c_int = 0x30;
+    const OSMESA_PROFILE: c_int = 0x33;
+    const OSMESA_CORE_PROFILE: c_int = 0x34;
+    const OSMESA_CONTEXT_MAJOR_VERSION: c_int = 0x36;
+    const OSMESA_CONTEXT_MINOR_VERSION: c_int = 0x37;
+
+    /// Names the library might have, newest first
+    const LIBRARY_NAMES: [&str; 3] = ["libOSMesa.so.8", "libOSMesa.so.6", "libOSMesa.so"];
+
+    /// The OSMesa functions we use
+    struct Library {
+        /// `OSMesaCreateContextAttribs`, which can make core profile contexts (Mesa 12 and later)
+        create_context_attribs:
+            Option<unsafe extern "C" fn(*const c_int, OsMesaContext) -> OsMesaContext>,
+        /// `OSMesaCreateContextExt`
+        create_context_ext:
+            unsafe extern "C" fn(c_uint, c_int, c_int, c_int, OsMesaContext) -> OsMesaContext,
+        /// `OSMesaMakeCurrent`
+        make_current: unsafe extern "C" fn(OsMesaContext, *mut c_void, c_uint, c_int, c_int) -> u8,
+        /// `OSMesaGetCurrentContext`
+        get_current_context: unsafe extern "C" fn() -> OsMesaContext,
+        /// `OSMesaGetProcAddress`
+        get_proc_address: unsafe extern "C" fn(*const c_char) -> *const c_void,
+        /// `OSMesaDestroyContext`
+        destroy_context: unsafe extern "C" fn(OsMesaContext),
+    }
+
+    /// Look up a symbol in a library loaded with `dlopen`
+    unsafe fn symbol(handle: *mut c_void, name: &str) -> Option<*mut c_void> {
+        let name = CString::new(name).unwrap();
+        let symbol = libc::dlsym(handle, name.as_ptr());
+        if symbol.is_null() {
+            None
+        } else {
+            Some(symbol)
+        }
+    }
+
+    impl Library {
+        /// Load the library. It's never unloaded, since contexts may outlive any one facade.
+        fn load() -> Result<Self, Error> {
+            unsafe {
+                let handle = LIBRARY_NAMES
+                    .iter()
+                    .map(|name| {
+                        let name = CString::new(*name).unwrap();
+                        libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL)
+                    })
+                    .find(|handle| !handle.is_null())
+                    .ok_or_else(|| format_err!("Could not load OSMesa, is it installed?"))?;
+
+                let required = |name: &str| {
+                    symbol(handle, name)
+                        .ok_or_else(|| format_err!("OSMesa is missing the function {}", name))
+                };
+                Ok(Self {
+                    create_context_attribs: symbol(handle, "OSMesaCreateContextAttribs")
+                        .map(|f| std::mem::transmute(f)),
+                    create_context_ext: std::mem::transmute(required("OSMesaCreateContextExt")?),
+                    make_current: std::mem::transmute(required("OSMesaMakeCurrent")?),
+                    get_current_context: std::mem::transmute(required("OSMesaGetCurrentContext")?),
+                    get_proc_address: std::mem::transmute(required("OSMesaGetProcAddress")?),
+                    destroy_context: std::mem::transmute(required("OSMesaDestroyContext")?),
+                })
+            }
+        }
+    }
+
+    /// An OSMesa context rendering into a buffer in memory
+    struct OsMesaBackend {
+        /// The OSMesa functions
+        library: Library,
+        /// The context
+        context: OsMesaContext,
+        /// The buffer the default framebuffer is stored in
+        buffer: RefCell<Vec<u8>>,
+        /// Size of the buffer in pixels
+        dimensions: (u32, u32),
+    }
+
+    impl Drop for OsMesaBackend {
+        fn drop(&mut self) {
+            unsafe { (self.library.destroy_context)(self.context) };
+        }
+    }
+
+    unsafe impl Backend for OsMesaBackend {
+        fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+            // Rendering goes straight into the buffer
+            Ok(())
+        }
+
+        unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+            let symbol = CString::new(symbol).unwrap();
+            (self.library.get_proc_address)(symbol.as_ptr())
+        }
+
+        fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+            self.dimensions
+        }
+
+        fn is_current(&self) -> bool {
+            unsafe { (self.library.get_current_context)() == self.context }
+        }
+
+        unsafe fn make_current(&self) {
+            let (width, height) = self.dimensions;
+            (self.library.make_current)(
+                self.context,
+                self.buffer.borrow_mut().as_mut_ptr() as *mut c_void,
+                GL_UNSIGNED_BYTE,
+                width as c_int,
+                height as c_int,
+            );
+        }
+    }
+
+    /// A facade for an OSMesa context
+    pub struct OsMesaFacade {
+        /// The glium context
+        context: Rc<Context>,
+    }
+
+    impl OsMesaFacade {
+        /// Create a context with a default framebuffer of the given size
+        pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+            let library = Library::load()?;
+
+            let context = unsafe {
+                match library.create_context_attribs {
+                    Some(create_context_attribs) => {
+                        // The shaders need at least OpenGL 3.1, which Mesa only offers for core
+                        // profile contexts
+                        #[cfg_attr(rustfmt, rustfmt_skip)]
+                        let attribs = [
+                            OSMESA_FORMAT, GL_RGBA as c_int,
+                            OSMESA_DEPTH_BITS, 24,
+                            OSMESA_PROFILE, OSMESA_CORE_PROFILE,
+                            OSMESA_CONTEXT_MAJOR_VERSION, 3,
+                            OSMESA_CONTEXT_MINOR_VERSION, 3,
+                            0,
+                        ];
+                        create_context_attribs(attribs.as_ptr(), ptr::null_mut())
+                    }
+                    None => (library.create_context_ext)(GL_RGBA, 24, 0, 0, ptr::null_mut()),
+                }
+            };
+            if context.is_null() {
+                bail!("Could not create an OSMesa context");
+            }
+
+            let backend = OsMesaBackend {
+                library,
+                context,
+                buffer: RefCell::new(vec![0; width as usize * height as usize * 4]),
+                dimensions: (width, height),
+            };
+            let context = unsafe { Context::new(backend, true, DebugCallbackBehavior::default())? };
+
+            Ok(Self { context })
+        }
+    }
+
+    impl Facade for OsMesaFacade {
+        fn get_context(&self) -> &Rc<Context> {
+            &self.context
+        }
+    }
+}
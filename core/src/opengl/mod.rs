@@ -0,0 +1,188 @@
+//! Contains everything for the OpenGL renderer pipeline
+
+pub mod backdrop;
+pub mod backend;
+pub mod gui;
+pub mod headless;
+pub mod nodes;
+pub mod profiler;
+pub mod program_cache;
+pub mod quad;
+pub mod renderer;
+pub mod resources;
+pub mod shader_log;
+pub mod target;
+pub mod text;
+
+use glium::{
+    texture::{Texture1d, Texture2d, Texture3d},
+    uniforms::{Sampler, UniformValue, Uniforms},
+};
+use std::{cell::RefCell, collections::HashSet, ops::Deref, rc::Rc};
+
+thread_local! {
+    /// Uniform names made at runtime, so each one is only allocated once
+    static NAMES: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// Returns the interned copy of a uniform name, for names that aren't known at compile time
+pub fn intern(name: &str) -> Rc<str> {
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        if let Some(interned) = names.get(name) {
+            return Rc::clone(interned);
+        }
+        let interned: Rc<str> = Rc::from(name);
+        names.insert(Rc::clone(&interned));
+        interned
+    })
+}
+
+/// The name of a uniform, either borrowed or interned
+#[derive(Clone, Debug)]
+pub enum UniformName<'name> {
+    /// A name which outlives the uniforms, like a literal or a connection's name
+    Borrowed(&'name str),
+    /// A name returned by `intern`
+    Interned(Rc<str>),
+}
+
+impl<'name> From<&'name str> for UniformName<'name> {
+    fn from(name: &'name str) -> Self {
+        UniformName::Borrowed(name)
+    }
+}
+
+impl<'name> From<Rc<str>> for UniformName<'name> {
+    fn from(name: Rc<str>) -> Self {
+        UniformName::Interned(name)
+    }
+}
+
+impl<'name> Deref for UniformName<'name> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match *self {
+            UniformName::Borrowed(name) => name,
+            UniformName::Interned(ref name) => name,
+        }
+    }
+}
+
+/// A value that can be stored in a `UniformsStorageVec`. Unlike `AsUniformValue`, the value is
+/// converted up front, so it can be stored inline instead of being boxed.
+pub trait IntoUniformValue<'a> {
+    /// Convert into the value glium passes to the program
+    fn into_uniform_value(self) -> UniformValue<'a>;
+}
+
+macro_rules! impl_into_uniform_value {
+    ($($ty:ty => $variant:ident),* $(,)*) => {
+        $(
+            impl<'a> IntoUniformValue<'a> for $ty {
+                fn into_uniform_value(self) -> UniformValue<'a> {
+                    UniformValue::$variant(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_uniform_value! {
+    bool => Bool,
+    i32 => SignedInt,
+    u32 => UnsignedInt,
+    f32 => Float,
+    [f32; 2] => Vec2,
+    [f32; 3] => Vec3,
+    [f32; 4] => Vec4,
+    [[f32; 4]; 4] => Mat4,
+}
+
+impl<'a> IntoUniformValue<'a> for (f32, f32) {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Vec2([self.0, self.1])
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for &'a Texture1d {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture1d(self, None)
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for &'a Texture2d {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture2d(self, None)
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for &'a Texture3d {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture3d(self, None)
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for Sampler<'a, Texture1d> {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture1d(self.0, Some(self.1))
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for Sampler<'a, Texture2d> {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture2d(self.0, Some(self.1))
+    }
+}
+
+impl<'a> IntoUniformValue<'a> for Sampler<'a, Texture3d> {
+    fn into_uniform_value(self) -> UniformValue<'a> {
+        UniformValue::Texture3d(self.0, Some(self.1))
+    }
+}
+
+/// A `UniformsStorage` which has a `push` method for appending new uniforms. Values are stored
+/// inline, so the only allocation is the vector itself.
+#[derive(Clone, Default)]
+pub struct UniformsStorageVec<'name, 'uniform>(Vec<(UniformName<'name>, UniformValue<'uniform>)>);
+
+impl<'name, 'uniform> UniformsStorageVec<'name, 'uniform> {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a new instance with room for a number of uniforms
+    pub fn with_capacity(capacity: usize) -> Self {
+        UniformsStorageVec(Vec::with_capacity(capacity))
+    }
+
+    /// Empty the storage, keeping its allocation so it can be filled again next frame with uniforms
+    /// that are borrowed for a different lifetime
+    pub fn recycle<'n, 'u>(mut self) -> UniformsStorageVec<'n, 'u> {
+        self.0.clear();
+        // The elements have the same layout whatever their lifetimes, so collecting the empty
+        // vector into the new type reuses its allocation
+        UniformsStorageVec(self.0.into_iter().map(|_| unreachable!()).collect())
+    }
+
+    /// Push a new uniform onto the array
+    pub fn push<S, U>(&mut self, name: S, uniform: U)
+    where
+        S: Into<UniformName<'name>>,
+        U: IntoUniformValue<'uniform>,
+    {
+        self.0.push((name.into(), uniform.into_uniform_value()))
+    }
+}
+
+impl<'name, 'uniform> Uniforms for UniformsStorageVec<'name, 'uniform> {
+    #[cfg_attr(feature = "cargo-clippy", allow(needless_lifetimes))]
+    #[inline]
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        for &(ref name, uniform) in &self.0 {
+            output(name, uniform);
+        }
+    }
+}
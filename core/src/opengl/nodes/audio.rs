@@ -0,0 +1,617 @@
+//! The audio node recieves audio input from PortAudio or cpal and analyzes it, outputting
+//! the power spectrum of the audio as a Texture1d. With more than one channel, the spectra of
+//! every channel are also output together as a Texture2d, along with the RMS level of each channel.
+//! The energies of the bass, mid and treble bands of the first channel are output as floats, and
+//! `beat` jumps to 1 on each beat of the bass and falls back to 0.
+//!
+//! `backend: cpal` gets the input from cpal instead of PortAudio, which doesn't need any C
+//! libraries. PortAudio can be left out of the build entirely by turning off the default
+//! `portaudio` feature. `device` picks an input by name, as listed by `yotredash audio-devices`.
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{AudioBackend, AudioConfig},
+    opengl::resources,
+};
+use anyhow::{bail, ensure, format_err, Error};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use fftw::{
+    plan::{R2CPlan, R2CPlan32},
+    types::{c32, Flag},
+};
+use glium::{
+    backend::Facade,
+    texture::{Texture1d, Texture2d},
+};
+use log::{debug, error, warn};
+use num_traits::Zero;
+#[cfg(feature = "portaudio")]
+use portaudio::{
+    self, Input, InputStreamCallbackArgs, InputStreamSettings, NonBlocking, PortAudio, Stream,
+    StreamParameters,
+};
+use rb::{Producer, RbConsumer, RbProducer, SpscRb, RB};
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    thread,
+    time::Instant,
+};
+
+// Channels are analyzed separately instead of being mixed down, which also sidesteps phase
+// cancellation.
+const FRAMES_PER_BUFFER: u32 = 1024; // how many sample frames to pass to each callback
+const SAMPLE_BUFFER_FRAMES: usize = FRAMES_PER_BUFFER as usize * 8;
+const FFT_SIZE: usize = 1024;
+const SPECTRUM_LENGTH: usize = FFT_SIZE / 2;
+const SMOOTHING: f32 = 0.8;
+const MIN_DB: f32 = -30.0;
+const MAX_DB: f32 = 20.0;
+// Scale the waveform to match the Web Audio API defaults
+const WAVEFORM_SCALE: f32 = (MAX_DB - MIN_DB) / (-30.0 - -100.0) / 2.0;
+
+/// The bands whose energies are output, with their lowest and highest frequencies (Hz)
+const BANDS: [(&str, f32, f32); 3] = [
+    ("bass", 20.0, 250.0),
+    ("mid", 250.0, 2000.0),
+    ("treble", 2000.0, 16000.0),
+];
+/// How far the bass has to rise above its recent average to count as a beat
+const BEAT_THRESHOLD: f32 = 1.3;
+/// The bass energy below which nothing counts as a beat, so noise in silence doesn't
+const MIN_BEAT_ENERGY: f32 = 0.1;
+/// Shortest time between two beats (seconds)
+const MIN_BEAT_INTERVAL: f32 = 0.25;
+/// Time constant of the average the bass is compared with (seconds)
+const BEAT_AVERAGE_TIME: f32 = 1.0;
+/// How long `beat` takes to fall back to 0 (seconds)
+const BEAT_DECAY: f32 = 0.2;
+
+/// The type of individual samples returned by PortAudio, and that cpal's samples are converted to.
+type Sample = f32;
+
+/// An open input stream, from whichever backend the node uses
+enum AudioInput {
+    /// A PortAudio stream, along with the connection it needs to stay open
+    #[cfg(feature = "portaudio")]
+    PortAudio {
+        /// Our connection to PortAudio.
+        #[allow(dead_code)]
+        pa: PortAudio,
+        /// The input stream we recieve samples from.
+        stream: Stream<NonBlocking, Input<Sample>>,
+    },
+    /// A cpal stream
+    Cpal(cpal::Stream),
+}
+
+impl AudioInput {
+    /// Starts sending samples to the callback
+    fn start(&mut self) -> Result<(), Error> {
+        match *self {
+            #[cfg(feature = "portaudio")]
+            AudioInput::PortAudio { ref mut stream, .. } => stream.start()?,
+            AudioInput::Cpal(ref stream) => stream.play()?,
+        }
+        Ok(())
+    }
+}
+
+/// Checks that an input has as many channels as the config asks for
+fn check_channels(config: &AudioConfig, max_channels: u32) -> Result<(), Error> {
+    ensure!(
+        config.channels >= 1 && config.channels <= max_channels,
+        "Audio input has {} channels, but {} were requested",
+        max_channels,
+        config.channels
+    );
+    Ok(())
+}
+
+/// Opens the input with PortAudio, returning the stream and its sample rate
+#[cfg(feature = "portaudio")]
+fn open_portaudio(
+    config: &AudioConfig,
+    producer: Producer<Sample>,
+) -> Result<(AudioInput, f64), Error> {
+    let pa = PortAudio::new()?;
+
+    debug!("PortAudio version: {} {}", pa.version(), pa.version_text()?);
+
+    let input = match config.device {
+        Some(ref name) => pa
+            .devices()?
+            .filter_map(Result::ok)
+            .find(|(_, info)| info.name == name.as_str() && info.max_input_channels > 0)
+            .map(|(index, _)| index)
+            .ok_or_else(|| format_err!("There's no PortAudio input device named `{}`", name))?,
+        None => pa.default_input_device()?,
+    };
+    let info = pa.device_info(input)?;
+    debug!("Input metadata: {:?}", info);
+
+    check_channels(config, info.max_input_channels.max(0) as u32)?;
+
+    let input_params = {
+        // Just making sure we document this instead of passing in a raw true :D
+        const INTERLEAVED: bool = true;
+
+        let latency = info.default_low_input_latency;
+        StreamParameters::new(input, config.channels as i32, INTERLEAVED, latency)
+    };
+
+    let sample_rate = info.default_sample_rate;
+    let input_settings = InputStreamSettings::new(input_params, sample_rate, FRAMES_PER_BUFFER);
+
+    let callback = move |InputStreamCallbackArgs { buffer, .. }| {
+        // TODO: Handle overruns gracefully instead of panic!()ing.
+        if let Err(_) = producer.write(&buffer) {
+            warn!("orun in producer");
+        }
+
+        portaudio::Continue
+    };
+
+    let stream = pa.open_non_blocking_stream(input_settings, callback)?;
+
+    Ok((AudioInput::PortAudio { pa, stream }, sample_rate))
+}
+
+/// Stands in for `open_portaudio` when PortAudio isn't built in
+#[cfg(not(feature = "portaudio"))]
+fn open_portaudio(_: &AudioConfig, _: Producer<Sample>) -> Result<(AudioInput, f64), Error> {
+    bail!("This build of yotredash doesn't include PortAudio, use `backend: cpal` instead");
+}
+
+/// Finds the cpal input device the config asks for
+fn cpal_device(config: &AudioConfig) -> Result<cpal::Device, Error> {
+    let host = cpal::default_host();
+    match config.device {
+        Some(ref name) => {
+            for device in host.input_devices()? {
+                if device.name()? == *name {
+                    return Ok(device);
+                }
+            }
+            bail!("There's no cpal input device named `{}`", name);
+        }
+        None => host
+            .default_input_device()
+            .ok_or_else(|| format_err!("There's no default cpal input device")),
+    }
+}
+
+/// Opens the input with cpal, returning the stream and its sample rate
+fn open_cpal(config: &AudioConfig, producer: Producer<Sample>) -> Result<(AudioInput, f64), Error> {
+    let device = cpal_device(config)?;
+    debug!("Input device: {}", device.name()?);
+
+    let max_channels = device
+        .supported_input_configs()?
+        .map(|supported| u32::from(supported.channels()))
+        .max()
+        .unwrap_or(0);
+    check_channels(config, max_channels)?;
+
+    let default_config = device.default_input_config()?;
+    debug!("Input metadata: {:?}", default_config);
+    let sample_rate = default_config.sample_rate();
+    let stream_config = cpal::StreamConfig {
+        channels: config.channels as u16,
+        sample_rate,
+        buffer_size: cpal::BufferSize::Default,
+    };
+
+    let on_error = |e| error!("Audio input error: {}", e);
+    // Samples are written as they come in, whatever size of buffer the device uses
+    let write = move |samples: &[Sample]| {
+        if producer.write(samples).is_err() {
+            warn!("orun in producer");
+        }
+    };
+    let stream = match default_config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &stream_config,
+            move |data: &[f32], _: &_| write(data),
+            on_error,
+            None,
+        )?,
+        cpal::SampleFormat::I16 => {
+            let mut converted = Vec::new();
+            device.build_input_stream(
+                &stream_config,
+                move |data: &[i16], _: &_| {
+                    converted.clear();
+                    converted.extend(data.iter().map(|&x| f32::from(x) / 32768.0));
+                    write(&converted);
+                },
+                on_error,
+                None,
+            )?
+        }
+        cpal::SampleFormat::U16 => {
+            let mut converted = Vec::new();
+            device.build_input_stream(
+                &stream_config,
+                move |data: &[u16], _: &_| {
+                    converted.clear();
+                    converted.extend(data.iter().map(|&x| (f32::from(x) - 32768.0) / 32768.0));
+                    write(&converted);
+                },
+                on_error,
+                None,
+            )?
+        }
+        format => bail!("Unsupported audio sample format: {:?}", format),
+    };
+
+    Ok((AudioInput::Cpal(stream), f64::from(sample_rate.0)))
+}
+
+/// Prints the input devices of a backend, with how many channels they have
+pub fn print_devices(backend: AudioBackend) -> Result<(), Error> {
+    match backend {
+        #[cfg(feature = "portaudio")]
+        AudioBackend::Portaudio => {
+            let pa = PortAudio::new()?;
+            let default = pa.default_input_device().ok();
+            for device in pa.devices()? {
+                let (index, info) = device?;
+                if info.max_input_channels > 0 {
+                    let marker = if Some(index) == default {
+                        " (default)"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "{}{}: {} channels",
+                        info.name, marker, info.max_input_channels
+                    );
+                }
+            }
+        }
+        #[cfg(not(feature = "portaudio"))]
+        AudioBackend::Portaudio => {
+            bail!("This build of yotredash doesn't include PortAudio");
+        }
+        AudioBackend::Cpal => {
+            let host = cpal::default_host();
+            let default = host
+                .default_input_device()
+                .and_then(|device| device.name().ok());
+            for device in host.input_devices()? {
+                let name = device.name()?;
+                let channels = device
+                    .supported_input_configs()
+                    .map(|configs| configs.map(|supported| supported.channels()).max())
+                    .ok()
+                    .and_then(|channels| channels)
+                    .unwrap_or(0);
+                let marker = if Some(&name) == default.as_ref() {
+                    " (default)"
+                } else {
+                    ""
+                };
+                println!("{}{}: {} channels", name, marker, channels);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Computes a Blackman window of size `size` with α=`alpha`.
+#[allow(non_snake_case)]
+fn blackman(size: usize, alpha: f32) -> Vec<f32> {
+    use std::f32::consts::PI;
+
+    let N = size as f32;
+    let alpha_0 = (1.0 - alpha) / 2.0;
+    let alpha_1 = 0.5;
+    let alpha_2 = alpha / 2.0;
+
+    let w = |n: f32| {
+        alpha_0 - alpha_1 * ((2.0 * PI * n) / (N - 1.0)).cos()
+            + alpha_2 * ((4.0 * PI * n) / (N - 1.0)).cos()
+    };
+
+    (0..size).map(|n| w(n as f32)).collect::<Vec<f32>>()
+}
+
+/// Returns the mean of a spectrum scaled from 0 to 1 (like the `spectrum` output) between two
+/// frequencies (Hz)
+pub fn band_energy(spectrum: &[f32], sample_rate: f32, low: f32, high: f32) -> f32 {
+    let bin_width = sample_rate / FFT_SIZE as f32;
+    let bins: Vec<f32> = spectrum
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| {
+            let frequency = i as f32 * bin_width;
+            frequency >= low && frequency < high
+        })
+        .map(|(_, &value)| value.max(0.0).min(1.0))
+        .collect();
+    if bins.is_empty() {
+        0.0
+    } else {
+        bins.iter().sum::<f32>() / bins.len() as f32
+    }
+}
+
+/// Finds beats as sudden rises of the bass over its recent average
+pub struct BeatDetector {
+    /// The recent average of the bass energy
+    average: f32,
+    /// Time since the last beat (seconds)
+    since_beat: f32,
+}
+
+impl BeatDetector {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Self {
+            average: 0.0,
+            since_beat: MIN_BEAT_INTERVAL,
+        }
+    }
+
+    /// Takes the bass energy of the next block of samples, `dt` seconds after the last one,
+    /// returning whether it's a beat
+    pub fn update(&mut self, energy: f32, dt: f32) -> bool {
+        self.since_beat += dt;
+        let beat = energy > MIN_BEAT_ENERGY
+            && energy > self.average * BEAT_THRESHOLD
+            && self.since_beat >= MIN_BEAT_INTERVAL;
+        if beat {
+            self.since_beat = 0.0;
+        }
+        self.average += (energy - self.average) * (1.0 - (-dt / BEAT_AVERAGE_TIME).exp());
+        beat
+    }
+}
+
+impl Default for BeatDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encapsulates the lifetime of the audio system, owning the input stream.
+pub struct AudioNode {
+    /// Our OpenGL context.
+    facade: Rc<dyn Facade>,
+
+    /// The input stream we recieve samples from.
+    input: AudioInput,
+
+    /// A ringbuffer of interleaved samples, produced by the input callback and consumed by
+    /// the analysis thread.
+    sample_buffer: SpscRb<Sample>,
+
+    /// Number of channels being analyzed.
+    channels: usize,
+
+    /// The current time domain data (waveform) of each channel
+    waveforms: Arc<RwLock<Vec<Vec<f32>>>>,
+
+    /// The current computed spectrum of each channel.
+    spectra: Arc<RwLock<Vec<Vec<f32>>>>,
+
+    /// The current RMS level of each channel.
+    rms: Arc<RwLock<Vec<f32>>>,
+
+    /// Samples per second of the input.
+    sample_rate: f64,
+
+    /// The current energy of each of the `BANDS` in the first channel.
+    bands: Arc<RwLock<Vec<f32>>>,
+
+    /// When the last beat was found, if there has been one.
+    last_beat: Arc<RwLock<Option<Instant>>>,
+}
+
+impl AudioNode {
+    /// Open the input the config asks for
+    pub fn new(facade: &Rc<dyn Facade>, config: &AudioConfig) -> Result<AudioNode, Error> {
+        let channels = config.channels as usize;
+        let sample_buffer = SpscRb::new(SAMPLE_BUFFER_FRAMES * channels.max(1));
+        let producer = sample_buffer.producer();
+
+        let (input, sample_rate) = match config.backend {
+            AudioBackend::Portaudio => open_portaudio(config, producer)?,
+            AudioBackend::Cpal => open_cpal(config, producer)?,
+        };
+
+        let mut node = AudioNode {
+            input,
+            sample_buffer,
+            facade: Rc::clone(facade),
+            channels,
+            waveforms: Arc::new(RwLock::new(vec![vec![0.5; SPECTRUM_LENGTH]; channels])),
+            spectra: Arc::new(RwLock::new(vec![vec![0.0; SPECTRUM_LENGTH]; channels])),
+            rms: Arc::new(RwLock::new(vec![0.0; channels])),
+            sample_rate,
+            bands: Arc::new(RwLock::new(vec![0.0; BANDS.len()])),
+            last_beat: Arc::new(RwLock::new(None)),
+        };
+
+        node.run()?;
+
+        Ok(node)
+    }
+
+    /// Launches the audio thread.
+    pub fn run(&mut self) -> Result<(), Error> {
+        let consumer = self.sample_buffer.consumer();
+        let channels = self.channels;
+        let mut interleaved = vec![Sample::default(); FFT_SIZE * channels];
+        // TODO: Replace with Default::default() when const generics are a thing
+        let mut buf: [Sample; FFT_SIZE as usize] = [Default::default(); FFT_SIZE as usize];
+
+        let n = FFT_SIZE as usize;
+
+        let waveforms_lock = Arc::clone(&self.waveforms);
+        let spectra_lock = Arc::clone(&self.spectra);
+        let rms_lock = Arc::clone(&self.rms);
+        let bands_lock = Arc::clone(&self.bands);
+        let last_beat_lock = Arc::clone(&self.last_beat);
+        let sample_rate = self.sample_rate as f32;
+        thread::spawn(move || {
+            // Use the window from §1.8.6 of the Web Audio API specification
+            let window = blackman(n, 0.16);
+
+            let mut spectrum = vec![c32::zero(); SPECTRUM_LENGTH];
+            let mut spectra_smoothed = vec![vec![f32::zero(); SPECTRUM_LENGTH]; channels];
+
+            let mut plan: R2CPlan32 =
+                { R2CPlan::new(&[n], &mut buf, &mut spectrum, Flag::Estimate).unwrap() };
+
+            let mut beats = BeatDetector::new();
+            // Each block is as long as the FFT
+            let dt = n as f32 / sample_rate;
+
+            loop {
+                consumer.read_blocking(&mut interleaved).unwrap();
+
+                let mut waveforms = Vec::with_capacity(channels);
+                let mut spectra = Vec::with_capacity(channels);
+                let mut rms = Vec::with_capacity(channels);
+
+                for channel in 0..channels {
+                    for (i, sample) in buf.iter_mut().enumerate() {
+                        *sample = interleaved[i * channels + channel];
+                    }
+
+                    waveforms.push(
+                        buf.iter()
+                            .map(|x| x * WAVEFORM_SCALE / 2.0 + 0.5)
+                            .take(SPECTRUM_LENGTH)
+                            .collect(),
+                    );
+                    rms.push((buf.iter().map(|x| x * x).sum::<f32>() / n as f32).sqrt());
+
+                    // window the buffer
+                    for i in 0..FRAMES_PER_BUFFER as usize {
+                        buf[i] *= window[i];
+                    }
+
+                    if let Err(e) = plan.r2c(&mut buf, &mut spectrum) {
+                        error!("fftw plan failed to execute: {:?}", e);
+                    }
+
+                    let smoothed = &mut spectra_smoothed[channel];
+                    for (x_old, x) in smoothed.iter_mut().zip(&spectrum) {
+                        *x_old = SMOOTHING * *x_old + (1.0 - SMOOTHING) * x.norm();
+                    }
+
+                    spectra.push(
+                        smoothed
+                            .iter()
+                            .map(|x| (20.0 * x.log10() - MIN_DB) / (MAX_DB - MIN_DB))
+                            .collect(),
+                    );
+                }
+
+                let bands: Vec<f32> = BANDS
+                    .iter()
+                    .map(|&(_, low, high)| band_energy(&spectra[0], sample_rate, low, high))
+                    .collect();
+                if beats.update(bands[0], dt) {
+                    *last_beat_lock.write().unwrap() = Some(Instant::now());
+                }
+
+                *waveforms_lock.write().unwrap() = waveforms;
+                *spectra_lock.write().unwrap() = spectra;
+                *rms_lock.write().unwrap() = rms;
+                *bands_lock.write().unwrap() = bands;
+            }
+        });
+
+        self.input.start()?;
+
+        Ok(())
+    }
+}
+
+impl Node for AudioNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        let waveforms = self.waveforms.read().unwrap().clone();
+        let spectra = self.spectra.read().unwrap().clone();
+        let rms = self.rms.read().unwrap().clone();
+        let bands = self.bands.read().unwrap().clone();
+        let last_beat = *self.last_beat.read().unwrap();
+
+        // The 1D outputs are the first channel, so configs made for a single channel keep working
+        let waveform_texture =
+            resources::track(Texture1d::new(&*self.facade, waveforms[0].clone())?);
+        let spectrum_texture = resources::track(Texture1d::new(&*self.facade, spectra[0].clone())?);
+        // Each row is a channel
+        let spectra_texture = resources::track(Texture2d::new(&*self.facade, spectra)?);
+
+        let mut outputs = HashMap::new();
+        outputs.insert(
+            "waveform".to_string(),
+            NodeOutput::Texture1d(waveform_texture),
+        );
+        outputs.insert(
+            "spectrum".to_string(),
+            NodeOutput::Texture1d(spectrum_texture),
+        );
+        outputs.insert(
+            "spectra".to_string(),
+            NodeOutput::Texture2d(spectra_texture),
+        );
+        for (channel, rms) in rms.into_iter().enumerate() {
+            outputs.insert(format!("rms_{}", channel), NodeOutput::Float(rms));
+        }
+        for (&(name, _, _), energy) in BANDS.iter().zip(bands) {
+            outputs.insert(name.to_string(), NodeOutput::Float(energy));
+        }
+        let beat = last_beat.map_or(0.0, |last_beat| {
+            let elapsed = last_beat.elapsed();
+            let elapsed = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1e9;
+            (1.0 - elapsed / BEAT_DECAY).max(0.0)
+        });
+        outputs.insert("beat".to_string(), NodeOutput::Float(beat));
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{band_energy, BeatDetector, FFT_SIZE};
+
+    #[test]
+    fn averages_bands() {
+        // With this rate each bin is 100Hz wide
+        let sample_rate = 100.0 * FFT_SIZE as f32;
+        let spectrum = [0.0, 0.5, 1.0, 2.0, -1.0];
+        assert_eq!(band_energy(&spectrum, sample_rate, 100.0, 300.0), 0.75);
+        assert_eq!(band_energy(&spectrum, sample_rate, 300.0, 500.0), 0.5);
+        assert_eq!(band_energy(&spectrum, sample_rate, 1000.0, 2000.0), 0.0);
+    }
+
+    #[test]
+    fn finds_beats() {
+        let mut beats = BeatDetector::new();
+        let dt = 0.02;
+        // A steady bass line comes in first, which only counts as a beat until the average
+        // catches up with it
+        for _ in 0..150 {
+            beats.update(0.3, dt);
+        }
+        let mut found = Vec::new();
+        for block in 0..100 {
+            // A kick every half second over a steady bass line, and a second one right after the
+            // first that's too soon to count
+            let energy = if block % 25 == 0 || block == 1 {
+                0.8
+            } else {
+                0.3
+            };
+            if beats.update(energy, dt) {
+                found.push(block);
+            }
+        }
+        assert_eq!(found, vec![0, 25, 50, 75]);
+    }
+}
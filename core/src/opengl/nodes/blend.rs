@@ -1,51 +1,20 @@
 //! The blend node takes the output of other nodes and blends them to produce one output
 
-use failure::{bail, Error};
-use glium::{
-    backend::Facade,
-    implement_vertex,
-    index::{NoIndices, PrimitiveType},
-    program::ProgramCreationInput,
-    texture::Texture2d,
-    Program, Surface, VertexBuffer,
-};
+use anyhow::{bail, ensure, Error};
+use glium::{backend::Facade, texture::Texture2d, Program, Surface};
 use std::{collections::HashMap, rc::Rc, sync::mpsc::Receiver};
 
 use super::{Node, NodeInputs, NodeOutput};
 use crate::{
     config::nodes::{BlendConfig, BlendOp},
     event::RendererEvent,
-    opengl::UniformsStorageVec,
+    opengl::{
+        intern, profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
 };
 
-/// Implementation of the vertex attributes for the vertex buffer
-#[derive(Copy, Clone)]
-pub struct Vertex {
-    /// Position of the vertex in 2D space
-    position: [f32; 2],
-}
-implement_vertex!(Vertex, position);
-
-#[cfg_attr(rustfmt, rustfmt_skip)]
-const VERTICES: [Vertex; 6] = [
-    Vertex { position: [-1.0, -1.0] },
-    Vertex { position: [ 1.0, -1.0] },
-    Vertex { position: [ 1.0,  1.0] },
-    Vertex { position: [-1.0, -1.0] },
-    Vertex { position: [ 1.0,  1.0] },
-    Vertex { position: [-1.0,  1.0] },
-];
-
-const VERTEX: &str = "
-    #version 140
-
-    in vec2 position;
-
-    void main() {
-        gl_Position = vec4(position, 0.0, 1.0);
-    }
-";
-
 const FRAGMENT: &str = "
     #version 140
 
@@ -69,10 +38,10 @@ pub struct BlendNode {
     texture: Rc<Texture2d>,
     /// Shader program used to blend the inputs
     program: Program,
-    /// Vertex buffer for the shader
-    vertex_buffer: VertexBuffer<Vertex>,
-    /// Index buffer for the shader
-    index_buffer: NoIndices,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// The names of the input textures' uniforms
+    names: Vec<Rc<str>>,
     /// Receiver for events
     receiver: Receiver<RendererEvent>,
 }
@@ -84,6 +53,11 @@ impl BlendNode {
         config: &BlendConfig,
         receiver: Receiver<RendererEvent>,
     ) -> Result<Self, Error> {
+        ensure!(
+            !config.textures.is_empty(),
+            "Blend node needs at least one input"
+        );
+
         let op_fmt = match config.operation {
             BlendOp::Min => "color = min(texture(%INPUT%, uv);",
             BlendOp::Max => "color = max(texture(%INPUT%, uv);",
@@ -103,7 +77,7 @@ impl BlendNode {
                 let mut iter = (0..config.textures.len()).map(|i| format!("texture_{}", i));
                 &format!(
                     "color = texture({}, uv);\n{}",
-                    iter.next().expect("Blend node needs at least one input"),
+                    iter.next().unwrap_or_default(),
                     iter.map(|name| op_fmt.replace("%INPUT%", &name))
                         .collect::<Vec<String>>()
                         .join("\n")
@@ -111,29 +85,19 @@ impl BlendNode {
                 )
             });
 
-        let program = {
-            let input = ProgramCreationInput::SourceCode {
-                vertex_shader: VERTEX,
-                tessellation_control_shader: None,
-                tessellation_evaluation_shader: None,
-                geometry_shader: None,
-                fragment_shader: &fragment,
-                transform_feedback_varyings: None,
-                outputs_srgb: true,
-                uses_point_size: false,
-            };
-            Program::new(&**facade, input)?
-        };
+        let program = quad::program(&**facade, &fragment)?;
 
         let (width, height) = facade.get_context().get_framebuffer_dimensions();
-        let texture = Rc::new(Texture2d::empty(&**facade, width, height)?);
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
 
         Ok(Self {
             facade: Rc::clone(facade),
             texture,
             program,
-            vertex_buffer: VertexBuffer::new(&**facade, &VERTICES)?,
-            index_buffer: NoIndices(PrimitiveType::TrianglesList),
+            quad: Quad::new(&**facade)?,
+            names: (0..config.textures.len())
+                .map(|i| intern(&format!("texture_{}", i)))
+                .collect(),
             receiver,
         })
     }
@@ -141,32 +105,29 @@ impl BlendNode {
 
 impl Node for BlendNode {
     fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
-        if let Ok(event) = self.receiver.try_recv() {
-            match event {
-                RendererEvent::Resize(width, height) => {
-                    self.texture = Rc::new(Texture2d::empty(&*self.facade, width, height)?);
-                }
-                _ => (),
-            }
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
         }
 
         if let NodeInputs::Blend { ref textures } = *inputs {
             let resolution = (self.texture.width() as f32, self.texture.height() as f32);
 
-            let mut uniforms = UniformsStorageVec::new();
+            let mut uniforms = UniformsStorageVec::with_capacity(textures.len() + 1);
             uniforms.push("resolution", resolution);
-            for (i, texture) in textures.iter().enumerate() {
-                uniforms.push(format!("texture_{}", i), texture.sampled());
+            for (name, texture) in self.names.iter().zip(textures) {
+                uniforms.push(Rc::clone(name), texture.sampled());
             }
 
+            let query = profiler::active_query();
             let mut surface = self.texture.as_surface();
             surface.clear_color(0.0, 0.0, 0.0, 1.0);
-            surface.draw(
-                &self.vertex_buffer,
-                &self.index_buffer,
+            self.quad.draw(
+                &mut surface,
                 &self.program,
                 &uniforms,
-                &Default::default(),
+                &profiler::draw_parameters(&query),
             )?;
 
             let mut outputs = HashMap::new();
@@ -179,4 +140,10 @@ impl Node for BlendNode {
             bail!("Wrong input type for node");
         }
     }
+
+    fn changed(&mut self) -> bool {
+        // Resizes are picked up from the target's dimensions, which clear the renderer's cache
+        while self.receiver.try_recv().is_ok() {}
+        false
+    }
 }
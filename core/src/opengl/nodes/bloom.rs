@@ -0,0 +1,168 @@
+//! The bloom node makes the bright parts of a texture glow
+
+use anyhow::{bail, Error};
+use glium::{backend::Facade, texture::Texture2d, Program};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{
+    blur::{intermediate_texture, GaussianBlur},
+    Node, NodeInputs, NodeOutput,
+};
+use crate::{
+    config::nodes::BloomConfig,
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// Keeps only the part of each pixel brighter than the threshold
+const THRESHOLD: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform vec2 resolution;
+    uniform float threshold;
+
+    void main() {
+        vec4 texel = texture(input_texture, gl_FragCoord.xy / resolution);
+        float luma = dot(texel.rgb, vec3(0.2126, 0.7152, 0.0722));
+        float bright = max(luma - threshold, 0.0) / max(luma, 0.0001);
+        color = vec4(texel.rgb * bright, texel.a);
+    }
+";
+
+/// Adds the blurred highlights back onto the input
+const COMBINE: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform sampler2D bloom_texture;
+    uniform vec2 resolution;
+    uniform float intensity;
+
+    void main() {
+        vec2 uv = gl_FragCoord.xy / resolution;
+        vec4 base = texture(input_texture, uv);
+        vec4 bloom = texture(bloom_texture, uv);
+        color = vec4(base.rgb + bloom.rgb * intensity, max(base.a, bloom.a * intensity));
+    }
+";
+
+/// A node that adds a glow around the bright parts of a texture
+pub struct BloomNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// Shader program that extracts the highlights
+    threshold_program: Program,
+    /// Shader program that adds the highlights to the input
+    combine_program: Program,
+    /// The quad the shaders are drawn over
+    quad: Quad,
+    /// The blur passes
+    blur: GaussianBlur,
+    /// Holds the highlights
+    highlights: Rc<Texture2d>,
+    /// Holds the blurred highlights
+    bloom: Rc<Texture2d>,
+    /// Brightness above which pixels glow, if not connected to another node
+    threshold: f32,
+    /// Radius of the glow in pixels
+    radius: f32,
+    /// Strength of the glow, if not connected to another node
+    intensity: f32,
+}
+
+impl BloomNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: BloomConfig) -> Result<Self, Error> {
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture: resources::track(Texture2d::empty(&**facade, width, height)?),
+            threshold_program: quad::program(&**facade, THRESHOLD)?,
+            combine_program: quad::program(&**facade, COMBINE)?,
+            quad: Quad::new(&**facade)?,
+            blur: GaussianBlur::new(facade)?,
+            highlights: intermediate_texture(&**facade, width, height)?,
+            bloom: intermediate_texture(&**facade, width, height)?,
+            threshold: config.threshold.or_default(),
+            radius: config.radius,
+            intensity: config.intensity.or_default(),
+        })
+    }
+}
+
+impl Node for BloomNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+            self.highlights = intermediate_texture(&*self.facade, width, height)?;
+            self.bloom = intermediate_texture(&*self.facade, width, height)?;
+        }
+
+        if let NodeInputs::Bloom {
+            ref texture,
+            threshold,
+            intensity,
+        } = *inputs
+        {
+            let resolution = [width as f32, height as f32];
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("input_texture", texture.sampled());
+            uniforms.push("resolution", resolution);
+            uniforms.push("threshold", threshold.unwrap_or(self.threshold));
+            {
+                let query = profiler::active_query();
+                self.quad.draw(
+                    &mut self.highlights.as_surface(),
+                    &self.threshold_program,
+                    &uniforms,
+                    &profiler::draw_parameters(&query),
+                )?;
+            }
+
+            self.blur
+                .apply(&self.highlights, &self.bloom, self.radius)?;
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("input_texture", texture.sampled());
+            uniforms.push("bloom_texture", self.bloom.sampled());
+            uniforms.push("resolution", resolution);
+            uniforms.push("intensity", intensity.unwrap_or(self.intensity));
+            {
+                let query = profiler::active_query();
+                self.quad.draw(
+                    &mut self.texture.as_surface(),
+                    &self.combine_program,
+                    &uniforms,
+                    &profiler::draw_parameters(&query),
+                )?;
+            }
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,199 @@
+//! The blur node applies a separable gaussian blur to a texture
+
+use anyhow::{bail, Error};
+use glium::{
+    backend::Facade,
+    texture::{MipmapsOption, Texture2d, UncompressedFloatFormat},
+    uniforms::{MagnifySamplerFilter, SamplerWrapFunction},
+    Program,
+};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::BlurConfig,
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// The largest radius the shader will sample, so a bad value can't stall the GPU
+const MAX_RADIUS: f32 = 64.0;
+
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform vec2 resolution;
+    uniform vec2 direction;
+    uniform float radius;
+
+    void main() {
+        vec2 uv = gl_FragCoord.xy / resolution;
+        vec2 step = direction / resolution;
+        float sigma = max(radius / 2.0, 0.001);
+        int samples = int(ceil(radius));
+
+        vec4 sum = vec4(0.0);
+        float total = 0.0;
+        for (int i = -samples; i <= samples; i++) {
+            float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+            sum += texture(input_texture, uv + float(i) * step) * weight;
+            total += weight;
+        }
+        color = sum / total;
+    }
+";
+
+/// Two-pass gaussian blur, shared by the nodes that need one
+pub struct GaussianBlur {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// Shader program for a single pass in one direction
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// Holds the result of the horizontal pass
+    horizontal: Rc<Texture2d>,
+}
+
+impl GaussianBlur {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            program,
+            quad: Quad::new(&**facade)?,
+            horizontal: intermediate_texture(&**facade, width, height)?,
+        })
+    }
+
+    /// Blurs `input` into `output`. The radius is in window pixels, so the result looks the same
+    /// while supersampling.
+    pub fn apply(
+        &mut self,
+        input: &Texture2d,
+        output: &Texture2d,
+        radius: f32,
+    ) -> Result<(), Error> {
+        let (width, height) = output.dimensions();
+        if self.horizontal.dimensions() != (width, height) {
+            self.horizontal = intermediate_texture(&*self.facade, width, height)?;
+        }
+
+        let (window_width, _) = self.facade.get_context().get_framebuffer_dimensions();
+        let scale = width as f32 / window_width.max(1) as f32;
+        let radius = (radius * scale).max(0.0).min(MAX_RADIUS);
+        let resolution = [width as f32, height as f32];
+
+        let passes = [
+            (input, &*self.horizontal, [1.0_f32, 0.0_f32]),
+            (&*self.horizontal, output, [0.0, 1.0]),
+        ];
+        for &(source, destination, direction) in &passes {
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push(
+                "input_texture",
+                source
+                    .sampled()
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .wrap_function(SamplerWrapFunction::Clamp),
+            );
+            uniforms.push("resolution", resolution);
+            uniforms.push("direction", direction);
+            uniforms.push("radius", radius);
+
+            let query = profiler::active_query();
+            self.quad.draw(
+                &mut destination.as_surface(),
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a texture for intermediate results, with enough range to hold values above 1
+pub fn intermediate_texture(
+    facade: &dyn Facade,
+    width: u32,
+    height: u32,
+) -> Result<Rc<Texture2d>, Error> {
+    Ok(resources::track(Texture2d::empty_with_format(
+        facade,
+        UncompressedFloatFormat::F16F16F16F16,
+        MipmapsOption::NoMipmap,
+        width,
+        height,
+    )?))
+}
+
+/// A node that blurs a texture
+pub struct BlurNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// The blur passes
+    blur: GaussianBlur,
+    /// Blur radius, used if the parameter isn't connected to another node
+    radius: f32,
+}
+
+impl BlurNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: BlurConfig) -> Result<Self, Error> {
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            blur: GaussianBlur::new(facade)?,
+            radius: config.radius.or_default(),
+        })
+    }
+}
+
+impl Node for BlurNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::Blur {
+            ref texture,
+            radius,
+        } = *inputs
+        {
+            self.blur
+                .apply(texture, &self.texture, radius.unwrap_or(self.radius))?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,294 @@
+//! A `Node` that keeps a camera which can be moved with the mouse and keyboard, and outputs its
+//! matrices, so raymarching shaders don't each need their own mouse handling. Matrices are column
+//! major, like GLSL expects.
+
+use anyhow::Error;
+use glium::backend::Facade;
+use std::{
+    collections::HashMap, f32::consts::FRAC_PI_2, rc::Rc, sync::mpsc::Receiver, time::Instant,
+};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{CameraConfig, CameraMode},
+    event::{KeyEvent, MovementKey, PointerButton, PointerEvent, RendererEvent},
+    opengl::target,
+};
+
+/// How close the camera may get to looking straight up or down, where the up direction flips
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+
+/// How much one line of scrolling zooms an orbiting camera
+const ZOOM_PER_LINE: f32 = 0.9;
+
+/// Longest time step, so the camera doesn't jump after a pause
+const MAX_STEP: f32 = 0.1;
+
+type Vec3 = [f32; 3];
+type Mat4 = [[f32; 4]; 4];
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: Vec3, factor: f32) -> Vec3 {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Vec3) -> Vec3 {
+    scale(a, 1.0 / dot(a, a).sqrt())
+}
+
+/// Returns the direction a camera turned by `yaw` and `pitch` (radians) looks in. With both at
+/// zero, it looks down the negative z axis.
+pub fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    [
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        -pitch.cos() * yaw.cos(),
+    ]
+}
+
+/// Returns the view matrix of a camera at `eye` looking in `forward`, and its inverse, which turns
+/// directions from the camera into the world
+pub fn look(eye: Vec3, forward: Vec3) -> (Mat4, Mat4) {
+    let forward = normalize(forward);
+    let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+    let up = cross(right, forward);
+
+    let view = [
+        [right[0], up[0], -forward[0], 0.0],
+        [right[1], up[1], -forward[1], 0.0],
+        [right[2], up[2], -forward[2], 0.0],
+        [-dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0],
+    ];
+    let inverse = [
+        [right[0], right[1], right[2], 0.0],
+        [up[0], up[1], up[2], 0.0],
+        [-forward[0], -forward[1], -forward[2], 0.0],
+        [eye[0], eye[1], eye[2], 1.0],
+    ];
+    (view, inverse)
+}
+
+/// Returns a perspective projection matrix, with the vertical field of view in radians
+pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov / 2.0).tan();
+    [
+        [f / aspect, 0.0, 0.0, 0.0],
+        [0.0, f, 0.0, 0.0],
+        [0.0, 0.0, (far + near) / (near - far), -1.0],
+        [0.0, 0.0, 2.0 * far * near / (near - far), 0.0],
+    ]
+}
+
+/// A node that outputs the matrices of a camera moved with the mouse and keyboard
+pub struct CameraNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// Receiver for events
+    receiver: Receiver<RendererEvent>,
+    /// How the camera is controlled
+    mode: CameraMode,
+    /// The camera's position when flying, or the point it circles when orbiting
+    position: Vec3,
+    /// Distance from the point an orbiting camera circles
+    distance: f32,
+    /// Rotation around the vertical axis (radians)
+    yaw: f32,
+    /// Rotation up and down (radians)
+    pitch: f32,
+    /// Vertical field of view (radians)
+    fov: f32,
+    /// Distance to the near clipping plane
+    near: f32,
+    /// Distance to the far clipping plane
+    far: f32,
+    /// Movement speed (units per second)
+    speed: f32,
+    /// Turning speed (radians per pixel)
+    sensitivity: f32,
+    /// The movement keys being held
+    keys: Vec<MovementKey>,
+    /// Whether the left button is held
+    dragging: bool,
+    /// Where the pointer was last
+    pointer: Option<(f32, f32)>,
+    /// When the camera was last moved
+    last_update: Instant,
+}
+
+impl CameraNode {
+    /// Create a new instance
+    pub fn new(
+        facade: &Rc<dyn Facade>,
+        config: &CameraConfig,
+        receiver: Receiver<RendererEvent>,
+    ) -> Self {
+        let offset = [
+            config.target[0] - config.position[0],
+            config.target[1] - config.position[1],
+            config.target[2] - config.position[2],
+        ];
+        let distance = dot(offset, offset).sqrt();
+        let forward = if distance > 0.0 {
+            scale(offset, 1.0 / distance)
+        } else {
+            [0.0, 0.0, -1.0]
+        };
+
+        Self {
+            facade: Rc::clone(facade),
+            receiver,
+            mode: config.mode,
+            position: match config.mode {
+                CameraMode::Orbit => config.target,
+                CameraMode::Fly => config.position,
+            },
+            distance,
+            yaw: forward[0].atan2(-forward[2]),
+            pitch: forward[1].asin().max(-PITCH_LIMIT).min(PITCH_LIMIT),
+            fov: config.fov.to_radians(),
+            near: config.near,
+            far: config.far,
+            speed: config.speed,
+            sensitivity: config.sensitivity,
+            keys: Vec::new(),
+            dragging: false,
+            pointer: None,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Take the position and rotation of the camera this replaces
+    pub fn inherit(&mut self, old: &CameraNode) {
+        if self.mode == old.mode {
+            self.position = old.position;
+            self.distance = old.distance;
+            self.yaw = old.yaw;
+            self.pitch = old.pitch;
+        }
+    }
+
+    /// Turn the camera for pointer events, and keep track of the keys held
+    fn handle_events(&mut self) {
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                RendererEvent::Pointer(PointerEvent::Move(x, y)) => {
+                    if let (true, Some((last_x, last_y))) = (self.dragging, self.pointer) {
+                        self.yaw += (x - last_x) * self.sensitivity;
+                        self.pitch = (self.pitch - (y - last_y) * self.sensitivity)
+                            .max(-PITCH_LIMIT)
+                            .min(PITCH_LIMIT);
+                    }
+                    self.pointer = Some((x, y));
+                }
+                RendererEvent::Pointer(PointerEvent::Press(PointerButton::Left)) => {
+                    self.dragging = true
+                }
+                RendererEvent::Pointer(PointerEvent::Release(PointerButton::Left)) => {
+                    self.dragging = false
+                }
+                RendererEvent::Pointer(PointerEvent::Scroll(_, y)) => {
+                    if self.mode == CameraMode::Orbit {
+                        self.distance *= ZOOM_PER_LINE.powf(y);
+                    }
+                }
+                RendererEvent::Key(KeyEvent::Press(key)) => {
+                    if !self.keys.contains(&key) {
+                        self.keys.push(key);
+                    }
+                }
+                RendererEvent::Key(KeyEvent::Release(key)) => self.keys.retain(|&held| held != key),
+                _ => (),
+            }
+        }
+    }
+
+    /// Move the camera for the keys held since the last frame
+    fn step(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last_update;
+        self.last_update = now;
+        let seconds =
+            (elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9).min(MAX_STEP);
+
+        let forward = direction(self.yaw, self.pitch);
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let mut movement = [0.0; 3];
+        for key in &self.keys {
+            let step = match *key {
+                MovementKey::Forward => forward,
+                MovementKey::Back => scale(forward, -1.0),
+                MovementKey::Right => right,
+                MovementKey::Left => scale(right, -1.0),
+                MovementKey::Up => [0.0, 1.0, 0.0],
+                MovementKey::Down => [0.0, -1.0, 0.0],
+            };
+            movement = add(movement, step);
+        }
+        self.position = add(self.position, scale(movement, self.speed * seconds));
+    }
+}
+
+impl Node for CameraNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        self.handle_events();
+        self.step();
+
+        let forward = direction(self.yaw, self.pitch);
+        let eye = match self.mode {
+            CameraMode::Orbit => add(self.position, scale(forward, -self.distance)),
+            CameraMode::Fly => self.position,
+        };
+        let (view, inverse_view) = look(eye, forward);
+
+        let (width, height) = target::dimensions(&*self.facade);
+        let aspect = width as f32 / height.max(1) as f32;
+        let projection = perspective(self.fov, aspect, self.near, self.far);
+
+        let mut outputs = HashMap::new();
+        outputs.insert("view".to_string(), NodeOutput::Mat4(view));
+        outputs.insert("projection".to_string(), NodeOutput::Mat4(projection));
+        outputs.insert("inverse_view".to_string(), NodeOutput::Mat4(inverse_view));
+        outputs.insert(
+            "position".to_string(),
+            NodeOutput::Float4([eye[0], eye[1], eye[2], 1.0]),
+        );
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{direction, look};
+
+    #[test]
+    fn view_matrix_moves_the_eye_to_the_origin() {
+        let eye = [1.0, 2.0, 3.0];
+        let (view, inverse) = look(eye, direction(0.0, 0.0));
+
+        // The view matrix takes the eye to the origin, and a point in front of it down the
+        // negative z axis
+        let transform = |m: [[f32; 4]; 4], p: [f32; 3]| -> Vec<f32> {
+            (0..3)
+                .map(|row| m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row])
+                .collect()
+        };
+        assert_eq!(transform(view, eye), vec![0.0, 0.0, 0.0]);
+        assert_eq!(transform(view, [1.0, 2.0, 1.0]), vec![0.0, 0.0, -2.0]);
+        assert_eq!(transform(inverse, [0.0, 0.0, -2.0]), vec![1.0, 2.0, 1.0]);
+    }
+}
@@ -0,0 +1,65 @@
+//! A `Node` that outputs the current date and time, formatted as text
+
+use anyhow::{bail, Error};
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::ClockConfig;
+
+/// A node that outputs the current date and time
+pub struct ClockNode {
+    /// `strftime` pattern to format the time with
+    format: String,
+    /// Whether to use UTC instead of the local time zone
+    utc: bool,
+    /// The time as of the last check
+    text: String,
+}
+
+impl ClockNode {
+    /// Create a new instance
+    pub fn new(config: ClockConfig) -> Result<Self, Error> {
+        let mut node = Self {
+            format: config.format,
+            utc: config.utc,
+            text: String::new(),
+        };
+        // Check the pattern now, so a bad one is reported when the config is loaded
+        node.text = node.format_now()?;
+        Ok(node)
+    }
+
+    /// Formats the current time with the pattern
+    fn format_now(&self) -> Result<String, Error> {
+        let now = if self.utc {
+            time::now_utc()
+        } else {
+            time::now()
+        };
+        Ok(time::strftime(&self.format, &now)?)
+    }
+}
+
+impl Node for ClockNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Clock = *inputs {
+            let mut outputs = HashMap::new();
+            outputs.insert("text".to_string(), NodeOutput::Text(self.text.clone()));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        // The text only changes as often as the pattern shows, so the text node downstream isn't
+        // laid out again every frame
+        match self.format_now() {
+            Ok(ref text) if *text != self.text => {
+                self.text = text.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
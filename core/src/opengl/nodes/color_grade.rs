@@ -0,0 +1,162 @@
+//! The color grade node adjusts the exposure, contrast, and saturation of a texture, and can map
+//! its colors through a lookup table
+
+use anyhow::{bail, Error};
+use glium::{
+    backend::Facade,
+    texture::{RawImage2d, Texture2d},
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction},
+    Program,
+};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::ColorGradeConfig,
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// The lookup table is a strip of `n` squares of `n` by `n` pixels, one square per blue value,
+/// with red increasing to the right and green increasing downwards
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform sampler2D lut;
+    uniform bool use_lut;
+    uniform vec2 resolution;
+    uniform float exposure;
+    uniform float contrast;
+    uniform float saturation;
+
+    vec3 lookup(vec3 rgb) {
+        float n = float(textureSize(lut, 0).y);
+        float blue = rgb.b * (n - 1.0);
+        float slice = floor(blue);
+        float next = min(slice + 1.0, n - 1.0);
+        float x = (rgb.r * (n - 1.0) + 0.5) / (n * n);
+        float y = 1.0 - (rgb.g * (n - 1.0) + 0.5) / n;
+        vec3 a = texture(lut, vec2(x + slice / n, y)).rgb;
+        vec3 b = texture(lut, vec2(x + next / n, y)).rgb;
+        return mix(a, b, blue - slice);
+    }
+
+    void main() {
+        vec4 texel = texture(input_texture, gl_FragCoord.xy / resolution);
+        vec3 rgb = texel.rgb * exp2(exposure);
+        rgb = (rgb - 0.5) * contrast + 0.5;
+        float luma = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+        rgb = mix(vec3(luma), rgb, saturation);
+        rgb = clamp(rgb, 0.0, 1.0);
+        if (use_lut) {
+            rgb = lookup(rgb);
+        }
+        color = vec4(rgb, texel.a);
+    }
+";
+
+/// A node that grades the colors of a texture
+pub struct ColorGradeNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// Shader program used to grade the input
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// Bound in place of the lookup table when there isn't one
+    blank: Texture2d,
+    /// Exposure adjustment in stops, if not connected to another node
+    exposure: f32,
+    /// Contrast multiplier, if not connected to another node
+    contrast: f32,
+    /// Saturation multiplier, if not connected to another node
+    saturation: f32,
+}
+
+impl ColorGradeNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: ColorGradeConfig) -> Result<Self, Error> {
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+        let blank = Texture2d::new(&**facade, RawImage2d::from_raw_rgba(vec![0u8; 4], (1, 1)))?;
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            program,
+            quad: Quad::new(&**facade)?,
+            blank,
+            exposure: config.exposure.or_default(),
+            contrast: config.contrast.or_default(),
+            saturation: config.saturation.or_default(),
+        })
+    }
+}
+
+impl Node for ColorGradeNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::ColorGrade {
+            ref texture,
+            ref lut,
+            exposure,
+            contrast,
+            saturation,
+        } = *inputs
+        {
+            let lut_texture = lut.as_ref().map(|lut| &**lut).unwrap_or(&self.blank);
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("input_texture", texture.sampled());
+            uniforms.push(
+                "lut",
+                lut_texture
+                    .sampled()
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .wrap_function(SamplerWrapFunction::Clamp),
+            );
+            uniforms.push("use_lut", lut.is_some());
+            uniforms.push("resolution", [width as f32, height as f32]);
+            uniforms.push("exposure", exposure.unwrap_or(self.exposure));
+            uniforms.push("contrast", contrast.unwrap_or(self.contrast));
+            uniforms.push("saturation", saturation.unwrap_or(self.saturation));
+
+            let query = profiler::active_query();
+            self.quad.draw(
+                &mut self.texture.as_surface(),
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+}
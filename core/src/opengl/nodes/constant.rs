@@ -0,0 +1,84 @@
+//! A `Node` that outputs the same value every frame
+
+use anyhow::{bail, ensure, Error};
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::ConstantConfig;
+
+/// A `Node` that outputs the same value every frame
+pub struct ConstantNode {
+    value: NodeOutput,
+    /// Whether the value was set since it was last rendered
+    changed: bool,
+}
+
+impl ConstantNode {
+    /// Create a new instance
+    pub fn new(config: &ConstantConfig) -> Self {
+        let value = match *config {
+            ConstantConfig::Float(value) => NodeOutput::Float(value),
+            ConstantConfig::Float2(value) => NodeOutput::Float2(value),
+            ConstantConfig::Float4(value) => NodeOutput::Float4(value),
+            ConstantConfig::Color(value) => NodeOutput::Color(value),
+            ConstantConfig::Mat4(value) => NodeOutput::Mat4(value),
+            ConstantConfig::FloatArray(ref values) => NodeOutput::FloatArray(values.clone()),
+        };
+
+        Self {
+            value,
+            changed: false,
+        }
+    }
+
+    /// Set the value from a list of numbers, which must be as long as the value's type needs
+    pub fn set(&mut self, values: &[f32]) -> Result<(), Error> {
+        let expected = match self.value {
+            NodeOutput::Float(_) => 1,
+            NodeOutput::Float2(_) => 2,
+            NodeOutput::Float4(_) | NodeOutput::Color(_) => 4,
+            NodeOutput::Mat4(_) => 16,
+            NodeOutput::FloatArray(_) => values.len(),
+            _ => bail!("Can't set this constant"),
+        };
+        ensure!(
+            values.len() == expected,
+            "Expected {} values, got {}",
+            expected,
+            values.len()
+        );
+
+        self.value = match self.value {
+            NodeOutput::Float(_) => NodeOutput::Float(values[0]),
+            NodeOutput::Float2(_) => NodeOutput::Float2([values[0], values[1]]),
+            NodeOutput::Float4(_) => {
+                NodeOutput::Float4([values[0], values[1], values[2], values[3]])
+            }
+            NodeOutput::Color(_) => NodeOutput::Color([values[0], values[1], values[2], values[3]]),
+            NodeOutput::Mat4(_) => {
+                let mut matrix = [[0.0; 4]; 4];
+                for (column, chunk) in matrix.iter_mut().zip(values.chunks(4)) {
+                    column.copy_from_slice(chunk);
+                }
+                NodeOutput::Mat4(matrix)
+            }
+            _ => NodeOutput::FloatArray(values.to_vec()),
+        };
+        self.changed = true;
+        Ok(())
+    }
+}
+
+impl Node for ConstantNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        let mut outputs = HashMap::new();
+        outputs.insert("value".to_string(), self.value.clone());
+        Ok(outputs)
+    }
+
+    fn changed(&mut self) -> bool {
+        let changed = self.changed;
+        self.changed = false;
+        changed
+    }
+}
@@ -0,0 +1,134 @@
+//! A `Node` that follows the brightness of a texture over time, like an audio envelope follower
+
+use anyhow::{bail, Error};
+use glium::{
+    backend::Facade,
+    texture::{MipmapsOption, Texture2d, UncompressedFloatFormat},
+    uniforms::MagnifySamplerFilter,
+    BlitTarget, Rect, Surface,
+};
+use std::{collections::HashMap, rc::Rc};
+use time;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::EnvelopeConfig;
+
+/// Size of the texture the input is shrunk to before its brightness is measured
+const SAMPLE_SIZE: u32 = 32;
+
+/// Moves an envelope towards a new level, rising with the `attack` time constant and falling with
+/// the `release` time constant (both in seconds) over `dt` seconds
+pub fn follow(envelope: f32, level: f32, dt: f32, attack: f32, release: f32) -> f32 {
+    let time_constant = if level > envelope { attack } else { release };
+    if time_constant <= 0.0 {
+        return level;
+    }
+    let coefficient = 1.0 - (-dt / time_constant).exp();
+    envelope + (level - envelope) * coefficient
+}
+
+/// A `Node` that outputs the mean brightness of a texture, and an envelope following it
+pub struct EnvelopeNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The small texture the input is shrunk into
+    sample: Texture2d,
+    /// Rise time (seconds)
+    attack: f32,
+    /// Fall time (seconds)
+    release: f32,
+    /// The current value of the envelope
+    envelope: f32,
+    /// When the node was last rendered (nanoseconds)
+    last_render: Option<u64>,
+}
+
+impl EnvelopeNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: &EnvelopeConfig) -> Result<Self, Error> {
+        Ok(Self {
+            facade: Rc::clone(facade),
+            sample: Texture2d::empty_with_format(
+                &**facade,
+                UncompressedFloatFormat::F32F32F32F32,
+                MipmapsOption::NoMipmap,
+                SAMPLE_SIZE,
+                SAMPLE_SIZE,
+            )?,
+            attack: config.attack,
+            release: config.release,
+            envelope: 0.0,
+            last_render: None,
+        })
+    }
+
+    /// Measures the mean luminance of a texture
+    fn brightness(&self, texture: &Texture2d) -> f32 {
+        let (width, height) = texture.dimensions();
+        let source_rect = Rect {
+            left: 0,
+            bottom: 0,
+            width,
+            height,
+        };
+        let target_rect = BlitTarget {
+            left: 0,
+            bottom: 0,
+            width: SAMPLE_SIZE as i32,
+            height: SAMPLE_SIZE as i32,
+        };
+        self.sample.as_surface().blit_from_simple_framebuffer(
+            &texture.as_surface(),
+            &source_rect,
+            &target_rect,
+            MagnifySamplerFilter::Linear,
+        );
+
+        let rows: Vec<Vec<(f32, f32, f32, f32)>> = self.sample.read();
+        let total: f32 = rows
+            .iter()
+            .flatten()
+            .map(|&(r, g, b, _)| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+            .sum();
+        total / (SAMPLE_SIZE * SAMPLE_SIZE) as f32
+    }
+}
+
+impl Node for EnvelopeNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Envelope { ref texture } = *inputs {
+            let level = self.brightness(texture);
+
+            let now = time::precise_time_ns();
+            self.envelope = match self.last_render {
+                Some(last_render) => {
+                    let dt = (now - last_render) as f32 / 1_000_000_000.0;
+                    follow(self.envelope, level, dt, self.attack, self.release)
+                }
+                None => level,
+            };
+            self.last_render = Some(now);
+
+            let mut outputs = HashMap::new();
+            outputs.insert("level".to_string(), NodeOutput::Float(level));
+            outputs.insert("envelope".to_string(), NodeOutput::Float(self.envelope));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::follow;
+
+    #[test]
+    fn rises_and_falls_at_different_rates() {
+        let risen = follow(0.0, 1.0, 0.1, 0.1, 1.0);
+        let fallen = follow(1.0, 0.0, 0.1, 0.1, 1.0);
+        assert!((risen - 0.632).abs() < 0.001);
+        assert!((fallen - 0.905).abs() < 0.001);
+        assert_eq!(follow(0.0, 1.0, 0.1, 0.0, 1.0), 1.0);
+    }
+}
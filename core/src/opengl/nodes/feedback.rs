@@ -0,0 +1,236 @@
+//! A `Node` that takes an initial input and a node connection and loops it back into a node to
+//! avoid dependency cycles. Textures are double buffered: at the end of each frame the inputs are
+//! drawn to back buffers owned by the node, which are swapped with the ones the graph reads, so
+//! the node never holds on to textures that other nodes draw to. Textures fed back can be faded
+//! out a little every frame, and are cleared to a color at the start, when the window is resized,
+//! and on request.
+
+use anyhow::{bail, format_err, Error};
+use glium::{
+    backend::Facade,
+    texture::{Texture1d, Texture2d},
+    Program, Surface,
+};
+use std::{collections::HashMap, mem, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{FeedbackConfig, InputType, NodeConnection},
+    opengl::{
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// The value of matrix inputs until the first frame has been fed back
+#[cfg_attr(rustfmt, rustfmt_skip)]
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform vec2 resolution;
+    uniform float decay;
+
+    void main() {
+        color = texture(input_texture, gl_FragCoord.xy / resolution) * decay;
+    }
+";
+
+/// A `Node` that loops the values of other nodes back into the graph on the next frame
+pub struct FeedbackNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// Connections to the values fed back, named by their `name`
+    inputs: Vec<NodeConnection>,
+    /// Values from the last frame, which the graph reads
+    values: HashMap<String, NodeOutput>,
+    /// Values from this frame, which replace the others when the node is swapped
+    back: HashMap<String, NodeOutput>,
+    /// Color textures are cleared to
+    clear_color: [f32; 4],
+    /// Whether to clear textures that aren't the size of the window
+    clear_on_resize: bool,
+    /// Factor textures are multiplied by each frame
+    decay: f32,
+    /// Shader program used to decay textures
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+}
+
+impl FeedbackNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: FeedbackConfig) -> Result<Self, Error> {
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let mut node = Self {
+            facade: Rc::clone(facade),
+            inputs: Vec::new(),
+            values: HashMap::new(),
+            back: HashMap::new(),
+            clear_color: config.clear_color,
+            clear_on_resize: config.clear_on_resize,
+            decay: config.decay,
+            program,
+            quad: Quad::new(&**facade)?,
+        };
+
+        for input in config.inputs {
+            if let InputType::Any = input.type_ {
+                bail!("Must specify `type` for inputs of feedback node");
+            }
+            node.inputs.push(input);
+        }
+        node.clear()?;
+
+        Ok(node)
+    }
+
+    /// Returns a texture the size of the window, cleared to the clear color
+    fn blank(&self) -> Result<Rc<Texture2d>, Error> {
+        let (width, height) = target::dimensions(&*self.facade);
+        let texture = Texture2d::empty(&*self.facade, width, height)?;
+        let color = self.clear_color;
+        texture
+            .as_surface()
+            .clear_color(color[0], color[1], color[2], color[3]);
+        Ok(resources::track(texture))
+    }
+
+    /// Sets every input back to the value it has before the first frame is fed back
+    pub fn clear(&mut self) -> Result<(), Error> {
+        for input in &self.inputs {
+            let value = match input.type_ {
+                InputType::Any => bail!("Must specify `type` for input `{}`", input.name),
+                InputType::Color => NodeOutput::Color(Default::default()),
+                InputType::Float => NodeOutput::Float(Default::default()),
+                InputType::Float2 => NodeOutput::Float2(Default::default()),
+                InputType::Float4 => NodeOutput::Float4(Default::default()),
+                InputType::Mat4 => NodeOutput::Mat4(IDENTITY),
+                InputType::FloatArray => NodeOutput::FloatArray(Default::default()),
+                InputType::Text => NodeOutput::Text(Default::default()),
+                InputType::Texture2d => NodeOutput::Texture2d(self.blank()?),
+                InputType::Texture1d => {
+                    NodeOutput::Texture1d(resources::track(Texture1d::empty(&*self.facade, 0)?))
+                }
+            };
+            self.values.insert(input.name.clone(), value);
+        }
+        self.back.clear();
+        Ok(())
+    }
+
+    /// Take the values of a feedback node this replaces, for the inputs with the same name and
+    /// type
+    pub fn inherit(&mut self, old: &FeedbackNode) {
+        for (name, value) in &mut self.values {
+            if let Some(old_value) = old.values.get(name) {
+                if mem::discriminant(value) == mem::discriminant(old_value) {
+                    *value = old_value.clone();
+                }
+            }
+        }
+    }
+
+    /// Writes the values of the inputs from the outputs of this frame to the back buffers.
+    /// Textures are drawn to textures of the node's own, multiplied by the decay factor.
+    pub fn update(
+        &mut self,
+        outputs: &HashMap<String, HashMap<String, NodeOutput>>,
+    ) -> Result<(), Error> {
+        for input in &self.inputs {
+            let output = outputs
+                .get(&input.node)
+                .ok_or_else(|| format_err!("No such node: `{}`", input.node))?
+                .get(&input.output)
+                .ok_or_else(|| {
+                    format_err!(
+                        "No such output on node `{}`: `{}`",
+                        input.node,
+                        input.output
+                    )
+                })?;
+
+            let value = match *output {
+                NodeOutput::Texture2d(ref texture) => {
+                    let dimensions = texture.dimensions();
+                    // The back buffer is reused unless the input changed size
+                    let back = match self.back.remove(&input.name) {
+                        Some(NodeOutput::Texture2d(ref back))
+                            if back.dimensions() == dimensions =>
+                        {
+                            Rc::clone(back)
+                        }
+                        _ => resources::track(Texture2d::empty(
+                            &*self.facade,
+                            dimensions.0,
+                            dimensions.1,
+                        )?),
+                    };
+
+                    let mut uniforms = UniformsStorageVec::new();
+                    uniforms.push("input_texture", texture.sampled());
+                    uniforms.push("resolution", [dimensions.0 as f32, dimensions.1 as f32]);
+                    uniforms.push("decay", self.decay);
+                    self.quad.draw(
+                        &mut back.as_surface(),
+                        &self.program,
+                        &uniforms,
+                        &Default::default(),
+                    )?;
+
+                    NodeOutput::Texture2d(back)
+                }
+                ref output => output.clone(),
+            };
+            self.back.insert(input.name.clone(), value);
+        }
+        Ok(())
+    }
+
+    /// Makes the values written by `update` the ones the graph reads, keeping the textures read
+    /// until now as back buffers for the next frame
+    pub fn swap(&mut self) {
+        for (name, value) in &mut self.back {
+            if let Some(front) = self.values.get_mut(name) {
+                mem::swap(front, value);
+            }
+        }
+    }
+}
+
+impl Node for FeedbackNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // Captures render at a size of their own, but the window hasn't been resized
+        if self.clear_on_resize && !target::capturing() {
+            // Frames from before the window was resized, or from a renderer this one replaced,
+            // would be stretched to the new size
+            let dimensions = target::dimensions(&*self.facade);
+            let stale: Vec<String> = self
+                .values
+                .iter()
+                .filter_map(|(name, value)| match *value {
+                    NodeOutput::Texture2d(ref texture) if texture.dimensions() != dimensions => {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                })
+                .collect();
+            for name in stale {
+                let texture = self.blank()?;
+                self.values.insert(name, NodeOutput::Texture2d(texture));
+            }
+        }
+
+        Ok(self.values.clone())
+    }
+}
@@ -0,0 +1,248 @@
+//! A `Node` that outputs the contents of a text, CSV or JSON file. The file's modification time is
+//! checked every frame and the file is read again when it changes, rather than reloading the whole
+//! graph, so other programs can keep feeding data in by rewriting it.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use log::warn;
+use serde_json::Value;
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::{FileConfig, FileFormat};
+
+/// Splits a line of CSV into its fields, keeping commas inside double quotes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // Two quotes in a quoted field are a quote
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(field.split_off(0).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Reads the named columns of a CSV file, whose first line holds the column names
+fn parse_csv(contents: &str, columns: &[String]) -> Result<HashMap<String, Vec<f32>>, Error> {
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+    let header = split_csv_line(lines.next().unwrap_or_default());
+    let indices = columns
+        .iter()
+        .map(|column| {
+            header
+                .iter()
+                .position(|name| name == column)
+                .ok_or_else(|| format_err!("No column named `{}`", column))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut values = vec![Vec::new(); columns.len()];
+    for (row, line) in lines.enumerate() {
+        let fields = split_csv_line(line);
+        for ((column, &index), values) in columns.iter().zip(&indices).zip(&mut values) {
+            let field = fields.get(index).map_or("", |field| field.as_str());
+            values.push(field.parse().map_err(|_| {
+                format_err!(
+                    "Row {}, column `{}`: `{}` is not a number",
+                    row + 1,
+                    column,
+                    field
+                )
+            })?);
+        }
+    }
+    Ok(columns.iter().cloned().zip(values).collect())
+}
+
+/// Reads the named keys of a JSON file, which is either an object whose values are numbers or
+/// lists of numbers, or a list of objects whose values are numbers
+fn parse_json(contents: &str, columns: &[String]) -> Result<HashMap<String, Vec<f32>>, Error> {
+    let number = |value: &Value, column: &str| {
+        value
+            .as_f64()
+            .map(|value| value as f32)
+            .ok_or_else(|| format_err!("`{}` has a value that's not a number", column))
+    };
+
+    let json: Value = serde_json::from_str(contents)?;
+    let mut values = HashMap::new();
+    for column in columns {
+        let column_values = match json {
+            Value::Object(ref object) => match object.get(column) {
+                Some(Value::Array(array)) => array
+                    .iter()
+                    .map(|value| number(value, column))
+                    .collect::<Result<_, _>>()?,
+                Some(value) => vec![number(value, column)?],
+                None => bail!("No key named `{}`", column),
+            },
+            Value::Array(ref rows) => rows
+                .iter()
+                .map(|row| {
+                    let value = row
+                        .get(column)
+                        .ok_or_else(|| format_err!("A row has no key named `{}`", column))?;
+                    number(value, column)
+                })
+                .collect::<Result<_, _>>()?,
+            _ => bail!("Expected an object or a list of objects"),
+        };
+        values.insert(column.clone(), column_values);
+    }
+    Ok(values)
+}
+
+/// A node that outputs the contents of a file
+pub struct FileNode {
+    /// Path to the file
+    path: PathBuf,
+    /// How the file is read
+    format: FileFormat,
+    /// Numeric columns to output
+    columns: Vec<String>,
+    /// When the file was last modified, as of the last time it was read
+    modified: Option<SystemTime>,
+    /// Outputs from the last time the file was read
+    outputs: HashMap<String, NodeOutput>,
+}
+
+impl FileNode {
+    /// Create a new instance
+    pub fn new(config: FileConfig) -> Result<Self, Error> {
+        let format = config.format.unwrap_or_else(|| {
+            match config
+                .path
+                .extension()
+                .and_then(|extension| extension.to_str())
+            {
+                Some("csv") => FileFormat::Csv,
+                Some("json") => FileFormat::Json,
+                _ => FileFormat::Text,
+            }
+        });
+        ensure!(
+            format != FileFormat::Text || config.columns.is_empty(),
+            "Columns can only be read from CSV and JSON files"
+        );
+        ensure!(
+            !config.columns.iter().any(|column| column == "text"),
+            "A column can't be named `text`, which is the name of the file's text output"
+        );
+
+        // Whatever writes the file might not have started yet, so it's fine for it to be missing
+        // until then
+        let mut outputs = HashMap::new();
+        outputs.insert("text".to_string(), NodeOutput::Text(String::new()));
+        for column in &config.columns {
+            outputs.insert(column.clone(), NodeOutput::FloatArray(Vec::new()));
+        }
+
+        let mut node = Self {
+            path: config.path,
+            format,
+            columns: config.columns,
+            modified: None,
+            outputs,
+        };
+        if node.path.exists() {
+            node.read()?;
+        } else {
+            warn!("`{}` doesn't exist yet", node.path.display());
+        }
+        Ok(node)
+    }
+
+    /// Reads the file and updates the outputs from it
+    fn read(&mut self) -> Result<(), Error> {
+        self.modified = fs::metadata(&self.path)?.modified().ok();
+
+        let contents = fs::read_to_string(&self.path)
+            .context(format!("Failed to read `{}`", self.path.display()))?;
+        let columns = match self.format {
+            FileFormat::Text => HashMap::new(),
+            FileFormat::Csv => parse_csv(&contents, &self.columns)?,
+            FileFormat::Json => parse_json(&contents, &self.columns)?,
+        };
+
+        self.outputs
+            .insert("text".to_string(), NodeOutput::Text(contents));
+        for (column, values) in columns {
+            self.outputs.insert(column, NodeOutput::FloatArray(values));
+        }
+        Ok(())
+    }
+}
+
+impl Node for FileNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::File = *inputs {
+            Ok(self.outputs.clone())
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+
+        // The file might be read while it's half written, so the last good contents are kept
+        // until it can be read again
+        match self.read() {
+            Ok(()) => true,
+            Err(error) => {
+                warn!("Failed to read `{}`: {}", self.path.display(), error);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_csv, parse_json, split_csv_line};
+
+    #[test]
+    fn splits_quoted_csv_fields() {
+        assert_eq!(
+            split_csv_line(r#"a, "b, ""c""" ,d"#),
+            vec!["a", r#"b, "c""#, "d"]
+        );
+    }
+
+    #[test]
+    fn parses_csv_columns() {
+        let columns = vec!["y".to_string(), "x".to_string()];
+        let values = parse_csv("x,y,name\n1,2,a\n\n3,4.5,b\n", &columns).unwrap();
+        assert_eq!(values["x"], vec![1.0, 3.0]);
+        assert_eq!(values["y"], vec![2.0, 4.5]);
+        assert!(parse_csv("x,y\n1,a\n", &columns).is_err());
+        assert!(parse_csv("x\n1\n", &columns).is_err());
+    }
+
+    #[test]
+    fn parses_json_columns() {
+        let columns = vec!["level".to_string()];
+        let values = parse_json(r#"{"level": [0.5, 1], "title": "song"}"#, &columns).unwrap();
+        assert_eq!(values["level"], vec![0.5, 1.0]);
+        let values = parse_json(r#"{"level": 2}"#, &columns).unwrap();
+        assert_eq!(values["level"], vec![2.0]);
+        let values = parse_json(r#"[{"level": 1}, {"level": 3}]"#, &columns).unwrap();
+        assert_eq!(values["level"], vec![1.0, 3.0]);
+        assert!(parse_json(r#"{"level": "loud"}"#, &columns).is_err());
+    }
+}
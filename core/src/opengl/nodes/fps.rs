@@ -1,12 +1,13 @@
-//! The FPS counter node draws an FPS counter, using a `TextNode`
+//! The FPS counter node draws an FPS counter, using a `TextNode`, and outputs stats of recent frame
+//! times for overlays that want to show more
 
-use failure::{bail, Error};
+use anyhow::{bail, Error};
 use glium::backend::Facade;
 use std::{collections::HashMap, rc::Rc, sync::mpsc::Receiver};
 
 use super::{Node, NodeInputs, NodeOutput, TextNode};
 use crate::{
-    config::nodes::{FpsConfig, NodeParameter, TextConfig},
+    config::nodes::{FpsConfig, NodeParameter, TextAlign, TextConfig},
     event::RendererEvent,
     util::FpsCounter,
 };
@@ -35,10 +36,14 @@ impl FpsNode {
                     color: config.color.clone(),
                     font_name: config.font_name,
                     font_size: config.font_size,
+                    align: TextAlign::Left,
+                    max_width: None,
+                    line_spacing: 1.0,
+                    scroll: None,
                 },
                 receiver,
             )?,
-            fps_counter: FpsCounter::new(config.interval),
+            fps_counter: FpsCounter::new(config.interval, config.window),
             position: config.position.or_default(),
             color: config.color.or_default(),
         })
@@ -54,9 +59,21 @@ impl Node for FpsNode {
                 text: Some(format!("FPS: {:.01}", self.fps_counter.fps())),
                 position: Some(position.unwrap_or(self.position)),
                 color: Some(color.unwrap_or(self.color)),
+                time: 0.0,
             };
 
-            self.text_node.render(&inputs)
+            let mut outputs = self.text_node.render(&inputs)?;
+            let percentiles = self.fps_counter.percentiles();
+            for &(name, value) in &[
+                ("fps", self.fps_counter.fps()),
+                ("frame_time", self.fps_counter.average_frame_time()),
+                ("low_1", self.fps_counter.one_percent_low()),
+                ("p50", percentiles.p50),
+                ("p99", percentiles.p99),
+            ] {
+                outputs.insert(name.to_string(), NodeOutput::Float(value));
+            }
+            Ok(outputs)
         } else {
             bail!("Wrong input type for node");
         }
@@ -0,0 +1,196 @@
+//! A `Node` that polls a URL on a background thread and outputs the latest response, along with
+//! numeric fields picked out of it if it's JSON
+
+use anyhow::{bail, ensure, Error};
+use log::{debug, warn};
+use reqwest::{header::USER_AGENT, Client};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::HttpConfig;
+
+/// Finds a number in a JSON value by a path of keys and list indices separated by dots. Booleans
+/// count as 0 or 1, and strings holding numbers are parsed, since APIs often send those.
+fn field(value: &Value, path: &str) -> Option<f32> {
+    let mut value = value;
+    for key in path.split('.') {
+        value = match *value {
+            Value::Array(ref array) => array.get(key.parse::<usize>().ok()?)?,
+            Value::Object(ref object) => object.get(key)?,
+            _ => return None,
+        };
+    }
+
+    match *value {
+        Value::Number(ref number) => number.as_f64().map(|number| number as f32),
+        Value::Bool(boolean) => Some(if boolean { 1.0 } else { 0.0 }),
+        Value::String(ref string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// How often the polling thread checks whether it should stop while waiting for the next request
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Requests the URL, returning the body of the response
+fn fetch(client: &Client, url: &str) -> Result<String, Error> {
+    Ok(client
+        .get(url)
+        .header(USER_AGENT, "yotredash")
+        .send()?
+        .error_for_status()?
+        .text()?)
+}
+
+/// A node that outputs the latest response from a URL
+pub struct HttpNode {
+    /// Receives response bodies from the polling thread
+    receiver: Receiver<String>,
+    /// Tells the polling thread to stop
+    stop: Arc<AtomicBool>,
+    /// Numeric fields to output
+    fields: Vec<String>,
+    /// Outputs from the latest response
+    outputs: HashMap<String, NodeOutput>,
+}
+
+impl HttpNode {
+    /// Create a new instance, which starts polling right away
+    pub fn new(config: HttpConfig) -> Result<Self, Error> {
+        ensure!(
+            config.interval > 0.0,
+            "The interval between requests must be above 0"
+        );
+        ensure!(
+            !config.fields.iter().any(|field| field == "text"),
+            "A field can't be named `text`, which is the name of the response's text output"
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let url = config.url.clone();
+        let interval = Duration::from_millis((config.interval * 1000.0) as u64);
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let client = Client::new();
+            // The node sets the flag when it's dropped, so there's no one left to poll for. It's
+            // checked while waiting too, so long intervals don't keep the thread around.
+            while !thread_stop.load(Ordering::Relaxed) {
+                match fetch(&client, &url) {
+                    Ok(body) => {
+                        if sender.send(body).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Request to `{}` failed: {}", url, e),
+                }
+
+                let next = Instant::now() + interval;
+                while !thread_stop.load(Ordering::Relaxed) {
+                    let now = Instant::now();
+                    if now >= next {
+                        break;
+                    }
+                    thread::sleep((next - now).min(STOP_CHECK_INTERVAL));
+                }
+            }
+            debug!("Stopped polling `{}`", url);
+        });
+
+        // Until the first response arrives, fields are 0 and the text is empty
+        let mut outputs = HashMap::new();
+        outputs.insert("text".to_string(), NodeOutput::Text(String::new()));
+        for field in &config.fields {
+            outputs.insert(field.clone(), NodeOutput::Float(0.0));
+        }
+
+        Ok(Self {
+            receiver,
+            stop,
+            fields: config.fields,
+            outputs,
+        })
+    }
+
+    /// Updates the outputs from a response. Fields missing from it keep their last values, so a
+    /// flaky API doesn't make the graph flicker.
+    fn update(&mut self, body: String) {
+        if !self.fields.is_empty() {
+            match serde_json::from_str::<Value>(&body) {
+                Ok(json) => {
+                    for name in &self.fields {
+                        match field(&json, name) {
+                            Some(value) => {
+                                self.outputs.insert(name.clone(), NodeOutput::Float(value));
+                            }
+                            None => debug!("Response has no numeric field `{}`", name),
+                        }
+                    }
+                }
+                Err(e) => warn!("Response isn't JSON: {}", e),
+            }
+        }
+        self.outputs
+            .insert("text".to_string(), NodeOutput::Text(body));
+    }
+}
+
+impl Node for HttpNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Http = *inputs {
+            Ok(self.outputs.clone())
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        // Only the latest response matters if several arrived since the last frame
+        match self.receiver.try_iter().last() {
+            Some(body) => {
+                self.update(body);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for HttpNode {
+    fn drop(&mut self) {
+        // The thread isn't joined, since it might be in the middle of a slow request
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+    use serde_json::json;
+
+    #[test]
+    fn finds_nested_fields() {
+        let value = json!({
+            "viewers": 42,
+            "live": true,
+            "data": { "readings": [1.5, "2.5"], "name": "sensor" },
+        });
+        assert_eq!(field(&value, "viewers"), Some(42.0));
+        assert_eq!(field(&value, "live"), Some(1.0));
+        assert_eq!(field(&value, "data.readings.0"), Some(1.5));
+        assert_eq!(field(&value, "data.readings.1"), Some(2.5));
+        assert_eq!(field(&value, "data.readings.2"), None);
+        assert_eq!(field(&value, "data.name"), None);
+        assert_eq!(field(&value, "missing.field"), None);
+    }
+}
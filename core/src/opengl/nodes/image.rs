@@ -0,0 +1,391 @@
+//! A `Node` that reads an image from file and returns frames from that image. Radiance HDR and
+//! OpenEXR images are uploaded to floating point textures, so they keep their full range.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use exr::prelude::read_first_rgba_layer_from_file;
+use gif::{self, SetParameter};
+use gif_dispose;
+use glium::{
+    backend::Facade,
+    texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat},
+    Rect,
+};
+use image::{self, ImageDecoder, ImageFormat::*};
+use log::debug;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{prelude::*, BufReader, SeekFrom},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use time::{self, Duration, Tm};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{ImageConfig, ImageFrames},
+    opengl::resources,
+};
+
+/// Opens a GIF, returning a reader for its frames and a screen to draw them onto
+fn open_gif(path: &Path) -> Result<(gif::Reader<BufReader<File>>, gif_dispose::Screen), Error> {
+    let file = File::open(path).context("Could not open image file")?;
+    let mut decoder = gif::Decoder::new(BufReader::new(file));
+    decoder.set(gif::ColorOutput::Indexed);
+    let reader = decoder.read_info()?;
+    let screen = gif_dispose::Screen::new_reader(&reader);
+    Ok((reader, screen))
+}
+
+/// Returns the pixels of a GIF screen as RGBA
+fn screen_pixels(screen: &gif_dispose::Screen) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(screen.pixels.width() * screen.pixels.height() * 4);
+    for pixel in screen.pixels.pixels() {
+        pixels.extend(pixel.iter());
+    }
+    pixels
+}
+
+/// The first bytes of every OpenEXR file
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+/// Uploads a floating point image to a half float texture, which keeps values outside of 0 to 1
+fn upload_float(facade: &Rc<dyn Facade>, raw: RawImage2d<'_, f32>) -> Result<Frames, Error> {
+    let texture = Texture2d::with_format(
+        &**facade,
+        raw,
+        UncompressedFloatFormat::F16F16F16F16,
+        MipmapsOption::NoMipmap,
+    )?;
+    Ok(Frames::Uploaded(vec![resources::track(texture)]))
+}
+
+/// Decodes a Radiance HDR image into a floating point texture
+fn decode_hdr<R: BufRead>(reader: R, facade: &Rc<dyn Facade>) -> Result<Frames, Error> {
+    let decoder = image::hdr::HDRDecoder::new(reader)?;
+    let metadata = decoder.metadata();
+    let mut pixels = Vec::with_capacity((metadata.width * metadata.height * 3) as usize);
+    for pixel in decoder.read_image_hdr()? {
+        pixels.extend_from_slice(&pixel.data);
+    }
+    upload_float(
+        facade,
+        RawImage2d::from_raw_rgb_reversed(&pixels, (metadata.width, metadata.height)),
+    )
+}
+
+/// Decodes the first layer of an OpenEXR image into a floating point texture. Images without an
+/// alpha channel are opaque.
+fn decode_exr(path: &Path, facade: &Rc<dyn Facade>) -> Result<Frames, Error> {
+    let image = read_first_rgba_layer_from_file(
+        path,
+        |size, _| (size.width(), vec![0.0_f32; size.area() * 4]),
+        |&mut (width, ref mut data): &mut (usize, Vec<f32>),
+         position,
+         (r, g, b, a): (f32, f32, f32, f32)| {
+            let i = (position.y() * width + position.x()) * 4;
+            data[i..i + 4].copy_from_slice(&[r, g, b, a]);
+        },
+    )
+    .context("Could not read OpenEXR image")?;
+
+    let size = image.layer_data.size;
+    let (_, ref data) = image.layer_data.channel_data.pixels;
+    upload_float(
+        facade,
+        RawImage2d::from_raw_rgba_reversed(data, (size.width() as u32, size.height() as u32)),
+    )
+}
+
+/// Textures that frames are uploaded into as they're shown, which are reused in turn
+struct Resident {
+    /// The textures, and the frame each one holds
+    textures: Vec<(Option<usize>, Rc<Texture2d>)>,
+    /// The texture the next frame is uploaded into
+    next: usize,
+}
+
+impl Resident {
+    /// Create `count` textures for frames of a size. There are always at least two, so the frame
+    /// before is still whole while the next one is uploaded.
+    fn new(facade: &Rc<dyn Facade>, count: usize, width: u32, height: u32) -> Result<Self, Error> {
+        let textures = (0..count.max(2))
+            .map(|_| {
+                Ok((
+                    None,
+                    resources::track(Texture2d::empty_with_mipmaps(
+                        &**facade,
+                        MipmapsOption::NoMipmap,
+                        width,
+                        height,
+                    )?),
+                ))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { textures, next: 0 })
+    }
+
+    /// Returns the texture holding a frame, if it's still there
+    fn get(&self, frame: usize) -> Option<Rc<Texture2d>> {
+        self.textures
+            .iter()
+            .find(|&&(resident, _)| resident == Some(frame))
+            .map(|&(_, ref texture)| Rc::clone(texture))
+    }
+
+    /// Upload a frame into the texture that was used longest ago
+    fn upload(&mut self, frame: usize, pixels: &[u8]) -> Rc<Texture2d> {
+        let (ref mut resident, ref texture) = self.textures[self.next];
+        let (width, height) = texture.dimensions();
+        texture.write(
+            Rect {
+                left: 0,
+                bottom: 0,
+                width,
+                height,
+            },
+            RawImage2d::from_raw_rgba_reversed(pixels, (width, height)),
+        );
+        *resident = Some(frame);
+        self.next = (self.next + 1) % self.textures.len();
+        Rc::clone(texture)
+    }
+}
+
+/// Where the frames of an image come from
+enum Frames {
+    /// Every frame, already uploaded
+    Uploaded(Vec<Rc<Texture2d>>),
+    /// The pixels of every frame, uploaded as they're shown
+    Memory {
+        /// The pixels of each frame
+        frames: Vec<Vec<u8>>,
+        /// The frames on the GPU
+        resident: Resident,
+    },
+    /// Frames decoded from the file as they're shown
+    Stream {
+        /// The image file, which is opened again to loop
+        path: PathBuf,
+        /// Reads the frames from the file
+        reader: gif::Reader<BufReader<File>>,
+        /// The frames drawn so far, since frames only hold what changed from the one before
+        screen: gif_dispose::Screen,
+        /// The frame the reader reads next
+        position: usize,
+        /// The frames on the GPU
+        resident: Resident,
+    },
+}
+
+impl Frames {
+    /// Returns the texture for a frame, uploading it if it isn't on the GPU
+    fn texture(&mut self, frame: usize) -> Result<Rc<Texture2d>, Error> {
+        match *self {
+            Frames::Uploaded(ref textures) => Ok(Rc::clone(&textures[frame])),
+            Frames::Memory {
+                ref frames,
+                ref mut resident,
+            } => Ok(resident
+                .get(frame)
+                .unwrap_or_else(|| resident.upload(frame, &frames[frame]))),
+            Frames::Stream {
+                ref path,
+                ref mut reader,
+                ref mut screen,
+                ref mut position,
+                ref mut resident,
+            } => {
+                if let Some(texture) = resident.get(frame) {
+                    return Ok(texture);
+                }
+
+                // Frames are drawn over the ones before, so they have to be read in order
+                if *position > frame {
+                    let (new_reader, new_screen) = open_gif(path)?;
+                    *reader = new_reader;
+                    *screen = new_screen;
+                    *position = 0;
+                }
+                while *position <= frame {
+                    let next = reader
+                        .read_next_frame()?
+                        .ok_or_else(|| format_err!("Image has fewer frames than it did"))?;
+                    screen.blit_frame(next)?;
+                    *position += 1;
+                }
+                Ok(resident.upload(frame, &screen_pixels(screen)))
+            }
+        }
+    }
+}
+
+/// Reads an image from file, returning its frames and how long each is shown for
+fn load(facade: &Rc<dyn Facade>, config: &ImageConfig) -> Result<(Frames, Vec<Duration>), Error> {
+    let file = File::open(&config.path).context("Could not open image file")?;
+    let mut buf_reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    buf_reader.read_to_end(&mut buf)?;
+    buf_reader.seek(SeekFrom::Start(0))?;
+
+    fn decode_single<D>(decoder: D, facade: &Rc<dyn Facade>) -> Result<Frames, Error>
+    where
+        D: ImageDecoder,
+    {
+        let (width, height) = decoder.dimensions();
+        let buffer = decoder.read_image()?;
+        let raw = RawImage2d::from_raw_rgba_reversed(&buffer, (width as u32, height as u32));
+        let textures = vec![resources::track(Texture2d::with_mipmaps(
+            &**facade,
+            raw,
+            MipmapsOption::NoMipmap,
+        )?)];
+        Ok(Frames::Uploaded(textures))
+    }
+
+    let mut durations = Vec::new();
+    let frames = match image::guess_format(&buf) {
+        // The image crate doesn't know about OpenEXR
+        _ if buf.starts_with(&EXR_MAGIC) => decode_exr(&config.path, facade)?,
+        Ok(BMP) => decode_single(image::bmp::BMPDecoder::new(buf_reader)?, facade)?,
+        Ok(ICO) => decode_single(image::ico::ICODecoder::new(buf_reader)?, facade)?,
+        Ok(JPEG) => decode_single(image::jpeg::JPEGDecoder::new(buf_reader)?, facade)?,
+        Ok(PNG) => decode_single(image::png::PNGDecoder::new(buf_reader)?, facade)?,
+        Ok(PNM) => decode_single(image::pnm::PNMDecoder::new(buf_reader)?, facade)?,
+        Ok(TGA) => decode_single(image::tga::TGADecoder::new(buf_reader)?, facade)?,
+        Ok(TIFF) => decode_single(image::tiff::TIFFDecoder::new(buf_reader)?, facade)?,
+        Ok(WEBP) => decode_single(image::webp::WebpDecoder::new(buf_reader)?, facade)?,
+        Ok(HDR) => decode_hdr(buf_reader, facade)?,
+        Ok(GIF) => {
+            let (mut reader, mut screen) = open_gif(&config.path)?;
+            let width = reader.width() as u32;
+            let height = reader.height() as u32;
+
+            // Every frame is decoded once to find out how long it's shown for, even when
+            // they're streamed
+            let mut pixels = Vec::new();
+            while let Some(frame) = reader.read_next_frame()? {
+                // GIF delays are in 100ths of a second
+                durations.push(Duration::milliseconds(i64::from(frame.delay) * 10));
+                if config.frames != ImageFrames::Stream {
+                    screen.blit_frame(frame)?;
+                    pixels.push(screen_pixels(&screen));
+                }
+            }
+            ensure!(!durations.is_empty(), "Image has no frames");
+
+            match config.frames {
+                ImageFrames::Upload => Frames::Uploaded(
+                    pixels
+                        .into_iter()
+                        .map(|pixels| {
+                            let raw = RawImage2d::from_raw_rgba_reversed(&pixels, (width, height));
+                            Ok(resources::track(Texture2d::with_mipmaps(
+                                &**facade,
+                                raw,
+                                MipmapsOption::NoMipmap,
+                            )?))
+                        })
+                        .collect::<Result<_, Error>>()?,
+                ),
+                ImageFrames::Memory => Frames::Memory {
+                    frames: pixels,
+                    resident: Resident::new(facade, config.resident_frames, width, height)?,
+                },
+                ImageFrames::Stream => {
+                    let (reader, screen) = open_gif(&config.path)?;
+                    Frames::Stream {
+                        path: config.path.clone(),
+                        reader,
+                        screen,
+                        position: 0,
+                        resident: Resident::new(facade, config.resident_frames, width, height)?,
+                    }
+                }
+            }
+        }
+        _ => bail!("Image format not supported"),
+    };
+    Ok((frames, durations))
+}
+
+/// A `Node` that reads an image from file and returns frames from that image
+pub struct ImageNode {
+    /// The frames of the image, or `None` if they were evicted to save memory
+    frames: Option<Frames>,
+    /// The current frame of an animated image
+    current_frame: usize,
+    /// The time that the current frame started rendering - we need to keep track of this so we can
+    /// increment the frame number when the delay is done
+    frame_start: Tm,
+    /// Array of frame durations
+    durations: Vec<Duration>,
+    /// Facade to upload the frames again with after they're evicted
+    facade: Rc<dyn Facade>,
+    /// The configuration the image was read with
+    config: ImageConfig,
+}
+
+impl ImageNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: ImageConfig) -> Result<Self, Error> {
+        debug!("New image node: {}", config.path.to_string_lossy());
+
+        let (frames, durations) = load(facade, &config)?;
+        Ok(Self {
+            frames: Some(frames),
+            current_frame: 0,
+            frame_start: time::now(),
+            durations,
+            facade: Rc::clone(facade),
+            config,
+        })
+    }
+
+    /// Drops the image's textures, which are read from file again the next time it's rendered
+    pub fn evict(&mut self) {
+        if self.frames.take().is_some() {
+            debug!("Evicted image: {}", self.config.path.to_string_lossy());
+        }
+    }
+
+    /// Move on to the next frame of an animated image if it's time, returning whether it moved
+    fn update(&mut self) -> bool {
+        if self.durations.len() > 1
+            && time::now() - self.frame_start > self.durations[self.current_frame]
+        {
+            self.current_frame += 1;
+            if self.current_frame == self.durations.len() {
+                self.current_frame = 0;
+            }
+            self.frame_start = time::now();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Node for ImageNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if self.frames.is_none() {
+            self.frames = Some(load(&self.facade, &self.config)?.0);
+        }
+        let texture = match self.frames {
+            Some(ref mut frames) => frames.texture(self.current_frame)?,
+            None => unreachable!(),
+        };
+
+        let mut outputs = HashMap::new();
+        outputs.insert("texture".to_string(), NodeOutput::Texture2d(texture));
+        Ok(outputs)
+    }
+
+    fn changed(&mut self) -> bool {
+        self.update()
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.config.path.clone()]
+    }
+}
@@ -0,0 +1,174 @@
+//! A `Node` that produces values based on information about the renderer and window
+
+use anyhow::Error;
+use std::{collections::HashMap, sync::mpsc::Receiver};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::{accessibility::AccessibilityConfig, nodes::INFO_MONITORS, PresentMode},
+    event::{PointerButton, PointerEvent, RendererEvent},
+    opengl::target,
+    util::Clock,
+};
+
+/// A `Node` that produces values based on information about the renderer and window
+pub struct InfoNode {
+    receiver: Receiver<RendererEvent>,
+    clock: Clock,
+    resolution: [f32; 2],
+    pointer: [f32; 4],
+    /// Whether the left, right and middle buttons are held, as 1.0 or 0.0
+    buttons: [f32; 4],
+    /// Vertical scrolling accumulated since the node was created, in lines
+    scroll: f32,
+    /// Pointer movement accumulated while any button is held, in physical pixels
+    drag: [f32; 2],
+    reduced_motion: bool,
+    present_mode: PresentMode,
+    monitors: Vec<[f32; 4]>,
+    hidpi_factor: f32,
+}
+
+impl InfoNode {
+    /// Create a new instance
+    pub fn new(
+        receiver: Receiver<RendererEvent>,
+        resolution: [f32; 2],
+        accessibility: &AccessibilityConfig,
+        present_mode: PresentMode,
+    ) -> Self {
+        let mut clock = Clock::new();
+        clock.set_max_speed(accessibility.max_speed());
+
+        Self {
+            receiver,
+            clock,
+            resolution,
+            pointer: [0.0; 4],
+            buttons: [0.0; 4],
+            scroll: 0.0,
+            drag: [0.0; 2],
+            reduced_motion: accessibility.reduced_motion,
+            present_mode,
+            monitors: Vec::new(),
+            hidpi_factor: 1.0,
+        }
+    }
+}
+
+impl InfoNode {
+    /// Carry on from the time of another clock, like the one of a node this replaces
+    pub fn continue_from(&mut self, clock: &Clock) {
+        self.clock.continue_from(clock);
+    }
+}
+
+/// The index of a button in the `buttons` output
+fn button_index(button: PointerButton) -> usize {
+    match button {
+        PointerButton::Left => 0,
+        PointerButton::Right => 1,
+        PointerButton::Middle => 2,
+    }
+}
+
+impl Node for InfoNode {
+    fn render(&mut self, _inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                RendererEvent::Pointer(PointerEvent::Move(x, y)) => {
+                    // winit gives positions in logical pixels, but shaders work in physical ones
+                    let x = x * self.hidpi_factor;
+                    let y = self.resolution[1] - y * self.hidpi_factor;
+                    if self.buttons.iter().any(|&held| held != 0.0) {
+                        self.drag[0] += x - self.pointer[0];
+                        self.drag[1] += y - self.pointer[1];
+                    }
+                    self.pointer[0] = x;
+                    self.pointer[1] = y;
+                }
+                RendererEvent::Pointer(PointerEvent::Press(button)) => {
+                    // Like Shadertoy, the click position is only kept for the left button
+                    if button == PointerButton::Left {
+                        self.pointer[2] = self.pointer[0];
+                        self.pointer[3] = self.pointer[1];
+                    }
+                    self.buttons[button_index(button)] = 1.0;
+                }
+                RendererEvent::Pointer(PointerEvent::Release(button)) => {
+                    if button == PointerButton::Left {
+                        self.pointer[2] = 0.0;
+                        self.pointer[3] = 0.0;
+                    }
+                    self.buttons[button_index(button)] = 0.0;
+                }
+                RendererEvent::Pointer(PointerEvent::Scroll(_, y)) => self.scroll += y,
+                RendererEvent::Resize(width, height) => {
+                    self.resolution = [width as f32, height as f32];
+                }
+                RendererEvent::Time(ref time_event) => self.clock.handle(time_event),
+                RendererEvent::PresentMode(present_mode) => self.present_mode = present_mode,
+                RendererEvent::Monitors(ref monitors) => self.monitors = monitors.clone(),
+                RendererEvent::HidpiFactor(hidpi_factor) => self.hidpi_factor = hidpi_factor,
+                _ => (),
+            }
+        }
+
+        let time = self.clock.time() % 4096.0;
+
+        // While supersampling, report the size of the offscreen target and scale the pointer and
+        // monitors to match
+        let (resolution, scale) = match target::offscreen() {
+            Some(texture) => (
+                [texture.width() as f32, texture.height() as f32],
+                texture.width() as f32 / self.resolution[0],
+            ),
+            None => {
+                let scale = target::supersample() as f32;
+                (
+                    [self.resolution[0] * scale, self.resolution[1] * scale],
+                    scale,
+                )
+            }
+        };
+        let scaled = |v: [f32; 4]| [v[0] * scale, v[1] * scale, v[2] * scale, v[3] * scale];
+
+        let mut outputs = HashMap::new();
+        outputs.insert("time".to_string(), NodeOutput::Float(time));
+        outputs.insert("resolution".to_string(), NodeOutput::Float2(resolution));
+        outputs.insert(
+            "pointer".to_string(),
+            NodeOutput::Float4(scaled(self.pointer)),
+        );
+        outputs.insert("buttons".to_string(), NodeOutput::Float4(self.buttons));
+        outputs.insert("scroll".to_string(), NodeOutput::Float(self.scroll));
+        outputs.insert(
+            "drag".to_string(),
+            NodeOutput::Float2([self.drag[0] * scale, self.drag[1] * scale]),
+        );
+        outputs.insert(
+            "reduced_motion".to_string(),
+            NodeOutput::Float(if self.reduced_motion { 1.0 } else { 0.0 }),
+        );
+        outputs.insert(
+            "present_mode".to_string(),
+            NodeOutput::Text(self.present_mode.to_string()),
+        );
+        outputs.insert(
+            "hidpi_factor".to_string(),
+            NodeOutput::Float(self.hidpi_factor),
+        );
+        outputs.insert(
+            "monitor_count".to_string(),
+            NodeOutput::Float(self.monitors.len() as f32),
+        );
+        for i in 0..INFO_MONITORS {
+            let monitor = self.monitors.get(i).cloned().unwrap_or([0.0; 4]);
+            outputs.insert(
+                format!("monitor_{}", i),
+                NodeOutput::Float4(scaled(monitor)),
+            );
+        }
+        Ok(outputs)
+    }
+}
@@ -0,0 +1,40 @@
+//! A `Node` that outputs the value of a keyframe track at the demo time
+
+use anyhow::{bail, Error};
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::{keyframes::TrackConfig, params::ParamValue};
+
+/// A node that animates a value between keys
+pub struct KeyframesNode {
+    /// The keys and how to move between them
+    track: TrackConfig,
+}
+
+impl KeyframesNode {
+    /// Create a new instance
+    pub fn new(config: &TrackConfig) -> Result<Self, Error> {
+        config.validate()?;
+        Ok(Self {
+            track: config.clone(),
+        })
+    }
+}
+
+impl Node for KeyframesNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Keyframes { time } = *inputs {
+            let value = match self.track.value_at(time) {
+                ParamValue::Scalar(value) => NodeOutput::Float(value),
+                ParamValue::Color(value) => NodeOutput::Color(value),
+            };
+
+            let mut outputs = HashMap::new();
+            outputs.insert("value".to_string(), value);
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! A `Node` that joins an Ableton Link session on the local network and outputs its tempo and
+//! phase, so visuals stay in time with DJ software and other apps that support Link
+
+use anyhow::{bail, ensure, Error};
+use log::info;
+use rusty_link::{AblLink, SessionState};
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::LinkConfig;
+
+/// Returns how far through the current beat and the current bar a beat time is, from 0 to 1
+fn phases(beat: f64, quantum: f64) -> (f32, f32) {
+    (
+        beat.rem_euclid(1.0) as f32,
+        (beat.rem_euclid(quantum) / quantum) as f32,
+    )
+}
+
+/// A node that follows the tempo and phase of a Link session
+pub struct LinkNode {
+    /// Our peer in the session
+    link: AblLink,
+    /// The session's timeline, captured again every frame
+    state: SessionState,
+    /// Beats in a bar
+    quantum: f64,
+    /// Number of peers last frame, to log when it changes
+    peers: u64,
+}
+
+impl LinkNode {
+    /// Create a new instance, joining the session
+    pub fn new(config: &LinkConfig) -> Result<Self, Error> {
+        ensure!(config.quantum > 0.0, "The quantum must be more than 0");
+        ensure!(config.tempo > 0.0, "The tempo must be more than 0");
+
+        let link = AblLink::new(f64::from(config.tempo));
+        link.enable(true);
+        Ok(Self {
+            link,
+            state: SessionState::new(),
+            quantum: f64::from(config.quantum),
+            peers: 0,
+        })
+    }
+}
+
+impl Node for LinkNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Link = *inputs {
+            self.link.capture_app_session_state(&mut self.state);
+            let now = self.link.clock_micros();
+            let beat = self.state.beat_at_time(now, self.quantum);
+            let (beat_phase, bar_phase) = phases(beat, self.quantum);
+
+            let peers = self.link.num_peers();
+            if peers != self.peers {
+                info!("Link session has {} other peers", peers);
+                self.peers = peers;
+            }
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "tempo".to_string(),
+                NodeOutput::Float(self.state.tempo() as f32),
+            );
+            outputs.insert("beat".to_string(), NodeOutput::Float(beat as f32));
+            outputs.insert("beat_phase".to_string(), NodeOutput::Float(beat_phase));
+            outputs.insert("bar_phase".to_string(), NodeOutput::Float(bar_phase));
+            outputs.insert("peers".to_string(), NodeOutput::Float(peers as f32));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
+
+impl Drop for LinkNode {
+    fn drop(&mut self) {
+        self.link.enable(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::phases;
+
+    #[test]
+    fn splits_beats_into_phases() {
+        assert_eq!(phases(5.25, 4.0), (0.25, 0.3125));
+        assert_eq!(phases(-0.5, 4.0), (0.5, 0.875));
+    }
+}
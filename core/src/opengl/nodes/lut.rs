@@ -0,0 +1,297 @@
+//! The LUT node maps the colors of a texture through a 3D lookup table read from an Adobe `.cube`
+//! file. The table is uploaded as a `Texture3d`, so colors between its entries are interpolated by
+//! the GPU.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use glium::{
+    backend::Facade,
+    texture::{
+        ClientFormat, MipmapsOption, RawImage3d, Texture2d, Texture3d, UncompressedFloatFormat,
+    },
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction},
+    Program,
+};
+use std::{borrow::Cow, collections::HashMap, fs, path::PathBuf, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::LutConfig,
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// Colors are scaled into the domain, then moved half an entry in from the edges so the first and
+/// last entries are sampled at their centers
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform sampler3D lut;
+    uniform vec2 resolution;
+    uniform vec3 domain_min;
+    uniform vec3 domain_max;
+    uniform float amount;
+
+    void main() {
+        vec4 texel = texture(input_texture, gl_FragCoord.xy / resolution);
+        float n = float(textureSize(lut, 0).x);
+        vec3 position = clamp((texel.rgb - domain_min) / (domain_max - domain_min), 0.0, 1.0);
+        vec3 mapped = texture(lut, position * (n - 1.0) / n + 0.5 / n).rgb;
+        color = vec4(mix(texel.rgb, mapped, amount), texel.a);
+    }
+";
+
+/// A 3D lookup table read from a `.cube` file
+#[derive(Debug, PartialEq)]
+pub struct Cube {
+    /// Number of entries along each side of the table
+    pub size: usize,
+    /// The input color mapped by the first entry
+    pub domain_min: [f32; 3],
+    /// The input color mapped by the last entry
+    pub domain_max: [f32; 3],
+    /// The output colors, with red changing fastest, then green, then blue
+    pub table: Vec<[f32; 3]>,
+}
+
+/// Parses three numbers from the rest of a line
+fn parse_triple<'a, I>(words: I) -> Result<[f32; 3], Error>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let values = words
+        .map(|word| word.parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()?;
+    ensure!(
+        values.len() == 3,
+        "Expected 3 numbers, found {}",
+        values.len()
+    );
+    Ok([values[0], values[1], values[2]])
+}
+
+/// Parses the contents of a `.cube` file. Only 3D tables are supported.
+pub fn parse_cube(source: &str) -> Result<Cube, Error> {
+    let mut size = None;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut table = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let result = (|| -> Result<(), Error> {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => (),
+                Some(word) if word.starts_with('#') => (),
+                Some("TITLE") => (),
+                Some("LUT_3D_SIZE") => {
+                    let value: usize = words
+                        .next()
+                        .ok_or_else(|| format_err!("Missing size"))?
+                        .parse()?;
+                    ensure!(
+                        value >= 2,
+                        "The table must have at least 2 entries per side"
+                    );
+                    size = Some(value);
+                }
+                Some("LUT_1D_SIZE") => bail!("1D lookup tables aren't supported"),
+                Some("DOMAIN_MIN") => domain_min = parse_triple(words)?,
+                Some("DOMAIN_MAX") => domain_max = parse_triple(words)?,
+                // Resolve writes the domain as a single range for all three channels
+                Some("LUT_3D_INPUT_RANGE") => {
+                    let range = words
+                        .map(|word| word.parse::<f32>())
+                        .collect::<Result<Vec<f32>, _>>()?;
+                    ensure!(
+                        range.len() == 2,
+                        "Expected 2 numbers, found {}",
+                        range.len()
+                    );
+                    domain_min = [range[0]; 3];
+                    domain_max = [range[1]; 3];
+                }
+                Some(word) if word.parse::<f32>().is_ok() => {
+                    table.push(parse_triple(line.split_whitespace())?);
+                }
+                Some(word) => bail!("Unknown keyword `{}`", word),
+            }
+            Ok(())
+        })();
+        result.with_context(|| format!("Error on line {}", index + 1))?;
+    }
+
+    let size = size.ok_or_else(|| format_err!("The file has no LUT_3D_SIZE"))?;
+    ensure!(
+        table.len() == size * size * size,
+        "Expected {} table entries, found {}",
+        size * size * size,
+        table.len()
+    );
+    ensure!(
+        domain_min
+            .iter()
+            .zip(&domain_max)
+            .all(|(min, max)| max > min),
+        "The domain's maximum must be above its minimum"
+    );
+
+    Ok(Cube {
+        size,
+        domain_min,
+        domain_max,
+        table,
+    })
+}
+
+/// A node that maps the colors of a texture through a lookup table
+pub struct LutNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// The lookup table
+    lut: Rc<Texture3d>,
+    /// The input color mapped by the first entry of the table
+    domain_min: [f32; 3],
+    /// The input color mapped by the last entry of the table
+    domain_max: [f32; 3],
+    /// Shader program used to map the input
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// How much of the mapped color to use, if not connected to another node
+    amount: f32,
+    /// The `.cube` file
+    path: PathBuf,
+}
+
+impl LutNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: LutConfig) -> Result<Self, Error> {
+        let source = fs::read_to_string(&config.path)
+            .with_context(|| format!("Could not read {}", config.path.display()))?;
+        let cube = parse_cube(&source)
+            .with_context(|| format!("Could not parse {}", config.path.display()))?;
+
+        let data: Vec<f32> = cube
+            .table
+            .iter()
+            .flat_map(|entry| entry.iter())
+            .cloned()
+            .collect();
+        let raw = RawImage3d {
+            data: Cow::Owned(data),
+            width: cube.size as u32,
+            height: cube.size as u32,
+            depth: cube.size as u32,
+            format: ClientFormat::F32F32F32,
+        };
+        let lut = resources::track(Texture3d::with_format(
+            &**facade,
+            raw,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::NoMipmap,
+        )?);
+
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            lut,
+            domain_min: cube.domain_min,
+            domain_max: cube.domain_max,
+            program,
+            quad: Quad::new(&**facade)?,
+            amount: config.amount.or_default(),
+            path: config.path,
+        })
+    }
+}
+
+impl Node for LutNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::Lut {
+            ref texture,
+            amount,
+        } = *inputs
+        {
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("input_texture", texture.sampled());
+            uniforms.push(
+                "lut",
+                self.lut
+                    .sampled()
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .wrap_function(SamplerWrapFunction::Clamp),
+            );
+            uniforms.push("resolution", [width as f32, height as f32]);
+            uniforms.push("domain_min", self.domain_min);
+            uniforms.push("domain_max", self.domain_max);
+            uniforms.push("amount", amount.unwrap_or(self.amount));
+
+            let query = profiler::active_query();
+            self.quad.draw(
+                &mut self.texture.as_surface(),
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_cube;
+
+    #[test]
+    fn parses_cube_files() {
+        let source = "# Created by hand\nTITLE \"Identity\"\nLUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\n\
+                      DOMAIN_MAX 1 1 1\n\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let cube = parse_cube(source).unwrap();
+        assert_eq!(cube.size, 2);
+        assert_eq!(cube.domain_max, [1.0; 3]);
+        assert_eq!(cube.table[1], [1.0, 0.0, 0.0]);
+        assert_eq!(cube.table[6], [0.0, 1.0, 1.0]);
+
+        let ranged = parse_cube(&source.replace("DOMAIN_MAX 1 1 1", "LUT_3D_INPUT_RANGE 0 2"));
+        assert_eq!(ranged.unwrap().domain_max, [2.0; 3]);
+
+        assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(parse_cube("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").is_err());
+    }
+}
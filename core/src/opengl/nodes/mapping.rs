@@ -0,0 +1,53 @@
+//! A `Node` that scales and smooths a value on its way to another node's parameter
+
+use anyhow::{bail, Error};
+use std::collections::HashMap;
+
+use super::{envelope::follow, Node, NodeInputs, NodeOutput};
+use crate::config::mappings::MappingConfig;
+
+/// A node that outputs its input moved into a range and smoothed
+pub struct MappingNode {
+    /// How to scale and smooth the value
+    mapping: MappingConfig,
+    /// The smoothed value
+    value: Option<f32>,
+    /// Demo time the node was last rendered at (seconds), so smoothing stops while paused
+    last_time: Option<f32>,
+}
+
+impl MappingNode {
+    /// Create a new instance
+    pub fn new(mapping: &MappingConfig) -> Self {
+        Self {
+            mapping: mapping.clone(),
+            value: None,
+            last_time: None,
+        }
+    }
+}
+
+impl Node for MappingNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Mapping { value, time } = *inputs {
+            let target = self.mapping.scale(value);
+
+            let smoothing = self.mapping.smoothing;
+            let value = match (self.value, self.last_time) {
+                // Seeking backwards jumps straight to the target
+                (Some(value), Some(last_time)) if time >= last_time => {
+                    follow(value, target, time - last_time, smoothing, smoothing)
+                }
+                _ => target,
+            };
+            self.value = Some(value);
+            self.last_time = Some(time);
+
+            let mut outputs = HashMap::new();
+            outputs.insert("value".to_string(), NodeOutput::Float(value));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
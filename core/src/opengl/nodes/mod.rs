@@ -0,0 +1,470 @@
+//! Nodes are the basic building blocks for the renderer.
+// TODO: expand documentation and add examples
+
+pub mod audio;
+pub mod blend;
+pub mod bloom;
+pub mod blur;
+pub mod camera;
+pub mod clock;
+pub mod color_grade;
+pub mod constant;
+pub mod envelope;
+pub mod feedback;
+pub mod file;
+pub mod fps;
+pub mod http;
+pub mod image;
+pub mod info;
+pub mod keyframes;
+#[cfg(feature = "link")]
+pub mod link;
+pub mod lut;
+pub mod mapping;
+pub mod output;
+pub mod plugin;
+pub mod random;
+pub mod readback;
+pub mod script;
+pub mod shader;
+pub mod switch;
+pub mod text;
+pub mod timeline;
+pub mod transform;
+pub mod transition;
+
+use anyhow::Error;
+use glium::texture::{Texture1d, Texture2d};
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
+
+#[cfg(feature = "link")]
+pub use self::link::LinkNode;
+pub use self::{
+    audio::AudioNode, blend::BlendNode, bloom::BloomNode, blur::BlurNode, camera::CameraNode,
+    clock::ClockNode, color_grade::ColorGradeNode, constant::ConstantNode, envelope::EnvelopeNode,
+    feedback::FeedbackNode, file::FileNode, fps::FpsNode, http::HttpNode, image::ImageNode,
+    info::InfoNode, keyframes::KeyframesNode, lut::LutNode, mapping::MappingNode,
+    output::OutputNode, plugin::PluginNode, random::RandomNode, readback::ReadbackNode,
+    script::ScriptNode, shader::ShaderNode, switch::SwitchNode, text::TextNode,
+    timeline::TimelineNode, transform::TransformNode, transition::TransitionNode,
+};
+use crate::config::nodes::NodeConnection;
+
+/// Inputs for each node
+pub enum NodeInputs {
+    /// Inputs for info node
+    Info,
+
+    /// Inputs for output node
+    Output {
+        /// Texture to render to the screen
+        texture: Rc<Texture2d>,
+    },
+
+    /// Inputs for image node
+    Image,
+
+    /// Inputs for shader node
+    Shader {
+        /// Node connections for uniforms as input for the shader program
+        uniforms: HashMap<NodeConnection, NodeOutput>,
+    },
+
+    /// Inputs for blend node
+    Blend {
+        /// Textures to blend together
+        textures: Vec<Rc<Texture2d>>,
+    },
+
+    /// Inputs for text node
+    Text {
+        /// Text to render
+        text: Option<String>,
+        /// Position to render at
+        position: Option<[f32; 2]>,
+        /// Color to render in
+        color: Option<[f32; 4]>,
+        /// Demo time (seconds)
+        time: f32,
+    },
+
+    /// Inputs for FPS counter node
+    Fps {
+        /// Position to render at
+        position: Option<[f32; 2]>,
+        /// Color to render in
+        color: Option<[f32; 4]>,
+    },
+
+    /// Inputs for audio node
+    Audio,
+
+    /// Inputs for constant node
+    Constant,
+
+    /// Inputs for camera node
+    Camera,
+
+    /// Inputs for random node
+    Random {
+        /// Demo time (seconds)
+        time: f32,
+    },
+
+    /// Inputs for keyframes node
+    Keyframes {
+        /// Demo time (seconds)
+        time: f32,
+    },
+
+    /// Inputs for mapping node
+    Mapping {
+        /// The value to scale
+        value: f32,
+        /// Demo time (seconds)
+        time: f32,
+    },
+
+    /// Inputs for file node
+    File,
+
+    /// Inputs for HTTP node
+    Http,
+
+    /// Inputs for Link node
+    Link,
+
+    /// Inputs for clock node
+    Clock,
+
+    /// Inputs for envelope node
+    Envelope {
+        /// Texture to follow the brightness of
+        texture: Rc<Texture2d>,
+    },
+
+    /// Inputs for readback node
+    Readback {
+        /// Texture to read back
+        texture: Rc<Texture2d>,
+    },
+
+    /// Inputs for transform node
+    Transform {
+        /// Texture to transform
+        texture: Rc<Texture2d>,
+        /// Offset, as a fraction of the output size
+        translate: Option<[f32; 2]>,
+        /// Rotation (radians)
+        rotate: Option<f32>,
+        /// Scale
+        scale: Option<[f32; 2]>,
+        /// Region of the texture to use
+        crop: Option<[f32; 4]>,
+    },
+
+    /// Inputs for blur node
+    Blur {
+        /// Texture to blur
+        texture: Rc<Texture2d>,
+        /// Blur radius (pixels)
+        radius: Option<f32>,
+    },
+
+    /// Inputs for bloom node
+    Bloom {
+        /// Texture to add the glow to
+        texture: Rc<Texture2d>,
+        /// Brightness above which pixels glow
+        threshold: Option<f32>,
+        /// Strength of the glow
+        intensity: Option<f32>,
+    },
+
+    /// Inputs for color grade node
+    ColorGrade {
+        /// Texture to grade
+        texture: Rc<Texture2d>,
+        /// Lookup table
+        lut: Option<Rc<Texture2d>>,
+        /// Exposure adjustment (stops)
+        exposure: Option<f32>,
+        /// Contrast multiplier
+        contrast: Option<f32>,
+        /// Saturation multiplier
+        saturation: Option<f32>,
+    },
+
+    /// Inputs for LUT node
+    Lut {
+        /// Texture to map
+        texture: Rc<Texture2d>,
+        /// How much of the mapped color to use
+        amount: Option<f32>,
+    },
+
+    /// Inputs for switch node
+    Switch {
+        /// The texture to pass through
+        texture: Rc<Texture2d>,
+        /// Index of the selected input
+        index: usize,
+    },
+
+    /// Inputs for transition node
+    Transition {
+        /// Texture at the start of the transition
+        from: Rc<Texture2d>,
+        /// Texture at the end of the transition
+        to: Rc<Texture2d>,
+        /// Mask for luma dissolves
+        mask: Option<Rc<Texture2d>>,
+        /// How far the transition has gone
+        progress: Option<f32>,
+    },
+
+    /// Inputs for timeline node
+    Timeline {
+        /// The current scene
+        from: Rc<Texture2d>,
+        /// The scene being faded into, if any
+        to: Option<Rc<Texture2d>>,
+        /// How far the fade has gone
+        mix: f32,
+    },
+
+    /// Inputs for feedback node (unused because we have to special-case it somewhere else)
+    Feedback,
+
+    /// Inputs for plugin node
+    Plugin {
+        /// Values of the connected outputs, by input name
+        inputs: Vec<(String, NodeOutput)>,
+    },
+
+    /// Inputs for script node
+    Script {
+        /// Values of the connected outputs, by input name
+        inputs: Vec<(String, NodeOutput)>,
+    },
+}
+
+/// Enum of possible output types for nodes
+#[derive(Clone)]
+pub enum NodeOutput {
+    /// A color (RGBA)
+    Color([f32; 4]),
+    /// An f32
+    Float(f32),
+    /// An array of 2 f32 values
+    Float2([f32; 2]),
+    /// An array of 4 f32 values
+    Float4([f32; 4]),
+    /// A 4x4 matrix, as an array of columns
+    Mat4([[f32; 4]; 4]),
+    /// Any number of f32 values
+    FloatArray(Vec<f32>),
+    /// A string
+    Text(String),
+    /// A 2D texture
+    Texture2d(Rc<Texture2d>),
+    /// A 1D texture
+    Texture1d(Rc<Texture1d>),
+}
+
+/// An enum of all node types
+pub enum NodeType {
+    /// Info node
+    Info(InfoNode),
+    /// Output node
+    Output(OutputNode),
+    /// Image node
+    Image(ImageNode),
+    /// Shader node
+    Shader(ShaderNode),
+    /// Blend node
+    Blend(BlendNode),
+    /// Text node
+    Text(TextNode),
+    /// Fps node
+    Fps(FpsNode),
+    /// Audio node
+    Audio(AudioNode),
+    /// Constant node
+    Constant(ConstantNode),
+    /// Camera node
+    Camera(CameraNode),
+    /// Random node
+    Random(RandomNode),
+    /// Keyframes node
+    Keyframes(KeyframesNode),
+    /// Mapping node
+    Mapping(MappingNode),
+    /// File node
+    File(FileNode),
+    /// HTTP node
+    Http(HttpNode),
+    /// Link node
+    #[cfg(feature = "link")]
+    Link(LinkNode),
+    /// Clock node
+    Clock(ClockNode),
+    /// Envelope node
+    Envelope(EnvelopeNode),
+    /// Readback node
+    Readback(ReadbackNode),
+    /// Transform node
+    Transform(TransformNode),
+    /// Blur node
+    Blur(BlurNode),
+    /// Bloom node
+    Bloom(BloomNode),
+    /// Color grade node
+    ColorGrade(ColorGradeNode),
+    /// LUT node
+    Lut(LutNode),
+    /// Switch node
+    Switch(SwitchNode),
+    /// Transition node
+    Transition(TransitionNode),
+    /// Timeline node
+    Timeline(TimelineNode),
+    /// Feedback node
+    Feedback(FeedbackNode),
+    /// Plugin node
+    Plugin(PluginNode),
+    /// Script node
+    Script(ScriptNode),
+}
+
+impl Node for NodeType {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        use self::NodeType::*;
+        match self {
+            &mut Info(ref mut node) => node.render(inputs),
+            &mut Output(ref mut node) => node.render(inputs),
+            &mut Image(ref mut node) => node.render(inputs),
+            &mut Shader(ref mut node) => node.render(inputs),
+            &mut Blend(ref mut node) => node.render(inputs),
+            &mut Text(ref mut node) => node.render(inputs),
+            &mut Fps(ref mut node) => node.render(inputs),
+            &mut Audio(ref mut node) => node.render(inputs),
+            &mut Constant(ref mut node) => node.render(inputs),
+            &mut Camera(ref mut node) => node.render(inputs),
+            &mut Random(ref mut node) => node.render(inputs),
+            &mut Keyframes(ref mut node) => node.render(inputs),
+            &mut Mapping(ref mut node) => node.render(inputs),
+            &mut File(ref mut node) => node.render(inputs),
+            &mut Http(ref mut node) => node.render(inputs),
+            #[cfg(feature = "link")]
+            &mut Link(ref mut node) => node.render(inputs),
+            &mut Clock(ref mut node) => node.render(inputs),
+            &mut Envelope(ref mut node) => node.render(inputs),
+            &mut Readback(ref mut node) => node.render(inputs),
+            &mut Transform(ref mut node) => node.render(inputs),
+            &mut Blur(ref mut node) => node.render(inputs),
+            &mut Bloom(ref mut node) => node.render(inputs),
+            &mut ColorGrade(ref mut node) => node.render(inputs),
+            &mut Lut(ref mut node) => node.render(inputs),
+            &mut Switch(ref mut node) => node.render(inputs),
+            &mut Transition(ref mut node) => node.render(inputs),
+            &mut Timeline(ref mut node) => node.render(inputs),
+            &mut Feedback(ref mut node) => node.render(inputs),
+            &mut Plugin(ref mut node) => node.render(inputs),
+            &mut Script(ref mut node) => node.render(inputs),
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        use self::NodeType::*;
+        match self {
+            &mut Info(ref mut node) => node.changed(),
+            &mut Output(ref mut node) => node.changed(),
+            &mut Image(ref mut node) => node.changed(),
+            &mut Shader(ref mut node) => node.changed(),
+            &mut Blend(ref mut node) => node.changed(),
+            &mut Text(ref mut node) => node.changed(),
+            &mut Fps(ref mut node) => node.changed(),
+            &mut Audio(ref mut node) => node.changed(),
+            &mut Constant(ref mut node) => node.changed(),
+            &mut Camera(ref mut node) => node.changed(),
+            &mut Random(ref mut node) => node.changed(),
+            &mut Keyframes(ref mut node) => node.changed(),
+            &mut Mapping(ref mut node) => node.changed(),
+            &mut File(ref mut node) => node.changed(),
+            &mut Http(ref mut node) => node.changed(),
+            #[cfg(feature = "link")]
+            &mut Link(ref mut node) => node.changed(),
+            &mut Clock(ref mut node) => node.changed(),
+            &mut Envelope(ref mut node) => node.changed(),
+            &mut Readback(ref mut node) => node.changed(),
+            &mut Transform(ref mut node) => node.changed(),
+            &mut Blur(ref mut node) => node.changed(),
+            &mut Bloom(ref mut node) => node.changed(),
+            &mut ColorGrade(ref mut node) => node.changed(),
+            &mut Lut(ref mut node) => node.changed(),
+            &mut Switch(ref mut node) => node.changed(),
+            &mut Transition(ref mut node) => node.changed(),
+            &mut Timeline(ref mut node) => node.changed(),
+            &mut Feedback(ref mut node) => node.changed(),
+            &mut Plugin(ref mut node) => node.changed(),
+            &mut Script(ref mut node) => node.changed(),
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        use self::NodeType::*;
+        match self {
+            &Info(ref node) => node.files(),
+            &Output(ref node) => node.files(),
+            &Image(ref node) => node.files(),
+            &Shader(ref node) => node.files(),
+            &Blend(ref node) => node.files(),
+            &Text(ref node) => node.files(),
+            &Fps(ref node) => node.files(),
+            &Audio(ref node) => node.files(),
+            &Constant(ref node) => node.files(),
+            &Camera(ref node) => node.files(),
+            &Random(ref node) => node.files(),
+            &Keyframes(ref node) => node.files(),
+            &Mapping(ref node) => node.files(),
+            &File(ref node) => node.files(),
+            &Http(ref node) => node.files(),
+            #[cfg(feature = "link")]
+            &Link(ref node) => node.files(),
+            &Clock(ref node) => node.files(),
+            &Envelope(ref node) => node.files(),
+            &Readback(ref node) => node.files(),
+            &Transform(ref node) => node.files(),
+            &Blur(ref node) => node.files(),
+            &Bloom(ref node) => node.files(),
+            &ColorGrade(ref node) => node.files(),
+            &Lut(ref node) => node.files(),
+            &Switch(ref node) => node.files(),
+            &Transition(ref node) => node.files(),
+            &Timeline(ref node) => node.files(),
+            &Feedback(ref node) => node.files(),
+            &Plugin(ref node) => node.files(),
+            &Script(ref node) => node.files(),
+        }
+    }
+}
+
+/// A `Node` is something that takes input and returns an output
+pub trait Node {
+    /// Does stuff and returns a `NodeOutputs`
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error>;
+
+    /// Returns whether the output could be different from the last frame's even if the inputs are
+    /// the same. Nodes that return false keep their outputs from the last frame while none of
+    /// their inputs change, instead of being rendered. This is called every frame the node is
+    /// needed, before `render`.
+    fn changed(&mut self) -> bool {
+        true
+    }
+
+    /// Returns the files this node was built from, which are watched for changes
+    fn files(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
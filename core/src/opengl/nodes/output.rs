@@ -0,0 +1,98 @@
+//! A `Node` that takes a texture and draws it to the screen
+
+use anyhow::{bail, Error};
+use glium::{backend::Facade, framebuffer::DefaultFramebuffer, program::Program, Surface};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::opengl::{
+    profiler,
+    quad::{self, Quad},
+    target, UniformsStorageVec,
+};
+
+const FRAGMENT: &str = "
+    #version 140
+    out vec4 color;
+    uniform vec2 resolution;
+    uniform int scale;
+    uniform sampler2D texture0;
+    void main() {
+        // Average the block of pixels that makes up this one when supersampling
+        vec4 sum = vec4(0.0);
+        for (int y = 0; y < scale; y++) {
+            for (int x = 0; x < scale; x++) {
+                vec2 offset = (vec2(x, y) + 0.5) / float(scale);
+                vec2 uv = (floor(gl_FragCoord.xy) + offset) / resolution;
+                sum += texture(texture0, uv);
+            }
+        }
+        color = sum / float(scale * scale);
+    }
+";
+
+/// A node that renders its input to the program output
+pub struct OutputNode {
+    /// The `Facade` it uses to work with OpenGL
+    facade: Rc<dyn Facade>,
+    /// The shader program it uses to copy its input to the main output
+    program: Program,
+    /// The quad the program is drawn over
+    quad: Quad,
+}
+
+impl OutputNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        Ok(Self {
+            facade: Rc::clone(facade),
+            program: quad::program(&**facade, FRAGMENT)?,
+            quad: Quad::new(&**facade)?,
+        })
+    }
+
+    /// Draw the input texture to a surface
+    fn draw<S: Surface>(
+        &self,
+        surface: &mut S,
+        uniforms: &UniformsStorageVec<'_, '_>,
+    ) -> Result<(), Error> {
+        let query = profiler::active_query();
+        surface.clear_color(0.0, 0.0, 0.0, 1.0);
+        self.quad.draw(
+            surface,
+            &self.program,
+            uniforms,
+            &profiler::draw_parameters(&query),
+        )
+    }
+}
+
+impl Node for OutputNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Output { ref texture } = *inputs {
+            // The input is `scale` times bigger than what we're drawing to
+            let scale = target::supersample();
+            let (width, height) = target::dimensions(&*self.facade);
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push(
+                "resolution",
+                ((width / scale) as f32, (height / scale) as f32),
+            );
+            uniforms.push("scale", scale as i32);
+            uniforms.push("texture0", &**texture);
+
+            match target::offscreen() {
+                Some(offscreen) => self.draw(&mut offscreen.as_surface(), &uniforms)?,
+                // We draw to the back buffer without finishing the frame so that overlays can be
+                // drawn on top before the buffers are swapped
+                None => self.draw(&mut DefaultFramebuffer::back_left(&*self.facade), &uniforms)?,
+            }
+
+            Ok(HashMap::new())
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! A `Node` whose outputs are rendered by a plugin
+
+use anyhow::{bail, ensure, format_err, Error};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{InputType, PluginConfig},
+    plugin::{Instance, PluginType, Value},
+};
+
+/// Converts a node output to a value for a plugin
+fn to_value(output: &NodeOutput) -> Result<Value, Error> {
+    Ok(match *output {
+        NodeOutput::Color(color) => Value::Color(color),
+        NodeOutput::Float(value) => Value::Float(value),
+        NodeOutput::Float2(value) => Value::Float2(value),
+        NodeOutput::Float4(value) => Value::Float4(value),
+        NodeOutput::Mat4(matrix) => Value::Mat4(matrix),
+        NodeOutput::FloatArray(ref values) => Value::FloatArray(values.clone()),
+        NodeOutput::Text(ref text) => Value::Text(text.clone()),
+        NodeOutput::Texture2d(_) | NodeOutput::Texture1d(_) => {
+            bail!("Textures can't be passed to plugins")
+        }
+    })
+}
+
+/// Converts a value from a plugin to a node output, along with its type
+fn from_value(value: Value) -> (NodeOutput, InputType) {
+    match value {
+        Value::Color(color) => (NodeOutput::Color(color), InputType::Color),
+        Value::Float(value) => (NodeOutput::Float(value), InputType::Float),
+        Value::Float2(value) => (NodeOutput::Float2(value), InputType::Float2),
+        Value::Float4(value) => (NodeOutput::Float4(value), InputType::Float4),
+        Value::Mat4(matrix) => (NodeOutput::Mat4(matrix), InputType::Mat4),
+        Value::FloatArray(values) => (NodeOutput::FloatArray(values), InputType::FloatArray),
+        Value::Text(text) => (NodeOutput::Text(text), InputType::Text),
+    }
+}
+
+/// A `Node` whose outputs are rendered by a plugin
+pub struct PluginNode {
+    instance: Instance,
+    outputs: IndexMap<String, InputType>,
+}
+
+impl PluginNode {
+    /// Create a new instance
+    pub fn new(plugin_type: &PluginType, config: PluginConfig) -> Result<Self, Error> {
+        let settings = serde_json::to_string(&config.settings)?;
+        Ok(Self {
+            instance: Instance::new(plugin_type, &settings)?,
+            outputs: config.outputs,
+        })
+    }
+}
+
+impl Node for PluginNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Plugin { ref inputs } = *inputs {
+            let mut values = Vec::with_capacity(inputs.len());
+            for (name, output) in inputs {
+                values.push((name.clone(), to_value(output)?));
+            }
+
+            let mut outputs = HashMap::new();
+            for (name, value) in self.instance.render(&values)? {
+                let expected = self
+                    .outputs
+                    .get(&name)
+                    .ok_or_else(|| format_err!("Plugin returned undeclared output `{}`", name))?;
+                let (output, actual) = from_value(value);
+                ensure!(
+                    *expected == actual,
+                    "Plugin output `{}` is declared as {} but was {}",
+                    name,
+                    expected,
+                    actual
+                );
+                outputs.insert(name, output);
+            }
+
+            if let Some(missing) = self
+                .outputs
+                .keys()
+                .find(|name| !outputs.contains_key(*name))
+            {
+                bail!("Plugin didn't return output `{}`", missing);
+            }
+
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        self.instance.changed()
+    }
+}
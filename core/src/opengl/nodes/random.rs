@@ -0,0 +1,128 @@
+//! A `Node` that outputs random values which depend only on its seed and the time, so they're the
+//! same on every run, stay put while paused, and come out the same in offline renders
+
+use anyhow::{bail, ensure, Error};
+use std::collections::HashMap;
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::{RandomConfig, RandomMode};
+
+/// Mixes the bits of a number (SplitMix64's finalizer)
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Returns a random value from 0 to 1 for a step of a channel, the same every time for the same
+/// seed
+pub fn white(seed: u64, channel: u64, step: i64) -> f32 {
+    let hash = mix(seed ^ mix(channel ^ mix(step as u64)));
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Returns Perlin noise from 0 to 1 at a position, which passes through 0.5 at every whole step
+/// and moves smoothly between them
+pub fn smooth(seed: u64, channel: u64, position: f64) -> f32 {
+    let step = position.floor();
+    let t = (position - step) as f32;
+    let gradient = |step: i64| white(seed, channel, step) * 2.0 - 1.0;
+    let from = gradient(step as i64) * t;
+    let to = gradient(step as i64 + 1) * (t - 1.0);
+    let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    0.5 + from + (to - from) * fade
+}
+
+/// A node that outputs random values
+pub struct RandomNode {
+    /// Seed for the values
+    seed: u64,
+    /// How the values change over time
+    mode: RandomMode,
+    /// Steps per second of demo time, or 0 for a step every frame
+    rate: f32,
+    /// Number of frames rendered with the time moving, for a new value every frame
+    frame: i64,
+    /// The time of the last frame, to tell whether time has moved
+    last_time: Option<f32>,
+}
+
+impl RandomNode {
+    /// Create a new instance
+    pub fn new(config: &RandomConfig) -> Result<Self, Error> {
+        ensure!(
+            config.mode == RandomMode::White || config.rate > 0.0,
+            "Smooth random values need a `rate` above 0"
+        );
+        Ok(Self {
+            seed: config.seed,
+            mode: config.mode,
+            rate: config.rate,
+            frame: 0,
+            last_time: None,
+        })
+    }
+}
+
+impl Node for RandomNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Random { time } = *inputs {
+            // Frames only count while time is moving, so a paused render keeps its values
+            if self.last_time.map_or(false, |last_time| last_time != time) {
+                self.frame += 1;
+            }
+            self.last_time = Some(time);
+
+            let value = |channel| match self.mode {
+                RandomMode::White if self.rate > 0.0 => white(
+                    self.seed,
+                    channel,
+                    (f64::from(time) * f64::from(self.rate)).floor() as i64,
+                ),
+                RandomMode::White => white(self.seed, channel, self.frame),
+                RandomMode::Smooth => {
+                    smooth(self.seed, channel, f64::from(time) * f64::from(self.rate))
+                }
+            };
+
+            let mut outputs = HashMap::new();
+            outputs.insert("value".to_string(), NodeOutput::Float(value(0)));
+            outputs.insert(
+                "value2".to_string(),
+                NodeOutput::Float2([value(1), value(2)]),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{smooth, white};
+
+    #[test]
+    fn white_noise_is_repeatable() {
+        assert_eq!(white(1, 0, 5), white(1, 0, 5));
+        assert_ne!(white(1, 0, 5), white(2, 0, 5));
+        assert_ne!(white(1, 0, 5), white(1, 1, 5));
+        for step in -100..100 {
+            let value = white(7, 0, step);
+            assert!(value >= 0.0 && value < 1.0);
+        }
+    }
+
+    #[test]
+    fn smooth_noise_is_continuous() {
+        assert_eq!(smooth(3, 0, 4.0), 0.5);
+        let mut previous = smooth(3, 0, 0.0);
+        for i in 1..1000 {
+            let value = smooth(3, 0, f64::from(i) * 0.01);
+            assert!(value >= 0.0 && value <= 1.0);
+            assert!((value - previous).abs() < 0.05);
+            previous = value;
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! A `Node` that reads a texture back to CPU memory for the consumers in `crate::readback`.
+//! Reading pixels straight into memory stalls until the GPU has finished drawing them, so each
+//! frame is copied into one of two pixel buffers instead and read the frame after, once the copy
+//! has had a frame to finish.
+
+use anyhow::{bail, format_err, Error};
+use glium::{
+    backend::Facade,
+    texture::{pixel_buffer::PixelBuffer, Texture2d},
+    Rect,
+};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{readback, renderer::Frame};
+
+/// A pixel buffer and the size of the frame being copied into it
+struct Slot {
+    /// The buffer, with room for `capacity` pixels
+    buffer: PixelBuffer<(u8, u8, u8, u8)>,
+    /// Number of pixels the buffer has room for
+    capacity: usize,
+    /// Size of the frame copied into the buffer, if there's one waiting to be read
+    pending: Option<(u32, u32)>,
+}
+
+/// A `Node` that passes a texture through, reading it back for anything subscribed to the node
+pub struct ReadbackNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The name of the node, which consumers subscribe to
+    name: String,
+    /// The two pixel buffers frames are copied into in turn
+    slots: [Slot; 2],
+    /// The slot the next frame is copied into
+    next: usize,
+}
+
+impl ReadbackNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, name: &str) -> Self {
+        let slot = || Slot {
+            buffer: PixelBuffer::new_empty(&**facade, 0),
+            capacity: 0,
+            pending: None,
+        };
+
+        Self {
+            facade: Rc::clone(facade),
+            name: name.to_string(),
+            slots: [slot(), slot()],
+            next: 0,
+        }
+    }
+
+    /// Read the frame copied into a slot last time, if there is one
+    fn finish(&mut self, index: usize) -> Result<(), Error> {
+        let slot = &mut self.slots[index];
+        let (width, height) = match slot.pending.take() {
+            Some(dimensions) => dimensions,
+            None => return Ok(()),
+        };
+
+        let pixels = slot
+            .buffer
+            .read()
+            .map_err(|e| format_err!("Could not read back pixels: {:?}", e))?;
+
+        // OpenGL stores rows from the bottom up
+        let row = width as usize * 4;
+        let mut flipped = Vec::with_capacity(row * height as usize);
+        for line in pixels[..width as usize * height as usize]
+            .chunks(width as usize)
+            .rev()
+        {
+            for &(r, g, b, a) in line {
+                flipped.extend_from_slice(&[r, g, b, a]);
+            }
+        }
+
+        readback::publish(
+            &self.name,
+            Frame {
+                width,
+                height,
+                pixels: flipped,
+            },
+        );
+        Ok(())
+    }
+
+    /// Start copying a texture into a slot
+    fn start(&mut self, index: usize, texture: &Texture2d) {
+        let (width, height) = texture.dimensions();
+        let pixels = width as usize * height as usize;
+
+        let slot = &mut self.slots[index];
+        if slot.capacity != pixels {
+            slot.buffer = PixelBuffer::new_empty(&*self.facade, pixels);
+            slot.capacity = pixels;
+        }
+
+        let rect = Rect {
+            left: 0,
+            bottom: 0,
+            width,
+            height,
+        };
+        texture.raw_read_to_pixel_buffer(&rect, &mut slot.buffer);
+        slot.pending = Some((width, height));
+    }
+}
+
+impl Node for ReadbackNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Readback { ref texture } = *inputs {
+            let index = self.next;
+            self.next = 1 - index;
+
+            // The other slot was filled last frame, so its copy should be done by now
+            self.finish(1 - index)?;
+            if readback::has_subscribers(&self.name) {
+                self.start(index, texture);
+            }
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
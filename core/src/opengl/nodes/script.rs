@@ -0,0 +1,266 @@
+//! A `Node` that runs a Rhai script every frame, for logic that's too involved to wire up out of
+//! other nodes but doesn't need a plugin
+
+use anyhow::{bail, format_err, Context, Error};
+use indexmap::IndexMap;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::config::nodes::{InputType, ScriptConfig};
+
+/// Most operations a script may run in a frame, so a script stuck in a loop fails instead of
+/// hanging the renderer
+const MAX_OPERATIONS: u64 = 10_000_000;
+/// Longest string a script may build, in bytes. Like the limits below, this keeps a runaway
+/// script from eating all of the memory instead of failing.
+const MAX_STRING_SIZE: usize = 1 << 20;
+/// Most elements an array may hold
+const MAX_ARRAY_SIZE: usize = 100_000;
+/// Most properties an object map may hold
+const MAX_MAP_SIZE: usize = 10_000;
+
+fn float_array(values: &[f32]) -> Dynamic {
+    Dynamic::from_array(
+        values
+            .iter()
+            .map(|&value| Dynamic::from_float(value))
+            .collect(),
+    )
+}
+
+/// Converts a node output to a value for a script
+fn to_dynamic(output: &NodeOutput) -> Result<Dynamic, Error> {
+    Ok(match *output {
+        NodeOutput::Float(value) => Dynamic::from_float(value),
+        NodeOutput::Float2(ref values) => float_array(values),
+        NodeOutput::Float4(ref values) | NodeOutput::Color(ref values) => float_array(values),
+        NodeOutput::Mat4(ref matrix) => Dynamic::from_array(
+            matrix
+                .iter()
+                .map(|column| float_array(&column[..]))
+                .collect(),
+        ),
+        NodeOutput::FloatArray(ref values) => float_array(values),
+        NodeOutput::Text(ref text) => Dynamic::from(text.clone()),
+        NodeOutput::Texture2d(_) | NodeOutput::Texture1d(_) => {
+            bail!("Textures can't be passed to scripts")
+        }
+    })
+}
+
+/// Reads a number, which scripts may write as an integer
+fn to_float(value: &Dynamic) -> Option<f32> {
+    value
+        .as_float()
+        .ok()
+        .or_else(|| value.as_int().ok().map(|value| value as f32))
+}
+
+/// Reads an array of exactly as many numbers as `values` holds
+fn to_floats(value: &Dynamic, values: &mut [f32]) -> Option<()> {
+    let array = value.clone().into_array().ok()?;
+    if array.len() != values.len() {
+        return None;
+    }
+    for (value, element) in values.iter_mut().zip(&array) {
+        *value = to_float(element)?;
+    }
+    Some(())
+}
+
+/// Converts a value set by a script to a node output of the given type
+fn from_dynamic(value: &Dynamic, type_: &InputType) -> Option<NodeOutput> {
+    Some(match *type_ {
+        InputType::Float => NodeOutput::Float(to_float(value)?),
+        InputType::Float2 => {
+            let mut values = [0.0; 2];
+            to_floats(value, &mut values)?;
+            NodeOutput::Float2(values)
+        }
+        InputType::Color => {
+            let mut values = [0.0; 4];
+            to_floats(value, &mut values)?;
+            NodeOutput::Color(values)
+        }
+        InputType::Text => NodeOutput::Text(value.to_string()),
+        _ => return None,
+    })
+}
+
+/// The value an output has until the script first sets it
+fn default_value(type_: &InputType) -> Dynamic {
+    match *type_ {
+        InputType::Float2 => float_array(&[0.0; 2]),
+        InputType::Color => float_array(&[0.0, 0.0, 0.0, 1.0]),
+        InputType::Text => Dynamic::from(String::new()),
+        _ => Dynamic::from_float(0.0),
+    }
+}
+
+/// A `Node` that runs a Rhai script every frame
+pub struct ScriptNode {
+    /// Path of the script, for error messages and watching it for changes
+    path: PathBuf,
+    /// The engine that runs the script, with its limits set
+    engine: Engine,
+    /// The compiled script
+    ast: AST,
+    /// Holds the outputs, which keep their values from one frame to the next
+    scope: Scope<'static>,
+    /// Names and types of the variables the script sets, which become the node's outputs
+    outputs: IndexMap<String, InputType>,
+}
+
+impl ScriptNode {
+    /// Create a new instance
+    pub fn new(config: ScriptConfig) -> Result<Self, Error> {
+        let source = fs::read_to_string(&config.path)
+            .with_context(|| format!("Could not read script `{}`", config.path.display()))?;
+        Self::from_source(&config.path, &source, config.outputs)
+    }
+
+    fn from_source(
+        path: &Path,
+        source: &str,
+        outputs: IndexMap<String, InputType>,
+    ) -> Result<Self, Error> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_ARRAY_SIZE);
+        engine.set_max_map_size(MAX_MAP_SIZE);
+
+        let ast = engine
+            .compile(source)
+            .map_err(|error| format_err!("Error in script `{}`: {}", path.display(), error))?;
+
+        let mut scope = Scope::new();
+        for (name, type_) in &outputs {
+            scope.push_dynamic(name.as_str(), default_value(type_));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            scope,
+            outputs,
+        })
+    }
+
+    fn run(
+        &mut self,
+        inputs: &[(String, NodeOutput)],
+    ) -> Result<HashMap<String, NodeOutput>, Error> {
+        // Inputs are pushed after the outputs so they can be rewound away along with any
+        // variables the script declares
+        let base = self.scope.len();
+        for (name, output) in inputs {
+            self.scope
+                .push_constant_dynamic(name.as_str(), to_dynamic(output)?);
+        }
+        let result = self.engine.run_ast_with_scope(&mut self.scope, &self.ast);
+        self.scope.rewind(base);
+        result
+            .map_err(|error| format_err!("Error in script `{}`: {}", self.path.display(), error))?;
+
+        let mut outputs = HashMap::new();
+        for (name, type_) in &self.outputs {
+            let value = self
+                .scope
+                .get(name)
+                .ok_or_else(|| format_err!("Script output `{}` is missing", name))?;
+            let output = from_dynamic(value, type_).ok_or_else(|| {
+                format_err!(
+                    "Script output `{}` should be {}, but is `{}`",
+                    name,
+                    type_,
+                    value
+                )
+            })?;
+            outputs.insert(name.clone(), output);
+        }
+        Ok(outputs)
+    }
+}
+
+impl Node for ScriptNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Script { ref inputs } = *inputs {
+            self.run(inputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScriptNode;
+    use crate::{config::nodes::InputType, opengl::nodes::NodeOutput};
+    use indexmap::IndexMap;
+    use std::path::Path;
+
+    fn node(source: &str, outputs: &[(&str, InputType)]) -> ScriptNode {
+        let outputs: IndexMap<String, InputType> = outputs
+            .iter()
+            .map(|(name, type_)| (name.to_string(), type_.clone()))
+            .collect();
+        ScriptNode::from_source(Path::new("test.rhai"), source, outputs).unwrap()
+    }
+
+    #[test]
+    fn outputs_keep_their_values() {
+        let mut node = node(
+            r#"
+                count += 1.0;
+                let doubled = time * 2.0;
+                position = [doubled, 1];
+                label = if count > 1.0 { "again" } else { "first" };
+            "#,
+            &[
+                ("count", InputType::Float),
+                ("position", InputType::Float2),
+                ("label", InputType::Text),
+            ],
+        );
+        let inputs = vec![("time".to_string(), NodeOutput::Float(1.5))];
+        node.run(&inputs).unwrap();
+        let outputs = node.run(&inputs).unwrap();
+
+        match outputs["count"] {
+            NodeOutput::Float(count) => assert_eq!(count, 2.0),
+            _ => panic!("count should be a float"),
+        }
+        match outputs["position"] {
+            NodeOutput::Float2(position) => assert_eq!(position, [3.0, 1.0]),
+            _ => panic!("position should be a float2"),
+        }
+        match outputs["label"] {
+            NodeOutput::Text(ref label) => assert_eq!(label, "again"),
+            _ => panic!("label should be text"),
+        }
+    }
+
+    #[test]
+    fn reports_script_errors() {
+        let inputs = vec![("time".to_string(), NodeOutput::Float(0.0))];
+        assert!(node("time = 1.0;", &[]).run(&inputs).is_err());
+        assert!(node("color = 1.0;", &[("color", InputType::Color)])
+            .run(&inputs)
+            .is_err());
+        assert!(node("loop {}", &[]).run(&inputs).is_err());
+        assert!(
+            ScriptNode::from_source(Path::new("test.rhai"), "let = ;", IndexMap::new()).is_err()
+        );
+    }
+}
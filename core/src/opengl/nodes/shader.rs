@@ -0,0 +1,740 @@
+//! A `Shader` contains a `Program` and renders it to an inner texture with inputs from
+//! `Source`s and other `Shader` dependencies
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use glium::{
+    backend::Facade,
+    framebuffer::MultiOutputFrameBuffer,
+    index::{NoIndices, PrimitiveType},
+    program::{ProgramCreationError, ProgramCreationInput},
+    texture::Texture2d,
+    uniforms::UniformType,
+    Api, Program, Surface, VertexBuffer,
+};
+use indexmap::IndexMap;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{prelude::*, BufReader},
+    mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{
+        DefineValue, InputType, Node as ConfigNode, NodeConfig, NodeConnection, ShaderConfig,
+    },
+    opengl::{
+        intern, profiler, program_cache,
+        quad::{Vertex, VERTICES},
+        resources, shader_log, target, UniformsStorageVec,
+    },
+};
+
+/// The body of the vertex shader used when a shader node doesn't have one
+const DEFAULT_VERTEX: &str = "\
+in vec2 position;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+/// Returns the built-in vertex shader, with the same `#version` directive as a fragment shader
+fn default_vertex(fragment_source: &str) -> String {
+    match fragment_source
+        .lines()
+        .find(|line| line.trim_start().starts_with("#version"))
+    {
+        Some(version) => format!("{}\n\n{}", version.trim(), DEFAULT_VERTEX),
+        None => DEFAULT_VERTEX.to_string(),
+    }
+}
+
+/// Adds `#define` lines for the given definitions after the `#version` directive (which must come
+/// first), followed by a `#line` directive so errors still point at the right lines
+fn inject_defines(source: &str, defines: &IndexMap<String, DefineValue>) -> String {
+    if defines.is_empty() {
+        return source.to_string();
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let version = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("#version"));
+    let split = version.map_or(0, |index| index + 1);
+
+    let mut result = String::new();
+    for line in &lines[..split] {
+        result.push_str(line);
+        result.push('\n');
+    }
+    for (name, value) in defines {
+        result.push_str(&format!("#define {} {}\n", name, value));
+    }
+    result.push_str(&format!("#line {}\n", split + 1));
+    for line in &lines[split..] {
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// Checks whether a GLSL extension is supported by compiling a tiny program that requires it,
+/// using the same `#version` directive as the real shaders
+fn extension_supported(facade: &dyn Facade, version: &str, extension: &str) -> bool {
+    let vertex = format!("{}\nvoid main() {{ gl_Position = vec4(0.0); }}\n", version);
+    let fragment = format!(
+        "{}\n#extension {} : require\nvoid main() {{}}\n",
+        version, extension
+    );
+    Program::from_source(facade, &vertex, &fragment, None).is_ok()
+}
+
+/// Reads a shader source file, naming the stage in errors
+fn read_source(path: &Path, stage: &str) -> Result<String, Error> {
+    let file = File::open(path).context(format!("Could not open {} shader file", stage))?;
+    let mut buf_reader = BufReader::new(file);
+    let mut source = String::new();
+    buf_reader
+        .read_to_string(&mut source)
+        .context(format!("Could not read {} shader file", stage))?;
+    Ok(source)
+}
+
+/// Checks whether a vertex shader compiles by pairing it with an empty fragment shader, since info
+/// logs don't say which shader they came from
+fn vertex_compiles(facade: &dyn Facade, vertex_source: &str) -> bool {
+    let version = vertex_source
+        .lines()
+        .find(|line| line.trim_start().starts_with("#version"))
+        .unwrap_or("");
+    let fragment = format!("{}\nvoid main() {{}}\n", version);
+    Program::from_source(facade, vertex_source, &fragment, None).is_ok()
+}
+
+/// Reads the main fragment shader, from its file or from the config, along with the path to name
+/// in errors
+fn main_fragment(config: &ShaderConfig) -> Result<(String, PathBuf), Error> {
+    match (&config.fragment, &config.fragment_source) {
+        (Some(path), None) => Ok((read_source(path, "fragment")?, path.clone())),
+        (None, Some(source)) => Ok((source.clone(), PathBuf::from("fragment_source"))),
+        (Some(_), Some(_)) => {
+            bail!("Shader nodes can't have both `fragment` and `fragment_source`")
+        }
+        (None, None) => bail!("Shader nodes need `fragment` or `fragment_source`"),
+    }
+}
+
+/// Chooses which fragment shader to use, depending on which extensions are supported and whether
+/// the context is OpenGL ES. Returns the path of the shader to use instead of the main one, if
+/// any.
+fn select_fragment(
+    facade: &dyn Facade,
+    config: &ShaderConfig,
+    vertex_source: &str,
+) -> Result<Option<PathBuf>, Error> {
+    let version = vertex_source
+        .lines()
+        .find(|line| line.trim_start().starts_with("#version"))
+        .unwrap_or("");
+    let missing: Vec<&str> = config
+        .extensions
+        .iter()
+        .map(String::as_str)
+        .filter(|extension| !extension_supported(facade, version, extension))
+        .collect();
+
+    if !missing.is_empty() {
+        match config.fragment_fallback {
+            Some(ref fallback) => {
+                warn!(
+                    "Unsupported extensions ({}), using fallback fragment shader {}",
+                    missing.join(", "),
+                    fallback.display()
+                );
+                return Ok(Some(fallback.clone()));
+            }
+            None => bail!(
+                "Shader requires unsupported extensions: {}",
+                missing.join(", ")
+            ),
+        }
+    }
+
+    let api = facade.get_context().get_opengl_version().0;
+    if let (Api::GlEs, Some(ref gles)) = (api, &config.fragment_gles) {
+        warn!("Using OpenGL ES fragment shader {}", gles.display());
+        return Ok(Some(gles.clone()));
+    }
+
+    if config.fragment_gles.is_some() || config.fragment_fallback.is_some() {
+        match config.fragment {
+            Some(ref fragment) => info!("Using fragment shader {}", fragment.display()),
+            None => info!("Using fragment shader from `fragment_source`"),
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the type of output a uniform takes, for the types connections can provide
+fn uniform_input_type(ty: UniformType, array: bool) -> Option<InputType> {
+    Some(match (ty, array) {
+        (UniformType::Float, false) => InputType::Float,
+        (UniformType::Float, true) => InputType::FloatArray,
+        (UniformType::FloatVec2, false) => InputType::Float2,
+        (UniformType::FloatVec4, false) => InputType::Float4,
+        (UniformType::FloatMat4, false) => InputType::Mat4,
+        (UniformType::Sampler1d, false) => InputType::Texture1d,
+        (UniformType::Sampler2d, false) => InputType::Texture2d,
+        _ => return None,
+    })
+}
+
+/// Finds the output to bind a uniform of a shader node to: an output with the same name (one of
+/// the info node's if there are several), or else the only output of the right type of a node
+/// with the same name
+fn resolve_uniform(
+    nodes: &IndexMap<String, ConfigNode>,
+    node_name: &str,
+    name: &str,
+    type_: &InputType,
+) -> Result<NodeConnection, Error> {
+    // Colors are vec4s too
+    let fits = |output: &InputType| {
+        output == type_ || (*type_ == InputType::Float4 && *output == InputType::Color)
+    };
+    let connection = |node: &str, (output, output_type): (String, InputType)| NodeConnection {
+        node: node.to_string(),
+        output,
+        name: name.to_string(),
+        type_: output_type,
+        param: None,
+        track: None,
+    };
+
+    let mut by_output: Vec<(&String, &ConfigNode)> = nodes
+        .iter()
+        .filter(|(other, node)| {
+            *other != node_name
+                && node
+                    .config
+                    .outputs()
+                    .iter()
+                    .any(|(output, output_type)| output == name && fits(output_type))
+        })
+        .collect();
+    if let Some(info) = by_output.iter().position(|(_, node)| match node.config {
+        NodeConfig::Info => true,
+        _ => false,
+    }) {
+        by_output = vec![by_output[info]];
+    }
+    match by_output[..] {
+        [(node, found)] => {
+            let output = found
+                .config
+                .outputs()
+                .into_iter()
+                .find(|(output, _)| output == name)
+                .unwrap();
+            return Ok(connection(node, output));
+        }
+        [] => (),
+        _ => bail!(
+            "Uniform `{}` could be bound to the `{}` output of nodes `{}`",
+            name,
+            name,
+            by_output
+                .iter()
+                .map(|(node, _)| node.as_str())
+                .collect::<Vec<_>>()
+                .join("`, `")
+        ),
+    }
+
+    match nodes.get(name) {
+        Some(node) if name != node_name => {
+            let outputs: Vec<(String, InputType)> = node
+                .config
+                .outputs()
+                .into_iter()
+                .filter(|(_, output_type)| fits(output_type))
+                .collect();
+            match outputs.len() {
+                1 => Ok(connection(name, outputs.into_iter().next().unwrap())),
+                0 => bail!(
+                    "Node `{}` has no {} output for uniform `{}`",
+                    name,
+                    type_,
+                    name
+                ),
+                _ => bail!(
+                    "Node `{}` has several {} outputs for uniform `{}`",
+                    name,
+                    type_,
+                    name
+                ),
+            }
+        }
+        _ => bail!("Found nothing to bind uniform `{}` to", name),
+    }
+}
+
+/// Binds the uniforms of a shader node that aren't connected in its config, given as their names
+/// and types (`None` for types that can't be connected), reporting every one that can't be bound
+fn auto_bind(
+    nodes: &IndexMap<String, ConfigNode>,
+    node_name: &str,
+    uniforms: &[(String, Option<InputType>)],
+) -> Result<Vec<NodeConnection>, Error> {
+    let connected: Vec<&str> = match nodes.get(node_name).map(|node| &node.config) {
+        Some(&NodeConfig::Shader(ref shader_config)) => shader_config
+            .uniforms
+            .iter()
+            .map(|connection| connection.name.as_str())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut connections = Vec::new();
+    let mut errors = Vec::new();
+    for (name, type_) in uniforms {
+        if connected.contains(&name.as_str()) {
+            continue;
+        }
+        let resolved = match *type_ {
+            Some(ref type_) => resolve_uniform(nodes, node_name, name, type_),
+            None => Err(format_err!(
+                "Uniform `{}` has a type that can't be bound",
+                name
+            )),
+        };
+        match resolved {
+            Ok(connection) => connections.push(connection),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    ensure!(
+        errors.is_empty(),
+        "Could not bind the uniforms of `{}` automatically:\n{}",
+        node_name,
+        errors.join("\n")
+    );
+    Ok(connections)
+}
+
+/// Builds the program of a shader node, loading it from the cache (see `program_cache`) if
+/// `cache` is set and it's there
+fn build_program(
+    facade: &dyn Facade,
+    config: &ShaderConfig,
+    cache: bool,
+) -> Result<Program, Error> {
+    let (main_source, main_path) = main_fragment(config)?;
+    let vertex_source = match config.vertex {
+        Some(ref vertex) => read_source(vertex, "vertex")?,
+        None => default_vertex(&main_source),
+    };
+    let (fragment_source, fragment) = match select_fragment(facade, config, &vertex_source)? {
+        Some(path) => (read_source(&path, "fragment")?, path),
+        None => (main_source, main_path),
+    };
+    // The fragment shader used instead may have another version
+    let vertex_source = match config.vertex {
+        Some(_) => vertex_source,
+        None => default_vertex(&fragment_source),
+    };
+    let vertex_path = config
+        .vertex
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("built-in vertex shader"));
+
+    let vertex_injected = inject_defines(&vertex_source, &config.defines);
+    let fragment_injected = inject_defines(&fragment_source, &config.defines);
+
+    // The optional stages get the same definitions as the others
+    let optional = |path: &Option<PathBuf>, stage| -> Result<Option<String>, Error> {
+        match *path {
+            Some(ref path) => Ok(Some(inject_defines(
+                &read_source(path, stage)?,
+                &config.defines,
+            ))),
+            None => Ok(None),
+        }
+    };
+    let geometry_injected = optional(&config.geometry, "geometry")?;
+    let tess_control_injected = optional(&config.tess_control, "tessellation control")?;
+    let tess_eval_injected = optional(&config.tess_eval, "tessellation evaluation")?;
+
+    let key = program_cache::key(
+        facade,
+        &[
+            Some(vertex_injected.as_str()),
+            tess_control_injected.as_ref().map(String::as_str),
+            tess_eval_injected.as_ref().map(String::as_str),
+            geometry_injected.as_ref().map(String::as_str),
+            Some(fragment_injected.as_str()),
+        ],
+    );
+    let cached = if cache {
+        program_cache::load(facade, &key)
+    } else {
+        None
+    };
+    if let Some(program) = cached {
+        return Ok(program);
+    }
+
+    let input = ProgramCreationInput::SourceCode {
+        vertex_shader: &vertex_injected,
+        tessellation_control_shader: tess_control_injected.as_ref().map(String::as_str),
+        tessellation_evaluation_shader: tess_eval_injected.as_ref().map(String::as_str),
+        geometry_shader: geometry_injected.as_ref().map(String::as_str),
+        fragment_shader: &fragment_injected,
+        transform_feedback_varyings: None,
+        outputs_srgb: true,
+        uses_point_size: false,
+    };
+
+    let program = Program::new(facade, input).map_err(|e| match e {
+        ProgramCreationError::CompilationError(ref log, ..) => {
+            // The defines are followed by a `#line` directive, so the log's line numbers
+            // match the original files
+            if !vertex_compiles(facade, &vertex_injected) {
+                format_err!(
+                    "Could not compile shader\n{}",
+                    shader_log::annotate(log, &vertex_source, &vertex_path)
+                )
+            } else if geometry_injected.is_none() && tess_eval_injected.is_none() {
+                format_err!(
+                    "Could not compile shader\n{}",
+                    shader_log::annotate(log, &fragment_source, &fragment)
+                )
+            } else {
+                // There's no cheap way to tell which of the other stages failed
+                format_err!("Could not compile shader\n{}", log)
+            }
+        }
+        e => e.into(),
+    })?;
+    if cache {
+        program_cache::store(&program, &key);
+    }
+    Ok(program)
+}
+
+/// A node that renders a shader program
+pub struct ShaderNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// A shader program which it uses for rendering
+    program: Program,
+    /// Vertex buffer
+    vertex_buffer: VertexBuffer<Vertex>,
+    /// Index buffer
+    index_buffer: NoIndices,
+    /// The shader source files, including variants that weren't used
+    files: Vec<PathBuf>,
+    /// The names of the color outputs, each rendered to its own texture
+    outputs: Vec<String>,
+    /// The names of the elements of array uniforms, like `palette[0]`, by the name of the array
+    element_names: HashMap<String, Vec<Rc<str>>>,
+    /// Storage for the uniforms, kept empty between frames so it's only allocated once
+    uniforms: UniformsStorageVec<'static, 'static>,
+    /// The config the program was built from, to build it again when its files change
+    config: ShaderConfig,
+    /// Whether programs are kept in the program cache
+    cache: bool,
+}
+
+impl ShaderNode {
+    /// Create a new instance, loading the program from the cache (see `program_cache`) if
+    /// `cache` is set and it's there
+    pub fn new(facade: &Rc<dyn Facade>, config: ShaderConfig, cache: bool) -> Result<Self, Error> {
+        for (index, name) in config.outputs.iter().enumerate() {
+            ensure!(!name.is_empty(), "Shader outputs must have a name");
+            ensure!(
+                !config.outputs[..index].contains(name),
+                "The shader output `{}` is listed twice",
+                name
+            );
+        }
+        ensure!(
+            config.tess_control.is_none() || config.tess_eval.is_some(),
+            "A tessellation control shader needs a tessellation evaluation shader too"
+        );
+
+        let program = build_program(&**facade, &config, cache)?;
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            program,
+            vertex_buffer: VertexBuffer::new(&**facade, &VERTICES)?,
+            index_buffer: if config.tess_eval.is_some() {
+                NoIndices(PrimitiveType::Patches {
+                    vertices_per_patch: 3,
+                })
+            } else {
+                NoIndices(PrimitiveType::TrianglesList)
+            },
+            files: config.sources().into_iter().cloned().collect(),
+            outputs: config.outputs.clone(),
+            element_names: HashMap::new(),
+            uniforms: UniformsStorageVec::new(),
+            config,
+            cache,
+        })
+    }
+
+    /// Builds the program again from its files. If that fails, the old program keeps being used.
+    pub fn rebuild(&mut self) -> Result<(), Error> {
+        self.program = build_program(&*self.facade, &self.config, self.cache)?;
+        Ok(())
+    }
+
+    /// Returns connections for the active uniforms of the program that the config of the node
+    /// named `node_name` doesn't connect, for `auto_bind`
+    pub fn auto_bind(
+        &self,
+        nodes: &IndexMap<String, ConfigNode>,
+        node_name: &str,
+    ) -> Result<Vec<NodeConnection>, Error> {
+        let mut uniforms: Vec<(String, Option<InputType>)> = self
+            .program
+            .uniforms()
+            .map(|(name, uniform)| {
+                // Arrays are listed by their first element
+                let array = name.ends_with("[0]");
+                let name = name.trim_end_matches("[0]").to_string();
+                (name, uniform_input_type(uniform.ty, array))
+            })
+            .collect();
+        uniforms.sort_by(|a, b| a.0.cmp(&b.0));
+        auto_bind(nodes, node_name, &uniforms)
+    }
+}
+
+impl Node for ShaderNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Shader { ref uniforms } = *inputs {
+            // Arrays are bound an element at a time, with names that are kept between frames
+            for (connection, uniform) in uniforms {
+                if let NodeOutput::FloatArray(ref values) = *uniform {
+                    let known = self.element_names.get(&connection.name).map_or(0, Vec::len);
+                    if known < values.len() {
+                        let names = self
+                            .element_names
+                            .entry(connection.name.clone())
+                            .or_insert_with(Vec::new);
+                        for index in known..values.len() {
+                            names.push(intern(&format!("{}[{}]", connection.name, index)));
+                        }
+                    }
+                }
+            }
+
+            let uniforms = {
+                let mut storage = mem::take(&mut self.uniforms).recycle();
+                for (connection, uniform) in uniforms {
+                    ensure!(
+                        !connection.name.is_empty(),
+                        "Connections for shader nodes must have a name"
+                    );
+                    let name = connection.name.as_str();
+                    match *uniform {
+                        NodeOutput::Float(uniform) => storage.push(name, uniform),
+                        NodeOutput::Float2(uniform) => storage.push(name, uniform),
+                        NodeOutput::Color(uniform) | NodeOutput::Float4(uniform) => {
+                            storage.push(name, uniform)
+                        }
+                        NodeOutput::Mat4(uniform) => storage.push(name, uniform),
+                        NodeOutput::FloatArray(ref values) => {
+                            // Arrays that have always been empty have no element names yet
+                            if let Some(elements) = self.element_names.get(name) {
+                                for (element, &value) in elements.iter().zip(values) {
+                                    storage.push(Rc::clone(element), value);
+                                }
+                            }
+                        }
+                        NodeOutput::Texture2d(ref uniform) => storage.push(name, uniform.sampled()),
+                        NodeOutput::Texture1d(ref uniform) => storage.push(name, uniform.sampled()),
+                        _ => bail!("Wrong input type for `uniforms`"),
+                    }
+                }
+                storage
+            };
+
+            let (width, height) = target::dimensions(&*self.facade);
+            let query = profiler::active_query();
+            let mut outputs = HashMap::new();
+
+            if self.outputs.is_empty() {
+                let texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+                let mut surface = texture.as_surface();
+                surface.clear_color(0.0, 0.0, 0.0, 1.0);
+                surface.draw(
+                    &self.vertex_buffer,
+                    &self.index_buffer,
+                    &self.program,
+                    &uniforms,
+                    &profiler::draw_parameters(&query),
+                )?;
+
+                outputs.insert(
+                    "texture".to_string(),
+                    NodeOutput::Texture2d(Rc::clone(&texture)),
+                );
+            } else {
+                // Each texture is attached to the fragment output with the same name
+                let textures = self
+                    .outputs
+                    .iter()
+                    .map(|_| Texture2d::empty(&*self.facade, width, height).map(resources::track))
+                    .collect::<Result<Vec<_>, _>>()?;
+                {
+                    let attachments = self
+                        .outputs
+                        .iter()
+                        .zip(&textures)
+                        .map(|(name, texture)| (name.as_str(), &**texture));
+                    let mut surface = MultiOutputFrameBuffer::new(&*self.facade, attachments)?;
+                    surface.clear_color(0.0, 0.0, 0.0, 1.0);
+                    surface.draw(
+                        &self.vertex_buffer,
+                        &self.index_buffer,
+                        &self.program,
+                        &uniforms,
+                        &profiler::draw_parameters(&query),
+                    )?;
+                }
+
+                for (name, texture) in self.outputs.iter().zip(textures) {
+                    outputs.insert(name.clone(), NodeOutput::Texture2d(texture));
+                }
+            }
+
+            self.uniforms = uniforms.recycle();
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.files.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{auto_bind, default_vertex, inject_defines};
+    use crate::config::{
+        nodes::{DefineValue, InputType},
+        Config,
+    };
+    use indexmap::IndexMap;
+
+    #[test]
+    fn injects_defines_after_version() {
+        let mut defines = IndexMap::new();
+        defines.insert("QUALITY".to_string(), DefineValue::Int(2));
+        defines.insert("USE_SHADOWS".to_string(), DefineValue::Bool(true));
+        defines.insert("SCALE".to_string(), DefineValue::Float(1.0));
+
+        assert_eq!(
+            inject_defines("#version 140\n\nvoid main() {}\n", &defines),
+            "#version 140\n#define QUALITY 2\n#define USE_SHADOWS 1\n#define SCALE 1.0\n#line 2\n\nvoid main() {}\n"
+        );
+        assert_eq!(
+            inject_defines("void main() {}", &defines),
+            "#define QUALITY 2\n#define USE_SHADOWS 1\n#define SCALE 1.0\n#line 1\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn default_vertex_follows_fragment_version() {
+        assert!(
+            default_vertex("#version 300 es\nprecision mediump float;\n")
+                .starts_with("#version 300 es\n\nin vec2 position;")
+        );
+        assert!(default_vertex("void main() {}").starts_with("in vec2 position;"));
+    }
+
+    #[test]
+    fn binds_uniforms_by_name() {
+        let source = "
+nodes:
+    info:
+        type: info
+    background:
+        type: image
+        path: background.png
+    tint:
+        type: constant
+        color: [1.0, 0.5, 0.0, 1.0]
+    clock:
+        type: constant
+        float: 0.0
+    time:
+        type: constant
+        float: 1.0
+    shader:
+        type: shader
+        vertex: shader.vert
+        fragment: shader.frag
+        auto_bind: true
+        uniforms:
+            - node: clock
+              output: value
+              name: speed
+";
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let uniforms = vec![
+            ("background".to_string(), Some(InputType::Texture2d)),
+            ("resolution".to_string(), Some(InputType::Float2)),
+            ("speed".to_string(), Some(InputType::Float)),
+            ("time".to_string(), Some(InputType::Float)),
+            ("tint".to_string(), Some(InputType::Float4)),
+        ];
+        let bound: Vec<(String, String, String)> = auto_bind(&config.nodes, "shader", &uniforms)
+            .unwrap()
+            .into_iter()
+            .map(|connection| (connection.name, connection.node, connection.output))
+            .collect();
+        let expected = [
+            ("background", "background", "texture"),
+            ("resolution", "info", "resolution"),
+            ("time", "info", "time"),
+            ("tint", "tint", "value"),
+        ];
+        assert_eq!(
+            bound,
+            expected
+                .iter()
+                .map(|&(name, node, output)| (
+                    name.to_string(),
+                    node.to_string(),
+                    output.to_string()
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        // Every uniform that can't be bound is reported
+        let uniforms = vec![
+            ("background".to_string(), Some(InputType::Float)),
+            ("missing".to_string(), Some(InputType::Float)),
+            ("lookup".to_string(), None),
+        ];
+        let error = auto_bind(&config.nodes, "shader", &uniforms)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("`background`"));
+        assert!(error.contains("`missing`"));
+        assert!(error.contains("`lookup`"));
+    }
+}
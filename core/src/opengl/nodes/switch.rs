@@ -0,0 +1,61 @@
+//! A `Node` that passes through one of several textures, so a performance can cut between scenes
+
+use anyhow::{bail, Error};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+
+/// Returns which of `count` inputs an index selects: the index is rounded down and clamped to the
+/// inputs there are
+pub fn switch_index(index: f32, count: usize) -> usize {
+    if count == 0 || index.is_nan() || index < 0.0 {
+        return 0;
+    }
+    (index.floor() as usize).min(count - 1)
+}
+
+/// A `Node` that passes through one of several textures
+#[derive(Default)]
+pub struct SwitchNode;
+
+impl SwitchNode {
+    /// Create a new instance
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Node for SwitchNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Switch { ref texture, index } = *inputs {
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(texture)),
+            );
+            outputs.insert("index".to_string(), NodeOutput::Float(index as f32));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::switch_index;
+
+    #[test]
+    fn clamps_index() {
+        assert_eq!(switch_index(0.0, 3), 0);
+        assert_eq!(switch_index(1.7, 3), 1);
+        assert_eq!(switch_index(5.0, 3), 2);
+        assert_eq!(switch_index(-1.0, 3), 0);
+        assert_eq!(switch_index(::std::f32::NAN, 3), 0);
+        assert_eq!(switch_index(2.0, 0), 0);
+    }
+}
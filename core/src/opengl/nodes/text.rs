@@ -0,0 +1,268 @@
+//! The text node draws text at a specified position and in a specified color, or scrolls it
+//! through a viewport
+
+use anyhow::{bail, Error};
+use glium::{backend::Facade, texture::Texture2d, Surface};
+use std::{collections::HashMap, rc::Rc, sync::mpsc::Receiver};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{ScrollDirection, ScrollMode, TextConfig, TextScroll},
+    event::RendererEvent,
+    opengl::{
+        resources, target,
+        text::{TextLayout, TextRenderer},
+    },
+};
+
+/// Returns where scrolling text is along the direction it moves in, after it's moved `distance`
+/// from `from` towards `to`
+fn scroll_offset(from: f32, to: f32, distance: f32, mode: ScrollMode) -> f32 {
+    let range = (to - from).abs();
+    if range == 0.0 {
+        return from;
+    }
+
+    // Moving backwards in time moves the text back too, so this has to wrap negative distances
+    let wrap = |distance: f32, period: f32| ((distance % period) + period) % period;
+    let travelled = match mode {
+        ScrollMode::Loop => wrap(distance, range),
+        ScrollMode::Bounce => {
+            let travelled = wrap(distance, range * 2.0);
+            if travelled > range {
+                range * 2.0 - travelled
+            } else {
+                travelled
+            }
+        }
+    };
+    from + (to - from).signum() * travelled
+}
+
+/// A node that draws text
+pub struct TextNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// The TextRenderer it uses to render text
+    text_renderer: TextRenderer,
+    /// Name of the font
+    font_name: String,
+    /// Font size, before scaling by the DPI factor
+    font_size: f32,
+    /// The DPI factor the text should be scaled by
+    hidpi_factor: f32,
+    /// The DPI factor the text renderer was created with
+    text_renderer_hidpi_factor: f32,
+    /// The text it draws
+    text: String,
+    /// The position to draw the text
+    position: [f32; 2],
+    /// The color of the text in RGBA format
+    color: [f32; 4],
+    /// How to lay out the text
+    layout: TextLayout,
+    /// How to scroll the text, if it scrolls
+    scroll: Option<TextScroll>,
+    /// How far the text has scrolled (pixels)
+    scrolled: f32,
+    /// Demo time of the last frame, to tell how far to scroll
+    last_time: Option<f32>,
+    /// Receiver for events
+    receiver: Receiver<RendererEvent>,
+}
+
+impl TextNode {
+    /// Create a new instance
+    pub fn new(
+        facade: &Rc<dyn Facade>,
+        config: TextConfig,
+        receiver: Receiver<RendererEvent>,
+    ) -> Result<Self, Error> {
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+
+        let text_renderer = TextRenderer::new(facade, &config.font_name, config.font_size)?;
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            text_renderer,
+            font_name: config.font_name,
+            font_size: config.font_size,
+            hidpi_factor: 1.0,
+            text_renderer_hidpi_factor: 1.0,
+            text: config.text.or_default(),
+            position: config.position.or_default(),
+            color: config.color.or_default(),
+            layout: TextLayout {
+                align: config.align,
+                max_width: config.max_width,
+                line_spacing: config.line_spacing,
+                clip: None,
+            },
+            scroll: config.scroll,
+            scrolled: 0.0,
+            last_time: None,
+            receiver,
+        })
+    }
+}
+
+impl TextNode {
+    /// Moves scrolling text along by the time since the last frame, returning where to draw it and
+    /// the box to clip it to
+    fn scroll(
+        &mut self,
+        text: &str,
+        position: [f32; 2],
+        time: f32,
+    ) -> Result<([f32; 2], Option<[f32; 4]>), Error> {
+        let scroll = match self.scroll {
+            Some(ref scroll) => scroll.clone(),
+            None => return Ok((position, None)),
+        };
+
+        let elapsed = self.last_time.map_or(0.0, |last_time| time - last_time);
+        self.last_time = Some(time);
+        self.scrolled += scroll.speed * elapsed;
+
+        let (width, height) = target::dimensions(&*self.facade);
+        let viewport = scroll
+            .viewport
+            .unwrap_or([0.0, 0.0, width as f32, height as f32]);
+        let size = self.text_renderer.measure(text, &self.layout)?;
+
+        // Looping text starts just outside the viewport and leaves through the other side, while
+        // bouncing text goes back and forth between having each of its ends against an edge.
+        // Positions are measured upwards, so text moving down goes towards lower values.
+        let (start, end) = match scroll.direction {
+            ScrollDirection::Left | ScrollDirection::Right => {
+                let (x, width) = (viewport[0], viewport[2]);
+                match scroll.mode {
+                    ScrollMode::Loop => (x + width, x - size[0]),
+                    ScrollMode::Bounce => (x + width - size[0], x),
+                }
+            }
+            ScrollDirection::Up | ScrollDirection::Down => {
+                let (y, height) = (viewport[1], viewport[3]);
+                match scroll.mode {
+                    ScrollMode::Loop => (y - height, y + size[1]),
+                    ScrollMode::Bounce => (y - height + size[1], y),
+                }
+            }
+        };
+        let (from, to) = match scroll.direction {
+            ScrollDirection::Left | ScrollDirection::Up => (start, end),
+            ScrollDirection::Right | ScrollDirection::Down => (end, start),
+        };
+
+        let offset = scroll_offset(from, to, self.scrolled, scroll.mode);
+        let position = match scroll.direction {
+            ScrollDirection::Left | ScrollDirection::Right => [offset, position[1]],
+            ScrollDirection::Up | ScrollDirection::Down => [position[0], offset],
+        };
+        Ok((position, Some(viewport)))
+    }
+
+    /// Handle the events sent since last time, returning whether any of them change the text
+    fn handle_events(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                RendererEvent::Resize(_, _) => changed = true,
+                RendererEvent::HidpiFactor(hidpi_factor) if hidpi_factor != self.hidpi_factor => {
+                    self.hidpi_factor = hidpi_factor;
+                    changed = true;
+                }
+                _ => (),
+            }
+        }
+        changed
+    }
+}
+
+impl Node for TextNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        self.handle_events();
+        if self.text_renderer_hidpi_factor != self.hidpi_factor {
+            self.text_renderer = TextRenderer::new(
+                &self.facade,
+                &self.font_name,
+                self.font_size * self.hidpi_factor,
+            )?;
+            self.text_renderer_hidpi_factor = self.hidpi_factor;
+        }
+
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::Text {
+            ref text,
+            ref position,
+            ref color,
+            time,
+        } = *inputs
+        {
+            let text = text.clone().unwrap_or_else(|| self.text.to_string());
+            let position = position.unwrap_or(self.position);
+            let color = color.unwrap_or(self.color);
+            let (position, clip) = self.scroll(&text, position, time)?;
+            self.layout.clip = clip;
+
+            let mut surface = self.texture.as_surface();
+            surface.clear_color(0.0, 0.0, 0.0, 1.0);
+            let [text_width, text_height] = self.text_renderer.draw_text_with_layout(
+                &mut surface,
+                &text,
+                position,
+                color,
+                &self.layout,
+            )?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            outputs.insert("width".to_string(), NodeOutput::Float(text_width));
+            outputs.insert("height".to_string(), NodeOutput::Float(text_height));
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        // Scrolling text moves every frame
+        self.handle_events() || self.scroll.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scroll_offset;
+    use crate::config::nodes::ScrollMode;
+
+    #[test]
+    fn loops_scrolling_text() {
+        assert_eq!(scroll_offset(100.0, -20.0, 0.0, ScrollMode::Loop), 100.0);
+        assert_eq!(scroll_offset(100.0, -20.0, 30.0, ScrollMode::Loop), 70.0);
+        assert_eq!(scroll_offset(100.0, -20.0, 150.0, ScrollMode::Loop), 70.0);
+        assert_eq!(scroll_offset(-20.0, 100.0, 30.0, ScrollMode::Loop), 10.0);
+        assert_eq!(scroll_offset(100.0, -20.0, -30.0, ScrollMode::Loop), 10.0);
+    }
+
+    #[test]
+    fn bounces_scrolling_text() {
+        assert_eq!(scroll_offset(80.0, 0.0, 30.0, ScrollMode::Bounce), 50.0);
+        assert_eq!(scroll_offset(80.0, 0.0, 80.0, ScrollMode::Bounce), 0.0);
+        assert_eq!(scroll_offset(80.0, 0.0, 110.0, ScrollMode::Bounce), 30.0);
+        assert_eq!(scroll_offset(80.0, 0.0, 190.0, ScrollMode::Bounce), 50.0);
+        assert_eq!(scroll_offset(5.0, 5.0, 42.0, ScrollMode::Bounce), 5.0);
+    }
+}
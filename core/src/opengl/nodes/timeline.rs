@@ -0,0 +1,110 @@
+//! The timeline node shows the current scene of the timeline, fading between scenes
+
+use anyhow::{bail, Error};
+use glium::{backend::Facade, texture::Texture2d, Program};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::opengl::{
+    profiler,
+    quad::{self, Quad},
+    resources, target, UniformsStorageVec,
+};
+
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D from_texture;
+    uniform sampler2D to_texture;
+    uniform vec2 resolution;
+    uniform float mix_amount;
+
+    void main() {
+        vec2 uv = gl_FragCoord.xy / resolution;
+        color = mix(texture(from_texture, uv), texture(to_texture, uv), mix_amount);
+    }
+";
+
+/// A node that shows the current scene of the timeline
+pub struct TimelineNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders fades to
+    texture: Rc<Texture2d>,
+    /// Shader program used to fade between scenes
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+}
+
+impl TimelineNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            program,
+            quad: Quad::new(&**facade)?,
+        })
+    }
+}
+
+impl Node for TimelineNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        if let NodeInputs::Timeline {
+            ref from,
+            ref to,
+            mix,
+        } = *inputs
+        {
+            let mut outputs = HashMap::new();
+
+            // Between fades, the scene is passed through as it is
+            let to = match *to {
+                Some(ref to) => to,
+                None => {
+                    outputs.insert(
+                        "texture".to_string(),
+                        NodeOutput::Texture2d(Rc::clone(from)),
+                    );
+                    return Ok(outputs);
+                }
+            };
+
+            // The target is bigger than the window while supersampling
+            let (width, height) = target::dimensions(&*self.facade);
+            if self.texture.dimensions() != (width, height) {
+                self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+            }
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("from_texture", from.sampled());
+            uniforms.push("to_texture", to.sampled());
+            uniforms.push("resolution", [width as f32, height as f32]);
+            uniforms.push("mix_amount", mix);
+
+            let query = profiler::active_query();
+            self.quad.draw(
+                &mut self.texture.as_surface(),
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
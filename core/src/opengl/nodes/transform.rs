@@ -0,0 +1,278 @@
+//! The transform node translates, rotates, scales, flips, and crops a texture
+
+use anyhow::{bail, Error};
+use glium::{backend::Facade, texture::Texture2d, Program, Surface};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::TransformConfig,
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D input_texture;
+    uniform mat3 transform;
+    uniform vec4 crop;
+
+    void main() {
+        vec2 uv = (transform * vec3(gl_FragCoord.xy, 1.0)).xy;
+        vec2 low = min(crop.xy, crop.xy + crop.zw);
+        vec2 high = max(crop.xy, crop.xy + crop.zw);
+        if (any(lessThan(uv, low)) || any(greaterThan(uv, high))) {
+            color = vec4(0.0);
+        } else {
+            color = texture(input_texture, uv);
+        }
+    }
+";
+
+/// Parameters of a transform
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    /// Offset, as a fraction of the size of the output
+    pub translate: [f32; 2],
+    /// Rotation around the center, counterclockwise (radians)
+    pub rotate: f32,
+    /// Scale around the center
+    pub scale: [f32; 2],
+    /// Whether to mirror horizontally and vertically
+    pub flip: [bool; 2],
+    /// The region of the input to use, as (x, y, width, height) in texture coordinates
+    pub crop: [f32; 4],
+}
+
+impl Transform {
+    /// Returns the matrix taking pixel coordinates in an output of the given size to texture
+    /// coordinates in the input, as columns
+    pub fn inverse_matrix(&self, resolution: [f32; 2]) -> [[f32; 3]; 3] {
+        // Avoid dividing by zero, which would fill the output with NaNs
+        let nonzero = |value: f32| {
+            if value.abs() < 1e-6 {
+                1e-6_f32.copysign(value)
+            } else {
+                value
+            }
+        };
+        let flip = |flip: bool| if flip { -1.0 } else { 1.0 };
+        let sx = flip(self.flip[0]) / nonzero(self.scale[0]);
+        let sy = flip(self.flip[1]) / nonzero(self.scale[1]);
+        let (sin, cos) = self.rotate.sin_cos();
+
+        // Undo the rotation, then the scale and flip
+        let a = [[sx * cos, sx * sin], [-sy * sin, sy * cos]];
+
+        // Everything happens around the center, after translating
+        let center = [resolution[0] / 2.0, resolution[1] / 2.0];
+        let moved = [
+            center[0] + self.translate[0] * resolution[0],
+            center[1] + self.translate[1] * resolution[1],
+        ];
+        let offset = [
+            center[0] - (a[0][0] * moved[0] + a[0][1] * moved[1]),
+            center[1] - (a[1][0] * moved[0] + a[1][1] * moved[1]),
+        ];
+
+        // Then the whole output is stretched over the cropped region
+        let [x, y, width, height] = self.crop;
+        let (rx, ry) = (resolution[0] / width, resolution[1] / height);
+        [
+            [a[0][0] / rx, a[1][0] / ry, 0.0],
+            [a[0][1] / rx, a[1][1] / ry, 0.0],
+            [x + offset[0] / rx, y + offset[1] / ry, 1.0],
+        ]
+    }
+}
+
+/// A node that transforms a texture
+pub struct TransformNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// Shader program used to transform the input
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// The transform, used for parameters that aren't connected to other nodes
+    transform: Transform,
+}
+
+impl TransformNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: TransformConfig) -> Result<Self, Error> {
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            program,
+            quad: Quad::new(&**facade)?,
+            transform: Transform {
+                translate: config.translate.or_default(),
+                rotate: config.rotate.or_default(),
+                scale: config.scale.or_default(),
+                flip: [config.flip_x, config.flip_y],
+                crop: config.crop.or_default(),
+            },
+        })
+    }
+}
+
+impl Node for TransformNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::Transform {
+            ref texture,
+            translate,
+            rotate,
+            scale,
+            crop,
+        } = *inputs
+        {
+            let transform = Transform {
+                translate: translate.unwrap_or(self.transform.translate),
+                rotate: rotate.unwrap_or(self.transform.rotate),
+                scale: scale.unwrap_or(self.transform.scale),
+                flip: self.transform.flip,
+                crop: crop.unwrap_or(self.transform.crop),
+            };
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("input_texture", texture.sampled());
+            uniforms.push(
+                "transform",
+                transform.inverse_matrix([width as f32, height as f32]),
+            );
+            uniforms.push("crop", transform.crop);
+
+            let query = profiler::active_query();
+            let mut surface = self.texture.as_surface();
+            surface.clear_color(0.0, 0.0, 0.0, 0.0);
+            self.quad.draw(
+                &mut surface,
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+
+    fn changed(&mut self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn apply(matrix: [[f32; 3]; 3], point: [f32; 2]) -> [f32; 2] {
+        [
+            matrix[0][0] * point[0] + matrix[1][0] * point[1] + matrix[2][0],
+            matrix[0][1] * point[0] + matrix[1][1] * point[1] + matrix[2][1],
+        ]
+    }
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn maps_output_pixels_to_input_coordinates() {
+        let resolution = [200.0, 100.0];
+        let identity = Transform {
+            translate: [0.0, 0.0],
+            rotate: 0.0,
+            scale: [1.0, 1.0],
+            flip: [false, false],
+            crop: [0.0, 0.0, 1.0, 1.0],
+        };
+        assert_close(
+            apply(identity.inverse_matrix(resolution), [50.0, 25.0]),
+            [0.25, 0.25],
+        );
+
+        let moved = Transform {
+            translate: [0.25, 0.0],
+            ..identity
+        };
+        assert_close(
+            apply(moved.inverse_matrix(resolution), [150.0, 50.0]),
+            [0.5, 0.5],
+        );
+
+        let zoomed = Transform {
+            scale: [2.0, 2.0],
+            ..identity
+        };
+        assert_close(
+            apply(zoomed.inverse_matrix(resolution), [200.0, 100.0]),
+            [0.75, 0.75],
+        );
+
+        let flipped = Transform {
+            flip: [true, false],
+            ..identity
+        };
+        assert_close(
+            apply(flipped.inverse_matrix(resolution), [0.0, 0.0]),
+            [1.0, 0.0],
+        );
+
+        // The output shows only the right half of the bottom half of the input
+        let cropped = Transform {
+            crop: [0.5, 0.0, 0.5, 0.5],
+            ..identity
+        };
+        assert_close(
+            apply(cropped.inverse_matrix(resolution), [0.0, 0.0]),
+            [0.5, 0.0],
+        );
+        assert_close(
+            apply(cropped.inverse_matrix(resolution), [200.0, 100.0]),
+            [1.0, 0.5],
+        );
+
+        // A quarter turn counterclockwise takes the right of the input to the top of the output
+        let rotated = Transform {
+            rotate: FRAC_PI_2,
+            ..identity
+        };
+        assert_close(
+            apply(rotated.inverse_matrix(resolution), [100.0, 90.0]),
+            [0.7, 0.5],
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! The transition node blends from one texture to another as its progress goes from 0 to 1
+
+use anyhow::{bail, ensure, Error};
+use glium::{
+    backend::Facade,
+    texture::{RawImage2d, Texture2d},
+    Program,
+};
+use std::{collections::HashMap, rc::Rc};
+
+use super::{Node, NodeInputs, NodeOutput};
+use crate::{
+    config::nodes::{TransitionConfig, TransitionKind},
+    opengl::{
+        profiler,
+        quad::{self, Quad},
+        resources, target, UniformsStorageVec,
+    },
+};
+
+/// Each kind of transition works out a threshold for every pixel, and the pixel switches from
+/// `from` to `to` once the progress passes it
+const FRAGMENT: &str = "
+    #version 140
+
+    out vec4 color;
+
+    uniform sampler2D from_texture;
+    uniform sampler2D to_texture;
+    uniform sampler2D mask;
+    uniform vec2 resolution;
+    uniform int kind;
+    uniform float progress;
+    uniform float angle;
+    uniform float softness;
+
+    void main() {
+        vec2 uv = gl_FragCoord.xy / resolution;
+        float mix_amount;
+        if (kind == 0) {
+            mix_amount = progress;
+        } else {
+            float threshold;
+            if (kind == 1) {
+                // Position along the direction of the wipe, from 0 to 1 over the whole texture
+                vec2 direction = vec2(cos(angle), sin(angle));
+                float extent = abs(direction.x) + abs(direction.y);
+                threshold = (dot(uv - 0.5, direction) / extent) + 0.5;
+            } else {
+                threshold = dot(texture(mask, uv).rgb, vec3(0.2126, 0.7152, 0.0722));
+            }
+            // Stretch the progress so the soft edge fully enters and leaves
+            float edge = progress * (1.0 + softness) - softness;
+            mix_amount = 1.0 - smoothstep(edge, edge + softness + 0.0001, threshold);
+        }
+        color = mix(texture(from_texture, uv), texture(to_texture, uv), clamp(mix_amount, 0.0, 1.0));
+    }
+";
+
+/// A node that transitions between two textures
+pub struct TransitionNode {
+    /// The Facade it uses to work with the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The inner texture it renders to
+    texture: Rc<Texture2d>,
+    /// Shader program used to blend the inputs
+    program: Program,
+    /// The quad the shader is drawn over
+    quad: Quad,
+    /// Bound in place of the mask when there isn't one
+    blank: Texture2d,
+    /// Which transition to use
+    kind: TransitionKind,
+    /// How far the transition has gone, if not connected to another node
+    progress: f32,
+    /// Direction of a wipe (radians)
+    angle: f32,
+    /// Width of the edge of a wipe or dissolve, as a fraction of the transition
+    softness: f32,
+}
+
+impl TransitionNode {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>, config: TransitionConfig) -> Result<Self, Error> {
+        ensure!(
+            config.transition != TransitionKind::LumaDissolve || config.mask.is_some(),
+            "Luma dissolves need a `mask` texture"
+        );
+
+        let program = quad::program(&**facade, FRAGMENT)?;
+
+        let (width, height) = facade.get_context().get_framebuffer_dimensions();
+        let texture = resources::track(Texture2d::empty(&**facade, width, height)?);
+        let blank = Texture2d::new(&**facade, RawImage2d::from_raw_rgba(vec![0u8; 4], (1, 1)))?;
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            texture,
+            program,
+            quad: Quad::new(&**facade)?,
+            blank,
+            kind: config.transition,
+            progress: config.progress.or_default(),
+            angle: config.angle,
+            softness: config.softness.max(0.0),
+        })
+    }
+}
+
+impl Node for TransitionNode {
+    fn render(&mut self, inputs: &NodeInputs) -> Result<HashMap<String, NodeOutput>, Error> {
+        // The target is bigger than the window while supersampling
+        let (width, height) = target::dimensions(&*self.facade);
+        if self.texture.dimensions() != (width, height) {
+            self.texture = resources::track(Texture2d::empty(&*self.facade, width, height)?);
+        }
+
+        if let NodeInputs::Transition {
+            ref from,
+            ref to,
+            ref mask,
+            progress,
+        } = *inputs
+        {
+            let kind = match self.kind {
+                TransitionKind::Crossfade => 0,
+                TransitionKind::Wipe => 1,
+                TransitionKind::LumaDissolve => 2,
+            };
+
+            let mut uniforms = UniformsStorageVec::new();
+            uniforms.push("from_texture", from.sampled());
+            uniforms.push("to_texture", to.sampled());
+            uniforms.push(
+                "mask",
+                mask.as_ref()
+                    .map(|mask| &**mask)
+                    .unwrap_or(&self.blank)
+                    .sampled(),
+            );
+            uniforms.push("resolution", [width as f32, height as f32]);
+            uniforms.push("kind", kind);
+            uniforms.push("progress", progress.unwrap_or(self.progress));
+            uniforms.push("angle", self.angle);
+            uniforms.push("softness", self.softness);
+
+            let query = profiler::active_query();
+            self.quad.draw(
+                &mut self.texture.as_surface(),
+                &self.program,
+                &uniforms,
+                &profiler::draw_parameters(&query),
+            )?;
+
+            let mut outputs = HashMap::new();
+            outputs.insert(
+                "texture".to_string(),
+                NodeOutput::Texture2d(Rc::clone(&self.texture)),
+            );
+            Ok(outputs)
+        } else {
+            bail!("Wrong input type for node");
+        }
+    }
+}
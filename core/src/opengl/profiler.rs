@@ -0,0 +1,202 @@
+//! Measures how long each node takes to render, on both the CPU and the GPU
+//!
+//! GPU times are measured with timer queries. While a node is being profiled, its query is made
+//! available through `active_query`, and nodes attach it to their draw calls with
+//! `draw_parameters`. Query results are read back in a later frame, once the GPU has finished with
+//! them, so we don't stall the pipeline waiting on them.
+
+use glium::{backend::Facade, draw_parameters::TimeElapsedQuery, DrawParameters};
+use log::warn;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    mem,
+    rc::Rc,
+};
+use time;
+
+use crate::renderer::{FrameStats, NodeTiming};
+
+/// Most frames whose GPU timings may be outstanding before we wait for the oldest one
+const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+/// A timer query for a single node
+pub struct NodeQuery {
+    /// The underlying OpenGL query
+    query: TimeElapsedQuery,
+    /// Whether any draw call has used this query yet
+    used: Cell<bool>,
+}
+
+thread_local! {
+    /// The query for the node currently being profiled, if any
+    static ACTIVE_QUERY: RefCell<Option<Rc<NodeQuery>>> = RefCell::new(None);
+}
+
+/// Returns the query for the node currently being profiled, if profiling is enabled
+pub fn active_query() -> Option<Rc<NodeQuery>> {
+    ACTIVE_QUERY.with(|query| query.borrow().clone())
+}
+
+/// Returns default draw parameters which report into the given query
+pub fn draw_parameters(query: &Option<Rc<NodeQuery>>) -> DrawParameters<'_> {
+    DrawParameters {
+        time_elapsed_query: query.as_ref().map(|query| {
+            query.used.set(true);
+            &query.query
+        }),
+        ..Default::default()
+    }
+}
+
+/// A node which has been rendered but whose GPU time hasn't been read back yet
+struct PendingTiming {
+    /// Name of the node
+    name: String,
+    /// Time spent on the CPU (nanoseconds)
+    cpu_time: u64,
+    /// Query measuring time spent on the GPU
+    query: Option<Rc<NodeQuery>>,
+}
+
+impl PendingTiming {
+    /// Whether the GPU time can be read back without waiting on the GPU
+    fn is_ready(&self) -> bool {
+        self.query
+            .as_ref()
+            .map_or(true, |query| !query.used.get() || query.query.is_ready())
+    }
+
+    /// Read back the GPU time, waiting for it if it isn't ready yet
+    fn into_timing(self) -> NodeTiming {
+        let gpu_time = self
+            .query
+            .and_then(|query| Rc::try_unwrap(query).ok())
+            .filter(|query| query.used.get())
+            .map(|query| query.query.get())
+            .unwrap_or(0);
+
+        NodeTiming {
+            name: self.name,
+            cpu_time: self.cpu_time as f32 / 1_000_000.0,
+            gpu_time: gpu_time as f32 / 1_000_000.0,
+            texture_memory: 0,
+        }
+    }
+}
+
+/// Collects timings for each node rendered in a frame
+pub struct Profiler {
+    /// The facade used to create queries
+    facade: Rc<dyn Facade>,
+    /// Whether GPU timer queries are enabled
+    enabled: bool,
+    /// Nodes rendered in the current frame
+    pending: Vec<PendingTiming>,
+    /// Earlier frames whose GPU timings haven't been read back yet, oldest first
+    in_flight: VecDeque<Vec<PendingTiming>>,
+    /// When the current frame started (nanoseconds)
+    frame_start: u64,
+    /// Stats for the last completed frame
+    stats: FrameStats,
+}
+
+impl Profiler {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Self {
+        Self {
+            facade: Rc::clone(facade),
+            enabled: false,
+            pending: Vec::new(),
+            in_flight: VecDeque::new(),
+            frame_start: time::precise_time_ns(),
+            stats: Default::default(),
+        }
+    }
+
+    /// Enable or disable GPU timer queries
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Start a new frame, reading back the node timings of the newest frame the GPU has finished
+    pub fn begin_frame(&mut self) {
+        let now = time::precise_time_ns();
+
+        self.in_flight
+            .push_back(mem::replace(&mut self.pending, Vec::new()));
+        // Once too many frames are outstanding, wait for the oldest rather than letting the
+        // timings fall further behind
+        while let Some(frame) = self.in_flight.front() {
+            if self.in_flight.len() <= MAX_FRAMES_IN_FLIGHT
+                && !frame.iter().all(PendingTiming::is_ready)
+            {
+                break;
+            }
+
+            if let Some(frame) = self.in_flight.pop_front() {
+                self.stats.nodes = frame.into_iter().map(PendingTiming::into_timing).collect();
+            }
+        }
+        self.stats.frame_time = (now - self.frame_start) as f32 / 1_000_000.0;
+
+        self.frame_start = now;
+    }
+
+    /// Run a node's render function while measuring it
+    pub fn profile<T, F>(&mut self, name: &str, render: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let query = if self.enabled {
+            match TimeElapsedQuery::new(&*self.facade) {
+                Ok(query) => Some(Rc::new(NodeQuery {
+                    query,
+                    used: Cell::new(false),
+                })),
+                Err(e) => {
+                    warn!(
+                        "Could not create timer query, disabling GPU timings: {:?}",
+                        e
+                    );
+                    self.enabled = false;
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        ACTIVE_QUERY.with(|active| *active.borrow_mut() = query.clone());
+        let start = time::precise_time_ns();
+        let result = render();
+        let cpu_time = time::precise_time_ns() - start;
+        ACTIVE_QUERY.with(|active| *active.borrow_mut() = None);
+
+        self.pending.push(PendingTiming {
+            name: name.to_string(),
+            cpu_time,
+            query,
+        });
+
+        result
+    }
+
+    /// Finish the current frame
+    pub fn end_frame(&mut self) {
+        self.stats.cpu_time = (time::precise_time_ns() - self.frame_start) as f32 / 1_000_000.0;
+    }
+
+    /// Record how much memory textures use, by the node they belong to
+    pub fn set_texture_memory(&mut self, usage: &HashMap<String, u64>) {
+        self.stats.texture_memory = usage.values().sum();
+        for node in &mut self.stats.nodes {
+            node.texture_memory = usage.get(&node.name).cloned().unwrap_or(0);
+        }
+    }
+
+    /// Get the stats for the last completed frame
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+}
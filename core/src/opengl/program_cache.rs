@@ -0,0 +1,182 @@
+//! Keeps linked shader programs on disk as the driver's own binaries (`glGetProgramBinary`), so
+//! graphs that ran before start and reload without compiling their shaders again. Programs are
+//! looked up by a hash of their preprocessed sources and the driver, since binaries only load on
+//! the driver that made them.
+
+use anyhow::{format_err, Error};
+use glium::{
+    backend::Facade,
+    program::{Binary, ProgramCreationInput},
+    Program,
+};
+use log::debug;
+use sha1::Sha1;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::SystemTime,
+};
+
+/// Most programs kept in the cache, after which the least recently written ones are removed
+const MAX_ENTRIES: usize = 256;
+
+/// Returns the directory the cache is kept in, under the user's cache directory
+fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("yotredash").join("programs"))
+}
+
+/// Hashes the sources of each stage of a program (`None` for stages it doesn't have) along with
+/// a description of the driver
+fn hash(driver: &[&str], sources: &[Option<&str>]) -> String {
+    let mut hasher = Sha1::new();
+    for part in driver {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\0");
+    }
+    for source in sources {
+        match *source {
+            Some(source) => {
+                hasher.update(b"+");
+                hasher.update(source.as_bytes());
+            }
+            None => hasher.update(b"-"),
+        }
+        hasher.update(b"\0");
+    }
+    hasher.digest().to_string()
+}
+
+/// Returns the key a program is cached under
+pub fn key(facade: &dyn Facade, sources: &[Option<&str>]) -> String {
+    let context = facade.get_context();
+    hash(
+        &[
+            context.get_opengl_vendor_string(),
+            context.get_opengl_renderer_string(),
+            context.get_opengl_version_string(),
+        ],
+        sources,
+    )
+}
+
+/// Encodes a binary for a cache file: its format as 4 little-endian bytes, then its contents
+fn encode(binary: &Binary) -> Vec<u8> {
+    let mut data = binary.format.to_le_bytes().to_vec();
+    data.extend_from_slice(&binary.content);
+    data
+}
+
+/// Decodes the contents of a cache file
+fn decode(data: &[u8]) -> Option<Binary> {
+    if data.len() <= 4 {
+        return None;
+    }
+    let mut format = [0; 4];
+    format.copy_from_slice(&data[..4]);
+    Some(Binary {
+        format: u32::from_le_bytes(format),
+        content: data[4..].to_vec(),
+    })
+}
+
+/// Loads a program from the cache. Binaries the driver rejects, like after it was updated without
+/// its version string changing, are removed.
+pub fn load(facade: &dyn Facade, key: &str) -> Option<Program> {
+    let path = cache_dir()?.join(key);
+    let binary = decode(&fs::read(&path).ok()?)?;
+    let input = ProgramCreationInput::Binary {
+        data: binary,
+        outputs_srgb: true,
+        uses_point_size: false,
+    };
+    match Program::new(facade, input) {
+        Ok(program) => {
+            debug!("Loaded cached program {}", key);
+            Some(program)
+        }
+        Err(e) => {
+            debug!("Could not load cached program {}: {}", key, e);
+            let _ = fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Saves a program to the cache, if the driver can give us its binary. Failing to is only logged,
+/// since the program works all the same.
+pub fn store(program: &Program, key: &str) {
+    if let Err(e) = try_store(program, key) {
+        debug!("Could not cache program {}: {}", key, e);
+    }
+}
+
+fn try_store(program: &Program, key: &str) -> Result<(), Error> {
+    let dir = cache_dir().ok_or_else(|| format_err!("No cache directory"))?;
+    let binary = program.get_binary().map_err(|e| format_err!("{:?}", e))?;
+    fs::create_dir_all(&dir)?;
+
+    // Written to a temporary file first, so another instance never reads half a binary
+    let temporary = dir.join(format!("{}.{}.tmp", key, process::id()));
+    fs::write(&temporary, encode(&binary))?;
+    fs::rename(&temporary, dir.join(key))?;
+
+    prune(&dir)
+}
+
+/// Removes the oldest programs while there are more than `MAX_ENTRIES`
+fn prune(dir: &Path) -> Result<(), Error> {
+    let mut entries: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if entries.len() > MAX_ENTRIES {
+        entries.sort();
+        for (_, path) in &entries[..entries.len() - MAX_ENTRIES] {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, hash};
+    use glium::program::Binary;
+
+    #[test]
+    fn keys_depend_on_sources_and_driver() {
+        let driver = ["vendor", "renderer", "4.6"];
+        let key = hash(&driver, &[Some("vertex"), None, Some("fragment")]);
+        assert_eq!(
+            key,
+            hash(&driver, &[Some("vertex"), None, Some("fragment")])
+        );
+        assert_ne!(
+            key,
+            hash(&driver, &[Some("vertex"), Some(""), Some("fragment")])
+        );
+        assert_ne!(key, hash(&driver, &[Some("vertexfragment"), None, None]));
+        assert_ne!(
+            key,
+            hash(
+                &["vendor", "renderer", "4.5"],
+                &[Some("vertex"), None, Some("fragment")]
+            )
+        );
+    }
+
+    #[test]
+    fn binaries_round_trip() {
+        let binary = Binary {
+            format: 0x8741,
+            content: vec![1, 2, 3],
+        };
+        let decoded = decode(&encode(&binary)).unwrap();
+        assert_eq!((decoded.format, decoded.content), (0x8741, vec![1, 2, 3]));
+        assert!(decode(&[1, 2, 3, 4]).is_none());
+    }
+}
@@ -0,0 +1,104 @@
+//! A quad covering the whole target, which the built-in nodes and overlays draw their fragment
+//! shaders over
+
+use anyhow::Error;
+use glium::{
+    backend::Facade,
+    implement_vertex,
+    index::{NoIndices, PrimitiveType},
+    program::ProgramCreationInput,
+    uniforms::Uniforms,
+    DrawParameters, Program, Surface, VertexBuffer,
+};
+
+/// Implementation of the vertex attributes for the vertex buffer
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    /// Position of the vertex in 2D space
+    position: [f32; 2],
+}
+implement_vertex!(Vertex, position);
+
+/// Two triangles covering the target
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const VERTICES: [Vertex; 6] = [
+    Vertex { position: [-1.0, -1.0] },
+    Vertex { position: [ 1.0, -1.0] },
+    Vertex { position: [ 1.0,  1.0] },
+    Vertex { position: [-1.0, -1.0] },
+    Vertex { position: [ 1.0,  1.0] },
+    Vertex { position: [-1.0,  1.0] },
+];
+
+/// Vertex shader which passes the quad through as it is, for fragment shaders that work from
+/// `gl_FragCoord`
+pub const VERTEX: &str = "
+    #version 140
+
+    in vec2 position;
+
+    void main() {
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+";
+
+/// Compiles a program that runs a fragment shader over the quad
+pub fn program(facade: &dyn Facade, fragment: &str) -> Result<Program, Error> {
+    program_with_vertex(facade, VERTEX, fragment)
+}
+
+/// Compiles a program from a fragment shader and a vertex shader of its own, which gets the
+/// quad's corners as `position`
+pub fn program_with_vertex(
+    facade: &dyn Facade,
+    vertex: &str,
+    fragment: &str,
+) -> Result<Program, Error> {
+    let input = ProgramCreationInput::SourceCode {
+        vertex_shader: vertex,
+        tessellation_control_shader: None,
+        tessellation_evaluation_shader: None,
+        geometry_shader: None,
+        fragment_shader: fragment,
+        transform_feedback_varyings: None,
+        outputs_srgb: true,
+        uses_point_size: false,
+    };
+    Ok(Program::new(facade, input)?)
+}
+
+/// The buffers needed to draw the quad
+pub struct Quad {
+    /// Vertex buffer for the shader
+    vertex_buffer: VertexBuffer<Vertex>,
+    /// Index buffer for the shader
+    index_buffer: NoIndices,
+}
+
+impl Quad {
+    /// Create a new instance
+    pub fn new(facade: &dyn Facade) -> Result<Self, Error> {
+        Ok(Self {
+            vertex_buffer: VertexBuffer::new(facade, &VERTICES)?,
+            index_buffer: NoIndices(PrimitiveType::TrianglesList),
+        })
+    }
+
+    /// Draw the quad to a surface with a program
+    pub fn draw<S: Surface, U: Uniforms>(
+        &self,
+        surface: &mut S,
+        program: &Program,
+        uniforms: &U,
+        parameters: &DrawParameters<'_>,
+    ) -> Result<(), Error> {
+        surface.draw(
+            &self.vertex_buffer,
+            &self.index_buffer,
+            program,
+            uniforms,
+            parameters,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1725 @@
+//! An implementation of `Renderer` using OpenGL
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use glium::{
+    backend::{glutin::Display, Facade},
+    framebuffer::DefaultFramebuffer,
+    glutin::{ContextBuilder, WindowBuilder},
+    texture::{MipmapsOption, Texture1d, Texture2d, UncompressedFloatFormat},
+    uniforms::MagnifySamplerFilter,
+    BlitTarget, Rect, Surface,
+};
+use imgui::DrawData;
+use indexmap::IndexMap;
+use log::{debug, error, info, warn};
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Instant,
+};
+use winit::EventsLoop;
+
+use super::{
+    backdrop::Backdrop,
+    gui::GuiRenderer,
+    headless,
+    nodes::*,
+    profiler::Profiler,
+    resources, target,
+    text::{TextLayout, TextRenderer},
+};
+use crate::{
+    capture::{self, CaptureMetadata},
+    config::{
+        capture::CaptureConfig,
+        nodes::{
+            render_order, InputType, Node as ConfigNode, NodeConfig, NodeConnection, NodeParameter,
+            TextAlign,
+        },
+        sandbox,
+        validation::find_cycle,
+        Config, PresentMode,
+    },
+    event::{RendererEvent, TimeEvent},
+    gui::FontTexture,
+    monitor, plugin,
+    renderer::{load_icon, DebugRenderer, Frame, FrameStats, Renderer},
+    util::{format_error, Clock},
+};
+
+type NodeMap = IndexMap<String, NodeType>;
+type NodeConfigMap = IndexMap<String, NodeConfig>;
+
+/// An implementation of a `Renderer` which uses OpenGL
+pub struct OpenGLRenderer {
+    /// The facade it uses to render
+    facade: Rc<dyn Facade>,
+    /// Maps names to nodes
+    nodes: NodeMap,
+    /// Node configurations for mapping outputs to inputs
+    node_configs: NodeConfigMap,
+    /// Order to render nodes in
+    order: Vec<String>,
+    /// Receiver for events
+    receiver: Receiver<RendererEvent>,
+    /// Sender for pointer events
+    senders: Vec<Sender<RendererEvent>>,
+    /// Measures how long each node takes to render
+    profiler: Profiler,
+    /// Keeps the same time as the info nodes, for capture metadata
+    clock: Clock,
+    /// How to capture images
+    capture: CaptureConfig,
+    /// Nodes rendered whatever switch nodes and timelines select, which `roots` describes
+    roots: Vec<String>,
+    /// The input each switch node selected last frame, which decides which branches are rendered
+    selections: HashMap<String, usize>,
+    /// How long a node may take on the GPU in one frame before the watchdog disables it
+    /// (milliseconds)
+    time_budget: Option<f32>,
+    /// Nodes the watchdog disabled, with the outputs they had when it did
+    disabled: HashMap<String, HashMap<String, NodeOutput>>,
+    /// The last error the renderer recovered from, like a node the watchdog disabled or a shader
+    /// that didn't build again, to show over the frame
+    recovered_error: Option<Error>,
+    /// Without a window, frames are rendered into this texture at the configured size
+    headless: Option<Rc<Texture2d>>,
+    /// The outputs of each node last time it was rendered, reused while nothing about it changes
+    cache: HashMap<String, HashMap<String, NodeOutput>>,
+    /// The size of the target the cached outputs were rendered at
+    cache_dimensions: (u32, u32),
+    /// The multiple of the window's size the graph renders at
+    supersample: u32,
+    /// Texture memory the graph may use before idle image nodes are evicted (bytes)
+    texture_budget: Option<u64>,
+    /// The number of the frame being rendered
+    frame: u64,
+    /// The frame each image node was last rendered in
+    last_used: HashMap<String, u64>,
+    /// Whether we've warned that evicting images didn't bring memory under the budget
+    over_budget: bool,
+    /// Shader nodes whose files changed, whose programs are built again one a frame
+    stale_programs: VecDeque<String>,
+}
+
+/// Fails if the nodes depend on each other in a cycle, since the render order can't be determined
+/// with one
+fn check_cycles(nodes: &IndexMap<String, ConfigNode>) -> Result<(), Error> {
+    if let Some(cycle) = find_cycle(nodes) {
+        bail!(
+            "Dependency cycle detected: `{}`\n\
+             A node can't depend on its own output. To use a node's output from the previous frame, \
+             insert a feedback node somewhere in the cycle.",
+            cycle.join("` -> `")
+        );
+    }
+    Ok(())
+}
+
+/// Returns the nodes rendered whatever switch nodes and timelines select: the output, nodes with a
+/// priority, and nodes nothing reads from, which are rendered for what they do on their own, like
+/// writing to a file. Without switches or timelines nothing is left out, so every node is a root.
+fn roots(nodes: &IndexMap<String, ConfigNode>) -> Vec<String> {
+    let selective = nodes.values().any(|node| match node.config {
+        NodeConfig::Switch(_) | NodeConfig::Timeline(_) => true,
+        _ => false,
+    });
+    let read: HashSet<&str> = nodes
+        .values()
+        .flat_map(|node| node.config.connections())
+        .map(|(_, connection, _)| connection.node.as_str())
+        .collect();
+
+    nodes
+        .iter()
+        .filter(|(name, node)| match node.config {
+            _ if !selective => true,
+            NodeConfig::Output(_) => true,
+            _ => node.priority.is_some() || !read.contains(name.as_str()),
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+fn init_nodes(
+    config: &Config,
+    facade: &Rc<dyn Facade>,
+) -> Result<
+    (
+        NodeMap,
+        NodeConfigMap,
+        Vec<String>,
+        Vec<Sender<RendererEvent>>,
+    ),
+    Error,
+> {
+    let mut senders = Vec::new();
+
+    // Check for cycles first, so nothing is loaded for a graph that can't be rendered
+    check_cycles(&config.nodes)?;
+
+    let plugin_paths: Vec<PathBuf> = config
+        .plugins
+        .iter()
+        .map(|path| config.path_to(path))
+        .collect();
+    let plugin_types = plugin::load(&plugin_paths)?;
+
+    let mut nodes: NodeMap = IndexMap::new();
+    let mut output_node = "";
+    // The uniforms of shader nodes with `auto_bind`, by the name of the node
+    let mut bindings = Vec::new();
+
+    for (name, node) in &config.nodes {
+        let _owner = resources::owner(name);
+        match node.config {
+            NodeConfig::Info => {
+                let (sender, receiver) = mpsc::channel();
+                senders.push(sender);
+
+                let (width, height) = facade.get_context().get_framebuffer_dimensions();
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Info(InfoNode::new(
+                        receiver,
+                        [width as f32, height as f32],
+                        &config.accessibility,
+                        config.present_mode().supported(),
+                    )),
+                );
+            }
+
+            NodeConfig::Output(_) => {
+                nodes.insert(name.to_string(), NodeType::Output(OutputNode::new(facade)?));
+
+                ensure!(output_node.is_empty(), "There can only be one output node");
+                output_node = name;
+            }
+
+            NodeConfig::Image(ref image_config) => {
+                let mut image_config = image_config.clone();
+                image_config.path = config.path_to(&image_config.path);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Image(ImageNode::new(facade, image_config)?),
+                );
+            }
+
+            NodeConfig::Shader(ref shader_config) => {
+                // Replace the paths with absolute paths
+                let mut shader_config = shader_config.clone();
+                shader_config.vertex = shader_config.vertex.map(|path| config.path_to(&path));
+                shader_config.fragment = shader_config.fragment.map(|path| config.path_to(&path));
+                shader_config.fragment_gles = shader_config
+                    .fragment_gles
+                    .map(|path| config.path_to(&path));
+                shader_config.fragment_fallback = shader_config
+                    .fragment_fallback
+                    .map(|path| config.path_to(&path));
+                shader_config.geometry = shader_config.geometry.map(|path| config.path_to(&path));
+                shader_config.tess_control =
+                    shader_config.tess_control.map(|path| config.path_to(&path));
+                shader_config.tess_eval = shader_config.tess_eval.map(|path| config.path_to(&path));
+
+                let auto_bind = shader_config.auto_bind;
+                let shader = ShaderNode::new(facade, shader_config, config.shader_cache)?;
+                if auto_bind {
+                    bindings.push((name.clone(), shader.auto_bind(&config.nodes, name)?));
+                }
+                nodes.insert(name.to_string(), NodeType::Shader(shader));
+            }
+
+            NodeConfig::Blend(ref blend_config) => {
+                let (sender, receiver) = mpsc::channel();
+                senders.push(sender);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Blend(BlendNode::new(facade, blend_config, receiver)?),
+                );
+            }
+
+            // TODO: Color in a better format
+            NodeConfig::Text(ref text_config) => {
+                let (sender, receiver) = mpsc::channel();
+                senders.push(sender);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Text(TextNode::new(facade, text_config.clone(), receiver)?),
+                );
+            }
+
+            NodeConfig::Fps(ref fps_config) => {
+                let (sender, receiver) = mpsc::channel();
+                senders.push(sender);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Fps(FpsNode::new(facade, fps_config.clone(), receiver)?),
+                );
+            }
+
+            NodeConfig::Audio(ref audio_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Audio(AudioNode::new(facade, audio_config)?),
+                );
+            }
+
+            NodeConfig::Constant(ref constant_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Constant(ConstantNode::new(constant_config)),
+                );
+            }
+
+            NodeConfig::Camera(ref camera_config) => {
+                let (sender, receiver) = mpsc::channel();
+                senders.push(sender);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Camera(CameraNode::new(facade, camera_config, receiver)),
+                );
+            }
+
+            NodeConfig::Random(ref random_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Random(RandomNode::new(random_config)?),
+                );
+            }
+
+            NodeConfig::Keyframes(ref track_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Keyframes(KeyframesNode::new(track_config)?),
+                );
+            }
+
+            NodeConfig::Mapping(ref mapping_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Mapping(MappingNode::new(&mapping_config.mapping)),
+                );
+            }
+
+            NodeConfig::File(ref file_config) => {
+                let mut file_config = file_config.clone();
+                file_config.path = config.path_to(&file_config.path);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::File(FileNode::new(file_config)?),
+                );
+            }
+
+            NodeConfig::Http(ref http_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Http(HttpNode::new(http_config.clone())?),
+                );
+            }
+
+            #[cfg(feature = "link")]
+            NodeConfig::Link(ref link_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Link(LinkNode::new(link_config)?),
+                );
+            }
+
+            #[cfg(not(feature = "link"))]
+            NodeConfig::Link(_) => bail!(
+                "Node `{}` needs Ableton Link, which this build doesn't support (build with the \
+                 `link` feature)",
+                name
+            ),
+
+            NodeConfig::Clock(ref clock_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Clock(ClockNode::new(clock_config.clone())?),
+                );
+            }
+
+            NodeConfig::Envelope(ref envelope_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Envelope(EnvelopeNode::new(facade, envelope_config)?),
+                );
+            }
+
+            NodeConfig::Readback(_) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Readback(ReadbackNode::new(facade, name)),
+                );
+            }
+
+            NodeConfig::Transform(ref transform_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Transform(TransformNode::new(facade, transform_config.clone())?),
+                );
+            }
+
+            NodeConfig::Blur(ref blur_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Blur(BlurNode::new(facade, blur_config.clone())?),
+                );
+            }
+
+            NodeConfig::Bloom(ref bloom_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Bloom(BloomNode::new(facade, bloom_config.clone())?),
+                );
+            }
+
+            NodeConfig::ColorGrade(ref color_grade_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::ColorGrade(ColorGradeNode::new(facade, color_grade_config.clone())?),
+                );
+            }
+
+            NodeConfig::Lut(ref lut_config) => {
+                let mut lut_config = lut_config.clone();
+                lut_config.path = config.path_to(&lut_config.path);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Lut(LutNode::new(facade, lut_config)?),
+                );
+            }
+
+            NodeConfig::Transition(ref transition_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Transition(TransitionNode::new(facade, transition_config.clone())?),
+                );
+            }
+
+            NodeConfig::Timeline(_) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Timeline(TimelineNode::new(facade)?),
+                );
+            }
+
+            NodeConfig::Switch(_) => {
+                nodes.insert(name.to_string(), NodeType::Switch(SwitchNode::new()));
+            }
+
+            NodeConfig::Feedback(ref feedback_config) => {
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Feedback(FeedbackNode::new(facade, feedback_config.clone())?),
+                );
+            }
+
+            NodeConfig::Plugin(ref plugin_config) => {
+                let plugin_type = plugin_types.get(&plugin_config.plugin).ok_or_else(|| {
+                    format_err!(
+                        "No plugin provides the node type `{}`",
+                        plugin_config.plugin
+                    )
+                })?;
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Plugin(PluginNode::new(plugin_type, plugin_config.clone())?),
+                );
+            }
+
+            NodeConfig::Script(ref script_config) => {
+                let mut script_config = script_config.clone();
+                script_config.path = config.path_to(&script_config.path);
+
+                nodes.insert(
+                    name.to_string(),
+                    NodeType::Script(ScriptNode::new(script_config)?),
+                );
+            }
+        }
+    }
+
+    ensure!(!output_node.is_empty(), "No output node specified");
+
+    let mut node_configs = config.nodes.clone();
+    if !bindings.is_empty() {
+        for (name, connections) in bindings {
+            if let NodeConfig::Shader(ref mut shader_config) = node_configs[&name].config {
+                shader_config.uniforms.extend(connections);
+            }
+        }
+        // Bound uniforms are connections like any other, so they can make cycles too
+        check_cycles(&node_configs)?;
+    }
+
+    let order = render_order(&node_configs, output_node);
+    debug!("Render order: {}", order.join(", "));
+
+    let dangling_nodes: Vec<String> = nodes
+        .keys()
+        .filter(|name| !order.contains(name))
+        .cloned()
+        .collect();
+    if dangling_nodes.len() == 1 {
+        warn!("Dangling node: `{}`", dangling_nodes[0]);
+    } else if dangling_nodes.len() > 1 {
+        warn!("Dangling nodes: `{}`", dangling_nodes.join(", "));
+    }
+
+    let node_configs = node_configs
+        .into_iter()
+        .map(|(name, node)| (name, node.config))
+        .collect();
+    Ok((nodes, node_configs, order, senders))
+}
+
+fn map_node_io(
+    config: &NodeConfig,
+    outputs: &HashMap<String, HashMap<String, NodeOutput>>,
+    time: f32,
+) -> Result<NodeInputs, Error> {
+    let get_node_output = |connection: &NodeConnection| -> Result<_, Error> {
+        Ok(outputs
+            .get(&connection.node)
+            .ok_or_else(|| format_err!("No such node: `{}`", connection.node))?
+            .get(&connection.output)
+            .ok_or_else(|| {
+                format_err!(
+                    "No such output on node `{}`: `{}`",
+                    connection.node,
+                    connection.output
+                )
+            })?)
+    };
+    let get_texture = |connection: &NodeConnection, name: &str| -> Result<_, Error> {
+        match *get_node_output(connection)? {
+            NodeOutput::Texture2d(ref texture) => Ok(Rc::clone(texture)),
+            _ => bail!("Wrong input type for `{}`", name),
+        }
+    };
+    let get_float = |parameter: &NodeParameter<f32>, name: &str| -> Result<_, Error> {
+        match *parameter {
+            NodeParameter::NodeConnection(ref connection) => match *get_node_output(connection)? {
+                NodeOutput::Float(value) => Ok(Some(value)),
+                _ => bail!("Wrong input type for `{}`", name),
+            },
+            NodeParameter::Static(_) => Ok(None),
+        }
+    };
+
+    Ok(match *config {
+        NodeConfig::Info => NodeInputs::Info,
+
+        NodeConfig::Output(ref output_config) => match *get_node_output(&output_config.texture)? {
+            NodeOutput::Texture2d(ref texture) => NodeInputs::Output {
+                texture: Rc::clone(texture),
+            },
+            _ => bail!("Wrong input type for `texture`"),
+        },
+
+        NodeConfig::Image(_) => NodeInputs::Image,
+
+        NodeConfig::Shader(ref shader_config) => {
+            let mut uniforms = HashMap::new();
+            for connection in &shader_config.uniforms {
+                uniforms.insert(connection.clone(), get_node_output(connection)?.clone());
+            }
+            NodeInputs::Shader { uniforms }
+        }
+
+        NodeConfig::Blend(ref blend_config) => {
+            let mut textures = Vec::new();
+            for connection in &blend_config.textures {
+                match *get_node_output(connection)? {
+                    NodeOutput::Texture2d(ref texture) => textures.push(Rc::clone(texture)),
+                    _ => bail!("Wrong input type for `uniforms`"),
+                };
+            }
+            NodeInputs::Blend { textures }
+        }
+
+        NodeConfig::Text(ref text_config) => {
+            let text = match text_config.text {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Text(ref text) => Some(text.to_string()),
+                        _ => bail!("Wrong input type for `text`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let position = match text_config.position {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float2(ref position) => Some(*position),
+                        _ => bail!("Wrong input type for `position`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let color = match text_config.color {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Color(ref color) => Some(*color),
+                        _ => bail!("Wrong input type for `position`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+
+            NodeInputs::Text {
+                text,
+                position,
+                color,
+                time,
+            }
+        }
+
+        NodeConfig::Fps(ref fps_config) => {
+            let position = match fps_config.position {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float2(ref position) => Some(*position),
+                        _ => bail!("Wrong input type for `position`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let color = match fps_config.color {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Color(ref color) => Some(*color),
+                        _ => bail!("Wrong input type for `position`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+
+            NodeInputs::Fps { position, color }
+        }
+
+        NodeConfig::Audio(_) => NodeInputs::Audio,
+
+        NodeConfig::Constant(_) => NodeInputs::Constant,
+        NodeConfig::Camera(_) => NodeInputs::Camera,
+        NodeConfig::Random(_) => NodeInputs::Random { time },
+        NodeConfig::Keyframes(_) => NodeInputs::Keyframes { time },
+        NodeConfig::Mapping(ref mapping_config) => match *get_node_output(&mapping_config.input)? {
+            NodeOutput::Float(value) => NodeInputs::Mapping { value, time },
+            _ => bail!("Wrong input type for `input`"),
+        },
+        NodeConfig::File(_) => NodeInputs::File,
+        NodeConfig::Http(_) => NodeInputs::Http,
+        NodeConfig::Link(_) => NodeInputs::Link,
+        NodeConfig::Clock(_) => NodeInputs::Clock,
+
+        NodeConfig::Envelope(ref envelope_config) => {
+            match *get_node_output(&envelope_config.texture)? {
+                NodeOutput::Texture2d(ref texture) => NodeInputs::Envelope {
+                    texture: Rc::clone(texture),
+                },
+                _ => bail!("Wrong input type for `texture`"),
+            }
+        }
+
+        NodeConfig::Readback(ref readback_config) => NodeInputs::Readback {
+            texture: get_texture(&readback_config.texture, "texture")?,
+        },
+
+        NodeConfig::Transform(ref transform_config) => {
+            let texture = match *get_node_output(&transform_config.texture)? {
+                NodeOutput::Texture2d(ref texture) => Rc::clone(texture),
+                _ => bail!("Wrong input type for `texture`"),
+            };
+            let translate = match transform_config.translate {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float2(ref translate) => Some(*translate),
+                        _ => bail!("Wrong input type for `translate`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let rotate = match transform_config.rotate {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float(ref rotate) => Some(*rotate),
+                        _ => bail!("Wrong input type for `rotate`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let scale = match transform_config.scale {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float2(ref scale) => Some(*scale),
+                        _ => bail!("Wrong input type for `scale`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+            let crop = match transform_config.crop {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float4(ref crop) => Some(*crop),
+                        _ => bail!("Wrong input type for `crop`"),
+                    }
+                }
+                NodeParameter::Static(_) => None,
+            };
+
+            NodeInputs::Transform {
+                texture,
+                translate,
+                rotate,
+                scale,
+                crop,
+            }
+        }
+
+        NodeConfig::Blur(ref blur_config) => NodeInputs::Blur {
+            texture: get_texture(&blur_config.texture, "texture")?,
+            radius: get_float(&blur_config.radius, "radius")?,
+        },
+
+        NodeConfig::Bloom(ref bloom_config) => NodeInputs::Bloom {
+            texture: get_texture(&bloom_config.texture, "texture")?,
+            threshold: get_float(&bloom_config.threshold, "threshold")?,
+            intensity: get_float(&bloom_config.intensity, "intensity")?,
+        },
+
+        NodeConfig::ColorGrade(ref color_grade_config) => NodeInputs::ColorGrade {
+            texture: get_texture(&color_grade_config.texture, "texture")?,
+            lut: match color_grade_config.lut {
+                Some(ref lut) => Some(get_texture(lut, "lut")?),
+                None => None,
+            },
+            exposure: get_float(&color_grade_config.exposure, "exposure")?,
+            contrast: get_float(&color_grade_config.contrast, "contrast")?,
+            saturation: get_float(&color_grade_config.saturation, "saturation")?,
+        },
+
+        NodeConfig::Lut(ref lut_config) => NodeInputs::Lut {
+            texture: get_texture(&lut_config.texture, "texture")?,
+            amount: get_float(&lut_config.amount, "amount")?,
+        },
+
+        NodeConfig::Switch(ref switch_config) => {
+            let index = match switch_config.index {
+                NodeParameter::NodeConnection(ref connection) => {
+                    match *get_node_output(connection)? {
+                        NodeOutput::Float(index) => index,
+                        _ => bail!("Wrong input type for `index`"),
+                    }
+                }
+                NodeParameter::Static(index) => index,
+            };
+            let count = switch_config.textures.len();
+            ensure!(count > 0, "The switch node has no textures");
+            let index = switch::switch_index(index, count);
+
+            // Unselected branches aren't rendered, so a newly selected branch is only available
+            // from the next frame on. Until then, keep passing through the one that was rendered.
+            let rendered = |i: usize| {
+                let connection = &switch_config.textures[i];
+                outputs
+                    .get(&connection.node)
+                    .and_then(|outputs| outputs.get(&connection.output))
+            };
+            let output = rendered(index)
+                .or_else(|| (0..count).filter_map(&rendered).next())
+                .ok_or_else(|| format_err!("None of the switch node's textures were rendered"))?;
+            match *output {
+                NodeOutput::Texture2d(ref texture) => NodeInputs::Switch {
+                    texture: Rc::clone(texture),
+                    index,
+                },
+                _ => bail!("Wrong input type for `textures`"),
+            }
+        }
+
+        NodeConfig::Transition(ref transition_config) => NodeInputs::Transition {
+            from: get_texture(&transition_config.from, "from")?,
+            to: get_texture(&transition_config.to, "to")?,
+            mask: match transition_config.mask {
+                Some(ref mask) => Some(get_texture(mask, "mask")?),
+                None => None,
+            },
+            progress: get_float(&transition_config.progress, "progress")?,
+        },
+
+        NodeConfig::Timeline(ref timeline_config) => {
+            let position = timeline_config.position(time);
+            let scene = |index: usize| {
+                timeline_config.scenes[index]
+                    .texture
+                    .as_ref()
+                    .ok_or_else(|| format_err!("Scene {} has no texture", index))
+                    .and_then(|texture| get_texture(texture, "scenes"))
+            };
+            NodeInputs::Timeline {
+                from: scene(position.scene)?,
+                to: match position.next {
+                    Some(next) => Some(scene(next)?),
+                    None => None,
+                },
+                mix: position.mix,
+            }
+        }
+
+        NodeConfig::Feedback(_) => NodeInputs::Feedback,
+
+        NodeConfig::Plugin(ref plugin_config) => {
+            let mut inputs = Vec::new();
+            for connection in &plugin_config.inputs {
+                inputs.push((
+                    connection.name.clone(),
+                    get_node_output(connection)?.clone(),
+                ));
+            }
+            NodeInputs::Plugin { inputs }
+        }
+
+        NodeConfig::Script(ref script_config) => {
+            let mut inputs = Vec::new();
+            for connection in &script_config.inputs {
+                inputs.push((
+                    connection.name.clone(),
+                    get_node_output(connection)?.clone(),
+                ));
+            }
+            NodeInputs::Script { inputs }
+        }
+    })
+}
+
+impl OpenGLRenderer {
+    /// Create a new instance on an existing Facade
+    pub fn new(
+        config: &Config,
+        facade: &Rc<dyn Facade>,
+        receiver: Receiver<RendererEvent>,
+    ) -> Result<Self, Error> {
+        debug!(
+            "OpenGL backend: {}",
+            facade.get_context().get_opengl_version_string()
+        );
+
+        ensure!(
+            config.supersample >= 1,
+            "Supersample factor must be at least 1"
+        );
+        ensure!(
+            config.gpu_watchdog.map_or(true, |limit| limit > 0.0),
+            "The GPU watchdog limit must be more than 0"
+        );
+        let (nodes, node_configs, order, senders) = init_nodes(config, facade)?;
+
+        let mut clock = Clock::new();
+        clock.set_max_speed(config.accessibility.max_speed());
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            nodes,
+            node_configs,
+            order,
+            receiver,
+            senders,
+            profiler: Profiler::new(facade),
+            clock,
+            capture: config.capture.clone(),
+            supersample: config.supersample,
+            texture_budget: config
+                .texture_budget
+                .map(|megabytes| megabytes * 1024 * 1024),
+            frame: 0,
+            last_used: HashMap::new(),
+            over_budget: false,
+            stale_programs: VecDeque::new(),
+            roots: roots(&config.nodes),
+            selections: config
+                .nodes
+                .iter()
+                .filter_map(|(name, node)| match node.config {
+                    NodeConfig::Switch(ref switch_config) => {
+                        let index = match switch_config.index {
+                            NodeParameter::Static(index) => index,
+                            NodeParameter::NodeConnection(_) => 0.0,
+                        };
+                        Some((
+                            name.clone(),
+                            switch::switch_index(index, switch_config.textures.len()),
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            time_budget: if config.trusted {
+                config.gpu_watchdog
+            } else {
+                config
+                    .gpu_watchdog
+                    .map(|limit| limit.min(sandbox::NODE_TIME_BUDGET))
+            },
+            disabled: HashMap::new(),
+            recovered_error: None,
+            headless: if config.headless {
+                Some(resources::track(Texture2d::empty_with_mipmaps(
+                    &**facade,
+                    MipmapsOption::NoMipmap,
+                    config.width,
+                    config.height,
+                )?))
+            } else {
+                None
+            },
+            cache: HashMap::new(),
+            cache_dimensions: (0, 0),
+        })
+    }
+
+    /// Returns outputs of the types a node declares, with textures cleared to transparent and
+    /// everything else zero
+    fn blank_outputs(&self, name: &str) -> Result<HashMap<String, NodeOutput>, Error> {
+        let (width, height) = self.frame_dimensions();
+        let mut outputs = HashMap::new();
+        let types = self
+            .node_configs
+            .get(name)
+            .map(NodeConfig::outputs)
+            .unwrap_or_default();
+        for (output, type_) in types {
+            let value = match type_ {
+                InputType::Color => NodeOutput::Color(Default::default()),
+                InputType::Any | InputType::Float => NodeOutput::Float(Default::default()),
+                InputType::Float2 => NodeOutput::Float2(Default::default()),
+                InputType::Float4 => NodeOutput::Float4(Default::default()),
+                InputType::Mat4 => NodeOutput::Mat4(Default::default()),
+                InputType::FloatArray => NodeOutput::FloatArray(Default::default()),
+                InputType::Text => NodeOutput::Text(Default::default()),
+                InputType::Texture2d => {
+                    let texture = Texture2d::empty(&*self.facade, width, height)?;
+                    texture.as_surface().clear_color(0.0, 0.0, 0.0, 0.0);
+                    NodeOutput::Texture2d(resources::track(texture))
+                }
+                InputType::Texture1d => {
+                    NodeOutput::Texture1d(resources::track(Texture1d::empty(&*self.facade, 0)?))
+                }
+            };
+            outputs.insert(output, value);
+        }
+        Ok(outputs)
+    }
+
+    /// Disables nodes that take too long on the GPU, before they make the whole system
+    /// unresponsive. They keep their last outputs, or blank ones if they never finished a frame,
+    /// so the rest of the graph carries on rendering. Timings lag a few frames behind, until the
+    /// GPU has finished with them, so this checks the newest frame it has.
+    fn check_watchdog(&mut self) -> Result<(), Error> {
+        let budget = match self.time_budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+
+        let over: Vec<(String, f32)> = self
+            .profiler
+            .stats()
+            .nodes
+            .iter()
+            .filter(|node| node.gpu_time > budget && !self.disabled.contains_key(&node.name))
+            .map(|node| (node.name.clone(), node.gpu_time))
+            .collect();
+        for (name, gpu_time) in over {
+            let error = format_err!(
+                "Node `{}` took {:.0}ms on the GPU, over the {:.0}ms watchdog limit, so it's \
+                 disabled until the graph is reloaded",
+                name,
+                gpu_time,
+                budget
+            );
+            error!("{}", format_error(&error));
+            let outputs = match self.cache.get(&name) {
+                Some(outputs) => outputs.clone(),
+                None => self.blank_outputs(&name)?,
+            };
+            self.disabled.insert(name, outputs);
+            self.recovered_error = Some(error);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the nodes which need rendering this frame, leaving out the branches that switch
+    /// nodes haven't selected and the scenes the timeline isn't showing
+    fn live_nodes(&self, time: f32) -> HashSet<String> {
+        let mut live = HashSet::new();
+        let mut stack: Vec<&str> = self.roots.iter().map(String::as_str).collect();
+        while let Some(name) = stack.pop() {
+            if !live.insert(name.to_string()) {
+                continue;
+            }
+            match self.node_configs.get(name) {
+                Some(&NodeConfig::Switch(ref switch_config)) => {
+                    if let NodeParameter::NodeConnection(ref connection) = switch_config.index {
+                        stack.push(&connection.node);
+                    }
+                    let selected = self.selections.get(name).cloned().unwrap_or(0);
+                    if let Some(connection) = switch_config.textures.get(selected) {
+                        stack.push(&connection.node);
+                    }
+                }
+                Some(&NodeConfig::Timeline(ref timeline_config)) => {
+                    let position = timeline_config.position(time);
+                    for index in Some(position.scene).into_iter().chain(position.next) {
+                        if let Some(ref texture) = timeline_config.scenes[index].texture {
+                            stack.push(&texture.node);
+                        }
+                    }
+                }
+                Some(config) => stack.extend(
+                    config
+                        .connections()
+                        .into_iter()
+                        .map(|(_, connection, _)| connection.node.as_str()),
+                ),
+                None => (),
+            }
+        }
+        live
+    }
+
+    /// Render every node in order
+    fn render_graph(&mut self) -> Result<(), Error> {
+        let mut outputs: HashMap<String, HashMap<String, NodeOutput>> = HashMap::new();
+        let time = self.clock.time();
+        let live = self.live_nodes(time);
+
+        let mut feedback_nodes = Vec::new();
+
+        // Everything is rendered again at a new size, including while supersampling
+        let dimensions = target::dimensions(&*self.facade);
+        if dimensions != self.cache_dimensions {
+            self.cache.clear();
+            self.cache_dimensions = dimensions;
+        }
+        // The nodes rendered this frame, whose outputs might be different from last frame's
+        let mut changed = HashSet::new();
+
+        for name in &self.order {
+            // Skip the branches that switch nodes haven't selected and hidden scenes. Their inputs
+            // could change while they're skipped, so they're rendered again when they come back.
+            if !live.contains(name) {
+                self.cache.remove(name);
+                continue;
+            }
+
+            ensure!(
+                self.node_configs.contains_key(name),
+                "No such node: `{}`",
+                name
+            );
+
+            // Disabled nodes are stuck on what they last rendered
+            if let Some(frozen) = self.disabled.get(name) {
+                outputs.insert(name.to_string(), frozen.clone());
+                continue;
+            }
+
+            let node = self.nodes.get_mut(name).unwrap();
+            let node_changed = node.changed();
+            let inputs_changed = self.node_configs[name]
+                .dependencies()
+                .iter()
+                .any(|dependency| changed.contains(*dependency));
+
+            match self.cache.get(name) {
+                Some(cached) if !node_changed && !inputs_changed => {
+                    outputs.insert(name.to_string(), cached.clone());
+                }
+                _ => {
+                    let inputs = map_node_io(&self.node_configs[name], &outputs, time)
+                        .context(format!("Error on node `{}`", name))?;
+                    let _owner = resources::owner(name);
+                    let node_outputs = self.profiler.profile(name, || node.render(&inputs))?;
+                    self.cache.insert(name.to_string(), node_outputs.clone());
+                    outputs.insert(name.to_string(), node_outputs);
+                    changed.insert(name.to_string());
+                }
+            }
+
+            match self.nodes[name] {
+                NodeType::Feedback(_) => feedback_nodes.push(name),
+                NodeType::Image(_) => {
+                    self.last_used.insert(name.to_string(), self.frame);
+                }
+                NodeType::Switch(_) => {
+                    if let Some(&NodeOutput::Float(index)) = outputs[name].get("index") {
+                        self.selections.insert(name.to_string(), index as usize);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Every feedback node writes its back buffers before any of them swap, so feedback nodes
+        // reading each other see the values from this frame's start. A capture renders the
+        // current frame again, so it leaves them as they are.
+        if !target::capturing() {
+            for &name in &feedback_nodes {
+                if let Some(&mut NodeType::Feedback(ref mut node)) = self.nodes.get_mut(name) {
+                    let _owner = resources::owner(name);
+                    node.update(&outputs)
+                        .context(format!("Error on node `{}`", name))?;
+                }
+            }
+            for &name in &feedback_nodes {
+                if let Some(&mut NodeType::Feedback(ref mut node)) = self.nodes.get_mut(name) {
+                    node.swap();
+                }
+            }
+        }
+
+        let usage = resources::usage();
+        self.profiler.set_texture_memory(&usage);
+        self.enforce_texture_budget(&usage);
+        self.frame += 1;
+
+        Ok(())
+    }
+
+    /// Evicts the textures of image nodes that weren't rendered this frame, least recently
+    /// rendered first, until memory use is back under the budget. Images that are showing can't be
+    /// evicted, so this warns if they alone are over it.
+    fn enforce_texture_budget(&mut self, usage: &HashMap<String, u64>) {
+        let budget = match self.texture_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let mut total: u64 = usage.values().sum();
+        if total > budget {
+            let current = self.frame;
+            let mut idle: Vec<(&String, u64)> = self
+                .last_used
+                .iter()
+                .filter(|&(name, &last)| last < current && usage.contains_key(name))
+                .map(|(name, &last)| (name, last))
+                .collect();
+            idle.sort_by_key(|&(_, last)| last);
+
+            for (name, _) in idle {
+                if total <= budget {
+                    break;
+                }
+                if let Some(&mut NodeType::Image(ref mut node)) = self.nodes.get_mut(name) {
+                    node.evict();
+                    total -= usage[name];
+                }
+            }
+        }
+
+        if total > budget && !self.over_budget {
+            warn!(
+                "Textures use {:.1} MB, over the {:.1} MB budget",
+                total as f32 / (1024.0 * 1024.0),
+                budget as f32 / (1024.0 * 1024.0)
+            );
+        }
+        self.over_budget = total > budget;
+    }
+
+    /// The size of the frames being rendered, which is the window's size unless we're headless
+    fn frame_dimensions(&self) -> (u32, u32) {
+        match self.headless {
+            Some(ref headless) => headless.dimensions(),
+            None => self.facade.get_context().get_framebuffer_dimensions(),
+        }
+    }
+
+    /// Read back the current frame as RGBA, from top to bottom, scaled to the given size
+    fn capture_frame(&self, width: u32, height: u32) -> Result<Vec<f32>, Error> {
+        let texture =
+            Texture2d::empty_with_mipmaps(&*self.facade, MipmapsOption::NoMipmap, width, height)?;
+
+        let (frame_width, frame_height) = self.frame_dimensions();
+        let source_rect = Rect {
+            left: 0,
+            bottom: 0,
+            width: frame_width,
+            height: frame_height,
+        };
+
+        let target_rect = BlitTarget {
+            left: 0,
+            bottom: height,
+            width: width as i32,
+            height: -(height as i32),
+        };
+
+        let filter = if (width, height) == (frame_width, frame_height) {
+            MagnifySamplerFilter::Nearest
+        } else {
+            MagnifySamplerFilter::Linear
+        };
+        match self.headless {
+            Some(ref headless) => texture.as_surface().blit_from_simple_framebuffer(
+                &headless.as_surface(),
+                &source_rect,
+                &target_rect,
+                filter,
+            ),
+            None => texture
+                .as_surface()
+                .blit_from_frame(&source_rect, &target_rect, filter),
+        }
+
+        let rows: Vec<Vec<(f32, f32, f32, f32)>> = texture.read();
+        Ok(capture::flatten(rows))
+    }
+
+    /// Render the graph again at a multiple of the window's resolution and downsample it, returning
+    /// RGBA from top to bottom
+    fn capture_supersampled(
+        &mut self,
+        width: u32,
+        height: u32,
+        factor: u32,
+    ) -> Result<Vec<f32>, Error> {
+        let texture = resources::track(Texture2d::empty_with_format(
+            &*self.facade,
+            UncompressedFloatFormat::F32F32F32F32,
+            MipmapsOption::NoMipmap,
+            width * factor,
+            height * factor,
+        )?);
+        target::render_capture(Rc::clone(&texture), || self.render_graph())?;
+
+        // OpenGL textures start at the bottom
+        let mut rows: Vec<Vec<(f32, f32, f32, f32)>> = texture.read();
+        rows.reverse();
+        Ok(capture::downsample(
+            &capture::flatten(rows),
+            width * factor,
+            height * factor,
+            factor,
+        ))
+    }
+
+    /// Capture the current frame to an image file
+    fn capture(&mut self, path: &Path, mut metadata: CaptureMetadata) -> Result<(), Error> {
+        let factor = self.capture.supersample;
+        let (width, height) = self.frame_dimensions();
+        let pixels = if factor > 1 {
+            self.capture_supersampled(width, height, factor)?
+        } else {
+            self.capture_frame(width, height)?
+        };
+
+        metadata.demo_time = self.clock.time();
+        capture::save(path, self.capture.format, &pixels, width, height, &metadata)
+            .with_context(|| format!("Could not capture to {}", path.display()))
+    }
+
+    /// Pause or resume the renderer's clock and every node's clock
+    fn set_clock(&mut self, event: TimeEvent) -> Result<(), Error> {
+        self.clock.handle(&event);
+        for sender in &self.senders {
+            sender.send(RendererEvent::Time(event.clone()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for OpenGLRenderer {
+    fn warm_up(&mut self, frames: u32) -> Result<(), Error> {
+        // Shaders are compiled, textures are uploaded, and the driver has seen all the pipeline
+        // state before the first frame is presented. The frames are never swapped, and time still
+        // starts at zero afterwards.
+        if frames == 0 {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        self.set_clock(TimeEvent::Pause)?;
+        for _ in 0..frames {
+            self.render()?;
+        }
+        // Wait for the GPU, so the work isn't still queued when the window appears
+        self.facade.get_context().finish();
+        self.set_clock(TimeEvent::Resume)?;
+
+        let elapsed = start.elapsed();
+        info!(
+            "Rendered {} warm-up frames in {:.1}ms",
+            frames,
+            elapsed.as_secs() as f64 * 1e3 + f64::from(elapsed.subsec_nanos()) / 1e6
+        );
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<(), Error> {
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                RendererEvent::Capture(path, metadata) => {
+                    // Captures can be asked for with `ctl capture <path>`, and a bad path
+                    // shouldn't stop the graph
+                    if let Err(e) = self.capture(&path, metadata) {
+                        error!("{}", format_error(&e));
+                    }
+                }
+
+                RendererEvent::ClearFeedback => {
+                    for (name, node) in &mut self.nodes {
+                        if let NodeType::Feedback(ref mut node) = *node {
+                            let _owner = resources::owner(name);
+                            node.clear()?;
+                        }
+                    }
+                }
+
+                RendererEvent::SetUniform(name, values) => match self.nodes.get_mut(&name) {
+                    Some(&mut NodeType::Constant(ref mut node)) => {
+                        if let Err(e) = node.set(&values) {
+                            warn!("Could not set `{}`: {}", name, e);
+                        }
+                    }
+                    _ => warn!("There's no constant node named `{}`", name),
+                },
+
+                event => {
+                    match event {
+                        RendererEvent::Time(ref time_event) => self.clock.handle(time_event),
+                        RendererEvent::Resize(width, height) if self.headless.is_some() => {
+                            self.headless = Some(resources::track(Texture2d::empty_with_mipmaps(
+                                &*self.facade,
+                                MipmapsOption::NoMipmap,
+                                width,
+                                height,
+                            )?));
+                        }
+                        _ => (),
+                    }
+
+                    for sender in &self.senders {
+                        sender.send(event.clone())?;
+                    }
+                }
+            }
+        }
+
+        // Only one program is built between two frames, so saving several shaders at once
+        // doesn't hold up a single frame for all of them
+        if let Some(name) = self.stale_programs.pop_front() {
+            if let Some(&mut NodeType::Shader(ref mut node)) = self.nodes.get_mut(&name) {
+                let _owner = resources::owner(&name);
+                // The old program keeps rendering, so the error is only shown over the frame
+                match node.rebuild() {
+                    Ok(()) => info!("Rebuilt the program of `{}`", name),
+                    Err(e) => {
+                        let e = e.context(format!("Error on node `{}`", name));
+                        error!("{}", format_error(&e));
+                        self.recovered_error = Some(e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render(&mut self) -> Result<(), Error> {
+        self.profiler.begin_frame();
+        let supersample = self.supersample;
+        let result = self
+            .check_watchdog()
+            .and_then(|()| match self.headless.clone() {
+                Some(texture) => target::render_offscreen(texture, || self.render_graph()),
+                None => target::render_supersampled(supersample, || self.render_graph()),
+            });
+        self.profiler.end_frame();
+
+        result
+    }
+
+    fn read_frame(&self, scale: f32) -> Result<Frame, Error> {
+        let (width, height) = self.frame_dimensions();
+        let width = ((width as f32 * scale).round() as u32).max(1);
+        let height = ((height as f32 * scale).round() as u32).max(1);
+
+        Ok(Frame {
+            width,
+            height,
+            pixels: capture::to_u8(&self.capture_frame(width, height)?, 4),
+        })
+    }
+
+    fn swap_buffers(&self) -> Result<(), Error> {
+        self.facade.get_context().swap_buffers()?;
+        Ok(())
+    }
+
+    fn set_profiling(&mut self, enabled: bool) {
+        // GPU timings are needed for the watchdog, when the config turned it on
+        self.profiler
+            .set_enabled(enabled || self.time_budget.is_some());
+    }
+
+    fn stats(&self) -> &FrameStats {
+        self.profiler.stats()
+    }
+
+    fn render_still(&mut self, width: u32, height: u32) -> Result<Vec<f32>, Error> {
+        // This doesn't need a window, so it works with headless facades
+        self.update()?;
+        self.capture_supersampled(width, height, 1)
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        self.nodes.values().flat_map(|node| node.files()).collect()
+    }
+
+    fn take_error(&mut self) -> Option<Error> {
+        self.recovered_error.take()
+    }
+
+    fn recompile(&mut self, path: &Path) -> bool {
+        let users: Vec<(&String, &NodeType)> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.files().iter().any(|file| file == path))
+            .collect();
+        let only_shaders = !users.is_empty()
+            && users.iter().all(|(_, node)| match **node {
+                NodeType::Shader(_) => true,
+                _ => false,
+            });
+
+        if only_shaders {
+            for (name, _) in users {
+                if !self.stale_programs.contains(name) {
+                    self.stale_programs.push_back(name.clone());
+                }
+            }
+        }
+        only_shaders
+    }
+
+    fn inherit_state(&mut self, old: &dyn Renderer) {
+        let old = match old.as_any().downcast_ref::<OpenGLRenderer>() {
+            Some(old) => old,
+            None => return,
+        };
+
+        // The time carries on, and feedback nodes with the same name keep the values from the
+        // last frame
+        self.clock.continue_from(&old.clock);
+
+        for (name, node) in &mut self.nodes {
+            match (node, old.nodes.get(name)) {
+                // Info nodes keep the same time as the renderer
+                (&mut NodeType::Info(ref mut node), _) => node.continue_from(&old.clock),
+                (
+                    &mut NodeType::Feedback(ref mut node),
+                    Some(&NodeType::Feedback(ref old_node)),
+                ) => node.inherit(old_node),
+                // Cameras stay where they were moved to
+                (&mut NodeType::Camera(ref mut node), Some(&NodeType::Camera(ref old_node))) => {
+                    node.inherit(old_node)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Colors and sizes for the text drawn by `OpenGLDebugRenderer`
+struct Theme {
+    /// Color of error text
+    error_color: [f32; 4],
+    /// Font size of error text
+    error_size: f32,
+    /// Color of the performance HUD
+    hud_color: [f32; 4],
+    /// Color of notices
+    notice_color: [f32; 4],
+    /// Font size of the HUD and notices
+    hud_size: f32,
+}
+
+impl Theme {
+    /// Returns the theme to use, depending on whether high contrast is wanted
+    fn new(high_contrast: bool) -> Self {
+        if high_contrast {
+            Self {
+                error_color: [1.0, 1.0, 1.0, 1.0],
+                error_size: 28.0,
+                hud_color: [1.0, 1.0, 0.0, 1.0],
+                notice_color: [1.0, 1.0, 1.0, 1.0],
+                hud_size: 20.0,
+            }
+        } else {
+            Self {
+                error_color: [1.0, 0.3, 0.3, 1.0],
+                error_size: 20.0,
+                hud_color: [1.0, 1.0, 0.0, 1.0],
+                notice_color: [0.7, 0.7, 0.7, 1.0],
+                hud_size: 14.0,
+            }
+        }
+    }
+}
+
+/// Renders errors
+pub struct OpenGLDebugRenderer {
+    /// Facade for interacting with OpenGL
+    facade: Rc<dyn Facade>,
+    /// Colors and sizes of the text
+    theme: Theme,
+    /// `TextRenderer` for displaying errors
+    error_renderer: TextRenderer,
+    /// `TextRenderer` for displaying the performance HUD
+    hud_renderer: TextRenderer,
+    /// Draws the widgets of the tweak panel and the graph editor
+    gui_renderer: GuiRenderer,
+    /// The last good frame, shown behind errors
+    backdrop: Backdrop,
+}
+
+impl OpenGLDebugRenderer {
+    /// Create a new instance, with text scaled by the DPI factor
+    pub fn new(
+        facade: &Rc<dyn Facade>,
+        high_contrast: bool,
+        hidpi_factor: f32,
+    ) -> Result<Self, Error> {
+        let theme = Theme::new(high_contrast);
+        Ok(Self {
+            facade: Rc::clone(facade),
+            error_renderer: TextRenderer::new(facade, "", theme.error_size * hidpi_factor)?,
+            hud_renderer: TextRenderer::new(facade, "", theme.hud_size * hidpi_factor)?,
+            gui_renderer: GuiRenderer::new(facade)?,
+            backdrop: Backdrop::new(facade)?,
+            theme,
+        })
+    }
+}
+
+impl DebugRenderer for OpenGLDebugRenderer {
+    fn draw_error(&mut self, error: &Error) -> Result<(), Error> {
+        let mut target = self.facade.draw();
+        self.backdrop.draw(&mut target)?;
+        self.error_renderer.draw_text(
+            &mut target,
+            &crate::util::format_error(error),
+            [0.0, 0.0],
+            self.theme.error_color,
+        )?;
+        target.finish()?;
+
+        Ok(())
+    }
+
+    fn draw_error_overlay(&mut self, error: &Error) -> Result<(), Error> {
+        let mut target = DefaultFramebuffer::back_left(&*self.facade);
+        let (width, _) = target.get_dimensions();
+        // Right-aligned in the right half, out of the way of the HUD
+        let half = width as f32 / 2.0;
+        self.hud_renderer.draw_text_with_layout(
+            &mut target,
+            &crate::util::format_error(error),
+            [half - 8.0, 8.0],
+            self.theme.error_color,
+            &TextLayout {
+                align: TextAlign::Right,
+                max_width: Some(half),
+                ..Default::default()
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn save_frame(&mut self) -> Result<(), Error> {
+        self.backdrop.save()
+    }
+
+    fn draw_hud(&mut self, stats: &FrameStats) -> Result<(), Error> {
+        let mut text = format!(
+            "frame {:6.2} ms  cpu {:6.2} ms",
+            stats.frame_time, stats.cpu_time
+        );
+        text.push_str(&format!(
+            "  textures {:7.1} MB",
+            stats.texture_memory as f32 / (1024.0 * 1024.0)
+        ));
+        for node in &stats.nodes {
+            text.push_str(&format!(
+                "\n{:<16} gpu {:6.2} ms  cpu {:6.2} ms  {:7.1} MB",
+                node.name,
+                node.gpu_time,
+                node.cpu_time,
+                node.texture_memory as f32 / (1024.0 * 1024.0)
+            ));
+        }
+
+        let mut target = DefaultFramebuffer::back_left(&*self.facade);
+        self.hud_renderer
+            .draw_text(&mut target, &text, [8.0, 8.0], self.theme.hud_color)?;
+
+        Ok(())
+    }
+
+    fn draw_notice(&mut self, notice: &str) -> Result<(), Error> {
+        let mut target = DefaultFramebuffer::back_left(&*self.facade);
+        let (_, height) = target.get_dimensions();
+        self.hud_renderer.draw_text(
+            &mut target,
+            notice,
+            [8.0, height as f32 - self.theme.hud_size * 1.7],
+            self.theme.notice_color,
+        )?;
+
+        Ok(())
+    }
+
+    fn draw_gui(&mut self, draw_data: &DrawData, fonts: &FontTexture) -> Result<(), Error> {
+        let mut target = DefaultFramebuffer::back_left(&*self.facade);
+        self.gui_renderer.draw(&mut target, draw_data, fonts)
+    }
+
+    fn inherit_frame(&mut self, old: &mut dyn DebugRenderer) {
+        if let Some(old) = old.as_any_mut().downcast_mut::<OpenGLDebugRenderer>() {
+            self.backdrop.inherit(&mut old.backdrop);
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Describes the window for a configuration. An icon that can't be loaded is left out, since the
+/// window is still usable without it.
+pub fn window_builder(config: &Config, events_loop: &EventsLoop, title: &str) -> WindowBuilder {
+    let icon = config
+        .icon
+        .as_ref()
+        .and_then(|path| match load_icon(&config.path_to(path)) {
+            Ok(icon) => Some(icon),
+            Err(e) => {
+                warn!("{}", format_error(&e));
+                None
+            }
+        });
+
+    WindowBuilder::new()
+        .with_dimensions((config.width, config.height).into())
+        .with_title(title)
+        .with_window_icon(icon)
+        .with_decorations(config.decorations)
+        .with_maximized(config.maximize)
+        .with_transparency(config.transparent)
+        .with_always_on_top(config.always_on_top)
+        .with_fullscreen(if config.fullscreen {
+            Some(monitor::find(
+                events_loop,
+                config.monitor.as_ref().map(String::as_str),
+            ))
+        } else {
+            None
+        })
+}
+
+/// Describes the context for a present mode and number of multisampling samples, returning the
+/// mode we'll actually get
+pub fn context_builder(
+    present_mode: PresentMode,
+    multisampling: u16,
+) -> (ContextBuilder<'static>, PresentMode) {
+    let supported = present_mode.supported();
+    if supported != present_mode {
+        warn!(
+            "Present mode {} isn't supported, using {} instead",
+            present_mode, supported
+        );
+    }
+    info!("Present mode: {}", supported);
+
+    let mut context_builder = ContextBuilder::new()
+        .with_vsync(supported.vsync())
+        .with_srgb(false);
+    if multisampling > 0 {
+        if multisampling.is_power_of_two() {
+            context_builder = context_builder.with_multisampling(multisampling);
+        } else {
+            warn!(
+                "Multisampling needs a power of two number of samples, not {}; turning it off",
+                multisampling
+            );
+        }
+    }
+    (context_builder, supported)
+}
+
+/// Create an appropriate Facade, along with the `Display` if there is a window. Headless facades
+/// don't need an events loop, since there may not be a display server to make one with.
+pub fn new_facade(
+    config: &Config,
+    events_loop: Option<&EventsLoop>,
+    title: &str,
+) -> Result<(Rc<dyn Facade>, Option<Display>), Error> {
+    // Layer surfaces and the root window aren't windows as far as winit knows, so there's no
+    // `Display` for them
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if !config.headless {
+            if let Some(layer) = config.platform_config.layer_shell {
+                let facade = crate::platform::layer_shell::new_facade(config, layer)?;
+                return Ok((facade, None));
+            }
+            if config.platform_config.root {
+                return Ok((crate::platform::root::new_facade()?, None));
+            }
+        }
+    }
+
+    if !config.headless {
+        let events_loop = events_loop.ok_or_else(|| {
+            format_err!("No display server to open a window on, try running headless")
+        })?;
+        // The window is shown once the warm-up frames are rendered
+        let window_builder =
+            window_builder(config, events_loop, title).with_visibility(config.warmup_frames == 0);
+        let (context_builder, _) = context_builder(config.present_mode(), config.multisampling);
+        let display = Display::new(window_builder, context_builder, events_loop)
+            .map_err(|error| format_err!("{}", error))?;
+        if let Some(position) = config.position {
+            let gl_window = display.gl_window();
+            let window = gl_window.window();
+            window.set_position((position[0], position[1]).into());
+            // Moving to another monitor can change the DPI factor, so set the size again
+            window.set_inner_size((config.width, config.height).into());
+        }
+        crate::platform::window::init(display.gl_window().window(), &config);
+
+        Ok((Rc::new(display.clone()), Some(display)))
+    } else {
+        Ok((headless::new_facade(config, events_loop)?, None))
+    }
+}
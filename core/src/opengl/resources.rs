@@ -0,0 +1,105 @@
+//! Keeps track of the textures allocated on the GPU and which node they belong to
+//!
+//! Textures are recorded with `track` as they're made, and belong to whichever node was set with
+//! `owner` at the time. The renderer sets the owner while building and rendering each node, so
+//! nodes don't need to know their own names. Textures count until they're dropped. Sizes assume
+//! 4 bytes per pixel, so floating point textures count for less than they use.
+
+use glium::texture::{Texture1d, Texture2d, Texture3d};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+/// Name that textures allocated outside of any node are listed under
+pub const NO_OWNER: &str = "(renderer)";
+
+/// A texture that has been recorded
+struct Allocation {
+    /// The node the texture belongs to
+    owner: Option<Rc<str>>,
+    /// Size of the texture (bytes)
+    bytes: u64,
+    /// Whether the texture still exists
+    alive: Box<dyn Fn() -> bool>,
+}
+
+thread_local! {
+    /// The node textures are being allocated for, if any
+    static OWNER: RefCell<Option<Rc<str>>> = RefCell::new(None);
+    /// Every texture recorded that may still exist
+    static ALLOCATIONS: RefCell<Vec<Allocation>> = RefCell::new(Vec::new());
+}
+
+/// A texture whose size can be measured
+pub trait TextureSize {
+    /// Returns the number of pixels in the texture
+    fn pixels(&self) -> u64;
+}
+
+impl TextureSize for Texture1d {
+    fn pixels(&self) -> u64 {
+        u64::from(self.width())
+    }
+}
+
+impl TextureSize for Texture2d {
+    fn pixels(&self) -> u64 {
+        u64::from(self.width()) * u64::from(self.height())
+    }
+}
+
+impl TextureSize for Texture3d {
+    fn pixels(&self) -> u64 {
+        u64::from(self.width()) * u64::from(self.height()) * u64::from(self.depth())
+    }
+}
+
+/// Records a new texture as belonging to the current owner
+pub fn track<T: TextureSize + 'static>(texture: T) -> Rc<T> {
+    let texture = Rc::new(texture);
+    let weak: Weak<T> = Rc::downgrade(&texture);
+    let allocation = Allocation {
+        owner: OWNER.with(|owner| owner.borrow().clone()),
+        bytes: texture.pixels() * 4,
+        alive: Box::new(move || weak.upgrade().is_some()),
+    };
+    ALLOCATIONS.with(|allocations| allocations.borrow_mut().push(allocation));
+    texture
+}
+
+/// Makes textures belong to a node until the returned guard is dropped
+pub fn owner(name: &str) -> OwnerGuard {
+    let previous = OWNER.with(|owner| owner.borrow_mut().replace(Rc::from(name)));
+    OwnerGuard { previous }
+}
+
+/// Restores the owner from before `owner` was called when it's dropped
+pub struct OwnerGuard {
+    /// The owner before this one
+    previous: Option<Rc<str>>,
+}
+
+impl Drop for OwnerGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        OWNER.with(|owner| *owner.borrow_mut() = previous);
+    }
+}
+
+/// Returns the memory used by the textures that still exist, by owner (bytes), forgetting the
+/// ones that have been dropped
+pub fn usage() -> HashMap<String, u64> {
+    ALLOCATIONS.with(|allocations| {
+        let mut allocations = allocations.borrow_mut();
+        allocations.retain(|allocation| (allocation.alive)());
+
+        let mut usage = HashMap::new();
+        for allocation in allocations.iter() {
+            let owner = allocation.owner.as_ref().map_or(NO_OWNER, |owner| &**owner);
+            *usage.entry(owner.to_string()).or_insert(0) += allocation.bytes;
+        }
+        usage
+    })
+}
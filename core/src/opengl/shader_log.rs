@@ -0,0 +1,205 @@
+//! Parses GLSL compiler info logs, which every driver formats differently, so compile errors can
+//! be shown next to the source line they point at
+
+use std::path::Path;
+
+/// Lines of source shown before and after the offending line
+const CONTEXT_LINES: usize = 2;
+/// Tabs are expanded to this many spaces, since the text renderer doesn't handle them
+const TAB_WIDTH: usize = 4;
+
+/// A single message from an info log
+#[derive(Debug, PartialEq)]
+pub struct LogMessage {
+    /// Line number in the source, if the driver gave one
+    pub line: Option<usize>,
+    /// Column in the source (starting at 1), if the driver gave one
+    pub column: Option<usize>,
+    /// The message itself
+    pub text: String,
+}
+
+/// Removes a prefix from a string, if it starts with it
+fn skip<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    if s.starts_with(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// Splits a number off the start of a string
+fn number(s: &str) -> Option<(usize, &str)> {
+    let end = s
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| s.len());
+    s[..end].parse().ok().map(|n| (n, &s[end..]))
+}
+
+/// Splits a number in parentheses off the start of a string
+fn parenthesized(s: &str) -> Option<(usize, &str)> {
+    let (n, rest) = number(skip(s, "(")?)?;
+    Some((n, skip(rest, ")")?))
+}
+
+/// Parses the location at the start of a message, returning the line, column, and the rest
+fn location(s: &str) -> Option<(usize, Option<usize>, &str)> {
+    let (_file, rest) = number(s)?;
+    let (line, column, rest) = if let Some((line, rest)) = parenthesized(rest) {
+        // NVIDIA: `0(12) : error C0000: ...`
+        (line, None, rest)
+    } else {
+        // Mesa: `0:12(5): error: ...`, others: `0:12: ...` or `0:12:5: ...`
+        let (line, rest) = number(skip(rest, ":")?)?;
+        match parenthesized(rest) {
+            Some((column, rest)) => (line, Some(column), rest),
+            None => match skip(rest, ":").and_then(number) {
+                Some((column, rest)) => (line, Some(column), rest),
+                None => (line, None, rest),
+            },
+        }
+    };
+    let rest = skip(rest.trim_start(), ":")?;
+    Some((line, column, rest.trim()))
+}
+
+/// Parses one line of an info log
+fn parse_line(line: &str) -> LogMessage {
+    let line = line.trim();
+    // AMD, Intel on Windows and Apple put the severity first: `ERROR: 0:12: ...`
+    let severity = ["ERROR:", "WARNING:"]
+        .iter()
+        .find(|prefix| line.starts_with(*prefix));
+    let rest = severity.map_or(line, |prefix| line[prefix.len()..].trim_start());
+
+    match location(rest) {
+        Some((line, column, text)) => LogMessage {
+            line: Some(line),
+            column,
+            text: match severity {
+                Some(prefix) => format!("{} {}", prefix.to_lowercase(), text),
+                None => text.to_string(),
+            },
+        },
+        None => LogMessage {
+            line: None,
+            column: None,
+            text: line.to_string(),
+        },
+    }
+}
+
+/// Parses a whole info log, skipping empty lines
+pub fn parse(log: &str) -> Vec<LogMessage> {
+    log.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+/// Formats an info log with the source lines each message refers to, with a caret under the
+/// column (or the start of the line if there isn't one)
+pub fn annotate(log: &str, source: &str, path: &Path) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut result = Vec::new();
+
+    for message in parse(log) {
+        let line = match message.line {
+            Some(line) if line >= 1 && line <= lines.len() => line,
+            _ => {
+                result.push(message.text);
+                continue;
+            }
+        };
+
+        result.push(match message.column {
+            Some(column) => format!("{}:{}:{}: {}", path.display(), line, column, message.text),
+            None => format!("{}:{}: {}", path.display(), line, message.text),
+        });
+
+        let first = line.saturating_sub(CONTEXT_LINES).max(1);
+        let last = (line + CONTEXT_LINES).min(lines.len());
+        let width = last.to_string().len();
+        for number in first..=last {
+            let text = lines[number - 1];
+            let expanded = text.replace('\t', &" ".repeat(TAB_WIDTH));
+            result.push(format!("{:>width$} | {}", number, expanded, width = width));
+            if number == line {
+                let column = message
+                    .column
+                    .unwrap_or_else(|| text.find(|c: char| !c.is_whitespace()).unwrap_or(0) + 1);
+                let indent: usize = text
+                    .chars()
+                    .take(column.saturating_sub(1))
+                    .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+                    .sum();
+                let indent = " ".repeat(indent);
+                result.push(format!("{:>width$} | {}^", "", indent, width = width));
+            }
+        }
+    }
+
+    result.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotate, parse, LogMessage};
+    use std::path::Path;
+
+    fn message(line: usize, column: Option<usize>, text: &str) -> LogMessage {
+        LogMessage {
+            line: Some(line),
+            column,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_driver_formats() {
+        assert_eq!(
+            parse("0:12(5): error: `foo' undeclared\n"),
+            vec![message(12, Some(5), "error: `foo' undeclared")]
+        );
+        assert_eq!(
+            parse("0(7) : error C1008: undefined variable \"foo\""),
+            vec![message(7, None, "error C1008: undefined variable \"foo\"")]
+        );
+        assert_eq!(
+            parse("ERROR: 0:3: 'foo' : undeclared identifier \nERROR: 1 compilation errors."),
+            vec![
+                message(3, None, "error: 'foo' : undeclared identifier"),
+                LogMessage {
+                    line: None,
+                    column: None,
+                    text: "ERROR: 1 compilation errors.".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            parse("WARNING: 0:4:9: implicit conversion"),
+            vec![message(4, Some(9), "warning: implicit conversion")]
+        );
+    }
+
+    #[test]
+    fn annotates_source() {
+        let source = "#version 140\n\nvoid main() {\n\tfoo = 1.0;\n}\n";
+        assert_eq!(
+            annotate(
+                "0:4(2): error: `foo' undeclared",
+                source,
+                Path::new("a.frag")
+            ),
+            [
+                "a.frag:4:2: error: `foo' undeclared",
+                "2 | ",
+                "3 | void main() {",
+                "4 |     foo = 1.0;",
+                "  |     ^",
+                "5 | }",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Nodes normally render at the size of the window, and the output node draws to the window. While
+//! capturing with supersampling, the graph is instead rendered into a larger offscreen texture,
+//! which is made available here while it's active. With the `supersample` option, nodes render at
+//! a multiple of the window's size and the output node scales the result down to the window.
+
+use glium::{backend::Facade, texture::Texture2d};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+thread_local! {
+    /// The offscreen texture the graph is currently being rendered into, if any
+    static OFFSCREEN: RefCell<Option<Rc<Texture2d>>> = RefCell::new(None);
+    /// The multiple of the window's size the graph is currently being rendered at
+    static SUPERSAMPLE: Cell<u32> = Cell::new(1);
+    /// Whether the graph is currently being rendered again for a capture
+    static CAPTURING: Cell<bool> = Cell::new(false);
+}
+
+/// Returns the offscreen texture the graph is being rendered into, if any
+pub fn offscreen() -> Option<Rc<Texture2d>> {
+    OFFSCREEN.with(|offscreen| offscreen.borrow().clone())
+}
+
+/// Returns the multiple of the window's size nodes render at, which is always 1 while rendering
+/// offscreen since the offscreen texture already has the size it needs
+pub fn supersample() -> u32 {
+    match offscreen() {
+        Some(_) => 1,
+        None => SUPERSAMPLE.with(Cell::get),
+    }
+}
+
+/// Returns the dimensions nodes should render at
+pub fn dimensions(facade: &dyn Facade) -> (u32, u32) {
+    match offscreen() {
+        Some(texture) => texture.dimensions(),
+        None => {
+            let (width, height) = facade.get_context().get_framebuffer_dimensions();
+            let factor = supersample();
+            (width * factor, height * factor)
+        }
+    }
+}
+
+/// Runs a render function with the output going to an offscreen texture instead of the window
+pub fn render_offscreen<T, F>(texture: Rc<Texture2d>, render: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    OFFSCREEN.with(|offscreen| *offscreen.borrow_mut() = Some(texture));
+    let result = render();
+    OFFSCREEN.with(|offscreen| *offscreen.borrow_mut() = None);
+    result
+}
+
+/// Returns whether the graph is being rendered again for a capture, in which case nodes shouldn't
+/// move on to the next frame or throw away state that's the size of the window
+pub fn capturing() -> bool {
+    CAPTURING.with(Cell::get)
+}
+
+/// Runs a render function that renders the current frame again into an offscreen texture, for a
+/// capture
+pub fn render_capture<T, F>(texture: Rc<Texture2d>, render: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    CAPTURING.with(|capturing| capturing.set(true));
+    let result = render_offscreen(texture, render);
+    CAPTURING.with(|capturing| capturing.set(false));
+    result
+}
+
+/// Runs a render function with nodes rendering at a multiple of the window's size
+pub fn render_supersampled<T, F>(factor: u32, render: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    SUPERSAMPLE.with(|supersample| supersample.set(factor));
+    let result = render();
+    SUPERSAMPLE.with(|supersample| supersample.set(1));
+    result
+}
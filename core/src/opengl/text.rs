@@ -0,0 +1,837 @@
+//! Contains a GPU cache implementation and methods for rendering strings on the screen using
+//! OpenGL
+
+use anyhow::{bail, ensure, Error};
+use glium::{
+    backend::Facade,
+    implement_vertex,
+    index::{NoIndices, PrimitiveType},
+    program::ProgramCreationInput,
+    texture::{
+        ClientFormat, MipmapsOption, RawImage2d, Texture2dDataSource, UncompressedFloatFormat,
+    },
+    uniforms::MagnifySamplerFilter,
+    Blend, DrawParameters, Program, Surface, Texture2d, VertexBuffer,
+};
+use log::debug;
+use rect_packer::{self, DensePacker};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::max,
+    collections::HashMap,
+    mem,
+    rc::{Rc, Weak},
+};
+
+use super::{profiler, resources, UniformsStorageVec};
+use crate::{
+    config::nodes::TextAlign,
+    font::{self, FreeTypeRasterizer, GlyphKey, GlyphLoader, RenderedGlyph},
+};
+use unicode_normalization::UnicodeNormalization;
+
+const VERTEX: &str = "
+    #version 140
+
+    in vec2 position;
+    in vec2 tex_coords;
+    out vec2 texCoords;
+
+    uniform mat4 projection;
+
+    void main() {
+        gl_Position = projection * vec4(position, 0.0, 1.0);
+        texCoords = tex_coords;
+    }
+";
+
+const FRAGMENT: &str = "
+    #version 140
+
+    in vec2 texCoords;
+    out vec4 color;
+
+    uniform sampler2D glyphTexture;
+    uniform vec4 glyphColor;
+    uniform bool colorGlyph;
+
+    void main() {
+        if (colorGlyph) {
+            // Color glyphs are premultiplied, and only take the alpha of the text's color
+            vec4 sampled = texture(glyphTexture, texCoords);
+            vec3 unpremultiplied = sampled.a > 0.0 ? sampled.rgb / sampled.a : vec3(0.0);
+            color = vec4(unpremultiplied, sampled.a * glyphColor.a);
+        } else {
+            vec4 sampled = vec4(1.0, 1.0, 1.0, texture(glyphTexture, texCoords).r);
+            color = glyphColor * sampled;
+        }
+    }
+";
+
+impl<'a> Texture2dDataSource<'a> for &'a RenderedGlyph {
+    type Data = u8;
+
+    fn into_raw(self) -> RawImage2d<'a, u8> {
+        RawImage2d {
+            data: Cow::Borrowed(&self.buffer),
+            width: self.width as u32,
+            height: self.height as u32,
+            format: if self.color {
+                ClientFormat::U8U8U8U8
+            } else {
+                ClientFormat::U8
+            },
+        }
+    }
+}
+
+/// Data about a glyph stored in the texture cache
+#[derive(Clone, Debug)]
+pub struct GlyphData {
+    /// Rectangle containing the glyph within the cache texture
+    pub rect: rect_packer::Rect,
+    /// Whether the glyph has colors of its own, and is stored in the color texture
+    pub color: bool,
+    /// Width of glyph in pixels
+    pub width: u32,
+    /// Height of glyph in pixels
+    pub height: u32,
+    /// Additional distance from left
+    pub bearing_x: i32,
+    /// Additional distance from top
+    pub bearing_y: i32,
+    /// Advance distance to start of next character
+    pub advance: u32,
+    /// Line height
+    pub line_height: u32,
+}
+
+impl GlyphData {
+    /// Describes a glyph that has nothing to draw
+    fn empty(rendered: &RenderedGlyph) -> Self {
+        Self {
+            rect: rect_packer::Rect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
+            color: rendered.color,
+            width: rendered.width,
+            height: rendered.height,
+            bearing_x: rendered.bearing_x,
+            bearing_y: rendered.bearing_y,
+            advance: rendered.advance,
+            line_height: rendered.line_height,
+        }
+    }
+}
+
+/// Name the glyph atlas's texture is listed under in texture memory stats
+const ATLAS_OWNER: &str = "(glyphs)";
+
+/// Largest the glyph atlas may grow to in either dimension before it evicts glyphs instead
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+/// A glyph in the atlas, along with when it was last used
+struct AtlasGlyph {
+    /// Where the glyph is and how to place it
+    data: GlyphData,
+    /// Value of the atlas's clock the last time the glyph was looked up
+    last_used: u64,
+}
+
+/// A font at a size, with the loader used to render its glyphs
+struct AtlasFont<L: GlyphLoader> {
+    /// Name of the font
+    name: String,
+    /// Size of the font
+    size: f32,
+    /// The loader for the font's glyphs
+    loader: L,
+}
+
+/// One of the textures of the atlas, along with what's packed into it
+struct AtlasPage {
+    /// The texture on which the rendered glyphs are stored
+    texture: Rc<Texture2d>,
+    /// The packer used to pack glyphs into the texture
+    packer: DensePacker,
+    /// Format of the texture
+    format: UncompressedFloatFormat,
+}
+
+impl AtlasPage {
+    /// Create a new instance with an empty texture
+    fn new(
+        facade: &Rc<dyn Facade>,
+        format: UncompressedFloatFormat,
+        size: u32,
+    ) -> Result<Self, Error> {
+        let _owner = resources::owner(ATLAS_OWNER);
+        Ok(Self {
+            texture: resources::track(Texture2d::empty_with_format(
+                &**facade,
+                format,
+                MipmapsOption::NoMipmap,
+                size,
+                size,
+            )?),
+            packer: DensePacker::new(size as i32, size as i32),
+            format,
+        })
+    }
+}
+
+/// A cache of glyphs on the GPU, shared by every font and size so text nodes don't each keep a
+/// texture of their own. Color glyphs go in a texture of their own, since they need four channels
+/// instead of one. When a texture can't grow any more, the least recently used glyphs in it are
+/// evicted and rendered again when they're next needed.
+pub struct GlyphAtlas<L: GlyphLoader> {
+    /// The `Facade` it uses to access the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The fonts whose glyphs are stored, indexed by the IDs given out by `font`
+    fonts: Vec<AtlasFont<L>>,
+    /// The rendered glyphs, keyed by font ID and glyph rather than by character, since the same
+    /// character can come from different fonts
+    glyphs: HashMap<(usize, GlyphKey), AtlasGlyph>,
+    /// The textures for coverage glyphs and for color glyphs, in that order
+    pages: [AtlasPage; 2],
+    /// Counts glyph lookups, to tell which glyphs were used least recently
+    clock: u64,
+    /// Largest size the textures may grow to
+    max_size: u32,
+}
+
+impl<L: GlyphLoader> GlyphAtlas<L> {
+    /// Create a new instance
+    pub fn new(facade: &Rc<dyn Facade>) -> Result<Self, Error> {
+        let max_texture_size = facade.get_context().get_capabilities().max_texture_size;
+        Ok(Self {
+            facade: Rc::clone(facade),
+            fonts: Vec::new(),
+            glyphs: HashMap::new(),
+            pages: [
+                AtlasPage::new(facade, UncompressedFloatFormat::U8, 512)?,
+                // Most text has no color glyphs, so this starts small
+                AtlasPage::new(facade, UncompressedFloatFormat::U8U8U8U8, 64)?,
+            ],
+            clock: 0,
+            max_size: MAX_ATLAS_SIZE.min(max_texture_size as u32),
+        })
+    }
+
+    /// Returns the ID of a font at a size, loading it if no other text uses it yet
+    pub fn font(&mut self, name: &str, size: f32) -> Result<usize, Error> {
+        if let Some(id) = self
+            .fonts
+            .iter()
+            .position(|font| font.name == name && font.size == size)
+        {
+            return Ok(id);
+        }
+
+        let id = self.fonts.len();
+        self.fonts.push(AtlasFont {
+            name: name.to_string(),
+            size,
+            loader: L::new(name, size)?,
+        });
+
+        // Prerender all visible ascii characters
+        for i in 32u8..127u8 {
+            let key = self.lookup(id, i as char, None);
+            self.insert(id, key)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Find the glyph for a character in a font, trying the `preferred` font of its fallback chain
+    /// first
+    pub fn lookup(&mut self, font: usize, character: char, preferred: Option<usize>) -> GlyphKey {
+        self.fonts[font].loader.lookup(character, preferred)
+    }
+
+    /// Returns the distance between lines of a font
+    pub fn line_height(&self, font: usize) -> u32 {
+        self.fonts[font].loader.line_height()
+    }
+
+    /// Get a `&GlyphData` corresponding to a glyph of a font, rendering it if it isn't stored
+    pub fn get(&mut self, font: usize, key: GlyphKey) -> Result<&GlyphData, Error> {
+        if !self.glyphs.contains_key(&(font, key)) {
+            self.insert(font, key)?;
+        }
+
+        self.clock += 1;
+        let glyph = self.glyphs.get_mut(&(font, key)).unwrap();
+        glyph.last_used = self.clock;
+        Ok(&glyph.data)
+    }
+
+    /// Returns the texture a glyph is stored in
+    pub fn texture(&self, glyph: &GlyphData) -> &Texture2d {
+        &self.pages[glyph.color as usize].texture
+    }
+
+    /// Render a glyph from the font's loader into the texture for its kind
+    fn insert(&mut self, font: usize, key: GlyphKey) -> Result<(), Error> {
+        let rendered = self.fonts[font].loader.load(key)?;
+
+        let data = if rendered.width == 0 || rendered.height == 0 {
+            GlyphData::empty(&rendered)
+        } else {
+            let page = rendered.color as usize;
+            let rect = self.pack(page, rendered.width, rendered.height)?;
+            let blit_source = Texture2d::with_format(
+                &*self.facade,
+                &rendered,
+                self.pages[page].format,
+                MipmapsOption::NoMipmap,
+            )?;
+            let blit_rect = ::glium::Rect {
+                left: 0,
+                bottom: 0,
+                width: rendered.width as u32,
+                height: rendered.height as u32,
+            };
+            let blit_target = ::glium::BlitTarget {
+                left: rect.x as u32,
+                bottom: rect.y as u32,
+                width: rect.width,
+                height: rect.height,
+            };
+            self.pages[page]
+                .texture
+                .as_surface()
+                .blit_from_simple_framebuffer(
+                    &blit_source.as_surface(),
+                    &blit_rect,
+                    &blit_target,
+                    MagnifySamplerFilter::Nearest,
+                );
+
+            GlyphData {
+                rect: rect,
+                color: rendered.color,
+                width: rendered.width,
+                height: rendered.height,
+                bearing_x: rendered.bearing_x,
+                bearing_y: rendered.bearing_y,
+                advance: rendered.advance,
+                line_height: rendered.line_height,
+            }
+        };
+
+        self.glyphs.insert(
+            (font, key),
+            AtlasGlyph {
+                data,
+                last_used: self.clock,
+            },
+        );
+        Ok(())
+    }
+
+    /// Find room in a texture for a glyph, growing the texture or evicting glyphs if it's full
+    fn pack(&mut self, page: usize, width: u32, height: u32) -> Result<rect_packer::Rect, Error> {
+        ensure!(
+            width <= self.max_size && height <= self.max_size,
+            "Glyph is too large for the glyph atlas ({}x{})",
+            width,
+            height
+        );
+
+        if !self.pages[page]
+            .packer
+            .can_pack(width as i32, height as i32, false)
+        {
+            let size = self.pages[page].packer.size();
+            let old_size = (size.0 as u32, size.1 as u32);
+            if old_size.0 < self.max_size || old_size.1 < self.max_size {
+                // Let new size be at least 2x the old size so we're not resizing so much
+                let new_size = (
+                    max(old_size.0 + width, old_size.0 * 2).min(self.max_size),
+                    max(old_size.1 + height, old_size.1 * 2).min(self.max_size),
+                );
+                self.grow(page, old_size, new_size)?;
+            }
+        }
+        if !self.pages[page]
+            .packer
+            .can_pack(width as i32, height as i32, false)
+        {
+            self.evict(page)?;
+        }
+
+        match self.pages[page]
+            .packer
+            .pack(width as i32, height as i32, false)
+        {
+            Some(rect) => Ok(rect),
+            None => bail!("Failed to pack texture"),
+        }
+    }
+
+    /// Make a texture bigger, keeping the glyphs where they are
+    fn grow(
+        &mut self,
+        page: usize,
+        old_size: (u32, u32),
+        new_size: (u32, u32),
+    ) -> Result<(), Error> {
+        debug!("Growing glyph atlas to {}x{}", new_size.0, new_size.1);
+        let page = &mut self.pages[page];
+        page.packer.resize(new_size.0 as i32, new_size.1 as i32);
+
+        let _owner = resources::owner(ATLAS_OWNER);
+        let new_texture = Texture2d::empty_with_format(
+            &*self.facade,
+            page.format,
+            MipmapsOption::NoMipmap,
+            new_size.0,
+            new_size.1,
+        )?;
+        let blit_rect = ::glium::Rect {
+            left: 0,
+            bottom: 0,
+            width: old_size.0,
+            height: old_size.1,
+        };
+        let blit_target = ::glium::BlitTarget {
+            left: 0,
+            bottom: 0,
+            width: old_size.0 as i32,
+            height: old_size.1 as i32,
+        };
+        new_texture.as_surface().blit_from_simple_framebuffer(
+            &page.texture.as_surface(),
+            &blit_rect,
+            &blit_target,
+            MagnifySamplerFilter::Nearest,
+        );
+        page.texture = resources::track(new_texture);
+        Ok(())
+    }
+
+    /// Rebuild a texture with only the most recently used glyphs in it, up to half of its area, so
+    /// there's room for new ones without evicting again right away
+    fn evict(&mut self, page: usize) -> Result<(), Error> {
+        let color = page == 1;
+        let (mut glyphs, others): (Vec<_>, Vec<_>) = self
+            .glyphs
+            .drain()
+            .partition(|&(_, ref glyph)| glyph.data.color == color);
+        self.glyphs.extend(others);
+        glyphs.sort_by(|a, b| b.1.last_used.cmp(&a.1.last_used));
+
+        let page = &mut self.pages[page];
+        let (width, height) = page.packer.size();
+        let _owner = resources::owner(ATLAS_OWNER);
+        let old_texture = mem::replace(
+            &mut page.texture,
+            resources::track(Texture2d::empty_with_format(
+                &*self.facade,
+                page.format,
+                MipmapsOption::NoMipmap,
+                width as u32,
+                height as u32,
+            )?),
+        );
+        page.packer = DensePacker::new(width, height);
+
+        let mut room = i64::from(width) * i64::from(height) / 2;
+        let mut evicted = 0;
+        for (key, mut glyph) in glyphs {
+            let rect = glyph.data.rect;
+            let area = i64::from(rect.width) * i64::from(rect.height);
+            if area == 0 {
+                self.glyphs.insert(key, glyph);
+                continue;
+            }
+            let packed = if area <= room {
+                page.packer.pack(rect.width, rect.height, false)
+            } else {
+                None
+            };
+            match packed {
+                Some(packed) => {
+                    let blit_rect = ::glium::Rect {
+                        left: rect.x as u32,
+                        bottom: rect.y as u32,
+                        width: rect.width as u32,
+                        height: rect.height as u32,
+                    };
+                    let blit_target = ::glium::BlitTarget {
+                        left: packed.x as u32,
+                        bottom: packed.y as u32,
+                        width: packed.width,
+                        height: packed.height,
+                    };
+                    page.texture.as_surface().blit_from_simple_framebuffer(
+                        &old_texture.as_surface(),
+                        &blit_rect,
+                        &blit_target,
+                        MagnifySamplerFilter::Nearest,
+                    );
+                    glyph.data.rect = packed;
+                    room -= area;
+                    self.glyphs.insert(key, glyph);
+                }
+                None => evicted += 1,
+            }
+        }
+
+        debug!("Evicted {} glyphs from the glyph atlas", evicted);
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The glyph atlas shared by the `TextRenderer`s on this thread, for as long as any of them
+    /// still use it
+    static ATLAS: RefCell<Weak<RefCell<GlyphAtlas<FreeTypeRasterizer>>>> = RefCell::new(Weak::new());
+}
+
+/// Returns the glyph atlas shared by text rendered with a facade, creating one if there isn't one
+fn shared_atlas(
+    facade: &Rc<dyn Facade>,
+) -> Result<Rc<RefCell<GlyphAtlas<FreeTypeRasterizer>>>, Error> {
+    let same_facade = |atlas: &Rc<RefCell<GlyphAtlas<FreeTypeRasterizer>>>| {
+        &*atlas.borrow().facade as *const dyn Facade as *const u8
+            == &**facade as *const dyn Facade as *const u8
+    };
+    if let Some(atlas) = ATLAS.with(|shared| shared.borrow().upgrade()) {
+        if same_facade(&atlas) {
+            return Ok(atlas);
+        }
+    }
+
+    let atlas = Rc::new(RefCell::new(GlyphAtlas::new(facade)?));
+    ATLAS.with(|shared| *shared.borrow_mut() = Rc::downgrade(&atlas));
+    Ok(atlas)
+}
+
+/// An implementation of vertex attributes needed for rendering text
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+implement_vertex!(Vertex, position, tex_coords);
+
+/// How to lay out text
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    /// How to align lines within the text box
+    pub align: TextAlign,
+    /// Wrap lines longer than this (pixels)
+    pub max_width: Option<f32>,
+    /// Distance between lines, relative to the line height of the font
+    pub line_spacing: f32,
+    /// Only draw inside this box, as `[x, y, width, height]` measured like the text's position
+    pub clip: Option<[f32; 4]>,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 1.0,
+            clip: None,
+        }
+    }
+}
+
+/// A glyph placed on a line
+#[derive(Clone, Copy)]
+struct PlacedGlyph {
+    /// The glyph to draw
+    key: GlyphKey,
+    /// Distance from the start of the line to the pen position for the glyph
+    x: i32,
+    /// How far the glyph advances the pen
+    advance: i32,
+}
+
+/// A line of text after wrapping
+#[derive(Default)]
+struct Line {
+    /// The glyphs on the line
+    glyphs: Vec<PlacedGlyph>,
+    /// Width of the line so far
+    width: i32,
+    /// Index of the last space on the line, where it can be wrapped
+    last_space: Option<usize>,
+}
+
+/// The actual `TextRenderer` which uses a `Program` and a `GlyphAtlas` to render glyphs on a
+/// given surface
+pub struct TextRenderer {
+    /// The `Facade` it uses to access the OpenGL context
+    facade: Rc<dyn Facade>,
+    /// The `GlyphAtlas` which it uses to store rendered glyphs, shared with other text renderers
+    atlas: Rc<RefCell<GlyphAtlas<FreeTypeRasterizer>>>,
+    /// ID of the font and size within the atlas
+    font: usize,
+    /// The shader program it uses for drawing
+    program: Program,
+}
+
+impl TextRenderer {
+    /// Create a new instance using a specified font and size
+    pub fn new(facade: &Rc<dyn Facade>, font: &str, font_size: f32) -> Result<Self, Error> {
+        let atlas = shared_atlas(facade)?;
+        let font = atlas.borrow_mut().font(font, font_size)?;
+
+        let program = {
+            let input = ProgramCreationInput::SourceCode {
+                vertex_shader: VERTEX,
+                tessellation_control_shader: None,
+                tessellation_evaluation_shader: None,
+                geometry_shader: None,
+                fragment_shader: FRAGMENT,
+                transform_feedback_varyings: None,
+                outputs_srgb: true,
+                uses_point_size: false,
+            };
+            Program::new(&**facade, input)?
+        };
+
+        Ok(Self {
+            facade: Rc::clone(facade),
+            atlas,
+            font,
+            program,
+        })
+    }
+
+    /// Draw text on the surface at specified XY coordinates and with a specified color
+    pub fn draw_text<S>(
+        &mut self,
+        surface: &mut S,
+        text: &str,
+        pos: [f32; 2],
+        color: [f32; 4],
+    ) -> Result<(), Error>
+    where
+        S: Surface,
+    {
+        self.draw_text_with_layout(surface, text, pos, color, &TextLayout::default())?;
+        Ok(())
+    }
+
+    /// Break text into lines of glyphs, wrapping at `max_width` if given
+    fn layout_lines(&mut self, text: &str, max_width: Option<f32>) -> Result<Vec<Line>, Error> {
+        let mut atlas = self.atlas.borrow_mut();
+        let mut lines = vec![Line::default()];
+        // The font of the last character that wasn't a combining mark
+        let mut base_font = None;
+        // Compose characters where possible, so accents come out right even if the font can't
+        // position combining marks
+        for c in text.nfc() {
+            if c == '\n' {
+                lines.push(Line::default());
+                base_font = None;
+                continue;
+            }
+            if font::is_ignorable(c) {
+                continue;
+            }
+
+            // Combining marks are drawn over the previous character, so they don't advance, and
+            // come from the same font if it has them
+            let combining = font::is_combining(c);
+            let key = atlas.lookup(self.font, c, if combining { base_font } else { None });
+            if !combining {
+                base_font = Some(key.font);
+            }
+            let advance = if combining {
+                0
+            } else {
+                atlas.get(self.font, key)?.advance as i32
+            };
+
+            let line = lines.last_mut().unwrap();
+            let overflows = match max_width {
+                Some(max_width) => {
+                    c != ' ' && !line.glyphs.is_empty() && (line.width + advance) as f32 > max_width
+                }
+                None => false,
+            };
+            if overflows {
+                // Break after the last space if there is one, otherwise in the middle of the word
+                let next = match line.last_space {
+                    Some(space) => {
+                        let rest = line.glyphs.split_off(space + 1);
+                        let offset = rest.first().map_or(line.width, |glyph| glyph.x);
+                        let rest_width = line.width - offset;
+                        // The space we break at isn't part of either line
+                        line.glyphs.pop();
+                        line.width = line
+                            .glyphs
+                            .last()
+                            .map_or(0, |glyph| glyph.x + glyph.advance);
+                        Line {
+                            width: rest_width,
+                            glyphs: rest
+                                .into_iter()
+                                .map(|glyph| PlacedGlyph {
+                                    x: glyph.x - offset,
+                                    ..glyph
+                                })
+                                .collect(),
+                            last_space: None,
+                        }
+                    }
+                    None => Line::default(),
+                };
+                lines.push(next);
+            }
+
+            let line = lines.last_mut().unwrap();
+            if c == ' ' {
+                line.last_space = Some(line.glyphs.len());
+            }
+            line.glyphs.push(PlacedGlyph {
+                key,
+                x: line.width,
+                advance,
+            });
+            line.width += advance;
+        }
+
+        Ok(lines)
+    }
+
+    /// Returns the width of the widest line, the width of the box lines are aligned in, and the
+    /// height of the text
+    fn extents(&self, lines: &[Line], layout: &TextLayout) -> (f32, f32, f32) {
+        let line_height = self.atlas.borrow().line_height(self.font) as f32;
+        let line_advance = line_height * layout.line_spacing;
+        let text_width = lines.iter().map(|line| line.width).max().unwrap_or(0) as f32;
+        let box_width = layout.max_width.unwrap_or(text_width);
+        let text_height = line_height + line_advance * (lines.len() - 1) as f32;
+        (text_width, box_width, text_height)
+    }
+
+    /// Returns the width and height of the box text would be drawn in, without drawing it
+    pub fn measure(&mut self, text: &str, layout: &TextLayout) -> Result<[f32; 2], Error> {
+        let lines = self.layout_lines(text, layout.max_width)?;
+        let (text_width, box_width, text_height) = self.extents(&lines, layout);
+        Ok([box_width.max(text_width), text_height])
+    }
+
+    /// Draw text on the surface with the top left of its box at the given position, returning the
+    /// width and height of the box
+    pub fn draw_text_with_layout<S>(
+        &mut self,
+        surface: &mut S,
+        text: &str,
+        pos: [f32; 2],
+        color: [f32; 4],
+        layout: &TextLayout,
+    ) -> Result<[f32; 2], Error>
+    where
+        S: Surface,
+    {
+        let lines = self.layout_lines(text, layout.max_width)?;
+        let (text_width, box_width, text_height) = self.extents(&lines, layout);
+
+        let mut atlas = self.atlas.borrow_mut();
+        let line_height = atlas.line_height(self.font) as f32;
+        let line_advance = line_height * layout.line_spacing;
+
+        let (win_width, win_height) = surface.get_dimensions();
+        // Boxes hang down from their position, which is measured up from the top of the surface
+        let scissor = layout.clip.map(|clip| {
+            let (x, width, height) = (clip[0], clip[2], clip[3]);
+            let bottom = clip[1] + win_height as f32 - height;
+            ::glium::Rect {
+                left: x.max(0.0) as u32,
+                bottom: bottom.max(0.0) as u32,
+                width: (width + x.min(0.0)).max(0.0) as u32,
+                height: (height + bottom.min(0.0)).max(0.0) as u32,
+            }
+        });
+        let p_x = 2.0 / win_width as f32;
+        let p_y = 2.0 / win_height as f32;
+
+        // Rows translate to columns in glsl
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let projection = [
+            [ p_x,  0.0,  0.0,  0.0],
+            [ 0.0,  p_y,  0.0,  0.0],
+            [ 0.0,  0.0,  1.0,  0.0],
+            [-1.0, -1.0,  0.0,  1.0],
+        ];
+
+        for (index, line) in lines.iter().enumerate() {
+            let offset_x = match layout.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => ((box_width - line.width as f32) / 2.0).round(),
+                TextAlign::Right => box_width - line.width as f32,
+            };
+            let offset_y = (line_advance * index as f32).round();
+
+            for placed in &line.glyphs {
+                let glyph = atlas.get(self.font, placed.key)?.clone();
+                if glyph.width == 0 || glyph.height == 0 {
+                    continue;
+                }
+
+                let texture = atlas.texture(&glyph);
+                let mut uniforms = UniformsStorageVec::new();
+                uniforms.push("glyphColor", color);
+                uniforms.push("glyphTexture", texture.sampled());
+                uniforms.push("colorGlyph", glyph.color);
+                uniforms.push("projection", projection);
+
+                let x = pos[0] + offset_x + (glyph.bearing_x + placed.x) as f32;
+                let y =
+                    pos[1] + glyph.bearing_y as f32 - offset_y - line_height + win_height as f32;
+                let w = glyph.width as f32;
+                let h = glyph.height as f32;
+
+                let t_x1 = glyph.rect.x as f32 / texture.width() as f32;
+                let t_x2 = (glyph.rect.x as f32 + glyph.rect.width as f32) / texture.width() as f32;
+                let t_y1 = glyph.rect.y as f32 / texture.height() as f32;
+                let t_y2 =
+                    (glyph.rect.y as f32 + glyph.rect.height as f32) / texture.height() as f32;
+
+                #[cfg_attr(rustfmt, rustfmt_skip)]
+                let vertices = [
+                    Vertex { position: [x    , y + h], tex_coords: [t_x1, t_y1] },
+                    Vertex { position: [x    , y    ], tex_coords: [t_x1, t_y2] },
+                    Vertex { position: [x + w, y    ], tex_coords: [t_x2, t_y2] },
+                    Vertex { position: [x    , y + h], tex_coords: [t_x1, t_y1] },
+                    Vertex { position: [x + w, y    ], tex_coords: [t_x2, t_y2] },
+                    Vertex { position: [x + w, y + h], tex_coords: [t_x2, t_y1] },
+                ];
+
+                let vertex_buffer = VertexBuffer::new(&*self.facade, &vertices)?;
+                let index_buffer = NoIndices(PrimitiveType::TrianglesList);
+
+                let query = profiler::active_query();
+                let params = DrawParameters {
+                    blend: Blend::alpha_blending(),
+                    scissor,
+                    ..profiler::draw_parameters(&query)
+                };
+
+                surface.draw(
+                    &vertex_buffer,
+                    &index_buffer,
+                    &self.program,
+                    &uniforms,
+                    &params,
+                )?;
+            }
+        }
+
+        Ok([box_width.max(text_width), text_height])
+    }
+}
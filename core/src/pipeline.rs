@@ -0,0 +1,102 @@
+//! Runs a config's node graph for another program, which decides when each frame is rendered
+//! and what time it's rendered at
+
+use anyhow::Error;
+use std::{
+    path::Path,
+    sync::mpsc::{self, Sender},
+};
+use winit::EventsLoop;
+
+use crate::{
+    capture,
+    config::Config,
+    event::{RendererEvent, TimeEvent},
+    renderer::{self, Backend, Frame, FrameStats, Renderer},
+};
+
+/// The node graph of a config, rendered headless at the config's size. The clock only moves when
+/// the pipeline is rendered, so frames can be rendered faster or slower than real time.
+pub struct Pipeline {
+    /// Renders the graph
+    renderer: Box<dyn Renderer>,
+    /// Sends events to the renderer
+    sender: Sender<RendererEvent>,
+    /// The context the renderer draws with, which has to outlive it
+    _backend: Box<dyn Backend>,
+    /// The display connection the context was made with, if there's a display server
+    _events_loop: Option<EventsLoop>,
+    /// Width of rendered frames in pixels
+    width: u32,
+    /// Height of rendered frames in pixels
+    height: u32,
+}
+
+impl Pipeline {
+    /// Build the node graph of a config. The config is rendered headless whatever it says, and
+    /// its window options are ignored.
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let mut config = config.clone();
+        config.headless = true;
+
+        let events_loop = renderer::events_loop();
+        let backend = renderer::new_backend(&config, events_loop.as_ref(), "yotredash")?;
+        let (sender, receiver) = mpsc::channel();
+        let renderer = backend.new_renderer(&config, receiver)?;
+        sender.send(RendererEvent::Time(TimeEvent::Pause))?;
+
+        Ok(Self {
+            renderer,
+            sender,
+            _backend: backend,
+            _events_loop: events_loop,
+            width: config.width,
+            height: config.height,
+        })
+    }
+
+    /// Load a config file and build its node graph
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        Self::new(&Config::from_file(path)?)
+    }
+
+    /// The size of rendered frames in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Send an event to the nodes, like pointer movement or a key press. It's handled when the
+    /// next frame is rendered.
+    pub fn send(&self, event: RendererEvent) -> Result<(), Error> {
+        self.sender.send(event)?;
+        Ok(())
+    }
+
+    /// Render the frame at `time` (seconds), returning RGBA pixels from top to bottom. Feedback
+    /// nodes see the frames in the order they're rendered, so step forward in small increments
+    /// for them to look the way they do in a window.
+    pub fn render(&mut self, time: f32) -> Result<Frame, Error> {
+        self.render_hdr(time).map(|pixels| Frame {
+            width: self.width,
+            height: self.height,
+            pixels: capture::to_u8(&pixels, 4),
+        })
+    }
+
+    /// Render the frame at `time` (seconds), returning RGBA pixels as floats from top to bottom,
+    /// which can be brighter than 1
+    pub fn render_hdr(&mut self, time: f32) -> Result<Vec<f32>, Error> {
+        self.send(RendererEvent::Time(TimeEvent::SeekTo(time)))?;
+        self.renderer.render_still(self.width, self.height)
+    }
+
+    /// Measure how long each node takes on the GPU, which costs a little time every frame
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.renderer.set_profiling(enabled);
+    }
+
+    /// Timings of the last frame. The GPU times of nodes are only measured with profiling on.
+    pub fn stats(&self) -> &FrameStats {
+        self.renderer.stats()
+    }
+}
@@ -0,0 +1,57 @@
+//! Contains extra macOS-specific configurations
+
+use clap::{App, Arg, ArgMatches};
+use serde_derive::Deserialize;
+
+use crate::Config;
+
+/// Platform-specific configuration
+/// Be careful with this, because specifying an unknown field will not cause an error
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct PlatformSpecificConfig {
+    /// Whether or not to put the window at the desktop level, behind the desktop icons, so it can
+    /// be used as a wallpaper
+    #[serde(default = "default_desktop")]
+    pub desktop: bool,
+
+    /// Whether or not to show the window on every space instead of only the one it was opened on.
+    /// The window also stays put in Mission Control
+    #[serde(default = "default_all_spaces")]
+    pub all_spaces: bool,
+}
+
+/// A function that returns the default value of the `desktop` field
+fn default_desktop() -> bool {
+    false
+}
+
+/// A function that returns the default value of the `all_spaces` field
+fn default_all_spaces() -> bool {
+    false
+}
+
+impl PlatformSpecificConfig {
+    /// Builds the application description needed to parse command-line arguments
+    pub fn build_cli() -> App<'static, 'static> {
+        Config::build_cli().args(&[
+            Arg::with_name("desktop")
+                .long("desktop")
+                .help("Display at the desktop level, behind the desktop icons"),
+            Arg::with_name("all_spaces")
+                .long("all-spaces")
+                .help("Display on every space"),
+        ])
+    }
+
+    /// Merges options given on the command line into the configuration
+    pub fn merge_args(&mut self, args: &ArgMatches<'_>) {
+        self.desktop |= args.is_present("desktop");
+        self.all_spaces |= args.is_present("all_spaces");
+    }
+
+    /// Sets the options needed for the window to act as a desktop wallpaper
+    pub fn set_wallpaper(&mut self) {
+        self.desktop = true;
+        self.all_spaces = true;
+    }
+}
@@ -0,0 +1,6 @@
+//! macOS-specific methods and structs
+
+#![cfg(target_os = "macos")]
+
+pub mod config;
+pub mod window;
@@ -0,0 +1,66 @@
+//! Contains functions to apply macOS-specific window attributes
+
+use log::info;
+use objc::{
+    msg_send,
+    runtime::{Object, YES},
+    sel, sel_impl,
+};
+use std::os::raw::c_int;
+use winit::{os::macos::WindowExt, Window};
+
+use crate::config::Config;
+
+/// `kCGDesktopWindowLevelKey`
+const DESKTOP_WINDOW_LEVEL_KEY: c_int = 2;
+/// `NSWindowCollectionBehaviorCanJoinAllSpaces`
+const CAN_JOIN_ALL_SPACES: usize = 1 << 0;
+/// `NSWindowCollectionBehaviorStationary`, which keeps the window in place in Mission Control
+const STATIONARY: usize = 1 << 4;
+/// `NSWindowCollectionBehaviorIgnoresCycle`, which leaves the window out of Cmd-`
+const IGNORES_CYCLE: usize = 1 << 6;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGWindowLevelForKey(key: c_int) -> i32;
+}
+
+/// Puts a window at the desktop level, behind the desktop icons
+unsafe fn desktop_window(ns_window: *mut Object) {
+    let level = CGWindowLevelForKey(DESKTOP_WINDOW_LEVEL_KEY) as isize;
+    let _: () = msg_send![ns_window, setLevel: level];
+}
+
+/// Shows a window on every space
+unsafe fn all_spaces(ns_window: *mut Object) {
+    let behavior: usize = msg_send![ns_window, collectionBehavior];
+    let behavior = behavior | CAN_JOIN_ALL_SPACES | STATIONARY | IGNORES_CYCLE;
+    let _: () = msg_send![ns_window, setCollectionBehavior: behavior];
+}
+
+/// Makes a window let clicks and other input through to the windows below it
+unsafe fn click_through(ns_window: *mut Object) {
+    let _: () = msg_send![ns_window, setIgnoresMouseEvents: YES];
+}
+
+/// Initializes a window according to a configuration
+pub fn init(window: &Window, config: &Config) {
+    let ns_window = window.get_nswindow() as *mut Object;
+
+    unsafe {
+        if config.platform_config.desktop {
+            info!("Using desktop window level");
+            desktop_window(ns_window);
+        }
+
+        if config.platform_config.all_spaces {
+            info!("Showing window on all spaces");
+            all_spaces(ns_window);
+        }
+
+        if config.click_through {
+            info!("Letting input through the window");
+            click_through(ns_window);
+        }
+    }
+}
@@ -7,9 +7,9 @@ pub use self::platform::*;
 #[cfg(windows)]
 #[path = "windows/mod.rs"]
 mod platform;
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos")))]
 #[path = "unix/mod.rs"]
 mod platform;
-#[cfg(macos)]
+#[cfg(target_os = "macos")]
 #[path = "macos/mod.rs"]
 mod platform;
@@ -9,8 +9,8 @@ use crate::Config;
 /// Be careful with this, because specifying an unknown field will not cause an error
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct PlatformSpecificConfig {
-    // TODO: implement
-    /// Whether or not to draw on the root window
+    /// Whether or not to draw on the root window instead of our own, for window managers without
+    /// a desktop window. Compositors usually hide the root window, so this works best without one
     #[serde(default = "default_root")]
     pub root: bool,
 
@@ -28,6 +28,26 @@ pub struct PlatformSpecificConfig {
     /// alongside the override_redirect option
     #[serde(default = "default_lower_window")]
     pub lower_window: bool,
+
+    /// Draw on a wlr-layer-shell surface in this layer instead of in a window. This works on
+    /// Wayland compositors like sway, and `background` makes it a live wallpaper
+    #[serde(default = "default_layer_shell")]
+    pub layer_shell: Option<Layer>,
+}
+
+/// A layer of the wlr-layer-shell protocol, from the bottom of the stack to the top. The values are
+/// the ones the protocol uses.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    /// Below everything, like a wallpaper
+    Background = 0,
+    /// Above the wallpaper but below windows
+    Bottom = 1,
+    /// Above windows but below fullscreen windows
+    Top = 2,
+    /// Above everything
+    Overlay = 3,
 }
 
 /// A function that returns the default value of the `root` field
@@ -50,6 +70,11 @@ fn default_lower_window() -> bool {
     false
 }
 
+/// A function that returns the default value of the `layer_shell` field
+fn default_layer_shell() -> Option<Layer> {
+    None
+}
+
 impl PlatformSpecificConfig {
     /// Builds the application description needed to parse command-line arguments
     pub fn build_cli() -> App<'static, 'static> {
@@ -66,16 +91,30 @@ impl PlatformSpecificConfig {
             Arg::with_name("lower_window")
                 .long("lower-window")
                 .help("Lower window to the bottom of the stack"),
+            Arg::with_name("layer_shell")
+                .long("layer-shell")
+                .help("Display on a Wayland layer-shell surface in this layer")
+                .possible_values(&["background", "bottom", "top", "overlay"])
+                .takes_value(true),
         ])
     }
 
-    /// Parses the configuration from command-line arguments
-    pub fn from_args(args: &ArgMatches<'_>) -> Self {
-        Self {
-            root: args.is_present("root"),
-            override_redirect: args.is_present("override_redirect"),
-            desktop: args.is_present("desktop"),
-            lower_window: args.is_present("lower_window"),
+    /// Merges options given on the command line into the configuration
+    pub fn merge_args(&mut self, args: &ArgMatches<'_>) {
+        self.root |= args.is_present("root");
+        self.override_redirect |= args.is_present("override_redirect");
+        self.desktop |= args.is_present("desktop");
+        self.lower_window |= args.is_present("lower_window");
+
+        if let Some(value) = args.value_of("layer_shell") {
+            // Clap already checked it's one of the possible values
+            self.layer_shell = ::serde_yaml::from_str(value).ok();
         }
     }
+
+    /// Sets the options needed for the window to act as a desktop wallpaper
+    pub fn set_wallpaper(&mut self) {
+        self.desktop = true;
+        self.lower_window = true;
+    }
 }
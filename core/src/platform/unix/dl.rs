@@ -0,0 +1,25 @@
+//! Helpers for loading libraries at runtime, so optional backends don't need them to be installed
+
+use anyhow::{ensure, format_err, Error};
+use std::{ffi::CString, os::raw::c_void};
+
+/// Load the first library that exists out of some names. Libraries are never unloaded, since
+/// contexts may outlive any one facade.
+pub fn open(names: &[&str]) -> Result<*mut c_void, Error> {
+    names
+        .iter()
+        .map(|name| {
+            let name = CString::new(*name).unwrap();
+            unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) }
+        })
+        .find(|handle| !handle.is_null())
+        .ok_or_else(|| format_err!("Could not load {}, is it installed?", names[0]))
+}
+
+/// Look up a symbol in a library loaded with `dlopen`
+pub fn symbol(handle: *mut c_void, name: &str) -> Result<*mut c_void, Error> {
+    let c_name = CString::new(name).unwrap();
+    let symbol = unsafe { libc::dlsym(handle, c_name.as_ptr()) };
+    ensure!(!symbol.is_null(), "Could not find the function {}", name);
+    Ok(symbol)
+}
@@ -0,0 +1,446 @@
+//! Renders on a wlr-layer-shell surface, so yotredash can be a live wallpaper (or overlay) on
+//! Wayland compositors like sway. winit gives its windows the xdg-shell role, and a surface can
+//! only have one role, so this makes its own surface and EGL context instead of using winit.
+//!
+//! libwayland-client, libwayland-egl and libEGL are loaded at runtime, so they're only needed when
+//! layer-shell is enabled.
+
+use anyhow::{bail, ensure, format_err, Error};
+use glium::{
+    backend::{Backend, Context, Facade},
+    debug::DebugCallbackBehavior,
+    SwapBuffersError,
+};
+use log::{info, warn};
+use smithay_client_toolkit::{
+    environment,
+    environment::{Environment, SimpleGlobal},
+    reexports::protocols::wlr::unstable::layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, Anchor, ZwlrLayerSurfaceV1},
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CString,
+    os::raw::{c_char, c_void},
+    ptr,
+    rc::Rc,
+};
+use wayland_client::{
+    protocol::{wl_compositor::WlCompositor, wl_surface::WlSurface},
+    Display, EventQueue, Main,
+};
+use wayland_egl::WlEglSurface;
+
+use super::{
+    config::Layer,
+    dl::{open, symbol},
+};
+use crate::config::Config;
+
+/// Namespace of our layer surfaces, which compositors can use in their rules
+const NAMESPACE: &str = "yotredash";
+
+/// Opaque EGL handle (display, config, context or surface)
+type EglHandle = *mut c_void;
+
+const EGL_NONE: i32 = 0x3038;
+const EGL_SURFACE_TYPE: i32 = 0x3033;
+const EGL_WINDOW_BIT: i32 = 0x0004;
+const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+const EGL_OPENGL_BIT: i32 = 0x0008;
+const EGL_RED_SIZE: i32 = 0x3024;
+const EGL_GREEN_SIZE: i32 = 0x3023;
+const EGL_BLUE_SIZE: i32 = 0x3022;
+const EGL_ALPHA_SIZE: i32 = 0x3021;
+const EGL_DEPTH_SIZE: i32 = 0x3025;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x0001;
+
+/// The libEGL functions we use
+struct Egl {
+    get_display: unsafe extern "C" fn(*mut c_void) -> EglHandle,
+    initialize: unsafe extern "C" fn(EglHandle, *mut i32, *mut i32) -> u32,
+    terminate: unsafe extern "C" fn(EglHandle) -> u32,
+    bind_api: unsafe extern "C" fn(u32) -> u32,
+    choose_config:
+        unsafe extern "C" fn(EglHandle, *const i32, *mut EglHandle, i32, *mut i32) -> u32,
+    create_context: unsafe extern "C" fn(EglHandle, EglHandle, EglHandle, *const i32) -> EglHandle,
+    destroy_context: unsafe extern "C" fn(EglHandle, EglHandle) -> u32,
+    create_window_surface:
+        unsafe extern "C" fn(EglHandle, EglHandle, *mut c_void, *const i32) -> EglHandle,
+    destroy_surface: unsafe extern "C" fn(EglHandle, EglHandle) -> u32,
+    make_current: unsafe extern "C" fn(EglHandle, EglHandle, EglHandle, EglHandle) -> u32,
+    get_current_context: unsafe extern "C" fn() -> EglHandle,
+    swap_buffers: unsafe extern "C" fn(EglHandle, EglHandle) -> u32,
+    swap_interval: unsafe extern "C" fn(EglHandle, i32) -> u32,
+    get_proc_address: unsafe extern "C" fn(*const c_char) -> *const c_void,
+}
+
+impl Egl {
+    fn load() -> Result<Self, Error> {
+        let egl = open(&["libEGL.so.1", "libEGL.so"])?;
+        unsafe {
+            Ok(Self {
+                get_display: std::mem::transmute(symbol(egl, "eglGetDisplay")?),
+                initialize: std::mem::transmute(symbol(egl, "eglInitialize")?),
+                terminate: std::mem::transmute(symbol(egl, "eglTerminate")?),
+                bind_api: std::mem::transmute(symbol(egl, "eglBindAPI")?),
+                choose_config: std::mem::transmute(symbol(egl, "eglChooseConfig")?),
+                create_context: std::mem::transmute(symbol(egl, "eglCreateContext")?),
+                destroy_context: std::mem::transmute(symbol(egl, "eglDestroyContext")?),
+                create_window_surface: std::mem::transmute(symbol(egl, "eglCreateWindowSurface")?),
+                destroy_surface: std::mem::transmute(symbol(egl, "eglDestroySurface")?),
+                make_current: std::mem::transmute(symbol(egl, "eglMakeCurrent")?),
+                get_current_context: std::mem::transmute(symbol(egl, "eglGetCurrentContext")?),
+                swap_buffers: std::mem::transmute(symbol(egl, "eglSwapBuffers")?),
+                swap_interval: std::mem::transmute(symbol(egl, "eglSwapInterval")?),
+                get_proc_address: std::mem::transmute(symbol(egl, "eglGetProcAddress")?),
+            })
+        }
+    }
+}
+
+/// The globals we need from the compositor
+struct Globals {
+    compositor: SimpleGlobal<WlCompositor>,
+    layer_shell: SimpleGlobal<ZwlrLayerShellV1>,
+}
+
+environment!(Globals,
+    singles = [
+        WlCompositor => compositor,
+        ZwlrLayerShellV1 => layer_shell,
+    ],
+    multis = [],
+);
+
+/// What the compositor has told us about our layer surface
+#[derive(Default)]
+struct SurfaceState {
+    /// The latest configure event that hasn't been handled yet: width and height
+    configure: Cell<Option<(u32, u32)>>,
+    /// Whether the compositor closed the surface (for example because its output went away)
+    closed: Cell<bool>,
+}
+
+/// A layer surface and an EGL context drawing to it
+struct LayerShellBackend {
+    egl: Egl,
+    display: Display,
+    queue: RefCell<EventQueue>,
+    surface: Main<WlSurface>,
+    layer_surface: Main<ZwlrLayerSurfaceV1>,
+    egl_window: WlEglSurface,
+    egl_display: EglHandle,
+    egl_context: EglHandle,
+    egl_surface: EglHandle,
+    /// Shared with the layer surface's event handler
+    state: Rc<SurfaceState>,
+    /// Size of the surface in pixels
+    dimensions: Cell<(u32, u32)>,
+}
+
+impl LayerShellBackend {
+    /// Handle a configure event, if there's one waiting. The event handler has already
+    /// acknowledged it.
+    fn configure(&self) {
+        if let Some((width, height)) = self.state.configure.take() {
+            if width > 0 && height > 0 && (width, height) != self.dimensions.get() {
+                info!("Layer surface resized to {}x{}", width, height);
+                self.egl_window.resize(width as i32, height as i32, 0, 0);
+                // The instance sees the new framebuffer size after this frame, and tells the graph
+                self.dimensions.set((width, height));
+            }
+        }
+    }
+}
+
+impl Drop for LayerShellBackend {
+    fn drop(&mut self) {
+        unsafe {
+            (self.egl.make_current)(
+                self.egl_display,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            (self.egl.destroy_surface)(self.egl_display, self.egl_surface);
+            (self.egl.destroy_context)(self.egl_display, self.egl_context);
+            (self.egl.terminate)(self.egl_display);
+        }
+
+        self.layer_surface.destroy();
+        self.surface.destroy();
+        let _ = self.display.flush();
+    }
+}
+
+unsafe impl Backend for LayerShellBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        unsafe {
+            if (self.egl.swap_buffers)(self.egl_display, self.egl_surface) == 0 {
+                return Err(SwapBuffersError::ContextLost);
+            }
+        }
+
+        // Handle whatever the compositor sent since the last frame. Reading doesn't block, there
+        // just might be nothing to read.
+        let mut queue = self.queue.borrow_mut();
+        if let Some(guard) = queue.prepare_read() {
+            let _ = guard.read_events();
+        }
+        if let Err(e) = queue.dispatch_pending(&mut (), |_, _, _| {}) {
+            warn!("Lost the connection to the compositor: {}", e);
+            return Err(SwapBuffersError::ContextLost);
+        }
+        if self.state.closed.get() {
+            warn!("The compositor closed the layer surface");
+            return Err(SwapBuffersError::ContextLost);
+        }
+        self.configure();
+        let _ = self.display.flush();
+
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        (self.egl.get_proc_address)(symbol.as_ptr())
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        self.dimensions.get()
+    }
+
+    fn is_current(&self) -> bool {
+        unsafe { (self.egl.get_current_context)() == self.egl_context }
+    }
+
+    unsafe fn make_current(&self) {
+        (self.egl.make_current)(
+            self.egl_display,
+            self.egl_surface,
+            self.egl_surface,
+            self.egl_context,
+        );
+    }
+}
+
+/// Create the EGL context and surface for a wayland-egl window
+unsafe fn egl_context(
+    egl: &Egl,
+    display: *mut c_void,
+    window: *mut c_void,
+    config: &Config,
+) -> Result<(EglHandle, EglHandle, EglHandle), Error> {
+    let egl_display = (egl.get_display)(display);
+    ensure!(!egl_display.is_null(), "Could not get an EGL display");
+    let (mut major, mut minor) = (0, 0);
+    ensure!(
+        (egl.initialize)(egl_display, &mut major, &mut minor) != 0,
+        "Could not initialize EGL"
+    );
+    info!("Using EGL {}.{}", major, minor);
+    ensure!(
+        (egl.bind_api)(EGL_OPENGL_API) != 0,
+        "EGL doesn't support desktop OpenGL"
+    );
+
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    let config_attribs = [
+        EGL_SURFACE_TYPE, EGL_WINDOW_BIT,
+        EGL_RENDERABLE_TYPE, EGL_OPENGL_BIT,
+        EGL_RED_SIZE, 8,
+        EGL_GREEN_SIZE, 8,
+        EGL_BLUE_SIZE, 8,
+        EGL_ALPHA_SIZE, if config.transparent { 8 } else { 0 },
+        EGL_DEPTH_SIZE, 24,
+        EGL_NONE,
+    ];
+    let mut egl_config = ptr::null_mut();
+    let mut configs = 0;
+    if (egl.choose_config)(
+        egl_display,
+        config_attribs.as_ptr(),
+        &mut egl_config,
+        1,
+        &mut configs,
+    ) == 0
+        || configs == 0
+    {
+        (egl.terminate)(egl_display);
+        bail!("Could not find a suitable EGL config");
+    }
+
+    // The shaders need at least OpenGL 3.1, which Mesa only offers for core profile contexts, but
+    // other drivers may only give us a compatibility context
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    let core_attribs = [
+        EGL_CONTEXT_MAJOR_VERSION, 3,
+        EGL_CONTEXT_MINOR_VERSION, 3,
+        EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+        EGL_NONE,
+    ];
+    let mut context = (egl.create_context)(
+        egl_display,
+        egl_config,
+        ptr::null_mut(),
+        core_attribs.as_ptr(),
+    );
+    if context.is_null() {
+        context = (egl.create_context)(
+            egl_display,
+            egl_config,
+            ptr::null_mut(),
+            [EGL_NONE].as_ptr(),
+        );
+    }
+    if context.is_null() {
+        (egl.terminate)(egl_display);
+        bail!("Could not create an EGL context");
+    }
+
+    let surface = (egl.create_window_surface)(egl_display, egl_config, window, ptr::null());
+    if surface.is_null() {
+        (egl.destroy_context)(egl_display, context);
+        (egl.terminate)(egl_display);
+        bail!("Could not create an EGL surface");
+    }
+
+    (egl.make_current)(egl_display, surface, surface, context);
+    let interval = if config.present_mode().vsync() { 1 } else { 0 };
+    (egl.swap_interval)(egl_display, interval);
+
+    Ok((egl_display, context, surface))
+}
+
+/// A facade for a layer surface
+pub struct LayerShellFacade {
+    /// The glium context
+    context: Rc<Context>,
+}
+
+impl LayerShellFacade {
+    /// Connect to the compositor and create a layer surface covering the output it picks
+    pub fn new(config: &Config, layer: Layer) -> Result<Self, Error> {
+        ensure!(
+            wayland_egl::is_available(),
+            "Could not load libwayland-egl, is it installed?"
+        );
+        let egl = Egl::load()?;
+
+        let display = Display::connect_to_env()
+            .map_err(|e| format_err!("Could not connect to a Wayland compositor: {}", e))?;
+        let mut queue = display.create_event_queue();
+        let globals = Environment::new(
+            &display.attach(queue.token()),
+            &mut queue,
+            Globals {
+                compositor: SimpleGlobal::new(),
+                layer_shell: SimpleGlobal::new(),
+            },
+        )?;
+        let compositor = globals
+            .get_global::<WlCompositor>()
+            .ok_or_else(|| format_err!("The compositor doesn't support wl_compositor"))?;
+        let layer_shell = globals
+            .get_global::<ZwlrLayerShellV1>()
+            .ok_or_else(|| format_err!("The compositor doesn't support zwlr_layer_shell_v1"))?;
+
+        let surface = compositor.create_surface();
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            // Let the compositor pick the output
+            None,
+            match layer {
+                Layer::Background => zwlr_layer_shell_v1::Layer::Background,
+                Layer::Bottom => zwlr_layer_shell_v1::Layer::Bottom,
+                Layer::Top => zwlr_layer_shell_v1::Layer::Top,
+                Layer::Overlay => zwlr_layer_shell_v1::Layer::Overlay,
+            },
+            NAMESPACE.to_string(),
+        );
+        let state = Rc::new(SurfaceState::default());
+        let handler_state = Rc::clone(&state);
+        layer_surface.quick_assign(move |layer_surface, event, _| match event {
+            zwlr_layer_surface_v1::Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                // Acknowledging before the next commit is enough, and the resize happens before
+                // the next buffer is swapped in
+                layer_surface.ack_configure(serial);
+                handler_state.configure.set(Some((width, height)));
+            }
+            zwlr_layer_surface_v1::Event::Closed => handler_state.closed.set(true),
+            _ => (),
+        });
+
+        // A size of zero along anchored edges means the compositor decides, which with every edge
+        // anchored is the size of the output. An exclusive zone of -1 asks to extend under panels
+        // instead of being moved out of their way.
+        layer_surface.set_size(0, 0);
+        layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+        layer_surface.set_exclusive_zone(-1);
+        if config.click_through {
+            // An empty input region lets input through to whatever is below
+            let region = compositor.create_region();
+            surface.set_input_region(Some(&*region));
+            region.destroy();
+        }
+        surface.commit();
+        queue.sync_roundtrip(&mut (), |_, _, _| {})?;
+
+        let (width, height) = match state.configure.take() {
+            Some((width, height)) if width > 0 && height > 0 && !state.closed.get() => {
+                (width, height)
+            }
+            _ => bail!("The compositor didn't configure the layer surface"),
+        };
+        info!("Created a {:?} layer surface ({}x{})", layer, width, height);
+
+        let egl_window = WlEglSurface::new(&surface, width as i32, height as i32);
+        let (egl_display, egl_context, egl_surface) = unsafe {
+            egl_context(
+                &egl,
+                display.get_display_ptr() as *mut c_void,
+                egl_window.ptr() as *mut c_void,
+                config,
+            )?
+        };
+
+        let backend = LayerShellBackend {
+            egl,
+            display,
+            queue: RefCell::new(queue),
+            surface,
+            layer_surface,
+            egl_window,
+            egl_display,
+            egl_context,
+            egl_surface,
+            state,
+            dimensions: Cell::new((width, height)),
+        };
+        let context = unsafe { Context::new(backend, true, DebugCallbackBehavior::default())? };
+
+        Ok(Self { context })
+    }
+}
+
+impl Facade for LayerShellFacade {
+    fn get_context(&self) -> &Rc<Context> {
+        &self.context
+    }
+}
+
+/// Create a facade drawing to a layer surface in the configured layer
+pub fn new_facade(config: &Config, layer: Layer) -> Result<Rc<dyn Facade>, Error> {
+    Ok(Rc::new(LayerShellFacade::new(config, layer)?))
+}
@@ -0,0 +1,9 @@
+//! Unix-specific methods and structs
+
+#![cfg(all(unix, not(target_os = "macos")))]
+
+pub mod config;
+pub mod dl;
+pub mod layer_shell;
+pub mod root;
+pub mod window;
@@ -0,0 +1,284 @@
+//! Renders straight to the X11 root window, for window managers that don't have a desktop window to
+//! draw a wallpaper on. winit can only make its own windows, so this makes a GLX context for the
+//! root window instead. libGL is loaded at runtime, so it's only needed when this is enabled.
+//!
+//! Compositors usually draw the root window's background pixmap rather than the window itself, so
+//! this is for setups without one.
+
+use anyhow::{bail, ensure, format_err, Error};
+use glium::{
+    backend::{Backend, Context, Facade},
+    debug::DebugCallbackBehavior,
+    SwapBuffersError,
+};
+use log::info;
+use std::{
+    cell::Cell,
+    ffi::CString,
+    mem,
+    os::raw::{c_int, c_ulong, c_void},
+    ptr,
+    rc::Rc,
+    slice,
+};
+use winit::os::unix::x11::{
+    ffi::{Display, XWindowAttributes, XID},
+    XConnection,
+};
+
+use super::dl::{open, symbol};
+
+/// Opaque GLX framebuffer config
+type GlxFbConfig = *mut c_void;
+/// Opaque GLX context
+type GlxContext = *mut c_void;
+
+const GLX_DOUBLEBUFFER: c_int = 5;
+const GLX_VISUAL_ID: c_int = 0x800B;
+const GLX_DRAWABLE_TYPE: c_int = 0x8010;
+const GLX_RENDER_TYPE: c_int = 0x8011;
+const GLX_RGBA_TYPE: c_int = 0x8014;
+const GLX_WINDOW_BIT: c_int = 0x0001;
+const GLX_RGBA_BIT: c_int = 0x0001;
+const GLX_CONTEXT_MAJOR_VERSION_ARB: c_int = 0x2091;
+const GLX_CONTEXT_MINOR_VERSION_ARB: c_int = 0x2092;
+const GLX_CONTEXT_PROFILE_MASK_ARB: c_int = 0x9126;
+const GLX_CONTEXT_CORE_PROFILE_BIT_ARB: c_int = 0x0001;
+
+/// `glXCreateContextAttribsARB`, which is an extension so it's looked up through GLX
+type CreateContextAttribs =
+    unsafe extern "C" fn(*mut Display, GlxFbConfig, GlxContext, c_int, *const c_int) -> GlxContext;
+
+/// The GLX functions we use
+struct Glx {
+    choose_fb_config:
+        unsafe extern "C" fn(*mut Display, c_int, *const c_int, *mut c_int) -> *mut GlxFbConfig,
+    get_fb_config_attrib:
+        unsafe extern "C" fn(*mut Display, GlxFbConfig, c_int, *mut c_int) -> c_int,
+    create_new_context:
+        unsafe extern "C" fn(*mut Display, GlxFbConfig, c_int, GlxContext, c_int) -> GlxContext,
+    destroy_context: unsafe extern "C" fn(*mut Display, GlxContext),
+    make_current: unsafe extern "C" fn(*mut Display, XID, GlxContext) -> c_int,
+    get_current_context: unsafe extern "C" fn() -> GlxContext,
+    swap_buffers: unsafe extern "C" fn(*mut Display, XID),
+    get_proc_address: unsafe extern "C" fn(*const u8) -> *const c_void,
+}
+
+impl Glx {
+    fn load() -> Result<Self, Error> {
+        let gl = open(&["libGL.so.1", "libGL.so"])?;
+        unsafe {
+            Ok(Self {
+                choose_fb_config: mem::transmute(symbol(gl, "glXChooseFBConfig")?),
+                get_fb_config_attrib: mem::transmute(symbol(gl, "glXGetFBConfigAttrib")?),
+                create_new_context: mem::transmute(symbol(gl, "glXCreateNewContext")?),
+                destroy_context: mem::transmute(symbol(gl, "glXDestroyContext")?),
+                make_current: mem::transmute(symbol(gl, "glXMakeCurrent")?),
+                get_current_context: mem::transmute(symbol(gl, "glXGetCurrentContext")?),
+                swap_buffers: mem::transmute(symbol(gl, "glXSwapBuffers")?),
+                get_proc_address: mem::transmute(symbol(gl, "glXGetProcAddressARB")?),
+            })
+        }
+    }
+
+    /// Look up a function through GLX, which is how extensions and core OpenGL functions are found
+    unsafe fn proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        (self.get_proc_address)(name.as_ptr() as *const u8)
+    }
+}
+
+/// The size of a window, which also tells us its visual
+unsafe fn attributes(x_connection: &XConnection, window: XID) -> XWindowAttributes {
+    let mut attributes: XWindowAttributes = mem::zeroed();
+    (x_connection.xlib.XGetWindowAttributes)(x_connection.display, window, &mut attributes);
+    attributes
+}
+
+/// Find a double buffered framebuffer config with the same visual as the root window, since a
+/// context can only draw to windows with a matching visual
+unsafe fn find_fb_config(
+    glx: &Glx,
+    x_connection: &XConnection,
+    screen: c_int,
+    visual_id: c_ulong,
+) -> Result<GlxFbConfig, Error> {
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    let attribs = [
+        GLX_DRAWABLE_TYPE, GLX_WINDOW_BIT,
+        GLX_RENDER_TYPE, GLX_RGBA_BIT,
+        GLX_DOUBLEBUFFER, 1,
+        0,
+    ];
+    let mut count = 0;
+    let configs =
+        (glx.choose_fb_config)(x_connection.display, screen, attribs.as_ptr(), &mut count);
+    ensure!(
+        !configs.is_null() && count > 0,
+        "No double buffered OpenGL configs are available"
+    );
+
+    let config = slice::from_raw_parts(configs, count as usize)
+        .iter()
+        .cloned()
+        .find(|&config| {
+            let mut id = 0;
+            (glx.get_fb_config_attrib)(x_connection.display, config, GLX_VISUAL_ID, &mut id);
+            id as c_ulong == visual_id
+        });
+    (x_connection.xlib.XFree)(configs as *mut c_void);
+
+    config.ok_or_else(|| {
+        format_err!("The root window's visual doesn't support double buffered OpenGL rendering")
+    })
+}
+
+/// Create a context, asking for a core profile first since Mesa only offers the OpenGL 3.1 the
+/// shaders need with one
+unsafe fn create_context(
+    glx: &Glx,
+    x_connection: &XConnection,
+    config: GlxFbConfig,
+) -> Result<GlxContext, Error> {
+    let create_context_attribs = glx.proc_address("glXCreateContextAttribsARB");
+    let mut context = ptr::null_mut();
+    if !create_context_attribs.is_null() {
+        let create_context_attribs: CreateContextAttribs = mem::transmute(create_context_attribs);
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let attribs = [
+            GLX_CONTEXT_MAJOR_VERSION_ARB, 3,
+            GLX_CONTEXT_MINOR_VERSION_ARB, 3,
+            GLX_CONTEXT_PROFILE_MASK_ARB, GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+            0,
+        ];
+        context = create_context_attribs(
+            x_connection.display,
+            config,
+            ptr::null_mut(),
+            1,
+            attribs.as_ptr(),
+        );
+    }
+    if context.is_null() {
+        context = (glx.create_new_context)(
+            x_connection.display,
+            config,
+            GLX_RGBA_TYPE,
+            ptr::null_mut(),
+            1,
+        );
+    }
+    if context.is_null() {
+        bail!("Could not create an OpenGL context for the root window");
+    }
+
+    Ok(context)
+}
+
+/// A GLX context drawing to the root window
+struct RootBackend {
+    /// The GLX functions
+    glx: Glx,
+    /// Our own connection to the X server
+    x_connection: XConnection,
+    /// The root window
+    root: XID,
+    /// The context
+    context: GlxContext,
+    /// Size of the root window in pixels, which changes when monitors do
+    dimensions: Cell<(u32, u32)>,
+}
+
+impl Drop for RootBackend {
+    fn drop(&mut self) {
+        unsafe {
+            (self.glx.make_current)(self.x_connection.display, 0, ptr::null_mut());
+            (self.glx.destroy_context)(self.x_connection.display, self.context);
+        }
+    }
+}
+
+unsafe impl Backend for RootBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        unsafe {
+            (self.glx.swap_buffers)(self.x_connection.display, self.root);
+
+            let attributes = attributes(&self.x_connection, self.root);
+            let dimensions = (attributes.width as u32, attributes.height as u32);
+            if dimensions != self.dimensions.get() {
+                info!("Root window resized to {}x{}", dimensions.0, dimensions.1);
+                self.dimensions.set(dimensions);
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.glx.proc_address(symbol)
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        self.dimensions.get()
+    }
+
+    fn is_current(&self) -> bool {
+        unsafe { (self.glx.get_current_context)() == self.context }
+    }
+
+    unsafe fn make_current(&self) {
+        (self.glx.make_current)(self.x_connection.display, self.root, self.context);
+    }
+}
+
+/// A facade for the root window
+pub struct RootFacade {
+    /// The glium context
+    context: Rc<Context>,
+}
+
+impl RootFacade {
+    /// Connect to the X server and create a context for its root window
+    pub fn new() -> Result<Self, Error> {
+        let glx = Glx::load()?;
+        let x_connection =
+            XConnection::new(None).map_err(|e| format_err!("Could not connect to X: {:?}", e))?;
+
+        unsafe {
+            let screen = (x_connection.xlib.XDefaultScreen)(x_connection.display);
+            let root = (x_connection.xlib.XRootWindow)(x_connection.display, screen);
+            let attributes = attributes(&x_connection, root);
+            let visual_id = (x_connection.xlib.XVisualIDFromVisual)(attributes.visual);
+
+            let config = find_fb_config(&glx, &x_connection, screen, visual_id)?;
+            let context = create_context(&glx, &x_connection, config)?;
+            info!(
+                "Drawing on the root window ({}x{})",
+                attributes.width, attributes.height
+            );
+
+            let backend = RootBackend {
+                glx,
+                x_connection,
+                root,
+                context,
+                dimensions: Cell::new((attributes.width as u32, attributes.height as u32)),
+            };
+            backend.make_current();
+            let context = Context::new(backend, true, DebugCallbackBehavior::default())?;
+
+            Ok(Self { context })
+        }
+    }
+}
+
+impl Facade for RootFacade {
+    fn get_context(&self) -> &Rc<Context> {
+        &self.context
+    }
+}
+
+/// Create a facade drawing to the root window
+pub fn new_facade() -> Result<Rc<dyn Facade>, Error> {
+    Ok(Rc::new(RootFacade::new()?))
+}
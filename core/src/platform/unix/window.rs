@@ -1,7 +1,13 @@
 //! Contains functions to apply Unix-specific window attributes and properties
 
-use log::info;
-use std::sync::Arc;
+use anyhow::Error;
+use log::{info, warn};
+use std::{
+    mem,
+    os::raw::{c_int, c_void},
+    ptr,
+    sync::Arc,
+};
 use winit::{
     os::unix::{
         x11::{
@@ -15,7 +21,19 @@ use winit::{
     Window,
 };
 
-use crate::config::Config;
+use super::dl::{open, symbol};
+use crate::{config::Config, util::format_error};
+
+/// `ShapeInput`, the kind of shape that decides where a window gets input
+const SHAPE_INPUT: c_int = 2;
+/// `ShapeSet`, which replaces the shape
+const SHAPE_SET: c_int = 0;
+/// `Unsorted`, the order of the rectangles we give
+const UNSORTED: c_int = 0;
+
+/// `XShapeCombineRectangles`, from libXext
+type ShapeCombineRectangles =
+    unsafe extern "C" fn(*mut Display, XID, c_int, c_int, c_int, *mut c_void, c_int, c_int, c_int);
 
 /// Sets the override-redirect flag of a window
 unsafe fn override_redirect(
@@ -73,6 +91,25 @@ unsafe fn desktop_window(x_connection: &Arc<XConnection>, x_display: *mut Displa
     );
 }
 
+/// Gives a window an empty input shape, so clicks and other input go through it
+unsafe fn click_through(x_display: *mut Display, x_window: XID) -> Result<(), Error> {
+    let xext = open(&["libXext.so.6", "libXext.so"])?;
+    let shape_combine_rectangles: ShapeCombineRectangles =
+        mem::transmute(symbol(xext, "XShapeCombineRectangles")?);
+    shape_combine_rectangles(
+        x_display,
+        x_window,
+        SHAPE_INPUT,
+        0,
+        0,
+        ptr::null_mut(),
+        0,
+        SHAPE_SET,
+        UNSORTED,
+    );
+    Ok(())
+}
+
 /// Unmaps a window and maps it again
 unsafe fn remap_window(x_connection: &Arc<XConnection>, x_display: *mut Display, x_window: XID) {
     // Remap the window so the override-redirect attribute can take effect
@@ -86,10 +123,24 @@ unsafe fn remap_window(x_connection: &Arc<XConnection>, x_display: *mut Display,
 
 /// Initializes an X11 window according to a configuration
 pub fn init(window: &Window, config: &Config) {
-    // Get info about our connection, display, and window
-    let x_connection = window.get_xlib_xconnection().unwrap();
-    let x_display = window.get_xlib_display().unwrap() as *mut Display;
-    let x_window = window.get_xlib_window().unwrap() as XID;
+    // Get info about our connection, display, and window. On Wayland there's none of that.
+    let (x_connection, x_display, x_window) = match (
+        window.get_xlib_xconnection(),
+        window.get_xlib_display(),
+        window.get_xlib_window(),
+    ) {
+        (Some(x_connection), Some(x_display), Some(x_window)) => {
+            (x_connection, x_display as *mut Display, x_window as XID)
+        }
+        _ => {
+            let platform_config = &config.platform_config;
+            if platform_config.override_redirect || platform_config.desktop || config.click_through
+            {
+                warn!("Window attributes only work on X11, use layer_shell on Wayland instead");
+            }
+            return;
+        }
+    };
 
     unsafe {
         if config.platform_config.override_redirect {
@@ -112,5 +163,15 @@ pub fn init(window: &Window, config: &Config) {
             info!("Using desktop window type");
             desktop_window(&x_connection, x_display, x_window);
         }
+
+        if config.click_through {
+            info!("Letting input through the window");
+            if let Err(e) = click_through(x_display, x_window) {
+                warn!(
+                    "Could not make the window click-through: {}",
+                    format_error(&e)
+                );
+            }
+        }
     }
 }
@@ -1,7 +1,7 @@
 use clap::{App, ArgMatches};
 use serde_derive::Deserialize;
 
-use config::Config;
+use crate::Config;
 
 /// Platform-specific configuration
 /// Be careful with this, because specifying an unknown field will not cause an error
@@ -14,8 +14,9 @@ impl PlatformSpecificConfig {
         Config::build_cli()
     }
 
-    /// Parses the configuration from command-line arguments
-    pub fn from_args(args: &ArgMatches) -> Self {
-        Self {}
-    }
+    /// Merges options given on the command line into the configuration
+    pub fn merge_args(&mut self, _args: &ArgMatches<'_>) {}
+
+    /// Sets the options needed for the window to act as a desktop wallpaper
+    pub fn set_wallpaper(&mut self) {}
 }
@@ -0,0 +1,47 @@
+//! Contains functions to apply Windows-specific window attributes
+
+use log::info;
+use std::os::raw::c_void;
+use winit::{os::windows::WindowExt, Window};
+
+use crate::config::Config;
+
+/// Index of the extended window style
+const GWL_EXSTYLE: i32 = -20;
+/// Layered windows can be see-through, and are needed for `WS_EX_TRANSPARENT`
+const WS_EX_LAYERED: u32 = 0x0008_0000;
+/// Lets input go through to the windows below
+const WS_EX_TRANSPARENT: u32 = 0x0000_0020;
+/// Tells `SetLayeredWindowAttributes` to use the alpha we give it
+const LWA_ALPHA: u32 = 0x0000_0002;
+
+#[link(name = "user32")]
+extern "system" {
+    fn GetWindowLongW(hwnd: *mut c_void, index: i32) -> i32;
+    fn SetWindowLongW(hwnd: *mut c_void, index: i32, new_long: i32) -> i32;
+    fn SetLayeredWindowAttributes(hwnd: *mut c_void, key: u32, alpha: u8, flags: u32) -> i32;
+}
+
+/// Makes a window let clicks and other input through to the windows below it
+unsafe fn click_through(hwnd: *mut c_void) {
+    let style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+    SetWindowLongW(
+        hwnd,
+        GWL_EXSTYLE,
+        (style | WS_EX_LAYERED | WS_EX_TRANSPARENT) as i32,
+    );
+    // Layered windows are invisible until they're given an opacity
+    SetLayeredWindowAttributes(hwnd, 0, 255, LWA_ALPHA);
+}
+
+/// Initializes a window according to a configuration
+pub fn init(window: &Window, config: &Config) {
+    let hwnd = window.get_hwnd() as *mut c_void;
+
+    unsafe {
+        if config.click_through {
+            info!("Letting input through the window");
+            click_through(hwnd);
+        }
+    }
+}
@@ -0,0 +1,591 @@
+//! Node types provided by plugins, which are dynamic libraries listed in the `plugins` section of
+//! a config.
+//!
+//! A plugin is a crate built as a `cdylib` that implements [`Node`] for each of its node types and
+//! exports them with [`export_plugin!`](../macro.export_plugin.html):
+//!
+//! ```ignore
+//! use yotredash_core::{export_plugin, plugin::{Node, Value}};
+//!
+//! struct Double;
+//!
+//! impl Node for Double {
+//!     const NAME: &'static str = "double";
+//!
+//!     fn new(_settings: &str) -> Result<Self, String> {
+//!         Ok(Double)
+//!     }
+//!
+//!     fn render(&mut self, inputs: &[(&str, Value)]) -> Result<Vec<(String, Value)>, String> {
+//!         match inputs.first() {
+//!             Some((_, Value::Float(value))) => Ok(vec![("value".to_string(), Value::Float(value * 2.0))]),
+//!             _ => Err("expected a float".to_string()),
+//!         }
+//!     }
+//! }
+//!
+//! export_plugin!(Double);
+//! ```
+//!
+//! Plugin nodes work with values, not textures, and only talk to yotredash through the small C ABI
+//! in this module, so a plugin keeps working with any yotredash built for the same
+//! [`ABI_VERSION`], whatever compiler either was built with.
+
+use anyhow::{bail, ensure, format_err, Context, Error};
+use std::{
+    collections::HashMap,
+    os::raw::c_void,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr, slice, str, thread,
+};
+
+/// Version of the plugin ABI, which changes whenever the types in this module do
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports, which returns a [`PluginInfo`]
+pub const ENTRY_POINT: &str = "yotredash_plugin";
+
+/// The kinds of values passed to and from plugins, as stored in [`RawValue::kind`]
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// A single float
+    Float = 0,
+    /// Two floats
+    Float2 = 1,
+    /// Four floats
+    Float4 = 2,
+    /// Four floats (RGBA)
+    Color = 3,
+    /// Sixteen floats, column by column
+    Mat4 = 4,
+    /// Any number of floats
+    FloatArray = 5,
+    /// UTF-8 text
+    Text = 6,
+}
+
+/// A value as it crosses the ABI. The pointers are only valid for the duration of the call they're
+/// passed to.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawValue {
+    /// A [`ValueKind`]
+    pub kind: u32,
+    /// The floats of any kind of value except text
+    pub floats: *const f32,
+    /// Number of floats
+    pub floats_len: usize,
+    /// The bytes of a text value
+    pub text: *const u8,
+    /// Number of bytes of text
+    pub text_len: usize,
+}
+
+/// A value along with the name of the input or output it belongs to
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawNamedValue {
+    /// UTF-8 name
+    pub name: *const u8,
+    /// Number of bytes of the name
+    pub name_len: usize,
+    /// The value
+    pub value: RawValue,
+}
+
+/// Called by a plugin for each output it renders
+pub type EmitFn = unsafe extern "C" fn(context: *mut c_void, output: *const RawNamedValue);
+
+/// Called by a plugin with a UTF-8 message when something fails
+pub type ErrorFn = unsafe extern "C" fn(context: *mut c_void, message: *const u8, len: usize);
+
+/// The functions behind one node type of a plugin
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct NodeType {
+    /// UTF-8 name of the node type, as used in `plugin:` fields of configs
+    pub name: *const u8,
+    /// Number of bytes of the name
+    pub name_len: usize,
+    /// Creates a node from its settings as JSON, returning null after calling `error` if it fails
+    pub create: unsafe extern "C" fn(
+        settings: *const u8,
+        settings_len: usize,
+        error: ErrorFn,
+        context: *mut c_void,
+    ) -> *mut c_void,
+    /// Renders a node, calling `emit` for every output, or returns false after calling `error`
+    pub render: unsafe extern "C" fn(
+        node: *mut c_void,
+        inputs: *const RawNamedValue,
+        inputs_len: usize,
+        emit: EmitFn,
+        error: ErrorFn,
+        context: *mut c_void,
+    ) -> bool,
+    /// Returns whether a node's outputs could change while its inputs stay the same
+    pub changed: unsafe extern "C" fn(node: *mut c_void) -> bool,
+    /// Frees a node
+    pub destroy: unsafe extern "C" fn(node: *mut c_void),
+}
+
+/// What a plugin's entry point returns
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginInfo {
+    /// The [`ABI_VERSION`] the plugin was built for
+    pub abi_version: u32,
+    /// The node types the plugin provides, which must live as long as the library is loaded
+    pub node_types: *const NodeType,
+    /// Number of node types
+    pub node_types_len: usize,
+}
+
+/// A value passed to or from a plugin node
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A color (RGBA)
+    Color([f32; 4]),
+    /// An f32
+    Float(f32),
+    /// An array of 2 f32 values
+    Float2([f32; 2]),
+    /// An array of 4 f32 values
+    Float4([f32; 4]),
+    /// A 4x4 matrix, as an array of columns
+    Mat4([[f32; 4]; 4]),
+    /// Any number of f32 values
+    FloatArray(Vec<f32>),
+    /// A string
+    Text(String),
+}
+
+impl Value {
+    /// Borrows the value as a [`RawValue`], which is valid as long as the value is
+    pub fn to_raw(&self) -> RawValue {
+        let (kind, floats): (ValueKind, &[f32]) = match *self {
+            Value::Color(ref color) => (ValueKind::Color, &color[..]),
+            Value::Float(ref value) => (ValueKind::Float, slice::from_ref(value)),
+            Value::Float2(ref value) => (ValueKind::Float2, &value[..]),
+            Value::Float4(ref value) => (ValueKind::Float4, &value[..]),
+            Value::Mat4(ref matrix) => (ValueKind::Mat4, unsafe {
+                slice::from_raw_parts(matrix.as_ptr() as *const f32, 16)
+            }),
+            Value::FloatArray(ref values) => (ValueKind::FloatArray, &values[..]),
+            Value::Text(_) => (ValueKind::Text, &[][..]),
+        };
+        let text: &[u8] = match *self {
+            Value::Text(ref text) => text.as_bytes(),
+            _ => &[],
+        };
+
+        RawValue {
+            kind: kind as u32,
+            floats: floats.as_ptr(),
+            floats_len: floats.len(),
+            text: text.as_ptr(),
+            text_len: text.len(),
+        }
+    }
+
+    /// Copies a value out of a [`RawValue`]
+    ///
+    /// # Safety
+    ///
+    /// The pointers in `raw` must be valid for their lengths, or null with a length of 0.
+    pub unsafe fn from_raw(raw: &RawValue) -> Result<Self, String> {
+        let floats = raw_slice(raw.floats, raw.floats_len);
+        let fixed = |len: usize| {
+            if floats.len() == len {
+                Ok(floats)
+            } else {
+                Err(format!("expected {} floats, got {}", len, floats.len()))
+            }
+        };
+        let mut two = [0.0; 2];
+        let mut four = [0.0; 4];
+
+        Ok(match raw.kind {
+            kind if kind == ValueKind::Float as u32 => Value::Float(fixed(1)?[0]),
+            kind if kind == ValueKind::Float2 as u32 => {
+                two.copy_from_slice(fixed(2)?);
+                Value::Float2(two)
+            }
+            kind if kind == ValueKind::Float4 as u32 => {
+                four.copy_from_slice(fixed(4)?);
+                Value::Float4(four)
+            }
+            kind if kind == ValueKind::Color as u32 => {
+                four.copy_from_slice(fixed(4)?);
+                Value::Color(four)
+            }
+            kind if kind == ValueKind::Mat4 as u32 => {
+                let floats = fixed(16)?;
+                let mut matrix = [[0.0; 4]; 4];
+                for (column, values) in matrix.iter_mut().zip(floats.chunks(4)) {
+                    column.copy_from_slice(values);
+                }
+                Value::Mat4(matrix)
+            }
+            kind if kind == ValueKind::FloatArray as u32 => Value::FloatArray(floats.to_vec()),
+            kind if kind == ValueKind::Text as u32 => {
+                Value::Text(str_from_raw(raw.text, raw.text_len)?.to_string())
+            }
+            kind => return Err(format!("unknown value kind {}", kind)),
+        })
+    }
+}
+
+/// A node type implemented by a plugin. Unlike the built-in nodes, plugin nodes only see values,
+/// not textures.
+pub trait Node: Sized {
+    /// The name configs use for this node type
+    const NAME: &'static str;
+
+    /// Creates a node from the `settings` of its config, as JSON
+    fn new(settings: &str) -> Result<Self, String>;
+
+    /// Renders the node from its named inputs, in the order they're declared in the config,
+    /// returning every declared output
+    fn render(&mut self, inputs: &[(&str, Value)]) -> Result<Vec<(String, Value)>, String>;
+
+    /// Returns whether the outputs could be different from the last frame's even if the inputs
+    /// are the same
+    fn changed(&mut self) -> bool {
+        true
+    }
+}
+
+/// Makes a slice from a pointer and length that crossed the ABI, allowing null when it's empty
+unsafe fn raw_slice<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Makes a string from a pointer and length that crossed the ABI
+unsafe fn str_from_raw<'a>(data: *const u8, len: usize) -> Result<&'a str, String> {
+    str::from_utf8(raw_slice(data, len)).map_err(|error| error.to_string())
+}
+
+/// Turns a panic into an error, so that it doesn't unwind across the ABI
+fn catch<T>(result: thread::Result<Result<T, String>>) -> Result<T, String> {
+    result.unwrap_or_else(|payload| {
+        Err(match payload.downcast_ref::<&str>() {
+            Some(message) => format!("panicked: {}", message),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => format!("panicked: {}", message),
+                None => "panicked".to_string(),
+            },
+        })
+    })
+}
+
+unsafe extern "C" fn create_shim<T: Node>(
+    settings: *const u8,
+    settings_len: usize,
+    error: ErrorFn,
+    context: *mut c_void,
+) -> *mut c_void {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        T::new(str_from_raw(settings, settings_len)?)
+    }));
+    match catch(result) {
+        Ok(node) => Box::into_raw(Box::new(node)) as *mut c_void,
+        Err(message) => {
+            error(context, message.as_ptr(), message.len());
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe extern "C" fn render_shim<T: Node>(
+    node: *mut c_void,
+    inputs: *const RawNamedValue,
+    inputs_len: usize,
+    emit: EmitFn,
+    error: ErrorFn,
+    context: *mut c_void,
+) -> bool {
+    let node = &mut *(node as *mut T);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut values = Vec::with_capacity(inputs_len);
+        for input in raw_slice(inputs, inputs_len) {
+            values.push((
+                str_from_raw(input.name, input.name_len)?,
+                Value::from_raw(&input.value)?,
+            ));
+        }
+        node.render(&values)
+    }));
+    match catch(result) {
+        Ok(outputs) => {
+            for (name, value) in &outputs {
+                let output = RawNamedValue {
+                    name: name.as_ptr(),
+                    name_len: name.len(),
+                    value: value.to_raw(),
+                };
+                emit(context, &output);
+            }
+            true
+        }
+        Err(message) => {
+            error(context, message.as_ptr(), message.len());
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn changed_shim<T: Node>(node: *mut c_void) -> bool {
+    let node = &mut *(node as *mut T);
+    panic::catch_unwind(AssertUnwindSafe(|| node.changed())).unwrap_or(true)
+}
+
+unsafe extern "C" fn destroy_shim<T: Node>(node: *mut c_void) {
+    let node = Box::from_raw(node as *mut T);
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(node)));
+}
+
+/// Describes a [`Node`] implementation for the ABI. Used by
+/// [`export_plugin!`](../macro.export_plugin.html).
+pub const fn node_type<T: Node>() -> NodeType {
+    NodeType {
+        name: T::NAME.as_ptr(),
+        name_len: T::NAME.len(),
+        create: create_shim::<T>,
+        render: render_shim::<T>,
+        changed: changed_shim::<T>,
+        destroy: destroy_shim::<T>,
+    }
+}
+
+/// Exports the entry point of a plugin, providing the given [`Node`] types
+///
+/// [`Node`]: plugin/trait.Node.html
+#[macro_export]
+macro_rules! export_plugin {
+    ($($node:ty),+ $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn yotredash_plugin() -> $crate::plugin::PluginInfo {
+            const NODE_TYPES: &[$crate::plugin::NodeType] =
+                &[$($crate::plugin::node_type::<$node>()),+];
+            $crate::plugin::PluginInfo {
+                abi_version: $crate::plugin::ABI_VERSION,
+                node_types: NODE_TYPES.as_ptr(),
+                node_types_len: NODE_TYPES.len(),
+            }
+        }
+    };
+}
+
+/// A node type provided by a loaded plugin
+pub struct PluginType {
+    /// The plugin that provides it
+    pub path: PathBuf,
+    functions: NodeType,
+}
+
+/// Loads plugins, returning the node types they provide by name
+pub fn load(paths: &[PathBuf]) -> Result<HashMap<String, PluginType>, Error> {
+    let mut types: HashMap<String, PluginType> = HashMap::new();
+
+    for path in paths {
+        let info = load_library(path)
+            .with_context(|| format!("Could not load plugin `{}`", path.display()))?;
+        ensure!(
+            info.abi_version == ABI_VERSION,
+            "Plugin `{}` was built for plugin ABI version {}, but this is version {}",
+            path.display(),
+            info.abi_version,
+            ABI_VERSION
+        );
+
+        for functions in unsafe { raw_slice(info.node_types, info.node_types_len) } {
+            let name = unsafe { str_from_raw(functions.name, functions.name_len) }
+                .map_err(|error| format_err!("Plugin `{}`: {}", path.display(), error))?;
+            if let Some(other) = types.get(name) {
+                bail!(
+                    "Plugins `{}` and `{}` both provide the node type `{}`",
+                    other.path.display(),
+                    path.display(),
+                    name
+                );
+            }
+            types.insert(
+                name.to_string(),
+                PluginType {
+                    path: path.clone(),
+                    functions: *functions,
+                },
+            );
+        }
+    }
+
+    Ok(types)
+}
+
+/// Loads a library and calls its entry point. Libraries are never unloaded, since the node types
+/// point into them.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn load_library(path: &Path) -> Result<PluginInfo, Error> {
+    use crate::platform::dl;
+    use std::mem;
+
+    // dlopen searches the library path for names without a slash
+    let path = if path.parent() == Some(Path::new("")) {
+        Path::new(".").join(path)
+    } else {
+        path.to_path_buf()
+    };
+    let name = path
+        .to_str()
+        .ok_or_else(|| format_err!("Path is not valid UTF-8"))?;
+
+    let entry = dl::symbol(dl::open(&[name])?, ENTRY_POINT)?;
+    let entry: extern "C" fn() -> PluginInfo = unsafe { mem::transmute(entry) };
+    Ok(entry())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn load_library(_path: &Path) -> Result<PluginInfo, Error> {
+    bail!("Plugins can only be loaded on Linux and BSD for now");
+}
+
+/// Collects what a plugin passes to the callbacks during a call
+#[derive(Default)]
+struct Sink {
+    outputs: Vec<Result<(String, Value), String>>,
+    error: Option<String>,
+}
+
+unsafe extern "C" fn emit(context: *mut c_void, output: *const RawNamedValue) {
+    let sink = &mut *(context as *mut Sink);
+    let output = &*output;
+    sink.outputs.push(
+        str_from_raw(output.name, output.name_len)
+            .and_then(|name| Ok((name.to_string(), Value::from_raw(&output.value)?))),
+    );
+}
+
+unsafe extern "C" fn error(context: *mut c_void, message: *const u8, len: usize) {
+    let sink = &mut *(context as *mut Sink);
+    sink.error = Some(String::from_utf8_lossy(raw_slice(message, len)).into_owned());
+}
+
+/// A node created by a plugin, which is destroyed when this is dropped
+pub struct Instance {
+    functions: NodeType,
+    node: *mut c_void,
+}
+
+impl Instance {
+    /// Creates a node of a plugin's node type, from its settings as JSON
+    pub fn new(plugin_type: &PluginType, settings: &str) -> Result<Self, Error> {
+        let functions = plugin_type.functions;
+        let mut sink = Sink::default();
+        let node = unsafe {
+            (functions.create)(
+                settings.as_ptr(),
+                settings.len(),
+                error,
+                &mut sink as *mut Sink as *mut c_void,
+            )
+        };
+        if node.is_null() {
+            bail!(
+                "Plugin `{}` failed to create a node: {}",
+                plugin_type.path.display(),
+                sink.error.unwrap_or_else(|| "unknown error".to_string())
+            );
+        }
+        Ok(Self { functions, node })
+    }
+
+    /// Renders the node, returning its outputs
+    pub fn render(&mut self, inputs: &[(String, Value)]) -> Result<Vec<(String, Value)>, Error> {
+        let raw_inputs: Vec<RawNamedValue> = inputs
+            .iter()
+            .map(|(name, value)| RawNamedValue {
+                name: name.as_ptr(),
+                name_len: name.len(),
+                value: value.to_raw(),
+            })
+            .collect();
+        let mut sink = Sink::default();
+        let rendered = unsafe {
+            (self.functions.render)(
+                self.node,
+                raw_inputs.as_ptr(),
+                raw_inputs.len(),
+                emit,
+                error,
+                &mut sink as *mut Sink as *mut c_void,
+            )
+        };
+        if !rendered {
+            bail!(
+                "Plugin node failed to render: {}",
+                sink.error.unwrap_or_else(|| "unknown error".to_string())
+            );
+        }
+        sink.outputs
+            .into_iter()
+            .map(|output| output.map_err(|error| format_err!("Invalid plugin output: {}", error)))
+            .collect()
+    }
+
+    /// Returns whether the node's outputs could change while its inputs stay the same
+    pub fn changed(&mut self) -> bool {
+        unsafe { (self.functions.changed)(self.node) }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        unsafe { (self.functions.destroy)(self.node) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+
+    #[test]
+    fn values_round_trip() {
+        let values = vec![
+            Value::Float(1.5),
+            Value::Float2([1.0, 2.0]),
+            Value::Float4([1.0, 2.0, 3.0, 4.0]),
+            Value::Color([0.1, 0.2, 0.3, 1.0]),
+            Value::Mat4([
+                [1.0, 2.0, 3.0, 4.0],
+                [5.0, 6.0, 7.0, 8.0],
+                [9.0, 10.0, 11.0, 12.0],
+                [13.0, 14.0, 15.0, 16.0],
+            ]),
+            Value::FloatArray(vec![]),
+            Value::FloatArray(vec![1.0, 2.0, 3.0]),
+            Value::Text("héllo".to_string()),
+        ];
+        for value in values {
+            assert_eq!(
+                unsafe { Value::from_raw(&value.to_raw()) },
+                Ok(value.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        let mut raw = Value::Float4([0.0; 4]).to_raw();
+        raw.floats_len = 3;
+        assert!(unsafe { Value::from_raw(&raw) }.is_err());
+        raw.kind = 42;
+        assert!(unsafe { Value::from_raw(&raw) }.is_err());
+    }
+}
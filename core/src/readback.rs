@@ -0,0 +1,140 @@
+//! Hands frames read back by readback nodes to code running on the CPU, like something piping them
+//! to ffmpeg, v4l2loopback or an LED matrix. Consumers subscribe to a node by name, so they keep
+//! getting frames when the config is reloaded and the node is recreated.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+};
+
+use crate::renderer::Frame;
+
+/// Frames a channel holds before new ones are dropped, so a slow consumer can't hold up rendering
+/// or use up memory
+const CHANNEL_CAPACITY: usize = 2;
+
+/// Somewhere frames are sent
+enum Sink {
+    /// A channel, which is dropped once its receiver is
+    Channel(SyncSender<Arc<Frame>>),
+    /// A function called on the render thread
+    Callback(Box<dyn FnMut(&Frame)>),
+}
+
+thread_local! {
+    /// The sinks for each readback node, by node name
+    static SINKS: RefCell<HashMap<String, Vec<Sink>>> = RefCell::new(HashMap::new());
+}
+
+/// Returns a channel receiving the frames read back by a node. If the receiver falls behind, frames
+/// are dropped rather than queued.
+pub fn subscribe(node: &str) -> Receiver<Arc<Frame>> {
+    let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    add_sink(node, Sink::Channel(sender));
+    receiver
+}
+
+/// Calls a function with each frame read back by a node. It's called on the render thread, so it
+/// should hand the frame off rather than doing anything slow.
+pub fn on_frame<F>(node: &str, callback: F)
+where
+    F: FnMut(&Frame) + 'static,
+{
+    add_sink(node, Sink::Callback(Box::new(callback)));
+}
+
+/// Removes every channel and callback for a node
+pub fn unsubscribe(node: &str) {
+    SINKS.with(|sinks| sinks.borrow_mut().remove(node));
+}
+
+/// Returns whether anything wants the frames of a node, so it can skip reading them back otherwise
+pub fn has_subscribers(node: &str) -> bool {
+    SINKS.with(|sinks| {
+        sinks
+            .borrow()
+            .get(node)
+            .map_or(false, |sinks| !sinks.is_empty())
+    })
+}
+
+fn add_sink(node: &str, sink: Sink) {
+    SINKS.with(|sinks| {
+        sinks
+            .borrow_mut()
+            .entry(node.to_string())
+            .or_insert_with(Vec::new)
+            .push(sink)
+    });
+}
+
+/// Sends a frame to everything subscribed to a node
+pub fn publish(node: &str, frame: Frame) {
+    // Callbacks could subscribe to nodes themselves, so take the sinks out while they run
+    let mut node_sinks = match SINKS.with(|sinks| sinks.borrow_mut().remove(node)) {
+        Some(node_sinks) => node_sinks,
+        None => return,
+    };
+
+    let frame = Arc::new(frame);
+    node_sinks.retain(|sink| match *sink {
+        Sink::Channel(ref sender) => match sender.try_send(Arc::clone(&frame)) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        },
+        Sink::Callback(_) => true,
+    });
+    for sink in &mut node_sinks {
+        if let Sink::Callback(ref mut callback) = *sink {
+            callback(&frame);
+        }
+    }
+
+    SINKS.with(|sinks| {
+        let mut sinks = sinks.borrow_mut();
+        let added = sinks.remove(node).unwrap_or_default();
+        node_sinks.extend(added);
+        if !node_sinks.is_empty() {
+            sinks.insert(node.to_string(), node_sinks);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_subscribers, on_frame, publish, subscribe};
+    use crate::renderer::Frame;
+    use std::{cell::Cell, rc::Rc};
+
+    fn frame(value: u8) -> Frame {
+        Frame {
+            width: 1,
+            height: 1,
+            pixels: vec![value; 4],
+        }
+    }
+
+    #[test]
+    fn drops_frames_for_slow_and_closed_receivers() {
+        let receiver = subscribe("test");
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        on_frame("test", move |_| counter.set(counter.get() + 1));
+
+        for value in 0..4 {
+            publish("test", frame(value));
+        }
+        let values: Vec<u8> = receiver.try_iter().map(|frame| frame.pixels[0]).collect();
+        assert_eq!(values, vec![0, 1]);
+        assert_eq!(calls.get(), 4);
+
+        drop(receiver);
+        publish("test", frame(4));
+        assert!(has_subscribers("test"));
+        assert!(!has_subscribers("other"));
+    }
+}
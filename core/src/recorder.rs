@@ -0,0 +1,146 @@
+//! Records the output to an animated GIF. Frames are encoded on a background thread as they come
+//! in, so the file is ready soon after recording stops.
+
+use anyhow::{bail, ensure, Context, Error};
+use gif::{self, Encoder, Repeat, SetParameter};
+use log::{error, info};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+};
+use time::{self, Tm};
+
+use crate::{config::recording::RecordingConfig, renderer::Frame, util::format_error};
+
+/// An animated GIF being recorded
+pub struct Recorder {
+    /// Where the recording is saved
+    path: PathBuf,
+    /// The recording configuration
+    config: RecordingConfig,
+    /// Sends frames to the encoder thread
+    sender: Option<Sender<Frame>>,
+    /// The encoder thread
+    thread: Option<JoinHandle<Result<(), Error>>>,
+    /// When the last frame was recorded
+    last_frame: Option<Tm>,
+    /// Number of frames recorded so far
+    frames: u32,
+}
+
+/// Encodes frames as they're received, until the channel is closed
+fn encode(path: &Path, frames: mpsc::Receiver<Frame>, delay: u16) -> Result<(), Error> {
+    let mut encoder: Option<Encoder<File>> = None;
+
+    for mut frame in frames {
+        if encoder.is_none() {
+            let file = File::create(path).context("Could not create recording file")?;
+            let mut new_encoder = Encoder::new(file, frame.width as u16, frame.height as u16, &[])?;
+            new_encoder.set(Repeat::Infinite)?;
+            encoder = Some(new_encoder);
+        }
+
+        // GIF transparency is all or nothing, so ignore the alpha channel
+        for pixel in frame.pixels.chunks_mut(4) {
+            pixel[3] = 255;
+        }
+
+        let mut gif_frame =
+            gif::Frame::from_rgba(frame.width as u16, frame.height as u16, &mut frame.pixels);
+        gif_frame.delay = delay;
+        encoder.as_mut().unwrap().write_frame(&gif_frame)?;
+    }
+
+    ensure!(encoder.is_some(), "No frames were recorded");
+    Ok(())
+}
+
+impl Recorder {
+    /// Start recording to a file
+    pub fn start(path: PathBuf, config: RecordingConfig) -> Result<Self, Error> {
+        ensure!(
+            config.framerate > 0.0,
+            "Recording framerate must be greater than zero"
+        );
+        ensure!(
+            config.scale > 0.0,
+            "Recording scale must be greater than zero"
+        );
+
+        // GIF frame delays are in hundredths of a second
+        let delay = (100.0 / config.framerate).round().max(1.0) as u16;
+
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.clone();
+        let thread = thread::spawn(move || encode(&thread_path, receiver, delay));
+
+        info!("Recording to {}", path.to_string_lossy());
+        Ok(Self {
+            path,
+            config,
+            sender: Some(sender),
+            thread: Some(thread),
+            last_frame: None,
+            frames: 0,
+        })
+    }
+
+    /// The size of the recording relative to the window
+    pub fn scale(&self) -> f32 {
+        self.config.scale
+    }
+
+    /// Returns whether it's time to record another frame
+    pub fn wants_frame(&self) -> bool {
+        match self.last_frame {
+            Some(last_frame) => {
+                let elapsed = (time::now() - last_frame).num_milliseconds() as f32 / 1_000.0;
+                elapsed >= 1.0 / self.config.framerate
+            }
+            None => true,
+        }
+    }
+
+    /// Returns whether the recording has reached its maximum duration
+    pub fn is_full(&self) -> bool {
+        self.frames as f32 >= self.config.max_duration * self.config.framerate
+    }
+
+    /// Add a frame to the recording
+    pub fn add_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        self.last_frame = Some(time::now());
+        self.frames += 1;
+
+        let sent = match self.sender {
+            Some(ref sender) => sender.send(frame).is_ok(),
+            None => false,
+        };
+        if !sent {
+            bail!("Recording encoder stopped unexpectedly");
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    /// Finishes the recording, waiting for the remaining frames to be encoded
+    fn drop(&mut self) {
+        // Closing the channel tells the encoder there are no more frames
+        self.sender.take();
+
+        if let Some(thread) = self.thread.take() {
+            match thread.join() {
+                Ok(Ok(())) => info!(
+                    "Saved recording of {} frames to {}",
+                    self.frames,
+                    self.path.to_string_lossy()
+                ),
+                Ok(Err(e)) => error!("{}", format_error(&e)),
+                Err(_) => error!("Recording encoder panicked"),
+            }
+        }
+    }
+}
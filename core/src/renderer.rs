@@ -0,0 +1,212 @@
+//! Traits every rendering backend implements, so the rest of the program can drive any of them.
+//! A backend creates its own window or headless surface, since what kind of context it needs
+//! decides how the window is made.
+
+use anyhow::{bail, Context, Error};
+use imgui::DrawData;
+use std::{
+    any::Any,
+    cell::Ref,
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+};
+use winit::{EventsLoop, Icon, Window, WindowId};
+
+use crate::{
+    config::{Config, PresentMode},
+    event::RendererEvent,
+    gui::FontTexture,
+    opengl::backend::OpenGLBackend,
+};
+
+/// Timing information for a single node
+#[derive(Clone, Debug, Default)]
+pub struct NodeTiming {
+    /// Name of the node
+    pub name: String,
+    /// Time spent in the node's render call on the CPU (milliseconds)
+    pub cpu_time: f32,
+    /// Time spent executing the node's draw calls on the GPU (milliseconds)
+    pub gpu_time: f32,
+    /// Memory used by the textures the node allocated, counting 4 bytes per pixel (bytes)
+    pub texture_memory: u64,
+}
+
+/// Timing information for a frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    /// Timings for each node, in render order (these lag a few frames behind)
+    pub nodes: Vec<NodeTiming>,
+    /// Time spent rendering the frame on the CPU (milliseconds)
+    pub cpu_time: f32,
+    /// Total time between the start of this frame and the last one (milliseconds)
+    pub frame_time: f32,
+    /// Memory used by every texture allocated for the graph as of the last frame, counting 4 bytes
+    /// per pixel (bytes)
+    pub texture_memory: u64,
+}
+
+/// An image of a rendered frame
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// RGBA pixels, 8 bits per channel, from top to bottom
+    pub pixels: Vec<u8>,
+}
+
+/// Renders a configured shader
+pub trait Renderer {
+    /// Do stuff like handle event queue, reload, etc
+    fn update(&mut self) -> Result<(), Error>;
+    /// Render frames with the clock stopped before the first frame is presented, so the first
+    /// real frames don't stutter
+    fn warm_up(&mut self, frames: u32) -> Result<(), Error>;
+    /// Render the current frame
+    fn render(&mut self) -> Result<(), Error>;
+    /// Read back the frame that was just rendered, scaled by a factor
+    fn read_frame(&self, scale: f32) -> Result<Frame, Error>;
+    /// Tells the renderer to swap buffers (only applicable to buffered renderers)
+    fn swap_buffers(&self) -> Result<(), Error>;
+    /// Enable or disable collecting GPU timings for each node
+    fn set_profiling(&mut self, enabled: bool);
+    /// Get timing information for the last rendered frame
+    fn stats(&self) -> &FrameStats;
+    /// Handle pending events, then render a single frame offscreen at the given size, returning
+    /// RGBA from top to bottom
+    fn render_still(&mut self, width: u32, height: u32) -> Result<Vec<f32>, Error>;
+    /// Returns the files the renderer was built from, which are watched for changes
+    fn files(&self) -> Vec<PathBuf>;
+    /// Returns an error the renderer recovered from since the last call, like a node the watchdog
+    /// disabled or a shader program that didn't build again, to show over the frame
+    fn take_error(&mut self) -> Option<Error>;
+    /// Build the shader programs that use a file again after it changed, without reloading the
+    /// graph: the old programs keep rendering until the new ones are built, and stay if they
+    /// don't build, with the error returned by `take_error`. Returns false if anything other than
+    /// shader programs uses the file, in which case the graph should be reloaded. Tweaks and
+    /// `auto_bind` uniforms aren't updated until the next reload.
+    fn recompile(&mut self, path: &Path) -> bool;
+    /// Carry over the state of the renderer this replaces after a reload, so the change doesn't
+    /// look like a restart. Renderers from other backends are ignored.
+    fn inherit_state(&mut self, old: &dyn Renderer);
+    /// Returns the renderer as `Any`, so a backend can find its own type in `inherit_state`
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Renders errors
+pub trait DebugRenderer {
+    /// Draw an error on the window
+    fn draw_error(&mut self, error: &Error) -> Result<(), Error>;
+    /// Draw an error in a corner of the current frame, for when a reload failed but the old
+    /// graph is still rendering
+    fn draw_error_overlay(&mut self, error: &Error) -> Result<(), Error>;
+    /// Keep a copy of the frame that was just rendered, to draw behind errors
+    fn save_frame(&mut self) -> Result<(), Error>;
+    /// Draw the performance HUD over the current frame
+    fn draw_hud(&mut self, stats: &FrameStats) -> Result<(), Error>;
+    /// Draw a one-line notice at the bottom of the current frame
+    fn draw_notice(&mut self, notice: &str) -> Result<(), Error>;
+    /// Draw a frame of widgets, like the tweak panel and the graph editor, over the current frame
+    fn draw_gui(&mut self, draw_data: &DrawData, fonts: &FontTexture) -> Result<(), Error>;
+    /// Keep showing the last good frame from the debug renderer being replaced. Debug renderers
+    /// from other backends are ignored.
+    fn inherit_frame(&mut self, old: &mut dyn DebugRenderer);
+    /// Returns the debug renderer as `Any`, so a backend can find its own type in
+    /// `inherit_frame`
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A window or headless surface, together with the context renderers draw to it with
+pub trait Backend {
+    /// The name of the backend, as written in the `renderer` field of configs
+    fn name(&self) -> &'static str;
+    /// The window, if there is one
+    fn window(&self) -> Option<Ref<'_, Window>>;
+    /// The size of the surface in pixels
+    fn dimensions(&self) -> (u32, u32);
+    /// How frames are presented
+    fn present_mode(&self) -> PresentMode;
+    /// Whether the context was lost, like when the GPU was reset after a node hung it. Nothing
+    /// drawn with a lost context shows up, so the backend has to be made again.
+    fn context_lost(&self) -> bool;
+    /// Make the window again with another present mode, returning the mode we actually got.
+    /// Without a window this does nothing.
+    fn rebuild(
+        &mut self,
+        config: &Config,
+        events_loop: &EventsLoop,
+        title: &str,
+        present_mode: PresentMode,
+    ) -> Result<PresentMode, Error>;
+    /// Create a renderer for a configuration, which receives events from `receiver`
+    fn new_renderer(
+        &self,
+        config: &Config,
+        receiver: Receiver<RendererEvent>,
+    ) -> Result<Box<dyn Renderer>, Error>;
+    /// Create a debug renderer, with text scaled by the DPI factor
+    fn new_debug_renderer(
+        &self,
+        high_contrast: bool,
+        hidpi_factor: f32,
+    ) -> Result<Box<dyn DebugRenderer>, Error>;
+
+    /// The ID of the window, if there is one
+    fn window_id(&self) -> Option<WindowId> {
+        self.window().map(|window| window.id())
+    }
+
+    /// The DPI factor of the window's monitor, or 1 if there's no window
+    fn hidpi_factor(&self) -> f32 {
+        self.window()
+            .map_or(1.0, |window| window.get_hidpi_factor() as f32)
+    }
+}
+
+/// Create the backend a configuration asks for. Headless backends don't need an events loop,
+/// since there may not be a display server to make one with.
+pub fn new_backend(
+    config: &Config,
+    events_loop: Option<&EventsLoop>,
+    title: &str,
+) -> Result<Box<dyn Backend>, Error> {
+    match config.renderer.as_ref() as &str {
+        "opengl" => Ok(Box::new(OpenGLBackend::new(config, events_loop, title)?)),
+        other => bail!("Renderer {} is not built in", other),
+    }
+}
+
+/// Load an image file to use as the window icon
+pub fn load_icon(path: &Path) -> Result<Icon, Error> {
+    let image = image::open(path)
+        .context("Could not open icon file")?
+        .to_rgba();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
+
+/// Returns whether there's a display server to open windows on. Creating an `EventsLoop` without
+/// one panics, so check this first.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn display_available() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).map_or(false, |value| !value.is_empty()))
+}
+
+/// Returns whether there's a display server to open windows on
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn display_available() -> bool {
+    true
+}
+
+/// Creates an `EventsLoop` if there's a display server to connect to
+pub fn events_loop() -> Option<EventsLoop> {
+    if display_available() {
+        Some(EventsLoop::new())
+    } else {
+        None
+    }
+}
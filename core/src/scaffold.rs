@@ -0,0 +1,57 @@
+//! Generates a starter project, so new users have a working node graph to build on instead of
+//! writing one from scratch
+
+use anyhow::{format_err, Context, Error};
+use log::info;
+use std::{
+    fs::{self, OpenOptions},
+    io::{prelude::*, ErrorKind},
+    path::Path,
+};
+
+/// The files in a new project, as (name, contents)
+const FILES: &[(&str, &str)] = &[
+    ("config.yml", include_str!("scaffold/config.yml")),
+    ("shader.frag", include_str!("scaffold/shader.frag")),
+];
+
+/// Creates a new project in a directory, which is created if it doesn't exist. Existing files are
+/// never overwritten.
+pub fn new_project(dir: &Path) -> Result<(), Error> {
+    fs::create_dir_all(dir).context("Could not create project directory")?;
+
+    for (name, contents) in FILES {
+        let path = dir.join(name);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(format_err!("{} already exists", path.to_string_lossy()));
+            }
+            Err(e) => return Err(e).context("Could not create project file"),
+        };
+        file.write_all(contents.as_bytes())
+            .context("Could not write project file")?;
+        info!("Created {}", path.to_string_lossy());
+    }
+
+    println!(
+        "Created a new project in {}\nRun it with: yotredash --config {}",
+        dir.to_string_lossy(),
+        dir.join("config.yml").to_string_lossy()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FILES;
+    use crate::config::{validation::validate, Config};
+
+    #[test]
+    fn config_is_valid() {
+        let source = FILES[0].1;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert!(validate(&config, source).is_ok());
+    }
+}
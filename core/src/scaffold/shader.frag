@@ -0,0 +1,11 @@
+#version 140
+
+out vec4 color;
+
+uniform vec2 resolution;
+uniform float time;
+
+void main() {
+    vec2 position = gl_FragCoord.xy / resolution;
+    color = vec4(0.5 + 0.5 * cos(time + position.xyx + vec3(0.0, 2.0, 4.0)), 1.0);
+}
@@ -0,0 +1,47 @@
+//! Renders a single frame of a config to an image and exits, so shaders can be checked against
+//! known-good images in scripts
+
+use anyhow::{format_err, Error};
+use log::info;
+use std::{path::Path, sync::mpsc};
+
+use crate::{
+    capture::{self, CaptureMetadata},
+    config::{capture::CaptureFormat, Config},
+    event::{RendererEvent, TimeEvent},
+    renderer,
+};
+
+/// Renders the frame at `time` headless, at the configured size, and saves it in the format given
+/// by the output's extension. Feedback nodes only see a single frame, so they start from black.
+pub fn screenshot(config_path: &Path, output: &Path, time: f32) -> Result<(), Error> {
+    let format = output
+        .extension()
+        .and_then(|extension| CaptureFormat::from_extension(&extension.to_string_lossy()))
+        .ok_or_else(|| {
+            format_err!(
+                "Unknown image format for {}, use .png, .jpg or .exr",
+                output.display()
+            )
+        })?;
+
+    let mut config = Config::parse(config_path)?;
+    config.headless = true;
+    let (width, height) = (config.width, config.height);
+
+    let events_loop = renderer::events_loop();
+    let backend = renderer::new_backend(&config, events_loop.as_ref(), "yotredash")?;
+    let (sender, receiver) = mpsc::channel();
+    let mut renderer = backend.new_renderer(&config, receiver)?;
+
+    sender.send(RendererEvent::Time(TimeEvent::Pause))?;
+    sender.send(RendererEvent::Time(TimeEvent::SeekTo(time)))?;
+    let pixels = renderer.render_still(width, height)?;
+
+    let mut metadata = CaptureMetadata::new(config_path, &config)?;
+    metadata.demo_time = time;
+    capture::save(output, format, &pixels, width, height, &metadata)?;
+
+    info!("Saved screenshot to {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Aggregates frame statistics over an interval and logs them or appends them to a file as JSON
+//! lines, so performance can be compared across shader changes
+
+use anyhow::{Context, Error};
+use indexmap::IndexMap;
+use log::{debug, info, warn};
+use serde_derive::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+};
+use time::{self, Tm};
+
+use crate::{config::stats::StatsConfig, renderer::FrameStats, util::format_error};
+
+/// Percentiles of frame times over an interval (milliseconds)
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    /// Median
+    pub p50: f32,
+    /// 90th percentile
+    pub p90: f32,
+    /// 99th percentile
+    pub p99: f32,
+    /// Slowest frame
+    pub max: f32,
+}
+
+impl Percentiles {
+    /// Compute the percentiles of some times, using the nearest rank
+    pub fn new(times: &[f32]) -> Self {
+        let mut sorted = times.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let rank = |p: f32| {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            let index = (p * sorted.len() as f32).ceil() as usize;
+            sorted[index.max(1).min(sorted.len()) - 1]
+        };
+        Self {
+            p50: rank(0.5),
+            p90: rank(0.9),
+            p99: rank(0.99),
+            max: rank(1.0),
+        }
+    }
+}
+
+/// Average times for a node over an interval (milliseconds)
+#[derive(Debug, Default, Serialize)]
+struct NodeRecord {
+    /// Time spent on the GPU
+    gpu_time: f32,
+    /// Time spent on the CPU
+    cpu_time: f32,
+    /// Number of frames the node was rendered in, since switches can skip it
+    #[serde(skip)]
+    frames: u32,
+}
+
+/// The stats for one interval
+#[derive(Debug, Serialize)]
+struct Record {
+    /// When the interval ended
+    timestamp: String,
+    /// Frames per second
+    fps: f32,
+    /// Percentiles of the time between frames
+    frame_time: Percentiles,
+    /// Mean CPU time per frame (milliseconds)
+    cpu_time: f32,
+    /// Largest texture memory use in a frame (bytes)
+    texture_memory: u64,
+    /// Mean times for each node
+    nodes: IndexMap<String, NodeRecord>,
+}
+
+/// Collects frame stats and writes them out at the end of each interval
+pub struct StatsLogger {
+    /// The stats configuration
+    config: StatsConfig,
+    /// The file the stats are appended to, if any
+    file: Option<BufWriter<File>>,
+    /// When the current interval started
+    start: Tm,
+    /// Time between frames in the current interval
+    frame_times: Vec<f32>,
+    /// Total CPU time in the current interval
+    cpu_time: f32,
+    /// Largest texture memory use in the current interval
+    texture_memory: u64,
+    /// Total times for each node in the current interval
+    nodes: IndexMap<String, NodeRecord>,
+}
+
+impl StatsLogger {
+    /// Create a new instance, opening the stats file if there is one
+    pub fn new(config: &StatsConfig) -> Result<Self, Error> {
+        let file = match config.file {
+            Some(ref path) => Some(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .context("Could not open stats file")?,
+            )),
+            None => None,
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            file,
+            start: time::now(),
+            frame_times: Vec::new(),
+            cpu_time: 0.0,
+            texture_memory: 0,
+            nodes: IndexMap::new(),
+        })
+    }
+
+    /// Add the stats for a frame, writing them out if the interval is over
+    pub fn add_frame(&mut self, stats: &FrameStats) {
+        self.frame_times.push(stats.frame_time);
+        self.cpu_time += stats.cpu_time;
+        self.texture_memory = self.texture_memory.max(stats.texture_memory);
+        for node in &stats.nodes {
+            let record = self
+                .nodes
+                .entry(node.name.clone())
+                .or_insert_with(NodeRecord::default);
+            record.gpu_time += node.gpu_time;
+            record.cpu_time += node.cpu_time;
+            record.frames += 1;
+        }
+
+        let elapsed = (time::now() - self.start).num_milliseconds() as f32 / 1_000.0;
+        if elapsed >= self.config.interval {
+            let record = self.finish_interval(elapsed);
+            if let Err(e) = self.write(&record) {
+                warn!("Could not write stats: {}", format_error(&e));
+                self.file = None;
+            }
+        }
+    }
+
+    /// Aggregate the current interval and start a new one
+    fn finish_interval(&mut self, elapsed: f32) -> Record {
+        let frames = self.frame_times.len() as f32;
+        let mut nodes = IndexMap::new();
+        for (name, record) in self.nodes.drain(..) {
+            let count = record.frames.max(1) as f32;
+            nodes.insert(
+                name,
+                NodeRecord {
+                    gpu_time: record.gpu_time / count,
+                    cpu_time: record.cpu_time / count,
+                    frames: record.frames,
+                },
+            );
+        }
+
+        let record = Record {
+            timestamp: time::now().rfc3339().to_string(),
+            fps: frames / elapsed,
+            frame_time: Percentiles::new(&self.frame_times),
+            cpu_time: self.cpu_time / frames.max(1.0),
+            texture_memory: self.texture_memory,
+            nodes,
+        };
+
+        self.start = time::now();
+        self.frame_times.clear();
+        self.cpu_time = 0.0;
+        self.texture_memory = 0;
+        record
+    }
+
+    /// Log a record and append it to the file
+    fn write(&mut self, record: &Record) -> Result<(), Error> {
+        if self.config.log {
+            info!(
+                "{:.1} fps, frame time p50 {:.2} ms p90 {:.2} ms p99 {:.2} ms max {:.2} ms, \
+                 textures {:.1} MiB",
+                record.fps,
+                record.frame_time.p50,
+                record.frame_time.p90,
+                record.frame_time.p99,
+                record.frame_time.max,
+                record.texture_memory as f32 / (1024.0 * 1024.0),
+            );
+            for (name, node) in &record.nodes {
+                debug!(
+                    "{:<16} gpu {:6.2} ms  cpu {:6.2} ms",
+                    name, node.gpu_time, node.cpu_time
+                );
+            }
+        }
+
+        if let Some(ref mut file) = self.file {
+            serde_json::to_writer(&mut *file, record)?;
+            file.write_all(b"\n")?;
+            file.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Percentiles;
+
+    #[test]
+    fn percentiles() {
+        let times: Vec<f32> = (1..=100).rev().map(|time| time as f32).collect();
+        assert_eq!(
+            Percentiles::new(&times),
+            Percentiles {
+                p50: 50.0,
+                p90: 90.0,
+                p99: 99.0,
+                max: 100.0,
+            }
+        );
+        assert_eq!(Percentiles::new(&[]), Percentiles::default());
+    }
+}
@@ -0,0 +1,106 @@
+//! Renders a still of a config for file manager previews, so yotredash can be registered as a
+//! freedesktop thumbnailer:
+//!
+//! ```ini
+//! [Thumbnailer Entry]
+//! TryExec=yotredash
+//! Exec=yotredash --thumbnailer %i %o --size %s
+//! MimeType=application/x-yaml;
+//! ```
+
+use anyhow::{bail, Error};
+use log::error;
+use std::{path::Path, process, sync::mpsc, thread, time::Duration};
+
+use crate::{
+    capture::{self, CaptureMetadata},
+    config::{nodes::NodeConfig, Config},
+    event::{RendererEvent, TimeEvent},
+    renderer,
+};
+
+/// How long to wait for a thumbnail before giving up, so a slow shader can't hang the file manager
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Demo time to render thumbnails at, since many demos start from black
+const DEMO_TIME: f32 = 1.0;
+
+/// Returns the largest size with the same aspect ratio as `width` by `height` that fits in a
+/// `size` by `size` square
+fn fit(width: u32, height: u32, size: u32) -> (u32, u32) {
+    let scale = size as f32 / width.max(height).max(1) as f32;
+    (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    )
+}
+
+/// Thumbnails are made of files we didn't choose to open, so only nodes that just read files and
+/// render are allowed
+fn check_safe(config: &Config) -> Result<(), Error> {
+    let unsafe_nodes: Vec<&str> = config
+        .nodes
+        .iter()
+        .filter(|(_, node)| match node.config {
+            NodeConfig::Audio(_) | NodeConfig::Plugin(_) => true,
+            _ => false,
+        })
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    if !unsafe_nodes.is_empty() {
+        bail!(
+            "Can't make thumbnails of configs with audio or plugin nodes: `{}`",
+            unsafe_nodes.join("`, `")
+        );
+    }
+
+    Ok(())
+}
+
+/// Renders a thumbnail of a config to a PNG that fits in a `size` by `size` square
+pub fn thumbnail(input: &Path, output: &Path, size: u32) -> Result<(), Error> {
+    thread::spawn(|| {
+        thread::sleep(TIMEOUT);
+        error!("Timed out rendering thumbnail");
+        process::exit(1);
+    });
+
+    let mut config = Config::parse(input)?;
+    check_safe(&config)?;
+    config.headless = true;
+
+    let (width, height) = fit(config.width, config.height, size);
+
+    let events_loop = renderer::events_loop();
+    let backend = renderer::new_backend(&config, events_loop.as_ref(), "yotredash")?;
+    let (sender, receiver) = mpsc::channel();
+    let mut renderer = backend.new_renderer(&config, receiver)?;
+
+    sender.send(RendererEvent::Resize(width, height))?;
+    sender.send(RendererEvent::Time(TimeEvent::Pause))?;
+    sender.send(RendererEvent::Time(TimeEvent::Seek(DEMO_TIME)))?;
+    let pixels = renderer.render_still(width, height)?;
+
+    let mut metadata = CaptureMetadata::new(input, &config)?;
+    metadata.demo_time = DEMO_TIME;
+    capture::save_png(
+        output,
+        &capture::to_u8(&pixels, 4),
+        width,
+        height,
+        &metadata,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fit;
+
+    #[test]
+    fn fits_in_square() {
+        assert_eq!(fit(640, 400, 256), (256, 160));
+        assert_eq!(fit(400, 640, 256), (160, 256));
+        assert_eq!(fit(100, 100, 256), (256, 256));
+    }
+}
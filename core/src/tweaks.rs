@@ -0,0 +1,222 @@
+//! The tweak panel, which shows the tweaks of a config (see `config::tweaks`) as sliders and color
+//! pickers over the frame, so they can be adjusted while the graph is running instead of editing
+//! files and reloading. The arrow keys adjust them too, one component at a time.
+
+use imgui::{Condition, SliderFlags, StyleColor, Ui};
+
+use crate::config::{
+    tweaks::{self, Tweak},
+    Config,
+};
+
+/// Background of the widget of the tweak the arrow keys adjust
+const HIGHLIGHT: [f32; 4] = [0.26, 0.59, 0.98, 0.6];
+
+/// A tweak along with its value from the config and its current value
+#[derive(Debug, Clone)]
+struct Entry {
+    tweak: Tweak,
+    default: Vec<f32>,
+    value: Vec<f32>,
+}
+
+/// Keeps track of the tweaks, their values, and which one is selected
+#[derive(Debug, Default)]
+pub struct TweakPanel {
+    entries: Vec<Entry>,
+    /// Index of the selected component, counting through the components of every tweak
+    selected: usize,
+    visible: bool,
+}
+
+impl TweakPanel {
+    /// Create a new instance with the tweaks of a config
+    pub fn new(config: &Config) -> Self {
+        let mut panel = Self::default();
+        panel.update(config, false);
+        panel
+    }
+
+    /// Takes the tweaks of a reloaded config. If `keep_values` is set, values adjusted in the panel
+    /// are kept if the config didn't change them, and are returned as the constant nodes and values
+    /// to set.
+    pub fn update(&mut self, config: &Config, keep_values: bool) -> Vec<(String, Vec<f32>)> {
+        let mut restored = Vec::new();
+        let entries = config
+            .tweaks
+            .iter()
+            .map(|tweak| {
+                let default = tweaks::value(config, tweak);
+                let old = self
+                    .entries
+                    .iter()
+                    .filter(|_| keep_values)
+                    .find(|entry| entry.tweak.node == tweak.node && entry.default == default);
+                let value = match old {
+                    Some(old) if old.value != default => {
+                        restored.push((tweak.node.clone(), old.value.clone()));
+                        old.value.clone()
+                    }
+                    _ => default.clone(),
+                };
+                Entry {
+                    tweak: tweak.clone(),
+                    default,
+                    value,
+                }
+            })
+            .collect();
+
+        self.entries = entries;
+        self.selected = self.selected.min(self.rows().len().saturating_sub(1));
+        restored
+    }
+
+    /// Whether the panel is shown
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the panel
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Returns each component of each tweak, as the index of the tweak and of the component
+    fn rows(&self) -> Vec<(usize, usize)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .flat_map(|(index, entry)| {
+                (0..entry.value.len()).map(move |component| (index, component))
+            })
+            .collect()
+    }
+
+    /// Moves the selection by this many rows, wrapping around
+    pub fn select(&mut self, offset: isize) {
+        let rows = self.rows().len() as isize;
+        if rows > 0 {
+            self.selected = (self.selected as isize + offset).rem_euclid(rows) as usize;
+        }
+    }
+
+    /// Changes the selected component by a fraction of its range, returning the constant node to
+    /// set and its new value
+    pub fn adjust(&mut self, fraction: f32) -> Option<(String, Vec<f32>)> {
+        let (index, component) = *self.rows().get(self.selected)?;
+        let entry = &mut self.entries[index];
+        let (min, max) = (entry.tweak.min, entry.tweak.max);
+        let value = &mut entry.value[component];
+        *value = (*value + fraction * (max - min)).max(min).min(max);
+        Some((entry.tweak.node.clone(), entry.value.clone()))
+    }
+
+    /// Shows the tweaks as sliders and color pickers, returning the constant nodes whose values
+    /// were changed and their new values. Closing the window hides the panel.
+    pub fn build(&mut self, ui: &Ui) -> Vec<(String, Vec<f32>)> {
+        let selected = self.rows().get(self.selected).map(|&(index, _)| index);
+        let Self {
+            entries, visible, ..
+        } = self;
+
+        let mut changed = Vec::new();
+        let [width, _] = ui.io().display_size;
+        ui.window("Tweaks")
+            .opened(visible)
+            .position([width - 8.0, 8.0], Condition::FirstUseEver)
+            .position_pivot([1.0, 0.0])
+            .always_auto_resize(true)
+            .build(|| {
+                if entries.is_empty() {
+                    ui.text("No tweaks: add `params`, or `// @tweak` comments to shader uniforms");
+                }
+
+                for (index, entry) in entries.iter_mut().enumerate() {
+                    let _highlight = if selected == Some(index) {
+                        Some(ui.push_style_color(StyleColor::FrameBg, HIGHLIGHT))
+                    } else {
+                        None
+                    };
+                    let (label, min, max) = (&entry.tweak.label, entry.tweak.min, entry.tweak.max);
+                    let edited = match entry.value[..] {
+                        // Keys aren't passed on to imgui, so values can't be typed in
+                        [ref mut value] => ui
+                            .slider_config(label, min, max)
+                            .flags(SliderFlags::NO_INPUT)
+                            .build(value),
+                        ref mut color if color.len() == 4 => {
+                            let mut rgba = [color[0], color[1], color[2], color[3]];
+                            let edited = ui.color_edit4(label, &mut rgba);
+                            color.copy_from_slice(&rgba);
+                            edited
+                        }
+                        _ => false,
+                    };
+                    if edited {
+                        changed.push((entry.tweak.node.clone(), entry.value.clone()));
+                    }
+                }
+            });
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TweakPanel;
+    use crate::config::Config;
+
+    fn config(speed: f32) -> Config {
+        let mut config: Config = serde_yaml::from_str(&format!(
+            "params:\n    speed: {:?}\n    tint: [1.0, 0.5, 0.0, 1.0]\n",
+            speed
+        ))
+        .unwrap();
+        crate::config::params::expand(&mut config).unwrap();
+        crate::config::tweaks::expand(&mut config).unwrap();
+        config
+    }
+
+    #[test]
+    fn adjusts_within_range() {
+        let mut panel = TweakPanel::new(&config(1.0));
+        assert_eq!(
+            panel.adjust(0.25),
+            Some(("params/speed".to_string(), vec![1.5]))
+        );
+        assert_eq!(
+            panel.adjust(10.0),
+            Some(("params/speed".to_string(), vec![2.0]))
+        );
+
+        // Each channel of the color is its own row, and the selection wraps around
+        panel.select(-1);
+        assert_eq!(
+            panel.adjust(-0.5),
+            Some(("params/tint".to_string(), vec![1.0, 0.5, 0.0, 0.5]))
+        );
+        panel.select(1);
+        assert_eq!(
+            panel.adjust(0.0),
+            Some(("params/speed".to_string(), vec![2.0]))
+        );
+    }
+
+    #[test]
+    fn keeps_adjusted_values_across_reloads() {
+        let mut panel = TweakPanel::new(&config(1.0));
+        panel.adjust(0.25);
+        assert_eq!(
+            panel.update(&config(1.0), true),
+            vec![("params/speed".to_string(), vec![1.5])]
+        );
+
+        // Values changed in the config win over adjusted ones
+        assert_eq!(panel.update(&config(3.0), true), vec![]);
+        assert_eq!(
+            panel.adjust(0.0),
+            Some(("params/speed".to_string(), vec![3.0]))
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Various utilities that don't really have a place elsewhere
+
+use anyhow::Error;
+use std::collections::VecDeque;
+use time::{self, Duration, Tm};
+
+use crate::{event::TimeEvent, stats::Percentiles};
+
+/// Returns the frame rate of the slowest 1% of frames, from their times (milliseconds). This is
+/// the rate of the average of those frames, so it shows stutters that an average over every frame
+/// hides.
+pub fn one_percent_low(times: &[f32]) -> f32 {
+    let mut sorted = times.to_vec();
+    sorted.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let slowest = &sorted[..(sorted.len() as f32 / 100.0).ceil() as usize];
+    if slowest.is_empty() {
+        return 0.0;
+    }
+    let average = slowest.iter().sum::<f32>() / slowest.len() as f32;
+    if average > 0.0 {
+        1_000.0 / average
+    } else {
+        0.0
+    }
+}
+
+/// A simple struct to count frames per second and update at a set interval
+pub struct FpsCounter {
+    /// The last time the counter reset
+    start: Tm,
+    /// The number of frames counted since the last reset
+    frames: u32,
+    /// Interval between resets
+    interval: Duration,
+    /// Current frames per second
+    fps: f32,
+    /// When the last frame was counted
+    last_frame: Option<Tm>,
+    /// How far back frame times are kept
+    window: Duration,
+    /// When each frame in the window was counted, and how long it took (milliseconds)
+    frame_times: VecDeque<(Tm, f32)>,
+}
+
+impl FpsCounter {
+    /// Create a new instance with the specified update interval, keeping frame times for `window`
+    /// seconds
+    pub fn new(interval: f32, window: f32) -> Self {
+        Self {
+            start: time::now(),
+            frames: 0,
+            interval: Duration::milliseconds((interval * 1_000.0) as i64),
+            fps: 0.0,
+            last_frame: None,
+            window: Duration::milliseconds((window * 1_000.0) as i64),
+            frame_times: VecDeque::new(),
+        }
+    }
+
+    /// Increment the frame count and update the timer
+    pub fn next_frame(&mut self) {
+        let now = time::now();
+        if let Some(last_frame) = self.last_frame {
+            let frame_time = (now - last_frame).num_microseconds().unwrap_or(0) as f32 / 1_000.0;
+            self.frame_times.push_back((now, frame_time));
+        }
+        self.last_frame = Some(now);
+        while let Some(&(counted, _)) = self.frame_times.front() {
+            if now - counted <= self.window {
+                break;
+            }
+            self.frame_times.pop_front();
+        }
+
+        self.frames += 1;
+        let delta = time::now() - self.start;
+        if delta > self.interval {
+            let delta_nsec = delta.num_nanoseconds().unwrap() as f32 / 1_000_000_000.0;
+            self.fps = self.frames as f32 / delta_nsec;
+            self.frames = 0;
+            self.start = time::now();
+        }
+    }
+
+    /// Get the current FPS count
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Returns the times of the frames in the window (milliseconds)
+    fn frame_times(&self) -> Vec<f32> {
+        self.frame_times.iter().map(|&(_, time)| time).collect()
+    }
+
+    /// Get the average frame time over the window (milliseconds)
+    pub fn average_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().map(|&(_, time)| time).sum::<f32>() / self.frame_times.len() as f32
+    }
+
+    /// Get the frame rate of the slowest 1% of frames in the window
+    pub fn one_percent_low(&self) -> f32 {
+        one_percent_low(&self.frame_times())
+    }
+
+    /// Get the percentiles of frame times in the window (milliseconds)
+    pub fn percentiles(&self) -> Percentiles {
+        Percentiles::new(&self.frame_times())
+    }
+}
+
+/// How far a single step moves the clock (seconds)
+const STEP: f64 = 1.0 / 60.0;
+
+/// A clock for the time seen by shaders, which can be paused, seeked, and sped up or slowed down
+#[derive(Clone)]
+pub struct Clock {
+    /// The current time (seconds)
+    time: f64,
+    /// When the time was last brought up to date
+    last_update: Tm,
+    /// How fast the clock runs compared to real time
+    speed: f64,
+    /// The fastest the clock may run, whatever `speed` is
+    max_speed: Option<f64>,
+    /// Whether the clock is stopped
+    paused: bool,
+}
+
+impl Clock {
+    /// Create a new instance, starting at zero
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            last_update: time::now(),
+            speed: 1.0,
+            max_speed: None,
+            paused: false,
+        }
+    }
+
+    /// Bring the time up to date
+    fn advance(&mut self) {
+        let now = time::now();
+        if !self.paused {
+            let elapsed = (now - self.last_update).num_nanoseconds().unwrap() as f64 / 1e9;
+            let speed = match self.max_speed {
+                Some(max_speed) => self.speed.min(max_speed),
+                None => self.speed,
+            };
+            self.time += elapsed * speed;
+        }
+        self.last_update = now;
+    }
+
+    /// Cap how fast the clock may run, or remove the cap
+    pub fn set_max_speed(&mut self, max_speed: Option<f32>) {
+        self.advance();
+        self.max_speed = max_speed.map(f64::from);
+    }
+
+    /// Carry on from where another clock is, keeping this clock's speed limit
+    pub fn continue_from(&mut self, other: &Clock) {
+        self.time = other.time;
+        self.last_update = other.last_update;
+        self.speed = other.speed;
+        self.paused = other.paused;
+    }
+
+    /// Get the current time (seconds)
+    pub fn time(&mut self) -> f32 {
+        self.advance();
+        self.time as f32
+    }
+
+    /// Pause, resume, step, seek, or change the speed of the clock
+    pub fn handle(&mut self, event: &TimeEvent) {
+        self.advance();
+        match *event {
+            TimeEvent::Pause => self.paused = true,
+            TimeEvent::Resume => self.paused = false,
+            TimeEvent::Step => self.time += STEP,
+            TimeEvent::Seek(offset) => self.time = (self.time + f64::from(offset)).max(0.0),
+            TimeEvent::SeekTo(time) => self.time = f64::from(time).max(0.0),
+            TimeEvent::ScaleSpeed(factor) => self.speed *= f64::from(factor),
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fills in a window title template with the config name, frames per second, and elapsed time
+pub fn format_title(template: &str, config_name: &str, fps: f32, time: f32) -> String {
+    template
+        .replace("{config}", config_name)
+        .replace("{fps}", &format!("{:.1}", fps))
+        .replace("{time}", &format!("{:.1}", time))
+}
+
+pub fn format_error(error: &Error) -> String {
+    let mut causes = error.chain();
+    format!(
+        "{}{}",
+        causes.next().unwrap(),
+        causes
+            .map(|cause| format!("\nCaused by: {}", cause))
+            .collect::<Vec<String>>()
+            .join("")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{one_percent_low, Clock};
+    use crate::event::TimeEvent;
+
+    #[test]
+    fn finds_one_percent_lows() {
+        // 198 smooth frames and 2 stutters, which are the slowest 1%
+        let mut times = vec![10.0; 198];
+        times.extend_from_slice(&[40.0, 60.0]);
+        assert_eq!(one_percent_low(&times), 20.0);
+
+        // With fewer than 100 frames the slowest frame is the 1%
+        assert_eq!(one_percent_low(&[10.0, 25.0, 12.5]), 40.0);
+        assert_eq!(one_percent_low(&[]), 0.0);
+    }
+
+    #[test]
+    fn paused_clock_only_moves_when_told() {
+        let mut clock = Clock::new();
+        clock.handle(&TimeEvent::Pause);
+        let start = clock.time();
+
+        clock.handle(&TimeEvent::Seek(2.0));
+        assert_eq!(clock.time(), start + 2.0);
+
+        clock.handle(&TimeEvent::Seek(-10.0));
+        assert_eq!(clock.time(), 0.0);
+
+        clock.handle(&TimeEvent::Step);
+        assert!(clock.time() > 0.0);
+
+        clock.handle(&TimeEvent::SeekTo(12.5));
+        assert_eq!(clock.time(), 12.5);
+    }
+}
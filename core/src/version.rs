@@ -0,0 +1,131 @@
+//! Version information, and checking for newer releases
+
+use anyhow::Error;
+use log::debug;
+use reqwest::{header::USER_AGENT, Client};
+use semver::Version;
+use serde_derive::Deserialize;
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{config::Config, opengl, renderer};
+
+/// The GitHub API endpoint for the latest release
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/ashkitten/yotredash/releases/latest";
+
+/// The parts of a GitHub release we care about
+#[derive(Debug, Deserialize)]
+struct Release {
+    /// Name of the release's tag, like `v0.3.0`
+    tag_name: String,
+    /// Link to the release page
+    html_url: String,
+}
+
+/// Returns the version information, optionally with details about the build and the OpenGL
+/// implementation
+pub fn version_info(verbose: bool) -> String {
+    let mut info = format!("yotredash {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return info;
+    }
+
+    info.push_str(&format!("\ncommit: {}", env!("YOTREDASH_COMMIT")));
+    info.push_str(&format!(
+        "\nplatform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ));
+    let features = env!("YOTREDASH_FEATURES");
+    info.push_str(&format!(
+        "\nfeatures: {}",
+        if features.is_empty() {
+            "none"
+        } else {
+            features
+        }
+    ));
+    info.push_str(&format!(
+        "\nprofile: {}",
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    ));
+
+    let mut config = Config::default();
+    config.headless = true;
+    let events_loop = renderer::events_loop();
+    match opengl::renderer::new_facade(&config, events_loop.as_ref(), "") {
+        Ok((facade, _)) => {
+            let context = facade.get_context();
+            info.push_str(&format!(
+                "\nopengl: {}\nopengl renderer: {} ({})",
+                context.get_opengl_version_string(),
+                context.get_opengl_renderer_string(),
+                context.get_opengl_vendor_string()
+            ));
+        }
+        Err(e) => info.push_str(&format!("\nopengl: unavailable ({})", e)),
+    }
+
+    info
+}
+
+/// Returns whether a release tag refers to a newer version than this one
+fn is_newer(tag: &str, current: &str) -> bool {
+    match (
+        Version::parse(tag.trim_start_matches('v')),
+        Version::parse(current),
+    ) {
+        (Ok(latest), Ok(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Fetches the latest release from GitHub
+fn latest_release() -> Result<Release, Error> {
+    Ok(Client::new()
+        .get(LATEST_RELEASE_URL)
+        .header(USER_AGENT, "yotredash")
+        .send()?
+        .error_for_status()?
+        .json()?)
+}
+
+/// Checks for a newer release on a background thread. If there is one, a message describing it is
+/// sent to the returned receiver.
+pub fn check_for_updates() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || match latest_release() {
+        Ok(ref release) if is_newer(&release.tag_name, env!("CARGO_PKG_VERSION")) => {
+            let _ = sender.send(format!(
+                "yotredash {} is available: {}",
+                release.tag_name.trim_start_matches('v'),
+                release.html_url
+            ));
+        }
+        Ok(_) => debug!("yotredash is up to date"),
+        Err(e) => debug!("Could not check for updates: {}", e),
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_newer;
+
+    #[test]
+    fn compares_versions() {
+        assert!(is_newer("v0.4.0", "0.3.0"));
+        assert!(is_newer("0.3.1", "0.3.0"));
+        assert!(!is_newer("v0.3.0", "0.3.0"));
+        assert!(!is_newer("v0.2.9", "0.3.0"));
+        assert!(!is_newer("nightly", "0.3.0"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Wallpaper mode draws on every monitor at once, using either one window per monitor or a single
+//! window spanning all of them. Each monitor can have its own config.
+//!
+//! All the windows belong to one process, so signals (and anything else that controls the
+//! process) apply to every monitor at once.
+
+use anyhow::{ensure, Error};
+use log::info;
+use std::path::Path;
+use winit::{EventsLoop, MonitorId};
+
+use crate::{
+    config::Config,
+    instance::{ConfigOverrides, Instance},
+    monitor::Geometry,
+};
+
+/// Returns overrides which turn a config into a borderless wallpaper covering an area
+fn overrides(geometry: Geometry) -> ConfigOverrides {
+    Box::new(move |config: &mut Config| {
+        geometry.apply(config);
+        config.platform_config.set_wallpaper();
+    })
+}
+
+/// Creates the instances for wallpaper mode
+pub fn instances(
+    config_path: &Path,
+    config: Config,
+    events_loop: &EventsLoop,
+) -> Result<Vec<Instance>, Error> {
+    let monitors: Vec<MonitorId> = events_loop.get_available_monitors().collect();
+    ensure!(!monitors.is_empty(), "No monitors found for wallpaper mode");
+
+    if config.wallpaper.span {
+        let hidpi_factor = events_loop.get_primary_monitor().get_hidpi_factor();
+        let geometry = Geometry::spanning(&monitors, hidpi_factor);
+        info!("Spanning wallpaper across {} monitors", monitors.len());
+
+        let instance = Instance::new(
+            config_path,
+            Ok(config),
+            Some(overrides(geometry)),
+            Some(events_loop),
+        )?;
+        return Ok(vec![instance]);
+    }
+
+    let mut instances = Vec::new();
+    for (i, monitor) in monitors.iter().enumerate() {
+        let name = monitor.get_name().unwrap_or_default();
+        let geometry = Geometry::of(monitor);
+
+        // Monitors can be referred to by either name or index
+        let monitor_config_path = config
+            .wallpaper
+            .monitors
+            .get(&name)
+            .or_else(|| config.wallpaper.monitors.get(&i.to_string()))
+            .map(|path| config.path_to(path));
+
+        let instance = match monitor_config_path {
+            Some(path) => {
+                info!(
+                    "Wallpaper on monitor {} ({}) using {}",
+                    i,
+                    name,
+                    path.to_string_lossy()
+                );
+                Instance::new(
+                    &path,
+                    Config::parse(&path),
+                    Some(overrides(geometry)),
+                    Some(events_loop),
+                )?
+            }
+            None => {
+                info!("Wallpaper on monitor {} ({})", i, name);
+                Instance::new(
+                    config_path,
+                    Ok(config.clone()),
+                    Some(overrides(geometry)),
+                    Some(events_loop),
+                )?
+            }
+        };
+        instances.push(instance);
+    }
+
+    Ok(instances)
+}
@@ -0,0 +1,396 @@
+//! Writes frames from a readback node to a v4l2loopback device, so the output can be used as a
+//! webcam. Frames are converted and written on a background thread, and the format is set again
+//! whenever the size of the frames changes.
+
+use anyhow::{bail, Error};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    config::{
+        nodes::NodeConfig,
+        webcam::{WebcamConfig, WebcamFormat},
+        Config,
+    },
+    renderer::Frame,
+};
+
+/// Returns the size frames are written at. The YUV formats share colors between pairs of pixels,
+/// so their sizes are rounded down to an even number.
+pub fn output_size(format: WebcamFormat, width: u32, height: u32) -> (u32, u32) {
+    match format {
+        WebcamFormat::Yuyv => ((width & !1).max(2), height),
+        WebcamFormat::Yu12 => ((width & !1).max(2), (height & !1).max(2)),
+        WebcamFormat::Rgb24 => (width, height),
+    }
+}
+
+/// Returns the bytes per line and bytes per image of a format at a size
+pub fn image_size(format: WebcamFormat, width: u32, height: u32) -> (u32, u32) {
+    match format {
+        WebcamFormat::Yuyv => (width * 2, width * 2 * height),
+        WebcamFormat::Yu12 => (width, width * height * 3 / 2),
+        WebcamFormat::Rgb24 => (width * 3, width * 3 * height),
+    }
+}
+
+/// Converts a color to BT.601 limited range YUV, which is what webcams send
+fn yuv(pixel: &[u8]) -> (f32, f32, f32) {
+    let (r, g, b) = (
+        f32::from(pixel[0]),
+        f32::from(pixel[1]),
+        f32::from(pixel[2]),
+    );
+    (
+        16.0 + 0.257 * r + 0.504 * g + 0.098 * b,
+        128.0 - 0.148 * r - 0.291 * g + 0.439 * b,
+        128.0 + 0.439 * r - 0.368 * g - 0.071 * b,
+    )
+}
+
+/// Rounds a channel to a byte
+fn byte(value: f32) -> u8 {
+    value.round().max(0.0).min(255.0) as u8
+}
+
+/// Converts a frame to a format, cropping it to the size returned by `output_size`
+pub fn convert(frame: &Frame, format: WebcamFormat) -> Vec<u8> {
+    let (width, height) = output_size(format, frame.width, frame.height);
+    let (width, height) = (width as usize, height as usize);
+    let pixel = |x: usize, y: usize| {
+        let x = x.min(frame.width as usize - 1);
+        let y = y.min(frame.height as usize - 1);
+        let offset = (y * frame.width as usize + x) * 4;
+        &frame.pixels[offset..offset + 4]
+    };
+    let mut data = Vec::with_capacity(image_size(format, width as u32, height as u32).1 as usize);
+
+    match format {
+        WebcamFormat::Yuyv => {
+            for y in 0..height {
+                for x in (0..width).step_by(2) {
+                    let (y0, u0, v0) = yuv(pixel(x, y));
+                    let (y1, u1, v1) = yuv(pixel(x + 1, y));
+                    data.extend_from_slice(&[
+                        byte(y0),
+                        byte((u0 + u1) / 2.0),
+                        byte(y1),
+                        byte((v0 + v1) / 2.0),
+                    ]);
+                }
+            }
+        }
+        WebcamFormat::Yu12 => {
+            for y in 0..height {
+                for x in 0..width {
+                    data.push(byte(yuv(pixel(x, y)).0));
+                }
+            }
+            // The U plane comes before the V plane, each averaged over 2x2 blocks
+            for plane in 0..2 {
+                for y in (0..height).step_by(2) {
+                    for x in (0..width).step_by(2) {
+                        let sum: f32 = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)]
+                            .iter()
+                            .map(|&(x, y)| {
+                                let (_, u, v) = yuv(pixel(x, y));
+                                if plane == 0 {
+                                    u
+                                } else {
+                                    v
+                                }
+                            })
+                            .sum();
+                        data.push(byte(sum / 4.0));
+                    }
+                }
+            }
+        }
+        WebcamFormat::Rgb24 => {
+            for pixel in frame.pixels.chunks(4) {
+                data.extend_from_slice(&pixel[..3]);
+            }
+        }
+    }
+
+    data
+}
+
+/// A virtual webcam being written to
+pub struct Webcam {
+    /// The configuration it was started with
+    config: WebcamConfig,
+    /// Tells the writer thread to stop
+    stop: Arc<AtomicBool>,
+    /// The writer thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Webcam {
+    /// Start writing the frames of the configured readback node to the webcam
+    pub fn start(config: &Config, webcam_config: &WebcamConfig) -> Result<Self, Error> {
+        match config
+            .nodes
+            .get(&webcam_config.node)
+            .map(|node| &node.config)
+        {
+            Some(NodeConfig::Readback(_)) => (),
+            Some(_) => bail!(
+                "Webcam node `{}` must be a readback node",
+                webcam_config.node
+            ),
+            None => bail!("No such node for the webcam: `{}`", webcam_config.node),
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = v4l2::start(webcam_config, Arc::clone(&stop))?;
+
+        Ok(Self {
+            config: webcam_config.clone(),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The configuration it was started with
+    pub fn config(&self) -> &WebcamConfig {
+        &self.config
+    }
+}
+
+impl Drop for Webcam {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod v4l2 {
+    //! Just enough of the V4L2 API to set the format of an output device
+
+    use anyhow::{format_err, Context, Error};
+    use log::{error, info};
+    use std::{
+        fs::{File, OpenOptions},
+        io::Write,
+        mem,
+        os::{
+            raw::{c_ulong, c_void},
+            unix::io::AsRawFd,
+        },
+        path::Path,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::{Receiver, RecvTimeoutError},
+            Arc,
+        },
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    };
+
+    use super::{convert, image_size, output_size};
+    use crate::{
+        config::webcam::{WebcamConfig, WebcamFormat},
+        readback,
+        renderer::Frame,
+        util::format_error,
+    };
+
+    const V4L2_BUF_TYPE_VIDEO_OUTPUT: u32 = 2;
+    const V4L2_FIELD_NONE: u32 = 1;
+    const V4L2_COLORSPACE_SRGB: u32 = 8;
+
+    /// `struct v4l2_pix_format`
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PixFormat {
+        width: u32,
+        height: u32,
+        pixelformat: u32,
+        field: u32,
+        bytesperline: u32,
+        sizeimage: u32,
+        colorspace: u32,
+        priv_: u32,
+        flags: u32,
+        ycbcr_enc: u32,
+        quantization: u32,
+        xfer_func: u32,
+    }
+
+    /// The union in `struct v4l2_format`. Some of its members hold pointers, which decide its
+    /// alignment.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    union FormatData {
+        pix: PixFormat,
+        raw_data: [u8; 200],
+        _align: *const c_void,
+    }
+
+    /// `struct v4l2_format`
+    #[repr(C)]
+    struct Format {
+        type_: u32,
+        fmt: FormatData,
+    }
+
+    /// `VIDIOC_S_FMT`, which is `_IOWR('V', 5, struct v4l2_format)`
+    fn vidioc_s_fmt() -> c_ulong {
+        (3 << 30) | ((mem::size_of::<Format>() as c_ulong) << 16) | (c_ulong::from(b'V') << 8) | 5
+    }
+
+    /// Set the format of the device
+    fn set_format(
+        device: &File,
+        format: WebcamFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error> {
+        let (bytesperline, sizeimage) = image_size(format, width, height);
+        let mut v4l2_format = Format {
+            type_: V4L2_BUF_TYPE_VIDEO_OUTPUT,
+            fmt: FormatData { raw_data: [0; 200] },
+        };
+        v4l2_format.fmt.pix = PixFormat {
+            width,
+            height,
+            pixelformat: format.fourcc(),
+            field: V4L2_FIELD_NONE,
+            bytesperline,
+            sizeimage,
+            colorspace: V4L2_COLORSPACE_SRGB,
+            priv_: 0,
+            flags: 0,
+            ycbcr_enc: 0,
+            quantization: 0,
+            xfer_func: 0,
+        };
+
+        let result = unsafe { libc::ioctl(device.as_raw_fd(), vidioc_s_fmt(), &mut v4l2_format) };
+        if result < 0 {
+            return Err(format_err!(
+                "Could not set the webcam format: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+
+    fn open(path: &Path) -> Result<File, Error> {
+        Ok(OpenOptions::new()
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Could not open webcam device {}", path.display()))?)
+    }
+
+    /// Write frames to the device until told to stop
+    fn write_frames(
+        config: &WebcamConfig,
+        mut device: File,
+        receiver: &Receiver<Arc<Frame>>,
+        stop: &AtomicBool,
+    ) -> Result<(), Error> {
+        let interval = Duration::from_nanos((1_000_000_000.0 / config.fps.max(1.0)) as u64);
+        let mut size = None;
+        let mut last_write: Option<Instant> = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            let frame = match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(frame) => frame,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if last_write.map_or(false, |last_write| last_write.elapsed() < interval) {
+                continue;
+            }
+
+            // v4l2loopback only lets the format change while nothing has the device open, so it's
+            // opened again when the size changes
+            let frame_size = output_size(config.format, frame.width, frame.height);
+            if size != Some(frame_size) {
+                if size.is_some() {
+                    drop(device);
+                    device = open(&config.device)?;
+                }
+                set_format(&device, config.format, frame_size.0, frame_size.1)?;
+                size = Some(frame_size);
+            }
+
+            device
+                .write_all(&convert(&frame, config.format))
+                .context("Could not write to the webcam")?;
+            last_write = Some(Instant::now());
+        }
+
+        Ok(())
+    }
+
+    /// Open the device and start writing frames to it
+    pub fn start(config: &WebcamConfig, stop: Arc<AtomicBool>) -> Result<JoinHandle<()>, Error> {
+        let device = open(&config.device)?;
+        let receiver = readback::subscribe(&config.node);
+        let config = config.clone();
+        info!("Writing frames to webcam {}", config.device.display());
+
+        Ok(thread::spawn(move || {
+            if let Err(e) = write_frames(&config, device, &receiver, &stop) {
+                error!("{}", format_error(&e));
+            }
+        }))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod v4l2 {
+    //! V4L2 only exists on Linux
+
+    use anyhow::{bail, Error};
+    use std::{
+        sync::{atomic::AtomicBool, Arc},
+        thread::JoinHandle,
+    };
+
+    use crate::config::webcam::WebcamConfig;
+
+    pub fn start(_config: &WebcamConfig, _stop: Arc<AtomicBool>) -> Result<JoinHandle<()>, Error> {
+        bail!("Virtual webcams are only supported on Linux");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert, output_size};
+    use crate::{config::webcam::WebcamFormat, renderer::Frame};
+
+    #[test]
+    fn converts_to_yuv() {
+        // A white and a black pixel, over a red and a blue one
+        let frame = Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                255, 255, 255, 255, 0, 0, 0, 255, //
+                255, 0, 0, 255, 0, 0, 255, 255,
+            ],
+        };
+        assert_eq!(
+            convert(&frame, WebcamFormat::Yuyv),
+            vec![235, 128, 16, 128, 82, 165, 41, 175]
+        );
+        assert_eq!(
+            convert(&frame, WebcamFormat::Yu12),
+            vec![235, 16, 82, 41, 147, 151]
+        );
+        assert_eq!(convert(&frame, WebcamFormat::Rgb24).len(), 12);
+        assert_eq!(output_size(WebcamFormat::Yu12, 641, 401), (640, 400));
+    }
+}
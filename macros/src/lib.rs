@@ -1,5 +1,4 @@
 #![recursion_limit = "128"]
-#![feature(box_patterns)]
 
 extern crate proc_macro;
 
@@ -38,21 +37,24 @@ pub fn wrap_result(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         })
         .collect();
-    let output = match &input.decl.output {
-        &syn::ReturnType::Type(_, box syn::Type::Path(ref path)) => {
-            let mut segments = path.path.segments.clone();
-            let last_segment = segments.pop().unwrap();
-            match last_segment.into_value().arguments {
-                syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-                    args,
-                    ..
-                }) => {
-                    let out_type = args.into_iter().next().unwrap();
-                    quote!(-> #out_type)
+    let output = match input.decl.output {
+        syn::ReturnType::Type(_, ref ty) => match **ty {
+            syn::Type::Path(ref path) => {
+                let mut segments = path.path.segments.clone();
+                let last_segment = segments.pop().unwrap();
+                match last_segment.into_value().arguments {
+                    syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
+                        args,
+                        ..
+                    }) => {
+                        let out_type = args.into_iter().next().unwrap();
+                        quote!(-> #out_type)
+                    }
+                    _ => unreachable!(),
                 }
-                _ => unreachable!(),
             }
-        }
+            _ => unreachable!(),
+        },
         _ => unreachable!(),
     };
     let inner_output = &input.decl.output;
@@ -61,14 +63,14 @@ pub fn wrap_result(attr: TokenStream, item: TokenStream) -> TokenStream {
     let tokens = quote! {
         #constness #unsafety #asyncness #abi fn #name(#inputs) #output {
             use std::io::Write;
-            use failure::Error;
+            use anyhow::Error;
             use log::{error, Level, log_enabled};
 
             fn inner(#inputs) #inner_output #body
 
             if let Err(error) = inner(#inner_inputs) {
                 if log_enabled!(Level::Debug) {
-                    let mut causes = error.iter_chain();
+                    let mut causes = error.chain();
 
                     error!(
                         "{}",
@@ -80,16 +82,16 @@ pub fn wrap_result(attr: TokenStream, item: TokenStream) -> TokenStream {
                         error!("Caused by: {}", cause);
                     }
 
-                    let backtrace = format!("{}", error.backtrace());
-                    if backtrace.is_empty() {
+                    let backtrace = error.backtrace();
+                    if backtrace.status() == ::std::backtrace::BacktraceStatus::Captured {
+                        writeln!(::std::io::stderr(), "{}", backtrace)
+                            .expect("Could not write to stderr");
+                    } else {
                         writeln!(
                             ::std::io::stderr(),
                             "Set RUST_BACKTRACE=1 to see a backtrace"
                         )
                             .expect("Could not write to stderr");
-                    } else {
-                        writeln!(::std::io::stderr(), "{}", error.backtrace())
-                            .expect("Could not write to stderr");
                     }
                 }
 
@@ -3,8 +3,8 @@
 #[test]
 fn wraps_result() {
     #[macros::wrap_result("std::process::exit(0)")]
-    fn wraps(owo: &'static str) -> Result<(), failure::Error> {
-        failure::bail!(owo);
+    fn wraps(owo: &'static str) -> Result<(), anyhow::Error> {
+        anyhow::bail!(owo);
     }
     wraps("blep");
 }
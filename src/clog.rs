@@ -1,7 +1,15 @@
-use libc::{c_char, c_int, size_t};
-use log::{info, warn, trace};
-use std::ffi::{CString, CStr, VaList};
+//! Routes messages that the ALSA and JACK C libraries print to stderr through our logger. ALSA's
+//! handler is variadic, which can only be written on nightly, so it needs the `nightly` feature.
 
+#[cfg(feature = "nightly")]
+use libc::size_t;
+use libc::{c_char, c_int};
+use log::{info, trace, warn};
+use std::ffi::CStr;
+#[cfg(feature = "nightly")]
+use std::ffi::{CString, VaList};
+
+#[cfg(feature = "nightly")]
 unsafe extern "C" fn alsa_error_handler(
     _file: *const c_char,
     _line: c_int,
@@ -28,10 +36,12 @@ extern "C" fn jack_error_handler(msg: *const c_char) {
     warn!(target: "yotredash::jack", "{}", msg);
 }
 
+#[cfg(feature = "nightly")]
 extern "C" {
     fn vsnprintf(s: *mut c_char, n: size_t, format: *const c_char, ap: VaList) -> c_int;
 }
 
+#[cfg(feature = "nightly")]
 #[link(name = "asound")]
 extern "C" {
     fn snd_lib_error_set_handler(
@@ -52,9 +62,9 @@ extern "C" {
     fn jack_set_error_function(handler: extern "C" fn(msg: *const c_char));
 }
 
-
 pub fn setup_c_logging() {
     unsafe {
+        #[cfg(feature = "nightly")]
         snd_lib_error_set_handler(alsa_error_handler);
         jack_set_info_function(jack_info_handler);
         jack_set_error_function(jack_error_handler);
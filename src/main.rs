@@ -6,7 +6,8 @@
 //!
 //! # Configuration
 //! Yotredash provides a simple yaml configuration from which a user can configure nearly all
-//! behaviors of the application.
+//! behaviors of the application. Configurations can also be written in json or toml, in which case
+//! the file must have a `.json` or `.toml` extension.
 //!
 //! ```yaml
 //! buffers:
@@ -38,77 +39,84 @@
 //!
 //! The above example will run yotredash in fullscreen mode, regardless of whether or not the
 //! `fullscreen` option is specified in the configuration file.
+//!
+//! If `--config` isn't given, the config is loaded from the path in the `YOTREDASH_CONFIG`
+//! environment variable, or from `~/.config/yotredash/config.yml` if it exists. Otherwise a file
+//! dialog is shown, unless `--no-dialog` is given, in which case yotredash exits with an error.
+//!
+//! Nodes are rendered in dependency order. When more than one node could go next, the one with the
+//! lowest `priority` is rendered first, and after that the one that comes first in the config file,
+//! so the render order (and any logging that follows it) is the same on every run.
+//!
+//! `--high-contrast` draws errors and the HUD in high-contrast colors with larger text, and
+//! `--reduced-motion` sets the `reduced_motion` output of info nodes. Configs can also set
+//! `accessibility.reduced_motion_speed` to slow time down when reduced motion is asked for.
+//!
+//! `yotredash --thumbnailer config.yml thumbnail.png --size 256` renders a still of a config
+//! without opening a window, for use as a file manager thumbnailer.
+//!
+//! `yotredash audio-devices --backend cpal` lists the input devices an audio node can open with
+//! `device`.
+//!
+//! To get started quickly, `yotredash new path/to/project` creates a project with a working config
+//! and example shaders.
+//!
+//! `yotredash graph --format dot` prints the node graph of a config, along with the order the
+//! nodes are rendered in, as Graphviz dot or JSON.
+//!
+//! An instance started with `--control-socket` can be driven from scripts with `yotredash ctl`,
+//! e.g. `yotredash ctl pause` or `yotredash ctl set tint 1 0.5 0 1`.
+//!
+//! `yotredash test config.yml --times 0 1 2` renders frames headless and compares them against
+//! reference images, for visual regression tests in CI. `--update` writes the references.
+//!
+//! `yotredash --benchmark 1000 --report report.json` renders 1000 frames (or `10s` for ten seconds)
+//! without vsync and writes the minimum, average, percentile and maximum frame and node times to
+//! the report, as JSON or as CSV if the file ends in `.csv`.
 
 // Warn if things are missing documentation
 #![warn(missing_docs)]
-#![feature(c_variadic)]
+// Logging ALSA's messages needs a variadic function, which only nightly can define
+#![cfg_attr(feature = "nightly", feature(c_variadic))]
 
+use anyhow::Error;
 use env_logger;
-use failure::{format_err, Error};
-use log::{error, info, warn};
-use notify::{self, Watcher};
-use std::{path::Path, sync::mpsc};
-use time;
-use winit;
-
-pub mod config;
-pub mod clog;
-pub mod event;
-pub mod font;
-pub mod opengl;
-pub mod platform;
-pub mod renderer;
-pub mod util;
+use log::{error, info};
+use std::path::{Path, PathBuf};
+use winit::{self, EventsLoop, WindowId};
+
+mod clog;
 
 #[cfg(unix)]
 use signal::trap::Trap;
 #[cfg(unix)]
 use signal::Signal;
 
-use crate::{
-    config::{nodes::NodeConfig, Config},
+#[cfg(unix)]
+use yotredash_core::control::ControlSocket;
+
+use yotredash_core::{
+    benchmark,
+    config::{nodes::AudioBackend, Config},
+    control,
     event::*,
-    opengl::renderer::{OpenGLDebugRenderer, OpenGLRenderer},
-    renderer::{DebugRenderer, Renderer},
+    golden, graph,
+    instance::{ConfigOverrides, Instance},
+    limiter::FrameLimiter,
+    monitor::{Geometry, MonitorWatcher},
+    opengl::nodes::audio,
+    platform, renderer, scaffold, screenshot, thumbnail,
     util::format_error,
+    version, wallpaper,
 };
 
-fn setup_watches(
-    config_path: &Path,
-    config: &Config,
-) -> Result<(notify::RecommendedWatcher, mpsc::Receiver<notify::RawEvent>), Error> {
-    // Create a watcher to receive filesystem events
-    let (sender, receiver) = mpsc::channel();
-    let mut watcher = notify::RecommendedWatcher::new_raw(sender)?;
-
-    // We still create the watcher, anyway, but if we're not watching anything then does it really
-    // matter?
-    if config.autoreload {
-        // Watch the config file for changes
-        watcher.watch(config_path, notify::RecursiveMode::NonRecursive)?;
-
-        for node in config.nodes.values() {
-            match *node {
-                NodeConfig::Image(ref image_config) => watcher.watch(
-                    config.path_to(Path::new(&image_config.path)),
-                    notify::RecursiveMode::NonRecursive,
-                )?,
-                NodeConfig::Shader(ref shader_config) => {
-                    watcher.watch(
-                        config.path_to(Path::new(&shader_config.vertex)),
-                        notify::RecursiveMode::NonRecursive,
-                    )?;
-                    watcher.watch(
-                        config.path_to(Path::new(&shader_config.fragment)),
-                        notify::RecursiveMode::NonRecursive,
-                    )?;
-                }
-                _ => (),
-            }
-        }
-    }
+/// How many pixels of touchpad scrolling count as one line of mouse wheel scrolling
+const PIXELS_PER_LINE: f64 = 20.0;
 
-    Ok((watcher, receiver))
+/// Overrides which make a config's window span every monitor
+fn span_overrides(events_loop: &EventsLoop) -> ConfigOverrides {
+    let geometry = Geometry::all(events_loop);
+    Box::new(move |config: &mut Config| geometry.apply(config))
 }
 
 fn main() -> Result<(), Error> {
@@ -139,8 +147,64 @@ fn main() -> Result<(), Error> {
         })
         .init();
 
-    // For catching and displaying errors
-    let mut error = None;
+    // `yotredash new <dir>` creates a project instead of running one
+    let args = platform::config::PlatformSpecificConfig::build_cli().get_matches();
+    if let Some(args) = args.subcommand_matches("new") {
+        return scaffold::new_project(Path::new(args.value_of("dir").unwrap()));
+    }
+    if let Some(args) = args.subcommand_matches("ctl") {
+        return control::ctl(args);
+    }
+    if let Some(args) = args.subcommand_matches("audio-devices") {
+        let backend = match args.value_of("backend").unwrap() {
+            "cpal" => AudioBackend::Cpal,
+            _ => AudioBackend::Portaudio,
+        };
+        return audio::print_devices(backend);
+    }
+    if let Some(args) = args.subcommand_matches("graph") {
+        let path = match args.value_of_os("config") {
+            Some(path) => PathBuf::from(path),
+            None => Config::get_path()?,
+        };
+        return graph::print(&path, args.value_of("format").unwrap());
+    }
+    if let Some(args) = args.subcommand_matches("test") {
+        let options = golden::Options {
+            times: golden::parse_times(args.values_of("times").unwrap())?,
+            threshold: args.value_of("threshold").unwrap().parse()?,
+            tolerance: args.value_of("tolerance").unwrap().parse()?,
+            update: args.is_present("update"),
+        };
+        return golden::test(
+            Path::new(args.value_of_os("config").unwrap()),
+            args.value_of_os("references").map(Path::new),
+            &options,
+        );
+    }
+
+    if let Some(mut paths) = args.values_of_os("thumbnailer") {
+        let input = Path::new(paths.next().unwrap());
+        let output = Path::new(paths.next().unwrap());
+        let size = args.value_of("size").unwrap().parse::<u32>()?;
+        return thumbnail::thumbnail(input, output, size);
+    }
+
+    if let Some(output) = args.value_of_os("screenshot") {
+        let time = args.value_of("at_time").unwrap().parse::<f32>()?;
+        return screenshot::screenshot(&Config::get_path()?, Path::new(output), time);
+    }
+
+    if let Some(length) = args.value_of("benchmark") {
+        let length = benchmark::Length::parse(length)?;
+        let report = args.value_of_os("report").map(Path::new);
+        return benchmark::benchmark(&Config::get_path()?, length, report);
+    }
+
+    if args.is_present("version") {
+        println!("{}", version::version_info(args.is_present("verbose")));
+        return Ok(());
+    }
 
     // Register signal handler (unix only)
     #[cfg(unix)]
@@ -148,232 +212,274 @@ fn main() -> Result<(), Error> {
 
     // Get configuration
     let config_path = Config::get_path()?;
-    let config = match Config::parse(&config_path) {
-        Ok(config) => config,
-        Err(e) => {
-            error!("{}", format_error(&e));
-            error = Some(e);
-            Config::backup()?
+    let config = Config::parse(&config_path);
+
+    // There's no events loop without a display server, which is only fine when we're headless
+    let mut events_loop = renderer::events_loop();
+
+    // Create an instance for each window we're drawing to
+    let (wallpaper, span) = match config {
+        Ok(ref config) => (config.wallpaper.enabled, config.span),
+        Err(_) => (false, false),
+    };
+    let mut instances = match events_loop {
+        Some(ref events_loop) if wallpaper => {
+            wallpaper::instances(&config_path, config?, events_loop)?
         }
+        Some(ref events_loop) if span => vec![Instance::new(
+            &config_path,
+            config,
+            Some(span_overrides(events_loop)),
+            Some(events_loop),
+        )?],
+        _ => vec![Instance::new(
+            &config_path,
+            config,
+            None,
+            events_loop.as_ref(),
+        )?],
     };
 
-    // Setup filesystem watches
-    let (mut watcher, mut receiver) = setup_watches(&config_path, &config)?;
-
-    // Creates an appropriate renderer for the configuration, exits with an error if that fails
-    let mut events_loop = winit::EventsLoop::new();
-
-    let (mut event_sender, event_receiver) = mpsc::channel();
-    // TODO: return something renderer-independent instead of Facade
-    let (mut renderer, mut debug_renderer, facade) = match config.renderer.as_ref() as &str {
-        "opengl" => {
-            let facade = opengl::renderer::new_facade(&config, &events_loop)?;
-            let renderer = match OpenGLRenderer::new(&config, &facade, event_receiver) {
-                Ok(r) => Some(Box::new(r)),
-                Err(e) => {
-                    error = Some(e);
-                    None
-                }
-            };
-            let debug_renderer = OpenGLDebugRenderer::new(&facade)?;
-            (renderer, Box::new(debug_renderer), facade)
-        }
-        other => {
-            let facade = opengl::renderer::new_facade(&config, &events_loop)?;
-            let debug_renderer = OpenGLDebugRenderer::new(&facade)?;
-            error = Some(format_err!("Renderer {} is not built in", other));
-            (None, Box::new(debug_renderer), facade)
+    let mut monitor_watcher = events_loop.as_ref().map(MonitorWatcher::new);
+
+    // Commands from the control socket go to every instance
+    let control_path = instances.iter().find_map(Instance::control_socket);
+    #[cfg(unix)]
+    let control = match control_path {
+        Some(ref path) => Some(ControlSocket::bind(path)?),
+        None => None,
+    };
+    #[cfg(not(unix))]
+    {
+        if control_path.is_some() {
+            log::warn!("Control sockets aren't supported on this platform");
         }
+    }
+
+    // Check for updates once, for every instance
+    let check_updates = instances.iter().any(Instance::check_updates);
+    let updates = if check_updates {
+        Some(version::check_for_updates())
+    } else {
+        None
     };
 
-    let mut paused = false;
-    loop {
-        let mut events: Vec<Event> = Vec::new();
+    let mut limiter = FrameLimiter::new();
 
-        if let Some(ref mut renderer) = renderer {
-            renderer.update()?;
-        }
+    loop {
+        // Events paired with the instance they're for, or `None` if they're for all instances
+        let mut events: Vec<(Option<WindowId>, Event)> = Vec::new();
 
-        match error {
-            None => {
-                if let Some(ref mut renderer) = renderer {
-                    if !paused {
-                        match renderer.render() {
-                            Err(e) => {
-                                error!("{}", format_error(&e));
-                                error = Some(e);
-                            }
-                            _ => (),
-                        }
-                    } else {
-                        match renderer.swap_buffers() {
-                            Err(e) => {
-                                error!("{}", format_error(&e));
-                                error = Some(e);
-                            }
-                            _ => (),
-                        }
-                    }
-                }
+        for instance in &mut instances {
+            instance.draw()?;
+            if instance.context_lost() {
+                instance.recover(events_loop.as_ref())?;
             }
-            Some(ref error) => debug_renderer.draw_error(error)?,
         }
 
+        // Every window is drawn in the same loop, so the highest limit wins, and a window without
+        // one leaves the loop uncapped
+        let fps_limit = instances
+            .iter()
+            .map(Instance::fps_limit)
+            .fold(Some(0.0), |limit, fps| match (limit, fps) {
+                (Some(limit), Some(fps)) => Some(f32::max(limit, fps)),
+                _ => None,
+            });
+        limiter.wait(fps_limit);
+
         #[cfg(unix)]
         {
             // Catch signals between draw calls
             let signal = trap.wait(std::time::Instant::now());
             if signal.is_some() {
                 match signal.unwrap() {
-                    Signal::SIGUSR1 => paused = true,
-                    Signal::SIGUSR2 => paused = false,
-                    Signal::SIGHUP => events.push(Event::Reload),
+                    Signal::SIGUSR1 => events.push((None, Event::Pause)),
+                    Signal::SIGUSR2 => events.push((None, Event::Resume)),
+                    Signal::SIGHUP => events.push((None, Event::Reload)),
                     _ => (),
                 }
             }
-        }
-
-        events_loop.poll_events(|event| {
-            if let winit::Event::WindowEvent { event, .. } = event {
-                use winit::WindowEvent;
 
-                match event {
-                    WindowEvent::Resized(size) => {
-                        let size: (u32, u32) = size.into();
-                        events.push(Event::Resize(size.0, size.1))
+            if let Some(ref control) = control {
+                while let Some(request) = control.try_recv() {
+                    match request.command.clone().into_event() {
+                        Some(event) => {
+                            events.push((None, event));
+                            request.reply(String::new());
+                        }
+                        None => {
+                            let status: Vec<String> =
+                                instances.iter().map(Instance::status).collect();
+                            request.reply(status.join("; "));
+                        }
                     }
+                }
+            }
+        }
 
-                    WindowEvent::CloseRequested => events.push(Event::Close),
-
-                    WindowEvent::KeyboardInput {
-                        input:
-                            winit::KeyboardInput {
-                                virtual_keycode: Some(keycode),
-                                state: winit::ElementState::Pressed,
-                                ..
-                            },
-                        ..
-                    } => match keycode {
-                        winit::VirtualKeyCode::Escape => events.push(Event::Close),
-                        winit::VirtualKeyCode::F2 => events.push(Event::Capture),
-                        winit::VirtualKeyCode::F5 => events.push(Event::Reload),
-                        winit::VirtualKeyCode::F6 => paused = !paused,
-                        _ => (),
-                    },
+        if let Some(ref mut events_loop) = events_loop {
+            events_loop.poll_events(|event| {
+                if let winit::Event::WindowEvent { event, window_id } = event {
+                    use winit::WindowEvent;
 
-                    WindowEvent::CursorMoved { position, .. } => {
-                        events.push(Event::Pointer(PointerEvent::Move(
-                            position.x as f32,
-                            position.y as f32,
-                        )));
-                    }
+                    let window_id = Some(window_id);
+                    match event {
+                        WindowEvent::Resized(size) => {
+                            let size: (u32, u32) = size.into();
+                            events.push((window_id, Event::Resize(size.0, size.1)))
+                        }
 
-                    WindowEvent::MouseInput {
-                        button: winit::MouseButton::Left,
-                        state,
-                        ..
-                    } => match state {
-                        winit::ElementState::Pressed => {
-                            events.push(Event::Pointer(PointerEvent::Press));
+                        WindowEvent::HiDpiFactorChanged(_) => {
+                            events.push((window_id, Event::HidpiFactorChanged))
                         }
-                        winit::ElementState::Released => {
-                            events.push(Event::Pointer(PointerEvent::Release));
+
+                        WindowEvent::CloseRequested => events.push((window_id, Event::Close)),
+
+                        WindowEvent::KeyboardInput {
+                            input:
+                                winit::KeyboardInput {
+                                    virtual_keycode: Some(keycode),
+                                    state,
+                                    modifiers,
+                                    ..
+                                },
+                            ..
+                        } if MovementKey::from_key(keycode, state, modifiers).is_some()
+                            && instances.iter().any(|instance| {
+                                instance.window_id() == window_id && instance.has_camera()
+                            }) =>
+                        {
+                            let key = MovementKey::from_key(keycode, state, modifiers).unwrap();
+                            let key_event = match state {
+                                winit::ElementState::Pressed => KeyEvent::Press(key),
+                                winit::ElementState::Released => KeyEvent::Release(key),
+                            };
+                            events.push((window_id, Event::Key(key_event)));
                         }
-                    },
 
-                    _ => (),
-                }
-            }
-        });
-
-        match receiver.try_recv() {
-            Ok(notify::RawEvent {
-                path, op: Ok(op), ..
-            }) => {
-                // We listen for both WRITE and REMOVE events because some editors (like vim) will
-                // remove the file and write a new one in its place, and on Linux this will also
-                // remove the watch, so we won't ever receive a WRITE event in this case
-                if op.intersects(notify::op::WRITE | notify::op::REMOVE) {
-                    if let Some(ref path) = path {
-                        info!(
-                            "Detected file change for {}, reloading...",
-                            path.to_str().unwrap()
-                        );
-                    } else {
-                        info!("Detected file change, reloading...");
-                    }
+                        WindowEvent::KeyboardInput {
+                            input:
+                                winit::KeyboardInput {
+                                    virtual_keycode: Some(keycode),
+                                    state: winit::ElementState::Pressed,
+                                    modifiers,
+                                    ..
+                                },
+                            ..
+                        } => {
+                            // Each window has the bindings from its own config
+                            let event = instances
+                                .iter()
+                                .find(|instance| instance.window_id() == window_id)
+                                .and_then(|instance| instance.key_event(keycode, modifiers));
+                            if let Some(event) = event {
+                                events.push((window_id, event));
+                            }
+                        }
 
-                    events.push(Event::Reload);
-                }
+                        WindowEvent::CursorMoved { position, .. } => {
+                            events.push((
+                                window_id,
+                                Event::Pointer(PointerEvent::Move(
+                                    position.x as f32,
+                                    position.y as f32,
+                                )),
+                            ));
+                        }
 
-                // If the file was removed and replaced (how certain editors save files)
-                if op.contains(notify::op::REMOVE) {
-                    if let Some(path) = path {
-                        if path.exists() {
-                            watcher.watch(path, notify::RecursiveMode::NonRecursive)?;
+                        WindowEvent::MouseInput { button, state, .. } => {
+                            let button = match button {
+                                winit::MouseButton::Left => PointerButton::Left,
+                                winit::MouseButton::Right => PointerButton::Right,
+                                winit::MouseButton::Middle => PointerButton::Middle,
+                                winit::MouseButton::Other(_) => return,
+                            };
+                            let pointer_event = match state {
+                                winit::ElementState::Pressed => PointerEvent::Press(button),
+                                winit::ElementState::Released => PointerEvent::Release(button),
+                            };
+                            events.push((window_id, Event::Pointer(pointer_event)));
                         }
+
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            let (x, y) = match delta {
+                                winit::MouseScrollDelta::LineDelta(x, y) => (x, y),
+                                // Touchpads scroll by pixels, so count them in lines of text
+                                winit::MouseScrollDelta::PixelDelta(position) => (
+                                    (position.x / PIXELS_PER_LINE) as f32,
+                                    (position.y / PIXELS_PER_LINE) as f32,
+                                ),
+                            };
+                            events.push((window_id, Event::Pointer(PointerEvent::Scroll(x, y))));
+                        }
+
+                        _ => (),
                     }
                 }
-            }
-            Err(mpsc::TryRecvError::Disconnected) => error!("Filesystem watcher disconnected"),
-            _ => (),
+            });
         }
 
-        for event in events {
-            match event {
-                Event::Pointer(pointer_event) => {
-                    if renderer.is_some() {
-                        event_sender.send(RendererEvent::Pointer(pointer_event))?;
+        if let Some(notice) = updates.as_ref().and_then(|updates| updates.try_recv().ok()) {
+            info!("{}", notice);
+            events.push((None, Event::UpdateAvailable(notice)));
+        }
+
+        if let (Some(ref events_loop), Some(ref mut monitor_watcher)) =
+            (&events_loop, &mut monitor_watcher)
+        {
+            if monitor_watcher.poll(events_loop) {
+                info!("Monitors changed");
+
+                if wallpaper {
+                    // Wallpaper windows are tied to the monitors they cover, so start over with a
+                    // new set of windows for the monitors we have now
+                    match Config::parse(&config_path)
+                        .and_then(|config| wallpaper::instances(&config_path, config, events_loop))
+                    {
+                        Ok(new_instances) => instances = new_instances,
+                        Err(e) => error!("{}", format_error(&e)),
                     }
-                }
-                Event::Resize(..) => {
-                    if renderer.is_some() {
-                        let (width, height) = facade.get_context().get_framebuffer_dimensions();
-                        event_sender.send(RendererEvent::Resize(width, height))?;
+                } else if span {
+                    // The spanning window has to grow or shrink to cover the monitors we have now
+                    match Instance::new(
+                        &config_path,
+                        Config::parse(&config_path),
+                        Some(span_overrides(events_loop)),
+                        Some(events_loop),
+                    ) {
+                        Ok(instance) => instances = vec![instance],
+                        Err(e) => error!("{}", format_error(&e)),
                     }
+                } else {
+                    events.push((None, Event::MonitorsChanged));
                 }
-                Event::Reload => {
-                    match Config::parse(&config_path) {
-                        Ok(config) => {
-                            // TODO: When destructuring assignment is added, change this
-                            let (watcher_, receiver_) = setup_watches(&config_path, &config)?;
-                            watcher = watcher_;
-                            receiver = receiver_;
-
-                            let (event_sender_, event_receiver) = mpsc::channel();
-                            event_sender = event_sender_;
-
-                            renderer = match config.renderer.as_ref() as &str {
-                                "opengl" => {
-                                    match OpenGLRenderer::new(&config, &facade, event_receiver) {
-                                        Ok(r) => {
-                                            error = None;
-                                            Some(Box::new(r))
-                                        }
-                                        Err(e) => {
-                                            error = Some(e);
-                                            None
-                                        }
-                                    }
-                                }
-                                other => {
-                                    error = Some(format_err!("Renderer {} is not built in", other));
-                                    None
-                                }
-                            }
+            }
+        }
+
+        for instance in &mut instances {
+            if instance.poll_watcher()? {
+                events.push((instance.window_id(), Event::Reload));
+            }
+        }
+
+        for (window_id, event) in events {
+            if let Event::Close = event {
+                return Ok(());
+            }
+
+            for instance in &mut instances {
+                if window_id.is_none() || window_id == instance.window_id() {
+                    match (&event, &events_loop) {
+                        (Event::CyclePresentMode, Some(ref events_loop)) => {
+                            instance.cycle_present_mode(events_loop)?
                         }
-                        Err(e) => {
-                            error!("{}", format_error(&e));
-                            error = Some(e);
+                        (Event::ToggleVsync, Some(ref events_loop)) => {
+                            instance.toggle_vsync(events_loop)?
                         }
+                        _ => instance.handle_event(event.clone())?,
                     }
                 }
-                Event::Capture => {
-                    let path =
-                        Path::new(&format!("{}.png", time::now().strftime("%F_%T")?)).to_path_buf();
-                    event_sender.send(RendererEvent::Capture(path))?;
-                }
-                Event::Close => return Ok(()),
             }
         }
     }
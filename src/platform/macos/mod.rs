@@ -1,6 +0,0 @@
-//! Macos-specific methods and structs
-
-#![cfg(macos)]
-
-pub mod config;
-pub mod window;
@@ -1,6 +0,0 @@
-//! Unix-specific methods and structs
-
-#![cfg(unix)]
-
-pub mod config;
-pub mod window;
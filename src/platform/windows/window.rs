@@ -1,10 +0,0 @@
-// TODO: remove when things are implemented
-#![allow(unused_variables)]
-
-extern crate winit;
-
-use winit::Window;
-
-use config::Config;
-
-pub fn init(window: &Window, config: &Config) {}